@@ -6,8 +6,10 @@ use std::time::Duration;
 use crate::error::ClientError;
 use crate::types::{
     ApiErrorResponse, BalanceResponse, BatchUsageRequest, BatchUsageResponse, CheckBalanceRequest,
-    CheckBalanceResponse, ComputeUsageEvent, LlmUsageEvent, UsageMetric, UsageQuoteRequest,
-    UsageQuoteResponse, UsageRequest, UsageResponse,
+    CheckBalanceResponse, ComputeUsageEvent, LlmUsageEvent, ReleaseRequest, ReleaseResponse,
+    ReserveRequest, ReserveResponse, ReverseUsageRequest, ReverseUsageResponse, SettleRequest,
+    StorageSnapshot, StorageSnapshotResponse, UsageMetric, UsageQuoteRequest, UsageQuoteResponse,
+    UsageRequest, UsageResponse,
 };
 
 /// Z-Billing API client.
@@ -82,9 +84,14 @@ impl ZBillingClient {
                 model: event.model,
                 input_tokens: event.input_tokens,
                 output_tokens: event.output_tokens,
+                cache_read_input_tokens: event.cache_read_input_tokens,
+                cache_creation_input_tokens: event.cache_creation_input_tokens,
+                batch: event.batch,
             },
             cost_cents: None,
+            timestamp: None,
             metadata: event.metadata,
+            org_id: None,
         };
 
         self.report_usage(request).await
@@ -108,12 +115,41 @@ impl ZBillingClient {
                 memory_gb_hours: event.memory_gb_hours,
             },
             cost_cents: None,
+            timestamp: None,
             metadata: event.metadata,
+            org_id: None,
         };
 
         self.report_usage(request).await
     }
 
+    /// Report how many bytes a storage resource holds.
+    ///
+    /// z-billing integrates the snapshots of each resource into GB-hours and
+    /// charges them at the storage rate. To report GB-hours you have already
+    /// metered, send a [`UsageMetric::Storage`] with [`Self::report_usage`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the server returns an error.
+    pub async fn report_storage_usage(
+        &self,
+        snapshot: StorageSnapshot,
+    ) -> Result<StorageSnapshotResponse, ClientError> {
+        let url = format!("{}/v1/usage/storage", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("x-service-name", &self.service_name)
+            .json(&snapshot)
+            .send()
+            .await?;
+
+        self.handle_response(response).await
+    }
+
     /// Report a generic usage event.
     ///
     /// # Errors
@@ -176,6 +212,9 @@ impl ZBillingClient {
                 model: model.into(),
                 input_tokens,
                 output_tokens,
+                cache_read_input_tokens: 0,
+                cache_creation_input_tokens: 0,
+                batch: false,
             },
             zero_pro_user: Some(zero_pro_user),
         })
@@ -267,6 +306,114 @@ impl ZBillingClient {
         self.handle_response(response).await
     }
 
+    /// Hold credits for an in-flight request (e.g. a streamed LLM response).
+    ///
+    /// The hold counts against the user's available balance until it is
+    /// settled with [`Self::settle_reservation`], released with
+    /// [`Self::release_reservation`], or expires.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the server returns an error.
+    pub async fn reserve_credits(
+        &self,
+        request: ReserveRequest,
+    ) -> Result<ReserveResponse, ClientError> {
+        let url = format!("{}/v1/usage/reserve", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("x-service-name", &self.service_name)
+            .json(&request)
+            .send()
+            .await?;
+
+        self.handle_response(response).await
+    }
+
+    /// Settle a reservation into the actual usage debit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the server returns an error.
+    pub async fn settle_reservation(
+        &self,
+        reservation_id: impl Into<String>,
+        usage: UsageRequest,
+    ) -> Result<UsageResponse, ClientError> {
+        let url = format!("{}/v1/usage/settle", self.base_url);
+        let request = SettleRequest {
+            reservation_id: reservation_id.into(),
+            usage,
+        };
+
+        let response = self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("x-service-name", &self.service_name)
+            .json(&request)
+            .send()
+            .await?;
+
+        self.handle_response(response).await
+    }
+
+    /// Release a reservation without charging.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the server returns an error.
+    pub async fn release_reservation(
+        &self,
+        reservation_id: impl Into<String>,
+    ) -> Result<ReleaseResponse, ClientError> {
+        let url = format!("{}/v1/usage/release", self.base_url);
+        let request = ReleaseRequest {
+            reservation_id: reservation_id.into(),
+        };
+
+        let response = self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("x-service-name", &self.service_name)
+            .json(&request)
+            .send()
+            .await?;
+
+        self.handle_response(response).await
+    }
+
+    /// Refund all or part of a usage event's charge.
+    ///
+    /// Retrying with the same `reversal_id` is safe: the server returns the
+    /// original reversal instead of refunding twice.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the server returns an error.
+    pub async fn reverse_usage(
+        &self,
+        event_id: &str,
+        request: ReverseUsageRequest,
+    ) -> Result<ReverseUsageResponse, ClientError> {
+        let url = format!("{}/v1/usage/{event_id}/reverse", self.base_url);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("x-service-name", &self.service_name)
+            .json(&request)
+            .send()
+            .await?;
+
+        self.handle_response(response).await
+    }
+
     /// Get a user's current balance (requires user JWT, not service API key).
     ///
     /// This method is typically used by the user-facing dashboard, not by services.
@@ -326,6 +473,30 @@ impl ZBillingClient {
 
                         Err(ClientError::InsufficientCredits { balance, required })
                     }
+                    "budget_exceeded" => {
+                        let details = api_error.error.details.unwrap_or_default();
+                        let int = |key: &str| {
+                            details
+                                .get(key)
+                                .and_then(serde_json::Value::as_i64)
+                                .unwrap_or(0)
+                        };
+                        let string = |key: &str| {
+                            details
+                                .get(key)
+                                .and_then(serde_json::Value::as_str)
+                                .unwrap_or_default()
+                                .to_string()
+                        };
+
+                        Err(ClientError::BudgetExceeded {
+                            agent_id: string("agent_id"),
+                            period: string("period"),
+                            limit: int("limit"),
+                            spent: int("spent"),
+                            required: int("required"),
+                        })
+                    }
                     "duplicate_event" => Err(ClientError::DuplicateEvent { event_id: message }),
                     "not_found" if message.contains("Account") => {
                         Err(ClientError::AccountNotFound {
@@ -27,6 +27,24 @@ pub enum ClientError {
         required: i64,
     },
 
+    /// The agent's budget would be exceeded. Unlike `InsufficientCredits`,
+    /// the user may still have balance for other agents.
+    #[error(
+        "agent budget exceeded: agent={agent_id}, period={period}, limit={limit}, spent={spent}, required={required}"
+    )]
+    BudgetExceeded {
+        /// The agent ID.
+        agent_id: String,
+        /// Budget period that was hit ("daily", "monthly" or "lifetime").
+        period: String,
+        /// Budget limit in cents.
+        limit: i64,
+        /// Amount already spent in the period in cents.
+        spent: i64,
+        /// Required amount in cents.
+        required: i64,
+    },
+
     /// Duplicate event (already processed).
     #[error("duplicate event: {event_id}")]
     DuplicateEvent {
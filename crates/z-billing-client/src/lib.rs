@@ -22,6 +22,9 @@
 //!     model: "claude-3-5-sonnet".to_string(),
 //!     input_tokens: 1000,
 //!     output_tokens: 500,
+//!     cache_read_input_tokens: 4000,
+//!     cache_creation_input_tokens: 0,
+//!     batch: false,
 //!     metadata: None,
 //! }).await?;
 //!
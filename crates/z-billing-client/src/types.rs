@@ -16,10 +16,19 @@ pub struct LlmUsageEvent {
     pub provider: String,
     /// Model name (e.g., "claude-3-5-sonnet").
     pub model: String,
-    /// Number of input tokens.
+    /// Number of uncached input tokens.
     pub input_tokens: u64,
     /// Number of output tokens.
     pub output_tokens: u64,
+    /// Number of input tokens read from the prompt cache.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub cache_read_input_tokens: u64,
+    /// Number of input tokens written to the prompt cache.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub cache_creation_input_tokens: u64,
+    /// Whether the request went through the provider's batch API.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub batch: bool,
     /// Additional metadata.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<serde_json::Value>,
@@ -44,6 +53,33 @@ pub struct ComputeUsageEvent {
     pub metadata: Option<serde_json::Value>,
 }
 
+/// Storage snapshot: how many bytes a resource holds now.
+///
+/// z-billing charges the GB-hours held since the resource's previous
+/// snapshot, so report on a schedule (e.g. hourly) and whenever usage
+/// changes a lot. The first snapshot of a resource charges nothing.
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageSnapshot {
+    /// User ID that owns the resource.
+    pub user_id: String,
+    /// Identifier of the resource (a bucket, volume, etc.), unique per user.
+    pub resource_id: String,
+    /// Bytes the resource holds.
+    pub bytes: u64,
+    /// When the bytes were measured (optional, defaults to when it is received).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    /// Agent ID that owns the resource (optional).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+    /// Additional metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    /// Organization whose shared pool pays for the storage (optional).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
+}
+
 /// Generic usage event request.
 #[derive(Debug, Clone, Serialize)]
 pub struct UsageRequest {
@@ -59,9 +95,15 @@ pub struct UsageRequest {
     /// Pre-calculated cost in cents (optional).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cost_cents: Option<i64>,
+    /// When the usage happened (optional, defaults to when it is received).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
     /// Additional metadata.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<serde_json::Value>,
+    /// Organization whose shared pool pays for the usage (optional).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
 }
 
 /// Usage metric variants.
@@ -74,10 +116,19 @@ pub enum UsageMetric {
         provider: String,
         /// Model name.
         model: String,
-        /// Input tokens.
+        /// Uncached input tokens.
         input_tokens: u64,
         /// Output tokens.
         output_tokens: u64,
+        /// Input tokens read from the prompt cache.
+        #[serde(skip_serializing_if = "is_zero")]
+        cache_read_input_tokens: u64,
+        /// Input tokens written to the prompt cache.
+        #[serde(skip_serializing_if = "is_zero")]
+        cache_creation_input_tokens: u64,
+        /// Whether the request went through the provider's batch API.
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        batch: bool,
     },
     /// Compute usage.
     Compute {
@@ -93,6 +144,67 @@ pub enum UsageMetric {
         /// Number of calls.
         count: u64,
     },
+    /// Storage usage.
+    Storage {
+        /// GB-hours of storage used.
+        gb_hours: f64,
+    },
+    /// Image generation.
+    ImageGeneration {
+        /// Provider name.
+        provider: String,
+        /// Model name.
+        model: String,
+        /// Number of images.
+        count: u64,
+        /// Resolution tier.
+        resolution: ImageResolution,
+    },
+    /// Audio transcription.
+    AudioTranscription {
+        /// Provider name.
+        provider: String,
+        /// Model name.
+        model: String,
+        /// Seconds of audio.
+        seconds: f64,
+    },
+    /// Text-to-speech.
+    TextToSpeech {
+        /// Provider name.
+        provider: String,
+        /// Model name.
+        model: String,
+        /// Characters of input text.
+        characters: u64,
+    },
+    /// Web search.
+    WebSearch {
+        /// Provider name.
+        provider: String,
+        /// Number of queries.
+        queries: u64,
+    },
+    /// Paid tool calls.
+    ToolCalls {
+        /// Tool name.
+        tool: String,
+        /// Number of calls.
+        count: u64,
+    },
+}
+
+/// Resolution tier of a generated image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageResolution {
+    /// Up to 1024×1024 pixels.
+    #[default]
+    Standard,
+    /// Up to 2048×2048 pixels.
+    High,
+    /// Larger than 2048×2048 pixels.
+    Ultra,
 }
 
 /// Usage response from the API.
@@ -108,6 +220,23 @@ pub struct UsageResponse {
     pub transaction_id: String,
 }
 
+/// Storage snapshot response from the API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageSnapshotResponse {
+    /// Whether the snapshot was recorded.
+    pub success: bool,
+    /// GB-hours charged for this snapshot.
+    pub gb_hours: f64,
+    /// GB-hours accrued but not yet charged, carried to the next snapshot.
+    pub unbilled_gb_hours: f64,
+    /// Cost deducted (0 when nothing was charged).
+    pub cost_cents: i64,
+    /// New balance, if anything was charged.
+    pub balance_cents: Option<i64>,
+    /// Usage transaction ID, if anything was charged.
+    pub transaction_id: Option<String>,
+}
+
 /// Usage quote request.
 #[derive(Debug, Clone, Serialize)]
 pub struct UsageQuoteRequest {
@@ -180,10 +309,116 @@ pub struct CheckBalanceResponse {
     pub sufficient: bool,
     /// Current balance.
     pub balance_cents: i64,
+    /// Amount held by active reservations.
+    #[serde(default)]
+    pub reserved_cents: i64,
     /// Required amount.
     pub required_cents: i64,
 }
 
+/// Credit reservation request.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReserveRequest {
+    /// User ID whose balance is held.
+    pub user_id: String,
+    /// Explicit amount to hold in cents (optional if provider/model are set).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_cents: Option<i64>,
+    /// Provider name for model-aware reserve lookup.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Model name for model-aware reserve lookup.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Whether the user has a ZERO Pro entitlement.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zero_pro_user: Option<bool>,
+    /// Whether the request will go through the provider's batch API.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub batch: bool,
+    /// Hold lifetime in seconds (defaults to the service setting).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+    /// Additional metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Credit reservation response.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReserveResponse {
+    /// Reservation ID to pass to settle or release.
+    pub reservation_id: String,
+    /// Amount held in cents.
+    pub reserved_cents: i64,
+    /// Balance still available after this hold.
+    pub available_cents: i64,
+    /// When the hold lapses if not settled (RFC 3339).
+    pub expires_at: String,
+}
+
+/// Settle reservation request.
+#[derive(Debug, Clone, Serialize)]
+pub struct SettleRequest {
+    /// Reservation ID returned by reserve.
+    pub reservation_id: String,
+    /// The actual usage to debit.
+    #[serde(flatten)]
+    pub usage: UsageRequest,
+}
+
+/// Release reservation request.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseRequest {
+    /// Reservation ID returned by reserve.
+    pub reservation_id: String,
+}
+
+/// Release reservation response.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseResponse {
+    /// Whether the hold is no longer in place.
+    pub success: bool,
+    /// Reservation ID.
+    pub reservation_id: String,
+    /// Final reservation status.
+    pub status: String,
+}
+
+/// Reverse usage request.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReverseUsageRequest {
+    /// Idempotency key for this reversal. Required for partial reversals;
+    /// full reversals default to `"full"` on the server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reversal_id: Option<String>,
+    /// Amount to refund in cents. Omit to refund everything not yet reversed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_cents: Option<i64>,
+    /// Reason recorded on the reversal transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Reverse usage response.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReverseUsageResponse {
+    /// Whether the reversal is recorded.
+    pub success: bool,
+    /// The reversed usage event.
+    pub event_id: String,
+    /// Reversal ID.
+    pub reversal_id: String,
+    /// Amount refunded by this reversal in cents.
+    pub reversed_cents: i64,
+    /// Amount refunded by all reversals of the event in cents.
+    pub total_reversed_cents: i64,
+    /// Balance right after the refund in cents.
+    pub balance_cents: i64,
+    /// Reversal transaction ID.
+    pub transaction_id: String,
+}
+
 /// Balance response.
 #[derive(Debug, Clone, Deserialize)]
 pub struct BalanceResponse {
@@ -193,6 +428,35 @@ pub struct BalanceResponse {
     pub balance_formatted: String,
     /// Current plan.
     pub plan: String,
+    /// Open credit lots in the order they will be spent.
+    #[serde(default)]
+    pub lots: Vec<CreditLotBalance>,
+    /// Balance not covered by any lot; spent last and never expires.
+    #[serde(default)]
+    pub untracked_cents: i64,
+    /// How far usage may take the balance below zero.
+    #[serde(default)]
+    pub credit_limit_cents: i64,
+    /// Whether the balance is below zero.
+    #[serde(default)]
+    pub overdrawn: bool,
+    /// Whether usage is blocked until the balance is back above zero.
+    #[serde(default)]
+    pub overdraft_locked: bool,
+}
+
+/// A credit lot in the balance breakdown.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreditLotBalance {
+    /// Lot ID.
+    pub id: String,
+    /// Where the credits came from (transaction type).
+    pub source: String,
+    /// Credits left in the lot.
+    pub remaining_cents: i64,
+    /// When unspent credits expire (RFC 3339), if ever.
+    #[serde(default)]
+    pub expires_at: Option<String>,
 }
 
 /// API error response.
@@ -212,3 +476,8 @@ pub struct ApiErrorBody {
     /// Additional details.
     pub details: Option<serde_json::Value>,
 }
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
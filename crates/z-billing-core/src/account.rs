@@ -91,6 +91,23 @@ pub struct Account {
     /// When monthly credit allowance was last granted (None = never).
     pub last_monthly_grant_at: Option<DateTime<Utc>>,
 
+    /// How far usage may take the balance below zero (in cents).
+    /// Zero means no overdraft. Set by admins for trusted accounts.
+    #[serde(default)]
+    pub credit_limit_cents: i64,
+
+    /// Whether usage is blocked because the balance reached the credit limit.
+    /// Cleared once the balance is back above zero.
+    #[serde(default)]
+    pub overdraft_locked: bool,
+
+    /// Write version for optimistic concurrency, bumped by every write of
+    /// the account's settings. Balance changes leave it alone: settings
+    /// writes never touch the balance, so the two cannot clobber each
+    /// other. Zero for an account that has not been stored yet.
+    #[serde(default = "first_version")]
+    pub version: i64,
+
     /// When the account was created.
     pub created_at: DateTime<Utc>,
 
@@ -98,6 +115,11 @@ pub struct Account {
     pub updated_at: DateTime<Utc>,
 }
 
+/// Version of an account stored before versioning existed.
+fn first_version() -> i64 {
+    1
+}
+
 impl Account {
     /// Create a new account with zero balance.
     #[must_use]
@@ -118,6 +140,9 @@ impl Account {
             signup_grant_at: None,
             last_daily_grant_at: None,
             last_monthly_grant_at: None,
+            credit_limit_cents: 0,
+            overdraft_locked: false,
+            version: 0,
             created_at: now,
             updated_at: now,
         }
@@ -126,7 +151,36 @@ impl Account {
     /// Check if the account has sufficient credits for a deduction.
     #[must_use]
     pub fn has_sufficient_credits(&self, amount_cents: i64) -> bool {
-        self.balance_cents >= amount_cents
+        self.spendable_cents() >= amount_cents
+    }
+
+    /// Credits available for usage: the balance plus whatever is left of the
+    /// credit line, or just the balance while the overdraft is locked.
+    #[must_use]
+    pub const fn spendable_cents(&self) -> i64 {
+        if self.overdraft_locked {
+            self.balance_cents
+        } else {
+            self.balance_cents + self.credit_limit_cents
+        }
+    }
+
+    /// Whether the balance is below zero.
+    #[must_use]
+    pub const fn is_overdrawn(&self) -> bool {
+        self.balance_cents < 0
+    }
+
+    /// Update the overdraft lock after the balance or credit limit changed.
+    ///
+    /// The lock is set once the balance reaches the credit limit and cleared
+    /// once the balance is back above zero.
+    pub fn update_overdraft_lock(&mut self) {
+        if self.balance_cents > 0 {
+            self.overdraft_locked = false;
+        } else if self.credit_limit_cents > 0 && self.balance_cents <= -self.credit_limit_cents {
+            self.overdraft_locked = true;
+        }
     }
 
     /// Get the current plan (Free if no subscription).
@@ -305,6 +359,36 @@ mod tests {
         assert!(!account.has_sufficient_credits(1001));
     }
 
+    #[test]
+    fn credit_limit_allows_overdraft_until_locked() {
+        let mut account = Account::new(UserId::generate());
+        account.balance_cents = 100;
+        account.credit_limit_cents = 500;
+        assert_eq!(account.spendable_cents(), 600);
+
+        // Spending into the credit line leaves the rest of it available
+        account.balance_cents = -300;
+        account.update_overdraft_lock();
+        assert!(account.is_overdrawn());
+        assert!(!account.overdraft_locked);
+        assert!(account.has_sufficient_credits(200));
+
+        // Reaching the limit blocks usage even after a partial repayment
+        account.balance_cents = -500;
+        account.update_overdraft_lock();
+        assert!(account.overdraft_locked);
+        account.balance_cents = -100;
+        account.update_overdraft_lock();
+        assert!(account.overdraft_locked);
+        assert!(!account.has_sufficient_credits(1));
+
+        // Back above zero unlocks the credit line
+        account.balance_cents = 50;
+        account.update_overdraft_lock();
+        assert!(!account.overdraft_locked);
+        assert_eq!(account.spendable_cents(), 550);
+    }
+
     #[test]
     fn plan_monthly_credits() {
         assert_eq!(Plan::Mortal.monthly_credits(), 2500);
@@ -0,0 +1,240 @@
+//! Per-agent budget types for z-billing.
+//!
+//! A user can cap how much each of their agents may spend per day, per
+//! calendar month, or over its lifetime. Spend is tracked per agent in
+//! rolling UTC windows so budgets can be checked without scanning usage.
+
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{AgentId, UserId};
+
+/// A spending limit for one agent over one period.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentBudget {
+    /// The user whose balance the agent spends.
+    pub user_id: UserId,
+
+    /// The agent being limited.
+    pub agent_id: AgentId,
+
+    /// The window the limit applies to.
+    pub period: BudgetPeriod,
+
+    /// Most the agent may spend in the period (in cents).
+    pub limit_cents: i64,
+
+    /// When the budget was created.
+    pub created_at: DateTime<Utc>,
+
+    /// When the budget was last updated.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AgentBudget {
+    /// Create a new budget.
+    #[must_use]
+    pub fn new(user_id: UserId, agent_id: AgentId, period: BudgetPeriod, limit_cents: i64) -> Self {
+        let now = Utc::now();
+        Self {
+            user_id,
+            agent_id,
+            period,
+            limit_cents,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// The window a budget applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPeriod {
+    /// Resets at midnight UTC.
+    Daily,
+    /// Resets on the first of each month, UTC.
+    Monthly,
+    /// Never resets.
+    Lifetime,
+}
+
+impl BudgetPeriod {
+    /// Get the string representation used in storage and API responses.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Monthly => "monthly",
+            Self::Lifetime => "lifetime",
+        }
+    }
+
+    /// Start of the window containing `now`, or None for lifetime budgets.
+    #[must_use]
+    pub fn window_start(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = match self {
+            Self::Daily => Utc.with_ymd_and_hms(now.year(), now.month(), now.day(), 0, 0, 0),
+            Self::Monthly => Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0),
+            Self::Lifetime => return None,
+        };
+        Some(start.single().unwrap_or(now))
+    }
+}
+
+impl std::str::FromStr for BudgetPeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daily" => Ok(Self::Daily),
+            "monthly" => Ok(Self::Monthly),
+            "lifetime" => Ok(Self::Lifetime),
+            other => Err(format!("unknown budget period: {other}")),
+        }
+    }
+}
+
+/// Running spend totals for one agent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSpend {
+    /// The user whose balance the agent spends.
+    pub user_id: UserId,
+
+    /// The agent.
+    pub agent_id: AgentId,
+
+    /// Start of the day `day_cents` was accumulated in.
+    pub day_start: DateTime<Utc>,
+
+    /// Spend in the day starting at `day_start` (in cents).
+    pub day_cents: i64,
+
+    /// Start of the month `month_cents` was accumulated in.
+    pub month_start: DateTime<Utc>,
+
+    /// Spend in the month starting at `month_start` (in cents).
+    pub month_cents: i64,
+
+    /// Spend since the agent first used credits (in cents).
+    pub lifetime_cents: i64,
+
+    /// When spend was last recorded.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl AgentSpend {
+    /// Create empty totals with windows starting at `now`.
+    #[must_use]
+    pub fn new(user_id: UserId, agent_id: AgentId, now: DateTime<Utc>) -> Self {
+        Self {
+            user_id,
+            agent_id,
+            day_start: BudgetPeriod::Daily.window_start(now).unwrap_or(now),
+            day_cents: 0,
+            month_start: BudgetPeriod::Monthly.window_start(now).unwrap_or(now),
+            month_cents: 0,
+            lifetime_cents: 0,
+            updated_at: now,
+        }
+    }
+
+    /// Amount spent in the `period` window containing `now`.
+    #[must_use]
+    pub fn spent_in(&self, period: BudgetPeriod, now: DateTime<Utc>) -> i64 {
+        match period {
+            BudgetPeriod::Daily if period.window_start(now) == Some(self.day_start) => {
+                self.day_cents
+            }
+            BudgetPeriod::Monthly if period.window_start(now) == Some(self.month_start) => {
+                self.month_cents
+            }
+            BudgetPeriod::Lifetime => self.lifetime_cents,
+            _ => 0,
+        }
+    }
+
+    /// Add `amount_cents` of spend at `now`, starting new windows as needed.
+    pub fn record(&mut self, amount_cents: i64, now: DateTime<Utc>) {
+        self.day_cents = self.spent_in(BudgetPeriod::Daily, now) + amount_cents;
+        self.month_cents = self.spent_in(BudgetPeriod::Monthly, now) + amount_cents;
+        self.lifetime_cents += amount_cents;
+        self.day_start = BudgetPeriod::Daily.window_start(now).unwrap_or(now);
+        self.month_start = BudgetPeriod::Monthly.window_start(now).unwrap_or(now);
+        self.updated_at = now;
+    }
+}
+
+/// Find the first budget that spending `amount_cents` more at `now` would
+/// exceed, along with what has already been spent against it.
+#[must_use]
+pub fn exceeded_budget<'a>(
+    budgets: &'a [AgentBudget],
+    totals: &AgentSpend,
+    amount_cents: i64,
+    now: DateTime<Utc>,
+) -> Option<(&'a AgentBudget, i64)> {
+    budgets.iter().find_map(|budget| {
+        let spent = totals.spent_in(budget.period, now);
+        (spent + amount_cents > budget.limit_cents).then_some((budget, spent))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn windows_roll_over() {
+        let mut spend = AgentSpend::new(
+            UserId::generate(),
+            AgentId::generate(),
+            at("2025-03-31T10:00:00Z"),
+        );
+        spend.record(40, at("2025-03-31T10:00:00Z"));
+        spend.record(10, at("2025-04-01T09:00:00Z"));
+
+        let now = at("2025-04-01T12:00:00Z");
+        assert_eq!(spend.spent_in(BudgetPeriod::Daily, now), 10);
+        assert_eq!(spend.spent_in(BudgetPeriod::Monthly, now), 10);
+        assert_eq!(spend.spent_in(BudgetPeriod::Lifetime, now), 50);
+        assert_eq!(
+            spend.spent_in(BudgetPeriod::Daily, at("2025-04-02T00:00:00Z")),
+            0
+        );
+    }
+
+    #[test]
+    fn exceeded_budget_reports_first_breach() {
+        let user_id = UserId::generate();
+        let agent_id = AgentId::generate();
+        let now = at("2025-03-14T12:00:00Z");
+        let mut totals = AgentSpend::new(user_id, agent_id, now);
+        totals.record(90, now);
+
+        let budgets = vec![
+            AgentBudget::new(user_id, agent_id, BudgetPeriod::Lifetime, 1000),
+            AgentBudget::new(user_id, agent_id, BudgetPeriod::Daily, 100),
+        ];
+
+        assert!(exceeded_budget(&budgets, &totals, 10, now).is_none());
+        let (budget, spent) = exceeded_budget(&budgets, &totals, 11, now).unwrap();
+        assert_eq!(budget.period, BudgetPeriod::Daily);
+        assert_eq!(spent, 90);
+    }
+
+    #[test]
+    fn period_string_roundtrip() {
+        for period in [
+            BudgetPeriod::Daily,
+            BudgetPeriod::Monthly,
+            BudgetPeriod::Lifetime,
+        ] {
+            assert_eq!(period.as_str().parse::<BudgetPeriod>().unwrap(), period);
+        }
+    }
+}
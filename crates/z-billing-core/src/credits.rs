@@ -2,10 +2,12 @@
 //!
 //! This module defines credit transactions that track all balance changes.
 
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::{TransactionId, UserId};
+use crate::{CreditLot, GiftCard, LedgerAccount, OrgId, TransactionId, UserId};
 
 /// A credit transaction representing a balance change.
 ///
@@ -16,9 +18,14 @@ pub struct CreditTransaction {
     /// Unique transaction ID (ULID for time-ordering).
     pub id: TransactionId,
 
-    /// The user whose balance was affected.
+    /// The user whose balance was affected, or the acting member for
+    /// organization transactions.
     pub user_id: UserId,
 
+    /// The organization whose pool was affected, if any.
+    #[serde(default)]
+    pub org_id: Option<OrgId>,
+
     /// Amount in cents. Positive = credit, Negative = debit.
     pub amount_cents: i64,
 
@@ -50,6 +57,7 @@ impl CreditTransaction {
         Self {
             id: TransactionId::generate(),
             user_id,
+            org_id: None,
             amount_cents,
             transaction_type: TransactionType::Purchase,
             balance_after_cents,
@@ -71,6 +79,7 @@ impl CreditTransaction {
         Self {
             id: TransactionId::generate(),
             user_id,
+            org_id: None,
             amount_cents: -amount_cents.abs(), // Always negative for usage
             transaction_type: TransactionType::Usage,
             balance_after_cents,
@@ -91,6 +100,7 @@ impl CreditTransaction {
         Self {
             id: TransactionId::generate(),
             user_id,
+            org_id: None,
             amount_cents,
             transaction_type: TransactionType::SubscriptionGrant,
             balance_after_cents,
@@ -111,6 +121,7 @@ impl CreditTransaction {
         Self {
             id: TransactionId::generate(),
             user_id,
+            org_id: None,
             amount_cents,
             transaction_type: TransactionType::Refund,
             balance_after_cents,
@@ -131,6 +142,7 @@ impl CreditTransaction {
         Self {
             id: TransactionId::generate(),
             user_id,
+            org_id: None,
             amount_cents,
             transaction_type: TransactionType::Bonus,
             balance_after_cents,
@@ -140,12 +152,29 @@ impl CreditTransaction {
         }
     }
 
+    /// Create a new bonus transaction for a promo code redemption.
+    ///
+    /// The code is recorded in the transaction metadata as `promo_code`.
+    #[must_use]
+    pub fn promo(user_id: UserId, amount_cents: i64, balance_after_cents: i64, code: &str) -> Self {
+        Self {
+            metadata: serde_json::json!({ "promo_code": code }),
+            ..Self::bonus(
+                user_id,
+                amount_cents,
+                balance_after_cents,
+                format!("Promo code {code}"),
+            )
+        }
+    }
+
     /// Create a new auto-refill transaction.
     #[must_use]
     pub fn auto_refill(user_id: UserId, amount_cents: i64, balance_after_cents: i64) -> Self {
         Self {
             id: TransactionId::generate(),
             user_id,
+            org_id: None,
             amount_cents,
             transaction_type: TransactionType::AutoRefill,
             balance_after_cents,
@@ -161,6 +190,7 @@ impl CreditTransaction {
         Self {
             id: TransactionId::generate(),
             user_id,
+            org_id: None,
             amount_cents,
             transaction_type: TransactionType::SignupGrant,
             balance_after_cents,
@@ -176,6 +206,7 @@ impl CreditTransaction {
         Self {
             id: TransactionId::generate(),
             user_id,
+            org_id: None,
             amount_cents,
             transaction_type: TransactionType::DailyGrant,
             balance_after_cents,
@@ -191,6 +222,7 @@ impl CreditTransaction {
         Self {
             id: TransactionId::generate(),
             user_id,
+            org_id: None,
             amount_cents,
             transaction_type: TransactionType::MonthlyAllowance,
             balance_after_cents,
@@ -211,6 +243,7 @@ impl CreditTransaction {
         Self {
             id: TransactionId::generate(),
             user_id,
+            org_id: None,
             amount_cents,
             transaction_type: TransactionType::ReferralBonus,
             balance_after_cents,
@@ -219,6 +252,141 @@ impl CreditTransaction {
             created_at: Utc::now(),
         }
     }
+
+    /// Attribute this transaction to an organization's pool.
+    #[must_use]
+    pub fn with_org(mut self, org_id: OrgId) -> Self {
+        self.org_id = Some(org_id);
+        self
+    }
+
+    /// Create a reversal transaction refunding all or part of a usage charge.
+    ///
+    /// The reversal is attributed to the same user and organization as the
+    /// original usage transaction and references it in its metadata.
+    #[must_use]
+    pub fn reversal(
+        original: &Self,
+        event_id: &str,
+        amount_cents: i64,
+        balance_after_cents: i64,
+        description: String,
+    ) -> Self {
+        Self {
+            id: TransactionId::generate(),
+            user_id: original.user_id,
+            org_id: original.org_id,
+            amount_cents: amount_cents.abs(), // Always positive for reversal
+            transaction_type: TransactionType::Reversal,
+            balance_after_cents,
+            description,
+            metadata: serde_json::json!({
+                "original_transaction_id": original.id.to_string(),
+                "event_id": event_id,
+            }),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Create the debit side of a credit transfer from `user_id`'s personal
+    /// balance to `to`.
+    #[must_use]
+    pub fn transfer_out(
+        user_id: UserId,
+        to: &LedgerAccount,
+        amount_cents: i64,
+        balance_after_cents: i64,
+        idempotency_key: &str,
+    ) -> Self {
+        Self {
+            id: TransactionId::generate(),
+            user_id,
+            org_id: None,
+            amount_cents: -amount_cents.abs(), // Always negative for transfer out
+            transaction_type: TransactionType::TransferOut,
+            balance_after_cents,
+            description: format!("Credit transfer to {to}"),
+            metadata: serde_json::json!({
+                "to": to.to_string(),
+                "idempotency_key": idempotency_key,
+            }),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Create the credit side of a credit transfer from `from` to `to`.
+    ///
+    /// Transfers into an organization's pool are attributed to the sender as
+    /// the acting member.
+    #[must_use]
+    pub fn transfer_in(
+        from: UserId,
+        to: &LedgerAccount,
+        amount_cents: i64,
+        balance_after_cents: i64,
+        idempotency_key: &str,
+    ) -> Self {
+        let (user_id, org_id) = match to {
+            LedgerAccount::User(user_id) => (*user_id, None),
+            LedgerAccount::Org(org_id) => (from, Some(*org_id)),
+            LedgerAccount::System(_) => (from, None),
+        };
+        Self {
+            id: TransactionId::generate(),
+            user_id,
+            org_id,
+            amount_cents: amount_cents.abs(), // Always positive for transfer in
+            transaction_type: TransactionType::TransferIn,
+            balance_after_cents,
+            description: format!("Credit transfer from user:{from}"),
+            metadata: serde_json::json!({
+                "from": format!("user:{from}"),
+                "idempotency_key": idempotency_key,
+            }),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Create a new gift card redemption transaction.
+    ///
+    /// The card ID is recorded in the transaction metadata as `gift_card_id`;
+    /// the code is not, since it is a bearer secret.
+    #[must_use]
+    pub fn gift_card(user_id: UserId, card: &GiftCard, balance_after_cents: i64) -> Self {
+        Self {
+            id: TransactionId::generate(),
+            user_id,
+            org_id: None,
+            amount_cents: card.amount_cents.abs(), // Always positive for gift card
+            transaction_type: TransactionType::GiftCard,
+            balance_after_cents,
+            description: "Gift card redeemed".to_string(),
+            metadata: serde_json::json!({
+                "gift_card_id": card.id.to_string(),
+                "purchaser_id": card.purchaser_id.to_string(),
+            }),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Create a new expiry transaction for the unused remainder of a lot.
+    #[must_use]
+    pub fn expiry(lot: &CreditLot, balance_after_cents: i64) -> Self {
+        Self {
+            id: TransactionId::generate(),
+            user_id: lot.user_id,
+            org_id: None,
+            amount_cents: -lot.remaining_cents.abs(), // Always negative for expiry
+            transaction_type: TransactionType::Expiry,
+            balance_after_cents,
+            description: "Unused credits expired".to_string(),
+            metadata: serde_json::json!({
+                "lot_id": lot.id.to_string(),
+                "source": lot.source.as_str(),
+            }),
+            created_at: Utc::now(),
+        }
+    }
 }
 
 /// Type of credit transaction.
@@ -254,6 +422,21 @@ pub enum TransactionType {
 
     /// Monthly credit allowance (from tier subscription or free Mortal allowance).
     MonthlyAllowance,
+
+    /// Unused credits removed when their lot expired.
+    Expiry,
+
+    /// Usage charge refunded (fully or partially) after the fact.
+    Reversal,
+
+    /// Credits sent to another user or an organization.
+    TransferOut,
+
+    /// Credits received from another user.
+    TransferIn,
+
+    /// Gift card redeemed into the account.
+    GiftCard,
 }
 
 impl TransactionType {
@@ -271,13 +454,163 @@ impl TransactionType {
                 | Self::DailyGrant
                 | Self::ReferralBonus
                 | Self::MonthlyAllowance
+                | Self::Reversal
+                | Self::TransferIn
+                | Self::GiftCard
         )
     }
 
     /// Check if this transaction type removes credits (negative balance change).
     #[must_use]
     pub const fn is_debit(&self) -> bool {
-        matches!(self, Self::Usage)
+        matches!(self, Self::Usage | Self::Expiry | Self::TransferOut)
+    }
+
+    /// Check if credits from this source may be transferred to someone else.
+    ///
+    /// Only paid-for credits are transferable: granted, bonus and refunded
+    /// credits stay with the account they were given to.
+    #[must_use]
+    pub const fn is_transferable(&self) -> bool {
+        matches!(
+            self,
+            Self::Purchase | Self::AutoRefill | Self::TransferIn | Self::GiftCard
+        )
+    }
+
+    /// Get the `snake_case` name used in storage and API responses.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Purchase => "purchase",
+            Self::Usage => "usage",
+            Self::SubscriptionGrant => "subscription_grant",
+            Self::Refund => "refund",
+            Self::Bonus => "bonus",
+            Self::AutoRefill => "auto_refill",
+            Self::SignupGrant => "signup_grant",
+            Self::DailyGrant => "daily_grant",
+            Self::ReferralBonus => "referral_bonus",
+            Self::MonthlyAllowance => "monthly_allowance",
+            Self::Expiry => "expiry",
+            Self::Reversal => "reversal",
+            Self::TransferOut => "transfer_out",
+            Self::TransferIn => "transfer_in",
+            Self::GiftCard => "gift_card",
+        }
+    }
+
+    /// Order in which credits from this source are spent (lower first).
+    ///
+    /// Daily grants go first since they expire soonest, then the monthly
+    /// allowance, then bonus credits, and purchased credits last.
+    #[must_use]
+    pub const fn consumption_priority(&self) -> u8 {
+        match self {
+            Self::DailyGrant => 0,
+            Self::MonthlyAllowance | Self::SubscriptionGrant => 1,
+            Self::Bonus | Self::SignupGrant | Self::ReferralBonus => 2,
+            Self::Purchase
+            | Self::AutoRefill
+            | Self::Refund
+            | Self::Reversal
+            | Self::Usage
+            | Self::Expiry
+            | Self::TransferOut
+            | Self::TransferIn
+            | Self::GiftCard => 3,
+        }
+    }
+}
+
+/// Whether a transaction added or removed credits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountSign {
+    /// Credits added (positive `amount_cents`).
+    Credit,
+
+    /// Credits removed (negative `amount_cents`).
+    Debit,
+}
+
+/// Filters and a cursor for listing a user's transactions.
+///
+/// Results are ordered newest first. Paging passes the ID of the last
+/// transaction seen as `before`; since transaction IDs are ULIDs, pages stay
+/// stable while new transactions arrive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransactionQuery {
+    /// Only these transaction types. Empty means every type.
+    pub transaction_types: Vec<TransactionType>,
+
+    /// Only transactions created at or after this time.
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only transactions created before this time.
+    pub until: Option<DateTime<Utc>>,
+
+    /// Only credits or only debits.
+    pub sign: Option<AmountSign>,
+
+    /// Only transactions whose metadata has these string values, such as
+    /// `model` or `agent_id`.
+    pub metadata: BTreeMap<String, String>,
+
+    /// Only transactions older than this one.
+    pub before: Option<TransactionId>,
+
+    /// Maximum number of transactions to return.
+    pub limit: usize,
+}
+
+impl TransactionQuery {
+    /// Create an unfiltered query for the newest `limit` transactions.
+    #[must_use]
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    /// Check whether a transaction passes every filter, including the cursor.
+    #[must_use]
+    pub fn matches(&self, transaction: &CreditTransaction) -> bool {
+        if !self.transaction_types.is_empty()
+            && !self
+                .transaction_types
+                .contains(&transaction.transaction_type)
+        {
+            return false;
+        }
+        if self
+            .since
+            .is_some_and(|since| transaction.created_at < since)
+            || self
+                .until
+                .is_some_and(|until| transaction.created_at >= until)
+        {
+            return false;
+        }
+        match self.sign {
+            Some(AmountSign::Credit) if transaction.amount_cents <= 0 => return false,
+            Some(AmountSign::Debit) if transaction.amount_cents >= 0 => return false,
+            _ => {}
+        }
+        if self
+            .before
+            .is_some_and(|before| transaction.id.as_ulid() >= before.as_ulid())
+        {
+            return false;
+        }
+        self.metadata.iter().all(|(key, value)| {
+            transaction
+                .metadata
+                .get(key)
+                .and_then(serde_json::Value::as_str)
+                == Some(value)
+        })
     }
 }
 
@@ -328,6 +661,71 @@ mod tests {
         assert!(!TransactionType::SignupGrant.is_debit());
         assert!(!TransactionType::DailyGrant.is_debit());
         assert!(!TransactionType::ReferralBonus.is_debit());
+        assert!(TransactionType::Expiry.is_debit());
+        assert!(!TransactionType::Expiry.is_credit());
+        assert!(TransactionType::Reversal.is_credit());
+        assert!(!TransactionType::Reversal.is_debit());
+        assert!(TransactionType::TransferOut.is_debit());
+        assert!(TransactionType::TransferIn.is_credit());
+    }
+
+    #[test]
+    fn only_paid_credits_are_transferable() {
+        assert!(TransactionType::Purchase.is_transferable());
+        assert!(TransactionType::AutoRefill.is_transferable());
+        assert!(TransactionType::TransferIn.is_transferable());
+        assert!(!TransactionType::SignupGrant.is_transferable());
+        assert!(!TransactionType::DailyGrant.is_transferable());
+        assert!(!TransactionType::Bonus.is_transferable());
+        assert!(!TransactionType::Reversal.is_transferable());
+    }
+
+    #[test]
+    fn transfer_to_org_is_attributed_to_sender() {
+        let from = UserId::generate();
+        let org_id = OrgId::generate();
+        let to = LedgerAccount::Org(org_id);
+        let out = CreditTransaction::transfer_out(from, &to, 300, 700, "key-1");
+        let tx = CreditTransaction::transfer_in(from, &to, 300, 1300, "key-1");
+
+        assert_eq!(out.amount_cents, -300);
+        assert_eq!(out.metadata["to"], format!("org:{org_id}"));
+        assert_eq!(tx.amount_cents, 300);
+        assert_eq!(tx.user_id, from);
+        assert_eq!(tx.org_id, Some(org_id));
+        assert_eq!(LedgerAccount::wallet_for(&tx), to);
+    }
+
+    #[test]
+    fn consumption_priority_order() {
+        assert!(
+            TransactionType::DailyGrant.consumption_priority()
+                < TransactionType::MonthlyAllowance.consumption_priority()
+        );
+        assert!(
+            TransactionType::MonthlyAllowance.consumption_priority()
+                < TransactionType::Bonus.consumption_priority()
+        );
+        assert!(
+            TransactionType::Bonus.consumption_priority()
+                < TransactionType::Purchase.consumption_priority()
+        );
+    }
+
+    #[test]
+    fn transaction_type_as_str_matches_serde() {
+        for tx_type in [
+            TransactionType::DailyGrant,
+            TransactionType::MonthlyAllowance,
+            TransactionType::AutoRefill,
+            TransactionType::Expiry,
+            TransactionType::Reversal,
+            TransactionType::TransferOut,
+            TransactionType::TransferIn,
+        ] {
+            let json = serde_json::to_string(&tx_type).unwrap();
+            assert_eq!(json.trim_matches('"'), tx_type.as_str());
+        }
     }
 
     #[test]
@@ -360,6 +758,24 @@ mod tests {
         assert_eq!(tx.balance_after_cents, 10000);
     }
 
+    #[test]
+    fn reversal_transaction_references_original() {
+        let user_id = UserId::generate();
+        let usage = CreditTransaction::usage(
+            user_id,
+            300,
+            700,
+            "LLM usage".into(),
+            serde_json::Value::Null,
+        );
+        let tx = CreditTransaction::reversal(&usage, "evt_123", -100, 800, "Refund".into());
+
+        assert_eq!(tx.amount_cents, 100);
+        assert_eq!(tx.transaction_type, TransactionType::Reversal);
+        assert_eq!(tx.metadata["original_transaction_id"], usage.id.to_string());
+        assert_eq!(tx.metadata["event_id"], "evt_123");
+    }
+
     #[test]
     fn monthly_allowance_transaction() {
         let user_id = UserId::generate();
@@ -370,4 +786,63 @@ mod tests {
         assert_eq!(tx.balance_after_cents, 7500);
         assert!(!tx.transaction_type.is_debit());
     }
+
+    #[test]
+    fn transaction_query_filters() {
+        let user_id = UserId::generate();
+        let purchase = CreditTransaction::purchase(user_id, 500, 500, "Purchase".into());
+        let usage = CreditTransaction::usage(
+            user_id,
+            100,
+            400,
+            "LLM usage".into(),
+            serde_json::json!({"model": "claude-sonnet-4"}),
+        );
+
+        let everything = TransactionQuery::new(10);
+        assert!(everything.matches(&purchase));
+        assert!(everything.matches(&usage));
+
+        let debits = TransactionQuery {
+            sign: Some(AmountSign::Debit),
+            ..TransactionQuery::new(10)
+        };
+        assert!(!debits.matches(&purchase));
+        assert!(debits.matches(&usage));
+
+        let purchases = TransactionQuery {
+            transaction_types: vec![TransactionType::Purchase],
+            ..TransactionQuery::new(10)
+        };
+        assert!(purchases.matches(&purchase));
+        assert!(!purchases.matches(&usage));
+
+        let mut by_model = TransactionQuery::new(10);
+        by_model
+            .metadata
+            .insert("model".into(), "claude-sonnet-4".into());
+        assert!(!by_model.matches(&purchase));
+        assert!(by_model.matches(&usage));
+
+        let future = TransactionQuery {
+            since: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..TransactionQuery::new(10)
+        };
+        assert!(!future.matches(&usage));
+    }
+
+    #[test]
+    fn transaction_query_cursor_excludes_newer() {
+        let user_id = UserId::generate();
+        let older = CreditTransaction::purchase(user_id, 500, 500, "Older".into());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let newer = CreditTransaction::purchase(user_id, 500, 1000, "Newer".into());
+
+        let page = TransactionQuery {
+            before: Some(newer.id),
+            ..TransactionQuery::new(10)
+        };
+        assert!(page.matches(&older));
+        assert!(!page.matches(&newer));
+    }
 }
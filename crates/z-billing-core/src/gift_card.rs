@@ -0,0 +1,218 @@
+//! Gift card types for z-billing.
+//!
+//! A gift card is bought through Stripe like a credit purchase, but instead
+//! of crediting the buyer it mints a single-use redemption code. Whoever
+//! holds the code can redeem it into their own account once. Cards move from
+//! issued to either redeemed or voided and never back.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{GiftCardId, TransactionId, UserId};
+
+/// Characters used in redemption codes (Crockford base32: no I, L, O or U).
+const CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Number of code characters per dash-separated group.
+const CODE_GROUP_LEN: usize = 4;
+
+/// Number of dash-separated groups in a code (80 random bits).
+const CODE_GROUPS: usize = 4;
+
+/// A prepaid credit gift card.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GiftCard {
+    /// Unique gift card ID (ULID for time-ordering).
+    pub id: GiftCardId,
+
+    /// Single-use redemption code (see [`GiftCard::generate_code`]).
+    pub code: String,
+
+    /// Credits the card is worth (in cents).
+    pub amount_cents: i64,
+
+    /// The user who bought the card.
+    pub purchaser_id: UserId,
+
+    /// Current lifecycle state.
+    pub status: GiftCardStatus,
+
+    /// Stripe checkout session the card was paid for with.
+    #[serde(default)]
+    pub stripe_session_id: Option<String>,
+
+    /// The user who redeemed the card, if redeemed.
+    #[serde(default)]
+    pub redeemed_by: Option<UserId>,
+
+    /// The transaction that credited the redeemer, if redeemed.
+    #[serde(default)]
+    pub transaction_id: Option<TransactionId>,
+
+    /// When the card was redeemed.
+    #[serde(default)]
+    pub redeemed_at: Option<DateTime<Utc>>,
+
+    /// When the card was voided.
+    #[serde(default)]
+    pub voided_at: Option<DateTime<Utc>>,
+
+    /// Why the card was voided.
+    #[serde(default)]
+    pub void_reason: Option<String>,
+
+    /// When the card was issued.
+    pub created_at: DateTime<Utc>,
+
+    /// When the card was last updated.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl GiftCard {
+    /// Issue a new card with a freshly generated code.
+    #[must_use]
+    pub fn issue(
+        purchaser_id: UserId,
+        amount_cents: i64,
+        stripe_session_id: Option<String>,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: GiftCardId::generate(),
+            code: Self::generate_code(),
+            amount_cents,
+            purchaser_id,
+            status: GiftCardStatus::Issued,
+            stripe_session_id,
+            redeemed_by: None,
+            transaction_id: None,
+            redeemed_at: None,
+            voided_at: None,
+            void_reason: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Generate a random redemption code such as `7KQ2-M9XD-4HTR-B0AE`.
+    #[must_use]
+    pub fn generate_code() -> String {
+        let random = ulid::Ulid::new().random();
+        let mut code = String::with_capacity(CODE_GROUPS * (CODE_GROUP_LEN + 1));
+        for i in 0..CODE_GROUPS * CODE_GROUP_LEN {
+            if i > 0 && i % CODE_GROUP_LEN == 0 {
+                code.push('-');
+            }
+            let index = (random >> (i * 5)) & 0x1f;
+            code.push(char::from(
+                CODE_ALPHABET[usize::try_from(index).unwrap_or(0)],
+            ));
+        }
+        code
+    }
+
+    /// Normalize a user-entered code for lookup.
+    ///
+    /// Codes are matched case-insensitively and with or without dashes or
+    /// spaces.
+    #[must_use]
+    pub fn normalize_code(code: &str) -> String {
+        let compact: Vec<char> = code
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        compact
+            .chunks(CODE_GROUP_LEN)
+            .map(|group| group.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// Mark the card redeemed by `user_id` through `transaction_id`.
+    pub fn redeem(&mut self, user_id: UserId, transaction_id: TransactionId) {
+        let now = Utc::now();
+        self.status = GiftCardStatus::Redeemed;
+        self.redeemed_by = Some(user_id);
+        self.transaction_id = Some(transaction_id);
+        self.redeemed_at = Some(now);
+        self.updated_at = now;
+    }
+
+    /// Mark the card voided.
+    pub fn void(&mut self, reason: String) {
+        let now = Utc::now();
+        self.status = GiftCardStatus::Voided;
+        self.voided_at = Some(now);
+        self.void_reason = Some(reason);
+        self.updated_at = now;
+    }
+}
+
+/// Lifecycle state of a gift card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GiftCardStatus {
+    /// Paid for and waiting to be redeemed.
+    Issued,
+    /// Credited to the redeemer's account.
+    Redeemed,
+    /// Cancelled by an admin; can no longer be redeemed.
+    Voided,
+}
+
+impl GiftCardStatus {
+    /// Get the string representation used in storage and API responses.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Issued => "issued",
+            Self::Redeemed => "redeemed",
+            Self::Voided => "voided",
+        }
+    }
+}
+
+impl std::str::FromStr for GiftCardStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "issued" => Ok(Self::Issued),
+            "redeemed" => Ok(Self::Redeemed),
+            "voided" => Ok(Self::Voided),
+            other => Err(format!("unknown gift card status: {other}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_codes_are_grouped_and_unique() {
+        let code = GiftCard::generate_code();
+        assert_eq!(code.len(), 19);
+        assert_eq!(code.split('-').count(), 4);
+        assert!(code
+            .bytes()
+            .all(|b| b == b'-' || CODE_ALPHABET.contains(&b)));
+        assert_ne!(code, GiftCard::generate_code());
+    }
+
+    #[test]
+    fn codes_normalize_loosely() {
+        assert_eq!(
+            GiftCard::normalize_code(" 7kq2 m9xd-4htr b0ae "),
+            "7KQ2-M9XD-4HTR-B0AE"
+        );
+
+        let card = GiftCard::issue(UserId::generate(), 2500, None);
+        assert_eq!(
+            GiftCard::normalize_code(&card.code.to_lowercase()),
+            card.code
+        );
+        assert_eq!(card.status, GiftCardStatus::Issued);
+    }
+}
@@ -0,0 +1,70 @@
+//! Grant keys for z-billing.
+//!
+//! Free credit grants (signup, daily, monthly, referral and subscription
+//! grants) are given through `Store::grant_once`, which records a key
+//! naming the grant in the same write as the credit. A second grant with
+//! the same key for the same user is a no-op, so concurrent requests and
+//! redelivered webhooks cannot grant twice. These functions build the keys.
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+
+use crate::UserId;
+
+/// Key of the one-time signup grant.
+pub const SIGNUP: &str = "signup";
+
+/// Key of the invitee's one-time referral bonus.
+pub const REFERRAL: &str = "referral";
+
+/// Key of an inviter's referral bonus for bringing in `invitee_id`.
+#[must_use]
+pub fn referrer(invitee_id: &UserId) -> String {
+    format!("referral:{invitee_id}")
+}
+
+/// Key of the daily grant for the UTC day `date`.
+#[must_use]
+pub fn daily(date: NaiveDate) -> String {
+    format!("daily:{date}")
+}
+
+/// Key of the monthly allowance for the billing period starting at
+/// `period_start`.
+#[must_use]
+pub fn monthly(period_start: DateTime<Utc>) -> String {
+    format!(
+        "monthly:{}",
+        period_start.to_rfc3339_opts(SecondsFormat::Secs, true)
+    )
+}
+
+/// Key of the subscription credits granted for a paid Stripe invoice.
+#[must_use]
+pub fn invoice(invoice_id: &str) -> String {
+    format!("invoice:{invoice_id}")
+}
+
+/// Key of the credits granted when a Lago subscription starts.
+#[must_use]
+pub fn lago_subscription(lago_subscription_id: &str) -> String {
+    format!("lago_subscription:{lago_subscription_id}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn keys_name_the_grant_and_its_period() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        assert_eq!(daily(date), "daily:2026-10-16");
+
+        let start = Utc.with_ymd_and_hms(2026, 10, 1, 8, 30, 0).unwrap();
+        assert_eq!(monthly(start), "monthly:2026-10-01T08:30:00Z");
+
+        let invitee = UserId::generate();
+        assert_eq!(referrer(&invitee), format!("referral:{invitee}"));
+        assert_ne!(referrer(&invitee), REFERRAL);
+    }
+}
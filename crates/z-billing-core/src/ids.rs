@@ -1,11 +1,14 @@
 //! Identifier types for z-billing.
 //!
-//! This module provides strongly-typed identifiers for users, transactions, and agents.
+//! This module provides strongly-typed identifiers for users, organizations,
+//! transactions, reservations, credit lots, gift cards, outbox messages, and
+//! agents.
 //!
 //! # Macro-based ID Types
 //!
 //! The `uuid_id_type!` macro reduces boilerplate for UUID-based identifier types,
 //! ensuring consistent implementation of serialization, parsing, and display traits.
+//! The `ulid_id_type!` macro does the same for time-ordered ULID identifiers.
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -107,85 +110,99 @@ macro_rules! uuid_id_type {
 // Define UUID-based identifier types using the macro
 uuid_id_type!(UserId, "A user identifier (UUID format from Zero-ID).\n\nUser IDs are provided by Zero-ID and extracted from JWT `sub` claims.");
 uuid_id_type!(AgentId, "An agent identifier (UUID format).\n\nAgent IDs reference agents in aura-swarm or other services.");
+uuid_id_type!(OrgId, "An organization identifier (UUID format).\n\nOrganizations own a shared credit pool that their members draw from.");
+uuid_id_type!(LedgerEntryId, "A ledger entry identifier (UUID format).\n\nEntries are ordered by their timestamp, not their ID.");
 
-/// A transaction identifier using ULID for time-ordering.
+/// Macro to define a ULID-based identifier type with standard trait implementations.
 ///
-/// Transaction IDs are time-ordered to allow efficient range queries
-/// and natural chronological sorting.
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(try_from = "String", into = "String")]
-pub struct TransactionId(Ulid);
-
-impl TransactionId {
-    /// Create a new `TransactionId` from a ULID.
-    #[must_use]
-    pub const fn from_ulid(ulid: Ulid) -> Self {
-        Self(ulid)
-    }
+/// ULID identifiers are time-ordered, which allows efficient range queries and
+/// natural chronological sorting in key-value storage.
+macro_rules! ulid_id_type {
+    ($name:ident, $doc:expr) => {
+        #[doc = $doc]
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(try_from = "String", into = "String")]
+        pub struct $name(Ulid);
 
-    /// Generate a new `TransactionId` with the current timestamp.
-    #[must_use]
-    pub fn generate() -> Self {
-        Self(Ulid::new())
-    }
+        impl $name {
+            /// Create a new identifier from a ULID.
+            #[must_use]
+            pub const fn from_ulid(ulid: Ulid) -> Self {
+                Self(ulid)
+            }
 
-    /// Return the underlying ULID.
-    #[must_use]
-    pub const fn as_ulid(&self) -> &Ulid {
-        &self.0
-    }
+            /// Generate a new identifier with the current timestamp.
+            #[must_use]
+            pub fn generate() -> Self {
+                Self(Ulid::new())
+            }
 
-    /// Return the bytes of the ULID (16 bytes).
-    #[must_use]
-    pub fn to_bytes(&self) -> [u8; 16] {
-        self.0.to_bytes()
-    }
+            /// Return the underlying ULID.
+            #[must_use]
+            pub const fn as_ulid(&self) -> &Ulid {
+                &self.0
+            }
 
-    /// Create a `TransactionId` from bytes.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if the bytes are invalid.
-    pub fn from_bytes(bytes: [u8; 16]) -> Result<Self, IdError> {
-        Ok(Self(Ulid::from_bytes(bytes)))
-    }
-}
+            /// Return the bytes of the ULID (16 bytes).
+            #[must_use]
+            pub fn to_bytes(&self) -> [u8; 16] {
+                self.0.to_bytes()
+            }
 
-impl FromStr for TransactionId {
-    type Err = IdError;
+            /// Create an identifier from bytes.
+            ///
+            /// # Errors
+            ///
+            /// Returns an error if the bytes are invalid.
+            pub fn from_bytes(bytes: [u8; 16]) -> Result<Self, IdError> {
+                Ok(Self(Ulid::from_bytes(bytes)))
+            }
+        }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let ulid = Ulid::from_string(s).map_err(|_| IdError::InvalidUlid)?;
-        Ok(Self(ulid))
-    }
-}
+        impl FromStr for $name {
+            type Err = IdError;
 
-impl fmt::Debug for TransactionId {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "TransactionId({})", self.0)
-    }
-}
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let ulid = Ulid::from_string(s).map_err(|_| IdError::InvalidUlid)?;
+                Ok(Self(ulid))
+            }
+        }
 
-impl fmt::Display for TransactionId {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self.0)
+            }
+        }
 
-impl TryFrom<String> for TransactionId {
-    type Error = IdError;
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        value.parse()
-    }
-}
+        impl TryFrom<String> for $name {
+            type Error = IdError;
 
-impl From<TransactionId> for String {
-    fn from(id: TransactionId) -> Self {
-        id.0.to_string()
-    }
+            fn try_from(value: String) -> Result<Self, Self::Error> {
+                value.parse()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0.to_string()
+            }
+        }
+    };
 }
 
+// Define ULID-based identifier types using the macro
+ulid_id_type!(TransactionId, "A transaction identifier using ULID for time-ordering.\n\nTransaction IDs are time-ordered to allow efficient range queries\nand natural chronological sorting.");
+ulid_id_type!(ReservationId, "A credit reservation identifier (ULID).\n\nReservation IDs are issued when a hold is placed and are used to settle\nor release it.");
+ulid_id_type!(LotId, "A credit lot identifier (ULID).\n\nLot IDs are time-ordered, so lots of the same priority are consumed\noldest first.");
+ulid_id_type!(GiftCardId, "A gift card identifier (ULID).\n\nGift card IDs identify a card to admins without revealing its redemption\ncode.");
+ulid_id_type!(OutboxId, "An outbox message identifier (ULID).\n\nOutbox IDs are time-ordered, so messages are delivered roughly in the\norder they were written.");
+
 /// Errors that can occur when parsing identifiers.
 #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
 pub enum IdError {
@@ -242,6 +259,21 @@ mod tests {
         assert_eq!(id, parsed);
     }
 
+    #[test]
+    fn reservation_id_roundtrip() {
+        let id = ReservationId::generate();
+        let parsed = ReservationId::from_str(&id.to_string()).unwrap();
+        assert_eq!(id, parsed);
+        assert_eq!(ReservationId::from_bytes(id.to_bytes()).unwrap(), id);
+    }
+
+    #[test]
+    fn org_id_roundtrip() {
+        let id = OrgId::generate();
+        let parsed = OrgId::from_str(&id.to_string()).unwrap();
+        assert_eq!(id, parsed);
+    }
+
     #[test]
     fn agent_id_roundtrip() {
         let id = AgentId::generate();
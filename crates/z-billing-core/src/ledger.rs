@@ -0,0 +1,289 @@
+//! Double-entry ledger types for z-billing.
+//!
+//! Every balance movement is posted as a pair of entries that sum to zero:
+//! one against the wallet whose balance changed (a user account or an
+//! organization pool) and one against the system account on the other side
+//! of the movement. A wallet's balance must always equal the sum of its
+//! entries; [`LedgerReport`] records any account where it does not.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{CreditTransaction, LedgerEntryId, OrgId, TransactionId, TransactionType, UserId};
+
+/// An internal account on the other side of wallet movements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemAccount {
+    /// Free credits: signup, daily, monthly, subscription and bonus grants.
+    Grants,
+    /// Credits paid for through Stripe.
+    StripeClearing,
+    /// Credits spent on usage, less reversals and refunds.
+    UsageRevenue,
+    /// Unspent credits removed when their lot expired.
+    Expirations,
+    /// Balances that existed before they were posted to the ledger.
+    OpeningBalances,
+    /// Direct balance changes made without a transaction.
+    Adjustments,
+    /// Credits in flight between two wallets; nets to zero once both sides
+    /// of a transfer are posted.
+    Transfers,
+}
+
+impl SystemAccount {
+    /// Get the string representation used in storage and API responses.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Grants => "grants",
+            Self::StripeClearing => "stripe_clearing",
+            Self::UsageRevenue => "usage_revenue",
+            Self::Expirations => "expirations",
+            Self::OpeningBalances => "opening_balances",
+            Self::Adjustments => "adjustments",
+            Self::Transfers => "transfers",
+        }
+    }
+
+    /// The system account that funds (or receives) a transaction of the
+    /// given type.
+    #[must_use]
+    pub const fn for_transaction(transaction_type: &TransactionType) -> Self {
+        match transaction_type {
+            TransactionType::Purchase | TransactionType::AutoRefill | TransactionType::GiftCard => {
+                Self::StripeClearing
+            }
+            TransactionType::SubscriptionGrant
+            | TransactionType::Bonus
+            | TransactionType::SignupGrant
+            | TransactionType::DailyGrant
+            | TransactionType::ReferralBonus
+            | TransactionType::MonthlyAllowance => Self::Grants,
+            TransactionType::Usage | TransactionType::Refund | TransactionType::Reversal => {
+                Self::UsageRevenue
+            }
+            TransactionType::Expiry => Self::Expirations,
+            TransactionType::TransferOut | TransactionType::TransferIn => Self::Transfers,
+        }
+    }
+}
+
+impl FromStr for SystemAccount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grants" => Ok(Self::Grants),
+            "stripe_clearing" => Ok(Self::StripeClearing),
+            "usage_revenue" => Ok(Self::UsageRevenue),
+            "expirations" => Ok(Self::Expirations),
+            "opening_balances" => Ok(Self::OpeningBalances),
+            "adjustments" => Ok(Self::Adjustments),
+            "transfers" => Ok(Self::Transfers),
+            other => Err(format!("unknown system account: {other}")),
+        }
+    }
+}
+
+/// An account that ledger entries are posted to.
+///
+/// The string form is `user:<id>`, `org:<id>` or `system:<name>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind", content = "id")]
+pub enum LedgerAccount {
+    /// A user's personal balance.
+    User(UserId),
+    /// An organization's shared pool.
+    Org(OrgId),
+    /// An internal system account.
+    System(SystemAccount),
+}
+
+impl LedgerAccount {
+    /// The wallet a transaction moves: the organization pool if the
+    /// transaction carries an org ID, otherwise the user's balance.
+    #[must_use]
+    pub const fn wallet_for(transaction: &CreditTransaction) -> Self {
+        match transaction.org_id {
+            Some(org_id) => Self::Org(org_id),
+            None => Self::User(transaction.user_id),
+        }
+    }
+}
+
+impl fmt::Display for LedgerAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::User(id) => write!(f, "user:{id}"),
+            Self::Org(id) => write!(f, "org:{id}"),
+            Self::System(account) => write!(f, "system:{}", account.as_str()),
+        }
+    }
+}
+
+impl FromStr for LedgerAccount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid ledger account: {s}");
+        match s.split_once(':') {
+            Some(("user", id)) => id.parse().map(Self::User).map_err(|_| invalid()),
+            Some(("org", id)) => id.parse().map(Self::Org).map_err(|_| invalid()),
+            Some(("system", name)) => name.parse().map(Self::System),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// One side of a balance movement.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// Unique entry ID.
+    pub id: LedgerEntryId,
+
+    /// The account whose balance changed.
+    pub account: LedgerAccount,
+
+    /// Change in the account's balance (in cents, signed).
+    pub amount_cents: i64,
+
+    /// The transaction that caused the movement (None for opening balances
+    /// and direct adjustments).
+    pub transaction_id: Option<TransactionId>,
+
+    /// When the entry was posted.
+    pub created_at: DateTime<Utc>,
+}
+
+/// Build the balanced pair of entries moving `amount_cents` into `wallet`
+/// from `counter` (or out of it, if negative).
+#[must_use]
+pub fn transfer(
+    wallet: LedgerAccount,
+    counter: SystemAccount,
+    amount_cents: i64,
+    transaction_id: Option<TransactionId>,
+    at: DateTime<Utc>,
+) -> [LedgerEntry; 2] {
+    let entry = |account, amount_cents| LedgerEntry {
+        id: LedgerEntryId::generate(),
+        account,
+        amount_cents,
+        transaction_id,
+        created_at: at,
+    };
+    [
+        entry(wallet, amount_cents),
+        entry(LedgerAccount::System(counter), -amount_cents),
+    ]
+}
+
+/// Build the entries for a transaction that changed `wallet`'s balance by
+/// `amount_cents`. The counter account is chosen by transaction type.
+#[must_use]
+pub fn postings(
+    wallet: LedgerAccount,
+    transaction: &CreditTransaction,
+    amount_cents: i64,
+) -> [LedgerEntry; 2] {
+    transfer(
+        wallet,
+        SystemAccount::for_transaction(&transaction.transaction_type),
+        amount_cents,
+        Some(transaction.id),
+        transaction.created_at,
+    )
+}
+
+/// A wallet whose balance does not match its ledger entries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerDrift {
+    /// The wallet.
+    pub account: LedgerAccount,
+
+    /// The stored balance (in cents).
+    pub balance_cents: i64,
+
+    /// The sum of the wallet's ledger entries (in cents).
+    pub ledger_cents: i64,
+}
+
+impl LedgerDrift {
+    /// Amount the stored balance is off by (positive = balance too high).
+    #[must_use]
+    pub const fn drift_cents(&self) -> i64 {
+        self.balance_cents - self.ledger_cents
+    }
+}
+
+/// Result of checking every wallet against the ledger.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LedgerReport {
+    /// Number of wallets (accounts and organizations) checked.
+    pub accounts_checked: usize,
+
+    /// Wallets whose balance does not match their entries.
+    pub drift: Vec<LedgerDrift>,
+
+    /// Sum of all entries across all accounts; non-zero means some
+    /// movement was posted one-sided.
+    pub unbalanced_cents: i64,
+}
+
+impl LedgerReport {
+    /// Whether every wallet matches the ledger and the ledger balances.
+    #[must_use]
+    pub fn is_consistent(&self) -> bool {
+        self.drift.is_empty() && self.unbalanced_cents == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn postings_balance_against_counter_account() {
+        let user_id = UserId::generate();
+        let tx = CreditTransaction::usage(user_id, 40, 60, "usage".into(), serde_json::Value::Null);
+        let [wallet, counter] = postings(LedgerAccount::wallet_for(&tx), &tx, -40);
+
+        assert_eq!(wallet.account, LedgerAccount::User(user_id));
+        assert_eq!(wallet.amount_cents, -40);
+        assert_eq!(
+            counter.account,
+            LedgerAccount::System(SystemAccount::UsageRevenue)
+        );
+        assert_eq!(wallet.amount_cents + counter.amount_cents, 0);
+        assert_eq!(counter.transaction_id, Some(tx.id));
+    }
+
+    #[test]
+    fn org_transactions_post_to_org_wallet() {
+        let org_id = OrgId::generate();
+        let tx =
+            CreditTransaction::bonus(UserId::generate(), 100, 100, "Team".into()).with_org(org_id);
+
+        assert_eq!(LedgerAccount::wallet_for(&tx), LedgerAccount::Org(org_id));
+    }
+
+    #[test]
+    fn ledger_account_string_roundtrip() {
+        for account in [
+            LedgerAccount::User(UserId::generate()),
+            LedgerAccount::Org(OrgId::generate()),
+            LedgerAccount::System(SystemAccount::StripeClearing),
+        ] {
+            assert_eq!(
+                account.to_string().parse::<LedgerAccount>().unwrap(),
+                account
+            );
+        }
+        assert!("wallet:123".parse::<LedgerAccount>().is_err());
+    }
+}
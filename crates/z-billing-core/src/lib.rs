@@ -2,11 +2,25 @@
 //!
 //! This crate provides the foundational types used throughout the z-billing platform:
 //!
-//! - **Identifiers**: `UserId`, `TransactionId`, `AgentId`
+//! - **Identifiers**: `UserId`, `OrgId`, `TransactionId`, `ReservationId`, `LotId`, `AgentId`,
+//!   `LedgerEntryId`, `GiftCardId`, `OutboxId`
 //! - **Accounts**: `Account`, `Subscription`, `AutoRefill`
 //! - **Credits**: `CreditTransaction`, `TransactionType`
-//! - **Usage**: `UsageEvent`, `UsageSource`, `UsageMetric`
-//! - **Pricing**: `PricingConfig`, `LlmPricing`
+//! - **Organizations**: `Organization`, `OrgMembership`, `OrgRole`
+//! - **Agent budgets**: `AgentBudget`, `AgentSpend`, `BudgetPeriod`
+//! - **Credit lots**: `CreditLot`, `LotDraw`
+//! - **Ledger**: `LedgerEntry`, `LedgerAccount`, `SystemAccount`, `LedgerReport`
+//! - **Reservations**: `Reservation`, `ReservationStatus`
+//! - **Transfers**: `CreditTransfer`
+//! - **Usage**: `UsageEvent`, `UsageReversal`, `UsageSource`, `UsageMetric`
+//! - **Usage summaries**: `UsageSummaryQuery`, `UsageSummaryRow`, `DailyUsage`
+//! - **Pricing**: `PricingConfig`, `LlmPricing`, `PricingCatalog`, `PricingDiff`
+//! - **Storage metering**: `StorageMeter`, `StorageCharge`
+//! - **Promo codes**: `PromoCode`, `PromoRedemption`, `PromoRejection`
+//! - **Gift cards**: `GiftCard`, `GiftCardStatus`
+//! - **Outbox**: `OutboxMessage`, `OutboxTopic`, `OutboxStatus`
+//! - **Grants**: idempotency keys for free credit grants (`grant` module)
+//! - **Webhooks**: `ProcessedWebhook`
 //!
 //! # Z Credit Unit
 //!
@@ -22,11 +36,25 @@
 #![warn(clippy::pedantic)]
 
 pub mod account;
+pub mod budget;
 pub mod credits;
 pub mod error;
+pub mod gift_card;
+pub mod grant;
 pub mod ids;
+pub mod ledger;
+pub mod lot;
+pub mod org;
+pub mod outbox;
 pub mod pricing;
+pub mod pricing_catalog;
+pub mod promo;
+pub mod reservation;
+pub mod storage_meter;
+pub mod transfer;
 pub mod usage;
+pub mod usage_summary;
+pub mod webhook;
 
 pub use account::{
     Account, AutoRefill, Plan, Subscription, SubscriptionStatus, DEFAULT_AUTO_REFILL_AMOUNT_CENTS,
@@ -34,8 +62,35 @@ pub use account::{
     MORTAL_PLAN_CREDITS, PRO_PLAN_CREDITS, PRO_PLAN_PRICE_CENTS, SAGE_PLAN_CREDITS,
     SAGE_PLAN_PRICE_CENTS,
 };
-pub use credits::{CreditTransaction, TransactionType};
+pub use budget::{AgentBudget, AgentSpend, BudgetPeriod};
+pub use credits::{AmountSign, CreditTransaction, TransactionQuery, TransactionType};
 pub use error::{BillingError, Result};
-pub use ids::{AgentId, IdError, TransactionId, UserId};
-pub use pricing::{maker_for_model, LlmPricing, Maker, ModelKey, PricingConfig};
-pub use usage::{LlmProvider, TokenDirection, UsageEvent, UsageMetric, UsageSource};
+pub use gift_card::{GiftCard, GiftCardStatus};
+pub use ids::{
+    AgentId, GiftCardId, IdError, LedgerEntryId, LotId, OrgId, OutboxId, ReservationId,
+    TransactionId, UserId,
+};
+pub use ledger::{LedgerAccount, LedgerDrift, LedgerEntry, LedgerReport, SystemAccount};
+pub use lot::{CreditLot, LotDraw};
+pub use org::{OrgMembership, OrgRole, Organization};
+pub use outbox::{OutboxMessage, OutboxStatus, OutboxTopic, OUTBOX_MAX_ATTEMPTS};
+pub use pricing::{
+    maker_for_model, ImageCredits, ImagePrice, LlmPricing, LlmTokenUsage, Maker, MediaPricing,
+    ModelKey, ModelRate, NamedRate, PricingConfig, DEFAULT_STORAGE_GB_HOUR_CREDITS,
+};
+pub use pricing_catalog::{
+    CatalogError, ModelPrice, ModelPriceChange, PricingCatalog, PricingDiff, RateChange,
+    BUILTIN_CATALOG_VERSION,
+};
+pub use promo::{PromoCode, PromoRedemption, PromoRejection};
+pub use reservation::{Reservation, ReservationStatus};
+pub use storage_meter::{StorageCharge, StorageMeter, BYTES_PER_GB};
+pub use transfer::CreditTransfer;
+pub use usage::{
+    ImageResolution, LlmProvider, TokenDirection, UsageEvent, UsageMetric, UsageReversal,
+    UsageSource,
+};
+pub use usage_summary::{
+    DailyUsage, UsageDimension, UsageGroup, UsageInterval, UsageSummaryQuery, UsageSummaryRow,
+};
+pub use webhook::ProcessedWebhook;
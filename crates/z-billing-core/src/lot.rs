@@ -0,0 +1,399 @@
+//! Credit lot types for z-billing.
+//!
+//! Every credit addition is stored as a lot that remembers where the credits
+//! came from and when they expire. Usage draws lots down in a fixed order
+//! (see [`TransactionType::consumption_priority`]) so that credits which are
+//! about to vanish are spent first.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{LotId, TransactionType, UserId};
+
+/// A batch of credits from a single source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreditLot {
+    /// Unique lot ID (ULID for time-ordering).
+    pub id: LotId,
+
+    /// The user who owns the credits.
+    pub user_id: UserId,
+
+    /// The kind of transaction that created the lot.
+    pub source: TransactionType,
+
+    /// Amount originally added in cents.
+    pub amount_cents: i64,
+
+    /// Amount not yet spent or expired in cents.
+    pub remaining_cents: i64,
+
+    /// When unspent credits are removed (None = never).
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// When the lot was created.
+    pub created_at: DateTime<Utc>,
+}
+
+impl CreditLot {
+    /// Create a new, unspent lot.
+    #[must_use]
+    pub fn new(
+        user_id: UserId,
+        source: TransactionType,
+        amount_cents: i64,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            id: LotId::generate(),
+            user_id,
+            source,
+            amount_cents,
+            remaining_cents: amount_cents,
+            expires_at,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Whether the lot's unspent credits are due to be removed at `now`.
+    #[must_use]
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.remaining_cents > 0 && self.expires_at.is_some_and(|at| at <= now)
+    }
+
+    /// Cap the lot at the balance left after its credits were added.
+    ///
+    /// Credits that pay off an overdraft are spent on arrival, so only the
+    /// part that lifts the balance above zero stays in the lot.
+    pub fn cap_to_balance(&mut self, balance_cents: i64) {
+        self.remaining_cents = self.remaining_cents.min(balance_cents.max(0));
+    }
+}
+
+/// Credits a usage charge took from one lot.
+///
+/// Kept with the usage event so that a reversal can return the credits with
+/// the source and expiry they were spent from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LotDraw {
+    /// The lot the credits came from.
+    pub lot_id: LotId,
+
+    /// The lot's source.
+    pub source: TransactionType,
+
+    /// The lot's expiry (None = never).
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// Amount taken from the lot in cents.
+    pub amount_cents: i64,
+}
+
+/// Default expiry for credits of the given type granted at `granted_at`.
+///
+/// Daily grants are use-it-or-lose-it and expire at the next UTC midnight.
+/// All other credits do not expire.
+#[must_use]
+pub fn default_expiry(
+    source: &TransactionType,
+    granted_at: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    match source {
+        TransactionType::DailyGrant => {
+            let next_day = granted_at.date_naive() + Duration::days(1);
+            next_day.and_hms_opt(0, 0, 0).map(|t| t.and_utc())
+        }
+        _ => None,
+    }
+}
+
+/// Sort lots into consumption order.
+///
+/// Lots are ordered by source priority, then by earliest expiry (lots that
+/// never expire last), then oldest first.
+pub fn sort_for_consumption(lots: &mut [CreditLot]) {
+    lots.sort_by(|a, b| {
+        a.source
+            .consumption_priority()
+            .cmp(&b.source.consumption_priority())
+            .then_with(|| match (a.expires_at, b.expires_at) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            })
+            .then_with(|| a.id.as_ulid().cmp(b.id.as_ulid()))
+    });
+}
+
+/// Draw `amount_cents` down from `lots` in consumption order.
+///
+/// Sorts `lots` with [`sort_for_consumption`] and reduces `remaining_cents`
+/// on as many lots as needed. Returns how many leading lots were touched;
+/// only `lots[..n]` need to be written back. Any amount the lots cannot
+/// cover is taken from balance that predates lot tracking.
+pub fn draw_down(lots: &mut [CreditLot], amount_cents: i64) -> usize {
+    sort_for_consumption(lots);
+
+    let mut left = amount_cents;
+    let mut touched = 0;
+    for lot in lots.iter_mut() {
+        if left <= 0 {
+            break;
+        }
+        let take = left.min(lot.remaining_cents);
+        if take <= 0 {
+            continue;
+        }
+        lot.remaining_cents -= take;
+        left -= take;
+        touched += 1;
+    }
+
+    touched
+}
+
+/// Unspent credits in `lots` that came from a transferable source.
+#[must_use]
+pub fn transferable_cents(lots: &[CreditLot]) -> i64 {
+    lots.iter()
+        .filter(|lot| lot.source.is_transferable())
+        .map(|lot| lot.remaining_cents.max(0))
+        .sum()
+}
+
+/// Draw `amount_cents` down from the transferable lots in `lots`.
+///
+/// Like [`draw_down`], but only lots whose source
+/// [`is_transferable`](TransactionType::is_transferable) are drawn, in
+/// consumption order. Transferable lots are moved to the front so that only
+/// `lots[..n]` need to be written back.
+pub fn draw_down_transferable(lots: &mut [CreditLot], amount_cents: i64) -> usize {
+    sort_for_consumption(lots);
+    lots.sort_by_key(|lot| !lot.source.is_transferable());
+
+    let transferable = lots
+        .iter()
+        .take_while(|lot| lot.source.is_transferable())
+        .count();
+    draw_down(&mut lots[..transferable], amount_cents)
+}
+
+/// Like [`draw_down`], but also returns what was taken from each lot, in
+/// the order the lots were drawn.
+pub fn draw_down_recorded(lots: &mut [CreditLot], amount_cents: i64) -> (usize, Vec<LotDraw>) {
+    sort_for_consumption(lots);
+    let before: Vec<i64> = lots.iter().map(|lot| lot.remaining_cents).collect();
+
+    let touched = draw_down(lots, amount_cents);
+    let draws = lots[..touched]
+        .iter()
+        .zip(before)
+        .filter(|(lot, before)| *before > lot.remaining_cents)
+        .map(|(lot, before)| LotDraw {
+            lot_id: lot.id,
+            source: lot.source.clone(),
+            expires_at: lot.expires_at,
+            amount_cents: before - lot.remaining_cents,
+        })
+        .collect();
+
+    (touched, draws)
+}
+
+/// Lots that return `amount_cents` of a refunded usage charge to `user_id`.
+///
+/// `draws` are what the charge of `charged_cents` took from lots, and
+/// `refunded_cents` how much of it earlier reversals already returned.
+/// Refunds undo the charge from its end: first the part no lot covered,
+/// which was taken from balance that predates lot tracking and gets no lot
+/// back, then the draws, last first. Each returned lot has the source and
+/// expiry of the lot it refills, and the lots are capped together at
+/// `balance_cents`, the balance after the refund.
+#[must_use]
+pub fn refund_draws(
+    user_id: UserId,
+    draws: &[LotDraw],
+    charged_cents: i64,
+    refunded_cents: i64,
+    amount_cents: i64,
+    balance_cents: i64,
+) -> Vec<CreditLot> {
+    // The refund covers this span of the charge, counted in draw order
+    let end = charged_cents - refunded_cents;
+    let start = end - amount_cents;
+
+    let mut offset = 0;
+    let mut spans = Vec::new();
+    for draw in draws {
+        let from = offset.max(start);
+        let to = (offset + draw.amount_cents).min(end);
+        if to > from {
+            spans.push((draw, to - from));
+        }
+        offset += draw.amount_cents;
+    }
+
+    let mut room = balance_cents.max(0);
+    spans
+        .into_iter()
+        .rev()
+        .map(|(draw, amount_cents)| {
+            let mut lot =
+                CreditLot::new(user_id, draw.source.clone(), amount_cents, draw.expires_at);
+            lot.cap_to_balance(room);
+            room -= lot.remaining_cents;
+            lot
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lot(source: TransactionType, amount_cents: i64) -> CreditLot {
+        CreditLot::new(UserId::generate(), source, amount_cents, None)
+    }
+
+    #[test]
+    fn daily_grant_expires_at_next_midnight() {
+        let granted_at = "2025-03-14T15:09:26Z".parse::<DateTime<Utc>>().unwrap();
+        let expiry = default_expiry(&TransactionType::DailyGrant, granted_at).unwrap();
+        assert_eq!(expiry.to_rfc3339(), "2025-03-15T00:00:00+00:00");
+
+        assert!(default_expiry(&TransactionType::Purchase, granted_at).is_none());
+        assert!(default_expiry(&TransactionType::MonthlyAllowance, granted_at).is_none());
+    }
+
+    #[test]
+    fn draw_down_follows_priority_order() {
+        let mut lots = vec![
+            lot(TransactionType::Purchase, 1000),
+            lot(TransactionType::Bonus, 300),
+            lot(TransactionType::MonthlyAllowance, 200),
+            lot(TransactionType::DailyGrant, 50),
+        ];
+
+        let touched = draw_down(&mut lots, 400);
+
+        assert_eq!(touched, 3);
+        assert_eq!(lots[0].source, TransactionType::DailyGrant);
+        assert_eq!(lots[0].remaining_cents, 0);
+        assert_eq!(lots[1].source, TransactionType::MonthlyAllowance);
+        assert_eq!(lots[1].remaining_cents, 0);
+        assert_eq!(lots[2].source, TransactionType::Bonus);
+        assert_eq!(lots[2].remaining_cents, 150);
+        assert_eq!(lots[3].remaining_cents, 1000);
+    }
+
+    #[test]
+    fn draw_down_prefers_earliest_expiry_within_priority() {
+        let now = Utc::now();
+        let mut later = lot(TransactionType::Bonus, 100);
+        later.expires_at = Some(now + Duration::days(7));
+        let mut sooner = lot(TransactionType::Bonus, 100);
+        sooner.expires_at = Some(now + Duration::days(1));
+        let never = lot(TransactionType::Bonus, 100);
+        let mut lots = vec![never, later, sooner];
+
+        draw_down(&mut lots, 150);
+
+        assert_eq!(lots[0].expires_at, Some(now + Duration::days(1)));
+        assert_eq!(lots[0].remaining_cents, 0);
+        assert_eq!(lots[1].remaining_cents, 50);
+        assert!(lots[2].expires_at.is_none());
+        assert_eq!(lots[2].remaining_cents, 100);
+    }
+
+    #[test]
+    fn draw_down_beyond_lots_touches_all() {
+        let mut lots = vec![lot(TransactionType::DailyGrant, 50)];
+        assert_eq!(draw_down(&mut lots, 80), 1);
+        assert_eq!(lots[0].remaining_cents, 0);
+    }
+
+    #[test]
+    fn transfers_draw_only_paid_credits() {
+        let mut lots = vec![
+            lot(TransactionType::DailyGrant, 50),
+            lot(TransactionType::Purchase, 500),
+            lot(TransactionType::Bonus, 300),
+            lot(TransactionType::TransferIn, 200),
+        ];
+        assert_eq!(transferable_cents(&lots), 700);
+
+        let touched = draw_down_transferable(&mut lots, 600);
+
+        assert_eq!(touched, 2);
+        assert_eq!(transferable_cents(&lots), 100);
+        let untouched: i64 = lots[2..].iter().map(|lot| lot.remaining_cents).sum();
+        assert_eq!(untouched, 350);
+    }
+
+    #[test]
+    fn refunds_refill_drawn_lots_last_first() {
+        let user_id = UserId::generate();
+        let tomorrow = Utc::now() + Duration::days(1);
+        let daily = CreditLot::new(user_id, TransactionType::DailyGrant, 50, Some(tomorrow));
+        let mut lots = vec![lot(TransactionType::Purchase, 200), daily];
+
+        // 300 charged: 50 daily, 200 purchased, 50 beyond the lots
+        let (touched, draws) = draw_down_recorded(&mut lots, 300);
+        assert_eq!(touched, 2);
+        assert_eq!(draws.len(), 2);
+        assert_eq!(draws[0].source, TransactionType::DailyGrant);
+        assert_eq!(draws[0].expires_at, Some(tomorrow));
+        assert_eq!(draws[0].amount_cents, 50);
+        assert_eq!(draws[1].source, TransactionType::Purchase);
+        assert_eq!(draws[1].amount_cents, 200);
+
+        // The untracked part comes back first, with no lot
+        let first = refund_draws(user_id, &draws, 300, 0, 100, 100);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].source, TransactionType::Purchase);
+        assert_eq!(first[0].remaining_cents, 50);
+        assert!(first[0].expires_at.is_none());
+
+        // Then the rest of the purchase and the daily grant, with its expiry
+        let rest = refund_draws(user_id, &draws, 300, 100, 200, 300);
+        assert_eq!(rest.len(), 2);
+        assert_eq!(rest[0].source, TransactionType::Purchase);
+        assert_eq!(rest[0].remaining_cents, 150);
+        assert_eq!(rest[1].source, TransactionType::DailyGrant);
+        assert_eq!(rest[1].remaining_cents, 50);
+        assert_eq!(rest[1].expires_at, Some(tomorrow));
+    }
+
+    #[test]
+    fn refunds_repaying_overdraft_are_spent() {
+        let user_id = UserId::generate();
+        let draws = vec![LotDraw {
+            lot_id: LotId::generate(),
+            source: TransactionType::Purchase,
+            expires_at: None,
+            amount_cents: 300,
+        }];
+
+        // Refunded onto a -100 balance, only 200 stays in lots
+        let lots = refund_draws(user_id, &draws, 300, 0, 300, 200);
+        assert_eq!(lots[0].amount_cents, 300);
+        assert_eq!(lots[0].remaining_cents, 200);
+    }
+
+    #[test]
+    fn credits_repaying_overdraft_are_spent() {
+        // 1000 credited onto a -400 balance leaves 600 in the lot
+        let mut repaying = lot(TransactionType::Purchase, 1000);
+        repaying.cap_to_balance(600);
+        assert_eq!(repaying.remaining_cents, 600);
+
+        let mut swallowed = lot(TransactionType::Purchase, 100);
+        swallowed.cap_to_balance(-300);
+        assert_eq!(swallowed.remaining_cents, 0);
+
+        let mut untouched = lot(TransactionType::Purchase, 100);
+        untouched.cap_to_balance(250);
+        assert_eq!(untouched.remaining_cents, 100);
+    }
+}
@@ -0,0 +1,172 @@
+//! Organization types for z-billing.
+//!
+//! An organization owns a shared credit pool. Members draw usage from the
+//! pool, each optionally limited by a monthly spend cap, while every
+//! transaction still records the acting user.
+
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{OrgId, UserId};
+
+/// An organization with a shared credit balance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Organization {
+    /// The organization ID.
+    pub id: OrgId,
+
+    /// Display name.
+    pub name: String,
+
+    /// Shared Z Credit balance in cents.
+    pub balance_cents: i64,
+
+    /// Lifetime credits added to the pool (in cents).
+    pub lifetime_purchased_cents: i64,
+
+    /// Lifetime credits used by members (in cents).
+    pub lifetime_used_cents: i64,
+
+    /// When the organization was created.
+    pub created_at: DateTime<Utc>,
+
+    /// When the organization was last updated.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Organization {
+    /// Create a new organization with zero balance.
+    #[must_use]
+    pub fn new(name: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id: OrgId::generate(),
+            name,
+            balance_cents: 0,
+            lifetime_purchased_cents: 0,
+            lifetime_used_cents: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// A user's membership in an organization.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrgMembership {
+    /// The organization.
+    pub org_id: OrgId,
+
+    /// The member.
+    pub user_id: UserId,
+
+    /// The member's role.
+    pub role: OrgRole,
+
+    /// Most the member may spend from the pool per calendar month (UTC),
+    /// in cents. None = no cap.
+    pub monthly_spend_cap_cents: Option<i64>,
+
+    /// When the membership was created.
+    pub created_at: DateTime<Utc>,
+
+    /// When the membership was last updated.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl OrgMembership {
+    /// Create a new, uncapped membership.
+    #[must_use]
+    pub fn new(org_id: OrgId, user_id: UserId, role: OrgRole) -> Self {
+        let now = Utc::now();
+        Self {
+            org_id,
+            user_id,
+            role,
+            monthly_spend_cap_cents: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Role of an organization member.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrgRole {
+    /// Full control, including managing other owners.
+    Owner,
+    /// Can manage members and their spend caps.
+    Admin,
+    /// Can spend from the pool.
+    Member,
+}
+
+impl OrgRole {
+    /// Get the string representation used in storage and API responses.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Owner => "owner",
+            Self::Admin => "admin",
+            Self::Member => "member",
+        }
+    }
+
+    /// Whether this role may add, update, or remove members.
+    #[must_use]
+    pub const fn can_manage_members(&self) -> bool {
+        matches!(self, Self::Owner | Self::Admin)
+    }
+}
+
+impl std::str::FromStr for OrgRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "owner" => Ok(Self::Owner),
+            "admin" => Ok(Self::Admin),
+            "member" => Ok(Self::Member),
+            other => Err(format!("unknown organization role: {other}")),
+        }
+    }
+}
+
+/// Start of the spend cap period containing `now` (the first of the month, UTC).
+#[must_use]
+pub fn spend_cap_period_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_organization_has_zero_balance() {
+        let org = Organization::new("Acme".to_string());
+        assert_eq!(org.balance_cents, 0);
+        assert_eq!(org.lifetime_used_cents, 0);
+    }
+
+    #[test]
+    fn role_string_roundtrip() {
+        for role in [OrgRole::Owner, OrgRole::Admin, OrgRole::Member] {
+            assert_eq!(role.as_str().parse::<OrgRole>().unwrap(), role);
+        }
+        assert!(OrgRole::Admin.can_manage_members());
+        assert!(!OrgRole::Member.can_manage_members());
+    }
+
+    #[test]
+    fn spend_cap_period_starts_on_first_of_month() {
+        let now = "2025-03-14T15:09:26Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(
+            spend_cap_period_start(now).to_rfc3339(),
+            "2025-03-01T00:00:00+00:00"
+        );
+    }
+}
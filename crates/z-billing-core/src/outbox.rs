@@ -0,0 +1,252 @@
+//! Transactional outbox types for z-billing.
+//!
+//! Side effects of a balance change (forwarding usage to Lago, analytics,
+//! zOS sync, balance notifications) are written as outbox messages in the
+//! same store write as the change itself. A background dispatcher then
+//! delivers them, retrying with backoff, so a crash or deploy delays a side
+//! effect instead of dropping it. Messages that keep failing are
+//! dead-lettered for an admin to inspect and replay.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::OutboxId;
+
+/// Delivery attempts before a message is dead-lettered.
+pub const OUTBOX_MAX_ATTEMPTS: u32 = 10;
+
+/// Delay before the first retry. Each further retry doubles it.
+const BASE_RETRY_DELAY_SECONDS: i64 = 5;
+
+/// Longest delay between retries.
+const MAX_RETRY_DELAY_SECONDS: i64 = 3600;
+
+/// A side effect waiting to be delivered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    /// Unique message ID (ULID for time-ordering).
+    pub id: OutboxId,
+
+    /// Where the message is delivered.
+    pub topic: OutboxTopic,
+
+    /// Topic-specific payload.
+    pub payload: serde_json::Value,
+
+    /// Delivery state.
+    pub status: OutboxStatus,
+
+    /// Failed delivery attempts so far.
+    pub attempts: u32,
+
+    /// Earliest time the dispatcher may (re)try delivery.
+    pub next_attempt_at: DateTime<Utc>,
+
+    /// Error from the most recent failed attempt.
+    #[serde(default)]
+    pub last_error: Option<String>,
+
+    /// When the message was written.
+    pub created_at: DateTime<Utc>,
+
+    /// When the message was last updated.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl OutboxMessage {
+    /// Create a pending message that is due immediately.
+    #[must_use]
+    pub fn new(topic: OutboxTopic, payload: serde_json::Value) -> Self {
+        let now = Utc::now();
+        Self {
+            id: OutboxId::generate(),
+            topic,
+            payload,
+            status: OutboxStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Whether the dispatcher should try to deliver the message at `now`.
+    #[must_use]
+    pub fn is_due_at(&self, now: DateTime<Utc>) -> bool {
+        self.status == OutboxStatus::Pending && self.next_attempt_at <= now
+    }
+
+    /// Record a failed delivery attempt, scheduling a retry with
+    /// exponential backoff or dead-lettering the message once
+    /// [`OUTBOX_MAX_ATTEMPTS`] is reached.
+    pub fn record_failure(&mut self, error: impl Into<String>, now: DateTime<Utc>) {
+        self.attempts += 1;
+        self.last_error = Some(error.into());
+        self.updated_at = now;
+        if self.attempts >= OUTBOX_MAX_ATTEMPTS {
+            self.status = OutboxStatus::DeadLettered;
+        } else {
+            self.next_attempt_at = now + retry_delay(self.attempts);
+        }
+    }
+
+    /// Put the message back in the queue with a fresh set of attempts.
+    ///
+    /// The last error is kept for reference until the next attempt.
+    pub fn replay(&mut self, now: DateTime<Utc>) {
+        self.status = OutboxStatus::Pending;
+        self.attempts = 0;
+        self.next_attempt_at = now;
+        self.updated_at = now;
+    }
+}
+
+/// Delay before retrying a message that has failed `attempts` times.
+#[must_use]
+pub fn retry_delay(attempts: u32) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+    Duration::seconds((BASE_RETRY_DELAY_SECONDS << exponent).min(MAX_RETRY_DELAY_SECONDS))
+}
+
+/// Where an outbox message is delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxTopic {
+    /// Usage event forwarded to Lago.
+    LagoUsage,
+    /// Analytics event sent to Mixpanel.
+    Mixpanel,
+    /// Pro subscription status synced to zOS.
+    ZosProStatus,
+    /// Balance notification broadcast to WebSocket clients.
+    BalanceUpdate,
+}
+
+impl OutboxTopic {
+    /// Get the string representation used in storage and API responses.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::LagoUsage => "lago_usage",
+            Self::Mixpanel => "mixpanel",
+            Self::ZosProStatus => "zos_pro_status",
+            Self::BalanceUpdate => "balance_update",
+        }
+    }
+}
+
+impl std::str::FromStr for OutboxTopic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lago_usage" => Ok(Self::LagoUsage),
+            "mixpanel" => Ok(Self::Mixpanel),
+            "zos_pro_status" => Ok(Self::ZosProStatus),
+            "balance_update" => Ok(Self::BalanceUpdate),
+            other => Err(format!("unknown outbox topic: {other}")),
+        }
+    }
+}
+
+/// Delivery state of an outbox message.
+///
+/// Delivered messages are deleted, so there is no delivered state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    /// Waiting for (re)delivery.
+    Pending,
+    /// Gave up after [`OUTBOX_MAX_ATTEMPTS`] failures; needs a replay.
+    DeadLettered,
+}
+
+impl OutboxStatus {
+    /// Get the string representation used in storage and API responses.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::DeadLettered => "dead_lettered",
+        }
+    }
+}
+
+impl std::str::FromStr for OutboxStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "dead_lettered" => Ok(Self::DeadLettered),
+            other => Err(format!("unknown outbox status: {other}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> OutboxMessage {
+        OutboxMessage::new(OutboxTopic::Mixpanel, serde_json::json!({}))
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_cap() {
+        assert_eq!(retry_delay(1), Duration::seconds(5));
+        assert_eq!(retry_delay(2), Duration::seconds(10));
+        assert_eq!(retry_delay(4), Duration::seconds(40));
+        assert_eq!(retry_delay(20), Duration::seconds(3600));
+    }
+
+    #[test]
+    fn failures_back_off_then_dead_letter() {
+        let mut message = message();
+        let now = message.created_at;
+        assert!(message.is_due_at(now));
+
+        message.record_failure("timeout", now);
+        assert_eq!(message.attempts, 1);
+        assert_eq!(message.status, OutboxStatus::Pending);
+        assert!(!message.is_due_at(now));
+        assert!(message.is_due_at(now + Duration::seconds(5)));
+
+        for _ in 1..OUTBOX_MAX_ATTEMPTS {
+            message.record_failure("timeout", now);
+        }
+        assert_eq!(message.status, OutboxStatus::DeadLettered);
+        assert!(!message.is_due_at(now + Duration::days(1)));
+        assert_eq!(message.last_error.as_deref(), Some("timeout"));
+    }
+
+    #[test]
+    fn replay_resets_attempts() {
+        let mut message = message();
+        let now = message.created_at;
+        for _ in 0..OUTBOX_MAX_ATTEMPTS {
+            message.record_failure("boom", now);
+        }
+
+        message.replay(now);
+        assert_eq!(message.status, OutboxStatus::Pending);
+        assert_eq!(message.attempts, 0);
+        assert!(message.is_due_at(now));
+    }
+
+    #[test]
+    fn enum_string_roundtrip() {
+        for topic in [
+            OutboxTopic::LagoUsage,
+            OutboxTopic::Mixpanel,
+            OutboxTopic::ZosProStatus,
+            OutboxTopic::BalanceUpdate,
+        ] {
+            assert_eq!(topic.as_str().parse::<OutboxTopic>().unwrap(), topic);
+        }
+        for status in [OutboxStatus::Pending, OutboxStatus::DeadLettered] {
+            assert_eq!(status.as_str().parse::<OutboxStatus>().unwrap(), status);
+        }
+    }
+}
@@ -1,8 +1,18 @@
 //! Pricing configuration for z-billing.
 //!
 //! This module defines pricing for compute resources and LLM models.
+//!
+//! A model can have several price versions, each in effect for a range of
+//! time, so usage is billed at the price that applied when it happened
+//! rather than when it was reported. Prompt-cache reads and writes and
+//! batch API requests can have their own rates.
+//!
+//! Image generation, audio transcription, text-to-speech, web search and
+//! paid tool calls are priced per unit from [`MediaPricing`].
 
 use crate::account::Plan;
+use crate::usage::ImageResolution;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -10,19 +20,8 @@ const OPENAI_LONG_CONTEXT_THRESHOLD: u64 = 272_000;
 const XAI_LONG_CONTEXT_THRESHOLD: u64 = 200_000;
 const GOOGLE_LONG_CONTEXT_THRESHOLD: u64 = 200_000;
 
-fn is_sonnet_5_model(model: &str) -> bool {
-    matches!(
-        model.strip_prefix("anthropic/").unwrap_or(model),
-        "claude-sonnet-5" | "aura-claude-sonnet-5"
-    )
-}
-
-fn sonnet_5_pricing_on(_date: chrono::NaiveDate) -> LlmPricing {
-    LlmPricing {
-        input_credits_per_million: 200,
-        output_credits_per_million: 1000,
-    }
-}
+/// Built-in storage price: $0.00004 per GB-hour, about $0.029 per GB-month.
+pub const DEFAULT_STORAGE_GB_HOUR_CREDITS: f64 = 0.004;
 
 /// Pricing configuration for all billable resources.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,34 +35,81 @@ pub struct PricingConfig {
     /// Cost per GB-hour of memory in Z Credits.
     pub memory_gb_hour_credits: i64,
 
-    /// LLM pricing by provider and model.
-    pub llm_pricing: HashMap<ModelKey, LlmPricing>,
+    /// Cost per GB-hour of storage in Z Credits. Fractional, since a GB
+    /// stored for an hour costs far less than a credit.
+    pub storage_gb_hour_credits: f64,
+
+    /// LLM price versions by provider and model, oldest first.
+    pub llm_pricing: HashMap<ModelKey, Vec<LlmPricing>>,
 
     /// Default LLM pricing for unknown models.
     pub default_llm_pricing: LlmPricing,
+
+    /// Image, audio, speech, search and tool pricing.
+    pub media_pricing: MediaPricing,
 }
 
 impl Default for PricingConfig {
     fn default() -> Self {
         let mut llm_pricing = HashMap::new();
 
-        // Anthropic models — current (Claude 4.x) at vendor/base rates
+        // Anthropic models — current (Claude 4.x) at vendor/base rates.
+        // Cache reads are 0.1x input, 5-minute cache writes 1.25x input, and
+        // the Message Batches API half price.
         let sonnet_pricing = LlmPricing {
             input_credits_per_million: 300,   // $3.00 per 1M input tokens
             output_credits_per_million: 1500, // $15.00 per 1M output tokens
+            cache_read_credits_per_million: Some(30),
+            cache_creation_credits_per_million: Some(375),
+            batch_input_credits_per_million: Some(150),
+            batch_output_credits_per_million: Some(750),
+            ..LlmPricing::default()
+        };
+        // Sonnet 5 launched at $2/$10 with an increase announced for
+        // September 1, 2026. Anthropic cancelled it and made the launch price
+        // permanent, so the version from that date keeps the same rates.
+        let sonnet_5_cutover = "2026-09-01T00:00:00Z".parse().ok();
+        let sonnet_5_launch_pricing = LlmPricing {
+            input_credits_per_million: 200,   // $2.00 per 1M
+            output_credits_per_million: 1000, // $10.00 per 1M
+            cache_read_credits_per_million: Some(20),
+            cache_creation_credits_per_million: Some(250),
+            batch_input_credits_per_million: Some(100),
+            batch_output_credits_per_million: Some(500),
+            effective_until: sonnet_5_cutover,
+            ..LlmPricing::default()
+        };
+        let sonnet_5_pricing = LlmPricing {
+            effective_from: sonnet_5_cutover,
+            effective_until: None,
+            ..sonnet_5_launch_pricing.clone()
         };
-        let sonnet_5_pricing = sonnet_5_pricing_on(chrono::Utc::now().date_naive());
         let opus_pricing = LlmPricing {
             input_credits_per_million: 500,   // $5.00 per 1M
             output_credits_per_million: 2500, // $25.00 per 1M
+            cache_read_credits_per_million: Some(50),
+            cache_creation_credits_per_million: Some(625),
+            batch_input_credits_per_million: Some(250),
+            batch_output_credits_per_million: Some(1250),
+            ..LlmPricing::default()
         };
         let fable_pricing = LlmPricing {
             input_credits_per_million: 1000,  // $10.00 per 1M
             output_credits_per_million: 5000, // $50.00 per 1M
+            cache_read_credits_per_million: Some(100),
+            cache_creation_credits_per_million: Some(1250),
+            batch_input_credits_per_million: Some(500),
+            batch_output_credits_per_million: Some(2500),
+            ..LlmPricing::default()
         };
         let haiku_pricing = LlmPricing {
             input_credits_per_million: 100,  // $1.00 per 1M
             output_credits_per_million: 500, // $5.00 per 1M
+            cache_read_credits_per_million: Some(10),
+            cache_creation_credits_per_million: Some(125),
+            batch_input_credits_per_million: Some(50),
+            batch_output_credits_per_million: Some(250),
+            ..LlmPricing::default()
         };
 
         // Current model IDs
@@ -71,14 +117,6 @@ impl Default for PricingConfig {
             ModelKey::new("anthropic", "claude-sonnet-4-6"),
             sonnet_pricing.clone(),
         );
-        llm_pricing.insert(
-            ModelKey::new("anthropic", "claude-sonnet-5"),
-            sonnet_5_pricing.clone(),
-        );
-        llm_pricing.insert(
-            ModelKey::new("anthropic", "aura-claude-sonnet-5"),
-            sonnet_5_pricing,
-        );
         llm_pricing.insert(
             ModelKey::new("anthropic", "claude-fable-5"),
             fable_pricing.clone(),
@@ -150,6 +188,7 @@ impl Default for PricingConfig {
             LlmPricing {
                 input_credits_per_million: 25,
                 output_credits_per_million: 125,
+                ..LlmPricing::default()
             },
         );
         llm_pricing.insert(
@@ -157,6 +196,7 @@ impl Default for PricingConfig {
             LlmPricing {
                 input_credits_per_million: 1500,
                 output_credits_per_million: 7500,
+                ..LlmPricing::default()
             },
         );
 
@@ -166,6 +206,7 @@ impl Default for PricingConfig {
             LlmPricing {
                 input_credits_per_million: 250,
                 output_credits_per_million: 1000,
+                ..LlmPricing::default()
             },
         );
         llm_pricing.insert(
@@ -173,35 +214,50 @@ impl Default for PricingConfig {
             LlmPricing {
                 input_credits_per_million: 15,
                 output_credits_per_million: 60,
+                ..LlmPricing::default()
             },
         );
         let gpt_5_4_pricing = LlmPricing {
             input_credits_per_million: 250,
             output_credits_per_million: 1500,
+            ..LlmPricing::default()
         };
         let gpt_5_5_pricing = LlmPricing {
             input_credits_per_million: 500,
             output_credits_per_million: 3000,
+            ..LlmPricing::default()
         };
+        // GPT-5.6 cache reads are 0.1x input and cache writes 1.25x input.
         let gpt_5_6_sol_pricing = LlmPricing {
             input_credits_per_million: 500,
             output_credits_per_million: 3000,
+            cache_read_credits_per_million: Some(50),
+            cache_creation_credits_per_million: Some(625),
+            ..LlmPricing::default()
         };
         let gpt_5_6_terra_pricing = LlmPricing {
             input_credits_per_million: 200,
             output_credits_per_million: 1200,
+            cache_read_credits_per_million: Some(20),
+            cache_creation_credits_per_million: Some(250),
+            ..LlmPricing::default()
         };
         let gpt_5_6_luna_pricing = LlmPricing {
             input_credits_per_million: 20,
             output_credits_per_million: 120,
+            cache_read_credits_per_million: Some(2),
+            cache_creation_credits_per_million: Some(25),
+            ..LlmPricing::default()
         };
         let gpt_5_4_mini_pricing = LlmPricing {
             input_credits_per_million: 75,
             output_credits_per_million: 450,
+            ..LlmPricing::default()
         };
         let gpt_5_4_nano_pricing = LlmPricing {
             input_credits_per_million: 20,
             output_credits_per_million: 125,
+            ..LlmPricing::default()
         };
         for model in [
             "gpt-5.6",
@@ -254,12 +310,13 @@ impl Default for PricingConfig {
             gpt_5_4_nano_pricing,
         );
 
-        // xAI Grok chat models at vendor/base rates. Cache-aware usage can be
-        // reported with precomputed cost_cents by aura-router; these base rates
-        // cover quotes, reserves, and fallback usage calculation.
+        // xAI Grok chat models at vendor/base rates. Grok 4.6 cached input is
+        // $0.50 per 1M, doubling with the rest above the long-context threshold.
         let grok_4_6_pricing = LlmPricing {
             input_credits_per_million: 200,
             output_credits_per_million: 600,
+            cache_read_credits_per_million: Some(50),
+            ..LlmPricing::default()
         };
         for model in ["aura-grok-4-6", "grok-4.6", "xai/grok-4.6"] {
             llm_pricing.insert(ModelKey::new("xai", model), grok_4_6_pricing.clone());
@@ -267,6 +324,7 @@ impl Default for PricingConfig {
         let grok_4_5_pricing = LlmPricing {
             input_credits_per_million: 200,
             output_credits_per_million: 600,
+            ..LlmPricing::default()
         };
         for model in ["aura-grok-4-5", "grok-4.5", "xai/grok-4.5"] {
             llm_pricing.insert(ModelKey::new("xai", model), grok_4_5_pricing.clone());
@@ -274,6 +332,7 @@ impl Default for PricingConfig {
         let grok_4_3_pricing = LlmPricing {
             input_credits_per_million: 125,
             output_credits_per_million: 250,
+            ..LlmPricing::default()
         };
         for model in ["aura-grok-4-3", "grok-4.3", "xai/grok-4.3"] {
             llm_pricing.insert(ModelKey::new("xai", model), grok_4_3_pricing.clone());
@@ -281,6 +340,7 @@ impl Default for PricingConfig {
         let grok_build_pricing = LlmPricing {
             input_credits_per_million: 100,
             output_credits_per_million: 200,
+            ..LlmPricing::default()
         };
         for model in [
             "aura-grok-build-0-1",
@@ -308,6 +368,7 @@ impl Default for PricingConfig {
                 LlmPricing {
                     input_credits_per_million: 200,
                     output_credits_per_million: 1200,
+                    ..LlmPricing::default()
                 },
             ),
             (
@@ -316,6 +377,7 @@ impl Default for PricingConfig {
                 LlmPricing {
                     input_credits_per_million: 150,
                     output_credits_per_million: 900,
+                    ..LlmPricing::default()
                 },
             ),
             (
@@ -324,6 +386,7 @@ impl Default for PricingConfig {
                 LlmPricing {
                     input_credits_per_million: 50,
                     output_credits_per_million: 300,
+                    ..LlmPricing::default()
                 },
             ),
             (
@@ -332,6 +395,7 @@ impl Default for PricingConfig {
                 LlmPricing {
                     input_credits_per_million: 25,
                     output_credits_per_million: 150,
+                    ..LlmPricing::default()
                 },
             ),
             (
@@ -340,6 +404,7 @@ impl Default for PricingConfig {
                 LlmPricing {
                     input_credits_per_million: 125,
                     output_credits_per_million: 1000,
+                    ..LlmPricing::default()
                 },
             ),
             (
@@ -348,6 +413,7 @@ impl Default for PricingConfig {
                 LlmPricing {
                     input_credits_per_million: 30,
                     output_credits_per_million: 250,
+                    ..LlmPricing::default()
                 },
             ),
             (
@@ -356,6 +422,7 @@ impl Default for PricingConfig {
                 LlmPricing {
                     input_credits_per_million: 10,
                     output_credits_per_million: 40,
+                    ..LlmPricing::default()
                 },
             ),
         ];
@@ -364,17 +431,19 @@ impl Default for PricingConfig {
             llm_pricing.insert(ModelKey::new("google", upstream), pricing);
         }
 
-        // DeepSeek direct API models at cache-miss/base input rates. Callers can
-        // send cost_cents when DeepSeek returns cache hit/miss token details.
+        // DeepSeek direct API models at cache-miss/base input rates, so cache
+        // hits are billed as misses unless the caller sends cost_cents.
         // The direct Pro input rate is $0.435/M; the integer Z-credit fallback
         // rounds that to 44 cents. aura-router reports exact cache-aware cost.
         let deepseek_v4_pro_direct_pricing = LlmPricing {
             input_credits_per_million: 44,
             output_credits_per_million: 87,
+            ..LlmPricing::default()
         };
         let deepseek_v4_flash_direct_pricing = LlmPricing {
             input_credits_per_million: 14,
             output_credits_per_million: 28,
+            ..LlmPricing::default()
         };
         llm_pricing.insert(
             ModelKey::new("deepseek", "aura-deepseek-v4-pro"),
@@ -414,10 +483,12 @@ impl Default for PricingConfig {
         let deepseek_v4_pro_fireworks_pricing = LlmPricing {
             input_credits_per_million: 174,
             output_credits_per_million: 348,
+            ..LlmPricing::default()
         };
         let deepseek_v4_flash_fireworks_pricing = LlmPricing {
             input_credits_per_million: 14,
             output_credits_per_million: 28,
+            ..LlmPricing::default()
         };
         llm_pricing.insert(
             ModelKey::new("fireworks", "aura-deepseek-v4-pro"),
@@ -446,12 +517,12 @@ impl Default for PricingConfig {
             deepseek_v4_flash_fireworks_pricing,
         );
 
-        // Moonshot Kimi K3 direct API pricing. Cache-aware requests carry an
-        // explicit cost override from aura-router; these base rates cover
-        // balance checks, reserves, and non-cached fallback calculation.
+        // Moonshot Kimi K3 direct API pricing. Cache hits are $0.30 per 1M.
         let kimi_k3_pricing = LlmPricing {
             input_credits_per_million: 300,
             output_credits_per_million: 1500,
+            cache_read_credits_per_million: Some(30),
+            ..LlmPricing::default()
         };
         for model in ["aura-kimi-k3", "kimi-k3", "moonshot/kimi-k3"] {
             llm_pricing.insert(ModelKey::new("moonshot", model), kimi_k3_pricing.clone());
@@ -461,30 +532,37 @@ impl Default for PricingConfig {
         let kimi_k2_5_pricing = LlmPricing {
             input_credits_per_million: 60,
             output_credits_per_million: 300,
+            ..LlmPricing::default()
         };
         let kimi_k2_5_turbo_pricing = LlmPricing {
             input_credits_per_million: 99,
             output_credits_per_million: 494,
+            ..LlmPricing::default()
         };
         let kimi_k2_6_pricing = LlmPricing {
             input_credits_per_million: 95,
             output_credits_per_million: 400,
+            ..LlmPricing::default()
         };
         let kimi_k2_7_code_pricing = LlmPricing {
             input_credits_per_million: 95,
             output_credits_per_million: 400,
+            ..LlmPricing::default()
         };
         let kimi_k2_6_turbo_pricing = LlmPricing {
             input_credits_per_million: 200,
             output_credits_per_million: 800,
+            ..LlmPricing::default()
         };
         let kimi_k2_base_pricing = LlmPricing {
             input_credits_per_million: 60,
             output_credits_per_million: 250,
+            ..LlmPricing::default()
         };
         let gpt_oss_120b_pricing = LlmPricing {
             input_credits_per_million: 15,
             output_credits_per_million: 60,
+            ..LlmPricing::default()
         };
         llm_pricing.insert(
             ModelKey::new("fireworks", "aura-kimi-k2-5"),
@@ -552,34 +630,42 @@ impl Default for PricingConfig {
         let minimax_m3_pricing = LlmPricing {
             input_credits_per_million: 30,
             output_credits_per_million: 120,
+            ..LlmPricing::default()
         };
         let minimax_m2_7_pricing = LlmPricing {
             input_credits_per_million: 30,
             output_credits_per_million: 120,
+            ..LlmPricing::default()
         };
         let glm_5_1_pricing = LlmPricing {
             input_credits_per_million: 140,
             output_credits_per_million: 440,
+            ..LlmPricing::default()
         };
         let glm_5_2_pricing = LlmPricing {
             input_credits_per_million: 140,
             output_credits_per_million: 440,
+            ..LlmPricing::default()
         };
         let qwen3_6_plus_pricing = LlmPricing {
             input_credits_per_million: 50,
             output_credits_per_million: 300,
+            ..LlmPricing::default()
         };
         let qwen3_7_plus_pricing = LlmPricing {
             input_credits_per_million: 40,
             output_credits_per_million: 160,
+            ..LlmPricing::default()
         };
         let gemma_4_31b_pricing = LlmPricing {
             input_credits_per_million: 90,
             output_credits_per_million: 90,
+            ..LlmPricing::default()
         };
         let gemma_4_26b_a4b_pricing = LlmPricing {
             input_credits_per_million: 50,
             output_credits_per_million: 50,
+            ..LlmPricing::default()
         };
         llm_pricing.insert(
             ModelKey::new("fireworks", "aura-minimax-m3"),
@@ -646,15 +732,35 @@ impl Default for PricingConfig {
             gemma_4_26b_a4b_pricing,
         );
 
+        // Built-in prices are undated (one version per model, always in
+        // effect) unless a model's price changed on a known date
+        let mut llm_pricing: HashMap<ModelKey, Vec<LlmPricing>> = llm_pricing
+            .into_iter()
+            .map(|(key, pricing)| (key, vec![pricing]))
+            .collect();
+        for model in [
+            "claude-sonnet-5",
+            "anthropic/claude-sonnet-5",
+            "aura-claude-sonnet-5",
+        ] {
+            llm_pricing.insert(
+                ModelKey::new("anthropic", model),
+                vec![sonnet_5_launch_pricing.clone(), sonnet_5_pricing.clone()],
+            );
+        }
+
         Self {
             z_credit_rate_usd: 0.01,
             cpu_hour_credits: 6,       // $0.06 per CPU hour
             memory_gb_hour_credits: 2, // $0.02 per GB-hour
+            storage_gb_hour_credits: DEFAULT_STORAGE_GB_HOUR_CREDITS,
             llm_pricing,
             default_llm_pricing: LlmPricing {
                 input_credits_per_million: 100,  // Default $1.00 per 1M
                 output_credits_per_million: 300, // Default $3.00 per 1M
+                ..LlmPricing::default()
             },
+            media_pricing: MediaPricing::default(),
         }
     }
 }
@@ -664,50 +770,38 @@ impl PricingConfig {
         20
     }
 
-    fn marked_up_llm_pricing(&self, pricing: &LlmPricing, is_zero_pro_user: bool) -> LlmPricing {
-        let markup_percent = Self::llm_markup_percent(is_zero_pro_user);
-        LlmPricing {
-            input_credits_per_million: (pricing.input_credits_per_million * (100 + markup_percent)
-                + 50)
-                / 100,
-            output_credits_per_million: (pricing.output_credits_per_million
-                * (100 + markup_percent)
-                + 50)
-                / 100,
-        }
-    }
-
-    fn llm_pricing_for_usage(&self, provider: &str, model: &str, input_tokens: u64) -> LlmPricing {
-        let key = ModelKey::new(provider, model);
-        let mut pricing = self
-            .llm_pricing
-            .get(&key)
-            .unwrap_or(&self.default_llm_pricing)
-            .clone();
-
-        // Anthropic made Sonnet 5's original $2/$10 launch pricing permanent.
-        if provider.eq_ignore_ascii_case("anthropic") && is_sonnet_5_model(model) {
-            pricing = sonnet_5_pricing_on(chrono::Utc::now().date_naive());
-        }
+    fn token_rates_for_usage(
+        &self,
+        provider: &str,
+        model: &str,
+        usage: &LlmTokenUsage,
+        at: DateTime<Utc>,
+    ) -> TokenRates {
+        let pricing = self
+            .llm_pricing_at(provider, model, at)
+            .unwrap_or(&self.default_llm_pricing);
+        let mut rates = TokenRates::new(pricing, usage.batch);
 
+        // Long-context thresholds count the whole prompt, cached or not
+        let prompt_tokens = usage.prompt_tokens();
         let normalized_model = model
             .strip_prefix("openai/")
             .or_else(|| model.strip_prefix("xai/"))
             .or_else(|| model.strip_prefix("google/"))
             .unwrap_or(model);
         let openai_long = provider.eq_ignore_ascii_case("openai")
-            && input_tokens > OPENAI_LONG_CONTEXT_THRESHOLD
+            && prompt_tokens > OPENAI_LONG_CONTEXT_THRESHOLD
             && (matches!(
                 normalized_model,
                 "gpt-5.4" | "gpt-5.5" | "aura-gpt-5-4" | "aura-gpt-5-5"
             ) || normalized_model.starts_with("gpt-5.6")
                 || normalized_model.starts_with("aura-gpt-5-6-"));
         let xai_long = provider.eq_ignore_ascii_case("xai")
-            && input_tokens >= XAI_LONG_CONTEXT_THRESHOLD
+            && prompt_tokens >= XAI_LONG_CONTEXT_THRESHOLD
             && (normalized_model.starts_with("grok-")
                 || normalized_model.starts_with("aura-grok-"));
         let google_long = provider.eq_ignore_ascii_case("google")
-            && input_tokens > GOOGLE_LONG_CONTEXT_THRESHOLD
+            && prompt_tokens > GOOGLE_LONG_CONTEXT_THRESHOLD
             && matches!(
                 normalized_model,
                 "aura-gemini-3-1-pro"
@@ -716,17 +810,37 @@ impl PricingConfig {
                     | "gemini-2.5-pro"
             );
         if openai_long || google_long || xai_long {
-            pricing.input_credits_per_million = pricing.input_credits_per_million.saturating_mul(2);
-            pricing.output_credits_per_million = if xai_long {
-                pricing.output_credits_per_million.saturating_mul(2)
+            rates.input = rates.input.saturating_mul(2);
+            rates.cache_read = rates.cache_read.saturating_mul(2);
+            rates.cache_creation = rates.cache_creation.saturating_mul(2);
+            rates.output = if xai_long {
+                rates.output.saturating_mul(2)
             } else {
-                pricing.output_credits_per_million.saturating_mul(3) / 2
+                rates.output.saturating_mul(3) / 2
             };
         }
-        pricing
+        rates
     }
 
-    /// Calculate the cost in cents for LLM token usage.
+    /// The model's price version in effect at `at`.
+    ///
+    /// `None` if the model has no price at that time, in which case usage is
+    /// billed at `default_llm_pricing`.
+    #[must_use]
+    pub fn llm_pricing_at(
+        &self,
+        provider: &str,
+        model: &str,
+        at: DateTime<Utc>,
+    ) -> Option<&LlmPricing> {
+        self.llm_pricing
+            .get(&ModelKey::new(provider, model))?
+            .iter()
+            .rev()
+            .find(|pricing| pricing.is_effective_at(at))
+    }
+
+    /// Calculate the cost in cents for LLM token usage at current prices.
     ///
     /// Minimum cost is 1 credit for any non-zero usage.
     #[must_use]
@@ -737,23 +851,44 @@ impl PricingConfig {
         input_tokens: u64,
         output_tokens: u64,
     ) -> i64 {
-        let pricing = self.llm_pricing_for_usage(provider, model, input_tokens);
-
-        let input_cost = (i64::try_from(input_tokens).unwrap_or(i64::MAX)
-            * pricing.input_credits_per_million)
-            / 1_000_000;
-        let output_cost = (i64::try_from(output_tokens).unwrap_or(i64::MAX)
-            * pricing.output_credits_per_million)
-            / 1_000_000;
+        self.calculate_llm_cost_at(provider, model, input_tokens, output_tokens, Utc::now())
+    }
 
-        let total = input_cost + output_cost;
+    /// Calculate the cost in cents for LLM token usage at the prices in
+    /// effect at `at`, normally the usage event's timestamp.
+    ///
+    /// Minimum cost is 1 credit for any non-zero usage.
+    #[must_use]
+    pub fn calculate_llm_cost_at(
+        &self,
+        provider: &str,
+        model: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+        at: DateTime<Utc>,
+    ) -> i64 {
+        self.calculate_llm_usage_cost_at(
+            provider,
+            model,
+            &LlmTokenUsage::new(input_tokens, output_tokens),
+            at,
+        )
+    }
 
-        // Minimum 1 credit for any non-zero usage
-        if total == 0 && (input_tokens > 0 || output_tokens > 0) {
-            1
-        } else {
-            total
-        }
+    /// Calculate the cost in cents for LLM token usage, including cached
+    /// and batch tokens, at the prices in effect at `at`.
+    ///
+    /// Minimum cost is 1 credit for any non-zero usage.
+    #[must_use]
+    pub fn calculate_llm_usage_cost_at(
+        &self,
+        provider: &str,
+        model: &str,
+        usage: &LlmTokenUsage,
+        at: DateTime<Utc>,
+    ) -> i64 {
+        self.token_rates_for_usage(provider, model, usage, at)
+            .cost(usage)
     }
 
     /// Calculate the cost in cents for LLM token usage after applying the ZERO Pro markup.
@@ -766,22 +901,52 @@ impl PricingConfig {
         output_tokens: u64,
         is_zero_pro_user: bool,
     ) -> i64 {
-        let pricing = self.llm_pricing_for_usage(provider, model, input_tokens);
-        let marked_up_pricing = self.marked_up_llm_pricing(&pricing, is_zero_pro_user);
-
-        let input_cost = (i64::try_from(input_tokens).unwrap_or(i64::MAX)
-            * marked_up_pricing.input_credits_per_million)
-            / 1_000_000;
-        let output_cost = (i64::try_from(output_tokens).unwrap_or(i64::MAX)
-            * marked_up_pricing.output_credits_per_million)
-            / 1_000_000;
-        let total = input_cost + output_cost;
-
-        if total == 0 && (input_tokens > 0 || output_tokens > 0) {
-            1
-        } else {
-            total
-        }
+        self.calculate_llm_cost_for_zero_pro_user_at(
+            provider,
+            model,
+            input_tokens,
+            output_tokens,
+            is_zero_pro_user,
+            Utc::now(),
+        )
+    }
+
+    /// Calculate the cost in cents for LLM token usage after applying the
+    /// ZERO Pro markup, at the prices in effect at `at`.
+    #[must_use]
+    pub fn calculate_llm_cost_for_zero_pro_user_at(
+        &self,
+        provider: &str,
+        model: &str,
+        input_tokens: u64,
+        output_tokens: u64,
+        is_zero_pro_user: bool,
+        at: DateTime<Utc>,
+    ) -> i64 {
+        self.calculate_llm_usage_cost_for_zero_pro_user_at(
+            provider,
+            model,
+            &LlmTokenUsage::new(input_tokens, output_tokens),
+            is_zero_pro_user,
+            at,
+        )
+    }
+
+    /// Calculate the cost in cents for LLM token usage, including cached
+    /// and batch tokens, after applying the ZERO Pro markup, at the prices
+    /// in effect at `at`.
+    #[must_use]
+    pub fn calculate_llm_usage_cost_for_zero_pro_user_at(
+        &self,
+        provider: &str,
+        model: &str,
+        usage: &LlmTokenUsage,
+        is_zero_pro_user: bool,
+        at: DateTime<Utc>,
+    ) -> i64 {
+        self.token_rates_for_usage(provider, model, usage, at)
+            .marked_up(Self::llm_markup_percent(is_zero_pro_user))
+            .cost(usage)
     }
 
     /// Legacy billing-plan wrapper. Billing plans no longer affect LLM markup;
@@ -821,15 +986,27 @@ impl PricingConfig {
         model: &str,
         is_zero_pro_user: bool,
     ) -> i64 {
-        let key = ModelKey::new(provider, model);
+        self.minimum_llm_reserve_cents_for_usage(provider, model, false, is_zero_pro_user)
+    }
+
+    /// Calculate the minimum balance reserve in cents for starting a short text turn
+    /// through the standard or batch API, taking the user's ZERO Pro markup into
+    /// account. The turn is priced as uncached input.
+    #[must_use]
+    pub fn minimum_llm_reserve_cents_for_usage(
+        &self,
+        provider: &str,
+        model: &str,
+        batch: bool,
+        is_zero_pro_user: bool,
+    ) -> i64 {
         let pricing = self
-            .llm_pricing
-            .get(&key)
+            .llm_pricing_at(provider, model, Utc::now())
             .unwrap_or(&self.default_llm_pricing);
-        let marked_up_pricing = self.marked_up_llm_pricing(pricing, is_zero_pro_user);
+        let rates =
+            TokenRates::new(pricing, batch).marked_up(Self::llm_markup_percent(is_zero_pro_user));
 
-        let reserve_numerator = 2_000_i64 * marked_up_pricing.input_credits_per_million
-            + 1_000_i64 * marked_up_pricing.output_credits_per_million;
+        let reserve_numerator = 2_000_i64 * rates.input + 1_000_i64 * rates.output;
         ((reserve_numerator + 999_999) / 1_000_000).max(1)
     }
 
@@ -862,6 +1039,81 @@ impl PricingConfig {
         }
     }
 
+    /// Calculate the cost in cents for storage usage.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn calculate_storage_cost(&self, gb_hours: f64) -> i64 {
+        let total = (gb_hours * self.storage_gb_hour_credits).round() as i64;
+
+        // Minimum 1 credit for any non-zero usage
+        if total == 0 && gb_hours > 0.0 {
+            1
+        } else {
+            total
+        }
+    }
+
+    /// Calculate the cost in cents for generating `count` images.
+    #[must_use]
+    pub fn calculate_image_cost(
+        &self,
+        provider: &str,
+        model: &str,
+        resolution: ImageResolution,
+        count: u64,
+    ) -> i64 {
+        let credits = self
+            .media_pricing
+            .image_credits(provider, model)
+            .for_resolution(resolution);
+        unit_cost(count, credits, 1)
+    }
+
+    /// Calculate the cost in cents for transcribing `seconds` of audio.
+    ///
+    /// Minimum cost is 1 credit for any non-zero usage.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    pub fn calculate_audio_cost(&self, provider: &str, model: &str, seconds: f64) -> i64 {
+        let credits_per_hour = self.media_pricing.audio_credits_per_hour(provider, model);
+        let total = (seconds * credits_per_hour as f64 / 3600.0).round() as i64;
+
+        if total == 0 && seconds > 0.0 {
+            1
+        } else {
+            total
+        }
+    }
+
+    /// Calculate the cost in cents for synthesizing `characters` of speech.
+    ///
+    /// Minimum cost is 1 credit for any non-zero usage.
+    #[must_use]
+    pub fn calculate_speech_cost(&self, provider: &str, model: &str, characters: u64) -> i64 {
+        let credits = self
+            .media_pricing
+            .speech_credits_per_million_characters(provider, model);
+        unit_cost(characters, credits, 1_000_000)
+    }
+
+    /// Calculate the cost in cents for `queries` web searches.
+    ///
+    /// Minimum cost is 1 credit for any non-zero usage.
+    #[must_use]
+    pub fn calculate_search_cost(&self, provider: &str, queries: u64) -> i64 {
+        let credits = self.media_pricing.search_credits_per_thousand(provider);
+        unit_cost(queries, credits, 1_000)
+    }
+
+    /// Calculate the cost in cents for `count` calls to a paid tool.
+    ///
+    /// Minimum cost is 1 credit for any non-zero usage.
+    #[must_use]
+    pub fn calculate_tool_cost(&self, tool: &str, count: u64) -> i64 {
+        let credits = self.media_pricing.tool_credits_per_thousand(tool);
+        unit_cost(count, credits, 1_000)
+    }
+
     /// Convert USD to Z Credits.
     #[must_use]
     #[allow(clippy::cast_possible_truncation)]
@@ -897,13 +1149,389 @@ impl ModelKey {
     }
 }
 
-/// Pricing for an LLM model.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Pricing for an LLM model, in effect from `effective_from` (inclusive)
+/// until `effective_until` (exclusive). An open end is unbounded.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LlmPricing {
     /// Credits per 1 million input tokens.
     pub input_credits_per_million: i64,
     /// Credits per 1 million output tokens.
     pub output_credits_per_million: i64,
+    /// Credits per 1 million input tokens read from the prompt cache
+    /// (`None` = the input rate).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_credits_per_million: Option<i64>,
+    /// Credits per 1 million input tokens written to the prompt cache
+    /// (`None` = the input rate).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_credits_per_million: Option<i64>,
+    /// Credits per 1 million uncached input tokens sent through the batch
+    /// API (`None` = the input rate).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_input_credits_per_million: Option<i64>,
+    /// Credits per 1 million output tokens from the batch API
+    /// (`None` = the output rate).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_output_credits_per_million: Option<i64>,
+    /// When this price took effect (`None` = always).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_from: Option<DateTime<Utc>>,
+    /// When this price stopped applying (`None` = still current).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_until: Option<DateTime<Utc>>,
+}
+
+impl LlmPricing {
+    /// Whether this price applies to usage at `at`.
+    #[must_use]
+    pub fn is_effective_at(&self, at: DateTime<Utc>) -> bool {
+        self.effective_from <= Some(at)
+            && !matches!(self.effective_until, Some(until) if until <= at)
+    }
+}
+
+/// Token counts for one LLM request.
+///
+/// `input_tokens` are the uncached prompt tokens. Tokens read from or
+/// written to the prompt cache are counted separately, as Anthropic
+/// reports them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LlmTokenUsage {
+    /// Uncached input tokens.
+    pub input_tokens: u64,
+    /// Output tokens.
+    pub output_tokens: u64,
+    /// Input tokens read from the prompt cache.
+    pub cache_read_input_tokens: u64,
+    /// Input tokens written to the prompt cache.
+    pub cache_creation_input_tokens: u64,
+    /// Whether the request went through the provider's batch API.
+    pub batch: bool,
+}
+
+impl LlmTokenUsage {
+    /// Uncached, non-batch usage.
+    #[must_use]
+    pub fn new(input_tokens: u64, output_tokens: u64) -> Self {
+        Self {
+            input_tokens,
+            output_tokens,
+            ..Self::default()
+        }
+    }
+
+    /// The whole prompt: uncached plus cached input tokens.
+    #[must_use]
+    pub fn prompt_tokens(&self) -> u64 {
+        self.input_tokens
+            .saturating_add(self.cache_read_input_tokens)
+            .saturating_add(self.cache_creation_input_tokens)
+    }
+}
+
+/// Per-million rates for each kind of token in one request.
+struct TokenRates {
+    input: i64,
+    output: i64,
+    cache_read: i64,
+    cache_creation: i64,
+}
+
+impl TokenRates {
+    /// Resolve a price's optional rates, using the batch rates for batch requests.
+    fn new(pricing: &LlmPricing, batch: bool) -> Self {
+        let (input, output) = if batch {
+            (
+                pricing
+                    .batch_input_credits_per_million
+                    .unwrap_or(pricing.input_credits_per_million),
+                pricing
+                    .batch_output_credits_per_million
+                    .unwrap_or(pricing.output_credits_per_million),
+            )
+        } else {
+            (
+                pricing.input_credits_per_million,
+                pricing.output_credits_per_million,
+            )
+        };
+        Self {
+            input,
+            output,
+            cache_read: pricing
+                .cache_read_credits_per_million
+                .unwrap_or(pricing.input_credits_per_million),
+            cache_creation: pricing
+                .cache_creation_credits_per_million
+                .unwrap_or(pricing.input_credits_per_million),
+        }
+    }
+
+    fn marked_up(&self, markup_percent: i64) -> Self {
+        let mark_up = |rate: i64| (rate * (100 + markup_percent) + 50) / 100;
+        Self {
+            input: mark_up(self.input),
+            output: mark_up(self.output),
+            cache_read: mark_up(self.cache_read),
+            cache_creation: mark_up(self.cache_creation),
+        }
+    }
+
+    /// Cost in cents, with a minimum of 1 credit for any non-zero usage.
+    fn cost(&self, usage: &LlmTokenUsage) -> i64 {
+        let tokens = |count: u64| i64::try_from(count).unwrap_or(i64::MAX);
+        let total = (tokens(usage.input_tokens) * self.input) / 1_000_000
+            + (tokens(usage.output_tokens) * self.output) / 1_000_000
+            + (tokens(usage.cache_read_input_tokens) * self.cache_read) / 1_000_000
+            + (tokens(usage.cache_creation_input_tokens) * self.cache_creation) / 1_000_000;
+
+        if total == 0 && (usage.prompt_tokens() > 0 || usage.output_tokens > 0) {
+            1
+        } else {
+            total
+        }
+    }
+}
+
+/// Prices for multimodal and tool usage: image generation, audio
+/// transcription, text-to-speech, web search and paid tool calls.
+///
+/// Each table lists the priced models, providers or tools; anything not
+/// listed is billed at the table's default. The default value is the
+/// built-in pricing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MediaPricing {
+    /// Credits per image for models not in `images`.
+    pub default_image_credits: ImageCredits,
+    /// Credits per hour of audio for models not in `audio`.
+    pub default_audio_credits_per_hour: i64,
+    /// Credits per million characters for models not in `speech`.
+    pub default_speech_credits_per_million_characters: i64,
+    /// Credits per thousand queries for providers not in `search`.
+    pub default_search_credits_per_thousand: i64,
+    /// Credits per thousand calls for tools not in `tools`.
+    pub default_tool_credits_per_thousand: i64,
+    /// Image generation prices by provider and model.
+    #[serde(default)]
+    pub images: Vec<ImagePrice>,
+    /// Audio transcription credits per hour of audio, by provider and model.
+    #[serde(default)]
+    pub audio: Vec<ModelRate>,
+    /// Text-to-speech credits per million characters, by provider and model.
+    #[serde(default)]
+    pub speech: Vec<ModelRate>,
+    /// Web search credits per thousand queries, by provider.
+    #[serde(default)]
+    pub search: Vec<NamedRate>,
+    /// Tool credits per thousand calls, by tool name.
+    #[serde(default)]
+    pub tools: Vec<NamedRate>,
+}
+
+impl Default for MediaPricing {
+    fn default() -> Self {
+        let model_rate = |provider: &str, model: &str, credits| ModelRate {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            credits,
+        };
+        let named_rate = |name: &str, credits| NamedRate {
+            name: name.to_string(),
+            credits,
+        };
+        let image_price = |provider: &str, model: &str, standard, high, ultra| ImagePrice {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            credits: ImageCredits {
+                standard,
+                high,
+                ultra,
+            },
+        };
+
+        Self {
+            // $0.04 / $0.08 / $0.12 per image
+            default_image_credits: ImageCredits {
+                standard: 4,
+                high: 8,
+                ultra: 12,
+            },
+            default_audio_credits_per_hour: 36, // $0.006 per minute
+            default_speech_credits_per_million_characters: 1500, // $15.00 per 1M
+            default_search_credits_per_thousand: 1000, // $10.00 per 1K queries
+            default_tool_credits_per_thousand: 100, // $1.00 per 1K calls
+            images: vec![
+                image_price("openai", "dall-e-3", 4, 8, 12),
+                image_price("openai", "gpt-image-1", 4, 6, 17),
+                image_price("google", "imagen-4", 4, 4, 6),
+            ],
+            audio: vec![
+                model_rate("openai", "whisper-1", 36),
+                model_rate("openai", "gpt-4o-transcribe", 36),
+                model_rate("openai", "gpt-4o-mini-transcribe", 18), // $0.003 per minute
+            ],
+            speech: vec![
+                model_rate("openai", "tts-1", 1500),
+                model_rate("openai", "tts-1-hd", 3000), // $30.00 per 1M
+            ],
+            search: vec![
+                named_rate("anthropic", 1000),
+                named_rate("openai", 1000),
+                named_rate("xai", 2500), // $25.00 per 1K
+            ],
+            tools: Vec::new(),
+        }
+    }
+}
+
+impl MediaPricing {
+    /// Credits per image for a model.
+    #[must_use]
+    pub fn image_credits(&self, provider: &str, model: &str) -> &ImageCredits {
+        self.images
+            .iter()
+            .find(|price| price.provider == provider && price.model == model)
+            .map_or(&self.default_image_credits, |price| &price.credits)
+    }
+
+    /// Credits per hour of transcribed audio for a model.
+    #[must_use]
+    pub fn audio_credits_per_hour(&self, provider: &str, model: &str) -> i64 {
+        find_model_rate(&self.audio, provider, model).unwrap_or(self.default_audio_credits_per_hour)
+    }
+
+    /// Credits per million characters of speech for a model.
+    #[must_use]
+    pub fn speech_credits_per_million_characters(&self, provider: &str, model: &str) -> i64 {
+        find_model_rate(&self.speech, provider, model)
+            .unwrap_or(self.default_speech_credits_per_million_characters)
+    }
+
+    /// Credits per thousand web search queries for a provider.
+    #[must_use]
+    pub fn search_credits_per_thousand(&self, provider: &str) -> i64 {
+        find_named_rate(&self.search, provider).unwrap_or(self.default_search_credits_per_thousand)
+    }
+
+    /// Credits per thousand calls for a tool.
+    #[must_use]
+    pub fn tool_credits_per_thousand(&self, tool: &str) -> i64 {
+        find_named_rate(&self.tools, tool).unwrap_or(self.default_tool_credits_per_thousand)
+    }
+
+    /// Whether any rate is negative.
+    #[must_use]
+    pub fn has_negative_rate(&self) -> bool {
+        let image_rates = std::iter::once(&self.default_image_credits)
+            .chain(self.images.iter().map(|price| &price.credits))
+            .flat_map(|credits| [credits.standard, credits.high, credits.ultra]);
+        let unit_rates = [
+            self.default_audio_credits_per_hour,
+            self.default_speech_credits_per_million_characters,
+            self.default_search_credits_per_thousand,
+            self.default_tool_credits_per_thousand,
+        ]
+        .into_iter()
+        .chain(
+            self.audio
+                .iter()
+                .chain(&self.speech)
+                .map(|rate| rate.credits),
+        )
+        .chain(
+            self.search
+                .iter()
+                .chain(&self.tools)
+                .map(|rate| rate.credits),
+        );
+
+        image_rates.chain(unit_rates).any(|rate| rate < 0)
+    }
+}
+
+fn find_model_rate(rates: &[ModelRate], provider: &str, model: &str) -> Option<i64> {
+    rates
+        .iter()
+        .find(|rate| rate.provider == provider && rate.model == model)
+        .map(|rate| rate.credits)
+}
+
+fn find_named_rate(rates: &[NamedRate], name: &str) -> Option<i64> {
+    rates
+        .iter()
+        .find(|rate| rate.name == name)
+        .map(|rate| rate.credits)
+}
+
+/// Credits per image at each resolution tier.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImageCredits {
+    /// Up to 1024×1024 pixels.
+    pub standard: i64,
+    /// Up to 2048×2048 pixels.
+    pub high: i64,
+    /// Larger than 2048×2048 pixels.
+    pub ultra: i64,
+}
+
+impl ImageCredits {
+    /// Credits per image at `resolution`.
+    #[must_use]
+    pub fn for_resolution(&self, resolution: ImageResolution) -> i64 {
+        match resolution {
+            ImageResolution::Standard => self.standard,
+            ImageResolution::High => self.high,
+            ImageResolution::Ultra => self.ultra,
+        }
+    }
+}
+
+/// Image generation prices for one model.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImagePrice {
+    /// Provider name (e.g., "openai", "google").
+    pub provider: String,
+    /// Model name as reported in usage events.
+    pub model: String,
+    /// Credits per image at each resolution tier.
+    pub credits: ImageCredits,
+}
+
+/// A rate for one provider's model, in the unit of its table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelRate {
+    /// Provider name.
+    pub provider: String,
+    /// Model name as reported in usage events.
+    pub model: String,
+    /// Credits per unit of the table.
+    pub credits: i64,
+}
+
+/// A rate for a provider or tool, in the unit of its table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamedRate {
+    /// Provider name for search, tool name for tool calls.
+    pub name: String,
+    /// Credits per unit of the table.
+    pub credits: i64,
+}
+
+/// `units * credits / per`, rounded down, with a minimum of 1 credit for
+/// any non-zero usage.
+fn unit_cost(units: u64, credits: i64, per: i64) -> i64 {
+    let units_i64 = i64::try_from(units).unwrap_or(i64::MAX);
+    let total = units_i64.saturating_mul(credits) / per;
+    if total == 0 && units > 0 {
+        1
+    } else {
+        total
+    }
 }
 
 /// The company that *makes* a model (its research lab / vendor).
@@ -919,7 +1547,7 @@ pub struct LlmPricing {
 /// `MODEL_VENDOR_LABELS` (`interface/src/constants/models.ts`) and the
 /// aura-router `Maker` enum. These three repos share no code, so the set
 /// is duplicated and kept aligned by hand.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Maker {
     /// Anthropic (Claude).
     Anthropic,
@@ -1235,11 +1863,14 @@ mod tests {
     #[test]
     fn calculate_llm_cost_claude_sonnet_5_uses_sonnet_rates() {
         let config = PricingConfig::default();
-        let current = sonnet_5_pricing_on(chrono::Utc::now().date_naive());
-        let base_cost = current.input_credits_per_million + current.output_credits_per_million;
+        let base_cost = 200 + 1000;
         let marked_up_cost = (base_cost * 120 + 50) / 100;
 
-        for model in ["claude-sonnet-5", "aura-claude-sonnet-5"] {
+        for model in [
+            "claude-sonnet-5",
+            "anthropic/claude-sonnet-5",
+            "aura-claude-sonnet-5",
+        ] {
             assert_eq!(
                 config.calculate_llm_cost("anthropic", model, 1_000_000, 1_000_000),
                 base_cost,
@@ -1261,13 +1892,41 @@ mod tests {
 
     #[test]
     fn sonnet_5_launch_pricing_remains_permanent() {
-        let august = sonnet_5_pricing_on(chrono::NaiveDate::from_ymd_opt(2026, 8, 31).unwrap());
-        let september = sonnet_5_pricing_on(chrono::NaiveDate::from_ymd_opt(2026, 9, 1).unwrap());
-
+        let config = PricingConfig::default();
+        let cutover: DateTime<Utc> = "2026-09-01T00:00:00Z".parse().unwrap();
+        let august = config
+            .llm_pricing_at(
+                "anthropic",
+                "claude-sonnet-5",
+                cutover - chrono::Duration::seconds(1),
+            )
+            .unwrap();
+        let september = config
+            .llm_pricing_at("anthropic", "claude-sonnet-5", cutover)
+            .unwrap();
+
+        assert_eq!(august.effective_until, Some(cutover));
+        assert_eq!(september.effective_from, Some(cutover));
         assert_eq!(august.input_credits_per_million, 200);
         assert_eq!(august.output_credits_per_million, 1000);
         assert_eq!(september.input_credits_per_million, 200);
         assert_eq!(september.output_credits_per_million, 1000);
+        assert_eq!(
+            config.calculate_llm_cost_at(
+                "anthropic",
+                "claude-sonnet-5",
+                1_000_000,
+                1_000_000,
+                cutover - chrono::Duration::days(30),
+            ),
+            config.calculate_llm_cost_at(
+                "anthropic",
+                "claude-sonnet-5",
+                1_000_000,
+                1_000_000,
+                cutover + chrono::Duration::days(30),
+            )
+        );
     }
 
     #[test]
@@ -1757,6 +2416,95 @@ mod tests {
         );
     }
 
+    #[test]
+    fn cached_and_batch_tokens_use_their_own_rates() {
+        let config = PricingConfig::default();
+        let now = Utc::now();
+        let cost = |model: &str, usage: LlmTokenUsage| {
+            config.calculate_llm_usage_cost_at("anthropic", model, &usage, now)
+        };
+
+        // Sonnet 4.6: cache reads $0.30/M, cache writes $3.75/M
+        let cached = LlmTokenUsage {
+            cache_read_input_tokens: 1_000_000,
+            cache_creation_input_tokens: 1_000_000,
+            ..LlmTokenUsage::default()
+        };
+        assert_eq!(cost("claude-sonnet-4-6", cached), 405);
+
+        // Batch API at half price
+        let batch = LlmTokenUsage {
+            batch: true,
+            ..LlmTokenUsage::new(1_000_000, 1_000_000)
+        };
+        assert_eq!(cost("claude-sonnet-4-6", batch), 900);
+        assert_eq!(cost("claude-sonnet-5", batch), 600);
+
+        // The ZERO Pro markup applies to every rate
+        assert_eq!(
+            config.calculate_llm_usage_cost_for_zero_pro_user_at(
+                "anthropic",
+                "claude-sonnet-4-6",
+                &cached,
+                false,
+                now,
+            ),
+            486
+        );
+
+        // Without cache rates, cached tokens are billed as input
+        assert_eq!(
+            config.calculate_llm_usage_cost_at(
+                "openai",
+                "gpt-4o",
+                &LlmTokenUsage {
+                    cache_read_input_tokens: 1_000_000,
+                    ..LlmTokenUsage::default()
+                },
+                now,
+            ),
+            250
+        );
+    }
+
+    #[test]
+    fn cached_tokens_count_toward_long_context_thresholds() {
+        let config = PricingConfig::default();
+        // 250k prompt tokens, mostly cached: Grok 4.6 doubles every input rate
+        let usage = LlmTokenUsage {
+            input_tokens: 100_000,
+            cache_read_input_tokens: 150_000,
+            ..LlmTokenUsage::default()
+        };
+        assert_eq!(
+            config.calculate_llm_usage_cost_at("xai", "grok-4.6", &usage, Utc::now()),
+            40 + 15
+        );
+    }
+
+    #[test]
+    fn batch_reserve_uses_batch_rates() {
+        let config = PricingConfig::default();
+        assert_eq!(
+            config.minimum_llm_reserve_cents_for_usage(
+                "anthropic",
+                "claude-sonnet-4-6",
+                false,
+                false
+            ),
+            3
+        );
+        assert_eq!(
+            config.minimum_llm_reserve_cents_for_usage(
+                "anthropic",
+                "claude-sonnet-4-6",
+                true,
+                false
+            ),
+            2
+        );
+    }
+
     #[test]
     fn calculate_compute_cost() {
         let config = PricingConfig::default();
@@ -1767,6 +2515,52 @@ mod tests {
         assert_eq!(cost, 20);
     }
 
+    #[test]
+    fn calculate_storage_cost() {
+        let config = PricingConfig::default();
+
+        // 1 TB for 30 days at 0.004 credits/GB-hour = 2880 credits
+        assert_eq!(config.calculate_storage_cost(1_000.0 * 720.0), 2880);
+        // Minimum 1 credit for any non-zero usage
+        assert_eq!(config.calculate_storage_cost(1.0), 1);
+        assert_eq!(config.calculate_storage_cost(0.0), 0);
+    }
+
+    #[test]
+    fn media_and_tool_usage_is_priced_per_unit() {
+        let config = PricingConfig::default();
+
+        // 3 HD DALL-E 3 images at $0.08
+        assert_eq!(
+            config.calculate_image_cost("openai", "dall-e-3", ImageResolution::High, 3),
+            24
+        );
+        // Unknown image models use the default tier prices
+        assert_eq!(
+            config.calculate_image_cost("stability", "sd-4", ImageResolution::Ultra, 2),
+            24
+        );
+
+        // 10 minutes of Whisper at $0.006 per minute
+        assert_eq!(config.calculate_audio_cost("openai", "whisper-1", 600.0), 6);
+        assert_eq!(config.calculate_audio_cost("openai", "whisper-1", 1.0), 1);
+
+        // 100k characters of HD speech at $30 per 1M
+        assert_eq!(
+            config.calculate_speech_cost("openai", "tts-1-hd", 100_000),
+            300
+        );
+
+        // Searches at $10 and $25 per 1K queries
+        assert_eq!(config.calculate_search_cost("anthropic", 50), 50);
+        assert_eq!(config.calculate_search_cost("xai", 2), 5);
+
+        // Unlisted tools use the default, with the 1 credit minimum
+        assert_eq!(config.calculate_tool_cost("code_execution", 2_000), 200);
+        assert_eq!(config.calculate_tool_cost("code_execution", 1), 1);
+        assert_eq!(config.calculate_tool_cost("code_execution", 0), 0);
+    }
+
     #[test]
     fn usd_to_credits_conversion() {
         let config = PricingConfig::default();
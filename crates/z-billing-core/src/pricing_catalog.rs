@@ -0,0 +1,732 @@
+//! Versioned pricing catalogs for z-billing.
+//!
+//! A catalog is the file form of a [`PricingConfig`]: the same rates, with
+//! the model prices as a list and a version label. Catalogs are read from a
+//! TOML or JSON file or from the `pricing_catalog` table, so prices can
+//! change without a rebuild. Every catalog is validated before it is used,
+//! and two catalogs can be diffed to see what a reload changed.
+//!
+//! ```toml
+//! version = "2026-10-01"
+//! z_credit_rate_usd = 0.01
+//! cpu_hour_credits = 6
+//! memory_gb_hour_credits = 2
+//! storage_gb_hour_credits = 0.004
+//!
+//! [default_llm_pricing]
+//! input_credits_per_million = 100
+//! output_credits_per_million = 300
+//!
+//! [[models]]
+//! provider = "anthropic"
+//! model = "claude-sonnet-4-6"
+//! input_credits_per_million = 300
+//! output_credits_per_million = 1500
+//! effective_until = "2026-11-01T00:00:00Z"
+//!
+//! [[models]]
+//! provider = "anthropic"
+//! model = "claude-sonnet-4-6"
+//! input_credits_per_million = 250
+//! output_credits_per_million = 1250
+//! effective_from = "2026-11-01T00:00:00Z"
+//! ```
+//!
+//! A model may be listed once per price version, as long as the versions'
+//! effective ranges do not overlap. Image, audio, speech, search and tool
+//! prices go in an optional `[media_pricing]` table; without one the
+//! built-in [`MediaPricing`] applies.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::pricing::{
+    LlmPricing, MediaPricing, ModelKey, PricingConfig, DEFAULT_STORAGE_GB_HOUR_CREDITS,
+};
+
+/// Version label of the catalog compiled into the service.
+pub const BUILTIN_CATALOG_VERSION: &str = "builtin";
+
+/// A versioned pricing catalog.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PricingCatalog {
+    /// Version label, e.g. a date. A changed catalog must have a new one.
+    pub version: String,
+
+    /// Z Credit exchange rate in USD (0.01 = 1 Z Credit = $0.01).
+    pub z_credit_rate_usd: f64,
+
+    /// Cost per CPU hour in Z Credits.
+    pub cpu_hour_credits: i64,
+
+    /// Cost per GB-hour of memory in Z Credits.
+    pub memory_gb_hour_credits: i64,
+
+    /// Cost per GB-hour of storage in Z Credits (omitted = built in).
+    #[serde(default = "default_storage_gb_hour_credits")]
+    pub storage_gb_hour_credits: f64,
+
+    /// Pricing for models not in `models`.
+    pub default_llm_pricing: LlmPricing,
+
+    /// Pricing by provider and model.
+    #[serde(default)]
+    pub models: Vec<ModelPrice>,
+
+    /// Image, audio, speech, search and tool pricing (omitted = built in).
+    #[serde(default)]
+    pub media_pricing: MediaPricing,
+}
+
+fn default_storage_gb_hour_credits() -> f64 {
+    DEFAULT_STORAGE_GB_HOUR_CREDITS
+}
+
+/// One model's entry in a catalog.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelPrice {
+    /// Provider name (e.g., "anthropic", "fireworks").
+    pub provider: String,
+    /// Model name as reported in usage events.
+    pub model: String,
+    /// Credits per 1 million input tokens.
+    pub input_credits_per_million: i64,
+    /// Credits per 1 million output tokens.
+    pub output_credits_per_million: i64,
+    /// Credits per 1 million cache-read input tokens (omitted = input rate).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read_credits_per_million: Option<i64>,
+    /// Credits per 1 million cache-write input tokens (omitted = input rate).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation_credits_per_million: Option<i64>,
+    /// Credits per 1 million batch API input tokens (omitted = input rate).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_input_credits_per_million: Option<i64>,
+    /// Credits per 1 million batch API output tokens (omitted = output rate).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_output_credits_per_million: Option<i64>,
+    /// When this price took effect (omitted = always).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_from: Option<DateTime<Utc>>,
+    /// When this price stopped applying (omitted = still current).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_until: Option<DateTime<Utc>>,
+}
+
+/// A model price version whose price differs between two catalogs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModelPriceChange {
+    /// Provider name.
+    pub provider: String,
+    /// Model name.
+    pub model: String,
+    /// Pricing in the previous catalog.
+    pub before: LlmPricing,
+    /// Pricing in the new catalog.
+    pub after: LlmPricing,
+}
+
+/// A catalog-wide rate that differs between two catalogs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RateChange {
+    /// Catalog field, e.g. `cpu_hour_credits` or
+    /// `default_llm_pricing.input_credits_per_million`.
+    pub field: &'static str,
+    /// Value in the previous catalog.
+    pub before: serde_json::Value,
+    /// Value in the new catalog.
+    pub after: serde_json::Value,
+}
+
+/// What changed between two catalogs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PricingDiff {
+    /// Version of the previous catalog.
+    pub from_version: String,
+    /// Version of the new catalog.
+    pub to_version: String,
+    /// Catalog-wide rates that changed.
+    pub rates: Vec<RateChange>,
+    /// Models only in the new catalog.
+    pub added: Vec<ModelPrice>,
+    /// Models only in the previous catalog.
+    pub removed: Vec<ModelPrice>,
+    /// Models in both whose price changed.
+    pub changed: Vec<ModelPriceChange>,
+}
+
+/// Why a catalog was rejected.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CatalogError {
+    /// The document is not a well-formed catalog.
+    #[error("invalid pricing catalog: {0}")]
+    Parse(String),
+
+    /// The catalog parsed but breaks a rule.
+    #[error("pricing catalog {version} rejected: {reason}")]
+    Invalid {
+        /// Version of the rejected catalog.
+        version: String,
+        /// The rule it broke.
+        reason: String,
+    },
+}
+
+impl PricingCatalog {
+    /// The catalog compiled into the service.
+    #[must_use]
+    pub fn builtin() -> Self {
+        Self::from_config(BUILTIN_CATALOG_VERSION, &PricingConfig::default())
+    }
+
+    /// Build a catalog from a pricing configuration, with models sorted by
+    /// provider, name and `effective_from`.
+    #[must_use]
+    pub fn from_config(version: &str, config: &PricingConfig) -> Self {
+        let mut models: Vec<ModelPrice> = config
+            .llm_pricing
+            .iter()
+            .flat_map(|(key, versions)| {
+                versions.iter().map(|pricing| ModelPrice {
+                    provider: key.provider.clone(),
+                    model: key.model.clone(),
+                    input_credits_per_million: pricing.input_credits_per_million,
+                    output_credits_per_million: pricing.output_credits_per_million,
+                    cache_read_credits_per_million: pricing.cache_read_credits_per_million,
+                    cache_creation_credits_per_million: pricing.cache_creation_credits_per_million,
+                    batch_input_credits_per_million: pricing.batch_input_credits_per_million,
+                    batch_output_credits_per_million: pricing.batch_output_credits_per_million,
+                    effective_from: pricing.effective_from,
+                    effective_until: pricing.effective_until,
+                })
+            })
+            .collect();
+        models.sort_by(|a, b| a.key().cmp(&b.key()));
+
+        Self {
+            version: version.to_string(),
+            z_credit_rate_usd: config.z_credit_rate_usd,
+            cpu_hour_credits: config.cpu_hour_credits,
+            memory_gb_hour_credits: config.memory_gb_hour_credits,
+            storage_gb_hour_credits: config.storage_gb_hour_credits,
+            default_llm_pricing: config.default_llm_pricing.clone(),
+            models,
+            media_pricing: config.media_pricing.clone(),
+        }
+    }
+
+    /// Parse and validate a TOML catalog.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CatalogError::Parse` if the document does not match the
+    /// catalog schema, or `CatalogError::Invalid` if it fails validation.
+    pub fn from_toml(document: &str) -> Result<Self, CatalogError> {
+        let catalog: Self =
+            toml::from_str(document).map_err(|e| CatalogError::Parse(e.to_string()))?;
+        catalog.validate()?;
+        Ok(catalog)
+    }
+
+    /// Parse and validate a JSON catalog.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CatalogError::Parse` if the document does not match the
+    /// catalog schema, or `CatalogError::Invalid` if it fails validation.
+    pub fn from_json(document: &str) -> Result<Self, CatalogError> {
+        let catalog: Self =
+            serde_json::from_str(document).map_err(|e| CatalogError::Parse(e.to_string()))?;
+        catalog.validate()?;
+        Ok(catalog)
+    }
+
+    /// Check the rules a catalog must follow: a version, a positive credit
+    /// rate, no negative prices, and no model with two prices at once.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CatalogError::Invalid` naming the first rule broken.
+    pub fn validate(&self) -> Result<(), CatalogError> {
+        let invalid = |reason: String| CatalogError::Invalid {
+            version: self.version.clone(),
+            reason,
+        };
+
+        if self.version.trim().is_empty() {
+            return Err(invalid("version is empty".into()));
+        }
+        if !(self.z_credit_rate_usd.is_finite() && self.z_credit_rate_usd > 0.0) {
+            return Err(invalid(format!(
+                "z_credit_rate_usd must be positive, got {}",
+                self.z_credit_rate_usd
+            )));
+        }
+        if self.cpu_hour_credits < 0 || self.memory_gb_hour_credits < 0 {
+            return Err(invalid("compute rates must not be negative".into()));
+        }
+        if !(self.storage_gb_hour_credits.is_finite() && self.storage_gb_hour_credits >= 0.0) {
+            return Err(invalid(format!(
+                "storage_gb_hour_credits must not be negative, got {}",
+                self.storage_gb_hour_credits
+            )));
+        }
+        if self.default_llm_pricing.input_credits_per_million < 0
+            || self.default_llm_pricing.output_credits_per_million < 0
+        {
+            return Err(invalid("default_llm_pricing must not be negative".into()));
+        }
+        if self.media_pricing.has_negative_rate() {
+            return Err(invalid("media_pricing must not be negative".into()));
+        }
+
+        let mut versions: HashMap<(&str, &str), Vec<&ModelPrice>> = HashMap::new();
+        for model in &self.models {
+            if model.provider.is_empty() || model.model.is_empty() {
+                return Err(invalid("a model has an empty provider or name".into()));
+            }
+            let rates = [
+                Some(model.input_credits_per_million),
+                Some(model.output_credits_per_million),
+                model.cache_read_credits_per_million,
+                model.cache_creation_credits_per_million,
+                model.batch_input_credits_per_million,
+                model.batch_output_credits_per_million,
+            ];
+            if rates.into_iter().flatten().any(|rate| rate < 0) {
+                return Err(invalid(format!(
+                    "{}/{} has a negative price",
+                    model.provider, model.model
+                )));
+            }
+            if let (Some(from), Some(until)) = (model.effective_from, model.effective_until) {
+                if from >= until {
+                    return Err(invalid(format!(
+                        "{}/{} has effective_from {from} not before effective_until {until}",
+                        model.provider, model.model
+                    )));
+                }
+            }
+            versions
+                .entry((&model.provider, &model.model))
+                .or_default()
+                .push(model);
+        }
+
+        for ((provider, model), mut prices) in versions {
+            prices.sort_by_key(|price| price.effective_from);
+            for pair in prices.windows(2) {
+                // Sorted by start, so only neighbours can overlap
+                let ends_before_next = match (pair[0].effective_until, pair[1].effective_from) {
+                    (Some(until), Some(from)) => until <= from,
+                    _ => false,
+                };
+                if !ends_before_next {
+                    return Err(invalid(format!(
+                        "{provider}/{model} has overlapping prices"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The pricing configuration the catalog describes.
+    #[must_use]
+    pub fn to_config(&self) -> PricingConfig {
+        let mut llm_pricing: HashMap<ModelKey, Vec<LlmPricing>> = HashMap::new();
+        for model in &self.models {
+            llm_pricing
+                .entry(ModelKey::new(&model.provider, &model.model))
+                .or_default()
+                .push(model.pricing());
+        }
+        for versions in llm_pricing.values_mut() {
+            versions.sort_by_key(|pricing| pricing.effective_from);
+        }
+
+        PricingConfig {
+            z_credit_rate_usd: self.z_credit_rate_usd,
+            cpu_hour_credits: self.cpu_hour_credits,
+            memory_gb_hour_credits: self.memory_gb_hour_credits,
+            storage_gb_hour_credits: self.storage_gb_hour_credits,
+            llm_pricing,
+            default_llm_pricing: self.default_llm_pricing.clone(),
+            media_pricing: self.media_pricing.clone(),
+        }
+    }
+
+    /// What changed going from `previous` to this catalog.
+    #[must_use]
+    pub fn diff(&self, previous: &PricingCatalog) -> PricingDiff {
+        let mut rates = Vec::new();
+        let mut rate = |field, before: serde_json::Value, after: serde_json::Value| {
+            if before != after {
+                rates.push(RateChange {
+                    field,
+                    before,
+                    after,
+                });
+            }
+        };
+        rate(
+            "z_credit_rate_usd",
+            previous.z_credit_rate_usd.into(),
+            self.z_credit_rate_usd.into(),
+        );
+        rate(
+            "cpu_hour_credits",
+            previous.cpu_hour_credits.into(),
+            self.cpu_hour_credits.into(),
+        );
+        rate(
+            "memory_gb_hour_credits",
+            previous.memory_gb_hour_credits.into(),
+            self.memory_gb_hour_credits.into(),
+        );
+        rate(
+            "storage_gb_hour_credits",
+            previous.storage_gb_hour_credits.into(),
+            self.storage_gb_hour_credits.into(),
+        );
+        rate(
+            "default_llm_pricing.input_credits_per_million",
+            previous
+                .default_llm_pricing
+                .input_credits_per_million
+                .into(),
+            self.default_llm_pricing.input_credits_per_million.into(),
+        );
+        rate(
+            "default_llm_pricing.output_credits_per_million",
+            previous
+                .default_llm_pricing
+                .output_credits_per_million
+                .into(),
+            self.default_llm_pricing.output_credits_per_million.into(),
+        );
+        rate(
+            "media_pricing",
+            serde_json::to_value(&previous.media_pricing).unwrap_or_default(),
+            serde_json::to_value(&self.media_pricing).unwrap_or_default(),
+        );
+
+        let before = previous.models_by_key();
+        let after = self.models_by_key();
+        let added = after
+            .iter()
+            .filter(|(key, _)| !before.contains_key(*key))
+            .map(|(_, model)| (*model).clone())
+            .collect();
+        let removed = before
+            .iter()
+            .filter(|(key, _)| !after.contains_key(*key))
+            .map(|(_, model)| (*model).clone())
+            .collect();
+        let changed = after
+            .iter()
+            .filter_map(|(key, new)| {
+                let old = before.get(key)?;
+                (old != new).then(|| ModelPriceChange {
+                    provider: new.provider.clone(),
+                    model: new.model.clone(),
+                    before: old.pricing(),
+                    after: new.pricing(),
+                })
+            })
+            .collect();
+
+        PricingDiff {
+            from_version: previous.version.clone(),
+            to_version: self.version.clone(),
+            rates,
+            added,
+            removed,
+            changed,
+        }
+    }
+
+    fn models_by_key(&self) -> BTreeMap<ModelPriceKey<'_>, &ModelPrice> {
+        self.models
+            .iter()
+            .map(|model| (model.key(), model))
+            .collect()
+    }
+}
+
+/// Identifies a price version: provider, model and `effective_from`.
+type ModelPriceKey<'a> = (&'a str, &'a str, Option<DateTime<Utc>>);
+
+impl ModelPrice {
+    /// The model's token pricing.
+    #[must_use]
+    pub fn pricing(&self) -> LlmPricing {
+        LlmPricing {
+            input_credits_per_million: self.input_credits_per_million,
+            output_credits_per_million: self.output_credits_per_million,
+            cache_read_credits_per_million: self.cache_read_credits_per_million,
+            cache_creation_credits_per_million: self.cache_creation_credits_per_million,
+            batch_input_credits_per_million: self.batch_input_credits_per_million,
+            batch_output_credits_per_million: self.batch_output_credits_per_million,
+            effective_from: self.effective_from,
+            effective_until: self.effective_until,
+        }
+    }
+
+    fn key(&self) -> ModelPriceKey<'_> {
+        (&self.provider, &self.model, self.effective_from)
+    }
+}
+
+impl PricingDiff {
+    /// Whether the two catalogs price everything the same.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rates.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CATALOG: &str = r#"
+version = "2026-10-01"
+z_credit_rate_usd = 0.01
+cpu_hour_credits = 6
+memory_gb_hour_credits = 2
+
+[default_llm_pricing]
+input_credits_per_million = 100
+output_credits_per_million = 300
+
+[[models]]
+provider = "anthropic"
+model = "claude-sonnet-4-6"
+input_credits_per_million = 300
+output_credits_per_million = 1500
+
+[[models]]
+provider = "openai"
+model = "gpt-5.5"
+input_credits_per_million = 500
+output_credits_per_million = 3000
+"#;
+
+    #[test]
+    fn toml_and_json_catalogs_price_the_same() {
+        let catalog = PricingCatalog::from_toml(CATALOG).unwrap();
+        let json = serde_json::to_string(&catalog).unwrap();
+        assert_eq!(PricingCatalog::from_json(&json).unwrap(), catalog);
+
+        let config = catalog.to_config();
+        assert_eq!(
+            config.calculate_llm_cost("anthropic", "claude-sonnet-4-6", 1_000_000, 0),
+            300
+        );
+        assert_eq!(
+            config.calculate_llm_cost("unknown", "model", 1_000_000, 0),
+            100
+        );
+    }
+
+    #[test]
+    fn builtin_catalog_round_trips() {
+        let catalog = PricingCatalog::builtin();
+        catalog.validate().unwrap();
+        assert_eq!(
+            catalog.models.len(),
+            PricingConfig::default()
+                .llm_pricing
+                .values()
+                .map(Vec::len)
+                .sum::<usize>()
+        );
+        let round_trip = PricingCatalog::from_config("builtin", &catalog.to_config());
+        assert!(round_trip.diff(&catalog).is_empty());
+    }
+
+    #[test]
+    fn unknown_fields_and_bad_values_are_rejected() {
+        let typo = CATALOG.replace("cpu_hour_credits", "cpu_hours_credits");
+        assert!(matches!(
+            PricingCatalog::from_toml(&typo),
+            Err(CatalogError::Parse(_))
+        ));
+
+        let negative = CATALOG.replace("= 1500", "= -1500");
+        assert!(matches!(
+            PricingCatalog::from_toml(&negative),
+            Err(CatalogError::Invalid { .. })
+        ));
+
+        let duplicate = CATALOG
+            .replace("\"openai\"", "\"anthropic\"")
+            .replace("\"gpt-5.5\"", "\"claude-sonnet-4-6\"");
+        assert!(matches!(
+            PricingCatalog::from_toml(&duplicate),
+            Err(CatalogError::Invalid { .. })
+        ));
+
+        let unversioned = CATALOG.replace("\"2026-10-01\"", "\"\"");
+        assert!(PricingCatalog::from_toml(&unversioned).is_err());
+    }
+
+    #[test]
+    fn media_pricing_is_optional_and_diffed() {
+        let previous = PricingCatalog::from_toml(CATALOG).unwrap();
+        assert_eq!(previous.media_pricing, MediaPricing::default());
+
+        let with_media = format!(
+            "{}\n{}",
+            CATALOG.replace("2026-10-01", "2026-11-01"),
+            r#"
+[media_pricing]
+default_image_credits = { standard = 5, high = 10, ultra = 15 }
+default_audio_credits_per_hour = 36
+default_speech_credits_per_million_characters = 1500
+default_search_credits_per_thousand = 1000
+default_tool_credits_per_thousand = 100
+
+[[media_pricing.tools]]
+name = "code_execution"
+credits = 500
+"#
+        );
+        let next = PricingCatalog::from_toml(&with_media).unwrap();
+        let config = next.to_config();
+        assert_eq!(config.calculate_tool_cost("code_execution", 1_000), 500);
+        assert_eq!(
+            config.calculate_image_cost("openai", "dall-e-3", crate::ImageResolution::Standard, 1),
+            5
+        );
+
+        let diff = next.diff(&previous);
+        assert_eq!(diff.rates.len(), 1);
+        assert_eq!(diff.rates[0].field, "media_pricing");
+
+        let negative = with_media.replace("credits = 500", "credits = -500");
+        assert!(matches!(
+            PricingCatalog::from_toml(&negative),
+            Err(CatalogError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn storage_rate_is_optional_and_diffed() {
+        let previous = PricingCatalog::from_toml(CATALOG).unwrap();
+        assert!(
+            (previous.storage_gb_hour_credits - DEFAULT_STORAGE_GB_HOUR_CREDITS).abs()
+                < f64::EPSILON
+        );
+
+        let cheaper = CATALOG.replace(
+            "memory_gb_hour_credits = 2",
+            "memory_gb_hour_credits = 2\nstorage_gb_hour_credits = 0.002",
+        );
+        let mut next = PricingCatalog::from_toml(&cheaper).unwrap();
+        next.version = "2026-11-01".into();
+        assert_eq!(next.to_config().calculate_storage_cost(1_000.0), 2);
+
+        let diff = next.diff(&previous);
+        assert_eq!(diff.rates.len(), 1);
+        assert_eq!(diff.rates[0].field, "storage_gb_hour_credits");
+
+        let negative = cheaper.replace("= 0.002", "= -0.002");
+        assert!(matches!(
+            PricingCatalog::from_toml(&negative),
+            Err(CatalogError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn diff_lists_added_removed_and_changed_models() {
+        let previous = PricingCatalog::from_toml(CATALOG).unwrap();
+        let mut next = previous.clone();
+        next.version = "2026-11-01".into();
+        next.cpu_hour_credits = 7;
+        next.models[0].output_credits_per_million = 1200;
+        next.models.remove(1);
+        next.models.push(ModelPrice {
+            provider: "xai".into(),
+            model: "grok-5".into(),
+            input_credits_per_million: 200,
+            output_credits_per_million: 1000,
+            cache_read_credits_per_million: None,
+            cache_creation_credits_per_million: None,
+            batch_input_credits_per_million: None,
+            batch_output_credits_per_million: None,
+            effective_from: None,
+            effective_until: None,
+        });
+
+        let diff = next.diff(&previous);
+        assert_eq!(diff.from_version, "2026-10-01");
+        assert_eq!(diff.to_version, "2026-11-01");
+        assert_eq!(diff.rates.len(), 1);
+        assert_eq!(diff.rates[0].field, "cpu_hour_credits");
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].model, "grok-5");
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].model, "gpt-5.5");
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].before.output_credits_per_million, 1500);
+        assert_eq!(diff.changed[0].after.output_credits_per_million, 1200);
+
+        assert!(previous.diff(&previous).is_empty());
+    }
+
+    #[test]
+    fn dated_prices_apply_to_usage_in_their_range() {
+        let dated = CATALOG.replace(
+            "output_credits_per_million = 1500\n",
+            "output_credits_per_million = 1500\n\
+             effective_until = \"2026-11-01T00:00:00Z\"\n\n\
+             [[models]]\n\
+             provider = \"anthropic\"\n\
+             model = \"claude-sonnet-4-6\"\n\
+             input_credits_per_million = 250\n\
+             output_credits_per_million = 1250\n\
+             effective_from = \"2026-11-01T00:00:00Z\"\n",
+        );
+        let catalog = PricingCatalog::from_toml(&dated).unwrap();
+        let config = catalog.to_config();
+        let cutover: DateTime<Utc> = "2026-11-01T00:00:00Z".parse().unwrap();
+        let before = cutover - chrono::Duration::seconds(1);
+
+        let cost =
+            |at| config.calculate_llm_cost_at("anthropic", "claude-sonnet-4-6", 1_000_000, 0, at);
+        assert_eq!(cost(before), 300);
+        assert_eq!(cost(cutover), 250);
+        assert_eq!(
+            config
+                .llm_pricing_at("anthropic", "claude-sonnet-4-6", cutover)
+                .unwrap()
+                .effective_from,
+            Some(cutover)
+        );
+
+        // Both versions survive the round trip, and a later one is an addition
+        let round_trip = PricingCatalog::from_config("2026-10-01", &config);
+        assert_eq!(round_trip, catalog);
+        let diff = catalog.diff(&PricingCatalog::from_toml(CATALOG).unwrap());
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.changed.len(), 1);
+
+        let overlapping = dated.replace(
+            "effective_from = \"2026-11-01T00:00:00Z\"",
+            "effective_from = \"2026-10-15T00:00:00Z\"",
+        );
+        assert!(matches!(
+            PricingCatalog::from_toml(&overlapping),
+            Err(CatalogError::Invalid { .. })
+        ));
+    }
+}
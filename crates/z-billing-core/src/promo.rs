@@ -0,0 +1,247 @@
+//! Promo code types for z-billing.
+//!
+//! A promo code grants a fixed amount of bonus credits when redeemed. Codes
+//! can be limited in total redemptions, in redemptions per user, to a
+//! validity window, and to users on particular plans. Codes are matched
+//! case-insensitively and stored upper-case.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{Plan, TransactionId, UserId};
+
+/// A redeemable promo code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromoCode {
+    /// The code users enter (normalized with [`PromoCode::normalize`]).
+    pub code: String,
+
+    /// Credits granted per redemption (in cents).
+    pub amount_cents: i64,
+
+    /// Most redemptions across all users (None = unlimited).
+    pub max_redemptions: Option<i64>,
+
+    /// Most redemptions by a single user.
+    pub per_user_limit: i64,
+
+    /// Redemptions so far.
+    #[serde(default)]
+    pub redemption_count: i64,
+
+    /// When the code becomes redeemable (None = immediately).
+    pub valid_from: Option<DateTime<Utc>>,
+
+    /// When the code stops being redeemable (None = never).
+    pub valid_until: Option<DateTime<Utc>>,
+
+    /// Plans whose users may redeem the code (empty = any plan).
+    #[serde(default)]
+    pub plans: Vec<Plan>,
+
+    /// Whether the code can be redeemed at all.
+    pub active: bool,
+
+    /// Internal note on what the code is for.
+    #[serde(default)]
+    pub description: String,
+
+    /// When the code was created.
+    pub created_at: DateTime<Utc>,
+
+    /// When the code was last updated.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PromoCode {
+    /// Create a new active code redeemable once per user, with no other
+    /// restrictions.
+    #[must_use]
+    pub fn new(code: &str, amount_cents: i64) -> Self {
+        let now = Utc::now();
+        Self {
+            code: Self::normalize(code),
+            amount_cents,
+            max_redemptions: None,
+            per_user_limit: 1,
+            redemption_count: 0,
+            valid_from: None,
+            valid_until: None,
+            plans: Vec::new(),
+            active: true,
+            description: String::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Normalize a user-entered code for lookup.
+    #[must_use]
+    pub fn normalize(code: &str) -> String {
+        code.trim().to_ascii_uppercase()
+    }
+
+    /// Check whether a user on `plan` who has already redeemed the code
+    /// `user_redemptions` times may redeem it at `now`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first rule the redemption breaks.
+    pub fn check_redeemable(
+        &self,
+        plan: &Plan,
+        user_redemptions: i64,
+        now: DateTime<Utc>,
+    ) -> Result<(), PromoRejection> {
+        if !self.active {
+            return Err(PromoRejection::Inactive);
+        }
+        if self.valid_from.is_some_and(|from| now < from) {
+            return Err(PromoRejection::NotYetValid);
+        }
+        if self.valid_until.is_some_and(|until| now >= until) {
+            return Err(PromoRejection::Expired);
+        }
+        let plan = plan.normalized();
+        if !self.plans.is_empty() && !self.plans.iter().any(|p| p.normalized() == plan) {
+            return Err(PromoRejection::PlanNotEligible);
+        }
+        if self
+            .max_redemptions
+            .is_some_and(|max| self.redemption_count >= max)
+        {
+            return Err(PromoRejection::Exhausted);
+        }
+        if user_redemptions >= self.per_user_limit {
+            return Err(PromoRejection::UserLimitReached);
+        }
+        Ok(())
+    }
+}
+
+/// Why a promo code could not be redeemed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromoRejection {
+    /// The code has been switched off.
+    Inactive,
+    /// The validity window has not started.
+    NotYetValid,
+    /// The validity window has ended.
+    Expired,
+    /// The user's plan is not one the code is for.
+    PlanNotEligible,
+    /// The code has reached its total redemption limit.
+    Exhausted,
+    /// The user has reached the per-user redemption limit.
+    UserLimitReached,
+}
+
+impl PromoRejection {
+    /// Get the string representation used in storage and API responses.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Inactive => "inactive",
+            Self::NotYetValid => "not_yet_valid",
+            Self::Expired => "expired",
+            Self::PlanNotEligible => "plan_not_eligible",
+            Self::Exhausted => "exhausted",
+            Self::UserLimitReached => "user_limit_reached",
+        }
+    }
+}
+
+/// A single redemption of a promo code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromoRedemption {
+    /// The redeemed code.
+    pub code: String,
+
+    /// The user who redeemed it.
+    pub user_id: UserId,
+
+    /// The bonus transaction that credited the user.
+    pub transaction_id: TransactionId,
+
+    /// Credits granted (in cents).
+    pub amount_cents: i64,
+
+    /// When the code was redeemed.
+    pub redeemed_at: DateTime<Utc>,
+}
+
+impl PromoRedemption {
+    /// Create a new redemption record.
+    #[must_use]
+    pub fn new(
+        code: String,
+        user_id: UserId,
+        transaction_id: TransactionId,
+        amount_cents: i64,
+    ) -> Self {
+        Self {
+            code,
+            user_id,
+            transaction_id,
+            amount_cents,
+            redeemed_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn codes_are_case_insensitive() {
+        let promo = PromoCode::new("  launch-50 ", 500);
+        assert_eq!(promo.code, "LAUNCH-50");
+        assert_eq!(PromoCode::normalize("Launch-50"), promo.code);
+    }
+
+    #[test]
+    fn redemption_rules_are_enforced() {
+        let now = Utc::now();
+        let mut promo = PromoCode::new("SPRING", 500);
+        promo.max_redemptions = Some(2);
+        promo.per_user_limit = 1;
+        promo.valid_from = Some(now - Duration::days(1));
+        promo.valid_until = Some(now + Duration::days(1));
+        promo.plans = vec![Plan::Pro];
+
+        assert_eq!(promo.check_redeemable(&Plan::Pro, 0, now), Ok(()));
+        // Legacy plan names match their current tier
+        assert_eq!(promo.check_redeemable(&Plan::Standard, 0, now), Ok(()));
+        assert_eq!(
+            promo.check_redeemable(&Plan::Mortal, 0, now),
+            Err(PromoRejection::PlanNotEligible)
+        );
+        assert_eq!(
+            promo.check_redeemable(&Plan::Pro, 1, now),
+            Err(PromoRejection::UserLimitReached)
+        );
+        assert_eq!(
+            promo.check_redeemable(&Plan::Pro, 0, now - Duration::days(2)),
+            Err(PromoRejection::NotYetValid)
+        );
+        assert_eq!(
+            promo.check_redeemable(&Plan::Pro, 0, now + Duration::days(1)),
+            Err(PromoRejection::Expired)
+        );
+
+        promo.redemption_count = 2;
+        assert_eq!(
+            promo.check_redeemable(&Plan::Pro, 0, now),
+            Err(PromoRejection::Exhausted)
+        );
+
+        promo.active = false;
+        assert_eq!(
+            promo.check_redeemable(&Plan::Pro, 0, now),
+            Err(PromoRejection::Inactive)
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Credit reservation types for z-billing.
+//!
+//! A reservation places a temporary hold on part of an account's balance
+//! while a long-running operation (such as a streamed LLM response) is in
+//! flight. The hold is later settled into a real usage debit, released, or
+//! left to expire after its TTL.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{ReservationId, TransactionId, UserId};
+
+/// A hold on part of an account's balance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reservation {
+    /// Unique reservation ID (ULID for time-ordering).
+    pub id: ReservationId,
+
+    /// The user whose balance is held.
+    pub user_id: UserId,
+
+    /// Amount held in cents.
+    pub amount_cents: i64,
+
+    /// Current lifecycle state.
+    pub status: ReservationStatus,
+
+    /// The usage transaction created on settlement, if settled.
+    #[serde(default)]
+    pub transaction_id: Option<TransactionId>,
+
+    /// Additional context (provider, model, `request_id`, etc.).
+    pub metadata: serde_json::Value,
+
+    /// When the hold was placed.
+    pub created_at: DateTime<Utc>,
+
+    /// When the hold stops counting against the balance if not settled.
+    pub expires_at: DateTime<Utc>,
+
+    /// When the reservation was last updated.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Reservation {
+    /// Create a new active reservation that expires after `ttl`.
+    #[must_use]
+    pub fn new(
+        user_id: UserId,
+        amount_cents: i64,
+        ttl: Duration,
+        metadata: serde_json::Value,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: ReservationId::generate(),
+            user_id,
+            amount_cents,
+            status: ReservationStatus::Active,
+            transaction_id: None,
+            metadata,
+            created_at: now,
+            expires_at: now + ttl,
+            updated_at: now,
+        }
+    }
+
+    /// Whether the hold still counts against the balance at `now`.
+    #[must_use]
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        self.status == ReservationStatus::Active && self.expires_at > now
+    }
+}
+
+/// Lifecycle state of a reservation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReservationStatus {
+    /// Hold is in place.
+    Active,
+    /// Hold was converted into a usage debit.
+    Settled,
+    /// Hold was released without charging.
+    Released,
+    /// Hold lapsed after its TTL without being settled.
+    Expired,
+}
+
+impl ReservationStatus {
+    /// Get the string representation used in storage and API responses.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Settled => "settled",
+            Self::Released => "released",
+            Self::Expired => "expired",
+        }
+    }
+}
+
+impl std::str::FromStr for ReservationStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "active" => Ok(Self::Active),
+            "settled" => Ok(Self::Settled),
+            "released" => Ok(Self::Released),
+            "expired" => Ok(Self::Expired),
+            other => Err(format!("unknown reservation status: {other}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_reservation_is_active_until_expiry() {
+        let reservation = Reservation::new(
+            UserId::generate(),
+            50,
+            Duration::seconds(60),
+            serde_json::Value::Null,
+        );
+
+        assert_eq!(reservation.status, ReservationStatus::Active);
+        assert!(reservation.is_active_at(reservation.created_at));
+        assert!(!reservation.is_active_at(reservation.expires_at));
+    }
+
+    #[test]
+    fn closed_reservation_is_not_active() {
+        let mut reservation = Reservation::new(
+            UserId::generate(),
+            50,
+            Duration::seconds(60),
+            serde_json::Value::Null,
+        );
+        reservation.status = ReservationStatus::Released;
+
+        assert!(!reservation.is_active_at(reservation.created_at));
+    }
+
+    #[test]
+    fn status_string_roundtrip() {
+        for status in [
+            ReservationStatus::Active,
+            ReservationStatus::Settled,
+            ReservationStatus::Released,
+            ReservationStatus::Expired,
+        ] {
+            assert_eq!(
+                status.as_str().parse::<ReservationStatus>().unwrap(),
+                status
+            );
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Snapshot-based storage metering for z-billing.
+//!
+//! Storage services report how many bytes a resource holds, as often as
+//! they like, instead of reporting usage. z-billing keeps one meter per user
+//! and resource and, on each reading, integrates the bytes held since the
+//! previous reading into GB-hours. A GB-hour costs a fraction of a credit,
+//! so a reading is charged whole credits only; the GB-hours not yet charged
+//! carry over to the next reading.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::UserId;
+
+/// Bytes in a GB, as storage is priced (decimal, not GiB).
+pub const BYTES_PER_GB: f64 = 1_000_000_000.0;
+
+/// The last reading of one storage resource.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageMeter {
+    /// The user who owns the resource.
+    pub user_id: UserId,
+
+    /// The reporting service's identifier for the resource (a bucket,
+    /// volume, etc.).
+    pub resource_id: String,
+
+    /// Bytes held as of `read_at`.
+    pub bytes: u64,
+
+    /// When the last reading was taken.
+    pub read_at: DateTime<Utc>,
+
+    /// GB-hours accrued but not yet charged.
+    pub unbilled_gb_hours: f64,
+
+    /// Optimistic concurrency version, incremented on every write (0 before
+    /// the first).
+    #[serde(default)]
+    pub version: i64,
+}
+
+/// What one reading owes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StorageCharge {
+    /// GB-hours covered by the charge.
+    pub gb_hours: f64,
+    /// Credits to charge, possibly zero.
+    pub cost_cents: i64,
+}
+
+impl StorageMeter {
+    /// Start metering a resource from its first reading. Nothing is owed
+    /// until the next one.
+    #[must_use]
+    pub fn new(user_id: UserId, resource_id: String, bytes: u64, read_at: DateTime<Utc>) -> Self {
+        Self {
+            user_id,
+            resource_id,
+            bytes,
+            read_at,
+            unbilled_gb_hours: 0.0,
+            version: 0,
+        }
+    }
+
+    /// GB-hours accrued from the last reading to `at`, assuming the bytes
+    /// held did not change in between. Zero if `at` is not after it.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn gb_hours_until(&self, at: DateTime<Utc>) -> f64 {
+        let millis = (at - self.read_at).num_milliseconds();
+        if millis <= 0 {
+            return 0.0;
+        }
+        self.bytes as f64 / BYTES_PER_GB * (millis as f64 / 3_600_000.0)
+    }
+
+    /// Take a reading of `bytes` at `at` and work out the whole credits now
+    /// owed at `credits_per_gb_hour`.
+    ///
+    /// The meter moves to the new reading and keeps the GB-hours the charge
+    /// does not cover. A reading not after the last one is stale: it owes
+    /// nothing and leaves the meter unchanged.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    pub fn record(
+        &mut self,
+        bytes: u64,
+        at: DateTime<Utc>,
+        credits_per_gb_hour: f64,
+    ) -> StorageCharge {
+        if at <= self.read_at {
+            return StorageCharge {
+                gb_hours: 0.0,
+                cost_cents: 0,
+            };
+        }
+
+        let owed_gb_hours = self.unbilled_gb_hours + self.gb_hours_until(at);
+        self.bytes = bytes;
+        self.read_at = at;
+
+        // Free storage is never charged, so nothing carries over
+        if credits_per_gb_hour <= 0.0 {
+            self.unbilled_gb_hours = 0.0;
+            return StorageCharge {
+                gb_hours: owed_gb_hours,
+                cost_cents: 0,
+            };
+        }
+
+        let cost_cents = (owed_gb_hours * credits_per_gb_hour).floor() as i64;
+        let gb_hours = cost_cents as f64 / credits_per_gb_hour;
+        self.unbilled_gb_hours = (owed_gb_hours - gb_hours).max(0.0);
+        StorageCharge {
+            gb_hours,
+            cost_cents,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn readings_integrate_gb_hours_and_carry_the_remainder() {
+        let start = Utc::now();
+        let mut meter = StorageMeter::new(UserId::generate(), "bucket-1".into(), 0, start);
+
+        // Empty for the first hour
+        let charge = meter.record(500_000_000_000, start + Duration::hours(1), 0.004);
+        assert_eq!(charge.cost_cents, 0);
+        assert_eq!(meter.bytes, 500_000_000_000);
+
+        // 500 GB for an hour is 2 credits
+        let charge = meter.record(500_000_000_000, start + Duration::hours(2), 0.004);
+        assert_eq!(charge.cost_cents, 2);
+        assert!((charge.gb_hours - 500.0).abs() < 1e-6);
+        assert!(meter.unbilled_gb_hours.abs() < 1e-6);
+
+        // 500 GB for 15 minutes is half a credit, carried to the next reading
+        let charge = meter.record(500_000_000_000, start + Duration::minutes(135), 0.004);
+        assert_eq!(charge.cost_cents, 0);
+        assert!((meter.unbilled_gb_hours - 125.0).abs() < 1e-6);
+        let charge = meter.record(500_000_000_000, start + Duration::minutes(150), 0.004);
+        assert_eq!(charge.cost_cents, 1);
+        assert!(meter.unbilled_gb_hours.abs() < 1e-6);
+    }
+
+    #[test]
+    fn stale_readings_owe_nothing() {
+        let start = Utc::now();
+        let mut meter = StorageMeter::new(UserId::generate(), "bucket-1".into(), 1_000, start);
+        let before = meter.clone();
+
+        let charge = meter.record(2_000, start, 0.004);
+        assert_eq!(charge.cost_cents, 0);
+        assert_eq!(meter, before);
+        assert!(meter.gb_hours_until(start - Duration::hours(1)).abs() < f64::EPSILON);
+    }
+}
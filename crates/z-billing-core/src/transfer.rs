@@ -0,0 +1,60 @@
+//! Credit transfer types for z-billing.
+//!
+//! A transfer moves paid-for credits from a user's personal balance to
+//! another user or to an organization pool. Both sides are recorded as
+//! transactions (`TransferOut` on the sender, `TransferIn` on the
+//! recipient) and the transfer itself is stored under the sender's
+//! idempotency key so that retries return the original result.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{LedgerAccount, TransactionId, UserId};
+
+/// A completed credit transfer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreditTransfer {
+    /// The user whose personal balance was debited.
+    pub from_user_id: UserId,
+
+    /// Caller-supplied key, unique per sender.
+    pub idempotency_key: String,
+
+    /// The wallet that received the credits (a user or an organization).
+    pub to: LedgerAccount,
+
+    /// Amount moved (in cents, always positive).
+    pub amount_cents: i64,
+
+    /// The `TransferOut` transaction on the sender.
+    pub out_transaction_id: TransactionId,
+
+    /// The `TransferIn` transaction on the recipient.
+    pub in_transaction_id: TransactionId,
+
+    /// When the transfer was recorded.
+    pub created_at: DateTime<Utc>,
+}
+
+impl CreditTransfer {
+    /// Create a new transfer record.
+    #[must_use]
+    pub fn new(
+        from_user_id: UserId,
+        idempotency_key: String,
+        to: LedgerAccount,
+        amount_cents: i64,
+        out_transaction_id: TransactionId,
+        in_transaction_id: TransactionId,
+    ) -> Self {
+        Self {
+            from_user_id,
+            idempotency_key,
+            to,
+            amount_cents,
+            out_transaction_id,
+            in_transaction_id,
+            created_at: Utc::now(),
+        }
+    }
+}
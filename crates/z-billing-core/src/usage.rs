@@ -5,7 +5,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::{AgentId, UserId};
+use crate::{AgentId, TransactionId, UserId};
 
 /// A usage event reported by a service.
 ///
@@ -28,7 +28,8 @@ pub struct UsageEvent {
     /// What was used.
     pub metric: UsageMetric,
 
-    /// Quantity used (tokens, hours, etc.).
+    /// Quantity used (tokens, hours, images, audio seconds, characters,
+    /// queries or calls).
     pub quantity: f64,
 
     /// Pre-calculated cost in cents based on current pricing.
@@ -39,6 +40,10 @@ pub struct UsageEvent {
 
     /// Additional context (`session_id`, `request_id`, etc.).
     pub metadata: serde_json::Value,
+
+    /// The usage transaction that charged for this event, if any.
+    #[serde(default)]
+    pub transaction_id: Option<TransactionId>,
 }
 
 impl UsageEvent {
@@ -64,11 +69,15 @@ impl UsageEvent {
                 provider,
                 model,
                 direction,
+                cache_read_input_tokens: 0,
+                cache_creation_input_tokens: 0,
+                batch: false,
             },
             quantity: tokens as f64,
             cost_cents,
             timestamp: Utc::now(),
             metadata: serde_json::Value::Null,
+            transaction_id: None,
         }
     }
 
@@ -95,6 +104,7 @@ impl UsageEvent {
             cost_cents,
             timestamp: Utc::now(),
             metadata: serde_json::Value::Null,
+            transaction_id: None,
         }
     }
 
@@ -111,10 +121,58 @@ impl UsageEvent {
         self.source = source;
         self
     }
+
+    /// Set the transaction that charged for the event.
+    #[must_use]
+    pub fn with_transaction(mut self, transaction_id: TransactionId) -> Self {
+        self.transaction_id = Some(transaction_id);
+        self
+    }
+}
+
+/// A full or partial refund of a usage event's charge.
+///
+/// Reversals are keyed by `(event_id, reversal_id)` so retrying the same
+/// reversal is a no-op.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReversal {
+    /// The usage event being reversed.
+    pub event_id: String,
+
+    /// Caller-supplied ID for idempotency within the event.
+    pub reversal_id: String,
+
+    /// Amount refunded (in cents, always positive).
+    pub amount_cents: i64,
+
+    /// The reversal transaction that credited the refund.
+    pub transaction_id: TransactionId,
+
+    /// When the reversal was recorded.
+    pub created_at: DateTime<Utc>,
+}
+
+impl UsageReversal {
+    /// Create a new reversal record.
+    #[must_use]
+    pub fn new(
+        event_id: String,
+        reversal_id: String,
+        amount_cents: i64,
+        transaction_id: TransactionId,
+    ) -> Self {
+        Self {
+            event_id,
+            reversal_id,
+            amount_cents,
+            transaction_id,
+            created_at: Utc::now(),
+        }
+    }
 }
 
 /// Source service that generated the usage.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum UsageSource {
     /// Aura Swarm agent orchestration.
@@ -159,6 +217,15 @@ pub enum UsageMetric {
         model: String,
         /// Input or output tokens.
         direction: TokenDirection,
+        /// Input tokens read from the prompt cache.
+        #[serde(default)]
+        cache_read_input_tokens: u64,
+        /// Input tokens written to the prompt cache.
+        #[serde(default)]
+        cache_creation_input_tokens: u64,
+        /// Whether the request went through the provider's batch API.
+        #[serde(default)]
+        batch: bool,
     },
 
     /// API calls.
@@ -172,10 +239,48 @@ pub enum UsageMetric {
         /// GB-hours of storage used.
         gb_hours: f64,
     },
+
+    /// Image generation; the quantity is the number of images.
+    ImageGeneration {
+        /// Which provider generated the images.
+        provider: LlmProvider,
+        /// Model name (e.g., "gpt-image-1").
+        model: String,
+        /// Resolution tier of the images.
+        resolution: ImageResolution,
+    },
+
+    /// Audio transcription; the quantity is seconds of audio.
+    AudioTranscription {
+        /// Which provider transcribed the audio.
+        provider: LlmProvider,
+        /// Model name (e.g., "whisper-1").
+        model: String,
+    },
+
+    /// Text-to-speech; the quantity is characters of input text.
+    TextToSpeech {
+        /// Which provider synthesized the speech.
+        provider: LlmProvider,
+        /// Model name (e.g., "tts-1").
+        model: String,
+    },
+
+    /// Web search; the quantity is the number of queries.
+    WebSearch {
+        /// Which provider ran the searches.
+        provider: LlmProvider,
+    },
+
+    /// Paid tool calls; the quantity is the number of calls.
+    ToolCalls {
+        /// Tool name.
+        tool: String,
+    },
 }
 
 /// LLM provider.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum LlmProvider {
     /// Anthropic (Claude models).
@@ -212,6 +317,31 @@ impl LlmProvider {
     }
 }
 
+/// Resolution tier of a generated image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageResolution {
+    /// Up to 1024×1024 pixels.
+    #[default]
+    Standard,
+    /// Up to 2048×2048 pixels.
+    High,
+    /// Larger than 2048×2048 pixels.
+    Ultra,
+}
+
+impl ImageResolution {
+    /// Get the tier name as a string.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::High => "high",
+            Self::Ultra => "ultra",
+        }
+    }
+}
+
 /// Token direction (input or output).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
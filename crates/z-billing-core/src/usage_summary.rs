@@ -0,0 +1,406 @@
+//! Usage aggregation for spend reporting.
+//!
+//! Stores roll `usage_events` up into [`DailyUsage`] totals, one per UTC day
+//! and combination of event attributes. [`UsageSummaryQuery::summarize`] then
+//! folds those into whatever grouping the caller asked for. Grouping by
+//! [`Maker`] happens here rather than in the store, since makers come from
+//! [`maker_for_model`].
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+
+use crate::{
+    maker_for_model, AgentId, LlmProvider, Maker, TokenDirection, UsageEvent, UsageMetric,
+    UsageSource, UserId,
+};
+
+/// How usage is bucketed over time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageInterval {
+    /// One bucket per UTC day.
+    Day,
+    /// One bucket per ISO week (starting Monday, UTC).
+    Week,
+}
+
+impl UsageInterval {
+    /// Get the string representation used in API requests and responses.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+        }
+    }
+
+    /// First day of the bucket containing `day`.
+    #[must_use]
+    pub fn bucket_start(&self, day: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => day,
+            Self::Week => {
+                day - chrono::Duration::days(i64::from(day.weekday().num_days_from_monday()))
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for UsageInterval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Self::Day),
+            "week" => Ok(Self::Week),
+            other => Err(format!("unknown usage interval: {other}")),
+        }
+    }
+}
+
+/// An event attribute usage can be grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageDimension {
+    /// LLM provider that served the request.
+    Provider,
+    /// Model name.
+    Model,
+    /// Company that makes the model.
+    Maker,
+    /// Agent that generated the usage.
+    Agent,
+    /// Service that reported the usage.
+    Source,
+}
+
+impl UsageDimension {
+    /// Get the string representation used in API requests.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Provider => "provider",
+            Self::Model => "model",
+            Self::Maker => "maker",
+            Self::Agent => "agent",
+            Self::Source => "source",
+        }
+    }
+}
+
+impl std::str::FromStr for UsageDimension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "provider" => Ok(Self::Provider),
+            "model" => Ok(Self::Model),
+            "maker" => Ok(Self::Maker),
+            "agent" => Ok(Self::Agent),
+            "source" => Ok(Self::Source),
+            other => Err(format!("unknown usage dimension: {other}")),
+        }
+    }
+}
+
+/// Which usage to aggregate and how to group it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageSummaryQuery {
+    /// Only this user's usage, or every user's if `None`.
+    pub user_id: Option<UserId>,
+
+    /// Only usage at or after this time.
+    pub since: DateTime<Utc>,
+
+    /// Only usage before this time.
+    pub until: DateTime<Utc>,
+
+    /// Time buckets, or one bucket for the whole range if `None`.
+    pub interval: Option<UsageInterval>,
+
+    /// Attributes to group by. Attributes not listed are summed over.
+    pub group_by: Vec<UsageDimension>,
+}
+
+impl UsageSummaryQuery {
+    /// Check whether an event falls within the query's user and time range.
+    #[must_use]
+    pub fn includes(&self, event: &UsageEvent) -> bool {
+        if self.user_id.is_some_and(|user_id| event.user_id != user_id) {
+            return false;
+        }
+        event.timestamp >= self.since && event.timestamp < self.until
+    }
+
+    /// Fold daily totals into summary rows, ordered by period and then by
+    /// cost (highest first).
+    #[must_use]
+    pub fn summarize(&self, usage: impl IntoIterator<Item = DailyUsage>) -> Vec<UsageSummaryRow> {
+        let mut rows: HashMap<UsageGroup, UsageSummaryRow> = HashMap::new();
+        for daily in usage {
+            let group = self.group(&daily);
+            let row = rows
+                .entry(group.clone())
+                .or_insert_with(|| UsageSummaryRow {
+                    group,
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    cost_cents: 0,
+                    event_count: 0,
+                });
+            row.add(&daily);
+        }
+
+        let mut rows: Vec<_> = rows.into_values().collect();
+        rows.sort_by(|a, b| {
+            a.group
+                .period_start
+                .cmp(&b.group.period_start)
+                .then(b.cost_cents.cmp(&a.cost_cents))
+        });
+        rows
+    }
+
+    /// The group `daily` belongs to under this query.
+    fn group(&self, daily: &DailyUsage) -> UsageGroup {
+        let mut group = UsageGroup {
+            period_start: self
+                .interval
+                .map(|interval| interval.bucket_start(daily.day)),
+            ..UsageGroup::default()
+        };
+        for dimension in &self.group_by {
+            match dimension {
+                UsageDimension::Provider => group.provider.clone_from(&daily.provider),
+                UsageDimension::Model => group.model.clone_from(&daily.model),
+                UsageDimension::Maker => {
+                    group.maker = daily.model.as_deref().and_then(maker_for_model);
+                }
+                UsageDimension::Agent => group.agent_id = daily.agent_id,
+                UsageDimension::Source => group.source = Some(daily.source.clone()),
+            }
+        }
+        group
+    }
+}
+
+/// Usage totals for one UTC day and one combination of event attributes.
+///
+/// This is the grain stores aggregate `usage_events` to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyUsage {
+    /// The UTC day.
+    pub day: NaiveDate,
+
+    /// Service that reported the usage.
+    pub source: UsageSource,
+
+    /// Agent that generated the usage, if any.
+    pub agent_id: Option<AgentId>,
+
+    /// Provider, for token, media and search usage.
+    pub provider: Option<LlmProvider>,
+
+    /// Model name, for token and media usage.
+    pub model: Option<String>,
+
+    /// Input or output, for token usage.
+    pub direction: Option<TokenDirection>,
+
+    /// Sum of the events' quantities (tokens for token usage).
+    pub quantity: f64,
+
+    /// Sum of the events' costs in cents.
+    pub cost_cents: i64,
+
+    /// Number of events.
+    pub event_count: u64,
+}
+
+impl DailyUsage {
+    /// Totals for a single event.
+    #[must_use]
+    pub fn from_event(event: &UsageEvent) -> Self {
+        let (provider, model, direction) = match &event.metric {
+            UsageMetric::LlmTokens {
+                provider,
+                model,
+                direction,
+                ..
+            } => (
+                Some(provider.clone()),
+                Some(model.clone()),
+                Some(*direction),
+            ),
+            UsageMetric::ImageGeneration {
+                provider, model, ..
+            }
+            | UsageMetric::AudioTranscription { provider, model }
+            | UsageMetric::TextToSpeech { provider, model } => {
+                (Some(provider.clone()), Some(model.clone()), None)
+            }
+            UsageMetric::WebSearch { provider } => (Some(provider.clone()), None, None),
+            _ => (None, None, None),
+        };
+        Self {
+            day: event.timestamp.date_naive(),
+            source: event.source.clone(),
+            agent_id: event.agent_id,
+            provider,
+            model,
+            direction,
+            quantity: event.quantity,
+            cost_cents: event.cost_cents,
+            event_count: 1,
+        }
+    }
+}
+
+/// The attributes a summary row is grouped by. Attributes the query did not
+/// group by are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct UsageGroup {
+    /// First day of the time bucket.
+    pub period_start: Option<NaiveDate>,
+
+    /// LLM provider.
+    pub provider: Option<LlmProvider>,
+
+    /// Model name.
+    pub model: Option<String>,
+
+    /// Company that makes the model.
+    pub maker: Option<Maker>,
+
+    /// Agent.
+    pub agent_id: Option<AgentId>,
+
+    /// Service that reported the usage.
+    pub source: Option<UsageSource>,
+}
+
+/// Usage totals for one group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageSummaryRow {
+    /// What the totals are for.
+    pub group: UsageGroup,
+
+    /// Input (prompt) tokens.
+    pub input_tokens: u64,
+
+    /// Output (completion) tokens.
+    pub output_tokens: u64,
+
+    /// Total cost in cents.
+    pub cost_cents: i64,
+
+    /// Number of usage events.
+    pub event_count: u64,
+}
+
+impl UsageSummaryRow {
+    /// Add a day's totals to this row.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn add(&mut self, daily: &DailyUsage) {
+        let tokens = daily.quantity.round().max(0.0) as u64;
+        match daily.direction {
+            Some(TokenDirection::Input) => self.input_tokens += tokens,
+            Some(TokenDirection::Output) => self.output_tokens += tokens,
+            None => {}
+        }
+        self.cost_cents += daily.cost_cents;
+        self.event_count += daily.event_count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn llm_event(
+        model: &str,
+        direction: TokenDirection,
+        tokens: u64,
+        cost_cents: i64,
+    ) -> UsageEvent {
+        UsageEvent::llm(
+            format!("evt_{model}_{tokens}"),
+            UserId::generate(),
+            None,
+            LlmProvider::Anthropic,
+            model.to_string(),
+            direction,
+            tokens,
+            cost_cents,
+        )
+    }
+
+    fn query(interval: Option<UsageInterval>, group_by: Vec<UsageDimension>) -> UsageSummaryQuery {
+        UsageSummaryQuery {
+            user_id: None,
+            since: DateTime::<Utc>::MIN_UTC,
+            until: DateTime::<Utc>::MAX_UTC,
+            interval,
+            group_by,
+        }
+    }
+
+    #[test]
+    fn week_buckets_start_on_monday() {
+        let thursday = NaiveDate::from_ymd_opt(2025, 1, 16).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2025, 1, 13).unwrap();
+        assert_eq!(UsageInterval::Week.bucket_start(thursday), monday);
+        assert_eq!(UsageInterval::Week.bucket_start(monday), monday);
+        assert_eq!(UsageInterval::Day.bucket_start(thursday), thursday);
+    }
+
+    #[test]
+    fn summarize_groups_by_maker_and_splits_tokens() {
+        let events = [
+            llm_event("claude-opus-4", TokenDirection::Input, 1000, 30),
+            llm_event("aura-claude-sonnet-4", TokenDirection::Output, 200, 10),
+            llm_event("gpt-5", TokenDirection::Input, 500, 5),
+        ];
+
+        let rows = query(None, vec![UsageDimension::Maker])
+            .summarize(events.iter().map(DailyUsage::from_event));
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].group.maker, Some(Maker::Anthropic));
+        assert_eq!(rows[0].input_tokens, 1000);
+        assert_eq!(rows[0].output_tokens, 200);
+        assert_eq!(rows[0].cost_cents, 40);
+        assert_eq!(rows[0].event_count, 2);
+        assert_eq!(rows[1].group.maker, Some(Maker::OpenAi));
+        assert_eq!(rows[1].cost_cents, 5);
+    }
+
+    #[test]
+    fn summarize_without_grouping_totals_everything() {
+        let compute = UsageEvent::compute("evt_c".into(), UserId::generate(), None, 1.5, 2.0, 7);
+        let events = [
+            llm_event("claude-opus-4", TokenDirection::Input, 1000, 30),
+            compute,
+        ];
+
+        let rows = query(Some(UsageInterval::Day), vec![])
+            .summarize(events.iter().map(DailyUsage::from_event));
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].group.period_start, Some(Utc::now().date_naive()));
+        assert_eq!(rows[0].input_tokens, 1000);
+        assert_eq!(rows[0].cost_cents, 37);
+        assert_eq!(rows[0].event_count, 2);
+    }
+
+    #[test]
+    fn includes_filters_user_and_range() {
+        let event = llm_event("gpt-5", TokenDirection::Input, 1, 1);
+        let mut query = query(None, vec![]);
+        assert!(query.includes(&event));
+
+        query.user_id = Some(UserId::generate());
+        assert!(!query.includes(&event));
+
+        query.user_id = Some(event.user_id);
+        query.until = event.timestamp;
+        assert!(!query.includes(&event));
+    }
+}
@@ -0,0 +1,16 @@
+//! Processed webhook records for z-billing.
+//!
+//! Stripe and Lago webhook deliveries are recorded once processed, so a
+//! redelivered event is recognised and skipped.
+
+use serde::{Deserialize, Serialize};
+
+/// A webhook delivery recorded as processed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessedWebhook {
+    /// Deduplication key of the delivery (the provider's event ID).
+    pub event_id: String,
+
+    /// Provider that sent it (`stripe` or `lago`).
+    pub source: String,
+}
@@ -0,0 +1,117 @@
+//! Migration progress, saved between pages so a migration can resume.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{MigrateError, Result};
+
+/// How far a migration has got with each kind of data.
+///
+/// Saved as JSON after every page. The counts cover all runs that used the
+/// same checkpoint, so the checkpoint a run returns is also its report.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Accounts.
+    pub accounts: Progress,
+
+    /// Credit transactions.
+    pub transactions: Progress,
+
+    /// Usage events.
+    pub usage_events: Progress,
+
+    /// Processed webhook events.
+    pub webhook_events: Progress,
+}
+
+/// Progress through one kind of data.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    /// Key of the last item handled; the next page starts after it.
+    pub after: Option<String>,
+
+    /// Items copied to the target (or that would be, in a dry run).
+    pub copied: u64,
+
+    /// Items the target already had.
+    pub skipped: u64,
+
+    /// Transactions left behind because their account or organization no
+    /// longer exists in the source.
+    pub orphaned: u64,
+
+    /// Whether every item has been handled.
+    pub done: bool,
+}
+
+/// What happened to one item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    Copied,
+    Skipped,
+    Orphaned,
+}
+
+impl Checkpoint {
+    /// Load the checkpoint at `path`, or start from scratch if there is none.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MigrateError::Checkpoint` if the file exists but cannot be
+    /// read or parsed.
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| MigrateError::Checkpoint(format!("{}: {e}", path.display()))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(MigrateError::Checkpoint(format!("{}: {e}", path.display()))),
+        }
+    }
+
+    /// Write the checkpoint to `path`.
+    ///
+    /// The file is replaced atomically, so a crash mid-write leaves the
+    /// previous checkpoint intact.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MigrateError::Checkpoint` if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json =
+            serde_json::to_vec_pretty(self).map_err(|e| MigrateError::Checkpoint(e.to_string()))?;
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, json)
+            .and_then(|()| std::fs::rename(&partial, path))
+            .map_err(|e| MigrateError::Checkpoint(format!("{}: {e}", path.display())))
+    }
+
+    /// Whether every kind of data has been handled.
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.accounts.done
+            && self.transactions.done
+            && self.usage_events.done
+            && self.webhook_events.done
+    }
+}
+
+impl Progress {
+    /// Count one item.
+    pub(crate) fn record(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Copied => self.copied += 1,
+            Outcome::Skipped => self.skipped += 1,
+            Outcome::Orphaned => self.orphaned += 1,
+        }
+    }
+
+    /// Move past a page of `len` items ending at `last_key`. A short page
+    /// is the last one.
+    pub(crate) fn advance(&mut self, last_key: Option<String>, len: usize, batch_size: usize) {
+        if last_key.is_some() {
+            self.after = last_key;
+        }
+        self.done = len < batch_size;
+    }
+}
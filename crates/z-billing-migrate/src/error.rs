@@ -0,0 +1,23 @@
+//! Error types for store migration.
+
+use z_billing_store::StoreError;
+
+/// Result type for migration operations.
+pub type Result<T> = std::result::Result<T, MigrateError>;
+
+/// Errors that can stop a migration.
+#[derive(Debug, thiserror::Error)]
+pub enum MigrateError {
+    /// Reading the source or writing the target failed.
+    #[error(transparent)]
+    Store(#[from] StoreError),
+
+    /// The checkpoint file could not be read, parsed or written.
+    #[error("checkpoint error: {0}")]
+    Checkpoint(String),
+
+    /// The source holds data the migration does not copy, listed as counts
+    /// per kind.
+    #[error("source holds data the migration does not copy: {0}")]
+    Uncopied(String),
+}
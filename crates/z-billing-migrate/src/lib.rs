@@ -0,0 +1,49 @@
+//! Z-Billing store migration.
+//!
+//! Copies billing data from one `Store` backend to another, for example from
+//! a `RocksStore` to a `PgStore`:
+//!
+//! - **Accounts**, with their balances and lifetime counters
+//! - **Transactions** of all users (and the organizations they bill)
+//! - **Usage events**
+//! - **Processed webhooks**
+//!
+//! Nothing else is copied: credit lots, organization memberships, agent
+//! budgets, promo codes, unredeemed gift cards, given grants and storage
+//! meters would be lost. A source holding any of these is refused with
+//! `MigrateError::Uncopied` before anything is written.
+//!
+//! Each kind is read from the source a page at a time in key order. After
+//! every page the position is written to a checkpoint, so an interrupted
+//! migration resumes where it stopped; items the target already has are
+//! skipped, which makes re-copying the last page harmless.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use z_billing_migrate::Migration;
+//! use z_billing_store::Store;
+//!
+//! # async fn example(source: &dyn Store, target: &dyn Store) -> z_billing_migrate::Result<()> {
+//! let migration = Migration::new(source, target).checkpoint("migrate.checkpoint.json");
+//! let report = migration.run().await?;
+//! println!("{} accounts copied", report.accounts.copied);
+//!
+//! let verification = migration.verify().await?;
+//! assert!(verification.is_ok());
+//! # Ok(())
+//! # }
+//! ```
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+#![warn(clippy::all)]
+#![warn(clippy::pedantic)]
+
+pub mod checkpoint;
+pub mod error;
+pub mod migration;
+
+pub use checkpoint::{Checkpoint, Progress};
+pub use error::{MigrateError, Result};
+pub use migration::{CountCheck, Migration, Verification, DEFAULT_BATCH_SIZE};
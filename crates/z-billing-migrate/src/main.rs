@@ -0,0 +1,220 @@
+//! Z-Billing Migrate - copy billing data between storage backends
+//!
+//! ```text
+//! z-billing-migrate --from rocksdb:/data/z-billing --to postgres://... [options]
+//!
+//! Stores:
+//!   postgres://...     PostgreSQL (migrations are run first)
+//!   sqlite:...         SQLite (needs the sqlite-backend feature)
+//!   rocksdb:<path>     RocksDB (needs the rocksdb-backend feature)
+//!
+//! Options:
+//!   --checkpoint <file>  Progress file to resume from (default: z-billing-migrate.checkpoint.json)
+//!   --batch-size <n>     Items read per page (default: 500)
+//!   --dry-run            Report what would be copied without writing anything
+//!   --no-verify          Skip comparing the target with the source afterwards
+//! ```
+//!
+//! Exits with status 1 if verification finds a difference. Refuses to start
+//! if the source holds data it does not copy, such as credit lots or promo
+//! codes.
+
+use std::process::ExitCode;
+
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use z_billing_migrate::{Checkpoint, Migration, Progress, Verification, DEFAULT_BATCH_SIZE};
+use z_billing_store::Store;
+
+const USAGE: &str = "usage: z-billing-migrate --from <store> --to <store> \
+    [--checkpoint <file>] [--batch-size <n>] [--dry-run] [--no-verify]";
+
+/// Command-line options.
+struct Args {
+    from: String,
+    to: String,
+    checkpoint: String,
+    batch_size: usize,
+    dry_run: bool,
+    verify: bool,
+}
+
+#[tokio::main]
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let args = parse_args(std::env::args().skip(1))?;
+
+    let source = open_store(&args.from).await?;
+    let target = open_store(&args.to).await?;
+
+    let migration = Migration::new(source.as_ref(), target.as_ref())
+        .batch_size(args.batch_size)
+        .dry_run(args.dry_run)
+        .checkpoint(&args.checkpoint);
+
+    let report = migration.run().await?;
+    print_report(&report, args.dry_run);
+
+    if args.dry_run || !args.verify {
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let verification = migration.verify().await?;
+    print_verification(&verification);
+    Ok(if verification.is_ok() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut from = None;
+    let mut to = None;
+    let mut parsed = Args {
+        from: String::new(),
+        to: String::new(),
+        checkpoint: "z-billing-migrate.checkpoint.json".to_string(),
+        batch_size: DEFAULT_BATCH_SIZE,
+        dry_run: false,
+        verify: true,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("{arg} needs a value\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--from" => from = Some(value()?),
+            "--to" => to = Some(value()?),
+            "--checkpoint" => parsed.checkpoint = value()?,
+            "--batch-size" => {
+                parsed.batch_size = value()?
+                    .parse()
+                    .map_err(|e| format!("invalid --batch-size: {e}"))?;
+            }
+            "--dry-run" => parsed.dry_run = true,
+            "--no-verify" => parsed.verify = false,
+            _ => return Err(format!("unknown argument {arg}\n{USAGE}")),
+        }
+    }
+
+    parsed.from = from.ok_or_else(|| format!("--from is required\n{USAGE}"))?;
+    parsed.to = to.ok_or_else(|| format!("--to is required\n{USAGE}"))?;
+    Ok(parsed)
+}
+
+/// Open the store a URL names, running its migrations first.
+async fn open_store(url: &str) -> Result<Box<dyn Store>, Box<dyn std::error::Error>> {
+    if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+        tracing::info!("Connecting to PostgreSQL");
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(4)
+            .connect(url)
+            .await?;
+        sqlx::migrate!("../z-billing-store/migrations")
+            .run(&pool)
+            .await?;
+        return Ok(Box::new(z_billing_store::PgStore::new(pool)));
+    }
+
+    if url.starts_with("sqlite:") {
+        #[cfg(feature = "sqlite-backend")]
+        {
+            use std::str::FromStr;
+
+            use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+
+            tracing::info!("Opening SQLite database");
+            let options = SqliteConnectOptions::from_str(url)?
+                .create_if_missing(true)
+                .journal_mode(SqliteJournalMode::Wal);
+            let pool = SqlitePoolOptions::new()
+                .max_connections(4)
+                .connect_with(options)
+                .await?;
+            sqlx::migrate!("../z-billing-store/migrations-sqlite")
+                .run(&pool)
+                .await?;
+            return Ok(Box::new(z_billing_store::SqliteStore::new(pool)));
+        }
+        #[cfg(not(feature = "sqlite-backend"))]
+        return Err("sqlite: store given but SQLite backend not compiled".into());
+    }
+
+    if let Some(path) = url.strip_prefix("rocksdb:") {
+        #[cfg(feature = "rocksdb-backend")]
+        {
+            tracing::info!(path = %path, "Opening RocksDB store");
+            return Ok(Box::new(z_billing_store::RocksStore::open(path)?));
+        }
+        #[cfg(not(feature = "rocksdb-backend"))]
+        {
+            let _ = path;
+            return Err("rocksdb: store given but RocksDB backend not compiled".into());
+        }
+    }
+
+    Err(format!("unknown store {url}: expected postgres://, sqlite: or rocksdb:\n{USAGE}").into())
+}
+
+fn print_report(report: &Checkpoint, dry_run: bool) {
+    let copied = if dry_run { "would copy" } else { "copied" };
+    println!("{:<16}{copied:>12}{:>12}{:>12}", "", "present", "orphaned");
+    let rows: [(&str, &Progress); 4] = [
+        ("accounts", &report.accounts),
+        ("transactions", &report.transactions),
+        ("usage_events", &report.usage_events),
+        ("webhook_events", &report.webhook_events),
+    ];
+    for (kind, progress) in rows {
+        println!(
+            "{kind:<16}{:>12}{:>12}{:>12}",
+            progress.copied, progress.skipped, progress.orphaned
+        );
+    }
+}
+
+fn print_verification(verification: &Verification) {
+    println!();
+    println!(
+        "{:<16}{:>12}{:>12}{:>12}",
+        "", "source", "orphaned", "target"
+    );
+    for count in &verification.counts {
+        let flag = if count.is_ok() { "" } else { "  MISMATCH" };
+        println!(
+            "{:<16}{:>12}{:>12}{:>12}{flag}",
+            count.kind, count.source, count.orphaned, count.target
+        );
+    }
+    println!(
+        "{:<16}{:>12}{:>12}",
+        "balance_cents", verification.source_balance_cents, verification.target_balance_cents
+    );
+    for user_id in &verification.balance_mismatches {
+        println!("balance mismatch: account {user_id}");
+    }
+    if !verification.ledger.is_consistent() {
+        println!(
+            "target ledger inconsistent: {} wallets drifted, {} cents unbalanced",
+            verification.ledger.drift.len(),
+            verification.ledger.unbalanced_cents
+        );
+    }
+    println!(
+        "verification {}",
+        if verification.is_ok() {
+            "passed"
+        } else {
+            "FAILED"
+        }
+    );
+}
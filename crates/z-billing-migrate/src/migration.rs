@@ -0,0 +1,533 @@
+//! Copying a store to another backend and checking the copy.
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use z_billing_core::{CreditTransaction, LedgerReport, TransactionId, UserId};
+use z_billing_store::{Store, StoreError};
+
+use crate::checkpoint::{Checkpoint, Outcome, Progress};
+use crate::error::{MigrateError, Result};
+
+/// Items read from the source per page unless set otherwise.
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+/// A copy of all billing data from one store to another.
+///
+/// The target should be otherwise unused while the migration runs: items it
+/// already has are skipped rather than compared.
+pub struct Migration<'a> {
+    source: &'a dyn Store,
+    target: &'a dyn Store,
+    batch_size: usize,
+    dry_run: bool,
+    checkpoint_path: Option<PathBuf>,
+}
+
+/// Result of comparing the target against the source after a migration.
+#[derive(Debug, Clone)]
+pub struct Verification {
+    /// Items in the source and target, per kind of data.
+    pub counts: Vec<CountCheck>,
+
+    /// Accounts missing from the target or whose balance or lifetime
+    /// counters differ from the source.
+    pub balance_mismatches: Vec<UserId>,
+
+    /// Sum of all account balances in the source, in cents.
+    pub source_balance_cents: i64,
+
+    /// Sum of all account balances in the target, in cents.
+    pub target_balance_cents: i64,
+
+    /// The target's ledger check (see `Store::verify_ledger`).
+    pub ledger: LedgerReport,
+}
+
+/// Number of items of one kind on either side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountCheck {
+    /// Kind of data (`accounts`, `transactions`, ...).
+    pub kind: &'static str,
+
+    /// Items in the source.
+    pub source: u64,
+
+    /// Source items the migration leaves behind: transactions whose
+    /// account or organization no longer exists.
+    pub orphaned: u64,
+
+    /// Items in the target.
+    pub target: u64,
+}
+
+impl CountCheck {
+    /// Whether the target has every source item that is not orphaned.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.source.saturating_sub(self.orphaned) == self.target
+    }
+}
+
+impl Verification {
+    /// Whether the counts and balances match and the target's ledger is
+    /// consistent.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.counts.iter().all(CountCheck::is_ok)
+            && self.balance_mismatches.is_empty()
+            && self.source_balance_cents == self.target_balance_cents
+            && self.ledger.is_consistent()
+    }
+}
+
+impl<'a> Migration<'a> {
+    /// Create a migration from `source` to `target`.
+    #[must_use]
+    pub fn new(source: &'a dyn Store, target: &'a dyn Store) -> Self {
+        Self {
+            source,
+            target,
+            batch_size: DEFAULT_BATCH_SIZE,
+            dry_run: false,
+            checkpoint_path: None,
+        }
+    }
+
+    /// Set the number of items read from the source per page.
+    #[must_use]
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Only count what would be copied, without writing to the target or
+    /// the checkpoint.
+    #[must_use]
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Resume from, and save progress to, the checkpoint file at `path`.
+    #[must_use]
+    pub fn checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint_path = Some(path.into());
+        self
+    }
+
+    /// Copy everything not yet copied, in the order accounts, transactions,
+    /// usage events, webhook events (transactions need their account).
+    ///
+    /// Returns the final checkpoint, whose counts report what was copied.
+    ///
+    /// # Errors
+    ///
+    /// - `MigrateError::Uncopied` before anything is copied, if the source
+    ///   holds data this migration would leave behind (see
+    ///   `Store::count_unlisted`).
+    /// - Store and checkpoint errors if a store operation fails or the
+    ///   checkpoint cannot be read or written. Progress up to the last full
+    ///   page is kept in the checkpoint.
+    pub async fn run(&self) -> Result<Checkpoint> {
+        let uncopied: Vec<_> = self
+            .source
+            .count_unlisted()
+            .await?
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .map(|(kind, count)| format!("{count} {kind}"))
+            .collect();
+        if !uncopied.is_empty() {
+            return Err(MigrateError::Uncopied(uncopied.join(", ")));
+        }
+
+        let mut checkpoint = match &self.checkpoint_path {
+            Some(path) => Checkpoint::load(path)?,
+            None => Checkpoint::default(),
+        };
+
+        self.copy_accounts(&mut checkpoint).await?;
+        self.copy_transactions(&mut checkpoint).await?;
+        self.copy_usage_events(&mut checkpoint).await?;
+        self.copy_webhook_events(&mut checkpoint).await?;
+
+        Ok(checkpoint)
+    }
+
+    /// Compare the target against the source: item counts, every account's
+    /// balance and lifetime counters, the balance total, and the target's
+    /// ledger.
+    ///
+    /// Orphaned transactions are counted again here rather than taken from
+    /// a checkpoint, so a verification stands on its own.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a store operation fails.
+    pub async fn verify(&self) -> Result<Verification> {
+        let mut balance_mismatches = Vec::new();
+        let mut source_accounts = 0;
+        let mut source_balance_cents = 0;
+        let mut after = None;
+        loop {
+            let page = self
+                .source
+                .list_accounts(after.as_ref(), self.batch_size)
+                .await?;
+            for account in &page {
+                source_accounts += 1;
+                source_balance_cents += account.balance_cents;
+                let matches = self
+                    .target
+                    .get_account(&account.user_id)
+                    .await?
+                    .is_some_and(|copy| {
+                        copy.balance_cents == account.balance_cents
+                            && copy.lifetime_purchased_cents == account.lifetime_purchased_cents
+                            && copy.lifetime_granted_cents == account.lifetime_granted_cents
+                            && copy.lifetime_used_cents == account.lifetime_used_cents
+                    });
+                if !matches {
+                    balance_mismatches.push(account.user_id);
+                }
+            }
+            match page.last() {
+                Some(last) if page.len() == self.batch_size => after = Some(last.user_id),
+                _ => break,
+            }
+        }
+
+        let mut target_accounts = 0;
+        let mut target_balance_cents = 0;
+        let mut after = None;
+        loop {
+            let page = self
+                .target
+                .list_accounts(after.as_ref(), self.batch_size)
+                .await?;
+            target_accounts += page.len() as u64;
+            target_balance_cents += page.iter().map(|a| a.balance_cents).sum::<i64>();
+            match page.last() {
+                Some(last) if page.len() == self.batch_size => after = Some(last.user_id),
+                _ => break,
+            }
+        }
+
+        let batch_size = self.batch_size;
+        let counts = vec![
+            CountCheck {
+                kind: "accounts",
+                source: source_accounts,
+                orphaned: 0,
+                target: target_accounts,
+            },
+            CountCheck {
+                kind: "transactions",
+                source: count_transactions(self.source, batch_size).await?,
+                orphaned: self.count_orphaned_transactions().await?,
+                target: count_transactions(self.target, batch_size).await?,
+            },
+            CountCheck {
+                kind: "usage_events",
+                source: count_usage_events(self.source, batch_size).await?,
+                orphaned: 0,
+                target: count_usage_events(self.target, batch_size).await?,
+            },
+            CountCheck {
+                kind: "webhook_events",
+                source: count_webhook_events(self.source, batch_size).await?,
+                orphaned: 0,
+                target: count_webhook_events(self.target, batch_size).await?,
+            },
+        ];
+
+        Ok(Verification {
+            counts,
+            balance_mismatches,
+            source_balance_cents,
+            target_balance_cents,
+            ledger: self.target.verify_ledger().await?,
+        })
+    }
+
+    async fn copy_accounts(&self, checkpoint: &mut Checkpoint) -> Result<()> {
+        while !checkpoint.accounts.done {
+            let after = parse_cursor::<UserId>(&checkpoint.accounts)?;
+            let page = self
+                .source
+                .list_accounts(after.as_ref(), self.batch_size)
+                .await?;
+
+            for account in &page {
+                let outcome = if self.dry_run {
+                    if self.target.get_account(&account.user_id).await?.is_some() {
+                        Outcome::Skipped
+                    } else {
+                        Outcome::Copied
+                    }
+                } else {
+                    // Posts the balance to the ledger as an opening balance
+                    match self.target.create_account(account).await {
+                        Ok(()) => Outcome::Copied,
+                        Err(StoreError::VersionConflict { .. }) => Outcome::Skipped,
+                        Err(e) => return Err(e.into()),
+                    }
+                };
+                checkpoint.accounts.record(outcome);
+            }
+
+            let last_key = page.last().map(|account| account.user_id.to_string());
+            self.finish_page(
+                checkpoint,
+                "accounts",
+                |c| &mut c.accounts,
+                last_key,
+                page.len(),
+            )?;
+        }
+        Ok(())
+    }
+
+    async fn copy_transactions(&self, checkpoint: &mut Checkpoint) -> Result<()> {
+        while !checkpoint.transactions.done {
+            let after = parse_cursor::<TransactionId>(&checkpoint.transactions)?;
+            let page = self
+                .source
+                .list_transactions(after.as_ref(), self.batch_size)
+                .await?;
+
+            for tx in &page {
+                let outcome = self.copy_transaction(tx).await?;
+                checkpoint.transactions.record(outcome);
+            }
+
+            let last_key = page.last().map(|tx| tx.id.to_string());
+            self.finish_page(
+                checkpoint,
+                "transactions",
+                |c| &mut c.transactions,
+                last_key,
+                page.len(),
+            )?;
+        }
+        Ok(())
+    }
+
+    async fn copy_transaction(&self, tx: &CreditTransaction) -> Result<Outcome> {
+        if self.target.get_transaction(&tx.id).await?.is_some() {
+            return Ok(Outcome::Skipped);
+        }
+
+        if self.is_orphaned(tx).await? {
+            tracing::warn!(transaction_id = %tx.id, user_id = %tx.user_id, "Skipping transaction of deleted account or organization");
+            return Ok(Outcome::Orphaned);
+        }
+        if let Some(org_id) = tx.org_id {
+            if self.target.get_organization(&org_id).await?.is_none() {
+                if let Some(org) = self.source.get_organization(&org_id).await? {
+                    if !self.dry_run {
+                        self.target.put_organization(&org).await?;
+                    }
+                }
+            }
+        }
+
+        if !self.dry_run {
+            self.target.put_transaction(tx).await?;
+        }
+        Ok(Outcome::Copied)
+    }
+
+    /// Whether a transaction's account or organization is gone, so the
+    /// target cannot take it.
+    ///
+    /// Backends without foreign keys keep the transactions of deleted
+    /// accounts; the SQL backends cannot take them.
+    async fn is_orphaned(&self, tx: &CreditTransaction) -> Result<bool> {
+        if self.source.get_account(&tx.user_id).await?.is_none() {
+            return Ok(true);
+        }
+        let Some(org_id) = tx.org_id else {
+            return Ok(false);
+        };
+        Ok(self.target.get_organization(&org_id).await?.is_none()
+            && self.source.get_organization(&org_id).await?.is_none())
+    }
+
+    /// Count the source transactions `copy_transaction` leaves behind.
+    async fn count_orphaned_transactions(&self) -> Result<u64> {
+        let mut orphaned = 0;
+        let mut after = None;
+        loop {
+            let page = self
+                .source
+                .list_transactions(after.as_ref(), self.batch_size)
+                .await?;
+            for tx in &page {
+                if self.is_orphaned(tx).await? {
+                    orphaned += 1;
+                }
+            }
+            match page.last() {
+                Some(last) if page.len() == self.batch_size => after = Some(last.id),
+                _ => return Ok(orphaned),
+            }
+        }
+    }
+
+    async fn copy_usage_events(&self, checkpoint: &mut Checkpoint) -> Result<()> {
+        while !checkpoint.usage_events.done {
+            let page = self
+                .source
+                .list_usage_events(checkpoint.usage_events.after.as_deref(), self.batch_size)
+                .await?;
+
+            for event in &page {
+                let outcome = if self.target.has_usage_event(&event.event_id).await? {
+                    Outcome::Skipped
+                } else {
+                    if !self.dry_run {
+                        self.target.put_usage_event(event).await?;
+                    }
+                    Outcome::Copied
+                };
+                checkpoint.usage_events.record(outcome);
+            }
+
+            let last_key = page.last().map(|event| event.event_id.clone());
+            self.finish_page(
+                checkpoint,
+                "usage_events",
+                |c| &mut c.usage_events,
+                last_key,
+                page.len(),
+            )?;
+        }
+        Ok(())
+    }
+
+    async fn copy_webhook_events(&self, checkpoint: &mut Checkpoint) -> Result<()> {
+        while !checkpoint.webhook_events.done {
+            let page = self
+                .source
+                .list_webhook_events(checkpoint.webhook_events.after.as_deref(), self.batch_size)
+                .await?;
+
+            for webhook in &page {
+                let outcome = if self.target.has_webhook_event(&webhook.event_id).await? {
+                    Outcome::Skipped
+                } else {
+                    if !self.dry_run {
+                        self.target
+                            .record_webhook_event(&webhook.event_id, &webhook.source)
+                            .await?;
+                    }
+                    Outcome::Copied
+                };
+                checkpoint.webhook_events.record(outcome);
+            }
+
+            let last_key = page.last().map(|webhook| webhook.event_id.clone());
+            self.finish_page(
+                checkpoint,
+                "webhook_events",
+                |c| &mut c.webhook_events,
+                last_key,
+                page.len(),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Advance the `kind` progress past a page and save the checkpoint.
+    fn finish_page(
+        &self,
+        checkpoint: &mut Checkpoint,
+        kind: &str,
+        progress: fn(&mut Checkpoint) -> &mut Progress,
+        last_key: Option<String>,
+        len: usize,
+    ) -> Result<()> {
+        let progress = progress(checkpoint);
+        progress.advance(last_key, len, self.batch_size);
+        tracing::info!(
+            kind,
+            copied = progress.copied,
+            skipped = progress.skipped,
+            orphaned = progress.orphaned,
+            done = progress.done,
+            dry_run = self.dry_run,
+            "Migrated page"
+        );
+
+        match &self.checkpoint_path {
+            Some(path) if !self.dry_run => checkpoint.save(path),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parse the key a checkpoint resumes after.
+fn parse_cursor<K: FromStr>(progress: &Progress) -> Result<Option<K>>
+where
+    K::Err: std::fmt::Display,
+{
+    progress
+        .after
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e: K::Err| MigrateError::Checkpoint(format!("invalid cursor: {e}")))
+}
+
+/// Count items by paging through a listing. `page` fetches the page after
+/// a key and returns its length and last key.
+async fn count_pages<K, F, Fut>(batch_size: usize, mut page: F) -> Result<u64>
+where
+    F: FnMut(Option<K>) -> Fut,
+    Fut: Future<Output = z_billing_store::Result<(usize, Option<K>)>>,
+{
+    let mut total = 0;
+    let mut after = None;
+    loop {
+        let (len, last) = page(after).await?;
+        total += len as u64;
+        if len < batch_size {
+            return Ok(total);
+        }
+        after = last;
+    }
+}
+
+async fn count_transactions(store: &dyn Store, batch_size: usize) -> Result<u64> {
+    count_pages(batch_size, |after: Option<TransactionId>| async move {
+        let page = store.list_transactions(after.as_ref(), batch_size).await?;
+        Ok((page.len(), page.last().map(|tx| tx.id)))
+    })
+    .await
+}
+
+async fn count_usage_events(store: &dyn Store, batch_size: usize) -> Result<u64> {
+    count_pages(batch_size, |after: Option<String>| async move {
+        let page = store
+            .list_usage_events(after.as_deref(), batch_size)
+            .await?;
+        Ok((page.len(), page.last().map(|event| event.event_id.clone())))
+    })
+    .await
+}
+
+async fn count_webhook_events(store: &dyn Store, batch_size: usize) -> Result<u64> {
+    count_pages(batch_size, |after: Option<String>| async move {
+        let page = store
+            .list_webhook_events(after.as_deref(), batch_size)
+            .await?;
+        Ok((
+            page.len(),
+            page.last().map(|webhook| webhook.event_id.clone()),
+        ))
+    })
+    .await
+}
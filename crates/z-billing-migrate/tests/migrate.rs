@@ -0,0 +1,197 @@
+//! Store migration tests.
+
+use z_billing_core::{
+    Account, CreditTransaction, Organization, UsageEvent, UsageMetric, UsageSource, UserId,
+};
+use z_billing_migrate::{Checkpoint, MigrateError, Migration};
+use z_billing_store::{MemoryStore, Store};
+
+/// A source store with three funded accounts, their purchases and usage,
+/// an organization-billed transaction and two processed webhooks.
+///
+/// Purchases are recorded without credit lots, which the migration refuses.
+async fn populated_store() -> (MemoryStore, Vec<UserId>) {
+    let store = MemoryStore::new();
+    let mut user_ids = Vec::new();
+    for i in 0..3 {
+        let user_id = UserId::generate();
+        let mut account = Account::new(user_id);
+        account.balance_cents = 1000 + 100 * i;
+        account.lifetime_purchased_cents = 1000;
+        store.create_account(&account).await.unwrap();
+
+        let tx = CreditTransaction::purchase(user_id, 1000, 1000 + 100 * i, "Purchase".into());
+        store.put_transaction(&tx).await.unwrap();
+
+        let event = UsageEvent {
+            event_id: format!("evt-{user_id}"),
+            user_id,
+            agent_id: None,
+            source: UsageSource::AuraRuntime,
+            metric: UsageMetric::ApiCalls {
+                endpoint: "test".to_string(),
+            },
+            quantity: 1.0,
+            cost_cents: 25,
+            timestamp: chrono::Utc::now(),
+            metadata: serde_json::Value::Null,
+            transaction_id: None,
+        };
+        let tx = CreditTransaction::usage(
+            user_id,
+            25,
+            975 + 100 * i,
+            "Usage".into(),
+            serde_json::json!({}),
+        );
+        store.process_usage(&event, &tx, &[]).await.unwrap();
+        user_ids.push(user_id);
+    }
+
+    let mut org = Organization::new("Acme".into());
+    org.balance_cents = 5000;
+    store.put_organization(&org).await.unwrap();
+    let tx = CreditTransaction::usage(
+        user_ids[0],
+        10,
+        4990,
+        "Org usage".into(),
+        serde_json::json!({}),
+    )
+    .with_org(org.id);
+    store.put_transaction(&tx).await.unwrap();
+
+    store
+        .record_webhook_event("evt_stripe_1", "stripe")
+        .await
+        .unwrap();
+    store
+        .record_webhook_event("evt_lago_1", "lago")
+        .await
+        .unwrap();
+
+    (store, user_ids)
+}
+
+#[tokio::test]
+async fn copies_everything_and_verifies() {
+    let (source, user_ids) = populated_store().await;
+    let target = MemoryStore::new();
+
+    let migration = Migration::new(&source, &target).batch_size(2);
+    let report = migration.run().await.unwrap();
+
+    assert!(report.is_done());
+    assert_eq!(report.accounts.copied, 3);
+    assert_eq!(report.transactions.copied, 7);
+    assert_eq!(report.usage_events.copied, 3);
+    assert_eq!(report.webhook_events.copied, 2);
+
+    for user_id in &user_ids {
+        let original = source.get_account(user_id).await.unwrap().unwrap();
+        let copy = target.get_account(user_id).await.unwrap().unwrap();
+        assert_eq!(copy.balance_cents, original.balance_cents);
+        assert_eq!(
+            copy.lifetime_purchased_cents,
+            original.lifetime_purchased_cents
+        );
+        assert_eq!(copy.lifetime_used_cents, original.lifetime_used_cents);
+    }
+    assert!(target.has_webhook_event("evt_lago_1").await.unwrap());
+
+    let verification = migration.verify().await.unwrap();
+    assert!(verification.is_ok(), "{verification:?}");
+}
+
+#[tokio::test]
+async fn dry_run_reports_without_writing() {
+    let (source, _) = populated_store().await;
+    let target = MemoryStore::new();
+    let dir = tempfile::tempdir().unwrap();
+    let checkpoint = dir.path().join("checkpoint.json");
+
+    let report = Migration::new(&source, &target)
+        .dry_run(true)
+        .checkpoint(&checkpoint)
+        .run()
+        .await
+        .unwrap();
+
+    assert_eq!(report.accounts.copied, 3);
+    assert_eq!(report.transactions.copied, 7);
+    assert!(target.list_accounts(None, 10).await.unwrap().is_empty());
+    assert!(target.list_transactions(None, 10).await.unwrap().is_empty());
+    assert!(!checkpoint.exists());
+}
+
+#[tokio::test]
+async fn resumes_from_checkpoint_without_copying_twice() {
+    let (source, _) = populated_store().await;
+    let target = MemoryStore::new();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("checkpoint.json");
+    let migration = Migration::new(&source, &target)
+        .batch_size(2)
+        .checkpoint(&path);
+
+    let first = migration.run().await.unwrap();
+    assert_eq!(Checkpoint::load(&path).unwrap(), first);
+
+    // A finished migration has nothing left to do
+    let second = migration.run().await.unwrap();
+    assert_eq!(second, first);
+
+    // Interrupted before the transaction progress was saved: the pages are
+    // read again and what the target already has is skipped
+    let mut interrupted = first.clone();
+    interrupted.transactions = z_billing_migrate::Progress::default();
+    interrupted.save(&path).unwrap();
+    let resumed = migration.run().await.unwrap();
+    assert_eq!(resumed.transactions.copied, 0);
+    assert_eq!(resumed.transactions.skipped, 7);
+
+    assert!(migration.verify().await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn transactions_of_deleted_accounts_are_reported() {
+    let (source, _) = populated_store().await;
+    let gone = UserId::generate();
+    let tx = CreditTransaction::purchase(gone, 500, 500, "Purchase".into());
+    source.put_transaction(&tx).await.unwrap();
+    let target = MemoryStore::new();
+
+    let migration = Migration::new(&source, &target);
+    let report = migration.run().await.unwrap();
+    assert_eq!(report.transactions.orphaned, 1);
+    assert!(target.get_transaction(&tx.id).await.unwrap().is_none());
+
+    // Left behind on purpose, so the counts still match
+    let verification = migration.verify().await.unwrap();
+    assert!(verification.is_ok(), "{verification:?}");
+    let transactions = verification
+        .counts
+        .iter()
+        .find(|count| count.kind == "transactions")
+        .unwrap();
+    assert_eq!(transactions.orphaned, 1);
+    assert_eq!(transactions.source, transactions.target + 1);
+}
+
+#[tokio::test]
+async fn refuses_sources_with_data_it_does_not_copy() {
+    let (source, user_ids) = populated_store().await;
+    let tx = CreditTransaction::purchase(user_ids[0], 500, 1500, "Purchase".into());
+    source
+        .add_credits(&user_ids[0], 500, &tx, &[])
+        .await
+        .unwrap();
+    let target = MemoryStore::new();
+
+    let result = Migration::new(&source, &target).run().await;
+    assert!(
+        matches!(&result, Err(MigrateError::Uncopied(kinds)) if kinds == "1 credit_lots"),
+        "{result:?}"
+    );
+    assert!(target.list_accounts(None, 10).await.unwrap().is_empty());
+}
@@ -10,7 +10,8 @@ pub struct ServiceConfig {
     /// Address to listen on (default: "0.0.0.0:8080").
     pub listen_addr: String,
 
-    /// PostgreSQL connection string (preferred over RocksDB).
+    /// `PostgreSQL` connection string, or a `sqlite://` URL with the
+    /// `sqlite-backend` feature (preferred over `RocksDB`).
     pub database_url: Option<String>,
 
     /// Path to `RocksDB` data directory (fallback if DATABASE_URL not set).
@@ -58,9 +59,17 @@ pub struct ServiceConfig {
     /// Request timeout in seconds.
     pub request_timeout_seconds: u64,
 
-    /// Pricing configuration.
+    /// Built-in pricing, used until a catalog is loaded.
     pub pricing: PricingConfig,
 
+    /// Pricing catalog file (TOML, or JSON if it ends in `.json`). When
+    /// unset, the newest catalog saved in the store is used.
+    pub pricing_catalog_path: Option<String>,
+
+    /// How often to re-read the pricing catalog in seconds (default: 60,
+    /// 0 = only on startup and on request).
+    pub pricing_reload_seconds: u64,
+
     /// Lago organization ID (for reference).
     pub lago_organization_id: Option<String>,
 
@@ -75,6 +84,9 @@ pub struct ServiceConfig {
 
     /// Anthropic Admin API key used for authoritative daily cost sync.
     pub anthropic_admin_api_key: Option<String>,
+
+    /// Default lifetime of a credit reservation in seconds (default: 300).
+    pub reservation_ttl_seconds: u64,
 }
 
 /// Lago secrets file structure.
@@ -141,6 +153,13 @@ impl ServiceConfig {
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(30),
             pricing: PricingConfig::default(),
+            pricing_catalog_path: std::env::var("PRICING_CATALOG_PATH")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            pricing_reload_seconds: std::env::var("PRICING_RELOAD_SECONDS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(60),
             zos_api_url: std::env::var("ZOS_API_URL").ok().filter(|s| !s.is_empty()),
             zos_api_internal_token: std::env::var("ZOS_API_INTERNAL_TOKEN")
                 .ok()
@@ -151,6 +170,10 @@ impl ServiceConfig {
             anthropic_admin_api_key: std::env::var("ANTHROPIC_ADMIN_API_KEY")
                 .ok()
                 .filter(|s| !s.is_empty()),
+            reservation_ttl_seconds: std::env::var("RESERVATION_TTL_SECONDS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(300),
         }
     }
 }
@@ -262,10 +285,13 @@ impl Default for ServiceConfig {
             max_body_bytes: 1024 * 1024,
             request_timeout_seconds: 30,
             pricing: PricingConfig::default(),
+            pricing_catalog_path: None,
+            pricing_reload_seconds: 60,
             zos_api_url: None,
             zos_api_internal_token: None,
             mixpanel_token: None,
             anthropic_admin_api_key: None,
+            reservation_ttl_seconds: 300,
         }
     }
 }
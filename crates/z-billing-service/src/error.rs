@@ -37,6 +37,34 @@ pub enum ApiError {
         required: i64,
     },
 
+    /// Organization member monthly spend cap exceeded.
+    #[error("spend cap exceeded: cap={cap}, spent={spent}, required={required}")]
+    SpendCapExceeded {
+        /// Member's monthly cap.
+        cap: i64,
+        /// Amount already spent this month.
+        spent: i64,
+        /// Required amount.
+        required: i64,
+    },
+
+    /// Agent budget exceeded.
+    #[error(
+        "agent budget exceeded: agent={agent_id}, period={period}, limit={limit}, spent={spent}, required={required}"
+    )]
+    BudgetExceeded {
+        /// Agent whose budget was hit.
+        agent_id: String,
+        /// Budget period that was hit.
+        period: &'static str,
+        /// Budget limit.
+        limit: i64,
+        /// Amount already spent in the period.
+        spent: i64,
+        /// Required amount.
+        required: i64,
+    },
+
     /// Duplicate event (idempotency).
     #[error("duplicate event: {0}")]
     DuplicateEvent(String),
@@ -86,6 +114,38 @@ impl IntoResponse for ApiError {
                     "required": required
                 })),
             ),
+            Self::SpendCapExceeded {
+                cap,
+                spent,
+                required,
+            } => (
+                StatusCode::PAYMENT_REQUIRED,
+                "spend_cap_exceeded",
+                self.to_string(),
+                Some(serde_json::json!({
+                    "cap": cap,
+                    "spent": spent,
+                    "required": required
+                })),
+            ),
+            Self::BudgetExceeded {
+                agent_id,
+                period,
+                limit,
+                spent,
+                required,
+            } => (
+                StatusCode::PAYMENT_REQUIRED,
+                "budget_exceeded",
+                self.to_string(),
+                Some(serde_json::json!({
+                    "agent_id": agent_id,
+                    "period": period,
+                    "limit": limit,
+                    "spent": spent,
+                    "required": required
+                })),
+            ),
             Self::DuplicateEvent(id) => (
                 StatusCode::CONFLICT,
                 "duplicate_event",
@@ -130,11 +190,67 @@ impl From<z_billing_store::StoreError> for ApiError {
             z_billing_store::StoreError::InsufficientCredits { balance, required } => {
                 Self::InsufficientCredits { balance, required }
             }
+            z_billing_store::StoreError::SpendCapExceeded {
+                cap,
+                spent,
+                required,
+            } => Self::SpendCapExceeded {
+                cap,
+                spent,
+                required,
+            },
+            z_billing_store::StoreError::BudgetExceeded {
+                agent_id,
+                period,
+                limit,
+                spent,
+                required,
+            } => Self::BudgetExceeded {
+                agent_id: agent_id.to_string(),
+                period: period.as_str(),
+                limit,
+                spent,
+                required,
+            },
+            z_billing_store::StoreError::ReversalExceedsCharge {
+                charged,
+                reversed,
+                requested,
+            } => Self::Conflict(format!(
+                "Reversal of {requested} cents exceeds remaining charge: charged={charged}, reversed={reversed}"
+            )),
+            z_billing_store::StoreError::TransferExceedsPurchased {
+                transferable,
+                requested,
+            } => Self::Conflict(format!(
+                "Only purchased credits can be transferred: transferable={transferable}, requested={requested}"
+            )),
+            z_billing_store::StoreError::PromoRejected { code, reason } => Self::Conflict(format!(
+                "Promo code {code} cannot be redeemed: {}",
+                reason.as_str()
+            )),
             z_billing_store::StoreError::DuplicateEvent { event_id } => {
                 Self::DuplicateEvent(event_id)
             }
+            z_billing_store::StoreError::InvalidState { entity, id, state } => {
+                Self::Conflict(format!("{entity} {id} is {state}"))
+            }
+            z_billing_store::StoreError::VersionConflict { entity, id, .. } => {
+                Self::Conflict(format!("{entity} {id} was modified concurrently, retry"))
+            }
             z_billing_store::StoreError::Database(msg)
             | z_billing_store::StoreError::Serialization(msg) => Self::Internal(msg),
         }
     }
 }
+
+impl From<crate::pricing::ReloadError> for ApiError {
+    fn from(err: crate::pricing::ReloadError) -> Self {
+        match err {
+            crate::pricing::ReloadError::Catalog(e) => Self::BadRequest(e.to_string()),
+            crate::pricing::ReloadError::Store(e) => e.into(),
+            crate::pricing::ReloadError::Unversioned(_) => Self::Conflict(err.to_string()),
+            crate::pricing::ReloadError::Io { .. } => Self::Internal(err.to_string()),
+        }
+    }
+}
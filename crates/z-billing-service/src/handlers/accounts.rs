@@ -2,14 +2,14 @@
 
 use std::sync::Arc;
 
-use axum::extract::State;
+use axum::extract::{Path, State};
 use axum::Json;
 use serde::{Deserialize, Serialize};
 
-use z_billing_core::Account;
-use z_billing_store::Store;
+use z_billing_core::{Account, OutboxMessage, UserId};
+use z_billing_store::{Store, StoreError};
 
-use crate::auth::AuthUser;
+use crate::auth::{AdminAuth, AuthUser};
 use crate::error::ApiError;
 use crate::lago::{BillingConfiguration, CustomerInput};
 use crate::state::AppState;
@@ -61,6 +61,125 @@ pub struct CreateAccountRequest {
     pub email: Option<String>,
 }
 
+/// Read-modify-write attempts before a contended account update gives up.
+const ACCOUNT_UPDATE_ATTEMPTS: usize = 5;
+
+/// Get an account by user ID, creating it with zero balance if it doesn't exist.
+pub async fn get_or_create_account(
+    store: &dyn Store,
+    user_id: &UserId,
+) -> Result<Account, ApiError> {
+    if let Some(account) = store.get_account(user_id).await? {
+        return Ok(account);
+    }
+
+    // Auto-create account with zero balance
+    let mut account = Account::new(*user_id);
+    match store.put_account_if_version(&account, 0, &[]).await {
+        Ok(version) => {
+            account.version = version;
+            tracing::info!(user_id = %user_id, "Auto-created billing account");
+            Ok(account)
+        }
+        // Another request created it first
+        Err(StoreError::VersionConflict { .. }) => store
+            .get_account(user_id)
+            .await?
+            .ok_or_else(|| ApiError::NotFound("Account not found".into())),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Apply `update` to a stored account and write it back.
+///
+/// The write only lands if nobody else wrote the account since it was read;
+/// otherwise the account is read again and `update` re-applied, so `update`
+/// may run more than once. Returns the account as written, or `None` if it
+/// does not exist.
+pub async fn update_account(
+    store: &dyn Store,
+    user_id: &UserId,
+    update: impl FnMut(&mut Account),
+) -> Result<Option<Account>, ApiError> {
+    let written = write_account(store, user_id, false, |_| Vec::new(), update).await?;
+    Ok(written.map(|(account, _)| account))
+}
+
+/// Like [`update_account`], but also writes the outbox messages `outbox`
+/// builds from the updated account, in the same write. Returns the account
+/// and the messages written with it.
+pub async fn update_account_with_outbox(
+    store: &dyn Store,
+    user_id: &UserId,
+    outbox: impl Fn(&Account) -> Vec<OutboxMessage>,
+    update: impl FnMut(&mut Account),
+) -> Result<Option<(Account, Vec<OutboxMessage>)>, ApiError> {
+    write_account(store, user_id, false, outbox, update).await
+}
+
+/// Like [`update_account`], but creates the account with zero balance first
+/// if it doesn't exist.
+pub async fn upsert_account(
+    store: &dyn Store,
+    user_id: &UserId,
+    update: impl FnMut(&mut Account),
+) -> Result<Account, ApiError> {
+    let (account, _) = upsert_account_with_outbox(store, user_id, |_| Vec::new(), update).await?;
+    Ok(account)
+}
+
+/// Like [`update_account_with_outbox`], but creates the account with zero
+/// balance first if it doesn't exist.
+pub async fn upsert_account_with_outbox(
+    store: &dyn Store,
+    user_id: &UserId,
+    outbox: impl Fn(&Account) -> Vec<OutboxMessage>,
+    update: impl FnMut(&mut Account),
+) -> Result<(Account, Vec<OutboxMessage>), ApiError> {
+    write_account(store, user_id, true, outbox, update)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Account not found".into()))
+}
+
+async fn write_account(
+    store: &dyn Store,
+    user_id: &UserId,
+    create: bool,
+    outbox: impl Fn(&Account) -> Vec<OutboxMessage>,
+    mut update: impl FnMut(&mut Account),
+) -> Result<Option<(Account, Vec<OutboxMessage>)>, ApiError> {
+    let mut last_conflict = None;
+    for _ in 0..ACCOUNT_UPDATE_ATTEMPTS {
+        let mut account = match store.get_account(user_id).await? {
+            Some(account) => account,
+            None if create => Account::new(*user_id),
+            None => return Ok(None),
+        };
+        let expected_version = account.version;
+        update(&mut account);
+        account.updated_at = chrono::Utc::now();
+        let messages = outbox(&account);
+
+        match store
+            .put_account_if_version(&account, expected_version, &messages)
+            .await
+        {
+            Ok(version) => {
+                account.version = version;
+                return Ok(Some((account, messages)));
+            }
+            Err(e @ StoreError::VersionConflict { .. }) => last_conflict = Some(e),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    tracing::warn!(user_id = %user_id, "Gave up updating contended account");
+    Err(last_conflict.map_or_else(
+        || ApiError::Conflict("Account was modified concurrently, retry".into()),
+        ApiError::from,
+    ))
+}
+
 /// Create or register a new account.
 pub async fn create_account(
     State(state): State<Arc<AppState>>,
@@ -68,7 +187,7 @@ pub async fn create_account(
     Json(body): Json<CreateAccountRequest>,
 ) -> Result<Json<AccountResponse>, ApiError> {
     // Check if account already exists
-    if state.store.get_account(&auth.user_id)?.is_some() {
+    if state.store.get_account(&auth.user_id).await?.is_some() {
         return Err(ApiError::Conflict("Account already exists".into()));
     }
 
@@ -144,7 +263,14 @@ pub async fn create_account(
         }
     }
 
-    state.store.put_account(&account)?;
+    match state.store.put_account_if_version(&account, 0, &[]).await {
+        Ok(version) => account.version = version,
+        // Created by a concurrent request while the customers were set up
+        Err(StoreError::VersionConflict { .. }) => {
+            return Err(ApiError::Conflict("Account already exists".into()));
+        }
+        Err(e) => return Err(e.into()),
+    }
 
     tracing::info!(user_id = %auth.user_id, "Account created");
 
@@ -156,15 +282,7 @@ pub async fn get_account(
     State(state): State<Arc<AppState>>,
     auth: AuthUser,
 ) -> Result<Json<AccountResponse>, ApiError> {
-    let account = match state.store.get_account(&auth.user_id)? {
-        Some(account) => account,
-        None => {
-            let account = Account::new(auth.user_id);
-            state.store.put_account(&account)?;
-            tracing::info!(user_id = %auth.user_id, "Auto-created billing account on first access");
-            account
-        }
-    };
+    let account = get_or_create_account(state.store.as_ref(), &auth.user_id).await?;
 
     Ok(Json(AccountResponse::from(&account)))
 }
@@ -174,9 +292,110 @@ pub async fn delete_account(
     State(state): State<Arc<AppState>>,
     auth: AuthUser,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    state.store.delete_account(&auth.user_id)?;
+    state.store.delete_account(&auth.user_id).await?;
 
     tracing::info!(user_id = %auth.user_id, "Account deleted");
 
     Ok(Json(serde_json::json!({ "deleted": true })))
 }
+
+/// Credit limit response.
+#[derive(Debug, Serialize)]
+pub struct CreditLimitResponse {
+    /// User ID.
+    pub user_id: String,
+    /// How far usage may take the balance below zero, in cents.
+    pub credit_limit_cents: i64,
+    /// Current balance in cents.
+    pub balance_cents: i64,
+    /// Whether the balance is below zero.
+    pub overdrawn: bool,
+    /// Whether usage is blocked until the balance is back above zero.
+    pub overdraft_locked: bool,
+}
+
+impl From<&Account> for CreditLimitResponse {
+    fn from(account: &Account) -> Self {
+        Self {
+            user_id: account.user_id.to_string(),
+            credit_limit_cents: account.credit_limit_cents,
+            balance_cents: account.balance_cents,
+            overdrawn: account.is_overdrawn(),
+            overdraft_locked: account.overdraft_locked,
+        }
+    }
+}
+
+/// Set credit limit request.
+#[derive(Debug, Deserialize)]
+pub struct SetCreditLimitRequest {
+    /// How far usage may take the balance below zero, in cents (0 disables).
+    pub credit_limit_cents: i64,
+}
+
+/// Admin endpoint to get an account's credit limit and overdraft state.
+///
+/// Requires `X-Admin-Key` header for authentication.
+pub async fn admin_get_credit_limit(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminAuth,
+    Path(user_id): Path<String>,
+) -> Result<Json<CreditLimitResponse>, ApiError> {
+    let user_id = user_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid user ID".into()))?;
+
+    let account = state
+        .store
+        .get_account(&user_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
+
+    Ok(Json(CreditLimitResponse::from(&account)))
+}
+
+/// Admin endpoint to set how far an account's balance may go below zero.
+///
+/// Changing the limit re-checks the overdraft lock against the new limit, so
+/// raising it reopens the credit line of an account that hit the old one.
+///
+/// Requires `X-Admin-Key` header for authentication.
+pub async fn admin_set_credit_limit(
+    State(state): State<Arc<AppState>>,
+    admin: AdminAuth,
+    Path(user_id): Path<String>,
+    Json(body): Json<SetCreditLimitRequest>,
+) -> Result<Json<CreditLimitResponse>, ApiError> {
+    let user_id = user_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid user ID".into()))?;
+
+    if body.credit_limit_cents < 0 {
+        return Err(ApiError::BadRequest(
+            "credit_limit_cents must not be negative".into(),
+        ));
+    }
+
+    // Broadcast the overdraft state, which may have changed with the limit
+    let (account, outbox) = update_account_with_outbox(
+        state.store.as_ref(),
+        &user_id,
+        |account| vec![crate::outbox::balance_update(&account.user_id)],
+        |account| account.credit_limit_cents = body.credit_limit_cents,
+    )
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
+    crate::outbox::committed(&state, &outbox).await;
+
+    let account = state.store.get_account(&user_id).await?.unwrap_or(account);
+
+    tracing::info!(
+        admin_id = %admin.admin_id,
+        user_id = %user_id,
+        credit_limit_cents = %account.credit_limit_cents,
+        overdraft_locked = %account.overdraft_locked,
+        "Admin set credit limit"
+    );
+
+    Ok(Json(CreditLimitResponse::from(&account)))
+}
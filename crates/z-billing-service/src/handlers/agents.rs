@@ -0,0 +1,226 @@
+//! Agent budget and spend handlers.
+//!
+//! Users cap how much each of their agents may spend per day, per calendar
+//! month (UTC), or over its lifetime. Usage that would exceed a budget is
+//! rejected with `budget_exceeded`.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use z_billing_core::{AgentBudget, AgentId, AgentSpend, BudgetPeriod, UserId};
+use z_billing_store::Store;
+
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Budget with its current usage.
+#[derive(Debug, Serialize)]
+pub struct AgentBudgetResponse {
+    /// Budget period ("daily", "monthly" or "lifetime").
+    pub period: &'static str,
+    /// Budget limit in cents.
+    pub limit_cents: i64,
+    /// Amount spent in the current period in cents.
+    pub spent_cents: i64,
+    /// Amount left in the current period in cents.
+    pub remaining_cents: i64,
+}
+
+/// Agent spend summary.
+#[derive(Debug, Serialize)]
+pub struct AgentSpendResponse {
+    /// Agent ID.
+    pub agent_id: String,
+    /// Spend today (UTC) in cents.
+    pub today_cents: i64,
+    /// Spend this calendar month (UTC) in cents.
+    pub month_cents: i64,
+    /// Lifetime spend in cents.
+    pub lifetime_cents: i64,
+    /// The agent's budgets.
+    pub budgets: Vec<AgentBudgetResponse>,
+}
+
+/// List agent spend response.
+#[derive(Debug, Serialize)]
+pub struct ListAgentSpendResponse {
+    /// Every agent that has spent from the user's balance.
+    pub agents: Vec<AgentSpendResponse>,
+}
+
+/// Set budget request.
+#[derive(Debug, Deserialize)]
+pub struct SetAgentBudgetRequest {
+    /// Budget limit in cents.
+    pub limit_cents: i64,
+}
+
+fn parse_agent_id(agent_id: &str) -> Result<AgentId, ApiError> {
+    agent_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid agent ID".into()))
+}
+
+fn parse_period(period: &str) -> Result<BudgetPeriod, ApiError> {
+    period.parse().map_err(ApiError::BadRequest)
+}
+
+/// Build the summary for one agent from its totals and budgets.
+async fn spend_response(
+    store: &dyn Store,
+    user_id: &UserId,
+    agent_id: AgentId,
+    totals: Option<AgentSpend>,
+) -> Result<AgentSpendResponse, ApiError> {
+    let now = chrono::Utc::now();
+    let totals = totals.unwrap_or_else(|| AgentSpend::new(*user_id, agent_id, now));
+
+    let budgets = store
+        .list_agent_budgets(user_id, &agent_id)
+        .await?
+        .into_iter()
+        .map(|budget| {
+            let spent_cents = totals.spent_in(budget.period, now);
+            AgentBudgetResponse {
+                period: budget.period.as_str(),
+                limit_cents: budget.limit_cents,
+                spent_cents,
+                remaining_cents: (budget.limit_cents - spent_cents).max(0),
+            }
+        })
+        .collect();
+
+    Ok(AgentSpendResponse {
+        agent_id: agent_id.to_string(),
+        today_cents: totals.spent_in(BudgetPeriod::Daily, now),
+        month_cents: totals.spent_in(BudgetPeriod::Monthly, now),
+        lifetime_cents: totals.spent_in(BudgetPeriod::Lifetime, now),
+        budgets,
+    })
+}
+
+/// Get one agent's spend and budgets.
+pub async fn get_agent_spend(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(agent_id): Path<String>,
+) -> Result<Json<AgentSpendResponse>, ApiError> {
+    let agent_id = parse_agent_id(&agent_id)?;
+    let totals = state
+        .store
+        .get_agent_spend(&auth.user_id, &agent_id)
+        .await?;
+
+    Ok(Json(
+        spend_response(state.store.as_ref(), &auth.user_id, agent_id, totals).await?,
+    ))
+}
+
+/// List spend and budgets for every agent that has spent from the user's
+/// balance.
+pub async fn list_agent_spend(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<Json<ListAgentSpendResponse>, ApiError> {
+    let mut agents = Vec::new();
+    for totals in state.store.list_agent_spend(&auth.user_id).await? {
+        agents.push(
+            spend_response(
+                state.store.as_ref(),
+                &auth.user_id,
+                totals.agent_id,
+                Some(totals),
+            )
+            .await?,
+        );
+    }
+
+    Ok(Json(ListAgentSpendResponse { agents }))
+}
+
+/// Set an agent's budget for one period, replacing any existing limit.
+pub async fn set_agent_budget(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((agent_id, period)): Path<(String, String)>,
+    Json(body): Json<SetAgentBudgetRequest>,
+) -> Result<Json<AgentSpendResponse>, ApiError> {
+    let agent_id = parse_agent_id(&agent_id)?;
+    let period = parse_period(&period)?;
+
+    if body.limit_cents < 0 {
+        return Err(ApiError::BadRequest(
+            "limit_cents must not be negative".into(),
+        ));
+    }
+
+    if state.store.get_account(&auth.user_id).await?.is_none() {
+        return Err(ApiError::NotFound("Account not found".into()));
+    }
+
+    let budget = match state
+        .store
+        .list_agent_budgets(&auth.user_id, &agent_id)
+        .await?
+        .into_iter()
+        .find(|b| b.period == period)
+    {
+        Some(mut existing) => {
+            existing.limit_cents = body.limit_cents;
+            existing.updated_at = chrono::Utc::now();
+            existing
+        }
+        None => AgentBudget::new(auth.user_id, agent_id, period, body.limit_cents),
+    };
+    state.store.put_agent_budget(&budget).await?;
+
+    tracing::info!(
+        user_id = %auth.user_id,
+        agent_id = %agent_id,
+        period = period.as_str(),
+        limit_cents = %body.limit_cents,
+        "Agent budget set"
+    );
+
+    let totals = state
+        .store
+        .get_agent_spend(&auth.user_id, &agent_id)
+        .await?;
+    Ok(Json(
+        spend_response(state.store.as_ref(), &auth.user_id, agent_id, totals).await?,
+    ))
+}
+
+/// Remove an agent's budget for one period.
+pub async fn delete_agent_budget(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((agent_id, period)): Path<(String, String)>,
+) -> Result<Json<AgentSpendResponse>, ApiError> {
+    let agent_id = parse_agent_id(&agent_id)?;
+    let period = parse_period(&period)?;
+
+    state
+        .store
+        .delete_agent_budget(&auth.user_id, &agent_id, period)
+        .await?;
+
+    tracing::info!(
+        user_id = %auth.user_id,
+        agent_id = %agent_id,
+        period = period.as_str(),
+        "Agent budget removed"
+    );
+
+    let totals = state
+        .store
+        .get_agent_spend(&auth.user_id, &agent_id)
+        .await?;
+    Ok(Json(
+        spend_response(state.store.as_ref(), &auth.user_id, agent_id, totals).await?,
+    ))
+}
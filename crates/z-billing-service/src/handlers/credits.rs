@@ -4,28 +4,29 @@ use std::sync::Arc;
 
 use axum::extract::{Query, State};
 use axum::Json;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use z_billing_core::{
-    AutoRefill, CreditTransaction, DEFAULT_AUTO_REFILL_AMOUNT_CENTS,
-    DEFAULT_AUTO_REFILL_TRIGGER_CENTS,
+    grant, AmountSign, AutoRefill, CreditLot, CreditTransaction, TransactionId, TransactionQuery,
+    DEFAULT_AUTO_REFILL_AMOUNT_CENTS, DEFAULT_AUTO_REFILL_TRIGGER_CENTS,
 };
-use z_billing_store::Store;
 
 use crate::auth::{AdminAuth, AuthUser};
 use crate::error::ApiError;
+use crate::handlers::accounts::{get_or_create_account, update_account};
 use crate::state::AppState;
-use crate::stripe::PaymentResponse;
+use crate::stripe::{CheckoutPurpose, PaymentResponse};
 
 // ============================================================================
 // Constants
 // ============================================================================
 
 /// Minimum credit purchase amount in USD.
-const MIN_PURCHASE_USD: f64 = 5.0;
+pub(crate) const MIN_PURCHASE_USD: f64 = 5.0;
 
 /// Maximum credit purchase amount in USD.
-const MAX_PURCHASE_USD: f64 = 1000.0;
+pub(crate) const MAX_PURCHASE_USD: f64 = 1000.0;
 
 /// Minimum auto-refill trigger threshold in cents ($1).
 const MIN_AUTO_REFILL_TRIGGER_CENTS: i64 = 100;
@@ -42,6 +43,41 @@ pub struct BalanceResponse {
     pub balance_formatted: String,
     /// Current plan.
     pub plan: String,
+    /// Open credit lots in the order they will be spent.
+    pub lots: Vec<CreditLotResponse>,
+    /// Balance not covered by any lot (credits added before lots were
+    /// tracked). Spent last and never expires.
+    pub untracked_cents: i64,
+    /// How far usage may take the balance below zero.
+    pub credit_limit_cents: i64,
+    /// Whether the balance is below zero.
+    pub overdrawn: bool,
+    /// Whether usage is blocked until the balance is back above zero.
+    pub overdraft_locked: bool,
+}
+
+/// A credit lot in the balance breakdown.
+#[derive(Debug, Serialize)]
+pub struct CreditLotResponse {
+    /// Lot ID.
+    pub id: String,
+    /// Where the credits came from (transaction type).
+    pub source: &'static str,
+    /// Credits left in the lot.
+    pub remaining_cents: i64,
+    /// When unspent credits expire (RFC 3339), if ever.
+    pub expires_at: Option<String>,
+}
+
+impl From<&CreditLot> for CreditLotResponse {
+    fn from(lot: &CreditLot) -> Self {
+        Self {
+            id: lot.id.to_string(),
+            source: lot.source.as_str(),
+            remaining_cents: lot.remaining_cents,
+            expires_at: lot.expires_at.map(|at| at.to_rfc3339()),
+        }
+    }
 }
 
 /// Get current credit balance.
@@ -52,32 +88,41 @@ pub async fn get_balance(
 ) -> Result<Json<BalanceResponse>, ApiError> {
     let mut account = state
         .store
-        .get_account(&auth.user_id)?
+        .get_account(&auth.user_id)
+        .await?
         .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
 
     // Lazy monthly allowance: if not granted in the last 30 days, issue monthly credits
     if let Some(new_balance) =
-        try_monthly_allowance(state.store.as_ref(), &state.balance_tx, &account)?
+        try_monthly_allowance(&state, &account).await?
     {
         account.balance_cents = new_balance;
         // Re-read account to get updated last_monthly_grant_at for daily check
-        if let Some(refreshed) = state.store.get_account(&auth.user_id)? {
+        if let Some(refreshed) = state.store.get_account(&auth.user_id).await? {
             account = refreshed;
         }
     }
 
     // Lazy daily grant: if not yet granted today, issue daily credits
     if let Some(new_balance) =
-        try_daily_grant(state.store.as_ref(), &state.balance_tx, &account)?
+        try_daily_grant(&state, &account).await?
     {
         account.balance_cents = new_balance;
     }
 
+    let lots = state.store.list_credit_lots(&auth.user_id).await?;
+    let lot_total: i64 = lots.iter().map(|lot| lot.remaining_cents).sum();
+
     Ok(Json(BalanceResponse {
         balance_cents: account.balance_cents,
         #[allow(clippy::cast_precision_loss)]
         balance_formatted: format!("${:.2}", account.balance_cents as f64 / 100.0),
         plan: format!("{:?}", account.current_plan()).to_lowercase(),
+        lots: lots.iter().map(CreditLotResponse::from).collect(),
+        untracked_cents: (account.balance_cents - lot_total).max(0),
+        credit_limit_cents: account.credit_limit_cents,
+        overdrawn: account.is_overdrawn(),
+        overdraft_locked: account.overdraft_locked,
     }))
 }
 
@@ -87,9 +132,60 @@ pub struct ListTransactionsQuery {
     /// Maximum number of transactions to return (default: 50).
     #[serde(default = "default_limit")]
     pub limit: usize,
-    /// Offset for pagination (default: 0).
+    /// Offset for pagination (default: 0). Prefer `cursor`, which stays
+    /// stable while new transactions arrive.
     #[serde(default)]
     pub offset: usize,
+    /// `next_cursor` from the previous page.
+    pub cursor: Option<String>,
+    /// Comma-separated transaction types, e.g. `usage,refund`.
+    #[serde(rename = "type")]
+    pub transaction_types: Option<String>,
+    /// Only transactions created at or after this time (RFC 3339).
+    pub since: Option<DateTime<Utc>>,
+    /// Only transactions created before this time (RFC 3339).
+    pub until: Option<DateTime<Utc>>,
+    /// Only credits or only debits.
+    pub sign: Option<AmountSign>,
+    /// Only transactions for this model.
+    pub model: Option<String>,
+    /// Only transactions for this agent.
+    pub agent_id: Option<String>,
+}
+
+impl ListTransactionsQuery {
+    /// Build the store query for one page of `limit` transactions.
+    fn to_store_query(&self, limit: usize) -> Result<TransactionQuery, ApiError> {
+        let mut query = TransactionQuery::new(limit);
+        if let Some(types) = &self.transaction_types {
+            query.transaction_types = types
+                .split(',')
+                .map(|name| {
+                    serde_json::from_value(serde_json::Value::String(name.trim().to_string()))
+                        .map_err(|_| {
+                            ApiError::BadRequest(format!("Unknown transaction type: {name}"))
+                        })
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        query.since = self.since;
+        query.until = self.until;
+        query.sign = self.sign;
+        if let Some(model) = &self.model {
+            query.metadata.insert("model".into(), model.clone());
+        }
+        if let Some(agent_id) = &self.agent_id {
+            query.metadata.insert("agent_id".into(), agent_id.clone());
+        }
+        if let Some(cursor) = &self.cursor {
+            query.before = Some(
+                cursor
+                    .parse::<TransactionId>()
+                    .map_err(|_| ApiError::BadRequest("Invalid cursor".into()))?,
+            );
+        }
+        Ok(query)
+    }
 }
 
 fn default_limit() -> usize {
@@ -109,6 +205,9 @@ pub struct TransactionResponse {
     pub balance_after_cents: i64,
     /// Description.
     pub description: String,
+    /// Organization whose pool was charged or credited, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
     /// Timestamp.
     pub created_at: String,
 }
@@ -121,6 +220,7 @@ impl From<&CreditTransaction> for TransactionResponse {
             transaction_type: format!("{:?}", tx.transaction_type).to_lowercase(),
             balance_after_cents: tx.balance_after_cents,
             description: tx.description.clone(),
+            org_id: tx.org_id.map(|id| id.to_string()),
             created_at: tx.created_at.to_rfc3339(),
         }
     }
@@ -133,6 +233,9 @@ pub struct ListTransactionsResponse {
     pub transactions: Vec<TransactionResponse>,
     /// Whether there are more transactions.
     pub has_more: bool,
+    /// Pass as `cursor` to fetch the next page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// List transaction history.
@@ -144,19 +247,27 @@ pub async fn list_transactions(
     // Verify account exists
     state
         .store
-        .get_account(&auth.user_id)?
+        .get_account(&auth.user_id)
+        .await?
         .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
 
     // Fetch one more than requested to determine has_more
     let limit = query.limit.min(100);
-    let transactions =
-        state
-            .store
-            .list_transactions_by_user(&auth.user_id, limit + 1, query.offset)?;
-
-    let has_more = transactions.len() > limit;
-    let transactions: Vec<_> = transactions
-        .iter()
+    let store_query = query.to_store_query(query.offset + limit + 1)?;
+    let transactions = state
+        .store
+        .query_transactions(&auth.user_id, &store_query)
+        .await?;
+
+    let page: Vec<_> = transactions.iter().skip(query.offset).collect();
+    let has_more = page.len() > limit;
+    let next_cursor = if has_more {
+        page.get(limit - 1).map(|tx| tx.id.to_string())
+    } else {
+        None
+    };
+    let transactions: Vec<_> = page
+        .into_iter()
         .take(limit)
         .map(TransactionResponse::from)
         .collect();
@@ -164,6 +275,7 @@ pub async fn list_transactions(
     Ok(Json(ListTransactionsResponse {
         transactions,
         has_more,
+        next_cursor,
     }))
 }
 
@@ -211,7 +323,8 @@ pub async fn purchase_credits(
     // Verify account exists
     let account = state
         .store
-        .get_account(&auth.user_id)?
+        .get_account(&auth.user_id)
+        .await?
         .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
 
     // Convert to cents (no tier discount — 20% markup across the board)
@@ -243,6 +356,7 @@ pub async fn purchase_credits(
             &auth.user_id.to_string(),
             amount_cents,
             credits_amount,
+            CheckoutPurpose::Credits,
             &success_url,
             &cancel_url,
         )
@@ -285,11 +399,6 @@ pub async fn configure_auto_refill(
     auth: AuthUser,
     Json(body): Json<AutoRefillRequest>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    let mut account = state
-        .store
-        .get_account(&auth.user_id)?
-        .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
-
     // Validate amounts
     if let Some(trigger) = body.trigger_below_cents {
         if trigger < MIN_AUTO_REFILL_TRIGGER_CENTS {
@@ -308,18 +417,19 @@ pub async fn configure_auto_refill(
         }
     }
 
-    account.auto_refill = Some(AutoRefill {
-        enabled: body.enabled,
-        trigger_below_cents: body
-            .trigger_below_cents
-            .unwrap_or(DEFAULT_AUTO_REFILL_TRIGGER_CENTS),
-        refill_amount_cents: body
-            .refill_amount_cents
-            .unwrap_or(DEFAULT_AUTO_REFILL_AMOUNT_CENTS),
-    });
-    account.updated_at = chrono::Utc::now();
-
-    state.store.put_account(&account)?;
+    let account = update_account(state.store.as_ref(), &auth.user_id, |account| {
+        account.auto_refill = Some(AutoRefill {
+            enabled: body.enabled,
+            trigger_below_cents: body
+                .trigger_below_cents
+                .unwrap_or(DEFAULT_AUTO_REFILL_TRIGGER_CENTS),
+            refill_amount_cents: body
+                .refill_amount_cents
+                .unwrap_or(DEFAULT_AUTO_REFILL_AMOUNT_CENTS),
+        });
+    })
+    .await?
+    .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
 
     tracing::info!(
         user_id = %auth.user_id,
@@ -368,7 +478,8 @@ pub async fn list_payments(
     // Get account
     let account = state
         .store
-        .get_account(&auth.user_id)?
+        .get_account(&auth.user_id)
+        .await?
         .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
 
     // Get Stripe customer ID
@@ -431,7 +542,8 @@ pub async fn admin_add_credits(
     // Get account
     let account = state
         .store
-        .get_account(&user_id)?
+        .get_account(&user_id)
+        .await?
         .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
 
     // Create transaction
@@ -439,19 +551,13 @@ pub async fn admin_add_credits(
     let tx = CreditTransaction::bonus(user_id, body.amount_cents, new_balance, body.reason.clone());
 
     // Add credits
-    let balance = state.store.add_credits(&user_id, body.amount_cents, &tx)?;
-
-    // Broadcast balance update to WebSocket clients
-    #[allow(clippy::cast_precision_loss)]
-    let _ = state.balance_tx.send(
-        serde_json::json!({
-            "type": "balance.updated",
-            "userId": user_id.to_string(),
-            "balanceCents": balance,
-            "balanceFormatted": format!("${:.2}", balance as f64 / 100.0),
-        })
-        .to_string(),
-    );
+    let outbox = [crate::outbox::balance_update(&user_id)];
+    let balance = state
+        .store
+        .add_credits(&user_id, body.amount_cents, &tx, &outbox)
+        .await?;
+
+    crate::outbox::committed(&state, &outbox).await;
 
     tracing::info!(
         admin_id = %admin.admin_id,
@@ -496,8 +602,9 @@ pub struct SignupGrantRequest {
 /// Grant one-time signup credits to a new user.
 ///
 /// Idempotent: if the user has already received a signup grant, returns
-/// `granted: false`. Uses `signup_grant_at` timestamp on the account for
-/// O(1) duplicate detection.
+/// `granted: false`. The `signup_grant_at` timestamp on the account skips
+/// repeat calls cheaply, and the store's `signup` grant key stops concurrent
+/// ones from both granting.
 ///
 /// Requires service-to-service auth via `X-API-Key` header.
 pub async fn signup_grant(
@@ -513,14 +620,7 @@ pub async fn signup_grant(
     let amount = signup_grant_amount();
 
     // Get or create account
-    let account = match state.store.get_account(&user_id)? {
-        Some(a) => a,
-        None => {
-            let new_account = z_billing_core::Account::new(user_id);
-            state.store.put_account(&new_account)?;
-            new_account
-        }
-    };
+    let account = get_or_create_account(state.store.as_ref(), &user_id).await?;
 
     // Check if already granted
     if account.signup_grant_at.is_some() {
@@ -535,27 +635,28 @@ pub async fn signup_grant(
     let new_balance = account.balance_cents + amount;
     let tx = CreditTransaction::signup_grant(user_id, amount, new_balance);
 
-    let balance = state.store.add_credits(&user_id, amount, &tx)?;
+    let outbox = [crate::outbox::balance_update(&user_id)];
+    let Some(balance) = state
+        .store
+        .grant_once(&user_id, grant::SIGNUP, &tx, &outbox)
+        .await?
+    else {
+        return Ok(Json(serde_json::json!({
+            "granted": false,
+            "reason": "already_granted",
+            "balance_cents": account.balance_cents,
+        })));
+    };
 
     // Mark signup grant as issued and store referral + Zero Pro status
-    let mut updated = state.store.get_account(&user_id)?.unwrap_or(account);
-    updated.signup_grant_at = Some(chrono::Utc::now());
-    updated.is_zero_pro = body.is_zero_pro;
-    updated.referred_by = body.referred_by.clone();
-    updated.updated_at = chrono::Utc::now();
-    state.store.put_account(&updated)?;
-
-    // Broadcast balance update
-    #[allow(clippy::cast_precision_loss)]
-    let _ = state.balance_tx.send(
-        serde_json::json!({
-            "type": "balance.updated",
-            "userId": user_id.to_string(),
-            "balanceCents": balance,
-            "balanceFormatted": format!("${:.2}", balance as f64 / 100.0),
-        })
-        .to_string(),
-    );
+    update_account(state.store.as_ref(), &user_id, |account| {
+        account.signup_grant_at = Some(chrono::Utc::now());
+        account.is_zero_pro = body.is_zero_pro;
+        account.referred_by.clone_from(&body.referred_by);
+    })
+    .await?;
+
+    crate::outbox::committed(&state, &outbox).await;
 
     tracing::info!(
         user_id = %user_id,
@@ -602,14 +703,15 @@ fn daily_grant_amount(plan: &z_billing_core::Plan) -> i64 {
 ///
 /// Returns the new balance if a grant was issued, or None if not eligible.
 /// Uses the lazy approach: credits are granted on first use of the day.
-/// Previous days' unused daily credits are NOT carried over — you only get
-/// today's grant when you show up.
+/// Previous days' unused daily credits are NOT carried over — the grant's
+/// credit lot expires at the next UTC midnight and the sweeper removes
+/// whatever is left of it.
 ///
 /// This function is safe to call from multiple code paths — the
-/// `last_daily_grant_at` check prevents double-grants within the same day.
-pub fn try_daily_grant(
-    store: &dyn Store,
-    balance_tx: &tokio::sync::broadcast::Sender<String>,
+/// `last_daily_grant_at` check skips repeat calls cheaply, and the store's
+/// `daily:<date>` grant key stops concurrent calls from both granting.
+pub async fn try_daily_grant(
+    state: &AppState,
     account: &z_billing_core::Account,
 ) -> Result<Option<i64>, ApiError> {
     // Only activate daily grants for users who have received their signup grant.
@@ -637,25 +739,22 @@ pub fn try_daily_grant(
     let new_balance = account.balance_cents + amount;
     let tx = CreditTransaction::daily_grant(user_id, amount, new_balance);
 
-    let balance = store.add_credits(&user_id, amount, &tx)?;
+    let outbox = [crate::outbox::balance_update(&user_id)];
+    let Some(balance) = state
+        .store
+        .grant_once(&user_id, &grant::daily(today), &tx, &outbox)
+        .await?
+    else {
+        return Ok(None);
+    };
 
     // Update last_daily_grant_at
-    let mut updated = store.get_account(&user_id)?.unwrap_or_else(|| account.clone());
-    updated.last_daily_grant_at = Some(chrono::Utc::now());
-    updated.updated_at = chrono::Utc::now();
-    store.put_account(&updated)?;
-
-    // Broadcast balance update
-    #[allow(clippy::cast_precision_loss)]
-    let _ = balance_tx.send(
-        serde_json::json!({
-            "type": "balance.updated",
-            "userId": user_id.to_string(),
-            "balanceCents": balance,
-            "balanceFormatted": format!("${:.2}", balance as f64 / 100.0),
-        })
-        .to_string(),
-    );
+    update_account(state.store.as_ref(), &user_id, |account| {
+        account.last_daily_grant_at = Some(chrono::Utc::now());
+    })
+    .await?;
+
+    crate::outbox::committed(state, &outbox).await;
 
     tracing::info!(
         user_id = %user_id,
@@ -691,9 +790,10 @@ fn period_already_granted(
 ///
 /// Returns the new balance if a grant was issued, or None if not eligible.
 /// Checks `last_monthly_grant_at` — grants if it's been more than 30 days.
-pub fn try_monthly_allowance(
-    store: &dyn Store,
-    balance_tx: &tokio::sync::broadcast::Sender<String>,
+/// The grant is keyed by the open billing period's start, or by the day
+/// when there is none, so concurrent calls cannot both grant.
+pub async fn try_monthly_allowance(
+    state: &AppState,
     account: &z_billing_core::Account,
 ) -> Result<Option<i64>, ApiError> {
     // Only activate monthly grants for users who have received their signup grant.
@@ -723,8 +823,10 @@ pub fn try_monthly_allowance(
     // same period. This query only runs on the rare path (>30 days since the
     // last grant), so the hot path is unaffected.
     if let Some(sub) = account.subscription.as_ref() {
-        let granted =
-            store.sum_monthly_allowance_since(&account.user_id, sub.current_period_start)?;
+        let granted = state
+            .store
+            .sum_monthly_allowance_since(&account.user_id, sub.current_period_start)
+            .await?;
         if period_already_granted(now, sub.current_period_end, granted) {
             return Ok(None);
         }
@@ -733,26 +835,27 @@ pub fn try_monthly_allowance(
     let user_id = account.user_id;
     let new_balance = account.balance_cents + amount;
     let tx = CreditTransaction::monthly_allowance(user_id, amount, new_balance);
+    let grant_key = match account.subscription.as_ref() {
+        Some(sub) if sub.current_period_end > now => grant::monthly(sub.current_period_start),
+        _ => grant::monthly(now.date_naive().and_time(chrono::NaiveTime::MIN).and_utc()),
+    };
 
-    let balance = store.add_credits(&user_id, amount, &tx)?;
+    let outbox = [crate::outbox::balance_update(&user_id)];
+    let Some(balance) = state
+        .store
+        .grant_once(&user_id, &grant_key, &tx, &outbox)
+        .await?
+    else {
+        return Ok(None);
+    };
 
     // Update last_monthly_grant_at
-    let mut updated = store.get_account(&user_id)?.unwrap_or_else(|| account.clone());
-    updated.last_monthly_grant_at = Some(now);
-    updated.updated_at = now;
-    store.put_account(&updated)?;
-
-    // Broadcast balance update
-    #[allow(clippy::cast_precision_loss)]
-    let _ = balance_tx.send(
-        serde_json::json!({
-            "type": "balance.updated",
-            "userId": user_id.to_string(),
-            "balanceCents": balance,
-            "balanceFormatted": format!("${:.2}", balance as f64 / 100.0),
-        })
-        .to_string(),
-    );
+    update_account(state.store.as_ref(), &user_id, |account| {
+        account.last_monthly_grant_at = Some(now);
+    })
+    .await?;
+
+    crate::outbox::committed(state, &outbox).await;
 
     tracing::info!(
         user_id = %user_id,
@@ -788,10 +891,11 @@ pub async fn daily_grant(
 
     let account = state
         .store
-        .get_account(&user_id)?
+        .get_account(&user_id)
+        .await?
         .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
 
-    match try_daily_grant(state.store.as_ref(), &state.balance_tx, &account)? {
+    match try_daily_grant(&state, &account).await? {
         Some(balance) => Ok(Json(serde_json::json!({
             "granted": true,
             "amount_cents": daily_grant_amount(&account.current_plan()),
@@ -829,8 +933,9 @@ pub struct ReferralGrantRequest {
 /// Grant referral credits to both inviter and invitee.
 ///
 /// Idempotent: if this invitee has already received a referral bonus,
-/// returns `granted: false`. Both grants happen in sequence — invitee
-/// is checked first to prevent duplicate grants.
+/// returns `granted: false`. Both grants happen in sequence — the invitee's
+/// `referral` grant key is claimed first, so only one of several concurrent
+/// calls goes on to grant the inviter.
 ///
 /// Both parties receive a flat 5,000 credits ($50) regardless of tier.
 ///
@@ -854,17 +959,10 @@ pub async fn referral_grant(
     }
 
     // Get or create invitee account
-    let invitee_account = match state.store.get_account(&invitee_id)? {
-        Some(a) => a,
-        None => {
-            let new_account = z_billing_core::Account::new(invitee_id);
-            state.store.put_account(&new_account)?;
-            new_account
-        }
-    };
+    let invitee_account = get_or_create_account(state.store.as_ref(), &invitee_id).await?;
 
     // Check if invitee already received a referral bonus
-    let already_granted = state.store.has_referral_bonus(&invitee_id)?;
+    let already_granted = state.store.has_referral_bonus(&invitee_id).await?;
 
     if already_granted {
         return Ok(Json(serde_json::json!({
@@ -883,29 +981,22 @@ pub async fn referral_grant(
         invitee_new_balance,
         format!("Referral bonus — invited by {}", body.inviter_user_id),
     );
-    let invitee_balance = state.store.add_credits(&invitee_id, invitee_amount, &invitee_tx)?;
-
-    // Broadcast invitee balance update
-    #[allow(clippy::cast_precision_loss)]
-    let _ = state.balance_tx.send(
-        serde_json::json!({
-            "type": "balance.updated",
-            "userId": invitee_id.to_string(),
-            "balanceCents": invitee_balance,
-            "balanceFormatted": format!("${:.2}", invitee_balance as f64 / 100.0),
-        })
-        .to_string(),
-    );
+    let outbox = [crate::outbox::balance_update(&invitee_id)];
+    let Some(invitee_balance) = state
+        .store
+        .grant_once(&invitee_id, grant::REFERRAL, &invitee_tx, &outbox)
+        .await?
+    else {
+        return Ok(Json(serde_json::json!({
+            "granted": false,
+            "reason": "already_granted",
+        })));
+    };
+
+    crate::outbox::committed(&state, &outbox).await;
 
     // Get or create inviter account and determine bonus amount
-    let inviter_account = match state.store.get_account(&inviter_id)? {
-        Some(a) => a,
-        None => {
-            let new_account = z_billing_core::Account::new(inviter_id);
-            state.store.put_account(&new_account)?;
-            new_account
-        }
-    };
+    let inviter_account = get_or_create_account(state.store.as_ref(), &inviter_id).await?;
 
     let inviter_amount = referral_grant_amount();
 
@@ -917,19 +1008,18 @@ pub async fn referral_grant(
         inviter_new_balance,
         format!("Referral bonus — {} signed up with your invite", body.invitee_user_id),
     );
-    let inviter_balance = state.store.add_credits(&inviter_id, inviter_amount, &inviter_tx)?;
-
-    // Broadcast inviter balance update
-    #[allow(clippy::cast_precision_loss)]
-    let _ = state.balance_tx.send(
-        serde_json::json!({
-            "type": "balance.updated",
-            "userId": inviter_id.to_string(),
-            "balanceCents": inviter_balance,
-            "balanceFormatted": format!("${:.2}", inviter_balance as f64 / 100.0),
-        })
-        .to_string(),
-    );
+    let outbox = [crate::outbox::balance_update(&inviter_id)];
+    let inviter_balance = match state
+        .store
+        .grant_once(&inviter_id, &grant::referrer(&invitee_id), &inviter_tx, &outbox)
+        .await?
+    {
+        Some(balance) => {
+            crate::outbox::committed(&state, &outbox).await;
+            balance
+        }
+        None => inviter_account.balance_cents,
+    };
 
     tracing::info!(
         inviter_id = %inviter_id,
@@ -1069,7 +1159,6 @@ mod tests {
     // Gated on the rocksdb-backend feature (run: cargo test -p z-billing-service
     // --features rocksdb-backend).
 
-    #[cfg(feature = "rocksdb-backend")]
     fn crusader_account_open_period(
         now: chrono::DateTime<chrono::Utc>,
         period_end: chrono::DateTime<chrono::Utc>,
@@ -1091,65 +1180,94 @@ mod tests {
         account
     }
 
-    #[cfg(feature = "rocksdb-backend")]
-    #[test]
-    fn try_monthly_allowance_skips_when_open_period_already_granted() {
-        let dir = tempfile::TempDir::new().unwrap();
-        let store = z_billing_store::RocksStore::open(dir.path()).unwrap();
-        let (tx, _rx) = tokio::sync::broadcast::channel::<String>(16);
+    fn memory_state() -> AppState {
+        AppState::new(
+            Arc::new(z_billing_store::MemoryStore::new()),
+            crate::config::ServiceConfig::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn try_daily_grant_grants_once_for_concurrent_calls() {
+        let state = memory_state();
+        let store = state.store.as_ref();
+
+        // Both calls see the same stale snapshot with no grant today
+        let mut account = z_billing_core::Account::new(z_billing_core::UserId::generate());
+        account.signup_grant_at = Some(chrono::Utc::now() - chrono::Duration::days(2));
+        store.put_account(&account).await.unwrap();
+
+        let (first, second) = tokio::join!(
+            try_daily_grant(&state, &account),
+            try_daily_grant(&state, &account),
+        );
+        let granted = [first.unwrap(), second.unwrap()];
+        assert_eq!(granted.iter().flatten().count(), 1, "only one call may grant");
+
+        let amount = daily_grant_amount(&account.current_plan());
+        assert_eq!(
+            store.get_account(&account.user_id).await.unwrap().unwrap().balance_cents,
+            amount,
+        );
+    }
+
+    #[tokio::test]
+    async fn try_monthly_allowance_skips_when_open_period_already_granted() {
+        let state = memory_state();
+        let store = state.store.as_ref();
         let now = chrono::Utc::now();
 
         // Period still OPEN (ends in 5 days) and already granted this period.
         let account = crusader_account_open_period(now, now + chrono::Duration::days(5));
         let user_id = account.user_id;
-        store.put_account(&account).unwrap();
+        store.put_account(&account).await.unwrap();
         store
             .add_credits(
                 &user_id,
                 12_000,
                 &CreditTransaction::monthly_allowance(user_id, 12_000, 12_000),
-            )
+                &[crate::outbox::balance_update(&user_id)],
+            ).await
             .unwrap();
 
-        let account = store.get_account(&user_id).unwrap().unwrap();
+        let account = store.get_account(&user_id).await.unwrap().unwrap();
         let before = account.balance_cents;
-        let result = try_monthly_allowance(&store, &tx, &account).unwrap();
+        let result = try_monthly_allowance(&state, &account).await.unwrap();
 
         assert_eq!(
             result, None,
             "must not re-grant an already-granted open period"
         );
         assert_eq!(
-            store.get_account(&user_id).unwrap().unwrap().balance_cents,
+            store.get_account(&user_id).await.unwrap().unwrap().balance_cents,
             before,
             "balance must be unchanged",
         );
     }
 
-    #[cfg(feature = "rocksdb-backend")]
-    #[test]
-    fn try_monthly_allowance_grants_backstop_when_period_ended() {
-        let dir = tempfile::TempDir::new().unwrap();
-        let store = z_billing_store::RocksStore::open(dir.path()).unwrap();
-        let (tx, _rx) = tokio::sync::broadcast::channel::<String>(16);
+    #[tokio::test]
+    async fn try_monthly_allowance_grants_backstop_when_period_ended() {
+        let state = memory_state();
+        let store = state.store.as_ref();
         let now = chrono::Utc::now();
 
         // Period ENDED (renewal not processed) — the genuine dropped-invoice case.
         // Even a grant recorded against the ended period must not block the backstop.
         let account = crusader_account_open_period(now, now - chrono::Duration::days(1));
         let user_id = account.user_id;
-        store.put_account(&account).unwrap();
+        store.put_account(&account).await.unwrap();
         store
             .add_credits(
                 &user_id,
                 12_000,
                 &CreditTransaction::monthly_allowance(user_id, 12_000, 12_000),
-            )
+                &[crate::outbox::balance_update(&user_id)],
+            ).await
             .unwrap();
 
-        let account = store.get_account(&user_id).unwrap().unwrap();
+        let account = store.get_account(&user_id).await.unwrap().unwrap();
         let before = account.balance_cents;
-        let result = try_monthly_allowance(&store, &tx, &account).unwrap();
+        let result = try_monthly_allowance(&state, &account).await.unwrap();
 
         assert_eq!(
             result,
@@ -1157,7 +1275,7 @@ mod tests {
             "backstop must still cover a dropped renewal"
         );
         assert_eq!(
-            store.get_account(&user_id).unwrap().unwrap().balance_cents,
+            store.get_account(&user_id).await.unwrap().unwrap().balance_cents,
             before + 12_000,
         );
     }
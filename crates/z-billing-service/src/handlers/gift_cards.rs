@@ -0,0 +1,301 @@
+//! Gift card handlers.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use z_billing_core::{CreditTransaction, GiftCard, GiftCardId};
+
+use crate::auth::{AdminAuth, AuthUser};
+use crate::error::ApiError;
+use crate::handlers::credits::{MAX_PURCHASE_USD, MIN_PURCHASE_USD};
+use crate::state::AppState;
+use crate::stripe::CheckoutPurpose;
+
+/// Purchase gift card request.
+#[derive(Debug, Deserialize)]
+pub struct PurchaseGiftCardRequest {
+    /// Card value in dollars.
+    pub amount_usd: f64,
+}
+
+/// Purchase gift card response.
+#[derive(Debug, Serialize)]
+pub struct PurchaseGiftCardResponse {
+    /// Stripe checkout session URL.
+    pub checkout_url: String,
+    /// Session ID for tracking.
+    pub session_id: String,
+}
+
+/// Redeem gift card request.
+#[derive(Debug, Deserialize)]
+pub struct RedeemGiftCardRequest {
+    /// The card's redemption code.
+    pub code: String,
+}
+
+/// Redeem gift card response.
+#[derive(Debug, Serialize)]
+pub struct RedeemGiftCardResponse {
+    /// Redeemed card ID.
+    pub gift_card_id: String,
+    /// Credits added in cents.
+    pub amount_cents: i64,
+    /// New balance in cents.
+    pub balance_cents: i64,
+    /// Gift card transaction ID.
+    pub transaction_id: String,
+}
+
+/// Void gift card request.
+#[derive(Debug, Deserialize)]
+pub struct VoidGiftCardRequest {
+    /// Why the card is being voided.
+    pub reason: String,
+}
+
+/// Gift card response.
+#[derive(Debug, Serialize)]
+pub struct GiftCardResponse {
+    /// Gift card ID.
+    pub id: String,
+    /// Redemption code.
+    pub code: String,
+    /// Card value in cents.
+    pub amount_cents: i64,
+    /// Buyer's user ID.
+    pub purchaser_id: String,
+    /// Lifecycle state (`issued`, `redeemed` or `voided`).
+    pub status: String,
+    /// Redeemer's user ID, if redeemed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redeemed_by: Option<String>,
+    /// Redemption timestamp, if redeemed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redeemed_at: Option<String>,
+    /// Void timestamp, if voided.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voided_at: Option<String>,
+    /// Why the card was voided, if voided.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub void_reason: Option<String>,
+    /// Issue timestamp.
+    pub created_at: String,
+}
+
+impl From<&GiftCard> for GiftCardResponse {
+    fn from(card: &GiftCard) -> Self {
+        Self {
+            id: card.id.to_string(),
+            code: card.code.clone(),
+            amount_cents: card.amount_cents,
+            purchaser_id: card.purchaser_id.to_string(),
+            status: card.status.as_str().to_string(),
+            redeemed_by: card.redeemed_by.map(|id| id.to_string()),
+            redeemed_at: card.redeemed_at.map(|t| t.to_rfc3339()),
+            voided_at: card.voided_at.map(|t| t.to_rfc3339()),
+            void_reason: card.void_reason.clone(),
+            created_at: card.created_at.to_rfc3339(),
+        }
+    }
+}
+
+fn parse_card_id(card_id: &str) -> Result<GiftCardId, ApiError> {
+    card_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid gift card ID".into()))
+}
+
+// ============================================================================
+// Purchase
+// ============================================================================
+
+/// Start a Stripe checkout for a gift card.
+///
+/// Once the payment completes, the Stripe webhook issues the card; the buyer
+/// finds its code with `GET /v1/gift-cards`.
+#[allow(clippy::cast_precision_loss)]
+pub async fn purchase_gift_card(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<PurchaseGiftCardRequest>,
+) -> Result<Json<PurchaseGiftCardResponse>, ApiError> {
+    if body.amount_usd < MIN_PURCHASE_USD {
+        return Err(ApiError::BadRequest(format!(
+            "Minimum gift card is ${MIN_PURCHASE_USD}"
+        )));
+    }
+    if body.amount_usd > MAX_PURCHASE_USD {
+        return Err(ApiError::BadRequest(format!(
+            "Maximum gift card is ${MAX_PURCHASE_USD}"
+        )));
+    }
+
+    let stripe = state
+        .stripe
+        .as_ref()
+        .ok_or_else(|| ApiError::ExternalService("Stripe not configured".into()))?;
+
+    let account = state
+        .store
+        .get_account(&auth.user_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
+
+    // Credits are 1:1 with cents
+    #[allow(clippy::cast_possible_truncation)]
+    let amount_cents = (body.amount_usd * 100.0).round() as i64;
+
+    let success_url = format!("{}/checkout/success", state.config.frontend_url);
+    let cancel_url = format!("{}/checkout/cancelled", state.config.frontend_url);
+
+    let session = stripe
+        .create_checkout_session(
+            account.stripe_customer_id.as_deref(),
+            &auth.user_id.to_string(),
+            amount_cents,
+            amount_cents,
+            CheckoutPurpose::GiftCard,
+            &success_url,
+            &cancel_url,
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!(error = %e, "Failed to create Stripe checkout session");
+            ApiError::ExternalService(format!("Failed to create checkout session: {e}"))
+        })?;
+
+    let checkout_url = session
+        .url
+        .ok_or_else(|| ApiError::ExternalService("Stripe returned no checkout URL".into()))?;
+
+    tracing::info!(
+        user_id = %auth.user_id,
+        session_id = %session.id,
+        amount_cents = %amount_cents,
+        "Gift card checkout session created"
+    );
+
+    Ok(Json(PurchaseGiftCardResponse {
+        checkout_url,
+        session_id: session.id,
+    }))
+}
+
+/// List the gift cards the caller has bought, newest first.
+pub async fn list_gift_cards(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+) -> Result<Json<Vec<GiftCardResponse>>, ApiError> {
+    let cards = state
+        .store
+        .list_gift_cards_by_purchaser(&auth.user_id)
+        .await?;
+    Ok(Json(cards.iter().map(GiftCardResponse::from).collect()))
+}
+
+// ============================================================================
+// Redemption
+// ============================================================================
+
+/// Redeem a gift card code into the caller's account.
+pub async fn redeem_gift_card(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<RedeemGiftCardRequest>,
+) -> Result<Json<RedeemGiftCardResponse>, ApiError> {
+    let code = GiftCard::normalize_code(&body.code);
+    if code.is_empty() {
+        return Err(ApiError::BadRequest("code is required".into()));
+    }
+
+    let card = state
+        .store
+        .get_gift_card_by_code(&code)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Gift card not found".into()))?;
+
+    let account = state
+        .store
+        .get_account(&auth.user_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
+
+    let tx = CreditTransaction::gift_card(
+        auth.user_id,
+        &card,
+        account.balance_cents + card.amount_cents,
+    );
+    let outbox = [crate::outbox::balance_update(&auth.user_id)];
+    let balance = state.store.redeem_gift_card(&card.id, &tx, &outbox).await?;
+    crate::outbox::committed(&state, &outbox).await;
+
+    tracing::info!(
+        user_id = %auth.user_id,
+        gift_card_id = %card.id,
+        amount_cents = %card.amount_cents,
+        new_balance = %balance,
+        "Gift card redeemed"
+    );
+
+    Ok(Json(RedeemGiftCardResponse {
+        gift_card_id: card.id.to_string(),
+        amount_cents: card.amount_cents,
+        balance_cents: balance,
+        transaction_id: tx.id.to_string(),
+    }))
+}
+
+// ============================================================================
+// Admin
+// ============================================================================
+
+/// Admin endpoint to get a gift card.
+///
+/// Requires `X-Admin-Key` header for authentication.
+pub async fn admin_get_gift_card(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminAuth,
+    Path(card_id): Path<String>,
+) -> Result<Json<GiftCardResponse>, ApiError> {
+    let card_id = parse_card_id(&card_id)?;
+    let card = state
+        .store
+        .get_gift_card(&card_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Gift card not found: {card_id}")))?;
+
+    Ok(Json(GiftCardResponse::from(&card)))
+}
+
+/// Admin endpoint to void an issued gift card.
+///
+/// Redeemed cards cannot be voided; claw back their credits separately.
+///
+/// Requires `X-Admin-Key` header for authentication.
+pub async fn admin_void_gift_card(
+    State(state): State<Arc<AppState>>,
+    admin: AdminAuth,
+    Path(card_id): Path<String>,
+    Json(body): Json<VoidGiftCardRequest>,
+) -> Result<Json<GiftCardResponse>, ApiError> {
+    let card_id = parse_card_id(&card_id)?;
+    let reason = body.reason.trim();
+    if reason.is_empty() {
+        return Err(ApiError::BadRequest("reason is required".into()));
+    }
+
+    let card = state.store.void_gift_card(&card_id, reason).await?;
+
+    tracing::info!(
+        admin_id = %admin.admin_id,
+        gift_card_id = %card.id,
+        reason = %reason,
+        "Gift card voided"
+    );
+
+    Ok(Json(GiftCardResponse::from(&card)))
+}
@@ -0,0 +1,86 @@
+//! Ledger handlers.
+//!
+//! Every balance movement is posted to a double-entry ledger. These admin
+//! endpoints check that stored balances still agree with it.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::Json;
+use serde::Serialize;
+
+use z_billing_core::LedgerDrift;
+
+use crate::auth::AdminAuth;
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// A wallet whose balance does not match its ledger entries.
+#[derive(Debug, Serialize)]
+pub struct LedgerDriftResponse {
+    /// Ledger account (`user:<id>` or `org:<id>`).
+    pub account: String,
+    /// Stored balance in cents.
+    pub balance_cents: i64,
+    /// Sum of the account's ledger entries in cents.
+    pub ledger_cents: i64,
+    /// Amount the stored balance is off by in cents.
+    pub drift_cents: i64,
+}
+
+impl From<&LedgerDrift> for LedgerDriftResponse {
+    fn from(drift: &LedgerDrift) -> Self {
+        Self {
+            account: drift.account.to_string(),
+            balance_cents: drift.balance_cents,
+            ledger_cents: drift.ledger_cents,
+            drift_cents: drift.drift_cents(),
+        }
+    }
+}
+
+/// Ledger verification response.
+#[derive(Debug, Serialize)]
+pub struct VerifyLedgerResponse {
+    /// Whether every balance matches the ledger and the ledger balances.
+    pub consistent: bool,
+    /// Number of accounts and organizations checked.
+    pub accounts_checked: usize,
+    /// Sum of all ledger entries in cents (zero when balanced).
+    pub unbalanced_cents: i64,
+    /// Wallets whose balance does not match their entries.
+    pub drift: Vec<LedgerDriftResponse>,
+}
+
+/// Check every balance against the ledger.
+///
+/// Drift is reported, not repaired. Requires `X-Admin-Key` header.
+pub async fn verify_ledger(
+    State(state): State<Arc<AppState>>,
+    admin: AdminAuth,
+) -> Result<Json<VerifyLedgerResponse>, ApiError> {
+    let report = state.store.verify_ledger().await?;
+
+    if report.is_consistent() {
+        tracing::info!(
+            admin_id = %admin.admin_id,
+            accounts_checked = %report.accounts_checked,
+            "Ledger verified"
+        );
+    } else {
+        tracing::warn!(
+            admin_id = %admin.admin_id,
+            accounts_checked = %report.accounts_checked,
+            drifted = %report.drift.len(),
+            unbalanced_cents = %report.unbalanced_cents,
+            "Ledger drift detected"
+        );
+    }
+
+    Ok(Json(VerifyLedgerResponse {
+        consistent: report.is_consistent(),
+        accounts_checked: report.accounts_checked,
+        unbalanced_cents: report.unbalanced_cents,
+        drift: report.drift.iter().map(LedgerDriftResponse::from).collect(),
+    }))
+}
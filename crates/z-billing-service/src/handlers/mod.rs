@@ -1,10 +1,19 @@
 //! API handlers.
 
 pub mod accounts;
+pub mod agents;
 pub mod checkout_pages;
 pub mod credits;
+pub mod gift_cards;
 pub mod health;
+pub mod ledger;
+pub mod orgs;
+pub mod outbox;
+pub mod pricing;
+pub mod promos;
 pub mod subscriptions;
+pub mod transfers;
 pub mod usage;
+pub mod usage_summary;
 pub mod webhooks;
 pub mod ws;
@@ -0,0 +1,342 @@
+//! Organization handlers.
+//!
+//! Organizations own a shared credit pool that members draw usage from.
+//! Member management is done by owners and admins; funding the pool is an
+//! admin-key operation.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use z_billing_core::{org, CreditTransaction, OrgId, OrgMembership, OrgRole, Organization, UserId};
+use z_billing_store::Store;
+
+use crate::auth::{AdminAuth, AuthUser};
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Organization response.
+#[derive(Debug, Serialize)]
+pub struct OrgResponse {
+    /// Organization ID.
+    pub id: String,
+    /// Display name.
+    pub name: String,
+    /// Shared balance in cents.
+    pub balance_cents: i64,
+    /// Lifetime credits added to the pool in cents.
+    pub lifetime_purchased_cents: i64,
+    /// Lifetime credits used by members in cents.
+    pub lifetime_used_cents: i64,
+    /// Created timestamp.
+    pub created_at: String,
+}
+
+impl From<&Organization> for OrgResponse {
+    fn from(org: &Organization) -> Self {
+        Self {
+            id: org.id.to_string(),
+            name: org.name.clone(),
+            balance_cents: org.balance_cents,
+            lifetime_purchased_cents: org.lifetime_purchased_cents,
+            lifetime_used_cents: org.lifetime_used_cents,
+            created_at: org.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Organization member response.
+#[derive(Debug, Serialize)]
+pub struct OrgMemberResponse {
+    /// Member's user ID.
+    pub user_id: String,
+    /// Member's role.
+    pub role: &'static str,
+    /// Monthly spend cap in cents (None = no cap).
+    pub monthly_spend_cap_cents: Option<i64>,
+    /// Amount spent from the pool this calendar month in cents.
+    pub spent_this_month_cents: i64,
+}
+
+/// List organization members response.
+#[derive(Debug, Serialize)]
+pub struct ListOrgMembersResponse {
+    /// Members in the order they joined.
+    pub members: Vec<OrgMemberResponse>,
+}
+
+/// Create organization request.
+#[derive(Debug, Deserialize)]
+pub struct CreateOrgRequest {
+    /// Display name.
+    pub name: String,
+}
+
+/// Add or update member request.
+#[derive(Debug, Deserialize)]
+pub struct PutOrgMemberRequest {
+    /// Member's role.
+    pub role: OrgRole,
+    /// Monthly spend cap in cents (omit for no cap).
+    pub monthly_spend_cap_cents: Option<i64>,
+}
+
+/// Admin add organization credits request.
+#[derive(Debug, Deserialize)]
+pub struct AddOrgCreditsRequest {
+    /// Member recorded as acting on the transaction (e.g. the purchaser).
+    pub user_id: String,
+    /// Amount in cents.
+    pub amount_cents: i64,
+    /// Reason for the credit.
+    pub reason: String,
+}
+
+fn parse_org_id(org_id: &str) -> Result<OrgId, ApiError> {
+    org_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid organization ID".into()))
+}
+
+fn parse_user_id(user_id: &str) -> Result<UserId, ApiError> {
+    user_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid user ID".into()))
+}
+
+async fn load_org(store: &dyn Store, org_id: &OrgId) -> Result<Organization, ApiError> {
+    store
+        .get_organization(org_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Organization not found: {org_id}")))
+}
+
+/// Get the caller's membership, or `Forbidden` if they are not a member.
+async fn require_member(
+    store: &dyn Store,
+    org_id: &OrgId,
+    user_id: &UserId,
+) -> Result<OrgMembership, ApiError> {
+    store
+        .get_org_membership(org_id, user_id)
+        .await?
+        .ok_or(ApiError::Forbidden)
+}
+
+/// Check that `actor` may change `target`'s membership.
+///
+/// Owners and admins manage members, but only owners may grant, change or
+/// remove the owner role.
+fn check_can_manage(
+    actor: &OrgMembership,
+    target: Option<&OrgMembership>,
+    new_role: Option<OrgRole>,
+) -> Result<(), ApiError> {
+    if !actor.role.can_manage_members() {
+        return Err(ApiError::Forbidden);
+    }
+    let touches_owner =
+        target.is_some_and(|m| m.role == OrgRole::Owner) || new_role == Some(OrgRole::Owner);
+    if touches_owner && actor.role != OrgRole::Owner {
+        return Err(ApiError::Forbidden);
+    }
+    Ok(())
+}
+
+async fn member_response(
+    store: &dyn Store,
+    membership: &OrgMembership,
+) -> Result<OrgMemberResponse, ApiError> {
+    let since = org::spend_cap_period_start(chrono::Utc::now());
+    let spent_this_month_cents = store
+        .org_member_spend_since(&membership.org_id, &membership.user_id, since)
+        .await?;
+
+    Ok(OrgMemberResponse {
+        user_id: membership.user_id.to_string(),
+        role: membership.role.as_str(),
+        monthly_spend_cap_cents: membership.monthly_spend_cap_cents,
+        spent_this_month_cents,
+    })
+}
+
+/// Create an organization. The caller becomes its owner.
+pub async fn create_org(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<CreateOrgRequest>,
+) -> Result<Json<OrgResponse>, ApiError> {
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::BadRequest("name must not be empty".into()));
+    }
+
+    let org = Organization::new(name.to_string());
+    state.store.put_organization(&org).await?;
+    state
+        .store
+        .put_org_membership(&OrgMembership::new(org.id, auth.user_id, OrgRole::Owner))
+        .await?;
+
+    tracing::info!(
+        org_id = %org.id,
+        user_id = %auth.user_id,
+        "Created organization"
+    );
+
+    Ok(Json(OrgResponse::from(&org)))
+}
+
+/// Get an organization and its shared balance. Members only.
+pub async fn get_org(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(org_id): Path<String>,
+) -> Result<Json<OrgResponse>, ApiError> {
+    let org_id = parse_org_id(&org_id)?;
+    let org = load_org(state.store.as_ref(), &org_id).await?;
+    require_member(state.store.as_ref(), &org_id, &auth.user_id).await?;
+
+    Ok(Json(OrgResponse::from(&org)))
+}
+
+/// List an organization's members with their spend this month. Members only.
+pub async fn list_members(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path(org_id): Path<String>,
+) -> Result<Json<ListOrgMembersResponse>, ApiError> {
+    let org_id = parse_org_id(&org_id)?;
+    load_org(state.store.as_ref(), &org_id).await?;
+    require_member(state.store.as_ref(), &org_id, &auth.user_id).await?;
+
+    let mut members = Vec::new();
+    for membership in state.store.list_org_members(&org_id).await? {
+        members.push(member_response(state.store.as_ref(), &membership).await?);
+    }
+
+    Ok(Json(ListOrgMembersResponse { members }))
+}
+
+/// Add a member or update their role and spend cap. Owners and admins only.
+pub async fn put_member(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((org_id, user_id)): Path<(String, String)>,
+    Json(body): Json<PutOrgMemberRequest>,
+) -> Result<Json<OrgMemberResponse>, ApiError> {
+    let org_id = parse_org_id(&org_id)?;
+    let user_id = parse_user_id(&user_id)?;
+
+    if body.monthly_spend_cap_cents.is_some_and(|cap| cap < 0) {
+        return Err(ApiError::BadRequest(
+            "monthly_spend_cap_cents must not be negative".into(),
+        ));
+    }
+
+    load_org(state.store.as_ref(), &org_id).await?;
+    let actor = require_member(state.store.as_ref(), &org_id, &auth.user_id).await?;
+    let existing = state.store.get_org_membership(&org_id, &user_id).await?;
+    check_can_manage(&actor, existing.as_ref(), Some(body.role))?;
+
+    let mut membership = existing.unwrap_or_else(|| OrgMembership::new(org_id, user_id, body.role));
+    membership.role = body.role;
+    membership.monthly_spend_cap_cents = body.monthly_spend_cap_cents;
+    membership.updated_at = chrono::Utc::now();
+    state.store.put_org_membership(&membership).await?;
+
+    tracing::info!(
+        org_id = %org_id,
+        user_id = %user_id,
+        role = membership.role.as_str(),
+        actor = %auth.user_id,
+        "Updated organization member"
+    );
+
+    Ok(Json(
+        member_response(state.store.as_ref(), &membership).await?,
+    ))
+}
+
+/// Remove a member. Owners and admins only.
+pub async fn remove_member(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Path((org_id, user_id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let org_id = parse_org_id(&org_id)?;
+    let user_id = parse_user_id(&user_id)?;
+
+    load_org(state.store.as_ref(), &org_id).await?;
+    let actor = require_member(state.store.as_ref(), &org_id, &auth.user_id).await?;
+    let existing = state
+        .store
+        .get_org_membership(&org_id, &user_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Member not found: {user_id}")))?;
+    check_can_manage(&actor, Some(&existing), None)?;
+
+    state.store.delete_org_membership(&org_id, &user_id).await?;
+
+    tracing::info!(
+        org_id = %org_id,
+        user_id = %user_id,
+        actor = %auth.user_id,
+        "Removed organization member"
+    );
+
+    Ok(Json(serde_json::json!({ "removed": true })))
+}
+
+/// Admin endpoint to add credits to an organization's pool.
+///
+/// Requires `X-Admin-Key` header for authentication.
+pub async fn admin_add_org_credits(
+    State(state): State<Arc<AppState>>,
+    admin: AdminAuth,
+    Path(org_id): Path<String>,
+    Json(body): Json<AddOrgCreditsRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let org_id = parse_org_id(&org_id)?;
+    let user_id = parse_user_id(&body.user_id)?;
+
+    if body.amount_cents <= 0 {
+        return Err(ApiError::BadRequest("amount_cents must be positive".into()));
+    }
+
+    let org = load_org(state.store.as_ref(), &org_id).await?;
+    if state
+        .store
+        .get_org_membership(&org_id, &user_id)
+        .await?
+        .is_none()
+    {
+        return Err(ApiError::BadRequest(
+            "user_id must be a member of the organization".into(),
+        ));
+    }
+
+    let new_balance = org.balance_cents + body.amount_cents;
+    let tx = CreditTransaction::bonus(user_id, body.amount_cents, new_balance, body.reason.clone())
+        .with_org(org_id);
+    let balance = state
+        .store
+        .add_org_credits(&org_id, body.amount_cents, &tx)
+        .await?;
+
+    tracing::info!(
+        admin_id = %admin.admin_id,
+        org_id = %org_id,
+        amount_cents = %body.amount_cents,
+        reason = %body.reason,
+        new_balance = %balance,
+        "Admin added organization credits"
+    );
+
+    Ok(Json(serde_json::json!({
+        "balance_cents": balance,
+        "transaction_id": tx.id.to_string()
+    })))
+}
@@ -0,0 +1,136 @@
+//! Outbox handlers.
+//!
+//! Side effects waiting in the transactional outbox (Lago usage, Mixpanel
+//! events, zOS syncs, balance notifications). These admin endpoints show
+//! what is stuck and put dead-lettered messages back in the queue.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use z_billing_core::{OutboxId, OutboxMessage, OutboxStatus};
+
+use crate::auth::AdminAuth;
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// List outbox query parameters.
+#[derive(Debug, Deserialize)]
+pub struct ListOutboxParams {
+    /// Only messages in this state: `pending` or `dead_lettered`.
+    pub status: Option<String>,
+    /// Maximum messages to return (default 50, max 500).
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+/// An outbox message.
+#[derive(Debug, Serialize)]
+pub struct OutboxMessageResponse {
+    /// Message ID.
+    pub id: String,
+    /// Destination (`lago_usage`, `mixpanel`, `zos_pro_status` or
+    /// `balance_update`).
+    pub topic: &'static str,
+    /// Topic-specific payload.
+    pub payload: serde_json::Value,
+    /// `pending` or `dead_lettered`.
+    pub status: &'static str,
+    /// Failed delivery attempts so far.
+    pub attempts: u32,
+    /// Earliest time of the next delivery attempt.
+    pub next_attempt_at: String,
+    /// Error from the most recent failed attempt.
+    pub last_error: Option<String>,
+    /// When the message was written.
+    pub created_at: String,
+    /// When the message was last updated.
+    pub updated_at: String,
+}
+
+impl From<&OutboxMessage> for OutboxMessageResponse {
+    fn from(message: &OutboxMessage) -> Self {
+        Self {
+            id: message.id.to_string(),
+            topic: message.topic.as_str(),
+            payload: message.payload.clone(),
+            status: message.status.as_str(),
+            attempts: message.attempts,
+            next_attempt_at: message.next_attempt_at.to_rfc3339(),
+            last_error: message.last_error.clone(),
+            created_at: message.created_at.to_rfc3339(),
+            updated_at: message.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+/// List outbox response.
+#[derive(Debug, Serialize)]
+pub struct ListOutboxResponse {
+    /// Messages, oldest first.
+    pub messages: Vec<OutboxMessageResponse>,
+}
+
+/// List undelivered outbox messages, oldest first.
+///
+/// Requires `X-Admin-Key` header.
+pub async fn list_outbox(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminAuth,
+    Query(params): Query<ListOutboxParams>,
+) -> Result<Json<ListOutboxResponse>, ApiError> {
+    let status = params
+        .status
+        .as_deref()
+        .map(str::parse::<OutboxStatus>)
+        .transpose()
+        .map_err(ApiError::BadRequest)?;
+
+    let messages = state
+        .store
+        .list_outbox_messages(status, params.limit.min(500))
+        .await?;
+
+    Ok(Json(ListOutboxResponse {
+        messages: messages.iter().map(OutboxMessageResponse::from).collect(),
+    }))
+}
+
+/// Queue an outbox message for immediate redelivery with a fresh set of
+/// attempts.
+///
+/// Works on pending messages too, to skip the rest of a long backoff.
+/// Requires `X-Admin-Key` header.
+pub async fn replay_outbox_message(
+    State(state): State<Arc<AppState>>,
+    admin: AdminAuth,
+    Path(message_id): Path<String>,
+) -> Result<Json<OutboxMessageResponse>, ApiError> {
+    let message_id: OutboxId = message_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid outbox message ID".into()))?;
+    let mut message = state
+        .store
+        .get_outbox_message(&message_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Outbox message not found: {message_id}")))?;
+
+    message.replay(chrono::Utc::now());
+    state.store.put_outbox_message(&message).await?;
+    state.wake_outbox();
+
+    tracing::info!(
+        admin_id = %admin.admin_id,
+        outbox_id = %message.id,
+        topic = message.topic.as_str(),
+        "Outbox message replayed"
+    );
+
+    Ok(Json(OutboxMessageResponse::from(&message)))
+}
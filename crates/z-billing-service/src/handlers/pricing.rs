@@ -0,0 +1,103 @@
+//! Pricing catalog handlers.
+//!
+//! Usage is priced from a versioned catalog that can change without a
+//! restart (see [`crate::pricing`]). These admin endpoints show which
+//! version is active and what it changed, save new versions to the store
+//! and trigger a reload.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::Json;
+use serde::Serialize;
+
+use z_billing_core::{PricingCatalog, PricingDiff};
+
+use crate::auth::AdminAuth;
+use crate::error::ApiError;
+use crate::pricing::{self, ActivePricing};
+use crate::state::AppState;
+
+/// The active pricing catalog.
+#[derive(Debug, Serialize)]
+pub struct PricingStatusResponse {
+    /// Version of the active catalog (`builtin` for the compiled-in one).
+    pub version: String,
+    /// Where it was read from: `builtin`, `file` or `store`.
+    pub source: &'static str,
+    /// When it became active.
+    pub loaded_at: String,
+    /// Number of models with their own pricing.
+    pub model_count: usize,
+    /// Version of the catalog it replaced.
+    pub previous_version: Option<String>,
+    /// What changed from the previous catalog.
+    pub diff: Option<PricingDiff>,
+}
+
+impl From<&ActivePricing> for PricingStatusResponse {
+    fn from(active: &ActivePricing) -> Self {
+        Self {
+            version: active.catalog.version.clone(),
+            source: active.source.as_str(),
+            loaded_at: active.loaded_at.to_rfc3339(),
+            model_count: active.catalog.models.len(),
+            previous_version: active
+                .previous
+                .as_ref()
+                .map(|previous| previous.version.clone()),
+            diff: active.diff(),
+        }
+    }
+}
+
+/// Get the active catalog version and its diff against the previous one.
+///
+/// Requires `X-Admin-Key` header.
+pub async fn get_pricing(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminAuth,
+) -> Json<PricingStatusResponse> {
+    Json(PricingStatusResponse::from(state.pricing().as_ref()))
+}
+
+/// Save a new catalog version to the store and make it active.
+///
+/// Only available when pricing is not loaded from a file. Requires
+/// `X-Admin-Key` header.
+pub async fn put_pricing(
+    State(state): State<Arc<AppState>>,
+    admin: AdminAuth,
+    Json(catalog): Json<PricingCatalog>,
+) -> Result<Json<PricingStatusResponse>, ApiError> {
+    if state.config.pricing_catalog_path.is_some() {
+        return Err(ApiError::Conflict(
+            "Pricing is loaded from PRICING_CATALOG_PATH; edit the file instead".into(),
+        ));
+    }
+    catalog
+        .validate()
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    state.store.put_pricing_catalog(&catalog).await?;
+    tracing::info!(
+        admin_id = %admin.admin_id,
+        version = %catalog.version,
+        "Pricing catalog saved"
+    );
+
+    pricing::reload(&state).await?;
+    Ok(Json(PricingStatusResponse::from(state.pricing().as_ref())))
+}
+
+/// Re-read the catalog source now instead of waiting for the next poll.
+///
+/// Requires `X-Admin-Key` header.
+pub async fn reload_pricing(
+    State(state): State<Arc<AppState>>,
+    admin: AdminAuth,
+) -> Result<Json<PricingStatusResponse>, ApiError> {
+    let changed = pricing::reload(&state).await?;
+    tracing::info!(admin_id = %admin.admin_id, changed, "Pricing reload requested");
+    Ok(Json(PricingStatusResponse::from(state.pricing().as_ref())))
+}
@@ -0,0 +1,313 @@
+//! Promo code handlers.
+
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use z_billing_core::{CreditTransaction, Plan, PromoCode, PromoRedemption};
+
+use crate::auth::{AdminAuth, AuthUser};
+use crate::error::ApiError;
+use crate::state::AppState;
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Minimum code length.
+const MIN_CODE_LEN: usize = 3;
+
+/// Maximum code length.
+const MAX_CODE_LEN: usize = 32;
+
+/// Promo code settings (everything but the code itself).
+#[derive(Debug, Deserialize)]
+pub struct PromoCodeSettings {
+    /// Credits granted per redemption (in cents).
+    pub amount_cents: i64,
+    /// Most redemptions across all users (omit for unlimited).
+    #[serde(default)]
+    pub max_redemptions: Option<i64>,
+    /// Most redemptions by a single user (default 1).
+    #[serde(default = "default_per_user_limit")]
+    pub per_user_limit: i64,
+    /// Start of the validity window.
+    #[serde(default)]
+    pub valid_from: Option<DateTime<Utc>>,
+    /// End of the validity window.
+    #[serde(default)]
+    pub valid_until: Option<DateTime<Utc>>,
+    /// Plans whose users may redeem the code (empty = any plan).
+    #[serde(default)]
+    pub plans: Vec<Plan>,
+    /// Whether the code can be redeemed (default true).
+    #[serde(default = "default_active")]
+    pub active: bool,
+    /// Internal note on what the code is for.
+    #[serde(default)]
+    pub description: String,
+}
+
+const fn default_per_user_limit() -> i64 {
+    1
+}
+
+const fn default_active() -> bool {
+    true
+}
+
+impl PromoCodeSettings {
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.amount_cents <= 0 {
+            return Err(ApiError::BadRequest("amount_cents must be positive".into()));
+        }
+        if self.max_redemptions.is_some_and(|max| max <= 0) {
+            return Err(ApiError::BadRequest(
+                "max_redemptions must be positive".into(),
+            ));
+        }
+        if self.per_user_limit <= 0 {
+            return Err(ApiError::BadRequest(
+                "per_user_limit must be positive".into(),
+            ));
+        }
+        if let (Some(from), Some(until)) = (self.valid_from, self.valid_until) {
+            if until <= from {
+                return Err(ApiError::BadRequest(
+                    "valid_until must be after valid_from".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(self, promo: &mut PromoCode) {
+        promo.amount_cents = self.amount_cents;
+        promo.max_redemptions = self.max_redemptions;
+        promo.per_user_limit = self.per_user_limit;
+        promo.valid_from = self.valid_from;
+        promo.valid_until = self.valid_until;
+        promo.plans = self.plans;
+        promo.active = self.active;
+        promo.description = self.description;
+        promo.updated_at = Utc::now();
+    }
+}
+
+/// Create promo code request.
+#[derive(Debug, Deserialize)]
+pub struct CreatePromoCodeRequest {
+    /// The code users enter (matched case-insensitively).
+    pub code: String,
+    /// Code settings.
+    #[serde(flatten)]
+    pub settings: PromoCodeSettings,
+}
+
+/// Redeem promo code request.
+#[derive(Debug, Deserialize)]
+pub struct RedeemPromoCodeRequest {
+    /// The code to redeem.
+    pub code: String,
+}
+
+/// Redeem promo code response.
+#[derive(Debug, Serialize)]
+pub struct RedeemPromoCodeResponse {
+    /// The redeemed code.
+    pub code: String,
+    /// Credits granted in cents.
+    pub amount_cents: i64,
+    /// New balance in cents.
+    pub balance_cents: i64,
+    /// Bonus transaction ID.
+    pub transaction_id: String,
+}
+
+/// Normalize a code from a request and check its format.
+fn parse_code(code: &str) -> Result<String, ApiError> {
+    let code = PromoCode::normalize(code);
+    let valid_chars = code
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_chars || !(MIN_CODE_LEN..=MAX_CODE_LEN).contains(&code.len()) {
+        return Err(ApiError::BadRequest(format!(
+            "code must be {MIN_CODE_LEN}-{MAX_CODE_LEN} letters, digits, '-' or '_'"
+        )));
+    }
+    Ok(code)
+}
+
+async fn load_promo(state: &AppState, code: &str) -> Result<PromoCode, ApiError> {
+    state
+        .store
+        .get_promo_code(code)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Promo code not found: {code}")))
+}
+
+// ============================================================================
+// Admin
+// ============================================================================
+
+/// Admin endpoint to create a promo code.
+///
+/// Requires `X-Admin-Key` header for authentication.
+pub async fn admin_create_promo(
+    State(state): State<Arc<AppState>>,
+    admin: AdminAuth,
+    Json(body): Json<CreatePromoCodeRequest>,
+) -> Result<Json<PromoCode>, ApiError> {
+    let code = parse_code(&body.code)?;
+    body.settings.validate()?;
+
+    if state.store.get_promo_code(&code).await?.is_some() {
+        return Err(ApiError::Conflict(format!(
+            "Promo code already exists: {code}"
+        )));
+    }
+
+    let mut promo = PromoCode::new(&code, body.settings.amount_cents);
+    body.settings.apply(&mut promo);
+    state.store.put_promo_code(&promo).await?;
+
+    tracing::info!(
+        admin_id = %admin.admin_id,
+        code = %promo.code,
+        amount_cents = %promo.amount_cents,
+        "Promo code created"
+    );
+
+    Ok(Json(promo))
+}
+
+/// Admin endpoint to list all promo codes.
+///
+/// Requires `X-Admin-Key` header for authentication.
+pub async fn admin_list_promos(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminAuth,
+) -> Result<Json<Vec<PromoCode>>, ApiError> {
+    Ok(Json(state.store.list_promo_codes().await?))
+}
+
+/// Admin endpoint to get a promo code and its redemption count.
+///
+/// Requires `X-Admin-Key` header for authentication.
+pub async fn admin_get_promo(
+    State(state): State<Arc<AppState>>,
+    _admin: AdminAuth,
+    Path(code): Path<String>,
+) -> Result<Json<PromoCode>, ApiError> {
+    let code = PromoCode::normalize(&code);
+    Ok(Json(load_promo(&state, &code).await?))
+}
+
+/// Admin endpoint to replace a promo code's settings.
+///
+/// The redemption count is kept, so lowering `max_redemptions` below it
+/// exhausts the code.
+///
+/// Requires `X-Admin-Key` header for authentication.
+pub async fn admin_update_promo(
+    State(state): State<Arc<AppState>>,
+    admin: AdminAuth,
+    Path(code): Path<String>,
+    Json(body): Json<PromoCodeSettings>,
+) -> Result<Json<PromoCode>, ApiError> {
+    let code = PromoCode::normalize(&code);
+    body.validate()?;
+
+    let mut promo = load_promo(&state, &code).await?;
+    body.apply(&mut promo);
+    state.store.put_promo_code(&promo).await?;
+
+    tracing::info!(
+        admin_id = %admin.admin_id,
+        code = %promo.code,
+        active = promo.active,
+        "Promo code updated"
+    );
+
+    Ok(Json(load_promo(&state, &code).await?))
+}
+
+/// Admin endpoint to delete a promo code.
+///
+/// Past redemptions and their transactions are kept.
+///
+/// Requires `X-Admin-Key` header for authentication.
+pub async fn admin_delete_promo(
+    State(state): State<Arc<AppState>>,
+    admin: AdminAuth,
+    Path(code): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let code = PromoCode::normalize(&code);
+    load_promo(&state, &code).await?;
+
+    state.store.delete_promo_code(&code).await?;
+
+    tracing::info!(admin_id = %admin.admin_id, code = %code, "Promo code deleted");
+
+    Ok(Json(serde_json::json!({ "deleted": true })))
+}
+
+// ============================================================================
+// Redemption
+// ============================================================================
+
+/// Redeem a promo code for bonus credits.
+///
+/// The code's rules are re-checked by the store while the code is locked,
+/// so concurrent redemptions cannot exceed its limits.
+pub async fn redeem_promo(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<RedeemPromoCodeRequest>,
+) -> Result<Json<RedeemPromoCodeResponse>, ApiError> {
+    let code = PromoCode::normalize(&body.code);
+    if code.is_empty() {
+        return Err(ApiError::BadRequest("code is required".into()));
+    }
+
+    let promo = load_promo(&state, &code).await?;
+    let account = state
+        .store
+        .get_account(&auth.user_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
+
+    let tx = CreditTransaction::promo(
+        auth.user_id,
+        promo.amount_cents,
+        account.balance_cents + promo.amount_cents,
+        &promo.code,
+    );
+    let redemption =
+        PromoRedemption::new(promo.code.clone(), auth.user_id, tx.id, promo.amount_cents);
+
+    let outbox = [crate::outbox::balance_update(&auth.user_id)];
+    let balance = state
+        .store
+        .redeem_promo_code(&redemption, &tx, &outbox)
+        .await?;
+    crate::outbox::committed(&state, &outbox).await;
+
+    tracing::info!(
+        user_id = %auth.user_id,
+        code = %promo.code,
+        amount_cents = %promo.amount_cents,
+        new_balance = %balance,
+        "Promo code redeemed"
+    );
+
+    Ok(Json(RedeemPromoCodeResponse {
+        code: promo.code,
+        amount_cents: promo.amount_cents,
+        balance_cents: balance,
+        transaction_id: tx.id.to_string(),
+    }))
+}
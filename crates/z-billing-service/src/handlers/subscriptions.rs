@@ -10,6 +10,7 @@ use z_billing_core::Plan;
 
 use crate::auth::AuthUser;
 use crate::error::ApiError;
+use crate::handlers::accounts::upsert_account;
 use crate::state::AppState;
 
 // ============================================================================
@@ -86,7 +87,7 @@ pub async fn checkout(
     })?;
 
     // Check if user already has a Stripe customer ID
-    let account = state.store.get_account(&auth.user_id)?;
+    let account = state.store.get_account(&auth.user_id).await?;
     let customer_id = account.as_ref().and_then(|a| a.stripe_customer_id.as_deref());
 
     // Prevent duplicate subscriptions — if user has any subscription (active or
@@ -142,7 +143,8 @@ pub async fn portal(
 
     let account = state
         .store
-        .get_account(&auth.user_id)?
+        .get_account(&auth.user_id)
+        .await?
         .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
 
     let customer_id = account.stripe_customer_id.as_deref().ok_or_else(|| {
@@ -172,7 +174,8 @@ pub async fn status(
 ) -> Result<Json<SubscriptionStatusResponse>, ApiError> {
     let account = state
         .store
-        .get_account(&auth.user_id)?
+        .get_account(&auth.user_id)
+        .await?
         .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
 
     let plan = account.current_plan();
@@ -266,7 +269,7 @@ pub async fn subscribe_zero_pro(
     })?;
 
     // Check for existing subscription
-    let account = state.store.get_account(&auth.user_id)?;
+    let account = state.store.get_account(&auth.user_id).await?;
     if let Some(ref acc) = account {
         if acc.subscription.is_some() {
             return Err(ApiError::BadRequest(
@@ -292,10 +295,10 @@ pub async fn subscribe_zero_pro(
             .map_err(|e| ApiError::Internal(format!("Failed to create customer: {e}")))?;
 
         // Save customer ID
-        let mut acc = account.unwrap_or_else(|| z_billing_core::Account::new(auth.user_id));
-        acc.stripe_customer_id = Some(customer.id.clone());
-        acc.updated_at = chrono::Utc::now();
-        state.store.put_account(&acc)?;
+        upsert_account(state.store.as_ref(), &auth.user_id, |acc| {
+            acc.stripe_customer_id = Some(customer.id.clone());
+        })
+        .await?;
 
         customer.id
     };
@@ -346,7 +349,7 @@ pub async fn status_zero_pro(
     State(state): State<Arc<AppState>>,
     auth: AuthUser,
 ) -> Result<Json<ZosStatusResponse>, ApiError> {
-    let account = state.store.get_account(&auth.user_id)?;
+    let account = state.store.get_account(&auth.user_id).await?;
 
     let subscription = account
         .and_then(|acc| {
@@ -376,7 +379,8 @@ pub async fn cancel_zero_pro(
 ) -> Result<Json<ZosCancelResponse>, ApiError> {
     let account = state
         .store
-        .get_account(&auth.user_id)?
+        .get_account(&auth.user_id)
+        .await?
         .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
 
     let sub = account
@@ -0,0 +1,261 @@
+//! Credit transfer handlers.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use z_billing_core::{lot, CreditTransaction, CreditTransfer, LedgerAccount, OrgId, UserId};
+use z_billing_store::Store;
+
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use crate::state::AppState;
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Minimum transfer amount in cents ($1).
+const MIN_TRANSFER_CENTS: i64 = 100;
+
+/// Maximum transfer amount in cents ($1000).
+const MAX_TRANSFER_CENTS: i64 = 100_000;
+
+/// Maximum idempotency key length.
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 128;
+
+/// Transfer credits request.
+#[derive(Debug, Deserialize)]
+pub struct TransferCreditsRequest {
+    /// User to send the credits to (exclusive with `to_org_id`).
+    #[serde(default)]
+    pub to_user_id: Option<String>,
+    /// Organization pool to send the credits to (exclusive with `to_user_id`).
+    #[serde(default)]
+    pub to_org_id: Option<String>,
+    /// Amount in cents.
+    pub amount_cents: i64,
+    /// Caller-chosen key; retrying with the same key returns the original
+    /// transfer.
+    pub idempotency_key: String,
+    /// Execute the transfer. Without it the request only previews it.
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// Transfer credits response.
+#[derive(Debug, Serialize)]
+pub struct TransferCreditsResponse {
+    /// Whether the transfer was made (false for a preview).
+    pub completed: bool,
+    /// Recipient wallet (`user:<id>` or `org:<id>`).
+    pub to: String,
+    /// Amount in cents.
+    pub amount_cents: i64,
+    /// Caller's balance in cents (after the transfer, if completed).
+    pub balance_cents: i64,
+    /// Purchased credits the caller can still transfer.
+    pub transferable_cents: i64,
+    /// Debit transaction ID on the caller, if completed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_transaction_id: Option<String>,
+    /// Credit transaction ID on the recipient, if completed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_transaction_id: Option<String>,
+}
+
+/// Resolve the recipient wallet and check the caller may send to it.
+async fn resolve_recipient(
+    store: &dyn Store,
+    sender: &UserId,
+    body: &TransferCreditsRequest,
+) -> Result<LedgerAccount, ApiError> {
+    match (&body.to_user_id, &body.to_org_id) {
+        (Some(user_id), None) => {
+            let user_id: UserId = user_id
+                .parse()
+                .map_err(|_| ApiError::BadRequest("Invalid user ID".into()))?;
+            if user_id == *sender {
+                return Err(ApiError::BadRequest(
+                    "Cannot transfer credits to yourself".into(),
+                ));
+            }
+            if store.get_account(&user_id).await?.is_none() {
+                return Err(ApiError::NotFound("Recipient account not found".into()));
+            }
+            Ok(LedgerAccount::User(user_id))
+        }
+        (None, Some(org_id)) => {
+            let org_id: OrgId = org_id
+                .parse()
+                .map_err(|_| ApiError::BadRequest("Invalid organization ID".into()))?;
+            if store.get_organization(&org_id).await?.is_none() {
+                return Err(ApiError::NotFound(format!(
+                    "Organization not found: {org_id}"
+                )));
+            }
+            // Only members may fund an organization's pool
+            if store.get_org_membership(&org_id, sender).await?.is_none() {
+                return Err(ApiError::Forbidden);
+            }
+            Ok(LedgerAccount::Org(org_id))
+        }
+        _ => Err(ApiError::BadRequest(
+            "Exactly one of to_user_id or to_org_id is required".into(),
+        )),
+    }
+}
+
+/// Purchased credits `user_id` can transfer right now.
+///
+/// Reserved credits and the credit line are never transferable.
+async fn transferable_cents(
+    store: &dyn Store,
+    user_id: &UserId,
+    balance_cents: i64,
+) -> Result<i64, ApiError> {
+    let unreserved = balance_cents - store.reserved_cents(user_id).await?;
+    let purchased = lot::transferable_cents(&store.list_credit_lots(user_id).await?);
+    Ok(unreserved.min(purchased).max(0))
+}
+
+fn completed_response(
+    transfer: &CreditTransfer,
+    balance_cents: i64,
+    transferable_cents: i64,
+) -> TransferCreditsResponse {
+    TransferCreditsResponse {
+        completed: true,
+        to: transfer.to.to_string(),
+        amount_cents: transfer.amount_cents,
+        balance_cents,
+        transferable_cents,
+        out_transaction_id: Some(transfer.out_transaction_id.to_string()),
+        in_transaction_id: Some(transfer.in_transaction_id.to_string()),
+    }
+}
+
+/// Send purchased credits to another user or to an organization pool.
+///
+/// Without `confirm: true` the transfer is only previewed: the request is
+/// validated and the caller's transferable credits are returned. Retrying a
+/// confirmed transfer with the same `idempotency_key` returns the original
+/// result without moving credits again.
+pub async fn transfer_credits(
+    State(state): State<Arc<AppState>>,
+    auth: AuthUser,
+    Json(body): Json<TransferCreditsRequest>,
+) -> Result<Json<TransferCreditsResponse>, ApiError> {
+    let key = body.idempotency_key.trim();
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "idempotency_key must be 1-{MAX_IDEMPOTENCY_KEY_LEN} characters"
+        )));
+    }
+    if !(MIN_TRANSFER_CENTS..=MAX_TRANSFER_CENTS).contains(&body.amount_cents) {
+        return Err(ApiError::BadRequest(format!(
+            "amount_cents must be between {MIN_TRANSFER_CENTS} and {MAX_TRANSFER_CENTS}"
+        )));
+    }
+
+    let store = state.store.as_ref();
+    let to = resolve_recipient(store, &auth.user_id, &body).await?;
+
+    let account = store
+        .get_account(&auth.user_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
+
+    if let Some(existing) = store.get_credit_transfer(&auth.user_id, key).await? {
+        if existing.to != to || existing.amount_cents != body.amount_cents {
+            return Err(ApiError::Conflict(format!(
+                "Idempotency key {key} was already used for a different transfer"
+            )));
+        }
+        let transferable = transferable_cents(store, &auth.user_id, account.balance_cents).await?;
+        return Ok(Json(completed_response(
+            &existing,
+            account.balance_cents,
+            transferable,
+        )));
+    }
+
+    let transferable = transferable_cents(store, &auth.user_id, account.balance_cents).await?;
+    if !body.confirm {
+        return Ok(Json(TransferCreditsResponse {
+            completed: false,
+            to: to.to_string(),
+            amount_cents: body.amount_cents,
+            balance_cents: account.balance_cents,
+            transferable_cents: transferable,
+            out_transaction_id: None,
+            in_transaction_id: None,
+        }));
+    }
+
+    let recipient_balance = match to {
+        LedgerAccount::User(user_id) => store
+            .get_account(&user_id)
+            .await?
+            .map_or(0, |a| a.balance_cents),
+        LedgerAccount::Org(org_id) => store
+            .get_organization(&org_id)
+            .await?
+            .map_or(0, |org| org.balance_cents),
+        LedgerAccount::System(_) => 0,
+    };
+    let debit = CreditTransaction::transfer_out(
+        auth.user_id,
+        &to,
+        body.amount_cents,
+        account.balance_cents - body.amount_cents,
+        key,
+    );
+    let credit = CreditTransaction::transfer_in(
+        auth.user_id,
+        &to,
+        body.amount_cents,
+        recipient_balance + body.amount_cents,
+        key,
+    );
+    let transfer = CreditTransfer::new(
+        auth.user_id,
+        key.to_string(),
+        to,
+        body.amount_cents,
+        debit.id,
+        credit.id,
+    );
+
+    // Org pools have no balance subscribers
+    let mut outbox = vec![crate::outbox::balance_update(&auth.user_id)];
+    if let LedgerAccount::User(user_id) = to {
+        outbox.push(crate::outbox::balance_update(&user_id));
+    }
+    let transfer = store
+        .transfer_credits(&transfer, &debit, &credit, &outbox)
+        .await?;
+    crate::outbox::committed(&state, &outbox).await;
+
+    let sender_balance = store
+        .get_account(&auth.user_id)
+        .await?
+        .map_or(account.balance_cents, |a| a.balance_cents);
+
+    tracing::info!(
+        user_id = %auth.user_id,
+        to = %transfer.to,
+        amount_cents = %transfer.amount_cents,
+        idempotency_key = %transfer.idempotency_key,
+        "Credits transferred"
+    );
+
+    let transferable = transferable_cents(store, &auth.user_id, sender_balance).await?;
+    Ok(Json(completed_response(
+        &transfer,
+        sender_balance,
+        transferable,
+    )))
+}
@@ -1,49 +1,56 @@
 //! Usage event handlers.
 
 use std::sync::Arc;
-use std::time::Duration;
 
-use axum::extract::State;
+use axum::extract::{Path, State};
 use axum::Json;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use z_billing_core::{
-    Account, AgentId, CreditTransaction, LlmProvider, TokenDirection, UsageEvent, UsageMetric,
-    UsageSource, UserId,
+    Account, AgentId, CreditTransaction, ImageResolution, LlmProvider, LlmTokenUsage, OrgId,
+    Organization, OutboxMessage, Reservation, ReservationId, StorageMeter, TokenDirection,
+    UsageEvent, UsageMetric, UsageReversal, UsageSource, UserId,
 };
 use z_billing_store::Store;
 
 use crate::auth::ServiceAuth;
 use crate::error::ApiError;
+use crate::handlers::accounts::get_or_create_account;
+use crate::pricing::ActivePricing;
 use crate::state::AppState;
-use crate::stripe::StripeClient;
 
-/// Get an account by user ID, creating it with zero balance if it doesn't exist.
-fn get_or_create_account(store: &dyn Store, user_id: &UserId) -> Result<Account, ApiError> {
-    if let Some(account) = store.get_account(user_id)? {
-        return Ok(account);
+/// Load the organization paying for a member's usage.
+///
+/// Fails with `Forbidden` if the user is not a member of the organization.
+async fn org_for_member(
+    store: &dyn Store,
+    org_id: Option<&str>,
+    user_id: &UserId,
+) -> Result<Option<Organization>, ApiError> {
+    let Some(org_id) = org_id else {
+        return Ok(None);
+    };
+    let org_id: OrgId = org_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid organization ID".into()))?;
+
+    let org = store
+        .get_organization(&org_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Organization not found: {org_id}")))?;
+
+    if store.get_org_membership(&org_id, user_id).await?.is_none() {
+        return Err(ApiError::Forbidden);
     }
 
-    // Auto-create account with zero balance
-    let account = Account::new(*user_id);
-    store.put_account(&account)?;
-    tracing::info!(user_id = %user_id, "Auto-created billing account");
-    Ok(account)
+    Ok(Some(org))
 }
 
 // ============================================================================
 // Constants
 // ============================================================================
 
-/// Maximum number of retry attempts for Lago forwarding.
-const LAGO_MAX_RETRIES: u32 = 3;
-
-/// Initial backoff duration for retries (doubles with each attempt).
-const LAGO_INITIAL_BACKOFF_MS: u64 = 100;
-
-/// Maximum backoff duration for retries.
-const LAGO_MAX_BACKOFF_MS: u64 = 5000;
-
 /// Number of API calls that consume 1 credit.
 ///
 /// This defines the conversion rate for API call billing:
@@ -51,6 +58,13 @@ const LAGO_MAX_BACKOFF_MS: u64 = 5000;
 /// - Minimum charge is always 1 credit
 const API_CALLS_PER_CREDIT: u64 = 1000;
 
+/// How far ahead of the server clock a usage timestamp may be, to allow
+/// for clock skew between services.
+const MAX_USAGE_CLOCK_SKEW_SECONDS: i64 = 300;
+
+/// Longest lifetime a caller may request for a credit reservation.
+const MAX_RESERVATION_TTL_SECONDS: u64 = 3600;
+
 /// Usage event request from services.
 #[derive(Debug, Deserialize)]
 pub struct UsageRequest {
@@ -64,6 +78,10 @@ pub struct UsageRequest {
     pub metric: UsageMetricRequest,
     /// Pre-calculated cost in cents (optional, will be calculated if not provided).
     pub cost_cents: Option<i64>,
+    /// When the usage happened (defaults to now). Late or replayed usage
+    /// is priced at the rates in effect at this time.
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
     /// Whether the user has a ZERO Pro entitlement. This is separate from
     /// z-billing subscription plans and controls LLM markup.
     #[serde(
@@ -76,10 +94,14 @@ pub struct UsageRequest {
     /// Additional metadata.
     #[serde(default)]
     pub metadata: serde_json::Value,
+    /// Organization whose shared pool pays for the usage. The acting user
+    /// must be a member and is still recorded on the transaction.
+    #[serde(default)]
+    pub org_id: Option<String>,
 }
 
 /// Usage metric in request format.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum UsageMetricRequest {
     /// LLM token usage.
@@ -88,10 +110,19 @@ pub enum UsageMetricRequest {
         provider: String,
         /// Model name.
         model: String,
-        /// Input tokens.
+        /// Uncached input tokens.
         input_tokens: u64,
         /// Output tokens.
         output_tokens: u64,
+        /// Input tokens read from the prompt cache.
+        #[serde(default)]
+        cache_read_input_tokens: u64,
+        /// Input tokens written to the prompt cache.
+        #[serde(default)]
+        cache_creation_input_tokens: u64,
+        /// Whether the request went through the provider's batch API.
+        #[serde(default)]
+        batch: bool,
     },
     /// Compute usage.
     Compute {
@@ -107,6 +138,55 @@ pub enum UsageMetricRequest {
         /// Number of calls.
         count: u64,
     },
+    /// Storage usage.
+    Storage {
+        /// GB-hours of storage used.
+        gb_hours: f64,
+    },
+    /// Image generation.
+    ImageGeneration {
+        /// Provider name.
+        provider: String,
+        /// Model name.
+        model: String,
+        /// Number of images.
+        count: u64,
+        /// Resolution tier (defaults to standard).
+        #[serde(default)]
+        resolution: ImageResolution,
+    },
+    /// Audio transcription.
+    AudioTranscription {
+        /// Provider name.
+        provider: String,
+        /// Model name.
+        model: String,
+        /// Seconds of audio.
+        seconds: f64,
+    },
+    /// Text-to-speech.
+    TextToSpeech {
+        /// Provider name.
+        provider: String,
+        /// Model name.
+        model: String,
+        /// Characters of input text.
+        characters: u64,
+    },
+    /// Web search.
+    WebSearch {
+        /// Provider name.
+        provider: String,
+        /// Number of queries.
+        queries: u64,
+    },
+    /// Paid tool calls.
+    ToolCalls {
+        /// Tool name.
+        tool: String,
+        /// Number of calls.
+        count: u64,
+    },
 }
 
 /// Usage response.
@@ -114,7 +194,8 @@ pub enum UsageMetricRequest {
 pub struct UsageResponse {
     /// Whether the usage was processed successfully.
     pub success: bool,
-    /// New balance after deduction.
+    /// New balance after deduction (the organization pool's balance when
+    /// the usage was charged to an organization).
     pub balance_cents: i64,
     /// Cost deducted.
     pub cost_cents: i64,
@@ -127,6 +208,9 @@ pub struct UsageResponse {
 pub struct UsageQuoteRequest {
     /// Usage metric details.
     pub metric: UsageMetricRequest,
+    /// Price the usage as of this time (defaults to now).
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
     /// Whether the user has a ZERO Pro entitlement. This is separate from
     /// z-billing subscription plans and controls LLM markup.
     #[serde(
@@ -159,9 +243,10 @@ pub async fn quote_usage(
     );
 
     let cost_cents = calculate_cost(
-        &state.config.pricing,
+        &state.pricing().config,
         body.zero_pro_user.unwrap_or(false),
         &body.metric,
+        body.timestamp.unwrap_or_else(Utc::now),
     );
 
     Ok(Json(UsageQuoteResponse {
@@ -176,8 +261,21 @@ pub async fn report_usage(
     auth: ServiceAuth,
     Json(body): Json<UsageRequest>,
 ) -> Result<Json<UsageResponse>, ApiError> {
+    charge_usage(&state, &auth.service_name, body, None)
+        .await
+        .map(Json)
+}
+
+/// Debit a usage event, optionally settling a reservation, and queue the
+/// side effects (analytics, balance broadcast, Lago) in the outbox.
+async fn charge_usage(
+    state: &Arc<AppState>,
+    service_name: &str,
+    body: UsageRequest,
+    reservation_id: Option<ReservationId>,
+) -> Result<UsageResponse, ApiError> {
     tracing::debug!(
-        service = %auth.service_name,
+        service = %service_name,
         event_id = %body.event_id,
         user_id = %body.user_id,
         "Processing usage event"
@@ -196,14 +294,24 @@ pub async fn report_usage(
         .transpose()
         .map_err(|_| ApiError::BadRequest("Invalid agent ID".into()))?;
 
+    let org = org_for_member(state.store.as_ref(), body.org_id.as_deref(), &user_id).await?;
+    if org.is_some() && reservation_id.is_some() {
+        return Err(ApiError::BadRequest(
+            "Reservations cannot be settled against an organization".into(),
+        ));
+    }
+
+    let occurred_at = usage_timestamp(body.timestamp)?;
+
     // Get or create account before processing usage so balance/account state exists.
-    let account = get_or_create_account(state.store.as_ref(), &user_id)?;
+    let account = get_or_create_account(state.store.as_ref(), &user_id).await?;
     let zero_pro_user = usage_zero_pro_user(&body);
 
     // Calculate cost if not provided
-    let cost_cents = body
-        .cost_cents
-        .unwrap_or_else(|| calculate_cost(&state.config.pricing, zero_pro_user, &body.metric));
+    let pricing = state.pricing();
+    let cost_cents = body.cost_cents.unwrap_or_else(|| {
+        calculate_cost(&pricing.config, zero_pro_user, &body.metric, occurred_at)
+    });
 
     if cost_cents < 0 {
         return Err(ApiError::BadRequest(
@@ -211,37 +319,71 @@ pub async fn report_usage(
         ));
     }
 
+    let new_balance = org
+        .as_ref()
+        .map_or(account.balance_cents, |org| org.balance_cents)
+        - cost_cents;
+
+    // Create transaction
+    let description = format_usage_description(&body.metric, service_name);
+    let mut tx = CreditTransaction::usage(
+        user_id,
+        cost_cents,
+        new_balance,
+        description,
+        transaction_metadata(&body, &pricing, occurred_at),
+    );
+    if let Some(org) = &org {
+        tx = tx.with_org(org.id);
+    }
+
     // Build usage event
     let (metric, quantity) = convert_metric(&body.metric);
     let event = UsageEvent {
         event_id: body.event_id.clone(),
         user_id,
         agent_id,
-        source: UsageSource::Custom(auth.service_name.clone()),
+        source: UsageSource::Custom(service_name.to_string()),
         metric,
         quantity,
         cost_cents,
-        timestamp: chrono::Utc::now(),
+        timestamp: occurred_at,
         metadata: body.metadata.clone(),
+        transaction_id: Some(tx.id),
     };
 
-    let new_balance = account.balance_cents - cost_cents;
-
-    // Create transaction
-    let description = format_usage_description(&body.metric, &auth.service_name);
-    let tx = CreditTransaction::usage(
+    // Side effects are written with the charge and delivered by the
+    // outbox dispatcher
+    let outbox = usage_outbox(
+        state,
+        &body,
         user_id,
+        service_name,
         cost_cents,
         new_balance,
-        description,
-        body.metadata.clone(),
+        org.is_some(),
     );
 
     // Process usage atomically
-    let balance = state.store.process_usage(&event, &tx)?;
+    let balance = match (reservation_id, &org) {
+        (Some(reservation_id), _) => {
+            state
+                .store
+                .settle_reservation(&reservation_id, &event, &tx, &outbox)
+                .await?
+        }
+        (None, Some(org)) => {
+            state
+                .store
+                .process_org_usage(&org.id, &event, &tx, &outbox)
+                .await?
+        }
+        (None, None) => state.store.process_usage(&event, &tx, &outbox).await?,
+    };
+    crate::outbox::committed(state, &outbox).await;
 
     tracing::info!(
-        service = %auth.service_name,
+        service = %service_name,
         event_id = %body.event_id,
         user_id = %user_id,
         cost_cents = %cost_cents,
@@ -249,69 +391,75 @@ pub async fn report_usage(
         "Usage processed"
     );
 
-    {
-        let mut props = serde_json::json!({
-            "cost_cents": cost_cents,
-            "billed_cost_cents": cost_cents,
-            "balance_after": balance,
-            "service": auth.service_name.clone(),
-        });
-        if let UsageMetricRequest::LlmTokens {
-            ref provider,
-            ref model,
-            input_tokens,
-            output_tokens,
-        } = body.metric
-        {
-            props["provider"] = serde_json::json!(provider);
-            props["model"] = serde_json::json!(model);
-            props["input_tokens"] = serde_json::json!(input_tokens);
-            props["output_tokens"] = serde_json::json!(output_tokens);
-        }
-        append_cost_observability_properties(
-            &mut props,
-            &body.metadata,
-            cost_cents,
-            &auth.service_name,
-        );
-        crate::mixpanel::track(
-            state.config.mixpanel_token.as_deref(),
-            "tokens_consumed",
-            &user_id.to_string(),
-            props,
-        );
+    // Auto-refill is untouched by org usage
+    if org.is_none() {
+        // Check for auto-refill trigger (async, non-blocking)
+        maybe_trigger_auto_refill(state, &account, user_id, balance);
     }
 
-    // Broadcast balance update to WebSocket clients
-    #[allow(clippy::cast_precision_loss)]
-    let _ = state.balance_tx.send(
-        serde_json::json!({
-            "type": "balance.updated",
-            "userId": user_id.to_string(),
-            "balanceCents": balance,
-            "balanceFormatted": format!("${:.2}", balance as f64 / 100.0),
-        })
-        .to_string(),
-    );
-
-    // Check for auto-refill trigger (async, non-blocking)
-    maybe_trigger_auto_refill(&state, &account, user_id, balance);
-
-    // Forward to Lago for analytics (async, non-blocking, with retries)
-    maybe_forward_to_lago(
-        &state,
-        &body.event_id,
-        user_id,
-        agent_id_str.as_deref(),
-        &body.metric,
-    );
-
-    Ok(Json(UsageResponse {
+    Ok(UsageResponse {
         success: true,
         balance_cents: balance,
         cost_cents,
         transaction_id: tx.id.to_string(),
-    }))
+    })
+}
+
+/// Build the outbox messages written with a usage charge: the analytics
+/// event, the balance broadcast and the Lago event.
+fn usage_outbox(
+    state: &AppState,
+    body: &UsageRequest,
+    user_id: UserId,
+    service_name: &str,
+    cost_cents: i64,
+    new_balance: i64,
+    org_usage: bool,
+) -> Vec<OutboxMessage> {
+    let mut outbox = Vec::new();
+    let mut props = serde_json::json!({
+        "cost_cents": cost_cents,
+        "billed_cost_cents": cost_cents,
+        "balance_after": new_balance,
+        "service": service_name,
+    });
+    if let UsageMetricRequest::LlmTokens {
+        ref provider,
+        ref model,
+        input_tokens,
+        output_tokens,
+        cache_read_input_tokens,
+        cache_creation_input_tokens,
+        batch,
+    } = body.metric
+    {
+        props["provider"] = serde_json::json!(provider);
+        props["model"] = serde_json::json!(model);
+        props["input_tokens"] = serde_json::json!(input_tokens);
+        props["output_tokens"] = serde_json::json!(output_tokens);
+        props["cache_read_input_tokens"] = serde_json::json!(cache_read_input_tokens);
+        props["cache_creation_input_tokens"] = serde_json::json!(cache_creation_input_tokens);
+        props["batch"] = serde_json::json!(batch);
+    }
+    append_cost_observability_properties(&mut props, &body.metadata, cost_cents, service_name);
+    outbox.extend(crate::outbox::mixpanel(
+        &state.config,
+        "tokens_consumed",
+        &user_id.to_string(),
+        props,
+    ));
+    // Org pools have no balance subscribers
+    if !org_usage {
+        outbox.push(crate::outbox::balance_update(&user_id));
+    }
+    outbox.extend(crate::outbox::lago_usage(
+        state,
+        &body.event_id,
+        user_id,
+        body.agent_id.as_deref(),
+        &body.metric,
+    ));
+    outbox
 }
 
 /// Batch usage request.
@@ -409,6 +557,9 @@ pub struct CheckBalanceRequest {
     pub provider: Option<String>,
     /// Optional model name for model-aware reserve calculation.
     pub model: Option<String>,
+    /// Whether the request will go through the provider's batch API.
+    #[serde(default)]
+    pub batch: bool,
 }
 
 /// Check balance response.
@@ -418,6 +569,8 @@ pub struct CheckBalanceResponse {
     pub sufficient: bool,
     /// Current balance.
     pub balance_cents: i64,
+    /// Amount held by active reservations.
+    pub reserved_cents: i64,
     /// Required amount.
     pub required_cents: i64,
 }
@@ -428,17 +581,38 @@ fn effective_required_cents(
     request: &CheckBalanceRequest,
 ) -> i64 {
     match (request.provider.as_deref(), request.model.as_deref()) {
-        (Some(provider), Some(model)) if request.required_cents <= 0 => {
-            pricing.minimum_llm_reserve_cents_for_zero_pro_user(provider, model, zero_pro_user)
-        }
+        (Some(provider), Some(model)) if request.required_cents <= 0 => pricing
+            .minimum_llm_reserve_cents_for_usage(provider, model, request.batch, zero_pro_user),
         _ => request.required_cents,
     }
 }
 
+/// Load (or create) an account and apply any lazy monthly/daily grants that
+/// are due, so balance checks and holds see the credits on first use.
+async fn account_with_lazy_grants(state: &AppState, user_id: &UserId) -> Result<Account, ApiError> {
+    let mut account = get_or_create_account(state.store.as_ref(), user_id).await?;
+
+    // Lazy monthly allowance: if not granted in the last 30 days, issue monthly credits
+    if let Some(new_balance) = super::credits::try_monthly_allowance(state, &account).await? {
+        account.balance_cents = new_balance;
+        if let Some(refreshed) = state.store.get_account(user_id).await? {
+            account = refreshed;
+        }
+    }
+
+    // Lazy daily grant: if not yet granted today, issue daily credits
+    if let Some(new_balance) = super::credits::try_daily_grant(state, &account).await? {
+        account.balance_cents = new_balance;
+    }
+
+    Ok(account)
+}
+
 /// Check if a user has sufficient balance.
 ///
 /// Also triggers a lazy daily credit grant if the user hasn't received
-/// one today — credits appear on first use of the day.
+/// one today — credits appear on first use of the day. Credits held by
+/// active reservations are not counted as available.
 pub async fn check_balance(
     State(state): State<Arc<AppState>>,
     _auth: ServiceAuth,
@@ -449,38 +623,583 @@ pub async fn check_balance(
         .parse()
         .map_err(|_| ApiError::BadRequest("Invalid user ID".into()))?;
 
-    let mut account = get_or_create_account(state.store.as_ref(), &user_id)?;
-
-    // Lazy monthly allowance: if not granted in the last 30 days, issue monthly credits
-    if let Some(new_balance) =
-        super::credits::try_monthly_allowance(state.store.as_ref(), &state.balance_tx, &account)?
-    {
-        account.balance_cents = new_balance;
-        if let Some(refreshed) = state.store.get_account(&user_id)? {
-            account = refreshed;
-        }
-    }
-
-    // Lazy daily grant: if not yet granted today, issue daily credits
-    if let Some(new_balance) =
-        super::credits::try_daily_grant(state.store.as_ref(), &state.balance_tx, &account)?
-    {
-        account.balance_cents = new_balance;
-    }
+    let account = account_with_lazy_grants(&state, &user_id).await?;
+    let reserved_cents = state.store.reserved_cents(&user_id).await?;
 
     let required_cents = effective_required_cents(
-        &state.config.pricing,
+        &state.pricing().config,
         body.zero_pro_user.unwrap_or(false),
         &body,
     );
 
     Ok(Json(CheckBalanceResponse {
-        sufficient: account.balance_cents >= required_cents,
+        sufficient: account.spendable_cents() - reserved_cents >= required_cents,
         balance_cents: account.balance_cents,
+        reserved_cents,
         required_cents,
     }))
 }
 
+// ============================================================================
+// Reservations
+// ============================================================================
+
+/// Reserve credits request.
+#[derive(Debug, Deserialize)]
+pub struct ReserveRequest {
+    /// User ID whose balance is held.
+    pub user_id: String,
+    /// Explicit amount to hold in cents. When omitted, the hold is the
+    /// model-aware minimum reserve for `provider`/`model`.
+    pub amount_cents: Option<i64>,
+    /// Provider name for model-aware reserve calculation.
+    pub provider: Option<String>,
+    /// Model name for model-aware reserve calculation.
+    pub model: Option<String>,
+    /// Whether the user has a ZERO Pro entitlement for model-aware reserve calculation.
+    #[serde(
+        default,
+        alias = "zeroProUser",
+        alias = "is_zero_pro",
+        alias = "isZeroPro"
+    )]
+    pub zero_pro_user: Option<bool>,
+    /// Whether the request will go through the provider's batch API.
+    #[serde(default)]
+    pub batch: bool,
+    /// Hold lifetime in seconds (defaults to the service setting).
+    pub ttl_seconds: Option<u64>,
+    /// Additional metadata stored with the hold.
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+/// Reserve credits response.
+#[derive(Debug, Serialize)]
+pub struct ReserveResponse {
+    /// Reservation ID to pass to settle or release.
+    pub reservation_id: String,
+    /// Amount held in cents.
+    pub reserved_cents: i64,
+    /// Balance still available after this hold.
+    pub available_cents: i64,
+    /// When the hold lapses if not settled.
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Settle reservation request: a usage event plus the reservation it consumes.
+#[derive(Debug, Deserialize)]
+pub struct SettleRequest {
+    /// Reservation ID returned by reserve.
+    pub reservation_id: String,
+    /// The actual usage to debit.
+    #[serde(flatten)]
+    pub usage: UsageRequest,
+}
+
+/// Release reservation request.
+#[derive(Debug, Deserialize)]
+pub struct ReleaseRequest {
+    /// Reservation ID returned by reserve.
+    pub reservation_id: String,
+}
+
+/// Release reservation response.
+#[derive(Debug, Serialize)]
+pub struct ReleaseResponse {
+    /// Whether the hold is no longer in place.
+    pub success: bool,
+    /// Reservation ID.
+    pub reservation_id: String,
+    /// Final reservation status.
+    pub status: &'static str,
+}
+
+/// Hold credits for an in-flight request (e.g. a streamed LLM response).
+///
+/// The hold is subtracted from the available balance for every other
+/// reservation, balance check, and usage debit until it is settled,
+/// released, or expires.
+pub async fn reserve_usage(
+    State(state): State<Arc<AppState>>,
+    auth: ServiceAuth,
+    Json(body): Json<ReserveRequest>,
+) -> Result<Json<ReserveResponse>, ApiError> {
+    let user_id = body
+        .user_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid user ID".into()))?;
+
+    let amount_cents = match (
+        body.amount_cents,
+        body.provider.as_deref(),
+        body.model.as_deref(),
+    ) {
+        (Some(amount), _, _) if amount > 0 => amount,
+        (Some(_), _, _) => {
+            return Err(ApiError::BadRequest("amount_cents must be positive".into()))
+        }
+        (None, Some(provider), Some(model)) => {
+            state.pricing().config.minimum_llm_reserve_cents_for_usage(
+                provider,
+                model,
+                body.batch,
+                body.zero_pro_user.unwrap_or(false),
+            )
+        }
+        (None, _, _) => {
+            return Err(ApiError::BadRequest(
+                "Either amount_cents or provider and model are required".into(),
+            ))
+        }
+    };
+
+    let ttl_seconds = body
+        .ttl_seconds
+        .unwrap_or(state.config.reservation_ttl_seconds)
+        .clamp(1, MAX_RESERVATION_TTL_SECONDS);
+
+    account_with_lazy_grants(&state, &user_id).await?;
+
+    #[allow(clippy::cast_possible_wrap)]
+    let reservation = Reservation::new(
+        user_id,
+        amount_cents,
+        chrono::Duration::seconds(ttl_seconds as i64),
+        body.metadata,
+    );
+    let available_cents = state.store.create_reservation(&reservation).await?;
+
+    tracing::info!(
+        service = %auth.service_name,
+        user_id = %user_id,
+        reservation_id = %reservation.id,
+        amount_cents = %amount_cents,
+        "Credits reserved"
+    );
+
+    Ok(Json(ReserveResponse {
+        reservation_id: reservation.id.to_string(),
+        reserved_cents: amount_cents,
+        available_cents,
+        expires_at: reservation.expires_at,
+    }))
+}
+
+/// Settle a reservation into the real usage debit.
+///
+/// The debit is the actual cost of the usage, which may differ from the
+/// amount held. The response matches `POST /v1/usage`.
+pub async fn settle_usage(
+    State(state): State<Arc<AppState>>,
+    auth: ServiceAuth,
+    Json(body): Json<SettleRequest>,
+) -> Result<Json<UsageResponse>, ApiError> {
+    let reservation_id: ReservationId = body
+        .reservation_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid reservation ID".into()))?;
+
+    let reservation = state
+        .store
+        .get_reservation(&reservation_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Reservation not found: {reservation_id}")))?;
+
+    let user_id: UserId = body
+        .usage
+        .user_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid user ID".into()))?;
+    if reservation.user_id != user_id {
+        return Err(ApiError::BadRequest(
+            "Reservation does not belong to this user".into(),
+        ));
+    }
+
+    charge_usage(&state, &auth.service_name, body.usage, Some(reservation_id))
+        .await
+        .map(Json)
+}
+
+/// Release a reservation without charging.
+pub async fn release_usage(
+    State(state): State<Arc<AppState>>,
+    auth: ServiceAuth,
+    Json(body): Json<ReleaseRequest>,
+) -> Result<Json<ReleaseResponse>, ApiError> {
+    let reservation_id: ReservationId = body
+        .reservation_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid reservation ID".into()))?;
+
+    let reservation = state.store.release_reservation(&reservation_id).await?;
+
+    tracing::info!(
+        service = %auth.service_name,
+        user_id = %reservation.user_id,
+        reservation_id = %reservation_id,
+        status = reservation.status.as_str(),
+        "Reservation released"
+    );
+
+    Ok(Json(ReleaseResponse {
+        success: true,
+        reservation_id: reservation_id.to_string(),
+        status: reservation.status.as_str(),
+    }))
+}
+
+/// Reversal ID used when a request reverses everything left of a charge.
+const FULL_REVERSAL_ID: &str = "full";
+
+/// Reverse usage request.
+#[derive(Debug, Deserialize)]
+pub struct ReverseUsageRequest {
+    /// Idempotency key for this reversal. Required for partial reversals;
+    /// full reversals default to `"full"`.
+    #[serde(default)]
+    pub reversal_id: Option<String>,
+    /// Amount to refund in cents. Omit to refund everything not yet reversed.
+    #[serde(default)]
+    pub amount_cents: Option<i64>,
+    /// Reason recorded on the reversal transaction.
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Reverse usage response.
+#[derive(Debug, Serialize)]
+pub struct ReverseUsageResponse {
+    /// Whether the reversal is recorded.
+    pub success: bool,
+    /// The reversed usage event.
+    pub event_id: String,
+    /// Reversal ID.
+    pub reversal_id: String,
+    /// Amount refunded by this reversal in cents.
+    pub reversed_cents: i64,
+    /// Amount refunded by all reversals of the event in cents.
+    pub total_reversed_cents: i64,
+    /// Balance right after the refund in cents.
+    pub balance_cents: i64,
+    /// Reversal transaction ID.
+    pub transaction_id: String,
+}
+
+/// Build and record a reversal of `event` against its original usage
+/// transaction. Without an explicit amount, everything not yet reversed is
+/// refunded.
+async fn record_reversal(
+    state: &AppState,
+    event: &UsageEvent,
+    reversed_cents: i64,
+    reversal_id: String,
+    amount_cents: Option<i64>,
+    reason: Option<String>,
+) -> Result<UsageReversal, ApiError> {
+    let store = state.store.as_ref();
+    let event_id = &event.event_id;
+    let original_id = event.transaction_id.ok_or_else(|| {
+        ApiError::Conflict(format!("Usage event {event_id} has no recorded charge"))
+    })?;
+    let original = store
+        .get_transaction(&original_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Transaction not found: {original_id}")))?;
+
+    let amount_cents = amount_cents.unwrap_or(event.cost_cents - reversed_cents);
+    if amount_cents <= 0 {
+        return Err(ApiError::Conflict(format!(
+            "Usage event {event_id} is already fully reversed"
+        )));
+    }
+
+    let balance = match original.org_id {
+        Some(org_id) => store
+            .get_organization(&org_id)
+            .await?
+            .map_or(0, |org| org.balance_cents),
+        None => {
+            get_or_create_account(store, &original.user_id)
+                .await?
+                .balance_cents
+        }
+    };
+    let description = reason.unwrap_or_else(|| format!("Usage reversal for {event_id}"));
+    let tx = CreditTransaction::reversal(
+        &original,
+        event_id,
+        amount_cents,
+        balance + amount_cents,
+        description,
+    );
+
+    // Org pools have no balance subscribers
+    let mut outbox = Vec::new();
+    if original.org_id.is_none() {
+        outbox.push(crate::outbox::balance_update(&original.user_id));
+    }
+    let reversal = store
+        .reverse_usage(
+            &UsageReversal::new(event_id.clone(), reversal_id, amount_cents, tx.id),
+            &tx,
+            &outbox,
+        )
+        .await?;
+    crate::outbox::committed(state, &outbox).await;
+
+    Ok(reversal)
+}
+
+/// Refund all or part of a usage event's charge.
+///
+/// The refund is credited to the balance the event was charged against and
+/// recorded as a reversal transaction referencing the original usage
+/// transaction. Retrying with the same `reversal_id` returns the original
+/// result, and the reversals of an event never total more than it charged.
+pub async fn reverse_usage(
+    State(state): State<Arc<AppState>>,
+    auth: ServiceAuth,
+    Path(event_id): Path<String>,
+    Json(body): Json<ReverseUsageRequest>,
+) -> Result<Json<ReverseUsageResponse>, ApiError> {
+    let reversal_id = match (body.reversal_id, body.amount_cents) {
+        (Some(id), _) if id.trim().is_empty() => {
+            return Err(ApiError::BadRequest("reversal_id must not be empty".into()))
+        }
+        (Some(id), _) => id,
+        (None, None) => FULL_REVERSAL_ID.to_string(),
+        (None, Some(_)) => {
+            return Err(ApiError::BadRequest(
+                "reversal_id is required for partial reversals".into(),
+            ))
+        }
+    };
+    if body.amount_cents.is_some_and(|amount| amount <= 0) {
+        return Err(ApiError::BadRequest("amount_cents must be positive".into()));
+    }
+
+    let event = state
+        .store
+        .get_usage_event(&event_id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Usage event not found: {event_id}")))?;
+
+    let existing = state.store.list_usage_reversals(&event_id).await?;
+    let reversal = if let Some(found) = existing.iter().find(|r| r.reversal_id == reversal_id) {
+        found.clone()
+    } else {
+        let reversed_cents = existing.iter().map(|r| r.amount_cents).sum();
+        let reversal = record_reversal(
+            &state,
+            &event,
+            reversed_cents,
+            reversal_id,
+            body.amount_cents,
+            body.reason,
+        )
+        .await?;
+
+        tracing::info!(
+            service = %auth.service_name,
+            event_id = %event_id,
+            reversal_id = %reversal.reversal_id,
+            user_id = %event.user_id,
+            amount_cents = %reversal.amount_cents,
+            "Usage reversed"
+        );
+
+        reversal
+    };
+
+    let tx = state
+        .store
+        .get_transaction(&reversal.transaction_id)
+        .await?
+        .ok_or_else(|| {
+            ApiError::Internal(format!(
+                "Reversal transaction missing: {}",
+                reversal.transaction_id
+            ))
+        })?;
+
+    let total_reversed_cents = state
+        .store
+        .list_usage_reversals(&event_id)
+        .await?
+        .iter()
+        .map(|r| r.amount_cents)
+        .sum();
+
+    Ok(Json(ReverseUsageResponse {
+        success: true,
+        event_id,
+        reversal_id: reversal.reversal_id,
+        reversed_cents: reversal.amount_cents,
+        total_reversed_cents,
+        balance_cents: tx.balance_after_cents,
+        transaction_id: tx.id.to_string(),
+    }))
+}
+
+// ============================================================================
+// Storage Snapshots
+// ============================================================================
+
+/// Storage snapshot request: how many bytes a resource holds now.
+#[derive(Debug, Deserialize)]
+pub struct StorageSnapshotRequest {
+    /// User ID that owns the resource.
+    pub user_id: String,
+    /// The reporting service's identifier for the resource (a bucket,
+    /// volume, etc.), unique per user.
+    pub resource_id: String,
+    /// Bytes the resource holds.
+    pub bytes: u64,
+    /// When the bytes were measured (defaults to now).
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Agent ID that owns the resource (optional).
+    pub agent_id: Option<String>,
+    /// Organization whose shared pool pays for the storage.
+    #[serde(default)]
+    pub org_id: Option<String>,
+    /// Additional metadata recorded on charges.
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+/// Storage snapshot response.
+#[derive(Debug, Serialize)]
+pub struct StorageSnapshotResponse {
+    /// Whether the snapshot was recorded.
+    pub success: bool,
+    /// GB-hours charged for this snapshot.
+    pub gb_hours: f64,
+    /// GB-hours accrued but not yet charged, carried to the next snapshot.
+    pub unbilled_gb_hours: f64,
+    /// Cost deducted (0 when nothing was charged).
+    pub cost_cents: i64,
+    /// New balance, if anything was charged.
+    pub balance_cents: Option<i64>,
+    /// Usage transaction ID, if anything was charged.
+    pub transaction_id: Option<String>,
+}
+
+/// Record how many bytes a storage resource holds and charge the storage
+/// used since the previous snapshot.
+///
+/// The bytes of the previous snapshot are taken as held until this one, so
+/// services should report on a schedule and whenever usage changes a lot.
+/// The first snapshot of a resource starts its meter and charges nothing,
+/// and a snapshot not after the last one is ignored. Only whole credits are
+/// charged; the rest carries over to the next snapshot. If the charge fails
+/// (e.g. insufficient credits) the meter stays put and the next snapshot
+/// charges the whole period.
+pub async fn report_storage_snapshot(
+    State(state): State<Arc<AppState>>,
+    auth: ServiceAuth,
+    Json(body): Json<StorageSnapshotRequest>,
+) -> Result<Json<StorageSnapshotResponse>, ApiError> {
+    let user_id: UserId = body
+        .user_id
+        .parse()
+        .map_err(|_| ApiError::BadRequest("Invalid user ID".into()))?;
+    if body.resource_id.trim().is_empty() {
+        return Err(ApiError::BadRequest("resource_id must not be empty".into()));
+    }
+    let read_at = usage_timestamp(body.timestamp)?;
+
+    let Some(mut meter) = state
+        .store
+        .get_storage_meter(&user_id, &body.resource_id)
+        .await?
+    else {
+        let meter = StorageMeter::new(user_id, body.resource_id, body.bytes, read_at);
+        state.store.put_storage_meter_if_version(&meter, 0).await?;
+        tracing::info!(
+            service = %auth.service_name,
+            user_id = %user_id,
+            resource_id = %meter.resource_id,
+            bytes = meter.bytes,
+            "Storage metering started"
+        );
+        return Ok(Json(StorageSnapshotResponse {
+            success: true,
+            gb_hours: 0.0,
+            unbilled_gb_hours: 0.0,
+            cost_cents: 0,
+            balance_cents: None,
+            transaction_id: None,
+        }));
+    };
+
+    // Stale: the meter is already past this snapshot
+    if read_at <= meter.read_at {
+        return Ok(Json(StorageSnapshotResponse {
+            success: true,
+            gb_hours: 0.0,
+            unbilled_gb_hours: meter.unbilled_gb_hours,
+            cost_cents: 0,
+            balance_cents: None,
+            transaction_id: None,
+        }));
+    }
+
+    let expected_version = meter.version;
+    // Named by the period's start, so a retried charge is a duplicate
+    let event_id = format!(
+        "storage:{user_id}:{}:{}",
+        meter.resource_id,
+        meter.read_at.timestamp_millis()
+    );
+    let charge = meter.record(
+        body.bytes,
+        read_at,
+        state.pricing().config.storage_gb_hour_credits,
+    );
+
+    let mut response = StorageSnapshotResponse {
+        success: true,
+        gb_hours: charge.gb_hours,
+        unbilled_gb_hours: meter.unbilled_gb_hours,
+        cost_cents: charge.cost_cents,
+        balance_cents: None,
+        transaction_id: None,
+    };
+    if charge.cost_cents > 0 {
+        let usage = UsageRequest {
+            event_id,
+            user_id: body.user_id,
+            agent_id: body.agent_id,
+            metric: UsageMetricRequest::Storage {
+                gb_hours: charge.gb_hours,
+            },
+            cost_cents: Some(charge.cost_cents),
+            timestamp: Some(read_at),
+            zero_pro_user: None,
+            metadata: body.metadata,
+            org_id: body.org_id,
+        };
+        match charge_usage(&state, &auth.service_name, usage, None).await {
+            Ok(charged) => {
+                response.balance_cents = Some(charged.balance_cents);
+                response.transaction_id = Some(charged.transaction_id);
+            }
+            // Charged by an earlier attempt that failed to advance the meter
+            Err(ApiError::DuplicateEvent(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    state
+        .store
+        .put_storage_meter_if_version(&meter, expected_version)
+        .await?;
+    Ok(Json(response))
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
@@ -496,6 +1215,7 @@ mod tests {
             zero_pro_user: None,
             provider: Some("openai".to_string()),
             model: Some("aura-gpt-5-4".to_string()),
+            batch: false,
         };
 
         assert_eq!(effective_required_cents(&pricing, false, &request), 3);
@@ -510,6 +1230,7 @@ mod tests {
             zero_pro_user: Some(true),
             provider: Some("anthropic".to_string()),
             model: Some("aura-claude-opus-4-7".to_string()),
+            batch: false,
         };
 
         // 20% markup for everyone — Zero Pro gets same reserve
@@ -525,6 +1246,7 @@ mod tests {
             zero_pro_user: None,
             provider: Some("openai".to_string()),
             model: Some("aura-gpt-5-4".to_string()),
+            batch: false,
         };
 
         assert_eq!(effective_required_cents(&pricing, false, &request), 50);
@@ -584,7 +1306,7 @@ mod tests {
 
 /// Check if auto-refill should be triggered and spawn the task if needed.
 fn maybe_trigger_auto_refill(
-    state: &AppState,
+    state: &Arc<AppState>,
     account: &z_billing_core::Account,
     user_id: UserId,
     balance: i64,
@@ -597,21 +1319,12 @@ fn maybe_trigger_auto_refill(
         return;
     }
 
-    let stripe = state.stripe.clone();
-    let store = state.store.clone();
+    let state = Arc::clone(state);
     let refill_amount = auto_refill.refill_amount_cents;
     let customer_id = account.stripe_customer_id.clone();
 
     tokio::spawn(async move {
-        if let Err(e) = trigger_auto_refill(
-            stripe.as_deref(),
-            store.as_ref(),
-            user_id,
-            customer_id,
-            refill_amount,
-        )
-        .await
-        {
+        if let Err(e) = trigger_auto_refill(&state, user_id, customer_id, refill_amount).await {
             tracing::warn!(
                 user_id = %user_id,
                 error = %e,
@@ -621,38 +1334,6 @@ fn maybe_trigger_auto_refill(
     });
 }
 
-/// Forward usage to Lago if configured (with retries).
-fn maybe_forward_to_lago(
-    state: &AppState,
-    event_id: &str,
-    user_id: UserId,
-    agent_id: Option<&str>,
-    metric: &UsageMetricRequest,
-) {
-    let Some(lago) = &state.lago else {
-        return;
-    };
-
-    let lago = lago.clone();
-    let event_id = event_id.to_string();
-    let user_id_str = user_id.to_string();
-    let agent_id = agent_id.map(String::from);
-    let metric = metric.clone();
-
-    tokio::spawn(async move {
-        if let Err(e) =
-            forward_to_lago_with_retry(&lago, &event_id, &user_id_str, agent_id.as_deref(), &metric)
-                .await
-        {
-            tracing::error!(
-                event_id = %event_id,
-                error = %e,
-                "Failed to forward usage to Lago after all retries"
-            );
-        }
-    });
-}
-
 async fn process_single_usage(
     state: &AppState,
     service_name: &str,
@@ -670,15 +1351,25 @@ async fn process_single_usage(
         .transpose()
         .map_err(|_| ApiError::BadRequest("Invalid agent ID".into()))?;
 
-    let account = state
-        .store
-        .get_account(&user_id)?
-        .ok_or_else(|| ApiError::NotFound("Account not found".into()))?;
+    let org = org_for_member(state.store.as_ref(), body.org_id.as_deref(), &user_id).await?;
+    let balance_cents = match &org {
+        Some(org) => org.balance_cents,
+        None => {
+            state
+                .store
+                .get_account(&user_id)
+                .await?
+                .ok_or_else(|| ApiError::NotFound("Account not found".into()))?
+                .balance_cents
+        }
+    };
     let zero_pro_user = usage_zero_pro_user(&body);
+    let occurred_at = usage_timestamp(body.timestamp)?;
 
-    let cost_cents = body
-        .cost_cents
-        .unwrap_or_else(|| calculate_cost(&state.config.pricing, zero_pro_user, &body.metric));
+    let pricing = state.pricing();
+    let cost_cents = body.cost_cents.unwrap_or_else(|| {
+        calculate_cost(&pricing.config, zero_pro_user, &body.metric, occurred_at)
+    });
 
     if cost_cents < 0 {
         return Err(ApiError::BadRequest(
@@ -686,6 +1377,26 @@ async fn process_single_usage(
         ));
     }
 
+    let new_balance = balance_cents - cost_cents;
+    let description = format_usage_description(&body.metric, service_name);
+    let tx = CreditTransaction::usage(
+        user_id,
+        cost_cents,
+        new_balance,
+        description,
+        transaction_metadata(&body, &pricing, occurred_at),
+    );
+
+    let outbox = usage_outbox(
+        state,
+        &body,
+        user_id,
+        service_name,
+        cost_cents,
+        new_balance,
+        org.is_some(),
+    );
+
     let (metric, quantity) = convert_metric(&body.metric);
     let event = UsageEvent {
         event_id: body.event_id.clone(),
@@ -695,15 +1406,23 @@ async fn process_single_usage(
         metric,
         quantity,
         cost_cents,
-        timestamp: chrono::Utc::now(),
-        metadata: body.metadata.clone(),
+        timestamp: occurred_at,
+        metadata: body.metadata,
+        transaction_id: Some(tx.id),
     };
 
-    let new_balance = account.balance_cents - cost_cents;
-    let description = format_usage_description(&body.metric, service_name);
-    let tx = CreditTransaction::usage(user_id, cost_cents, new_balance, description, body.metadata);
-
-    state.store.process_usage(&event, &tx)?;
+    match &org {
+        Some(org) => {
+            state
+                .store
+                .process_org_usage(&org.id, &event, &tx.with_org(org.id), &outbox)
+                .await?;
+        }
+        None => {
+            state.store.process_usage(&event, &tx, &outbox).await?;
+        }
+    }
+    crate::outbox::committed(state, &outbox).await;
 
     Ok(cost_cents)
 }
@@ -712,6 +1431,7 @@ fn calculate_cost(
     pricing: &z_billing_core::PricingConfig,
     zero_pro_user: bool,
     metric: &UsageMetricRequest,
+    at: DateTime<Utc>,
 ) -> i64 {
     match metric {
         UsageMetricRequest::LlmTokens {
@@ -719,12 +1439,21 @@ fn calculate_cost(
             model,
             input_tokens,
             output_tokens,
-        } => pricing.calculate_llm_cost_for_zero_pro_user(
+            cache_read_input_tokens,
+            cache_creation_input_tokens,
+            batch,
+        } => pricing.calculate_llm_usage_cost_for_zero_pro_user_at(
             provider,
             model,
-            *input_tokens,
-            *output_tokens,
+            &LlmTokenUsage {
+                input_tokens: *input_tokens,
+                output_tokens: *output_tokens,
+                cache_read_input_tokens: *cache_read_input_tokens,
+                cache_creation_input_tokens: *cache_creation_input_tokens,
+                batch: *batch,
+            },
             zero_pro_user,
+            at,
         ),
         UsageMetricRequest::Compute {
             cpu_hours,
@@ -735,9 +1464,77 @@ fn calculate_cost(
             #[allow(clippy::cast_possible_wrap)]
             std::cmp::max(1, (*count as i64) / (API_CALLS_PER_CREDIT as i64))
         }
+        UsageMetricRequest::Storage { gb_hours } => pricing.calculate_storage_cost(*gb_hours),
+        UsageMetricRequest::ImageGeneration {
+            provider,
+            model,
+            count,
+            resolution,
+        } => pricing.calculate_image_cost(provider, model, *resolution, *count),
+        UsageMetricRequest::AudioTranscription {
+            provider,
+            model,
+            seconds,
+        } => pricing.calculate_audio_cost(provider, model, *seconds),
+        UsageMetricRequest::TextToSpeech {
+            provider,
+            model,
+            characters,
+        } => pricing.calculate_speech_cost(provider, model, *characters),
+        UsageMetricRequest::WebSearch { provider, queries } => {
+            pricing.calculate_search_cost(provider, *queries)
+        }
+        UsageMetricRequest::ToolCalls { tool, count } => pricing.calculate_tool_cost(tool, *count),
     }
 }
 
+/// When the usage happened: the reported timestamp, or now.
+fn usage_timestamp(timestamp: Option<DateTime<Utc>>) -> Result<DateTime<Utc>, ApiError> {
+    let now = Utc::now();
+    match timestamp {
+        Some(at) if at > now + chrono::Duration::seconds(MAX_USAGE_CLOCK_SKEW_SECONDS) => Err(
+            ApiError::BadRequest("timestamp must not be in the future".into()),
+        ),
+        Some(at) => Ok(at),
+        None => Ok(now),
+    }
+}
+
+/// Metadata for a usage transaction: the reported metadata plus, when
+/// z-billing priced the usage itself, the price version it used.
+fn transaction_metadata(
+    req: &UsageRequest,
+    pricing: &ActivePricing,
+    at: DateTime<Utc>,
+) -> serde_json::Value {
+    let mut metadata = req.metadata.clone();
+    if req.cost_cents.is_some() {
+        return metadata;
+    }
+
+    let model_price = match &req.metric {
+        UsageMetricRequest::LlmTokens {
+            provider, model, ..
+        } => pricing.config.llm_pricing_at(provider, model, at),
+        _ => None,
+    };
+    let price_version = serde_json::json!({
+        "catalog": pricing.catalog.version,
+        "effective_from": model_price.and_then(|price| price.effective_from),
+        "effective_until": model_price.and_then(|price| price.effective_until),
+    });
+    match &mut metadata {
+        serde_json::Value::Object(map) => {
+            map.insert("price_version".into(), price_version);
+        }
+        serde_json::Value::Null => {
+            metadata = serde_json::json!({ "price_version": price_version });
+        }
+        _ => {}
+    }
+    metadata
+}
+
 fn usage_zero_pro_user(req: &UsageRequest) -> bool {
     req.zero_pro_user
         .or_else(|| metadata_bool(&req.metadata, "zero_pro_user"))
@@ -854,28 +1651,28 @@ fn convert_metric(req: &UsageMetricRequest) -> (UsageMetric, f64) {
             model,
             input_tokens,
             output_tokens,
+            cache_read_input_tokens,
+            cache_creation_input_tokens,
+            batch,
         } => {
-            let llm_provider = match provider.to_lowercase().as_str() {
-                "anthropic" => LlmProvider::Anthropic,
-                "openai" => LlmProvider::OpenAi,
-                "google" => LlmProvider::Google,
-                "xai" => LlmProvider::Xai,
-                "moonshot" => LlmProvider::Moonshot,
-                other => LlmProvider::Custom(other.to_string()),
-            };
-
             // For simplicity, we'll create a combined metric
             // In practice, you might want separate events for input/output
-            let total_tokens = input_tokens + output_tokens;
+            let total_tokens = input_tokens
+                + output_tokens
+                + cache_read_input_tokens
+                + cache_creation_input_tokens;
             (
                 UsageMetric::LlmTokens {
-                    provider: llm_provider,
+                    provider: llm_provider(provider),
                     model: model.clone(),
                     direction: if *output_tokens > *input_tokens {
                         TokenDirection::Output
                     } else {
                         TokenDirection::Input
                     },
+                    cache_read_input_tokens: *cache_read_input_tokens,
+                    cache_creation_input_tokens: *cache_creation_input_tokens,
+                    batch: *batch,
                 },
                 total_tokens as f64,
             )
@@ -896,6 +1693,67 @@ fn convert_metric(req: &UsageMetricRequest) -> (UsageMetric, f64) {
             },
             *count as f64,
         ),
+        UsageMetricRequest::Storage { gb_hours } => (
+            UsageMetric::Storage {
+                gb_hours: *gb_hours,
+            },
+            *gb_hours,
+        ),
+        UsageMetricRequest::ImageGeneration {
+            provider,
+            model,
+            count,
+            resolution,
+        } => (
+            UsageMetric::ImageGeneration {
+                provider: llm_provider(provider),
+                model: model.clone(),
+                resolution: *resolution,
+            },
+            *count as f64,
+        ),
+        UsageMetricRequest::AudioTranscription {
+            provider,
+            model,
+            seconds,
+        } => (
+            UsageMetric::AudioTranscription {
+                provider: llm_provider(provider),
+                model: model.clone(),
+            },
+            *seconds,
+        ),
+        UsageMetricRequest::TextToSpeech {
+            provider,
+            model,
+            characters,
+        } => (
+            UsageMetric::TextToSpeech {
+                provider: llm_provider(provider),
+                model: model.clone(),
+            },
+            *characters as f64,
+        ),
+        UsageMetricRequest::WebSearch { provider, queries } => (
+            UsageMetric::WebSearch {
+                provider: llm_provider(provider),
+            },
+            *queries as f64,
+        ),
+        UsageMetricRequest::ToolCalls { tool, count } => {
+            (UsageMetric::ToolCalls { tool: tool.clone() }, *count as f64)
+        }
+    }
+}
+
+fn llm_provider(provider: &str) -> LlmProvider {
+    match provider.to_lowercase().as_str() {
+        "anthropic" => LlmProvider::Anthropic,
+        "openai" => LlmProvider::OpenAi,
+        "google" => LlmProvider::Google,
+        "xai" => LlmProvider::Xai,
+        "moonshot" => LlmProvider::Moonshot,
+        other => LlmProvider::Custom(other.to_string()),
     }
 }
 
@@ -906,9 +1764,20 @@ fn format_usage_description(metric: &UsageMetricRequest, service: &str) -> Strin
             model,
             input_tokens,
             output_tokens,
+            cache_read_input_tokens,
+            cache_creation_input_tokens,
+            batch,
         } => {
+            let cached = if cache_read_input_tokens + cache_creation_input_tokens > 0 {
+                format!(
+                    ", {cache_read_input_tokens} cache read, {cache_creation_input_tokens} cache write"
+                )
+            } else {
+                String::new()
+            };
+            let batch = if *batch { ", batch" } else { "" };
             format!(
-                "LLM usage: {provider} {model} ({input_tokens} input, {output_tokens} output tokens) via {service}"
+                "LLM usage: {provider} {model} ({input_tokens} input, {output_tokens} output tokens{cached}{batch}) via {service}"
             )
         }
         UsageMetricRequest::Compute {
@@ -922,55 +1791,45 @@ fn format_usage_description(metric: &UsageMetricRequest, service: &str) -> Strin
         UsageMetricRequest::ApiCalls { endpoint, count } => {
             format!("API calls: {count} calls to {endpoint} via {service}")
         }
-    }
-}
-
-/// Forward usage event to Lago for analytics with exponential backoff retry.
-async fn forward_to_lago_with_retry(
-    lago: &crate::lago::LagoClient,
-    event_id: &str,
-    user_id: &str,
-    agent_id: Option<&str>,
-    metric: &UsageMetricRequest,
-) -> Result<(), crate::lago::client::LagoError> {
-    let mut attempt = 0;
-    let mut backoff_ms = LAGO_INITIAL_BACKOFF_MS;
-
-    loop {
-        match forward_to_lago(lago, event_id, user_id, agent_id, metric).await {
-            Ok(()) => return Ok(()),
-            Err(e) => {
-                attempt += 1;
-
-                if attempt >= LAGO_MAX_RETRIES {
-                    tracing::warn!(
-                        event_id = %event_id,
-                        attempt = %attempt,
-                        error = %e,
-                        "Lago forwarding failed after max retries"
-                    );
-                    return Err(e);
-                }
-
-                tracing::debug!(
-                    event_id = %event_id,
-                    attempt = %attempt,
-                    backoff_ms = %backoff_ms,
-                    error = %e,
-                    "Lago forwarding failed, retrying"
-                );
-
-                tokio::time::sleep(Duration::from_millis(backoff_ms)).await;
-
-                // Exponential backoff with cap
-                backoff_ms = (backoff_ms * 2).min(LAGO_MAX_BACKOFF_MS);
-            }
+        UsageMetricRequest::Storage { gb_hours } => {
+            format!("Storage usage: {gb_hours:.2} GB-hours via {service}")
+        }
+        UsageMetricRequest::ImageGeneration {
+            provider,
+            model,
+            count,
+            resolution,
+        } => {
+            format!(
+                "Image generation: {provider} {model} ({count} {} images) via {service}",
+                resolution.as_str()
+            )
+        }
+        UsageMetricRequest::AudioTranscription {
+            provider,
+            model,
+            seconds,
+        } => {
+            format!("Audio transcription: {provider} {model} ({seconds:.1} seconds) via {service}")
+        }
+        UsageMetricRequest::TextToSpeech {
+            provider,
+            model,
+            characters,
+        } => {
+            format!("Text-to-speech: {provider} {model} ({characters} characters) via {service}")
+        }
+        UsageMetricRequest::WebSearch { provider, queries } => {
+            format!("Web search: {queries} queries to {provider} via {service}")
+        }
+        UsageMetricRequest::ToolCalls { tool, count } => {
+            format!("Tool calls: {count} calls to {tool} via {service}")
         }
     }
 }
 
 /// Forward usage event to Lago for analytics.
-async fn forward_to_lago(
+pub(crate) async fn forward_to_lago(
     lago: &crate::lago::LagoClient,
     event_id: &str,
     user_id: &str,
@@ -983,6 +1842,7 @@ async fn forward_to_lago(
             model,
             input_tokens,
             output_tokens,
+            ..
         } => {
             lago.send_llm_usage(
                 event_id,
@@ -1013,6 +1873,51 @@ async fn forward_to_lago(
             );
             Ok(())
         }
+        UsageMetricRequest::Storage { gb_hours } => {
+            lago.send_storage_usage(event_id, user_id, agent_id, *gb_hours)
+                .await
+        }
+        UsageMetricRequest::ImageGeneration {
+            provider,
+            model,
+            count,
+            resolution,
+        } => {
+            lago.send_image_usage(
+                event_id,
+                user_id,
+                provider,
+                model,
+                agent_id,
+                resolution.as_str(),
+                *count,
+            )
+            .await
+        }
+        UsageMetricRequest::AudioTranscription {
+            provider,
+            model,
+            seconds,
+        } => {
+            lago.send_audio_usage(event_id, user_id, provider, model, agent_id, *seconds)
+                .await
+        }
+        UsageMetricRequest::TextToSpeech {
+            provider,
+            model,
+            characters,
+        } => {
+            lago.send_speech_usage(event_id, user_id, provider, model, agent_id, *characters)
+                .await
+        }
+        UsageMetricRequest::WebSearch { provider, queries } => {
+            lago.send_search_usage(event_id, user_id, provider, agent_id, *queries)
+                .await
+        }
+        UsageMetricRequest::ToolCalls { tool, count } => {
+            lago.send_tool_usage(event_id, user_id, tool, agent_id, *count)
+                .await
+        }
     }
 }
 
@@ -1022,13 +1927,12 @@ async fn forward_to_lago(
 /// auto-refill threshold. It initiates a Stripe payment for the configured
 /// refill amount.
 async fn trigger_auto_refill(
-    stripe: Option<&StripeClient>,
-    store: &dyn Store,
+    state: &AppState,
     user_id: UserId,
     customer_id: Option<String>,
     amount_cents: i64,
 ) -> Result<(), String> {
-    let stripe = stripe.ok_or("Stripe not configured")?;
+    let stripe = state.stripe.as_deref().ok_or("Stripe not configured")?;
     let customer_id = customer_id.ok_or("No Stripe customer ID linked to account")?;
 
     tracing::info!(
@@ -1060,17 +1964,23 @@ async fn trigger_auto_refill(
     }
 
     // Payment succeeded, add credits to the account
-    let account = store
+    let account = state
+        .store
         .get_account(&user_id)
+        .await
         .map_err(|e| format!("Failed to get account: {e}"))?
         .ok_or("Account not found")?;
 
     let new_balance = account.balance_cents + amount_cents;
     let tx = CreditTransaction::auto_refill(user_id, amount_cents, new_balance);
 
-    let balance = store
-        .add_credits(&user_id, amount_cents, &tx)
+    let outbox = [crate::outbox::balance_update(&user_id)];
+    let balance = state
+        .store
+        .add_credits(&user_id, amount_cents, &tx, &outbox)
+        .await
         .map_err(|e| format!("Failed to add credits: {e}"))?;
+    crate::outbox::committed(state, &outbox).await;
 
     tracing::info!(
         user_id = %user_id,
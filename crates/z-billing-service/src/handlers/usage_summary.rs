@@ -0,0 +1,207 @@
+//! Usage summary handlers.
+//!
+//! Usage events rolled up by time bucket, provider, model, maker, agent and
+//! source service, so dashboards can answer "what did I spend on Opus this
+//! week" without paging through transactions.
+
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::Json;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use z_billing_core::{UsageDimension, UsageInterval, UsageSummaryQuery, UsageSummaryRow, UserId};
+
+use crate::auth::{AdminAuth, AuthUser};
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Range used when the request gives no `since`.
+const DEFAULT_RANGE_DAYS: i64 = 30;
+
+/// Usage summary query parameters.
+#[derive(Debug, Deserialize)]
+pub struct UsageSummaryParams {
+    /// Only usage at or after this time (RFC 3339, default: 30 days before
+    /// `until`).
+    pub since: Option<DateTime<Utc>>,
+    /// Only usage before this time (RFC 3339, default: now).
+    pub until: Option<DateTime<Utc>>,
+    /// Time buckets: `day` or `week` (UTC, weeks start Monday). Omit for
+    /// one total over the whole range.
+    pub interval: Option<String>,
+    /// Comma-separated attributes to group by: `provider`, `model`, `maker`,
+    /// `agent` and/or `source`.
+    pub group_by: Option<String>,
+}
+
+impl UsageSummaryParams {
+    /// Build the store query for `user_id` (or every user if `None`).
+    fn to_store_query(&self, user_id: Option<UserId>) -> Result<UsageSummaryQuery, ApiError> {
+        let until = self.until.unwrap_or_else(Utc::now);
+        let since = self
+            .since
+            .unwrap_or(until - Duration::days(DEFAULT_RANGE_DAYS));
+        if since >= until {
+            return Err(ApiError::BadRequest("since must be before until".into()));
+        }
+
+        let interval = self
+            .interval
+            .as_deref()
+            .map(str::parse::<UsageInterval>)
+            .transpose()
+            .map_err(ApiError::BadRequest)?;
+
+        let mut group_by = Vec::new();
+        for name in self.group_by.iter().flat_map(|names| names.split(',')) {
+            let dimension = name
+                .trim()
+                .parse::<UsageDimension>()
+                .map_err(ApiError::BadRequest)?;
+            if !group_by.contains(&dimension) {
+                group_by.push(dimension);
+            }
+        }
+
+        Ok(UsageSummaryQuery {
+            user_id,
+            since,
+            until,
+            interval,
+            group_by,
+        })
+    }
+}
+
+/// Admin usage summary query parameters.
+#[derive(Debug, Deserialize)]
+pub struct AdminUsageSummaryParams {
+    /// Only this user's usage (default: every user).
+    pub user_id: Option<String>,
+    /// Aggregation parameters.
+    #[serde(flatten)]
+    pub summary: UsageSummaryParams,
+}
+
+/// Usage totals for one group.
+#[derive(Debug, Serialize)]
+pub struct UsageSummaryRowResponse {
+    /// First day of the time bucket (`YYYY-MM-DD`), when grouped by time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period_start: Option<String>,
+    /// LLM provider, when grouped by provider.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Model name, when grouped by model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Company that makes the model, when grouped by maker.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maker: Option<&'static str>,
+    /// Agent ID, when grouped by agent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+    /// Service that reported the usage, when grouped by source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Input (prompt) tokens.
+    pub input_tokens: u64,
+    /// Output (completion) tokens.
+    pub output_tokens: u64,
+    /// Total cost in cents.
+    pub cost_cents: i64,
+    /// Number of usage events.
+    pub event_count: u64,
+}
+
+impl From<&UsageSummaryRow> for UsageSummaryRowResponse {
+    fn from(row: &UsageSummaryRow) -> Self {
+        let group = &row.group;
+        Self {
+            period_start: group.period_start.map(|day| day.to_string()),
+            provider: group.provider.as_ref().map(|p| p.as_str().to_string()),
+            model: group.model.clone(),
+            maker: group.maker.map(|maker| maker.display_name()),
+            agent_id: group.agent_id.map(|id| id.to_string()),
+            source: group.source.as_ref().map(|s| s.as_str().to_string()),
+            input_tokens: row.input_tokens,
+            output_tokens: row.output_tokens,
+            cost_cents: row.cost_cents,
+            event_count: row.event_count,
+        }
+    }
+}
+
+/// Usage summary response.
+#[derive(Debug, Serialize)]
+pub struct UsageSummaryResponse {
+    /// Start of the range (inclusive).
+    pub since: String,
+    /// End of the range (exclusive).
+    pub until: String,
+    /// Time buckets, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<&'static str>,
+    /// Attributes the rows are grouped by.
+    pub group_by: Vec<&'static str>,
+    /// Totals per group, by period and then by cost (highest first).
+    pub rows: Vec<UsageSummaryRowResponse>,
+    /// Total cost across all rows in cents.
+    pub total_cost_cents: i64,
+}
+
+async fn summarize(
+    state: &AppState,
+    query: &UsageSummaryQuery,
+) -> Result<UsageSummaryResponse, ApiError> {
+    let rows = state.store.aggregate_usage(query).await?;
+    Ok(UsageSummaryResponse {
+        since: query.since.to_rfc3339(),
+        until: query.until.to_rfc3339(),
+        interval: query.interval.map(|interval| interval.as_str()),
+        group_by: query.group_by.iter().map(UsageDimension::as_str).collect(),
+        total_cost_cents: rows.iter().map(|row| row.cost_cents).sum(),
+        rows: rows.iter().map(UsageSummaryRowResponse::from).collect(),
+    })
+}
+
+/// Summarize the current user's usage.
+///
+/// Costs are as charged; later reversals are not subtracted.
+pub async fn get_usage_summary(
+    State(state): State<Arc<AppState>>,
+    user: AuthUser,
+    Query(params): Query<UsageSummaryParams>,
+) -> Result<Json<UsageSummaryResponse>, ApiError> {
+    let query = params.to_store_query(Some(user.user_id))?;
+    Ok(Json(summarize(&state, &query).await?))
+}
+
+/// Summarize usage across all users, or one user given by `user_id`.
+///
+/// Requires `X-Admin-Key` header.
+pub async fn admin_get_usage_summary(
+    State(state): State<Arc<AppState>>,
+    admin: AdminAuth,
+    Query(params): Query<AdminUsageSummaryParams>,
+) -> Result<Json<UsageSummaryResponse>, ApiError> {
+    let user_id = params
+        .user_id
+        .as_deref()
+        .map(|id| {
+            id.parse::<UserId>()
+                .map_err(|_| ApiError::BadRequest("Invalid user ID".into()))
+        })
+        .transpose()?;
+    let query = params.summary.to_store_query(user_id)?;
+
+    tracing::info!(
+        admin_id = %admin.admin_id,
+        user_id = ?user_id,
+        "Admin usage summary"
+    );
+
+    Ok(Json(summarize(&state, &query).await?))
+}
@@ -7,12 +7,17 @@ use axum::http::HeaderMap;
 use axum::Json;
 use serde::{Deserialize, Serialize};
 
-use z_billing_core::{CreditTransaction, Plan, Subscription, SubscriptionStatus};
-use z_billing_store::Store;
+use z_billing_core::{grant, CreditTransaction, GiftCard, Plan, Subscription, SubscriptionStatus};
 
+use crate::config::ServiceConfig;
 use crate::crypto::{constant_time_eq, hmac_sha256_hex};
 use crate::error::ApiError;
+use crate::handlers::accounts::{
+    get_or_create_account, update_account, update_account_with_outbox, upsert_account,
+    upsert_account_with_outbox,
+};
 use crate::state::AppState;
+use crate::stripe::CheckoutPurpose;
 
 /// Stripe webhook payload (simplified).
 #[derive(Debug, Deserialize)]
@@ -83,7 +88,7 @@ pub async fn stripe_webhook(
     );
 
     // Replay protection: reject if this event was already processed
-    if state.store.has_webhook_event(&webhook.id)? {
+    if state.store.has_webhook_event(&webhook.id).await? {
         tracing::warn!(event_id = %webhook.id, "Duplicate Stripe webhook event, skipping");
         return Ok(Json(WebhookResponse { received: true }));
     }
@@ -115,7 +120,7 @@ pub async fn stripe_webhook(
     }
 
     // Record event as processed for replay protection
-    state.store.record_webhook_event(&webhook.id, "stripe")?;
+    state.store.record_webhook_event(&webhook.id, "stripe").await?;
 
     Ok(Json(WebhookResponse { received: true }))
 }
@@ -176,7 +181,7 @@ pub async fn lago_webhook(
     let dedup_key = format!("lago:{}:{}", webhook.webhook_type, lago_id);
 
     // Replay protection: reject if this event was already processed
-    if state.store.has_webhook_event(&dedup_key)? {
+    if state.store.has_webhook_event(&dedup_key).await? {
         tracing::warn!(dedup_key = %dedup_key, "Duplicate Lago webhook event, skipping");
         return Ok(Json(WebhookResponse { received: true }));
     }
@@ -201,7 +206,7 @@ pub async fn lago_webhook(
     }
 
     // Record event as processed for replay protection
-    state.store.record_webhook_event(&dedup_key, "lago")?;
+    state.store.record_webhook_event(&dedup_key, "lago").await?;
 
     Ok(Json(WebhookResponse { received: true }))
 }
@@ -223,17 +228,10 @@ async fn handle_checkout_completed(
 
         if let (Some(uid_str), Some(cid)) = (user_id_str, customer_id) {
             if let Ok(user_id) = uid_str.parse::<z_billing_core::UserId>() {
-                let mut account = match state.store.get_account(&user_id)? {
-                    Some(a) => a,
-                    None => {
-                        let a = z_billing_core::Account::new(user_id);
-                        state.store.put_account(&a)?;
-                        a
-                    }
-                };
-                account.stripe_customer_id = Some(cid.to_string());
-                account.updated_at = chrono::Utc::now();
-                state.store.put_account(&account)?;
+                upsert_account(state.store.as_ref(), &user_id, |account| {
+                    account.stripe_customer_id = Some(cid.to_string());
+                })
+                .await?;
 
                 tracing::info!(
                     user_id = %uid_str,
@@ -305,10 +303,20 @@ async fn handle_checkout_completed(
         .parse()
         .map_err(|_| ApiError::BadRequest(format!("Invalid user_id: {user_id_str}")))?;
 
+    // Gift card checkouts mint a redemption code instead of crediting the buyer
+    let purpose = data
+        .get("metadata")
+        .and_then(|m| m.get("purpose"))
+        .and_then(|v| v.as_str());
+    if purpose == Some(CheckoutPurpose::GiftCard.as_str()) {
+        return issue_gift_card(state, user_id, credits_amount, session_id).await;
+    }
+
     // Get account
     let account = state
         .store
-        .get_account(&user_id)?
+        .get_account(&user_id)
+        .await?
         .ok_or_else(|| ApiError::NotFound(format!("Account not found for user {user_id_str}")))?;
 
     // Create transaction
@@ -324,20 +332,24 @@ async fn handle_checkout_completed(
         ),
     );
 
-    // Add credits
-    let balance = state.store.add_credits(&user_id, credits_amount, &tx)?;
-
-    // Broadcast balance update to WebSocket clients
-    #[allow(clippy::cast_precision_loss)]
-    let _ = state.balance_tx.send(
+    // Add credits, queueing the balance broadcast and analytics with them
+    let mut outbox = vec![crate::outbox::balance_update(&user_id)];
+    outbox.extend(crate::outbox::mixpanel(
+        &state.config,
+        "payment_completed",
+        &user_id_str,
         serde_json::json!({
-            "type": "balance.updated",
-            "userId": user_id_str,
-            "balanceCents": balance,
-            "balanceFormatted": format!("${:.2}", balance as f64 / 100.0),
-        })
-        .to_string(),
-    );
+            "amount_cents": amount_total,
+            "amount_dollars": amount_total as f64 / 100.0,
+            "credits_purchased": credits_amount,
+            "balance_after": new_balance,
+        }),
+    ));
+    let balance = state
+        .store
+        .add_credits(&user_id, credits_amount, &tx, &outbox)
+        .await?;
+    crate::outbox::committed(state, &outbox).await;
 
     tracing::info!(
         user_id = %user_id_str,
@@ -347,16 +359,50 @@ async fn handle_checkout_completed(
         "Credits added from Stripe checkout"
     );
 
-    crate::mixpanel::track(
-        state.config.mixpanel_token.as_deref(),
-        "payment_completed",
-        &user_id_str,
+    Ok(())
+}
+
+/// Mint a gift card for a paid gift card checkout.
+async fn issue_gift_card(
+    state: &AppState,
+    purchaser_id: z_billing_core::UserId,
+    amount_cents: i64,
+    session_id: &str,
+) -> Result<(), ApiError> {
+    if amount_cents <= 0 {
+        return Err(ApiError::BadRequest(format!(
+            "Gift card checkout {session_id} has no credits amount"
+        )));
+    }
+
+    let card = GiftCard::issue(purchaser_id, amount_cents, Some(session_id.to_string()));
+    let mut outbox = Vec::new();
+    outbox.extend(crate::outbox::mixpanel(
+        &state.config,
+        "gift_card_purchased",
+        &purchaser_id.to_string(),
         serde_json::json!({
-            "amount_cents": amount_total,
-            "amount_dollars": amount_total as f64 / 100.0,
-            "credits_purchased": credits_amount,
-            "balance_after": balance,
+            "amount_cents": amount_cents,
+            "gift_card_id": card.id.to_string(),
         }),
+    ));
+    let issued = state.store.create_gift_card(&card, &outbox).await?;
+    if issued.id != card.id {
+        tracing::info!(
+            session_id = %session_id,
+            gift_card_id = %issued.id,
+            "Gift card already issued for checkout, skipping"
+        );
+        return Ok(());
+    }
+    state.wake_outbox();
+
+    tracing::info!(
+        user_id = %purchaser_id,
+        session_id = %session_id,
+        gift_card_id = %card.id,
+        amount_cents = %amount_cents,
+        "Gift card issued from Stripe checkout"
     );
 
     Ok(())
@@ -443,7 +489,10 @@ fn plan_from_invoice_lines(data: &serde_json::Value) -> Option<Plan> {
 ///
 /// Tries metadata first, then falls back to looking up the account
 /// by the Stripe customer ID (saved during checkout.session.completed).
-fn extract_user_id(data: &serde_json::Value, state: &AppState) -> Option<z_billing_core::UserId> {
+async fn extract_user_id(
+    data: &serde_json::Value,
+    state: &AppState,
+) -> Option<z_billing_core::UserId> {
     // Try metadata.user_id on the object itself
     let uid_str = data
         .get("metadata")
@@ -463,7 +512,7 @@ fn extract_user_id(data: &serde_json::Value, state: &AppState) -> Option<z_billi
 
     // Fallback: look up account by Stripe customer ID
     let customer_id = data.get("customer").and_then(|v| v.as_str())?;
-    let account = state.store.find_account_by_stripe_customer(customer_id).ok()??;
+    let account = state.store.find_account_by_stripe_customer(customer_id).await.ok()??;
     Some(account.user_id)
 }
 
@@ -480,7 +529,7 @@ async fn handle_subscription_update(
     let status = data.get("status").and_then(|v| v.as_str()).unwrap_or("unknown");
     let cancel_at_period_end = data.get("cancel_at_period_end").and_then(|v| v.as_bool()).unwrap_or(false);
 
-    let user_id = match extract_user_id(data, state) {
+    let user_id = match extract_user_id(data, state).await {
         Some(uid) => uid,
         None => {
             tracing::warn!(subscription_id = %subscription_id, "Subscription update — no user_id in metadata or customer lookup, skipping");
@@ -519,84 +568,87 @@ async fn handle_subscription_update(
         }
     };
 
-    // Update account
-    let mut account = match state.store.get_account(&user_id)? {
-        Some(a) => a,
-        None => {
-            let a = z_billing_core::Account::new(user_id);
-            state.store.put_account(&a)?;
-            a
+    // Sync pro status to zos-api (any paid tier = pro).
+    // cancel_at_period_end means user still has access until period ends,
+    // so they remain pro. Only handle_subscription_deleted revokes pro.
+    let is_pro = plan != Plan::Mortal;
+
+    // Update account, queueing the analytics and pro status sync with it
+    let queue = |_: &z_billing_core::Account| {
+        let mut outbox = Vec::new();
+        if event_type == "customer.subscription.created" {
+            outbox.extend(crate::outbox::mixpanel(
+                &state.config,
+                "subscription_created",
+                &user_id.to_string(),
+                serde_json::json!({
+                    "plan": format!("{plan:?}"),
+                    "subscription_id": subscription_id,
+                }),
+            ));
         }
+        outbox.extend(crate::outbox::zos_pro_status(&state.config, &user_id, is_pro));
+        outbox
     };
+    let (account, outbox) = upsert_account_with_outbox(state.store.as_ref(), &user_id, queue, |account| {
+        if let Some(cid) = data.get("customer").and_then(|v| v.as_str()) {
+            account.stripe_customer_id = Some(cid.to_string());
+        }
 
-    if let Some(cid) = data.get("customer").and_then(|v| v.as_str()) {
-        account.stripe_customer_id = Some(cid.to_string());
-    }
-
-    account.subscription = Some(Subscription {
-        plan: plan.clone(),
-        status: sub_status,
-        current_period_start: period_start,
-        current_period_end: period_end,
-        lago_subscription_id: String::new(),
-        stripe_subscription_id: Some(subscription_id.to_string()),
-        created_at: account.subscription.as_ref().map_or_else(chrono::Utc::now, |s| s.created_at),
-    });
-    account.updated_at = chrono::Utc::now();
-    state.store.put_account(&account)?;
+        account.subscription = Some(Subscription {
+            plan: plan.clone(),
+            status: sub_status.clone(),
+            current_period_start: period_start,
+            current_period_end: period_end,
+            lago_subscription_id: String::new(),
+            stripe_subscription_id: Some(subscription_id.to_string()),
+            created_at: account.subscription.as_ref().map_or_else(chrono::Utc::now, |s| s.created_at),
+        });
+    })
+    .await?;
+    crate::outbox::committed(state, &outbox).await;
 
     // Grant referral credits on first subscription if this user was referred.
-    // Only fires once — checked via ReferralBonus transaction history.
+    // Only fires once — checked via ReferralBonus transaction history, with
+    // the invitee's referral grant key guarding concurrent deliveries.
     if let Some(ref inviter_id_str) = account.referred_by {
         if let Ok(inviter_id) = inviter_id_str.parse::<z_billing_core::UserId>() {
             // Check if referral already granted
-            let already_granted = state.store.has_referral_bonus(&user_id)?;
+            let already_granted = state.store.has_referral_bonus(&user_id).await?;
 
             if !already_granted {
                 let amount = super::credits::referral_grant_amount();
 
                 // Grant to invitee
-                let invitee_balance = {
-                    let acc = state.store.get_account(&user_id)?.unwrap_or(account.clone());
+                let granted = {
+                    let acc = state.store.get_account(&user_id).await?.unwrap_or(account.clone());
                     let nb = acc.balance_cents + amount;
                     let tx = CreditTransaction::referral_bonus(user_id, amount, nb, format!("Referral bonus — invited by {inviter_id_str}"));
-                    state.store.add_credits(&user_id, amount, &tx)?
+                    let outbox = [crate::outbox::balance_update(&user_id)];
+                    let granted = state.store.grant_once(&user_id, grant::REFERRAL, &tx, &outbox).await?.is_some();
+                    if granted {
+                        crate::outbox::committed(state, &outbox).await;
+                    }
+                    granted
                 };
 
                 // Grant to inviter
-                let inviter_balance = {
-                    let acc = match state.store.get_account(&inviter_id)? {
-                        Some(a) => a,
-                        None => {
-                            let a = z_billing_core::Account::new(inviter_id);
-                            state.store.put_account(&a)?;
-                            a
-                        }
-                    };
+                if granted {
+                    let acc = get_or_create_account(state.store.as_ref(), &inviter_id).await?;
                     let nb = acc.balance_cents + amount;
                     let tx = CreditTransaction::referral_bonus(inviter_id, amount, nb, format!("Referral bonus — {} subscribed", user_id));
-                    state.store.add_credits(&inviter_id, amount, &tx)?
-                };
-
-                tracing::info!(
-                    user_id = %user_id,
-                    inviter_id = %inviter_id_str,
-                    amount = %amount,
-                    "Referral credits granted on first subscription"
-                );
+                    let outbox = [crate::outbox::balance_update(&inviter_id)];
+                    if state.store.grant_once(&inviter_id, &grant::referrer(&user_id), &tx, &outbox).await?.is_some() {
+                        crate::outbox::committed(state, &outbox).await;
+                    }
 
-                // Broadcast balance updates
-                #[allow(clippy::cast_precision_loss)]
-                let _ = state.balance_tx.send(serde_json::json!({
-                    "type": "balance.updated",
-                    "userId": user_id.to_string(),
-                    "balanceCents": invitee_balance,
-                }).to_string());
-                let _ = state.balance_tx.send(serde_json::json!({
-                    "type": "balance.updated",
-                    "userId": inviter_id_str,
-                    "balanceCents": inviter_balance,
-                }).to_string());
+                    tracing::info!(
+                        user_id = %user_id,
+                        inviter_id = %inviter_id_str,
+                        amount = %amount,
+                        "Referral credits granted on first subscription"
+                    );
+                }
             }
         }
     }
@@ -610,24 +662,6 @@ async fn handle_subscription_update(
         "Subscription synced to z-billing"
     );
 
-    if event_type == "customer.subscription.created" {
-        crate::mixpanel::track(
-            state.config.mixpanel_token.as_deref(),
-            "subscription_created",
-            &user_id.to_string(),
-            serde_json::json!({
-                "plan": format!("{plan:?}"),
-                "subscription_id": subscription_id,
-            }),
-        );
-    }
-
-    // Sync pro status to zos-api (any paid tier = pro).
-    // cancel_at_period_end means user still has access until period ends,
-    // so they remain pro. Only handle_subscription_deleted revokes pro.
-    let is_pro = plan != Plan::Mortal;
-    sync_pro_status_to_zos(state, &user_id, is_pro);
-
     Ok(())
 }
 
@@ -640,7 +674,7 @@ async fn handle_subscription_deleted(
 ) -> Result<(), ApiError> {
     let subscription_id = data.get("id").and_then(|v| v.as_str()).unwrap_or("unknown");
 
-    let user_id = match extract_user_id(data, state) {
+    let user_id = match extract_user_id(data, state).await {
         Some(uid) => uid,
         None => {
             tracing::warn!(subscription_id = %subscription_id, "Subscription deleted — no user_id, skipping");
@@ -648,22 +682,26 @@ async fn handle_subscription_deleted(
         }
     };
 
-    if let Some(mut account) = state.store.get_account(&user_id)? {
-        account.subscription = None;
-        account.updated_at = chrono::Utc::now();
-        state.store.put_account(&account)?;
-
-        tracing::info!(user_id = %user_id, subscription_id = %subscription_id, "Subscription ended — reverted to Mortal");
-
-        crate::mixpanel::track(
-            state.config.mixpanel_token.as_deref(),
+    // Sync pro status to zos-api (no subscription = not pro)
+    let queue = |_: &z_billing_core::Account| {
+        let mut outbox = Vec::new();
+        outbox.extend(crate::outbox::mixpanel(
+            &state.config,
             "subscription_cancelled",
             &user_id.to_string(),
             serde_json::json!({}),
-        );
+        ));
+        outbox.extend(crate::outbox::zos_pro_status(&state.config, &user_id, false));
+        outbox
+    };
+    let ended = update_account_with_outbox(state.store.as_ref(), &user_id, queue, |account| {
+        account.subscription = None;
+    })
+    .await?;
+    if let Some((_, outbox)) = ended {
+        crate::outbox::committed(state, &outbox).await;
 
-        // Sync pro status to zos-api (no subscription = not pro)
-        sync_pro_status_to_zos(state, &user_id, false);
+        tracing::info!(user_id = %user_id, subscription_id = %subscription_id, "Subscription ended — reverted to Mortal");
     }
 
     Ok(())
@@ -713,7 +751,7 @@ async fn handle_invoice_paid(
         None => return Ok(()),
     };
 
-    let user_id = match extract_user_id(data, state) {
+    let user_id = match extract_user_id(data, state).await {
         Some(uid) => uid,
         None => {
             tracing::warn!(subscription_id = %subscription_id, "invoice.paid — no user_id, skipping credit grant");
@@ -721,7 +759,7 @@ async fn handle_invoice_paid(
         }
     };
 
-    let account = match state.store.get_account(&user_id)? {
+    let account = match state.store.get_account(&user_id).await? {
         Some(a) => a,
         None => {
             tracing::warn!(user_id = %user_id, "invoice.paid — account not found");
@@ -785,7 +823,8 @@ async fn handle_invoice_paid(
             let remaining_seconds = (sub.current_period_end - now).num_seconds();
             let already_granted = state
                 .store
-                .sum_monthly_allowance_since(&user_id, sub.current_period_start)?;
+                .sum_monthly_allowance_since(&user_id, sub.current_period_start)
+                .await?;
             let c = prorated_upgrade_credits(
                 plan_credits,
                 already_granted,
@@ -821,31 +860,47 @@ async fn handle_invoice_paid(
 
     let new_balance = account.balance_cents + credits;
     let tx = CreditTransaction::monthly_allowance(user_id, credits, new_balance);
-    let balance = state.store.add_credits(&user_id, credits, &tx)?;
+    let mut outbox = vec![crate::outbox::balance_update(&user_id)];
+    outbox.extend(crate::outbox::mixpanel(
+        &state.config,
+        "subscription_payment_received",
+        &user_id.to_string(),
+        serde_json::json!({
+            "plan": format!("{plan:?}"),
+            "amount_cents": invoice_amount_cents,
+            "amount_dollars": invoice_amount_cents as f64 / 100.0,
+            "credits_granted": credits,
+            "balance_after": new_balance,
+            "billing_reason": billing_reason,
+            "grant_kind": grant_kind,
+        }),
+    ));
+    let invoice_id = data.get("id").and_then(|v| v.as_str()).unwrap_or(subscription_id);
+    let Some(balance) = state
+        .store
+        .grant_once(&user_id, &grant::invoice(invoice_id), &tx, &outbox)
+        .await?
+    else {
+        tracing::info!(
+            user_id = %user_id,
+            invoice_id = %invoice_id,
+            subscription_id = %subscription_id,
+            "invoice.paid — credits already granted for this invoice",
+        );
+        return Ok(());
+    };
+    crate::outbox::committed(state, &outbox).await;
 
     // Advance the monthly clock only on full-grant events (renewal / create).
     // For prorated mid-cycle grants we leave last_monthly_grant_at unchanged
     // so the lazy try_monthly_allowance check still fires at the right time
     // if a later invoice.paid is dropped.
     if grant_kind == "full" {
-        let mut updated = state
-            .store
-            .get_account(&user_id)?
-            .unwrap_or_else(|| account.clone());
-        updated.last_monthly_grant_at = Some(chrono::Utc::now());
-        updated.updated_at = chrono::Utc::now();
-        state.store.put_account(&updated)?;
-    }
-
-    let _ = state.balance_tx.send(
-        serde_json::json!({
-            "type": "balance.updated",
-            "userId": user_id.to_string(),
-            "balanceCents": balance,
-            "balanceFormatted": format!("${:.2}", balance as f64 / 100.0),
+        update_account(state.store.as_ref(), &user_id, |account| {
+            account.last_monthly_grant_at = Some(chrono::Utc::now());
         })
-        .to_string(),
-    );
+        .await?;
+    }
 
     tracing::info!(
         user_id = %user_id,
@@ -858,21 +913,6 @@ async fn handle_invoice_paid(
         "invoice.paid — credits granted",
     );
 
-    crate::mixpanel::track(
-        state.config.mixpanel_token.as_deref(),
-        "subscription_payment_received",
-        &user_id.to_string(),
-        serde_json::json!({
-            "plan": format!("{plan:?}"),
-            "amount_cents": invoice_amount_cents,
-            "amount_dollars": invoice_amount_cents as f64 / 100.0,
-            "credits_granted": credits,
-            "balance_after": balance,
-            "billing_reason": billing_reason,
-            "grant_kind": grant_kind,
-        }),
-    );
-
     Ok(())
 }
 
@@ -883,21 +923,29 @@ async fn handle_payment_failed(
 ) -> Result<(), ApiError> {
     let invoice_id = data.get("id").and_then(|v| v.as_str()).unwrap_or("unknown");
 
-    if let Some(user_id) = extract_user_id(data, state) {
-        if let Some(mut account) = state.store.get_account(&user_id)? {
-            if let Some(ref mut sub) = account.subscription {
-                sub.status = SubscriptionStatus::PastDue;
-                account.updated_at = chrono::Utc::now();
-                state.store.put_account(&account)?;
-
-                tracing::warn!(user_id = %user_id, invoice_id = %invoice_id, "Payment failed — subscription past_due");
-
-                crate::mixpanel::track(
-                    state.config.mixpanel_token.as_deref(),
+    if let Some(user_id) = extract_user_id(data, state).await {
+        let queue = |account: &z_billing_core::Account| {
+            let mut outbox = Vec::new();
+            if account.subscription.is_some() {
+                outbox.extend(crate::outbox::mixpanel(
+                    &state.config,
                     "payment_failed",
                     &user_id.to_string(),
                     serde_json::json!({}),
-                );
+                ));
+            }
+            outbox
+        };
+        let account = update_account_with_outbox(state.store.as_ref(), &user_id, queue, |account| {
+            if let Some(ref mut sub) = account.subscription {
+                sub.status = SubscriptionStatus::PastDue;
+            }
+        })
+        .await?;
+        if let Some((account, outbox)) = account {
+            if account.subscription.is_some() {
+                crate::outbox::committed(state, &outbox).await;
+                tracing::warn!(user_id = %user_id, invoice_id = %invoice_id, "Payment failed — subscription past_due");
 
                 return Ok(());
             }
@@ -971,16 +1019,14 @@ async fn handle_lago_subscription_started(
     }
 
     // Get or create account
-    let account = if let Some(acc) = state.store.get_account(&user_id)? {
+    let account = if let Some(acc) = state.store.get_account(&user_id).await? {
         acc
     } else {
         tracing::warn!(
             user_id = %user_id_str,
             "Account not found for Lago subscription, creating new account"
         );
-        let new_account = z_billing_core::Account::new(user_id);
-        state.store.put_account(&new_account)?;
-        new_account
+        get_or_create_account(state.store.as_ref(), &user_id).await?
     };
 
     // Create transaction for subscription credits
@@ -989,20 +1035,22 @@ async fn handle_lago_subscription_started(
     let tx =
         CreditTransaction::subscription_grant(user_id, monthly_credits, new_balance, &plan_name);
 
-    // Add credits
-    let balance = state.store.add_credits(&user_id, monthly_credits, &tx)?;
-
-    // Broadcast balance update to WebSocket clients
-    #[allow(clippy::cast_precision_loss)]
-    let _ = state.balance_tx.send(
-        serde_json::json!({
-            "type": "balance.updated",
-            "userId": user_id_str,
-            "balanceCents": balance,
-            "balanceFormatted": format!("${:.2}", balance as f64 / 100.0),
-        })
-        .to_string(),
-    );
+    // Add credits, once per Lago subscription
+    let grant_key = grant::lago_subscription(lago_subscription_id.unwrap_or(user_id_str));
+    let outbox = [crate::outbox::balance_update(&user_id)];
+    let Some(balance) = state
+        .store
+        .grant_once(&user_id, &grant_key, &tx, &outbox)
+        .await?
+    else {
+        tracing::info!(
+            user_id = %user_id_str,
+            lago_subscription_id = ?lago_subscription_id,
+            "Subscription credits already granted for this Lago subscription"
+        );
+        return Ok(());
+    };
+    crate::outbox::committed(state, &outbox).await;
 
     tracing::info!(
         user_id = %user_id_str,
@@ -1111,59 +1159,40 @@ fn verify_lago_signature(body: &str, signature: &str, secret: &str) -> Result<()
 /// Calls zos-api's internal billing endpoint to update the user's
 /// `isZeroPro` flag. Any paid tier (Pro/Crusader/Sage) sets it to true,
 /// Mortal (or no subscription) sets it to false.
-///
-/// Fire-and-forget: logs errors but does not fail the webhook handler.
-fn sync_pro_status_to_zos(state: &AppState, user_id: &z_billing_core::UserId, is_pro: bool) {
-    let zos_url = match &state.config.zos_api_url {
-        Some(url) => url.clone(),
-        None => return, // Not configured, skip silently
-    };
-    let zos_token = match &state.config.zos_api_internal_token {
-        Some(token) => token.clone(),
-        None => return,
+pub(crate) async fn sync_pro_status_to_zos(
+    config: &ServiceConfig,
+    user_id: &str,
+    is_pro: bool,
+) -> Result<(), String> {
+    let (Some(zos_url), Some(zos_token)) = (&config.zos_api_url, &config.zos_api_internal_token)
+    else {
+        return Err("zos-api not configured".into());
     };
 
-    let url = format!("{}/internal/billing/pro-status-changed", zos_url);
-    let user_id_str = user_id.to_string();
-
-    tokio::spawn(async move {
-        let client = reqwest::Client::new();
-        match client
-            .post(&url)
-            .header("x-internal-token", &zos_token)
-            .json(&serde_json::json!({
-                "userId": user_id_str,
-                "isZeroPro": is_pro,
-            }))
-            .send()
-            .await
-        {
-            Ok(resp) if resp.status().is_success() => {
-                tracing::info!(
-                    user_id = %user_id_str,
-                    is_pro = %is_pro,
-                    "Synced pro status to zos-api"
-                );
-            }
-            Ok(resp) => {
-                let status = resp.status();
-                let body = resp.text().await.unwrap_or_default();
-                tracing::warn!(
-                    user_id = %user_id_str,
-                    status = %status,
-                    body = %body,
-                    "Failed to sync pro status to zos-api"
-                );
-            }
-            Err(err) => {
-                tracing::warn!(
-                    user_id = %user_id_str,
-                    error = %err,
-                    "Failed to sync pro status to zos-api"
-                );
-            }
-        }
-    });
+    let url = format!("{zos_url}/internal/billing/pro-status-changed");
+    let resp = reqwest::Client::new()
+        .post(&url)
+        .header("x-internal-token", zos_token)
+        .json(&serde_json::json!({
+            "userId": user_id,
+            "isZeroPro": is_pro,
+        }))
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        return Err(format!("zos-api returned {status}: {body}"));
+    }
+
+    tracing::info!(
+        user_id = %user_id,
+        is_pro = %is_pro,
+        "Synced pro status to zos-api"
+    );
+    Ok(())
 }
 
 #[cfg(test)]
@@ -289,6 +289,166 @@ impl LagoClient {
         Ok(())
     }
 
+    /// Send storage usage event.
+    pub async fn send_storage_usage(
+        &self,
+        transaction_id: &str,
+        customer_id: &str,
+        agent_id: Option<&str>,
+        gb_hours: f64,
+    ) -> Result<(), LagoError> {
+        self.send_metric_event(
+            transaction_id,
+            customer_id,
+            metrics::STORAGE_GB_HOURS,
+            serde_json::json!({
+                "gb_hours": gb_hours,
+                "agent_id": agent_id,
+            }),
+        )
+        .await
+    }
+
+    /// Send image generation usage event.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_image_usage(
+        &self,
+        transaction_id: &str,
+        customer_id: &str,
+        provider: &str,
+        model: &str,
+        agent_id: Option<&str>,
+        resolution: &str,
+        count: u64,
+    ) -> Result<(), LagoError> {
+        self.send_metric_event(
+            transaction_id,
+            customer_id,
+            metrics::IMAGES_GENERATED,
+            serde_json::json!({
+                "images": count,
+                "resolution": resolution,
+                "provider": provider,
+                "model": model,
+                "agent_id": agent_id,
+            }),
+        )
+        .await
+    }
+
+    /// Send audio transcription usage event.
+    pub async fn send_audio_usage(
+        &self,
+        transaction_id: &str,
+        customer_id: &str,
+        provider: &str,
+        model: &str,
+        agent_id: Option<&str>,
+        seconds: f64,
+    ) -> Result<(), LagoError> {
+        self.send_metric_event(
+            transaction_id,
+            customer_id,
+            metrics::AUDIO_SECONDS,
+            serde_json::json!({
+                "seconds": seconds,
+                "provider": provider,
+                "model": model,
+                "agent_id": agent_id,
+            }),
+        )
+        .await
+    }
+
+    /// Send text-to-speech usage event.
+    pub async fn send_speech_usage(
+        &self,
+        transaction_id: &str,
+        customer_id: &str,
+        provider: &str,
+        model: &str,
+        agent_id: Option<&str>,
+        characters: u64,
+    ) -> Result<(), LagoError> {
+        self.send_metric_event(
+            transaction_id,
+            customer_id,
+            metrics::TTS_CHARACTERS,
+            serde_json::json!({
+                "characters": characters,
+                "provider": provider,
+                "model": model,
+                "agent_id": agent_id,
+            }),
+        )
+        .await
+    }
+
+    /// Send web search usage event.
+    pub async fn send_search_usage(
+        &self,
+        transaction_id: &str,
+        customer_id: &str,
+        provider: &str,
+        agent_id: Option<&str>,
+        queries: u64,
+    ) -> Result<(), LagoError> {
+        self.send_metric_event(
+            transaction_id,
+            customer_id,
+            metrics::SEARCH_QUERIES,
+            serde_json::json!({
+                "queries": queries,
+                "provider": provider,
+                "agent_id": agent_id,
+            }),
+        )
+        .await
+    }
+
+    /// Send paid tool call usage event.
+    pub async fn send_tool_usage(
+        &self,
+        transaction_id: &str,
+        customer_id: &str,
+        tool: &str,
+        agent_id: Option<&str>,
+        count: u64,
+    ) -> Result<(), LagoError> {
+        self.send_metric_event(
+            transaction_id,
+            customer_id,
+            metrics::TOOL_CALLS,
+            serde_json::json!({
+                "calls": count,
+                "tool": tool,
+                "agent_id": agent_id,
+            }),
+        )
+        .await
+    }
+
+    /// Send one event for a single-metric usage, timestamped now.
+    async fn send_metric_event(
+        &self,
+        transaction_id: &str,
+        customer_id: &str,
+        code: &str,
+        properties: serde_json::Value,
+    ) -> Result<(), LagoError> {
+        self.send_event(EventInput {
+            transaction_id: transaction_id.to_string(),
+            external_customer_id: customer_id.to_string(),
+            code: code.to_string(),
+            // Lago expects Unix timestamp (seconds)
+            timestamp: chrono::Utc::now().timestamp().to_string(),
+            properties: Some(properties),
+            external_subscription_id: None,
+        })
+        .await?;
+        Ok(())
+    }
+
     /// Handle API response and convert errors.
     async fn handle_response<T: serde::de::DeserializeOwned>(
         &self,
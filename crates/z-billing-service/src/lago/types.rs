@@ -204,10 +204,22 @@ pub mod metrics {
     pub const CPU_HOURS: &str = "cpu_hours";
     /// Memory GB hours metric.
     pub const MEMORY_GB_HOURS: &str = "memory_gb_hours";
+    /// Storage GB hours metric.
+    pub const STORAGE_GB_HOURS: &str = "storage_gb_hours";
     /// LLM input tokens metric.
     pub const LLM_INPUT_TOKENS: &str = "llm_input_tokens";
     /// LLM output tokens metric.
     pub const LLM_OUTPUT_TOKENS: &str = "llm_output_tokens";
+    /// Generated images metric.
+    pub const IMAGES_GENERATED: &str = "images_generated";
+    /// Transcribed audio seconds metric.
+    pub const AUDIO_SECONDS: &str = "audio_seconds";
+    /// Text-to-speech characters metric.
+    pub const TTS_CHARACTERS: &str = "tts_characters";
+    /// Web search queries metric.
+    pub const SEARCH_QUERIES: &str = "search_queries";
+    /// Paid tool calls metric.
+    pub const TOOL_CALLS: &str = "tool_calls";
 }
 
 /// Plan codes we use.
@@ -30,9 +30,12 @@ pub mod error;
 pub mod handlers;
 pub mod lago;
 pub mod mixpanel;
+pub mod outbox;
+pub mod pricing;
 pub mod routes;
 pub mod state;
 pub mod stripe;
+pub mod sweeper;
 
 pub use config::ServiceConfig;
 pub use error::ApiError;
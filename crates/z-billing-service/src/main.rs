@@ -1,13 +1,15 @@
 //! Z-Billing Service - HTTP API for Z Credits and Billing
 //!
 //! This is the main entry point for the z-billing service.
-//! Supports PostgreSQL (preferred) or RocksDB as the storage backend.
+//! Supports `PostgreSQL` (preferred), `SQLite` or `RocksDB` as the storage backend.
 
 use std::sync::Arc;
 
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use z_billing_service::{anthropic_cost, create_router, AppState, ServiceConfig};
+use z_billing_service::{
+    anthropic_cost, create_router, outbox, pricing, sweeper, AppState, ServiceConfig,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -27,7 +29,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tracing::info!(
         listen_addr = %config.listen_addr,
-        database = if config.database_url.is_some() { "postgresql" } else { "rocksdb" },
+        database = match config.database_url.as_deref() {
+            Some(url) if is_sqlite_url(url) => "sqlite",
+            Some(_) => "postgresql",
+            None => "rocksdb",
+        },
         lago_configured = %config.lago_api_url.is_some(),
         stripe_configured = %config.stripe_api_key.is_some(),
         "Service configuration loaded"
@@ -36,19 +42,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize store based on configuration
     let store: Arc<dyn z_billing_store::Store> = if let Some(ref database_url) = config.database_url
     {
-        tracing::info!("Connecting to PostgreSQL");
-        let pool = sqlx::postgres::PgPoolOptions::new()
-            .max_connections(20)
-            .connect(database_url)
-            .await?;
-
-        tracing::info!("Running database migrations");
-        sqlx::migrate!("../z-billing-store/migrations")
-            .run(&pool)
-            .await?;
-        tracing::info!("Migrations complete");
-
-        Arc::new(z_billing_store::PgStore::new(pool))
+        if is_sqlite_url(database_url) {
+            #[cfg(feature = "sqlite-backend")]
+            {
+                Arc::new(open_sqlite(database_url).await?)
+            }
+            #[cfg(not(feature = "sqlite-backend"))]
+            {
+                return Err("sqlite:// DATABASE_URL given but SQLite backend not compiled".into());
+            }
+        } else {
+            Arc::new(open_postgres(database_url).await?)
+        }
     } else {
         #[cfg(feature = "rocksdb-backend")]
         {
@@ -62,8 +67,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     // Build app state
-    let state = AppState::new(store, config.clone());
+    let state = AppState::new(store.clone(), config.clone());
+
+    // A configured catalog that does not load is fatal at startup, rather
+    // than silently pricing with the built-in one
+    pricing::reload(&state).await?;
+    let active_pricing = state.pricing();
+    tracing::info!(
+        version = %active_pricing.catalog.version,
+        source = active_pricing.source.as_str(),
+        "Pricing catalog loaded"
+    );
+
     anthropic_cost::spawn_daily_sync(&config);
+    sweeper::spawn(store);
+    outbox::spawn(Arc::new(state.clone()));
+    pricing::spawn(Arc::new(state.clone()));
 
     // Create the router
     let app = create_router(state);
@@ -76,3 +95,54 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Whether `DATABASE_URL` selects the `SQLite` backend.
+fn is_sqlite_url(database_url: &str) -> bool {
+    database_url.starts_with("sqlite:")
+}
+
+/// Connect to `PostgreSQL` and run its migrations.
+async fn open_postgres(
+    database_url: &str,
+) -> Result<z_billing_store::PgStore, Box<dyn std::error::Error>> {
+    tracing::info!("Connecting to PostgreSQL");
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .max_connections(20)
+        .connect(database_url)
+        .await?;
+
+    tracing::info!("Running database migrations");
+    sqlx::migrate!("../z-billing-store/migrations")
+        .run(&pool)
+        .await?;
+    tracing::info!("Migrations complete");
+
+    Ok(z_billing_store::PgStore::new(pool))
+}
+
+/// Open (creating if needed) a `SQLite` database and run its migrations.
+#[cfg(feature = "sqlite-backend")]
+async fn open_sqlite(
+    database_url: &str,
+) -> Result<z_billing_store::SqliteStore, Box<dyn std::error::Error>> {
+    use std::str::FromStr;
+
+    use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+
+    tracing::info!("Opening SQLite database");
+    let options = SqliteConnectOptions::from_str(database_url)?
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal);
+    let pool = SqlitePoolOptions::new()
+        .max_connections(8)
+        .connect_with(options)
+        .await?;
+
+    tracing::info!("Running database migrations");
+    sqlx::migrate!("../z-billing-store/migrations-sqlite")
+        .run(&pool)
+        .await?;
+    tracing::info!("Migrations complete");
+
+    Ok(z_billing_store::SqliteStore::new(pool))
+}
@@ -1,3 +1,6 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
 /// Fire-and-forget Mixpanel event tracking.
 ///
 /// Sends events to the Mixpanel Track API. Silently returns if the token
@@ -14,36 +17,62 @@ pub fn track(token: Option<&str>, event: &str, distinct_id: &str, extra: serde_j
     let distinct_id = distinct_id.to_string();
 
     tokio::spawn(async move {
-        let mut properties = match extra {
-            serde_json::Value::Object(map) => map,
-            _ => serde_json::Map::new(),
-        };
-        properties.insert("distinct_id".into(), serde_json::json!(distinct_id));
-        properties.insert("token".into(), serde_json::json!(token));
-
-        let payload = serde_json::json!([{
-            "event": event,
-            "properties": serde_json::Value::Object(properties),
-        }]);
-
-        let client = reqwest::Client::new();
-        match client
-            .post("https://api.mixpanel.com/track")
-            .header("Content-Type", "application/json")
-            .header("Accept", "text/plain")
-            .json(&payload)
-            .send()
-            .await
-        {
-            Ok(resp) if resp.status().is_success() => {
-                tracing::debug!(event = %event, "Mixpanel event tracked");
-            }
-            Ok(resp) => {
-                tracing::warn!(event = %event, status = %resp.status(), "Mixpanel track failed");
-            }
-            Err(err) => {
-                tracing::warn!(event = %event, error = %err, "Mixpanel track error");
-            }
+        if let Err(err) = send(&token, &event, &distinct_id, extra).await {
+            tracing::warn!(event = %event, error = %err, "Mixpanel track failed");
         }
     });
 }
+
+/// Timeout for one request, well under the outbox claim lease so a hung
+/// request can't outlive the claim and be delivered twice.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reusable HTTP client, so deliveries share a connection pool.
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new())
+    })
+}
+
+/// Send an event to Mixpanel and wait for the result.
+///
+/// Used by the outbox dispatcher, which retries failures itself.
+pub async fn send(
+    token: &str,
+    event: &str,
+    distinct_id: &str,
+    extra: serde_json::Value,
+) -> Result<(), String> {
+    let mut properties = match extra {
+        serde_json::Value::Object(map) => map,
+        _ => serde_json::Map::new(),
+    };
+    properties.insert("distinct_id".into(), serde_json::json!(distinct_id));
+    properties.insert("token".into(), serde_json::json!(token));
+
+    let payload = serde_json::json!([{
+        "event": event,
+        "properties": serde_json::Value::Object(properties),
+    }]);
+
+    let resp = client()
+        .post("https://api.mixpanel.com/track")
+        .header("Content-Type", "application/json")
+        .header("Accept", "text/plain")
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if resp.status().is_success() {
+        tracing::debug!(event = %event, "Mixpanel event tracked");
+        Ok(())
+    } else {
+        Err(format!("Mixpanel returned {}", resp.status()))
+    }
+}
@@ -0,0 +1,304 @@
+//! Transactional outbox dispatcher.
+//!
+//! Handlers never call Lago, Mixpanel, zOS or the WebSocket broadcast
+//! directly after a balance change. They build outbox messages with the
+//! constructors here and hand them to the store operation that makes the
+//! change, so the side effects are recorded in the same write. The
+//! dispatcher then delivers them in the background, retrying with backoff
+//! and dead-lettering messages that keep failing.
+//!
+//! Every instance's dispatcher claims from the same store, but a
+//! `balance.updated` notification only reaches the WebSocket clients of the
+//! instance that broadcasts it. So once the write has landed, the instance
+//! that made it delivers its own balance notifications with [`committed`];
+//! the stored copy is only dispatched if that fails or the instance goes
+//! away first.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use z_billing_core::{OutboxMessage, OutboxTopic, UserId};
+
+use crate::config::ServiceConfig;
+use crate::handlers::usage::{forward_to_lago, UsageMetricRequest};
+use crate::state::AppState;
+
+/// How often the dispatcher polls when nothing wakes it.
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Messages claimed per dispatch round.
+const BATCH_SIZE: usize = 100;
+
+/// How long a claimed message is hidden from other dispatchers.
+const CLAIM_LEASE_SECONDS: i64 = 60;
+
+/// Payload of a [`OutboxTopic::LagoUsage`] message.
+#[derive(Debug, Serialize, Deserialize)]
+struct LagoUsagePayload {
+    event_id: String,
+    user_id: String,
+    agent_id: Option<String>,
+    metric: UsageMetricRequest,
+}
+
+/// Payload of a [`OutboxTopic::Mixpanel`] message.
+#[derive(Debug, Serialize, Deserialize)]
+struct MixpanelPayload {
+    event: String,
+    distinct_id: String,
+    properties: serde_json::Value,
+}
+
+/// Payload of a [`OutboxTopic::ZosProStatus`] message.
+#[derive(Debug, Serialize, Deserialize)]
+struct ZosProStatusPayload {
+    user_id: String,
+    is_pro: bool,
+}
+
+/// Payload of a [`OutboxTopic::BalanceUpdate`] message.
+#[derive(Debug, Serialize, Deserialize)]
+struct BalanceUpdatePayload {
+    user_id: String,
+}
+
+/// Build a usage event for Lago, or `None` if Lago is not configured.
+#[must_use]
+pub fn lago_usage(
+    state: &AppState,
+    event_id: &str,
+    user_id: UserId,
+    agent_id: Option<&str>,
+    metric: &UsageMetricRequest,
+) -> Option<OutboxMessage> {
+    state.lago.as_ref()?;
+    Some(message(
+        OutboxTopic::LagoUsage,
+        &LagoUsagePayload {
+            event_id: event_id.to_string(),
+            user_id: user_id.to_string(),
+            agent_id: agent_id.map(String::from),
+            metric: metric.clone(),
+        },
+    ))
+}
+
+/// Build a Mixpanel event, or `None` if Mixpanel is not configured.
+#[must_use]
+pub fn mixpanel(
+    config: &ServiceConfig,
+    event: &str,
+    distinct_id: &str,
+    properties: serde_json::Value,
+) -> Option<OutboxMessage> {
+    config.mixpanel_token.as_deref().filter(|t| !t.is_empty())?;
+    Some(message(
+        OutboxTopic::Mixpanel,
+        &MixpanelPayload {
+            event: event.to_string(),
+            distinct_id: distinct_id.to_string(),
+            properties,
+        },
+    ))
+}
+
+/// Build a zOS pro status sync, or `None` if zOS is not configured.
+#[must_use]
+pub fn zos_pro_status(
+    config: &ServiceConfig,
+    user_id: &UserId,
+    is_pro: bool,
+) -> Option<OutboxMessage> {
+    config.zos_api_url.as_ref()?;
+    config.zos_api_internal_token.as_ref()?;
+    Some(message(
+        OutboxTopic::ZosProStatus,
+        &ZosProStatusPayload {
+            user_id: user_id.to_string(),
+            is_pro,
+        },
+    ))
+}
+
+/// Build a `balance.updated` notification for WebSocket clients.
+///
+/// The balance is read when the message is delivered, so clients always
+/// see the latest balance even if notifications are delayed.
+#[must_use]
+pub fn balance_update(user_id: &UserId) -> OutboxMessage {
+    message(
+        OutboxTopic::BalanceUpdate,
+        &BalanceUpdatePayload {
+            user_id: user_id.to_string(),
+        },
+    )
+}
+
+fn message(topic: OutboxTopic, payload: &impl Serialize) -> OutboxMessage {
+    OutboxMessage::new(
+        topic,
+        serde_json::to_value(payload).unwrap_or(serde_json::Value::Null),
+    )
+}
+
+/// Deliver the balance notifications of a landed write from this instance,
+/// and wake the dispatcher for the rest of `outbox`.
+///
+/// A notification may still be delivered twice (if a dispatcher claimed it
+/// first), which is harmless as the balance is read at delivery time.
+pub async fn committed(state: &AppState, outbox: &[OutboxMessage]) {
+    for message in outbox
+        .iter()
+        .filter(|m| m.topic == OutboxTopic::BalanceUpdate)
+    {
+        let result = match deliver(state, message).await {
+            Ok(()) => state.store.delete_outbox_message(&message.id).await,
+            // Left for the dispatcher to retry
+            Err(_) => Ok(()),
+        };
+        if let Err(e) = result {
+            tracing::warn!(outbox_id = %message.id, error = %e, "Failed to update outbox message");
+        }
+    }
+    state.wake_outbox();
+}
+
+/// Spawn the background dispatcher.
+///
+/// It runs every [`DISPATCH_INTERVAL`], and straight away whenever a
+/// handler calls [`AppState::wake_outbox`].
+pub fn spawn(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        loop {
+            while dispatch(&state).await == BATCH_SIZE {}
+            tokio::select! {
+                () = state.outbox_wake.notified() => {}
+                () = tokio::time::sleep(DISPATCH_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+/// Claim one batch of due messages and try to deliver each of them.
+///
+/// Delivered messages are deleted; failed ones are rescheduled or
+/// dead-lettered. Returns the number of messages claimed.
+pub async fn dispatch(state: &AppState) -> usize {
+    let now = chrono::Utc::now();
+    let lease = chrono::Duration::seconds(CLAIM_LEASE_SECONDS);
+    let messages = match state
+        .store
+        .claim_outbox_messages(now, lease, BATCH_SIZE)
+        .await
+    {
+        Ok(messages) => messages,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to claim outbox messages");
+            return 0;
+        }
+    };
+
+    for mut message in messages.iter().cloned() {
+        let result = match deliver(state, &message).await {
+            Ok(()) => state.store.delete_outbox_message(&message.id).await,
+            Err(error) => {
+                message.record_failure(error, chrono::Utc::now());
+                tracing::warn!(
+                    outbox_id = %message.id,
+                    topic = message.topic.as_str(),
+                    attempts = message.attempts,
+                    status = message.status.as_str(),
+                    error = message.last_error.as_deref().unwrap_or_default(),
+                    "Outbox delivery failed"
+                );
+                state.store.put_outbox_message(&message).await
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!(outbox_id = %message.id, error = %e, "Failed to update outbox message");
+        }
+    }
+
+    messages.len()
+}
+
+/// Deliver one message to its destination.
+async fn deliver(state: &AppState, message: &OutboxMessage) -> Result<(), String> {
+    match message.topic {
+        OutboxTopic::LagoUsage => {
+            let payload: LagoUsagePayload = parse(message)?;
+            let lago = state.lago.as_ref().ok_or("Lago not configured")?;
+            forward_to_lago(
+                lago,
+                &payload.event_id,
+                &payload.user_id,
+                payload.agent_id.as_deref(),
+                &payload.metric,
+            )
+            .await
+            .map_err(|e| e.to_string())
+        }
+        OutboxTopic::Mixpanel => {
+            let payload: MixpanelPayload = parse(message)?;
+            let token = state
+                .config
+                .mixpanel_token
+                .as_deref()
+                .ok_or("Mixpanel not configured")?;
+            crate::mixpanel::send(
+                token,
+                &payload.event,
+                &payload.distinct_id,
+                payload.properties,
+            )
+            .await
+        }
+        OutboxTopic::ZosProStatus => {
+            let payload: ZosProStatusPayload = parse(message)?;
+            crate::handlers::webhooks::sync_pro_status_to_zos(
+                &state.config,
+                &payload.user_id,
+                payload.is_pro,
+            )
+            .await
+        }
+        OutboxTopic::BalanceUpdate => {
+            let payload: BalanceUpdatePayload = parse(message)?;
+            let user_id: UserId = payload
+                .user_id
+                .parse()
+                .map_err(|_| format!("Invalid user ID: {}", payload.user_id))?;
+            let Some(account) = state
+                .store
+                .get_account(&user_id)
+                .await
+                .map_err(|e| e.to_string())?
+            else {
+                return Ok(());
+            };
+            let balance = account.balance_cents;
+
+            // No subscribers is not a failure: nobody is listening
+            #[allow(clippy::cast_precision_loss)]
+            let _ = state.balance_tx.send(
+                serde_json::json!({
+                    "type": "balance.updated",
+                    "userId": payload.user_id,
+                    "balanceCents": balance,
+                    "overdrawn": account.is_overdrawn(),
+                    "overdraftLocked": account.overdraft_locked,
+                    "creditLimitCents": account.credit_limit_cents,
+                    "balanceFormatted": format!("${:.2}", balance as f64 / 100.0),
+                })
+                .to_string(),
+            );
+            Ok(())
+        }
+    }
+}
+
+fn parse<T: serde::de::DeserializeOwned>(message: &OutboxMessage) -> Result<T, String> {
+    serde_json::from_value(message.payload.clone())
+        .map_err(|e| format!("Invalid {} payload: {e}", message.topic.as_str()))
+}
@@ -0,0 +1,210 @@
+//! Hot-reloadable pricing.
+//!
+//! Usage is priced with the active [`PricingCatalog`], held behind an
+//! [`ArcSwap`] in [`AppState`] so a reload replaces it without blocking
+//! requests in flight. The catalog comes from `PRICING_CATALOG_PATH` if set,
+//! otherwise from the newest version in the store's `pricing_catalog` table,
+//! otherwise from the catalog compiled into the service. A background task
+//! re-reads the source every `PRICING_RELOAD_SECONDS`.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+
+use z_billing_core::{
+    CatalogError, PricingCatalog, PricingConfig, PricingDiff, BUILTIN_CATALOG_VERSION,
+};
+use z_billing_store::StoreError;
+
+use crate::state::AppState;
+
+/// Where the active catalog was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PricingSource {
+    /// Compiled into the service.
+    Builtin,
+    /// A TOML or JSON file at this path.
+    File(String),
+    /// The store's `pricing_catalog` table.
+    Store,
+}
+
+impl PricingSource {
+    /// Source name for logs and API responses.
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Builtin => "builtin",
+            Self::File(_) => "file",
+            Self::Store => "store",
+        }
+    }
+}
+
+/// The pricing currently in effect.
+#[derive(Debug)]
+pub struct ActivePricing {
+    /// The catalog as loaded.
+    pub catalog: PricingCatalog,
+
+    /// The catalog's rates, indexed for pricing usage.
+    pub config: PricingConfig,
+
+    /// Where the catalog was read from.
+    pub source: PricingSource,
+
+    /// When the catalog became active.
+    pub loaded_at: DateTime<Utc>,
+
+    /// The catalog it replaced, if any.
+    pub previous: Option<PricingCatalog>,
+}
+
+impl ActivePricing {
+    /// Pricing from the configuration compiled into the service.
+    #[must_use]
+    pub fn builtin(config: &PricingConfig) -> Self {
+        Self {
+            catalog: PricingCatalog::from_config(BUILTIN_CATALOG_VERSION, config),
+            config: config.clone(),
+            source: PricingSource::Builtin,
+            loaded_at: Utc::now(),
+            previous: None,
+        }
+    }
+
+    /// What changed from the previous catalog, if there was one.
+    #[must_use]
+    pub fn diff(&self) -> Option<PricingDiff> {
+        self.previous
+            .as_ref()
+            .map(|previous| self.catalog.diff(previous))
+    }
+}
+
+/// Shared handle to the active pricing.
+pub type PricingHandle = Arc<ArcSwap<ActivePricing>>;
+
+/// Why a reload did not happen.
+#[derive(Debug, thiserror::Error)]
+pub enum ReloadError {
+    /// The catalog file could not be read.
+    #[error("reading pricing catalog {path}: {message}")]
+    Io {
+        /// Path of the catalog file.
+        path: String,
+        /// The I/O error.
+        message: String,
+    },
+
+    /// The catalog did not parse or failed validation.
+    #[error(transparent)]
+    Catalog(#[from] CatalogError),
+
+    /// The catalog could not be read from the store.
+    #[error(transparent)]
+    Store(#[from] StoreError),
+
+    /// The catalog differs from the active one but has the same version.
+    #[error("pricing catalog {0} changed without a new version")]
+    Unversioned(String),
+}
+
+/// Spawn the background reloader. Does nothing if
+/// `pricing_reload_seconds` is zero.
+pub fn spawn(state: Arc<AppState>) {
+    if state.config.pricing_reload_seconds == 0 {
+        return;
+    }
+    let interval = Duration::from_secs(state.config.pricing_reload_seconds);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Err(e) = reload(&state).await {
+                tracing::warn!(
+                    error = %e,
+                    "Failed to reload pricing catalog, keeping the active one"
+                );
+            }
+        }
+    });
+}
+
+/// Read the configured catalog source and make it active if its version
+/// differs from the active one.
+///
+/// Returns whether the active catalog changed. With no catalog file
+/// configured and none saved in the store, the active catalog is kept.
+pub async fn reload(state: &AppState) -> Result<bool, ReloadError> {
+    let (catalog, source) = match &state.config.pricing_catalog_path {
+        Some(path) => (
+            read_catalog_file(path).await?,
+            PricingSource::File(path.clone()),
+        ),
+        None => match state.store.get_latest_pricing_catalog().await? {
+            Some(catalog) => {
+                catalog.validate()?;
+                (catalog, PricingSource::Store)
+            }
+            None => return Ok(false),
+        },
+    };
+
+    let current = state.pricing.load_full();
+    if catalog.version == current.catalog.version {
+        if catalog != current.catalog {
+            return Err(ReloadError::Unversioned(catalog.version));
+        }
+        return Ok(false);
+    }
+
+    let diff = catalog.diff(&current.catalog);
+    let next = Arc::new(ActivePricing {
+        config: catalog.to_config(),
+        catalog,
+        source,
+        loaded_at: Utc::now(),
+        previous: Some(current.catalog.clone()),
+    });
+
+    // Another reload may have won the race; its catalog stays active
+    let replaced = state.pricing.compare_and_swap(&current, next);
+    if !Arc::ptr_eq(&replaced, &current) {
+        return Ok(false);
+    }
+
+    tracing::info!(
+        from_version = %diff.from_version,
+        to_version = %diff.to_version,
+        rates_changed = diff.rates.len(),
+        models_added = diff.added.len(),
+        models_removed = diff.removed.len(),
+        models_changed = diff.changed.len(),
+        "Pricing catalog reloaded"
+    );
+    Ok(true)
+}
+
+/// Read and validate a catalog file: JSON if the name ends in `.json`,
+/// TOML otherwise.
+async fn read_catalog_file(path: &str) -> Result<PricingCatalog, ReloadError> {
+    let document = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| ReloadError::Io {
+            path: path.to_string(),
+            message: e.to_string(),
+        })?;
+
+    let is_json = Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let catalog = if is_json {
+        PricingCatalog::from_json(&document)?
+    } else {
+        PricingCatalog::from_toml(&document)?
+    };
+    Ok(catalog)
+}
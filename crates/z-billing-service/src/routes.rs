@@ -5,7 +5,7 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use axum::routing::{delete, get, post};
+use axum::routing::{delete, get, post, put};
 use axum::Router;
 use tower::limit::ConcurrencyLimitLayer;
 use tower_http::cors::{Any, CorsLayer};
@@ -14,7 +14,8 @@ use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
 
 use crate::handlers::{
-    accounts, checkout_pages, credits, health, subscriptions, usage, webhooks, ws,
+    accounts, agents, checkout_pages, credits, gift_cards, health, ledger, orgs, outbox, pricing,
+    promos, subscriptions, transfers, usage, usage_summary, webhooks, ws,
 };
 use crate::state::AppState;
 
@@ -39,21 +40,76 @@ const API_MAX_CONCURRENT_REQUESTS: usize = 50;
 /// ## Accounts (ZID JWT auth)
 /// - `POST /v1/accounts` - Create/register account
 /// - `GET /v1/accounts/me` - Get current user's account
+/// - `GET /v1/accounts/:user_id/credit-limit` - Get credit limit and overdraft state (admin key)
+/// - `PUT /v1/accounts/:user_id/credit-limit` - Set credit limit (admin key)
 ///
 /// ## Credits (ZID JWT auth)
 /// - `GET /v1/credits/balance` - Get current balance
 /// - `GET /v1/credits/transactions` - List transaction history
 /// - `POST /v1/credits/purchase` - Initiate credit purchase
 /// - `POST /v1/credits/auto-refill` - Configure auto-refill
+/// - `POST /v1/credits/transfer` - Preview or send purchased credits to a user or organization
+/// - `POST /v1/credits/redeem` - Redeem a promo code for bonus credits
+///
+/// ## Gift cards (ZID JWT auth)
+/// - `POST /v1/gift-cards/purchase` - Start a Stripe checkout for a gift card
+/// - `GET /v1/gift-cards` - List gift cards the caller bought, with their codes
+/// - `POST /v1/gift-cards/redeem` - Redeem a gift card code into the caller's account
+/// - `GET /v1/gift-cards/:card_id` - Get a gift card (admin key)
+/// - `POST /v1/gift-cards/:card_id/void` - Void an unredeemed gift card (admin key)
+///
+/// ## Promo codes (admin key)
+/// - `POST /v1/promos` - Create a promo code
+/// - `GET /v1/promos` - List promo codes
+/// - `GET /v1/promos/:code` - Get a promo code and its redemption count
+/// - `PUT /v1/promos/:code` - Replace a promo code's settings
+/// - `DELETE /v1/promos/:code` - Delete a promo code
+///
+/// ## Agent budgets (ZID JWT auth)
+/// - `GET /v1/agents/spend` - Spend and budgets for every agent that has spent
+/// - `GET /v1/agents/:agent_id/spend` - Spend and budgets for one agent
+/// - `PUT /v1/agents/:agent_id/budgets/:period` - Set a daily/monthly/lifetime budget
+/// - `DELETE /v1/agents/:agent_id/budgets/:period` - Remove a budget
+///
+/// ## Organizations (ZID JWT auth)
+/// - `POST /v1/orgs` - Create an organization (caller becomes owner)
+/// - `GET /v1/orgs/:org_id` - Get organization and shared balance
+/// - `GET /v1/orgs/:org_id/members` - List members and their monthly spend
+/// - `PUT /v1/orgs/:org_id/members/:user_id` - Add or update a member
+/// - `DELETE /v1/orgs/:org_id/members/:user_id` - Remove a member
+/// - `POST /v1/orgs/:org_id/credits` - Add credits to the pool (admin key)
+///
+/// ## Ledger (admin key)
+/// - `GET /v1/ledger/verify` - Check every balance against the ledger
+///
+/// ## Outbox (admin key)
+/// - `GET /v1/outbox` - List undelivered side effects (Lago, Mixpanel, zOS, WebSocket)
+/// - `POST /v1/outbox/:message_id/replay` - Redeliver a stuck or dead-lettered message
+///
+/// ## Pricing (admin key)
+/// - `GET /v1/pricing` - Active catalog version and its diff against the previous one
+/// - `PUT /v1/pricing` - Save a new catalog version to the store and activate it
+/// - `POST /v1/pricing/reload` - Re-read the catalog source now
+///
+/// ## Usage summaries (ZID JWT auth)
+/// - `GET /v1/usage/summary` - Usage totals by day/week, provider, model, maker, agent or source
+/// - `GET /v1/usage/summary/all` - The same across all users, or for one user (admin key)
 ///
 /// ## Usage (Service API Key auth, rate-limited)
 /// - `POST /v1/usage` - Report usage event
 /// - `POST /v1/usage/batch` - Report multiple usage events
 /// - `POST /v1/usage/quote` - Quote usage cost without debiting an account
+/// - `POST /v1/usage/storage` - Report a storage snapshot and charge GB-hours since the last
+/// - `POST /v1/usage/check` - Check available balance
+/// - `POST /v1/usage/reserve` - Hold credits for an in-flight request
+/// - `POST /v1/usage/settle` - Settle a hold into the actual usage debit
+/// - `POST /v1/usage/release` - Release a hold without charging
+/// - `POST /v1/usage/:event_id/reverse` - Refund all or part of a usage charge
 ///
 /// ## Webhooks (Signature verification)
 /// - `POST /webhooks/stripe` - Stripe webhooks
 /// - `POST /webhooks/lago` - Lago webhooks
+#[allow(clippy::too_many_lines)] // One flat route table
 pub fn create_router(state: AppState) -> Router {
     // Extract config values before moving state
     let cors_origins = state.config.cors_origins.clone();
@@ -72,7 +128,14 @@ pub fn create_router(state: AppState) -> Router {
         .route("/", post(usage::report_usage))
         .route("/batch", post(usage::report_usage_batch))
         .route("/quote", post(usage::quote_usage))
+        .route("/storage", post(usage::report_storage_snapshot))
         .route("/check", post(usage::check_balance))
+        .route("/reserve", post(usage::reserve_usage))
+        .route("/settle", post(usage::settle_usage))
+        .route("/release", post(usage::release_usage))
+        .route("/:event_id/reverse", post(usage::reverse_usage))
+        .route("/summary", get(usage_summary::get_usage_summary))
+        .route("/summary/all", get(usage_summary::admin_get_usage_summary))
         .layer(ConcurrencyLimitLayer::new(USAGE_MAX_CONCURRENT_REQUESTS));
 
     // Create concurrency-limited API routes
@@ -81,15 +144,71 @@ pub fn create_router(state: AppState) -> Router {
         .route("/accounts", post(accounts::create_account))
         .route("/accounts/me", get(accounts::get_account))
         .route("/accounts/me", delete(accounts::delete_account))
+        .route(
+            "/accounts/:user_id/credit-limit",
+            get(accounts::admin_get_credit_limit).put(accounts::admin_set_credit_limit),
+        )
         // Credits
         .route("/credits/balance", get(credits::get_balance))
         .route("/credits/transactions", get(credits::list_transactions))
         .route("/credits/purchase", post(credits::purchase_credits))
         .route("/credits/auto-refill", post(credits::configure_auto_refill))
+        .route("/credits/transfer", post(transfers::transfer_credits))
+        .route("/credits/redeem", post(promos::redeem_promo))
         .route("/credits/add", post(credits::admin_add_credits))
         .route("/credits/signup-grant", post(credits::signup_grant))
         .route("/credits/daily-grant", post(credits::daily_grant))
         .route("/credits/referral-grant", post(credits::referral_grant))
+        // Agent budgets
+        .route("/agents/spend", get(agents::list_agent_spend))
+        .route("/agents/:agent_id/spend", get(agents::get_agent_spend))
+        .route(
+            "/agents/:agent_id/budgets/:period",
+            put(agents::set_agent_budget).delete(agents::delete_agent_budget),
+        )
+        // Gift cards
+        .route("/gift-cards", get(gift_cards::list_gift_cards))
+        .route("/gift-cards/purchase", post(gift_cards::purchase_gift_card))
+        .route("/gift-cards/redeem", post(gift_cards::redeem_gift_card))
+        .route("/gift-cards/:card_id", get(gift_cards::admin_get_gift_card))
+        .route(
+            "/gift-cards/:card_id/void",
+            post(gift_cards::admin_void_gift_card),
+        )
+        // Promo codes
+        .route(
+            "/promos",
+            post(promos::admin_create_promo).get(promos::admin_list_promos),
+        )
+        .route(
+            "/promos/:code",
+            get(promos::admin_get_promo)
+                .put(promos::admin_update_promo)
+                .delete(promos::admin_delete_promo),
+        )
+        // Organizations
+        .route("/orgs", post(orgs::create_org))
+        .route("/orgs/:org_id", get(orgs::get_org))
+        .route("/orgs/:org_id/members", get(orgs::list_members))
+        .route(
+            "/orgs/:org_id/members/:user_id",
+            put(orgs::put_member).delete(orgs::remove_member),
+        )
+        .route("/orgs/:org_id/credits", post(orgs::admin_add_org_credits))
+        // Ledger
+        .route("/ledger/verify", get(ledger::verify_ledger))
+        // Outbox
+        .route("/outbox", get(outbox::list_outbox))
+        .route(
+            "/outbox/:message_id/replay",
+            post(outbox::replay_outbox_message),
+        )
+        // Pricing
+        .route(
+            "/pricing",
+            get(pricing::get_pricing).put(pricing::put_pricing),
+        )
+        .route("/pricing/reload", post(pricing::reload_pricing))
         // Subscriptions
         .route("/subscriptions/checkout", post(subscriptions::checkout))
         .route("/subscriptions/portal", post(subscriptions::portal))
@@ -2,10 +2,12 @@
 
 use std::sync::Arc;
 
+use arc_swap::ArcSwap;
 use z_billing_store::Store;
 
 use crate::config::ServiceConfig;
 use crate::lago::LagoClient;
+use crate::pricing::{ActivePricing, PricingHandle};
 use crate::stripe::StripeClient;
 
 /// Application state shared across handlers.
@@ -25,6 +27,12 @@ pub struct AppState {
 
     /// Broadcast channel for real-time balance updates.
     pub balance_tx: tokio::sync::broadcast::Sender<String>,
+
+    /// Wakes the outbox dispatcher when new messages are written.
+    pub outbox_wake: Arc<tokio::sync::Notify>,
+
+    /// The pricing catalog in effect, replaced on reload.
+    pub pricing: PricingHandle,
 }
 
 impl AppState {
@@ -70,6 +78,9 @@ impl AppState {
         }
 
         let (balance_tx, _) = tokio::sync::broadcast::channel::<String>(256);
+        let pricing = Arc::new(ArcSwap::from_pointee(ActivePricing::builtin(
+            &config.pricing,
+        )));
 
         Self {
             store,
@@ -77,15 +88,30 @@ impl AppState {
             lago,
             stripe,
             balance_tx,
+            outbox_wake: Arc::new(tokio::sync::Notify::new()),
+            pricing,
         }
     }
 
+    /// The pricing in effect now. Hold on to it for the length of a
+    /// request so one request is priced with one catalog.
+    #[must_use]
+    pub fn pricing(&self) -> Arc<ActivePricing> {
+        self.pricing.load_full()
+    }
+
     /// Check if Lago is configured.
     #[must_use]
     pub fn has_lago(&self) -> bool {
         self.lago.is_some()
     }
 
+    /// Wake the outbox dispatcher so newly written messages go out now
+    /// rather than on its next poll.
+    pub fn wake_outbox(&self) {
+        self.outbox_wake.notify_one();
+    }
+
     /// Check if Stripe is configured.
     #[must_use]
     pub fn has_stripe(&self) -> bool {
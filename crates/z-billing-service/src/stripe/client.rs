@@ -5,8 +5,8 @@ use std::collections::HashMap;
 use std::time::Duration;
 
 use super::types::{
-    CheckoutLineItem, CheckoutSession, Customer, PaymentIntent, PriceData, ProductData,
-    StripeErrorResponse, StripeList,
+    CheckoutLineItem, CheckoutPurpose, CheckoutSession, Customer, PaymentIntent, PriceData,
+    ProductData, StripeErrorResponse, StripeList,
 };
 
 /// Error type for Stripe operations.
@@ -136,6 +136,7 @@ impl StripeClient {
     /// * `user_id` - Our internal user ID (`client_reference_id`)
     /// * `amount_cents` - Amount to charge in cents
     /// * `credits_amount` - Number of credits being purchased (for display)
+    /// * `purpose` - Whether the credits go to the buyer or onto a gift card
     /// * `success_url` - URL to redirect on success
     /// * `cancel_url` - URL to redirect on cancel
     #[allow(clippy::too_many_arguments)] // Required for Stripe checkout parameters
@@ -145,6 +146,7 @@ impl StripeClient {
         user_id: &str,
         amount_cents: i64,
         credits_amount: i64,
+        purpose: CheckoutPurpose,
         success_url: &str,
         cancel_url: &str,
     ) -> Result<CheckoutSession, StripeError> {
@@ -152,7 +154,7 @@ impl StripeClient {
             price_data: PriceData {
                 currency: "usd".to_string(),
                 product_data: ProductData {
-                    name: "Z Credits".to_string(),
+                    name: purpose.product_name().to_string(),
                     description: Some(format!("{credits_amount} Z Credits for Aura Swarm")),
                 },
                 unit_amount: amount_cents,
@@ -170,7 +172,7 @@ impl StripeClient {
             ("line_items[0][price_data][currency]", "usd".to_string()),
             (
                 "line_items[0][price_data][product_data][name]",
-                "Z Credits".to_string(),
+                purpose.product_name().to_string(),
             ),
             (
                 "line_items[0][price_data][product_data][description]",
@@ -183,6 +185,7 @@ impl StripeClient {
             ("line_items[0][quantity]", "1".to_string()),
             ("metadata[user_id]", user_id.to_string()),
             ("metadata[credits_amount]", credits_amount.to_string()),
+            ("metadata[purpose]", purpose.as_str().to_string()),
         ];
 
         if let Some(cid) = customer_id {
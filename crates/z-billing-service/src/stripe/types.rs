@@ -153,6 +153,35 @@ impl From<&PaymentIntent> for PaymentResponse {
     }
 }
 
+/// What a credit checkout session pays for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckoutPurpose {
+    /// Credits added to the buyer's own account.
+    Credits,
+    /// A gift card whose code the buyer can pass on.
+    GiftCard,
+}
+
+impl CheckoutPurpose {
+    /// Value sent as `metadata[purpose]` and read back by the webhook.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Credits => "credits",
+            Self::GiftCard => "gift_card",
+        }
+    }
+
+    /// Product name shown on the Checkout page.
+    #[must_use]
+    pub const fn product_name(&self) -> &'static str {
+        match self {
+            Self::Credits => "Z Credits",
+            Self::GiftCard => "Z Credits Gift Card",
+        }
+    }
+}
+
 /// Checkout line item for creating sessions.
 #[derive(Debug, Clone, Serialize)]
 pub struct CheckoutLineItem {
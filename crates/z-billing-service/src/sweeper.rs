@@ -0,0 +1,38 @@
+//! Background maintenance sweeps.
+//!
+//! Periodically closes out records whose lifetime has passed: credit
+//! reservations that were never settled or released, and credit lots whose
+//! unspent credits have expired.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use z_billing_store::Store;
+
+/// How often the sweeper runs.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Spawn the background sweeper.
+pub fn spawn(store: Arc<dyn Store>) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+            sweep(store.as_ref()).await;
+        }
+    });
+}
+
+/// Run a single sweep.
+pub async fn sweep(store: &dyn Store) {
+    match store.expire_reservations(chrono::Utc::now()).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!(count, "Expired stale credit reservations"),
+        Err(e) => tracing::warn!(error = %e, "Failed to expire credit reservations"),
+    }
+
+    match store.expire_credit_lots(chrono::Utc::now()).await {
+        Ok(0) => {}
+        Ok(count) => tracing::info!(count, "Expired unused credit lots"),
+        Err(e) => tracing::warn!(error = %e, "Failed to expire credit lots"),
+    }
+}
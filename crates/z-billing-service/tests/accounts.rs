@@ -108,6 +108,7 @@ async fn get_missing_account_auto_creates_zero_balance_account() {
     let account = harness
         .store
         .get_account(&harness.test_user_id)
+        .await
         .expect("store read should succeed");
     assert!(account.is_some());
 }
@@ -152,6 +153,7 @@ async fn delete_account_success() {
     let account = harness
         .store
         .get_account(&harness.test_user_id)
+        .await
         .expect("store read should succeed");
     assert!(account.is_none());
 }
@@ -168,3 +170,87 @@ async fn delete_nonexistent_account_fails() {
 
     response.assert_status_not_found();
 }
+
+// ============================================================================
+// Credit Limits
+// ============================================================================
+
+#[tokio::test]
+async fn credit_limit_lets_usage_overdraw_until_limit() {
+    let harness = TestHarness::new();
+    let user_id = harness.test_user_id.to_string();
+    harness
+        .store
+        .put_account(&z_billing_core::Account::new(harness.test_user_id))
+        .await
+        .unwrap();
+
+    let response = harness
+        .server
+        .put(&format!("/v1/accounts/{user_id}/credit-limit"))
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&json!({ "credit_limit_cents": 500 }))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["credit_limit_cents"], 500);
+    assert_eq!(body["overdraft_locked"], false);
+
+    let report = |event_id: &'static str, cost_cents: i64| {
+        harness
+            .server
+            .post("/v1/usage")
+            .add_header("x-api-key", &harness.service_api_key)
+            .add_header("x-service-name", "aura-runtime")
+            .json(&json!({
+                "event_id": event_id,
+                "user_id": user_id,
+                "metric": { "type": "api_calls", "endpoint": "/search", "count": 1 },
+                "cost_cents": cost_cents
+            }))
+    };
+
+    report("evt_overdraft_1", 500).await.assert_status_ok();
+    report("evt_overdraft_2", 1)
+        .await
+        .assert_status(axum::http::StatusCode::PAYMENT_REQUIRED);
+
+    let response = harness
+        .server
+        .get("/v1/credits/balance")
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["balance_cents"], -500);
+    assert_eq!(body["overdrawn"], true);
+    assert_eq!(body["overdraft_locked"], true);
+
+    // Raising the limit reopens the credit line
+    let response = harness
+        .server
+        .put(&format!("/v1/accounts/{user_id}/credit-limit"))
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&json!({ "credit_limit_cents": 1000 }))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["overdraft_locked"], false);
+    report("evt_overdraft_3", 100).await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn set_credit_limit_requires_admin_key() {
+    let harness = TestHarness::new();
+
+    let response = harness
+        .server
+        .put(&format!(
+            "/v1/accounts/{}/credit-limit",
+            harness.test_user_id
+        ))
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({ "credit_limit_cents": 500 }))
+        .await;
+    response.assert_status_unauthorized();
+}
@@ -0,0 +1,139 @@
+//! Agent budget integration tests.
+
+mod common;
+
+use common::TestHarness;
+use serde_json::json;
+use z_billing_core::AgentId;
+
+async fn create_funded_account(harness: &TestHarness, balance_cents: i64) {
+    harness
+        .server
+        .post("/v1/accounts")
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({}))
+        .await
+        .assert_status_ok();
+
+    harness
+        .server
+        .post("/v1/credits/add")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&json!({
+            "user_id": harness.test_user_id.to_string(),
+            "amount_cents": balance_cents,
+            "reason": "Test funding"
+        }))
+        .await
+        .assert_status_ok();
+}
+
+async fn report_agent_usage(
+    harness: &TestHarness,
+    event_id: &str,
+    agent_id: AgentId,
+    cost_cents: i64,
+) -> axum_test::TestResponse {
+    harness
+        .server
+        .post("/v1/usage")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-runtime")
+        .json(&json!({
+            "event_id": event_id,
+            "user_id": harness.test_user_id.to_string(),
+            "agent_id": agent_id.to_string(),
+            "metric": { "type": "api_calls", "endpoint": "/search", "count": 1 },
+            "cost_cents": cost_cents
+        }))
+        .await
+}
+
+#[tokio::test]
+async fn daily_budget_rejects_usage_with_budget_exceeded() {
+    let harness = TestHarness::new();
+    create_funded_account(&harness, 10000).await;
+    let agent_id = AgentId::generate();
+
+    harness
+        .server
+        .put(&format!("/v1/agents/{agent_id}/budgets/daily"))
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({ "limit_cents": 150 }))
+        .await
+        .assert_status_ok();
+
+    report_agent_usage(&harness, "evt_agent_001", agent_id, 100)
+        .await
+        .assert_status_ok();
+
+    let response = report_agent_usage(&harness, "evt_agent_002", agent_id, 100).await;
+    response.assert_status(axum::http::StatusCode::PAYMENT_REQUIRED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["code"], "budget_exceeded");
+    assert_eq!(body["error"]["details"]["period"], "daily");
+    assert_eq!(body["error"]["details"]["spent"], 100);
+
+    // A different agent still has access to the balance
+    report_agent_usage(&harness, "evt_agent_003", AgentId::generate(), 100)
+        .await
+        .assert_status_ok();
+}
+
+#[tokio::test]
+async fn agent_spend_summary_reports_budgets() {
+    let harness = TestHarness::new();
+    create_funded_account(&harness, 10000).await;
+    let agent_id = AgentId::generate();
+
+    harness
+        .server
+        .put(&format!("/v1/agents/{agent_id}/budgets/monthly"))
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({ "limit_cents": 500 }))
+        .await
+        .assert_status_ok();
+
+    report_agent_usage(&harness, "evt_summary_001", agent_id, 120)
+        .await
+        .assert_status_ok();
+
+    let response = harness
+        .server
+        .get(&format!("/v1/agents/{agent_id}/spend"))
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["today_cents"], 120);
+    assert_eq!(body["lifetime_cents"], 120);
+    assert_eq!(body["budgets"][0]["period"], "monthly");
+    assert_eq!(body["budgets"][0]["remaining_cents"], 380);
+
+    let response = harness
+        .server
+        .get("/v1/agents/spend")
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["agents"].as_array().unwrap().len(), 1);
+    assert_eq!(body["agents"][0]["agent_id"], agent_id.to_string());
+}
+
+#[tokio::test]
+async fn set_budget_rejects_unknown_period() {
+    let harness = TestHarness::new();
+    create_funded_account(&harness, 100).await;
+
+    let response = harness
+        .server
+        .put(&format!(
+            "/v1/agents/{}/budgets/weekly",
+            AgentId::generate()
+        ))
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({ "limit_cents": 100 }))
+        .await;
+    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+}
@@ -6,20 +6,20 @@ use std::sync::Arc;
 
 use axum::Router;
 use axum_test::TestServer;
-use tempfile::TempDir;
 
 use z_billing_core::UserId;
 use z_billing_service::{create_router, AppState, ServiceConfig};
-use z_billing_store::RocksStore;
+use z_billing_store::MemoryStore;
 
 /// Test harness containing everything needed for integration tests.
 pub struct TestHarness {
     /// The test server for making HTTP requests.
     pub server: TestServer,
     /// The backing store for direct setup and assertions.
-    pub store: Arc<RocksStore>,
-    /// Temporary directory for the database (kept alive for test duration).
-    pub _temp_dir: TempDir,
+    pub store: Arc<MemoryStore>,
+    /// The application state behind the server, for driving background
+    /// tasks such as the outbox dispatcher.
+    pub state: AppState,
     /// A test user ID for authenticated requests.
     pub test_user_id: UserId,
     /// The service API key for service-to-service requests.
@@ -31,8 +31,7 @@ pub struct TestHarness {
 impl TestHarness {
     /// Create a new test harness with a fresh database.
     pub fn new() -> Self {
-        let temp_dir = TempDir::new().expect("Failed to create temp directory");
-        let store = Arc::new(RocksStore::open(temp_dir.path()).expect("Failed to open store"));
+        let store = Arc::new(MemoryStore::new());
 
         let service_api_key = "test-service-key".to_string();
         let admin_api_key = "test-admin-key".to_string();
@@ -40,7 +39,7 @@ impl TestHarness {
         let config = ServiceConfig {
             listen_addr: "127.0.0.1:0".into(),
             database_url: None,
-            data_dir: temp_dir.path().to_string_lossy().to_string(),
+            data_dir: String::new(),
             auth_base_url: "http://localhost".into(),
             auth_audience: "z-billing".into(),
             auth_cookie_secret: None,
@@ -57,14 +56,17 @@ impl TestHarness {
             max_body_bytes: 1024 * 1024,
             request_timeout_seconds: 30,
             pricing: z_billing_core::PricingConfig::default(),
+            pricing_catalog_path: None,
+            pricing_reload_seconds: 60,
             zos_api_url: None,
             zos_api_internal_token: None,
             mixpanel_token: None,
             anthropic_admin_api_key: None,
+            reservation_ttl_seconds: 300,
         };
 
         let state = AppState::new(store.clone(), config);
-        let router: Router = create_router(state);
+        let router: Router = create_router(state.clone());
 
         let server = TestServer::new(router).expect("Failed to create test server");
         let test_user_id = UserId::generate();
@@ -72,7 +74,7 @@ impl TestHarness {
         Self {
             server,
             store,
-            _temp_dir: temp_dir,
+            state,
             test_user_id,
             service_api_key,
             admin_api_key,
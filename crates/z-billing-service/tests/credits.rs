@@ -34,6 +34,43 @@ async fn get_balance_success() {
     assert_eq!(body["balance_cents"], 0);
 }
 
+#[tokio::test]
+async fn get_balance_breaks_down_credit_lots() {
+    use z_billing_store::Store;
+
+    let harness = TestHarness::new();
+    let user_id = harness.test_user_id;
+
+    // Balance from before lot tracking has no lot
+    let mut account = z_billing_core::Account::new(user_id);
+    account.balance_cents = 300;
+    harness.store.create_account(&account).await.unwrap();
+
+    let tx = z_billing_core::CreditTransaction::daily_grant(user_id, 50, 350);
+    harness
+        .store
+        .add_credits(&user_id, 50, &tx, &[])
+        .await
+        .unwrap();
+
+    let response = harness
+        .server
+        .get("/v1/credits/balance")
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["balance_cents"], 350);
+    assert_eq!(body["untracked_cents"], 300);
+
+    let lots = body["lots"].as_array().unwrap();
+    assert_eq!(lots.len(), 1);
+    assert_eq!(lots[0]["source"], "daily_grant");
+    assert_eq!(lots[0]["remaining_cents"], 50);
+    assert!(lots[0]["expires_at"].is_string());
+}
+
 #[tokio::test]
 async fn get_balance_without_account_fails() {
     let harness = TestHarness::new();
@@ -108,6 +145,118 @@ async fn list_transactions_with_pagination() {
     response.assert_status_ok();
 }
 
+#[tokio::test]
+async fn list_transactions_filters_and_pages_by_cursor() {
+    use z_billing_store::Store;
+
+    let harness = TestHarness::new();
+    let user_id = harness.test_user_id;
+    harness
+        .store
+        .put_account(&z_billing_core::Account::new(user_id))
+        .await
+        .unwrap();
+
+    for i in 0..3 {
+        let purchase = z_billing_core::CreditTransaction::purchase(
+            user_id,
+            1000,
+            1000,
+            format!("Purchase {i}"),
+        );
+        harness.store.put_transaction(&purchase).await.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2)); // Ensure different ULIDs
+        let usage = z_billing_core::CreditTransaction::usage(
+            user_id,
+            100,
+            900,
+            format!("Usage {i}"),
+            json!({ "model": if i == 1 { "claude-sonnet-4" } else { "gpt-5" } }),
+        );
+        harness.store.put_transaction(&usage).await.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+
+    // Page through everything two at a time
+    let mut descriptions = Vec::new();
+    let mut path = "/v1/credits/transactions?limit=2".to_string();
+    loop {
+        let response = harness
+            .server
+            .get(&path)
+            .add_header("authorization", harness.user_auth_header())
+            .await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        for tx in body["transactions"].as_array().unwrap() {
+            descriptions.push(tx["description"].as_str().unwrap().to_string());
+        }
+        let Some(cursor) = body["next_cursor"].as_str() else {
+            assert_eq!(body["has_more"], false);
+            break;
+        };
+        path = format!("/v1/credits/transactions?limit=2&cursor={cursor}");
+    }
+    assert_eq!(
+        descriptions,
+        [
+            "Usage 2",
+            "Purchase 2",
+            "Usage 1",
+            "Purchase 1",
+            "Usage 0",
+            "Purchase 0"
+        ]
+    );
+
+    let response = harness
+        .server
+        .get("/v1/credits/transactions?type=usage&model=gpt-5")
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let descriptions: Vec<_> = body["transactions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tx| tx["description"].as_str().unwrap())
+        .collect();
+    assert_eq!(descriptions, ["Usage 2", "Usage 0"]);
+
+    let response = harness
+        .server
+        .get("/v1/credits/transactions?sign=credit&limit=1")
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["transactions"][0]["description"], "Purchase 2");
+    assert_eq!(body["has_more"], true);
+}
+
+#[tokio::test]
+async fn list_transactions_rejects_bad_filters() {
+    let harness = TestHarness::new();
+
+    harness
+        .server
+        .post("/v1/accounts")
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({}))
+        .await
+        .assert_status_ok();
+
+    for query in ["type=nonsense", "cursor=not-a-ulid"] {
+        harness
+            .server
+            .get(&format!("/v1/credits/transactions?{query}"))
+            .add_header("authorization", harness.user_auth_header())
+            .await
+            .assert_status_bad_request();
+    }
+}
+
 // ============================================================================
 // Admin Add Credits
 // ============================================================================
@@ -0,0 +1,158 @@
+//! Gift card integration tests.
+//!
+//! Cards are issued directly through the store here; issuing them from the
+//! Stripe checkout webhook needs a signed Stripe payload.
+
+mod common;
+
+use common::TestHarness;
+use serde_json::json;
+use z_billing_core::{Account, GiftCard, GiftCardStatus, TransactionType, UserId};
+use z_billing_store::Store;
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+async fn issue_card(harness: &TestHarness, purchaser_id: UserId, amount_cents: i64) -> GiftCard {
+    let session_id = format!("cs_test_{}", UserId::generate());
+    let card = GiftCard::issue(purchaser_id, amount_cents, Some(session_id));
+    harness.store.create_gift_card(&card, &[]).await.unwrap();
+    card
+}
+
+async fn redeem(harness: &TestHarness, code: &str) -> axum_test::TestResponse {
+    harness
+        .server
+        .post("/v1/gift-cards/redeem")
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({ "code": code }))
+        .await
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[tokio::test]
+async fn purchaser_lists_their_cards() {
+    let harness = TestHarness::new();
+    let card = issue_card(&harness, harness.test_user_id, 2500).await;
+    issue_card(&harness, UserId::generate(), 1000).await;
+
+    let response = harness
+        .server
+        .get("/v1/gift-cards")
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let cards = body.as_array().unwrap();
+    assert_eq!(cards.len(), 1);
+    assert_eq!(cards[0]["code"], card.code);
+    assert_eq!(cards[0]["status"], "issued");
+}
+
+#[tokio::test]
+async fn gift_card_redeems_once_into_recipient() {
+    let harness = TestHarness::new();
+    let buyer = UserId::generate();
+    harness
+        .store
+        .put_account(&Account::new(harness.test_user_id))
+        .await
+        .unwrap();
+    let card = issue_card(&harness, buyer, 2500).await;
+
+    // Codes are accepted in any case and without dashes
+    let response = redeem(&harness, &card.code.replace('-', "").to_lowercase()).await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["balance_cents"], 2500);
+
+    let transaction_id = body["transaction_id"].as_str().unwrap().parse().unwrap();
+    let tx = harness
+        .store
+        .get_transaction(&transaction_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(tx.transaction_type, TransactionType::GiftCard);
+    assert_eq!(tx.metadata["gift_card_id"], card.id.to_string());
+
+    let response = redeem(&harness, &card.code).await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+
+    let card = harness
+        .store
+        .get_gift_card(&card.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(card.status, GiftCardStatus::Redeemed);
+    assert_eq!(card.redeemed_by, Some(harness.test_user_id));
+    let account = harness
+        .store
+        .get_account(&harness.test_user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.balance_cents, 2500);
+    assert!(harness.store.verify_ledger().await.unwrap().is_consistent());
+
+    redeem(&harness, "0000-0000-0000-0000")
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn admin_voids_unredeemed_card() {
+    let harness = TestHarness::new();
+    harness
+        .store
+        .put_account(&Account::new(harness.test_user_id))
+        .await
+        .unwrap();
+    let card = issue_card(&harness, UserId::generate(), 1000).await;
+
+    // Users cannot void cards
+    let response = harness
+        .server
+        .post(&format!("/v1/gift-cards/{}/void", card.id))
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({ "reason": "chargeback" }))
+        .await;
+    response.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+    let response = harness
+        .server
+        .post(&format!("/v1/gift-cards/{}/void", card.id))
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&json!({ "reason": "chargeback" }))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"], "voided");
+    assert_eq!(body["void_reason"], "chargeback");
+
+    redeem(&harness, &card.code)
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+
+    // Voiding twice is rejected
+    let response = harness
+        .server
+        .post(&format!("/v1/gift-cards/{}/void", card.id))
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&json!({ "reason": "again" }))
+        .await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+
+    let account = harness
+        .store
+        .get_account(&harness.test_user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.balance_cents, 0);
+}
@@ -0,0 +1,78 @@
+//! Ledger integration tests.
+
+mod common;
+
+use common::TestHarness;
+use serde_json::json;
+
+#[tokio::test]
+async fn ledger_stays_consistent_across_credit_and_usage() {
+    let harness = TestHarness::new();
+
+    harness
+        .server
+        .post("/v1/accounts")
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({}))
+        .await
+        .assert_status_ok();
+
+    harness
+        .server
+        .post("/v1/credits/add")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&json!({
+            "user_id": harness.test_user_id.to_string(),
+            "amount_cents": 1000,
+            "reason": "Test funding"
+        }))
+        .await
+        .assert_status_ok();
+
+    harness
+        .server
+        .post("/v1/usage")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-runtime")
+        .json(&json!({
+            "event_id": "evt_ledger_001",
+            "user_id": harness.test_user_id.to_string(),
+            "metric": { "type": "api_calls", "endpoint": "/search", "count": 1 },
+            "cost_cents": 250
+        }))
+        .await
+        .assert_status_ok();
+
+    harness
+        .server
+        .post("/v1/usage/evt_ledger_001/reverse")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-runtime")
+        .json(&json!({ "amount_cents": 100, "reversal_id": "partial-1" }))
+        .await
+        .assert_status_ok();
+
+    let response = harness
+        .server
+        .get("/v1/ledger/verify")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["consistent"], true);
+    assert_eq!(body["accounts_checked"], 1);
+    assert_eq!(body["unbalanced_cents"], 0);
+    assert!(body["drift"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn verify_ledger_requires_admin_key() {
+    let harness = TestHarness::new();
+
+    let response = harness
+        .server
+        .get("/v1/ledger/verify")
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
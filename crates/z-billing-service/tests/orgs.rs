@@ -0,0 +1,173 @@
+//! Organization integration tests.
+
+mod common;
+
+use common::TestHarness;
+use serde_json::json;
+use z_billing_core::UserId;
+use z_billing_store::Store;
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Create an org owned by the test user, fund it, and add `member` with an
+/// optional monthly cap. Returns the org ID.
+async fn create_funded_org(
+    harness: &TestHarness,
+    balance_cents: i64,
+    member: UserId,
+    cap_cents: Option<i64>,
+) -> String {
+    let response = harness
+        .server
+        .post("/v1/orgs")
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({ "name": "Acme" }))
+        .await;
+    response.assert_status_ok();
+    let org_id = response.json::<serde_json::Value>()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    harness
+        .server
+        .put(&format!("/v1/orgs/{org_id}/members/{member}"))
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({ "role": "member", "monthly_spend_cap_cents": cap_cents }))
+        .await
+        .assert_status_ok();
+
+    harness
+        .server
+        .post(&format!("/v1/orgs/{org_id}/credits"))
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&json!({
+            "user_id": harness.test_user_id.to_string(),
+            "amount_cents": balance_cents,
+            "reason": "Team purchase"
+        }))
+        .await
+        .assert_status_ok();
+
+    org_id
+}
+
+async fn report_org_usage(
+    harness: &TestHarness,
+    event_id: &str,
+    user_id: UserId,
+    org_id: &str,
+    cost_cents: i64,
+) -> axum_test::TestResponse {
+    harness
+        .server
+        .post("/v1/usage")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-runtime")
+        .json(&json!({
+            "event_id": event_id,
+            "user_id": user_id.to_string(),
+            "org_id": org_id,
+            "metric": { "type": "api_calls", "endpoint": "/search", "count": 1 },
+            "cost_cents": cost_cents
+        }))
+        .await
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[tokio::test]
+async fn org_usage_debits_shared_pool() {
+    let harness = TestHarness::new();
+    let member = UserId::generate();
+    let org_id = create_funded_org(&harness, 1000, member, None).await;
+
+    let response = report_org_usage(&harness, "evt_org_001", member, &org_id, 250).await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["balance_cents"], 750);
+
+    // The pool is debited, not the member's personal balance
+    let account = harness.store.get_account(&member).await.unwrap().unwrap();
+    assert_eq!(account.balance_cents, 0);
+
+    let response = harness
+        .server
+        .get(&format!("/v1/orgs/{org_id}"))
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["balance_cents"], 750);
+    assert_eq!(body["lifetime_used_cents"], 250);
+
+    // The transaction records the acting member
+    let txs = harness
+        .store
+        .list_transactions_by_user(&member, 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0].org_id.unwrap().to_string(), org_id);
+}
+
+#[tokio::test]
+async fn org_usage_enforces_member_spend_cap() {
+    let harness = TestHarness::new();
+    let member = UserId::generate();
+    let org_id = create_funded_org(&harness, 1000, member, Some(300)).await;
+
+    report_org_usage(&harness, "evt_cap_001", member, &org_id, 200)
+        .await
+        .assert_status_ok();
+
+    let response = report_org_usage(&harness, "evt_cap_002", member, &org_id, 200).await;
+    response.assert_status(axum::http::StatusCode::PAYMENT_REQUIRED);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"]["code"], "spend_cap_exceeded");
+    assert_eq!(body["error"]["details"]["spent"], 200);
+
+    let response = harness
+        .server
+        .get(&format!("/v1/orgs/{org_id}/members"))
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let capped = body["members"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["user_id"] == member.to_string())
+        .unwrap();
+    assert_eq!(capped["spent_this_month_cents"], 200);
+}
+
+#[tokio::test]
+async fn org_usage_rejects_non_member() {
+    let harness = TestHarness::new();
+    let org_id = create_funded_org(&harness, 1000, UserId::generate(), None).await;
+
+    let response =
+        report_org_usage(&harness, "evt_outsider", UserId::generate(), &org_id, 10).await;
+    response.assert_status(axum::http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn member_cannot_manage_members() {
+    let harness = TestHarness::new();
+    let member = UserId::generate();
+    let org_id = create_funded_org(&harness, 1000, member, None).await;
+
+    let response = harness
+        .server
+        .put(&format!("/v1/orgs/{org_id}/members/{}", UserId::generate()))
+        .add_header("authorization", format!("Bearer test-token:{member}"))
+        .json(&json!({ "role": "member" }))
+        .await;
+    response.assert_status(axum::http::StatusCode::FORBIDDEN);
+}
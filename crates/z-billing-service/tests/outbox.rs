@@ -0,0 +1,171 @@
+//! Outbox integration tests.
+
+mod common;
+
+use axum::http::StatusCode;
+use common::TestHarness;
+use serde_json::json;
+use z_billing_core::{Account, OutboxMessage, OutboxStatus, OutboxTopic, OUTBOX_MAX_ATTEMPTS};
+use z_billing_service::outbox;
+use z_billing_store::Store;
+
+async fn funded_account(harness: &TestHarness) {
+    let mut account = Account::new(harness.test_user_id);
+    account.balance_cents = 10_000;
+    harness.store.create_account(&account).await.unwrap();
+}
+
+#[tokio::test]
+async fn usage_broadcasts_balance_update_after_commit() {
+    let harness = TestHarness::new();
+    funded_account(&harness).await;
+    let mut balance_rx = harness.state.balance_tx.subscribe();
+
+    let response = harness
+        .server
+        .post("/v1/usage")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-runtime")
+        .json(&json!({
+            "event_id": "evt_outbox_001",
+            "user_id": harness.test_user_id.to_string(),
+            "cost_cents": 250,
+            "metric": { "type": "api_calls", "endpoint": "search", "count": 1 }
+        }))
+        .await;
+    response.assert_status_ok();
+
+    // Lago and Mixpanel are not configured, so only the broadcast is queued,
+    // and this instance delivers it once the charge has landed
+    let update: serde_json::Value = serde_json::from_str(&balance_rx.try_recv().unwrap()).unwrap();
+    assert_eq!(update["type"], "balance.updated");
+    assert_eq!(update["userId"], harness.test_user_id.to_string());
+    assert_eq!(update["balanceCents"], 9_750);
+    assert!(harness
+        .store
+        .list_outbox_messages(None, 10)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(outbox::dispatch(&harness.state).await, 0);
+}
+
+#[tokio::test]
+async fn dispatcher_delivers_undelivered_balance_update() {
+    let harness = TestHarness::new();
+    funded_account(&harness).await;
+    let mut balance_rx = harness.state.balance_tx.subscribe();
+
+    // As left behind by an instance that stopped before delivering it
+    let message = outbox::balance_update(&harness.test_user_id);
+    harness.store.put_outbox_message(&message).await.unwrap();
+
+    assert_eq!(outbox::dispatch(&harness.state).await, 1);
+    let update: serde_json::Value = serde_json::from_str(&balance_rx.try_recv().unwrap()).unwrap();
+    assert_eq!(update["userId"], harness.test_user_id.to_string());
+    assert_eq!(update["balanceCents"], 10_000);
+    assert!(harness
+        .store
+        .get_outbox_message(&message.id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn failed_delivery_is_retried_later() {
+    let harness = TestHarness::new();
+
+    // Lago is not configured in the harness, so delivery fails
+    let message = OutboxMessage::new(OutboxTopic::LagoUsage, json!({}));
+    harness.store.put_outbox_message(&message).await.unwrap();
+
+    assert_eq!(outbox::dispatch(&harness.state).await, 1);
+    let stored = harness
+        .store
+        .get_outbox_message(&message.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.status, OutboxStatus::Pending);
+    assert_eq!(stored.attempts, 1);
+    assert!(stored.last_error.is_some());
+    assert!(stored.next_attempt_at > chrono::Utc::now());
+
+    // Not due again until the backoff has passed
+    assert_eq!(outbox::dispatch(&harness.state).await, 0);
+}
+
+#[tokio::test]
+async fn admin_lists_and_replays_dead_letters() {
+    let harness = TestHarness::new();
+    let mut message = OutboxMessage::new(
+        OutboxTopic::BalanceUpdate,
+        json!({ "user_id": harness.test_user_id.to_string() }),
+    );
+    for _ in 0..OUTBOX_MAX_ATTEMPTS {
+        message.record_failure("connection refused", chrono::Utc::now());
+    }
+    harness.store.put_outbox_message(&message).await.unwrap();
+
+    let response = harness
+        .server
+        .get("/v1/outbox?status=dead_lettered")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let messages = body["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["id"], message.id.to_string());
+    assert_eq!(messages[0]["topic"], "balance_update");
+    assert_eq!(messages[0]["last_error"], "connection refused");
+
+    let response = harness
+        .server
+        .post(&format!("/v1/outbox/{}/replay", message.id))
+        .add_header("x-admin-key", harness.admin_key_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"], "pending");
+    assert_eq!(body["attempts"], 0);
+
+    // The replayed message goes out on the next dispatch
+    assert_eq!(outbox::dispatch(&harness.state).await, 1);
+    assert!(harness
+        .store
+        .get_outbox_message(&message.id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn outbox_endpoints_require_admin_key() {
+    let harness = TestHarness::new();
+
+    let response = harness
+        .server
+        .get("/v1/outbox")
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+
+    let response = harness
+        .server
+        .get("/v1/outbox?status=delivered")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let response = harness
+        .server
+        .post(&format!(
+            "/v1/outbox/{}/replay",
+            z_billing_core::OutboxId::generate()
+        ))
+        .add_header("x-admin-key", harness.admin_key_header())
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,257 @@
+//! Pricing catalog integration tests.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use common::TestHarness;
+use serde_json::json;
+use z_billing_core::{Account, ModelPrice, PricingCatalog};
+use z_billing_service::pricing::{self, ReloadError};
+use z_billing_service::{AppState, ServiceConfig};
+use z_billing_store::{MemoryStore, Store};
+
+/// The built-in catalog under a new version, with one extra model.
+fn catalog(version: &str, input_credits_per_million: i64) -> PricingCatalog {
+    let mut catalog = PricingCatalog::builtin();
+    catalog.version = version.to_string();
+    catalog.models.push(ModelPrice {
+        provider: "test".into(),
+        model: "priced-model".into(),
+        input_credits_per_million,
+        output_credits_per_million: 0,
+        cache_read_credits_per_million: None,
+        cache_creation_credits_per_million: None,
+        batch_input_credits_per_million: None,
+        batch_output_credits_per_million: None,
+        effective_from: None,
+        effective_until: None,
+    });
+    catalog
+}
+
+async fn quote(harness: &TestHarness) -> i64 {
+    let response = harness
+        .server
+        .post("/v1/usage/quote")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-router")
+        .json(&json!({
+            "metric": {
+                "type": "llm_tokens",
+                "provider": "test",
+                "model": "priced-model",
+                "input_tokens": 1_000_000,
+                "output_tokens": 0
+            }
+        }))
+        .await;
+    response.assert_status_ok();
+    response.json::<serde_json::Value>()["cost_cents"]
+        .as_i64()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn saved_catalog_is_used_for_pricing_and_diffed() {
+    let harness = TestHarness::new();
+
+    let response = harness
+        .server
+        .get("/v1/pricing")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["version"], "builtin");
+    assert_eq!(body["source"], "builtin");
+    assert!(body["diff"].is_null());
+    // Unknown models fall back to the default $1.00 per 1M, plus markup
+    assert_eq!(quote(&harness).await, 120);
+
+    let response = harness
+        .server
+        .put("/v1/pricing")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&catalog("2026-11-01", 1000))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["version"], "2026-11-01");
+    assert_eq!(body["source"], "store");
+    assert_eq!(body["previous_version"], "builtin");
+    assert_eq!(body["diff"]["added"][0]["model"], "priced-model");
+    assert_eq!(body["diff"]["changed"], json!([]));
+    assert_eq!(quote(&harness).await, 1200);
+
+    // Versions are immutable
+    let response = harness
+        .server
+        .put("/v1/pricing")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&catalog("2026-11-01", 2000))
+        .await;
+    response.assert_status(StatusCode::CONFLICT);
+    assert_eq!(quote(&harness).await, 1200);
+}
+
+#[tokio::test]
+async fn invalid_catalog_is_rejected() {
+    let harness = TestHarness::new();
+
+    let response = harness
+        .server
+        .put("/v1/pricing")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&catalog("2026-11-01", -5))
+        .await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    let mut body = serde_json::to_value(catalog("2026-11-02", 1000)).unwrap();
+    body["cpu_hours_credits"] = json!(6);
+    let response = harness
+        .server
+        .put("/v1/pricing")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&body)
+        .await;
+    assert!(response.status_code().is_client_error());
+
+    let response = harness.server.get("/v1/pricing").await;
+    response.assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn late_usage_is_billed_at_the_price_in_effect_when_it_happened() {
+    let harness = TestHarness::new();
+    let mut account = Account::new(harness.test_user_id);
+    account.balance_cents = 10_000;
+    harness.store.create_account(&account).await.unwrap();
+
+    let cutover: chrono::DateTime<chrono::Utc> = "2026-01-01T00:00:00Z".parse().unwrap();
+    let mut dated = catalog("2026-01-01", 1000);
+    dated.models.last_mut().unwrap().effective_until = Some(cutover);
+    dated.models.push(ModelPrice {
+        effective_from: Some(cutover),
+        effective_until: None,
+        input_credits_per_million: 2000,
+        ..dated.models.last().unwrap().clone()
+    });
+    harness
+        .server
+        .put("/v1/pricing")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&dated)
+        .await
+        .assert_status_ok();
+
+    let report = |event_id: &str, timestamp: Option<&str>| {
+        let mut body = json!({
+            "event_id": event_id,
+            "user_id": harness.test_user_id.to_string(),
+            "metric": {
+                "type": "llm_tokens",
+                "provider": "test",
+                "model": "priced-model",
+                "input_tokens": 1_000_000,
+                "output_tokens": 0
+            }
+        });
+        if let Some(timestamp) = timestamp {
+            body["timestamp"] = json!(timestamp);
+        }
+        harness
+            .server
+            .post("/v1/usage")
+            .add_header("x-api-key", &harness.service_api_key)
+            .add_header("x-service-name", "aura-router")
+            .json(&body)
+    };
+
+    // Reported late, billed at the old price plus markup
+    let response = report("evt_late", Some("2025-12-31T23:59:59Z")).await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["cost_cents"], 1200);
+
+    let tx_id = body["transaction_id"].as_str().unwrap().parse().unwrap();
+    let tx = harness
+        .store
+        .get_transaction(&tx_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(tx.metadata["price_version"]["catalog"], "2026-01-01");
+    assert_eq!(
+        tx.metadata["price_version"]["effective_until"],
+        "2026-01-01T00:00:00Z"
+    );
+    let event = harness
+        .store
+        .get_usage_event("evt_late")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.timestamp, cutover - chrono::Duration::seconds(1));
+
+    let response = report("evt_now", None).await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["cost_cents"], 2400);
+
+    let response = report("evt_future", Some("2099-01-01T00:00:00Z")).await;
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn file_catalog_reloads_on_new_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pricing.json");
+    let write = |catalog: &PricingCatalog| {
+        std::fs::write(&path, serde_json::to_string(catalog).unwrap()).unwrap();
+    };
+    let state = AppState::new(
+        Arc::new(MemoryStore::new()),
+        ServiceConfig {
+            pricing_catalog_path: Some(path.to_string_lossy().into_owned()),
+            ..ServiceConfig::default()
+        },
+    );
+
+    write(&catalog("v1", 1000));
+    assert!(pricing::reload(&state).await.unwrap());
+    assert_eq!(state.pricing().catalog.version, "v1");
+    assert_eq!(state.pricing().source.as_str(), "file");
+    assert!(!pricing::reload(&state).await.unwrap());
+
+    write(&catalog("v2", 1500));
+    assert!(pricing::reload(&state).await.unwrap());
+    let active = state.pricing();
+    assert_eq!(active.catalog.version, "v2");
+    let diff = active.diff().unwrap();
+    assert_eq!(diff.from_version, "v1");
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(diff.changed[0].after.input_credits_per_million, 1500);
+    assert_eq!(
+        active
+            .config
+            .calculate_llm_cost("test", "priced-model", 1_000_000, 0),
+        1500
+    );
+
+    // A changed catalog without a new version is refused
+    write(&catalog("v2", 9000));
+    assert!(matches!(
+        pricing::reload(&state).await,
+        Err(ReloadError::Unversioned(_))
+    ));
+
+    // So is a broken file; the active catalog stays
+    std::fs::write(&path, "{ not json").unwrap();
+    assert!(matches!(
+        pricing::reload(&state).await,
+        Err(ReloadError::Catalog(_))
+    ));
+    assert_eq!(state.pricing().catalog.version, "v2");
+}
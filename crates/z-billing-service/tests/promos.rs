@@ -0,0 +1,210 @@
+//! Promo code integration tests.
+
+mod common;
+
+use common::TestHarness;
+use serde_json::json;
+use z_billing_core::{Account, TransactionType, UserId};
+use z_billing_store::Store;
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+async fn create_promo(harness: &TestHarness, body: serde_json::Value) -> axum_test::TestResponse {
+    harness
+        .server
+        .post("/v1/promos")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&body)
+        .await
+}
+
+async fn redeem(harness: &TestHarness, user_id: UserId, code: &str) -> axum_test::TestResponse {
+    harness
+        .server
+        .post("/v1/credits/redeem")
+        .add_header("authorization", format!("Bearer test-token:{user_id}"))
+        .json(&json!({ "code": code }))
+        .await
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[tokio::test]
+async fn promo_admin_requires_admin_key() {
+    let harness = TestHarness::new();
+
+    let response = harness
+        .server
+        .post("/v1/promos")
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({ "code": "WELCOME", "amount_cents": 500 }))
+        .await;
+    response.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn promo_code_crud() {
+    let harness = TestHarness::new();
+
+    let response = create_promo(
+        &harness,
+        json!({ "code": "welcome-5", "amount_cents": 500, "max_redemptions": 10 }),
+    )
+    .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "WELCOME-5");
+    assert_eq!(body["per_user_limit"], 1);
+    assert_eq!(body["active"], true);
+
+    // Codes are unique regardless of case
+    let response = create_promo(
+        &harness,
+        json!({ "code": "Welcome-5", "amount_cents": 100 }),
+    )
+    .await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+
+    let response = harness
+        .server
+        .put("/v1/promos/welcome-5")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .json(&json!({ "amount_cents": 750, "active": false }))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["amount_cents"], 750);
+    assert_eq!(body["active"], false);
+    assert!(body["max_redemptions"].is_null());
+
+    let response = harness
+        .server
+        .get("/v1/promos")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .await;
+    response.assert_status_ok();
+    assert_eq!(
+        response
+            .json::<serde_json::Value>()
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let response = harness
+        .server
+        .delete("/v1/promos/WELCOME-5")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .await;
+    response.assert_status_ok();
+
+    let response = harness
+        .server
+        .get("/v1/promos/WELCOME-5")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .await;
+    response.assert_status(axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn redeem_promo_credits_bonus() {
+    let harness = TestHarness::new();
+    let user_id = harness.test_user_id;
+    harness
+        .store
+        .put_account(&Account::new(user_id))
+        .await
+        .unwrap();
+
+    create_promo(&harness, json!({ "code": "LAUNCH", "amount_cents": 500 }))
+        .await
+        .assert_status_ok();
+
+    let response = redeem(&harness, user_id, " launch ").await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "LAUNCH");
+    assert_eq!(body["balance_cents"], 500);
+
+    let transaction_id = body["transaction_id"].as_str().unwrap().parse().unwrap();
+    let bonus = harness
+        .store
+        .get_transaction(&transaction_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(bonus.transaction_type, TransactionType::Bonus);
+    assert_eq!(bonus.metadata["promo_code"], "LAUNCH");
+
+    // Second redemption by the same user hits the per-user limit
+    let response = redeem(&harness, user_id, "LAUNCH").await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+
+    let account = harness.store.get_account(&user_id).await.unwrap().unwrap();
+    assert_eq!(account.balance_cents, 500);
+    assert!(harness.store.verify_ledger().await.unwrap().is_consistent());
+}
+
+#[tokio::test]
+async fn redeem_promo_enforces_limits() {
+    let harness = TestHarness::new();
+    let first = UserId::generate();
+    let second = UserId::generate();
+    harness
+        .store
+        .put_account(&Account::new(first))
+        .await
+        .unwrap();
+    harness
+        .store
+        .put_account(&Account::new(second))
+        .await
+        .unwrap();
+
+    create_promo(
+        &harness,
+        json!({ "code": "ONCE", "amount_cents": 200, "max_redemptions": 1 }),
+    )
+    .await
+    .assert_status_ok();
+    create_promo(
+        &harness,
+        json!({ "code": "PROONLY", "amount_cents": 200, "plans": ["pro"] }),
+    )
+    .await
+    .assert_status_ok();
+    create_promo(
+        &harness,
+        json!({
+            "code": "OVER",
+            "amount_cents": 200,
+            "valid_until": "2020-01-01T00:00:00Z"
+        }),
+    )
+    .await
+    .assert_status_ok();
+
+    redeem(&harness, first, "ONCE").await.assert_status_ok();
+    redeem(&harness, second, "ONCE")
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+    redeem(&harness, second, "PROONLY")
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+    redeem(&harness, second, "OVER")
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+    redeem(&harness, second, "MISSING")
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
+
+    let promo = harness.store.get_promo_code("ONCE").await.unwrap().unwrap();
+    assert_eq!(promo.redemption_count, 1);
+    let account = harness.store.get_account(&second).await.unwrap().unwrap();
+    assert_eq!(account.balance_cents, 0);
+}
@@ -12,11 +12,11 @@ use std::sync::Arc;
 
 use axum_test::TestServer;
 use serde_json::json;
-use tempfile::TempDir;
 
 use z_billing_core::UserId;
+use z_billing_service::stripe::CheckoutPurpose;
 use z_billing_service::{create_router, AppState, ServiceConfig, StripeClient};
-use z_billing_store::RocksStore;
+use z_billing_store::MemoryStore;
 
 /// Test configuration that loads real Stripe credentials.
 struct StripeTestConfig {
@@ -72,18 +72,17 @@ impl StripeTestConfig {
 }
 
 /// Create a test harness with real Stripe integration.
-fn create_stripe_test_harness() -> Option<(TestServer, TempDir, UserId, String)> {
+fn create_stripe_test_harness() -> Option<(TestServer, UserId, String)> {
     let config = StripeTestConfig::load()?;
 
-    let temp_dir = TempDir::new().expect("Failed to create temp directory");
-    let store = RocksStore::open(temp_dir.path()).expect("Failed to open store");
+    let store = MemoryStore::new();
 
     let service_api_key = "test-service-key".to_string();
 
     let app_config = ServiceConfig {
         listen_addr: "127.0.0.1:0".into(),
         database_url: None,
-        data_dir: temp_dir.path().to_string_lossy().to_string(),
+        data_dir: String::new(),
         auth_base_url: "http://localhost".into(),
         auth_audience: "z-billing".into(),
         auth_cookie_secret: None,
@@ -100,10 +99,13 @@ fn create_stripe_test_harness() -> Option<(TestServer, TempDir, UserId, String)>
         max_body_bytes: 1024 * 1024,
         request_timeout_seconds: 30,
         pricing: z_billing_core::PricingConfig::default(),
+        pricing_catalog_path: None,
+        pricing_reload_seconds: 60,
         zos_api_url: None,
         zos_api_internal_token: None,
         mixpanel_token: None,
         anthropic_admin_api_key: None,
+        reservation_ttl_seconds: 300,
     };
 
     let state = AppState::new(Arc::new(store), app_config);
@@ -111,7 +113,7 @@ fn create_stripe_test_harness() -> Option<(TestServer, TempDir, UserId, String)>
     let server = TestServer::new(router).expect("Failed to create test server");
     let test_user_id = UserId::generate();
 
-    Some((server, temp_dir, test_user_id, service_api_key))
+    Some((server, test_user_id, service_api_key))
 }
 
 /// Get auth header for a user.
@@ -168,6 +170,7 @@ async fn test_stripe_create_checkout_session() {
             &user_id,
             1000, // $10.00
             1000, // 1000 credits
+            CheckoutPurpose::Credits,
             "http://localhost:3000/billing/success?session_id={CHECKOUT_SESSION_ID}",
             "http://localhost:3000/billing/cancel",
         )
@@ -306,7 +309,7 @@ fn compute_test_signature(secret: &str, message: &str) -> String {
 #[tokio::test]
 #[ignore = "requires Stripe API credentials"]
 async fn test_full_account_creation_with_stripe() {
-    let (server, _temp_dir, user_id, _service_key) = match create_stripe_test_harness() {
+    let (server, user_id, _service_key) = match create_stripe_test_harness() {
         Some(h) => h,
         None => {
             println!("Skipping test - Stripe credentials not found");
@@ -341,7 +344,7 @@ async fn test_full_account_creation_with_stripe() {
 #[tokio::test]
 #[ignore = "requires Stripe API credentials"]
 async fn test_full_purchase_credits_flow() {
-    let (server, _temp_dir, user_id, _service_key) = match create_stripe_test_harness() {
+    let (server, user_id, _service_key) = match create_stripe_test_harness() {
         Some(h) => h,
         None => {
             println!("Skipping test - Stripe credentials not found");
@@ -408,7 +411,7 @@ async fn test_full_purchase_credits_flow() {
 #[tokio::test]
 #[ignore = "requires Stripe API credentials"]
 async fn test_full_payment_flow() {
-    let (server, _temp_dir, user_id, _service_key) = match create_stripe_test_harness() {
+    let (server, user_id, _service_key) = match create_stripe_test_harness() {
         Some(h) => h,
         None => {
             println!("Skipping test - Stripe credentials not found");
@@ -677,6 +680,7 @@ async fn manual_e2e_stripe_test() {
             &user_id,
             2500, // $25.00
             2500, // 2500 credits
+            CheckoutPurpose::Credits,
             "http://localhost:3000/billing/success",
             "http://localhost:3000/billing/cancel",
         )
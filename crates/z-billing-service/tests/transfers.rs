@@ -0,0 +1,254 @@
+//! Credit transfer integration tests.
+
+mod common;
+
+use common::TestHarness;
+use serde_json::json;
+use z_billing_core::{Account, CreditTransaction, UserId};
+use z_billing_store::Store;
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Create accounts for the test user (with `purchased_cents` bought and
+/// `bonus_cents` granted) and a recipient. Returns the recipient's ID.
+async fn setup_accounts(harness: &TestHarness, purchased_cents: i64, bonus_cents: i64) -> UserId {
+    let user_id = harness.test_user_id;
+    let recipient = UserId::generate();
+    harness
+        .store
+        .put_account(&Account::new(user_id))
+        .await
+        .unwrap();
+    harness
+        .store
+        .put_account(&Account::new(recipient))
+        .await
+        .unwrap();
+
+    let purchase =
+        CreditTransaction::purchase(user_id, purchased_cents, purchased_cents, "Purchase".into());
+    harness
+        .store
+        .add_credits(&user_id, purchased_cents, &purchase, &[])
+        .await
+        .unwrap();
+    let bonus = CreditTransaction::bonus(
+        user_id,
+        bonus_cents,
+        purchased_cents + bonus_cents,
+        "Bonus".into(),
+    );
+    harness
+        .store
+        .add_credits(&user_id, bonus_cents, &bonus, &[])
+        .await
+        .unwrap();
+
+    recipient
+}
+
+async fn transfer(harness: &TestHarness, body: serde_json::Value) -> axum_test::TestResponse {
+    harness
+        .server
+        .post("/v1/credits/transfer")
+        .add_header("authorization", harness.user_auth_header())
+        .json(&body)
+        .await
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[tokio::test]
+async fn transfer_requires_confirmation() {
+    let harness = TestHarness::new();
+    let recipient = setup_accounts(&harness, 1000, 500).await;
+
+    let response = transfer(
+        &harness,
+        json!({
+            "to_user_id": recipient.to_string(),
+            "amount_cents": 400,
+            "idempotency_key": "preview-1"
+        }),
+    )
+    .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["completed"], false);
+    assert_eq!(body["transferable_cents"], 1000);
+    assert_eq!(body["balance_cents"], 1500);
+
+    // Nothing moved
+    let account = harness
+        .store
+        .get_account(&recipient)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.balance_cents, 0);
+}
+
+#[tokio::test]
+async fn confirmed_transfer_is_idempotent() {
+    let harness = TestHarness::new();
+    let recipient = setup_accounts(&harness, 1000, 500).await;
+    let request = json!({
+        "to_user_id": recipient.to_string(),
+        "amount_cents": 400,
+        "idempotency_key": "gift-1",
+        "confirm": true
+    });
+
+    let response = transfer(&harness, request.clone()).await;
+    response.assert_status_ok();
+    let first: serde_json::Value = response.json();
+    assert_eq!(first["completed"], true);
+    assert_eq!(first["balance_cents"], 1100);
+    assert_eq!(first["transferable_cents"], 600);
+
+    let response = transfer(&harness, request).await;
+    response.assert_status_ok();
+    let retry: serde_json::Value = response.json();
+    assert_eq!(retry["out_transaction_id"], first["out_transaction_id"]);
+
+    let sender = harness
+        .store
+        .get_account(&harness.test_user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(sender.balance_cents, 1100);
+    let account = harness
+        .store
+        .get_account(&recipient)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.balance_cents, 400);
+
+    // Reusing the key for a different transfer is rejected
+    let response = transfer(
+        &harness,
+        json!({
+            "to_user_id": recipient.to_string(),
+            "amount_cents": 200,
+            "idempotency_key": "gift-1",
+            "confirm": true
+        }),
+    )
+    .await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+
+    assert!(harness.store.verify_ledger().await.unwrap().is_consistent());
+}
+
+#[tokio::test]
+async fn granted_credits_cannot_be_transferred() {
+    let harness = TestHarness::new();
+    let recipient = setup_accounts(&harness, 200, 5000).await;
+
+    let response = transfer(
+        &harness,
+        json!({
+            "to_user_id": recipient.to_string(),
+            "amount_cents": 1000,
+            "idempotency_key": "too-much",
+            "confirm": true
+        }),
+    )
+    .await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+
+    let response = transfer(
+        &harness,
+        json!({
+            "to_user_id": harness.test_user_id.to_string(),
+            "amount_cents": 100,
+            "idempotency_key": "self",
+            "confirm": true
+        }),
+    )
+    .await;
+    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+
+    let sender = harness
+        .store
+        .get_account(&harness.test_user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(sender.balance_cents, 5200);
+}
+
+#[tokio::test]
+async fn transfer_to_org_requires_membership() {
+    let harness = TestHarness::new();
+    setup_accounts(&harness, 1000, 0).await;
+
+    let response = harness
+        .server
+        .post("/v1/orgs")
+        .add_header(
+            "authorization",
+            format!("Bearer test-token:{}", UserId::generate()),
+        )
+        .json(&json!({ "name": "Other" }))
+        .await;
+    response.assert_status_ok();
+    let other_org = response.json::<serde_json::Value>()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let response = transfer(
+        &harness,
+        json!({
+            "to_org_id": other_org,
+            "amount_cents": 300,
+            "idempotency_key": "outsider",
+            "confirm": true
+        }),
+    )
+    .await;
+    response.assert_status(axum::http::StatusCode::FORBIDDEN);
+
+    let response = harness
+        .server
+        .post("/v1/orgs")
+        .add_header("authorization", harness.user_auth_header())
+        .json(&json!({ "name": "Mine" }))
+        .await;
+    response.assert_status_ok();
+    let own_org = response.json::<serde_json::Value>()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let response = transfer(
+        &harness,
+        json!({
+            "to_org_id": own_org,
+            "amount_cents": 300,
+            "idempotency_key": "team",
+            "confirm": true
+        }),
+    )
+    .await;
+    response.assert_status_ok();
+    assert_eq!(
+        response.json::<serde_json::Value>()["to"],
+        format!("org:{own_org}")
+    );
+
+    let org = harness
+        .store
+        .get_organization(&own_org.parse().unwrap())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(org.balance_cents, 300);
+}
@@ -4,7 +4,9 @@ mod common;
 
 use common::TestHarness;
 use serde_json::json;
-use z_billing_core::{LlmProvider, Plan, Subscription, SubscriptionStatus, UsageMetric};
+use z_billing_core::{
+    ImageResolution, LlmProvider, Plan, Subscription, SubscriptionStatus, UsageMetric, UserId,
+};
 use z_billing_store::Store;
 
 // ============================================================================
@@ -37,10 +39,11 @@ async fn create_funded_account(harness: &TestHarness, balance_cents: i64) {
     }
 }
 
-fn set_plan(harness: &TestHarness, plan: Plan) {
+async fn set_plan(harness: &TestHarness, plan: Plan) {
     let mut account = harness
         .store
         .get_account(&harness.test_user_id)
+        .await
         .expect("Failed to load account")
         .expect("Account should exist");
     let now = chrono::Utc::now();
@@ -56,6 +59,7 @@ fn set_plan(harness: &TestHarness, plan: Plan) {
     harness
         .store
         .put_account(&account)
+        .await
         .expect("Failed to update account plan");
 }
 
@@ -127,7 +131,7 @@ async fn report_llm_usage_applies_standard_markup_for_zero_pro_entitlement() {
 async fn report_llm_usage_keeps_twenty_percent_markup_without_zero_pro_entitlement() {
     let harness = TestHarness::new();
     create_funded_account(&harness, 10000).await;
-    set_plan(&harness, Plan::Pro);
+    set_plan(&harness, Plan::Pro).await;
 
     let response = harness
         .server
@@ -157,7 +161,7 @@ async fn report_llm_usage_keeps_twenty_percent_markup_without_zero_pro_entitleme
 async fn report_llm_usage_reads_zero_pro_from_metadata_alias() {
     let harness = TestHarness::new();
     create_funded_account(&harness, 10000).await;
-    set_plan(&harness, Plan::Standard);
+    set_plan(&harness, Plan::Standard).await;
 
     let response = harness
         .server
@@ -219,6 +223,7 @@ async fn report_xai_llm_usage_uses_grok_rates_and_provider() {
     let event = harness
         .store
         .get_usage_event("evt_test_xai_grok")
+        .await
         .expect("load usage event")
         .expect("usage event recorded");
     match event.metric {
@@ -232,6 +237,165 @@ async fn report_xai_llm_usage_uses_grok_rates_and_provider() {
     }
 }
 
+#[tokio::test]
+async fn report_media_and_tool_usage_uses_unit_prices() {
+    let harness = TestHarness::new();
+    create_funded_account(&harness, 10000).await;
+
+    let mut balance = 10000;
+    for (event_id, metric, cost) in [
+        (
+            "evt_test_images",
+            json!({
+                "type": "image_generation",
+                "provider": "openai",
+                "model": "dall-e-3",
+                "count": 3,
+                "resolution": "high"
+            }),
+            24,
+        ),
+        (
+            "evt_test_audio",
+            json!({
+                "type": "audio_transcription",
+                "provider": "openai",
+                "model": "whisper-1",
+                "seconds": 600.0
+            }),
+            6,
+        ),
+        (
+            "evt_test_speech",
+            json!({
+                "type": "text_to_speech",
+                "provider": "openai",
+                "model": "tts-1",
+                "characters": 200_000
+            }),
+            300,
+        ),
+        (
+            "evt_test_search",
+            json!({ "type": "web_search", "provider": "anthropic", "queries": 20 }),
+            20,
+        ),
+        (
+            "evt_test_tools",
+            json!({ "type": "tool_calls", "tool": "code_execution", "count": 5 }),
+            1,
+        ),
+    ] {
+        let response = harness
+            .server
+            .post("/v1/usage")
+            .add_header("x-api-key", &harness.service_api_key)
+            .add_header("x-service-name", "aura-router")
+            .json(&json!({
+                "event_id": event_id,
+                "user_id": harness.test_user_id.to_string(),
+                "metric": metric
+            }))
+            .await;
+
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        balance -= cost;
+        assert_eq!(body["cost_cents"], cost, "{event_id}");
+        assert_eq!(body["balance_cents"], balance, "{event_id}");
+    }
+
+    let event = harness
+        .store
+        .get_usage_event("evt_test_images")
+        .await
+        .expect("load usage event")
+        .expect("usage event recorded");
+    assert_eq!(
+        event.metric,
+        UsageMetric::ImageGeneration {
+            provider: LlmProvider::OpenAi,
+            model: "dall-e-3".into(),
+            resolution: ImageResolution::High,
+        }
+    );
+    assert!((event.quantity - 3.0).abs() < f64::EPSILON);
+}
+
+#[tokio::test]
+async fn storage_snapshots_charge_gb_hours_between_readings() {
+    let harness = TestHarness::new();
+    create_funded_account(&harness, 10000).await;
+    let start = chrono::Utc::now() - chrono::Duration::hours(3);
+
+    let snapshot = |minutes: i64| {
+        harness
+            .server
+            .post("/v1/usage/storage")
+            .add_header("x-api-key", &harness.service_api_key)
+            .add_header("x-service-name", "aura-storage")
+            .json(&json!({
+                "user_id": harness.test_user_id.to_string(),
+                "resource_id": "bucket-1",
+                "bytes": 500_000_000_000_u64,
+                "timestamp": (start + chrono::Duration::minutes(minutes)).to_rfc3339(),
+            }))
+    };
+
+    // The first reading starts the meter
+    let response = snapshot(0).await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["cost_cents"], 0);
+    assert!(body["transaction_id"].is_null());
+
+    // 500 GB for an hour at 0.004 credits per GB-hour
+    let response = snapshot(60).await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["cost_cents"], 2);
+    assert_eq!(body["balance_cents"], 9998);
+
+    // A repeated reading is stale and charges nothing
+    let body: serde_json::Value = snapshot(60).await.json();
+    assert_eq!(body["cost_cents"], 0);
+
+    // Half a credit is carried to the next reading
+    let body: serde_json::Value = snapshot(75).await.json();
+    assert_eq!(body["cost_cents"], 0);
+    assert!((body["unbilled_gb_hours"].as_f64().unwrap() - 125.0).abs() < 1e-6);
+
+    let event = harness
+        .store
+        .get_usage_event(&format!(
+            "storage:{}:bucket-1:{}",
+            harness.test_user_id,
+            start.timestamp_millis()
+        ))
+        .await
+        .expect("load usage event")
+        .expect("usage event recorded");
+    assert!(matches!(event.metric, UsageMetric::Storage { .. }));
+    assert!((event.quantity - 500.0).abs() < 1e-6);
+
+    // GB-hours metered by the service are priced directly
+    let response = harness
+        .server
+        .post("/v1/usage")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-storage")
+        .json(&json!({
+            "event_id": "evt_test_storage",
+            "user_id": harness.test_user_id.to_string(),
+            "metric": { "type": "storage", "gb_hours": 1000.0 }
+        }))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["cost_cents"], 4);
+    assert_eq!(body["balance_cents"], 9994);
+}
+
 #[tokio::test]
 async fn report_moonshot_llm_usage_uses_kimi_k3_rates_and_provider() {
     let harness = TestHarness::new();
@@ -264,6 +428,7 @@ async fn report_moonshot_llm_usage_uses_kimi_k3_rates_and_provider() {
     let event = harness
         .store
         .get_usage_event("evt_test_moonshot_kimi_k3")
+        .await
         .expect("load usage event")
         .expect("usage event recorded");
     match event.metric {
@@ -579,6 +744,7 @@ async fn quote_usage_calculates_llm_cost_without_account_mutation() {
     assert!(harness
         .store
         .get_account(&harness.test_user_id)
+        .await
         .unwrap()
         .is_none());
 }
@@ -610,6 +776,346 @@ async fn quote_usage_calculates_cheaper_xai_grok_build_cost() {
     assert!(harness
         .store
         .get_account(&harness.test_user_id)
+        .await
         .unwrap()
         .is_none());
 }
+
+#[tokio::test]
+async fn quote_usage_prices_cached_and_batch_tokens() {
+    let harness = TestHarness::new();
+    let quote = |metric: serde_json::Value| {
+        harness
+            .server
+            .post("/v1/usage/quote")
+            .add_header("x-api-key", &harness.service_api_key)
+            .add_header("x-service-name", "aura-router")
+            .json(&json!({ "metric": metric }))
+    };
+
+    // $3/M input plus $0.30/M cache reads, with the 20% markup
+    let response = quote(json!({
+        "type": "llm_tokens",
+        "provider": "anthropic",
+        "model": "claude-sonnet-4-6",
+        "input_tokens": 1_000_000,
+        "output_tokens": 0,
+        "cache_read_input_tokens": 1_000_000
+    }))
+    .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["cost_cents"], 396);
+
+    // Batch API at half of the $3/M input and $15/M output rates
+    let response = quote(json!({
+        "type": "llm_tokens",
+        "provider": "anthropic",
+        "model": "claude-sonnet-4-6",
+        "input_tokens": 1_000_000,
+        "output_tokens": 1_000_000,
+        "batch": true
+    }))
+    .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["cost_cents"], 1_080);
+}
+
+// ============================================================================
+// Reservations
+// ============================================================================
+
+async fn reserve(harness: &TestHarness, amount_cents: i64) -> serde_json::Value {
+    let response = harness
+        .server
+        .post("/v1/usage/reserve")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-router")
+        .json(&json!({
+            "user_id": harness.test_user_id.to_string(),
+            "amount_cents": amount_cents
+        }))
+        .await;
+
+    response.assert_status_ok();
+    response.json()
+}
+
+#[tokio::test]
+async fn reserve_holds_back_available_balance() {
+    let harness = TestHarness::new();
+    create_funded_account(&harness, 1000).await;
+
+    let body = reserve(&harness, 800).await;
+    assert_eq!(body["reserved_cents"], 800);
+    assert_eq!(body["available_cents"], 200);
+
+    // A second concurrent hold cannot overspend
+    let response = harness
+        .server
+        .post("/v1/usage/reserve")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-router")
+        .json(&json!({
+            "user_id": harness.test_user_id.to_string(),
+            "amount_cents": 500
+        }))
+        .await;
+    response.assert_status(axum::http::StatusCode::PAYMENT_REQUIRED);
+
+    // Balance checks see the hold
+    let response = harness
+        .server
+        .post("/v1/usage/check")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-router")
+        .json(&json!({
+            "user_id": harness.test_user_id.to_string(),
+            "required_cents": 500
+        }))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["sufficient"], false);
+    assert_eq!(body["balance_cents"], 1000);
+    assert_eq!(body["reserved_cents"], 800);
+}
+
+#[tokio::test]
+async fn reserve_uses_model_minimum_reserve() {
+    let harness = TestHarness::new();
+    create_funded_account(&harness, 1000).await;
+
+    let response = harness
+        .server
+        .post("/v1/usage/reserve")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-router")
+        .json(&json!({
+            "user_id": harness.test_user_id.to_string(),
+            "provider": "openai",
+            "model": "aura-gpt-5-4"
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["reserved_cents"], 3);
+}
+
+#[tokio::test]
+async fn settle_converts_hold_into_usage_debit() {
+    let harness = TestHarness::new();
+    create_funded_account(&harness, 1000).await;
+    let hold = reserve(&harness, 100).await;
+
+    let response = harness
+        .server
+        .post("/v1/usage/settle")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-router")
+        .json(&json!({
+            "reservation_id": hold["reservation_id"],
+            "event_id": "evt_settle_001",
+            "user_id": harness.test_user_id.to_string(),
+            "metric": {
+                "type": "api_calls",
+                "endpoint": "/stream",
+                "count": 1
+            },
+            "cost_cents": 40
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["cost_cents"], 40);
+    assert_eq!(body["balance_cents"], 960);
+    assert_eq!(
+        harness
+            .store
+            .reserved_cents(&harness.test_user_id)
+            .await
+            .unwrap(),
+        0
+    );
+
+    // A settled hold cannot be released
+    let response = harness
+        .server
+        .post("/v1/usage/release")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-router")
+        .json(&json!({ "reservation_id": hold["reservation_id"] }))
+        .await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn settle_compares_user_ids_not_strings() {
+    let harness = TestHarness::new();
+    create_funded_account(&harness, 1000).await;
+    let hold = reserve(&harness, 100).await;
+    let settle = |event_id: &'static str, user_id: String| {
+        harness
+            .server
+            .post("/v1/usage/settle")
+            .add_header("x-api-key", &harness.service_api_key)
+            .add_header("x-service-name", "aura-router")
+            .json(&json!({
+                "reservation_id": hold["reservation_id"],
+                "event_id": event_id,
+                "user_id": user_id,
+                "metric": {
+                    "type": "api_calls",
+                    "endpoint": "/stream",
+                    "count": 1
+                },
+                "cost_cents": 40
+            }))
+    };
+
+    let response = settle("evt_settle_bad_id", "not-a-user".into()).await;
+    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+    let response = settle("evt_settle_other", UserId::generate().to_string()).await;
+    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+
+    // The same ID in another spelling still owns the hold
+    let response = settle(
+        "evt_settle_upper",
+        harness.test_user_id.to_string().to_uppercase(),
+    )
+    .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["balance_cents"], 960);
+}
+
+#[tokio::test]
+async fn release_frees_hold_without_charging() {
+    let harness = TestHarness::new();
+    create_funded_account(&harness, 1000).await;
+    let hold = reserve(&harness, 900).await;
+
+    let response = harness
+        .server
+        .post("/v1/usage/release")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-router")
+        .json(&json!({ "reservation_id": hold["reservation_id"] }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"], "released");
+
+    let account = harness
+        .store
+        .get_account(&harness.test_user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.balance_cents, 1000);
+    assert_eq!(
+        harness
+            .store
+            .reserved_cents(&harness.test_user_id)
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+// ============================================================================
+// Reversals
+// ============================================================================
+
+async fn reverse(
+    harness: &TestHarness,
+    event_id: &str,
+    body: serde_json::Value,
+) -> axum_test::TestResponse {
+    harness
+        .server
+        .post(&format!("/v1/usage/{event_id}/reverse"))
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-router")
+        .json(&body)
+        .await
+}
+
+#[tokio::test]
+async fn reverse_usage_refunds_partial_then_remaining_charge() {
+    let harness = TestHarness::new();
+    create_funded_account(&harness, 1000).await;
+
+    let response = harness
+        .server
+        .post("/v1/usage")
+        .add_header("x-api-key", &harness.service_api_key)
+        .add_header("x-service-name", "aura-router")
+        .json(&json!({
+            "event_id": "evt_reverse_001",
+            "user_id": harness.test_user_id.to_string(),
+            "metric": { "type": "api_calls", "endpoint": "/search", "count": 1 },
+            "cost_cents": 300
+        }))
+        .await;
+    response.assert_status_ok();
+    let charge_tx = response.json::<serde_json::Value>()["transaction_id"].clone();
+
+    let body = json!({ "reversal_id": "partial-1", "amount_cents": 100, "reason": "Timeout" });
+    let response = reverse(&harness, "evt_reverse_001", body.clone()).await;
+    response.assert_status_ok();
+    let first: serde_json::Value = response.json();
+    assert_eq!(first["reversed_cents"], 100);
+    assert_eq!(first["balance_cents"], 800);
+
+    // Replaying the same reversal returns the original result
+    let response = reverse(&harness, "evt_reverse_001", body).await;
+    response.assert_status_ok();
+    let replay: serde_json::Value = response.json();
+    assert_eq!(replay["transaction_id"], first["transaction_id"]);
+    assert_eq!(replay["total_reversed_cents"], 100);
+
+    // Partial reversals must not exceed what is left of the charge
+    let response = reverse(
+        &harness,
+        "evt_reverse_001",
+        json!({ "reversal_id": "partial-2", "amount_cents": 250 }),
+    )
+    .await;
+    response.assert_status(axum::http::StatusCode::CONFLICT);
+
+    // A full reversal refunds the remainder
+    let response = reverse(&harness, "evt_reverse_001", json!({})).await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["reversal_id"], "full");
+    assert_eq!(body["reversed_cents"], 200);
+    assert_eq!(body["total_reversed_cents"], 300);
+    assert_eq!(body["balance_cents"], 1000);
+
+    let tx_id = body["transaction_id"].as_str().unwrap().parse().unwrap();
+    let tx = harness
+        .store
+        .get_transaction(&tx_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        tx.transaction_type,
+        z_billing_core::TransactionType::Reversal
+    );
+    assert_eq!(tx.metadata["original_transaction_id"], charge_tx);
+}
+
+#[tokio::test]
+async fn reverse_usage_requires_reversal_id_for_partial_amount() {
+    let harness = TestHarness::new();
+
+    let response = reverse(&harness, "evt_missing", json!({ "amount_cents": 10 })).await;
+    response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+
+    let response = reverse(&harness, "evt_missing", json!({})).await;
+    response.assert_status(axum::http::StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,203 @@
+//! Usage summary integration tests.
+
+mod common;
+
+use common::TestHarness;
+use serde_json::json;
+use z_billing_core::{
+    Account, AgentId, CreditTransaction, LlmProvider, TokenDirection, UsageEvent, UserId,
+};
+use z_billing_store::Store;
+
+/// Record an LLM usage event for `user_id` straight into the store.
+async fn record_llm_usage(
+    harness: &TestHarness,
+    user_id: UserId,
+    agent_id: Option<AgentId>,
+    model: &str,
+    direction: TokenDirection,
+    tokens: u64,
+    cost_cents: i64,
+) {
+    let event = UsageEvent::llm(
+        format!("evt_{}", uuid::Uuid::new_v4()),
+        user_id,
+        agent_id,
+        LlmProvider::Anthropic,
+        model.to_string(),
+        direction,
+        tokens,
+        cost_cents,
+    );
+    let tx = CreditTransaction::usage(user_id, cost_cents, 0, "usage".into(), json!({}));
+    harness.store.process_usage(&event, &tx, &[]).await.unwrap();
+}
+
+async fn funded_account(harness: &TestHarness, user_id: UserId) {
+    let mut account = Account::new(user_id);
+    account.balance_cents = 10_000;
+    harness.store.create_account(&account).await.unwrap();
+}
+
+#[tokio::test]
+async fn usage_summary_groups_by_maker_and_model() {
+    let harness = TestHarness::new();
+    let user_id = harness.test_user_id;
+    funded_account(&harness, user_id).await;
+    let agent_id = AgentId::generate();
+
+    record_llm_usage(
+        &harness,
+        user_id,
+        Some(agent_id),
+        "claude-opus-4",
+        TokenDirection::Input,
+        1000,
+        30,
+    )
+    .await;
+    record_llm_usage(
+        &harness,
+        user_id,
+        Some(agent_id),
+        "claude-opus-4",
+        TokenDirection::Output,
+        200,
+        20,
+    )
+    .await;
+    record_llm_usage(
+        &harness,
+        user_id,
+        None,
+        "aura-claude-sonnet-4",
+        TokenDirection::Input,
+        400,
+        4,
+    )
+    .await;
+    record_llm_usage(
+        &harness,
+        user_id,
+        None,
+        "gpt-5",
+        TokenDirection::Input,
+        100,
+        1,
+    )
+    .await;
+
+    // Someone else's usage never shows up in the caller's summary
+    let other = UserId::generate();
+    funded_account(&harness, other).await;
+    record_llm_usage(
+        &harness,
+        other,
+        None,
+        "claude-opus-4",
+        TokenDirection::Input,
+        9000,
+        90,
+    )
+    .await;
+
+    let response = harness
+        .server
+        .get("/v1/usage/summary?group_by=maker")
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["group_by"], json!(["maker"]));
+    assert_eq!(body["total_cost_cents"], 55);
+    let rows = body["rows"].as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["maker"], "Anthropic");
+    assert_eq!(rows[0]["input_tokens"], 1400);
+    assert_eq!(rows[0]["output_tokens"], 200);
+    assert_eq!(rows[0]["cost_cents"], 54);
+    assert_eq!(rows[0]["event_count"], 3);
+    assert!(rows[0].get("model").is_none());
+    assert_eq!(rows[1]["maker"], "OpenAI");
+
+    let response = harness
+        .server
+        .get("/v1/usage/summary?interval=week&group_by=model,agent")
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["interval"], "week");
+    let rows = body["rows"].as_array().unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0]["model"], "claude-opus-4");
+    assert_eq!(rows[0]["agent_id"], agent_id.to_string());
+    assert_eq!(rows[0]["cost_cents"], 50);
+    assert!(rows[0]["period_start"].is_string());
+}
+
+#[tokio::test]
+async fn admin_usage_summary_covers_all_users() {
+    let harness = TestHarness::new();
+    let user_id = harness.test_user_id;
+    let other = UserId::generate();
+    for id in [user_id, other] {
+        funded_account(&harness, id).await;
+        record_llm_usage(
+            &harness,
+            id,
+            None,
+            "claude-opus-4",
+            TokenDirection::Input,
+            1000,
+            30,
+        )
+        .await;
+    }
+
+    let response = harness
+        .server
+        .get("/v1/usage/summary/all?group_by=source")
+        .add_header("x-admin-key", harness.admin_key_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["total_cost_cents"], 60);
+    assert_eq!(body["rows"][0]["source"], "aura_runtime");
+    assert_eq!(body["rows"][0]["input_tokens"], 2000);
+
+    let response = harness
+        .server
+        .get(&format!("/v1/usage/summary/all?user_id={other}"))
+        .add_header("x-admin-key", harness.admin_key_header())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["total_cost_cents"], 30);
+
+    // Users cannot see everyone's usage
+    let response = harness
+        .server
+        .get("/v1/usage/summary/all")
+        .add_header("authorization", harness.user_auth_header())
+        .await;
+    response.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn usage_summary_rejects_bad_parameters() {
+    let harness = TestHarness::new();
+
+    for query in [
+        "interval=month",
+        "group_by=model,colour",
+        "since=2025-02-01T00:00:00Z&until=2025-01-01T00:00:00Z",
+    ] {
+        let response = harness
+            .server
+            .get(&format!("/v1/usage/summary?{query}"))
+            .add_header("authorization", harness.user_auth_header())
+            .await;
+        response.assert_status(axum::http::StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,44 @@
+-- Billing accounts
+CREATE TABLE accounts (
+    user_id TEXT PRIMARY KEY,
+    balance_cents INTEGER NOT NULL DEFAULT 0,
+    lifetime_purchased_cents INTEGER NOT NULL DEFAULT 0,
+    lifetime_granted_cents INTEGER NOT NULL DEFAULT 0,
+    lifetime_used_cents INTEGER NOT NULL DEFAULT 0,
+    subscription TEXT,
+    auto_refill TEXT,
+    lago_customer_id TEXT,
+    stripe_customer_id TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Credit transactions (ULID-ordered)
+CREATE TABLE credit_transactions (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES accounts(user_id),
+    amount_cents INTEGER NOT NULL,
+    transaction_type TEXT NOT NULL,
+    balance_after_cents INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    metadata TEXT NOT NULL DEFAULT '{}',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_credit_transactions_user_id ON credit_transactions(user_id, created_at DESC);
+
+-- Usage events (for idempotency)
+CREATE TABLE usage_events (
+    event_id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    agent_id TEXT,
+    source TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    quantity REAL NOT NULL,
+    cost_cents INTEGER NOT NULL,
+    event_timestamp TEXT NOT NULL,
+    metadata TEXT NOT NULL DEFAULT '{}',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_usage_events_user_id ON usage_events(user_id, event_timestamp DESC);
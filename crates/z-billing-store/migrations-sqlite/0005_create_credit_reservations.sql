@@ -0,0 +1,19 @@
+-- Credit reservations (holds) for in-flight usage such as streamed LLM responses.
+-- Active, unexpired holds are subtracted from the balance when checking
+-- whether new usage or new holds fit.
+
+CREATE TABLE credit_reservations (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES accounts(user_id),
+    amount_cents INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'active',
+    transaction_id TEXT,
+    metadata TEXT NOT NULL DEFAULT '{}',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_credit_reservations_active
+    ON credit_reservations(user_id, expires_at)
+    WHERE status = 'active';
@@ -0,0 +1,31 @@
+-- Organizations own a shared credit pool. Members spend from it, optionally
+-- limited by a monthly cap, and each transaction records the acting member
+-- in user_id alongside the org_id.
+
+CREATE TABLE organizations (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    balance_cents INTEGER NOT NULL DEFAULT 0,
+    lifetime_purchased_cents INTEGER NOT NULL DEFAULT 0,
+    lifetime_used_cents INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE org_memberships (
+    org_id TEXT NOT NULL REFERENCES organizations(id),
+    user_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    monthly_spend_cap_cents INTEGER,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (org_id, user_id)
+);
+
+CREATE INDEX idx_org_memberships_user_id ON org_memberships(user_id);
+
+ALTER TABLE credit_transactions ADD COLUMN org_id TEXT REFERENCES organizations(id);
+
+CREATE INDEX idx_credit_transactions_org_member
+    ON credit_transactions(org_id, user_id, created_at)
+    WHERE org_id IS NOT NULL;
@@ -0,0 +1,25 @@
+-- Per-agent spending limits and the running totals they are checked against.
+-- Totals are kept in rolling UTC day/month windows: a window whose start is
+-- in the past counts as zero and is reset on the next charge.
+
+CREATE TABLE agent_budgets (
+    user_id TEXT NOT NULL REFERENCES accounts(user_id),
+    agent_id TEXT NOT NULL,
+    period TEXT NOT NULL,
+    limit_cents INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, agent_id, period)
+);
+
+CREATE TABLE agent_spend (
+    user_id TEXT NOT NULL REFERENCES accounts(user_id),
+    agent_id TEXT NOT NULL,
+    day_start TEXT NOT NULL,
+    day_cents INTEGER NOT NULL DEFAULT 0,
+    month_start TEXT NOT NULL,
+    month_cents INTEGER NOT NULL DEFAULT 0,
+    lifetime_cents INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, agent_id)
+);
@@ -0,0 +1,13 @@
+-- Refunds of usage charges. Each usage event can be reversed in one or more
+-- parts; the reversal_id makes retries of the same part idempotent.
+
+ALTER TABLE usage_events ADD COLUMN transaction_id TEXT;
+
+CREATE TABLE usage_reversals (
+    event_id TEXT NOT NULL REFERENCES usage_events(event_id),
+    reversal_id TEXT NOT NULL,
+    amount_cents INTEGER NOT NULL CHECK (amount_cents > 0),
+    transaction_id TEXT NOT NULL REFERENCES credit_transactions(id),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (event_id, reversal_id)
+);
@@ -0,0 +1,17 @@
+-- Double-entry ledger. Every balance movement posts entries that sum to zero:
+-- one against the wallet ('user:<uuid>' or 'org:<uuid>') and one against a
+-- system account ('system:<name>'). A wallet's balance_cents must equal the
+-- sum of its entries.
+--
+-- Unlike the PostgreSQL migration there are no existing balances to open the
+-- ledger with: SQLite databases start out empty.
+
+CREATE TABLE ledger_entries (
+    id TEXT PRIMARY KEY,
+    account TEXT NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    transaction_id TEXT REFERENCES credit_transactions(id),
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_ledger_entries_account ON ledger_entries(account, created_at DESC);
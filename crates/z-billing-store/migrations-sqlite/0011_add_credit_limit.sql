@@ -0,0 +1,6 @@
+-- Credit line for trusted accounts. Usage may take the balance down to
+-- -credit_limit_cents; once it gets there, overdraft_locked blocks further
+-- usage until the balance is back above zero.
+
+ALTER TABLE accounts ADD COLUMN credit_limit_cents INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE accounts ADD COLUMN overdraft_locked BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,28 @@
+-- Promo codes granting bonus credits, and their redemptions. Redemptions
+-- are kept when a code is deleted so recreating it doesn't reset per-user
+-- limits.
+
+CREATE TABLE promo_codes (
+    code TEXT PRIMARY KEY,
+    amount_cents INTEGER NOT NULL CHECK (amount_cents > 0),
+    max_redemptions INTEGER CHECK (max_redemptions > 0),
+    per_user_limit INTEGER NOT NULL DEFAULT 1 CHECK (per_user_limit > 0),
+    redemption_count INTEGER NOT NULL DEFAULT 0,
+    valid_from TEXT,
+    valid_until TEXT,
+    plans TEXT NOT NULL DEFAULT '[]',
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    description TEXT NOT NULL DEFAULT '',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE promo_redemptions (
+    transaction_id TEXT PRIMARY KEY REFERENCES credit_transactions(id),
+    code TEXT NOT NULL,
+    user_id TEXT NOT NULL REFERENCES accounts(user_id),
+    amount_cents INTEGER NOT NULL,
+    redeemed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_promo_redemptions_code_user ON promo_redemptions(code, user_id);
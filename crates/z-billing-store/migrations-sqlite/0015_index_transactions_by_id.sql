@@ -0,0 +1,4 @@
+-- Transaction history pages by ULID id (newest first) instead of OFFSET, so
+-- the cursor seek needs (user_id, id) rather than (user_id, created_at).
+
+CREATE INDEX idx_credit_transactions_user_id_id ON credit_transactions(user_id, id DESC);
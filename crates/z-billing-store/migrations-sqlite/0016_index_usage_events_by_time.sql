@@ -0,0 +1,4 @@
+-- Usage summaries across all users scan by time alone, which the
+-- (user_id, event_timestamp) index cannot serve.
+
+CREATE INDEX idx_usage_events_event_timestamp ON usage_events(event_timestamp);
@@ -0,0 +1,11 @@
+-- Free credit grants already given, keyed by a name for the grant such as
+-- 'daily:2026-10-16' or 'monthly:<period start>'. The key is inserted in the
+-- same transaction as the credit, so each grant is given at most once.
+
+CREATE TABLE credit_grants (
+    user_id TEXT NOT NULL,
+    grant_key TEXT NOT NULL,
+    transaction_id TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, grant_key)
+);
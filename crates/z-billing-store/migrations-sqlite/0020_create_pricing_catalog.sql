@@ -0,0 +1,11 @@
+-- Versioned pricing catalogs. The service prices usage with the most
+-- recently saved version and picks up new ones without a restart. Versions
+-- are never updated in place; a price change is a new row.
+
+CREATE TABLE pricing_catalog (
+    version TEXT PRIMARY KEY,
+    catalog TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_pricing_catalog_created ON pricing_catalog(created_at DESC);
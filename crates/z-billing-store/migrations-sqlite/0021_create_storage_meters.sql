@@ -0,0 +1,13 @@
+-- The last reading of each metered storage resource. Storage services
+-- report the bytes a resource holds and z-billing charges the GB-hours
+-- since the previous reading, so each resource keeps one row, written
+-- with a version check so a reading is charged once.
+
+CREATE TABLE storage_meters (
+    user_id TEXT NOT NULL,
+    resource_id TEXT NOT NULL,
+    meter TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, resource_id)
+);
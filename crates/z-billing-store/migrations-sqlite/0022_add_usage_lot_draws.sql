@@ -0,0 +1,6 @@
+-- What each usage charge took from the user's credit lots, as a JSON list
+-- of lot draws, so that a reversal can return the credits with the source
+-- and expiry they were spent from. Events charged before this column have
+-- no draws and are refunded without lots.
+
+ALTER TABLE usage_events ADD COLUMN lot_draws TEXT;
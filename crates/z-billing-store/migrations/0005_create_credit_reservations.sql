@@ -0,0 +1,19 @@
+-- Credit reservations (holds) for in-flight usage such as streamed LLM responses.
+-- Active, unexpired holds are subtracted from the balance when checking
+-- whether new usage or new holds fit.
+
+CREATE TABLE credit_reservations (
+    id TEXT PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES accounts(user_id),
+    amount_cents BIGINT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'active',
+    transaction_id TEXT,
+    metadata JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_credit_reservations_active
+    ON credit_reservations(user_id, expires_at)
+    WHERE status = 'active';
@@ -0,0 +1,22 @@
+-- Credit lots: every credit addition is tracked with its source and an
+-- optional expiry so usage can spend credits in a fixed order and expired
+-- credits can be removed by the sweeper. Balance that predates this table
+-- has no lot and is spent last.
+
+CREATE TABLE credit_lots (
+    id TEXT PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES accounts(user_id),
+    source TEXT NOT NULL,
+    amount_cents BIGINT NOT NULL,
+    remaining_cents BIGINT NOT NULL,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_credit_lots_open
+    ON credit_lots(user_id)
+    WHERE remaining_cents > 0;
+
+CREATE INDEX idx_credit_lots_expiring
+    ON credit_lots(expires_at)
+    WHERE remaining_cents > 0 AND expires_at IS NOT NULL;
@@ -0,0 +1,25 @@
+-- Per-agent spending limits and the running totals they are checked against.
+-- Totals are kept in rolling UTC day/month windows: a window whose start is
+-- in the past counts as zero and is reset on the next charge.
+
+CREATE TABLE agent_budgets (
+    user_id UUID NOT NULL REFERENCES accounts(user_id),
+    agent_id UUID NOT NULL,
+    period TEXT NOT NULL,
+    limit_cents BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, agent_id, period)
+);
+
+CREATE TABLE agent_spend (
+    user_id UUID NOT NULL REFERENCES accounts(user_id),
+    agent_id UUID NOT NULL,
+    day_start TIMESTAMPTZ NOT NULL,
+    day_cents BIGINT NOT NULL DEFAULT 0,
+    month_start TIMESTAMPTZ NOT NULL,
+    month_cents BIGINT NOT NULL DEFAULT 0,
+    lifetime_cents BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, agent_id)
+);
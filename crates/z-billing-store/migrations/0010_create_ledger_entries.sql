@@ -0,0 +1,30 @@
+-- Double-entry ledger. Every balance movement posts entries that sum to zero:
+-- one against the wallet ('user:<uuid>' or 'org:<uuid>') and one against a
+-- system account ('system:<name>'). A wallet's balance_cents must equal the
+-- sum of its entries.
+
+CREATE TABLE ledger_entries (
+    id UUID PRIMARY KEY,
+    account TEXT NOT NULL,
+    amount_cents BIGINT NOT NULL,
+    transaction_id TEXT REFERENCES credit_transactions(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_ledger_entries_account ON ledger_entries(account, created_at DESC);
+
+-- Open the ledger with the balances that exist today.
+INSERT INTO ledger_entries (id, account, amount_cents)
+SELECT gen_random_uuid(), 'user:' || user_id::TEXT, balance_cents
+FROM accounts
+WHERE balance_cents <> 0;
+
+INSERT INTO ledger_entries (id, account, amount_cents)
+SELECT gen_random_uuid(), 'org:' || id::TEXT, balance_cents
+FROM organizations
+WHERE balance_cents <> 0;
+
+INSERT INTO ledger_entries (id, account, amount_cents)
+SELECT gen_random_uuid(), 'system:opening_balances', -SUM(amount_cents)
+FROM ledger_entries
+HAVING SUM(amount_cents) <> 0;
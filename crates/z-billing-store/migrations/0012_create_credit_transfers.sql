@@ -0,0 +1,14 @@
+-- Credit transfers from a user's personal balance to another user or an
+-- organization pool ('user:<uuid>' or 'org:<uuid>'). The idempotency_key
+-- makes retries of the same transfer by the same sender return the original.
+
+CREATE TABLE credit_transfers (
+    from_user_id UUID NOT NULL REFERENCES accounts(user_id),
+    idempotency_key TEXT NOT NULL,
+    to_account TEXT NOT NULL,
+    amount_cents BIGINT NOT NULL CHECK (amount_cents > 0),
+    out_transaction_id TEXT NOT NULL REFERENCES credit_transactions(id),
+    in_transaction_id TEXT NOT NULL REFERENCES credit_transactions(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (from_user_id, idempotency_key)
+);
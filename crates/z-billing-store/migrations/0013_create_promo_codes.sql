@@ -0,0 +1,28 @@
+-- Promo codes granting bonus credits, and their redemptions. Redemptions
+-- are kept when a code is deleted so recreating it doesn't reset per-user
+-- limits.
+
+CREATE TABLE promo_codes (
+    code TEXT PRIMARY KEY,
+    amount_cents BIGINT NOT NULL CHECK (amount_cents > 0),
+    max_redemptions BIGINT CHECK (max_redemptions > 0),
+    per_user_limit BIGINT NOT NULL DEFAULT 1 CHECK (per_user_limit > 0),
+    redemption_count BIGINT NOT NULL DEFAULT 0,
+    valid_from TIMESTAMPTZ,
+    valid_until TIMESTAMPTZ,
+    plans JSONB NOT NULL DEFAULT '[]',
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    description TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE promo_redemptions (
+    transaction_id TEXT PRIMARY KEY REFERENCES credit_transactions(id),
+    code TEXT NOT NULL,
+    user_id UUID NOT NULL REFERENCES accounts(user_id),
+    amount_cents BIGINT NOT NULL,
+    redeemed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_promo_redemptions_code_user ON promo_redemptions(code, user_id);
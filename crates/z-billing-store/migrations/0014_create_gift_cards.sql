@@ -0,0 +1,20 @@
+-- Prepaid credit gift cards bought through Stripe. A card is issued with a
+-- single-use code and moves to either 'redeemed' or 'voided'.
+
+CREATE TABLE gift_cards (
+    id TEXT PRIMARY KEY,
+    code TEXT NOT NULL UNIQUE,
+    amount_cents BIGINT NOT NULL CHECK (amount_cents > 0),
+    purchaser_id UUID NOT NULL,
+    status TEXT NOT NULL DEFAULT 'issued',
+    stripe_session_id TEXT UNIQUE,
+    redeemed_by UUID,
+    transaction_id TEXT,
+    redeemed_at TIMESTAMPTZ,
+    voided_at TIMESTAMPTZ,
+    void_reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_gift_cards_purchaser ON gift_cards(purchaser_id, created_at DESC);
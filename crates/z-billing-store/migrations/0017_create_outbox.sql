@@ -0,0 +1,19 @@
+-- Side effects of balance changes (Lago forwarding, analytics, zOS sync,
+-- balance notifications), written in the same transaction as the change and
+-- delivered by a background dispatcher. Delivered messages are deleted;
+-- messages that keep failing move to 'dead_lettered' until replayed.
+
+CREATE TABLE outbox (
+    id TEXT PRIMARY KEY,
+    topic TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_outbox_due ON outbox(next_attempt_at) WHERE status = 'pending';
+CREATE INDEX idx_outbox_status ON outbox(status, id);
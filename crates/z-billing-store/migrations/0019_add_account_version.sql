@@ -0,0 +1,5 @@
+-- Write version for optimistic concurrency on account settings, bumped by
+-- every settings write. Existing accounts start at version 1, since 0 means
+-- an account that has not been stored yet.
+
+ALTER TABLE accounts ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
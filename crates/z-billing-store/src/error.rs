@@ -1,5 +1,7 @@
 //! Error types for z-billing storage.
 
+use z_billing_core::{AgentId, BudgetPeriod, PromoRejection};
+
 /// Result type for storage operations.
 pub type Result<T> = std::result::Result<T, StoreError>;
 
@@ -32,10 +34,97 @@ pub enum StoreError {
         required: i64,
     },
 
+    /// An organization member's monthly spend cap would be exceeded.
+    #[error("spend cap exceeded: cap={cap}, spent={spent}, required={required}")]
+    SpendCapExceeded {
+        /// The member's monthly cap in cents.
+        cap: i64,
+        /// Amount already spent this month in cents.
+        spent: i64,
+        /// Required amount in cents.
+        required: i64,
+    },
+
+    /// An agent's spending budget would be exceeded.
+    #[error(
+        "agent budget exceeded: agent={agent_id}, period={}, limit={limit}, spent={spent}, required={required}",
+        .period.as_str()
+    )]
+    BudgetExceeded {
+        /// The agent whose budget was hit.
+        agent_id: AgentId,
+        /// The budget period that was hit.
+        period: BudgetPeriod,
+        /// The budget limit in cents.
+        limit: i64,
+        /// Amount already spent in the period in cents.
+        spent: i64,
+        /// Required amount in cents.
+        required: i64,
+    },
+
+    /// A reversal would refund more than the usage event charged.
+    #[error(
+        "reversal exceeds charge: charged={charged}, reversed={reversed}, requested={requested}"
+    )]
+    ReversalExceedsCharge {
+        /// Amount the event originally charged in cents.
+        charged: i64,
+        /// Amount already reversed in cents.
+        reversed: i64,
+        /// Requested reversal amount in cents.
+        requested: i64,
+    },
+
+    /// A transfer would send more than the sender's purchased credits.
+    #[error(
+        "transfer exceeds purchased credits: transferable={transferable}, requested={requested}"
+    )]
+    TransferExceedsPurchased {
+        /// Transferable credits the sender holds in cents.
+        transferable: i64,
+        /// Requested transfer amount in cents.
+        requested: i64,
+    },
+
+    /// A promo code cannot be redeemed by this user right now.
+    #[error("promo code {code} rejected: {}", .reason.as_str())]
+    PromoRejected {
+        /// The code being redeemed.
+        code: String,
+        /// The rule the redemption broke.
+        reason: PromoRejection,
+    },
+
     /// Duplicate event (idempotency check failed).
     #[error("duplicate event: {event_id}")]
     DuplicateEvent {
         /// The event ID that was duplicated.
         event_id: String,
     },
+
+    /// The record was written by someone else since it was read
+    /// (optimistic concurrency check failed).
+    #[error("{entity} {id} version conflict: expected={expected}, actual={actual}")]
+    VersionConflict {
+        /// The type of entity.
+        entity: &'static str,
+        /// The identifier of the entity.
+        id: String,
+        /// The version the writer read.
+        expected: i64,
+        /// The version currently stored (0 if the record does not exist).
+        actual: i64,
+    },
+
+    /// The record is in a state that does not allow the requested operation.
+    #[error("{entity} {id} is {state}")]
+    InvalidState {
+        /// The type of entity.
+        entity: &'static str,
+        /// The identifier of the entity.
+        id: String,
+        /// The entity's current state.
+        state: String,
+    },
 }
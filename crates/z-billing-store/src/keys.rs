@@ -2,7 +2,10 @@
 //!
 //! This module provides functions for encoding and decoding keys used in column families.
 
-use z_billing_core::{TransactionId, UserId};
+use z_billing_core::{
+    AgentId, BudgetPeriod, GiftCardId, LedgerAccount, LedgerEntry, LotId, OrgId, OutboxId,
+    ReservationId, TransactionId, UserId,
+};
 
 /// Create an account key from a user ID.
 #[must_use]
@@ -10,6 +13,95 @@ pub fn account_key(user_id: &UserId) -> Vec<u8> {
     user_id.as_bytes().to_vec()
 }
 
+/// Create a Stripe customer index key.
+#[must_use]
+pub fn stripe_customer_key(customer_id: &str) -> Vec<u8> {
+    customer_id.as_bytes().to_vec()
+}
+
+/// Create a Lago customer index key.
+#[must_use]
+pub fn lago_customer_key(customer_id: &str) -> Vec<u8> {
+    customer_id.as_bytes().to_vec()
+}
+
+/// Meta key set once the customer indexes hold every account.
+pub const CUSTOMER_INDEXES_BUILT: &[u8] = b"customer_indexes_built";
+
+/// Extract the user ID from a customer index value.
+///
+/// # Errors
+///
+/// Returns an error if the value is not exactly 16 bytes.
+pub fn extract_user_id(bytes: &[u8]) -> Result<UserId, crate::error::StoreError> {
+    let uuid = uuid::Uuid::from_slice(bytes).map_err(|e| {
+        crate::error::StoreError::Database(format!("invalid user ID in index: {e}"))
+    })?;
+    Ok(UserId::from_uuid(uuid))
+}
+
+/// Create an organization key from an org ID.
+#[must_use]
+pub fn org_key(org_id: &OrgId) -> Vec<u8> {
+    org_id.as_bytes().to_vec()
+}
+
+/// Create an organization membership key.
+///
+/// Format: `org_id (16 bytes) || user_id (16 bytes)`
+#[must_use]
+pub fn org_member_key(org_id: &OrgId, user_id: &UserId) -> Vec<u8> {
+    let mut key = Vec::with_capacity(32);
+    key.extend_from_slice(org_id.as_bytes());
+    key.extend_from_slice(user_id.as_bytes());
+    key
+}
+
+/// Create a prefix for iterating all members of an organization.
+#[must_use]
+pub fn org_members_prefix(org_id: &OrgId) -> Vec<u8> {
+    org_id.as_bytes().to_vec()
+}
+
+/// Create an agent spend key.
+///
+/// Format: `user_id (16 bytes) || agent_id (16 bytes)`
+#[must_use]
+pub fn agent_spend_key(user_id: &UserId, agent_id: &AgentId) -> Vec<u8> {
+    let mut key = Vec::with_capacity(32);
+    key.extend_from_slice(user_id.as_bytes());
+    key.extend_from_slice(agent_id.as_bytes());
+    key
+}
+
+/// Create a prefix for iterating all of a user's agent spend totals.
+#[must_use]
+pub fn user_agent_spend_prefix(user_id: &UserId) -> Vec<u8> {
+    user_id.as_bytes().to_vec()
+}
+
+/// Create an agent budget key.
+///
+/// Format: `user_id (16 bytes) || agent_id (16 bytes) || period`
+#[must_use]
+pub fn agent_budget_key(user_id: &UserId, agent_id: &AgentId, period: BudgetPeriod) -> Vec<u8> {
+    let mut key = agent_spend_key(user_id, agent_id);
+    key.extend_from_slice(period.as_str().as_bytes());
+    key
+}
+
+/// Create a prefix for iterating all budgets of one agent.
+#[must_use]
+pub fn agent_budgets_prefix(user_id: &UserId, agent_id: &AgentId) -> Vec<u8> {
+    agent_spend_key(user_id, agent_id)
+}
+
+/// Create a prefix for iterating all of a user's agent budgets.
+#[must_use]
+pub fn user_agent_budgets_prefix(user_id: &UserId) -> Vec<u8> {
+    user_id.as_bytes().to_vec()
+}
+
 /// Create a transaction key from a transaction ID.
 #[must_use]
 pub fn transaction_key(transaction_id: &TransactionId) -> Vec<u8> {
@@ -62,6 +154,292 @@ pub fn usage_event_key(event_id: &str) -> Vec<u8> {
     event_id.as_bytes().to_vec()
 }
 
+/// Create a usage reversal key.
+///
+/// Format: `event_id || 0x00 || reversal_id`
+#[must_use]
+pub fn usage_reversal_key(event_id: &str, reversal_id: &str) -> Vec<u8> {
+    let mut key = usage_reversals_prefix(event_id);
+    key.extend_from_slice(reversal_id.as_bytes());
+    key
+}
+
+/// Create a prefix for iterating all reversals of a usage event.
+#[must_use]
+pub fn usage_reversals_prefix(event_id: &str) -> Vec<u8> {
+    let mut key = Vec::with_capacity(event_id.len() + 1);
+    key.extend_from_slice(event_id.as_bytes());
+    key.push(0);
+    key
+}
+
+/// Create a reservation key from a reservation ID.
+#[must_use]
+pub fn reservation_key(reservation_id: &ReservationId) -> Vec<u8> {
+    reservation_id.to_bytes().to_vec()
+}
+
+/// Create a user-reservation index key.
+///
+/// Format: `user_id (16 bytes) || reservation_id (16 bytes)`
+#[must_use]
+pub fn user_reservation_key(user_id: &UserId, reservation_id: &ReservationId) -> Vec<u8> {
+    let mut key = Vec::with_capacity(32);
+    key.extend_from_slice(user_id.as_bytes());
+    key.extend_from_slice(&reservation_id.to_bytes());
+    key
+}
+
+/// Create a prefix for iterating all reservations for a user.
+#[must_use]
+pub fn user_reservations_prefix(user_id: &UserId) -> Vec<u8> {
+    user_id.as_bytes().to_vec()
+}
+
+/// Extract the reservation ID from a user-reservation index key.
+///
+/// # Errors
+///
+/// Returns an error if the key is not at least 32 bytes or contains invalid ULID bytes.
+pub fn extract_reservation_id_from_user_key(
+    key: &[u8],
+) -> Result<ReservationId, crate::error::StoreError> {
+    if key.len() < 32 {
+        return Err(crate::error::StoreError::Database(format!(
+            "user-reservation key too short: expected 32 bytes, got {}",
+            key.len()
+        )));
+    }
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&key[16..32]);
+    ReservationId::from_bytes(bytes).map_err(|e| {
+        crate::error::StoreError::Database(format!("invalid reservation ID in key: {e}"))
+    })
+}
+
+/// Create a credit lot key.
+///
+/// Format: `user_id (16 bytes) || lot_id (16 bytes)`
+#[must_use]
+pub fn credit_lot_key(user_id: &UserId, lot_id: &LotId) -> Vec<u8> {
+    let mut key = Vec::with_capacity(32);
+    key.extend_from_slice(user_id.as_bytes());
+    key.extend_from_slice(&lot_id.to_bytes());
+    key
+}
+
+/// Create a prefix for iterating all credit lots for a user.
+#[must_use]
+pub fn user_credit_lots_prefix(user_id: &UserId) -> Vec<u8> {
+    user_id.as_bytes().to_vec()
+}
+
+/// Create a lot expiry index key.
+///
+/// Format: `expires_at_millis (8 bytes, big-endian) || user_id (16 bytes) || lot_id (16 bytes)`
+///
+/// Big-endian timestamps sort chronologically, so a forward scan visits the
+/// soonest-expiring lots first.
+#[must_use]
+pub fn credit_lot_expiry_key(
+    expires_at: chrono::DateTime<chrono::Utc>,
+    user_id: &UserId,
+    lot_id: &LotId,
+) -> Vec<u8> {
+    let millis = u64::try_from(expires_at.timestamp_millis()).unwrap_or(0);
+    let mut key = Vec::with_capacity(40);
+    key.extend_from_slice(&millis.to_be_bytes());
+    key.extend_from_slice(user_id.as_bytes());
+    key.extend_from_slice(&lot_id.to_bytes());
+    key
+}
+
+/// Split a lot expiry index key into its expiry timestamp (millis) and lot key.
+///
+/// # Errors
+///
+/// Returns an error if the key is not exactly 40 bytes.
+pub fn split_credit_lot_expiry_key(key: &[u8]) -> Result<(u64, &[u8]), crate::error::StoreError> {
+    if key.len() != 40 {
+        return Err(crate::error::StoreError::Database(format!(
+            "lot expiry key has wrong length: expected 40 bytes, got {}",
+            key.len()
+        )));
+    }
+    let mut millis = [0u8; 8];
+    millis.copy_from_slice(&key[..8]);
+    Ok((u64::from_be_bytes(millis), &key[8..]))
+}
+
+/// Create a prefix for iterating all entries of a ledger account.
+///
+/// Format: `0x01 || user_id`, `0x02 || org_id` or `0x03 || name || 0x00`
+#[must_use]
+pub fn ledger_account_prefix(account: &LedgerAccount) -> Vec<u8> {
+    match account {
+        LedgerAccount::User(user_id) => {
+            let mut key = Vec::with_capacity(17);
+            key.push(0x01);
+            key.extend_from_slice(user_id.as_bytes());
+            key
+        }
+        LedgerAccount::Org(org_id) => {
+            let mut key = Vec::with_capacity(17);
+            key.push(0x02);
+            key.extend_from_slice(org_id.as_bytes());
+            key
+        }
+        LedgerAccount::System(system) => {
+            let name = system.as_str().as_bytes();
+            let mut key = Vec::with_capacity(name.len() + 2);
+            key.push(0x03);
+            key.extend_from_slice(name);
+            key.push(0x00);
+            key
+        }
+    }
+}
+
+/// Create a ledger entry key.
+///
+/// Format: `account prefix || created_at_millis (8 bytes BE) || entry_id (16 bytes)`
+///
+/// Entries for an account sort by posting time.
+#[must_use]
+pub fn ledger_entry_key(entry: &LedgerEntry) -> Vec<u8> {
+    let millis = u64::try_from(entry.created_at.timestamp_millis()).unwrap_or(0);
+    let mut key = ledger_account_prefix(&entry.account);
+    key.extend_from_slice(&millis.to_be_bytes());
+    key.extend_from_slice(entry.id.as_bytes());
+    key
+}
+
+/// Create a credit transfer key.
+///
+/// Format: `from_user_id (16 bytes) || idempotency_key`
+#[must_use]
+pub fn credit_transfer_key(from_user_id: &UserId, idempotency_key: &str) -> Vec<u8> {
+    let mut key = Vec::with_capacity(16 + idempotency_key.len());
+    key.extend_from_slice(from_user_id.as_bytes());
+    key.extend_from_slice(idempotency_key.as_bytes());
+    key
+}
+
+/// Create a credit grant key.
+///
+/// Format: `user_id (16 bytes) || grant_key`
+#[must_use]
+pub fn credit_grant_key(user_id: &UserId, grant_key: &str) -> Vec<u8> {
+    let mut key = Vec::with_capacity(16 + grant_key.len());
+    key.extend_from_slice(user_id.as_bytes());
+    key.extend_from_slice(grant_key.as_bytes());
+    key
+}
+
+/// Create a promo code key.
+#[must_use]
+pub fn promo_code_key(code: &str) -> Vec<u8> {
+    code.as_bytes().to_vec()
+}
+
+/// Create a promo redemption key.
+///
+/// Format: `code || 0x00 || user_id (16 bytes) || transaction_id (16 bytes)`
+#[must_use]
+pub fn promo_redemption_key(
+    code: &str,
+    user_id: &UserId,
+    transaction_id: &TransactionId,
+) -> Vec<u8> {
+    let mut key = user_promo_redemptions_prefix(code, user_id);
+    key.extend_from_slice(&transaction_id.to_bytes());
+    key
+}
+
+/// Create a prefix for iterating a user's redemptions of a promo code.
+#[must_use]
+pub fn user_promo_redemptions_prefix(code: &str, user_id: &UserId) -> Vec<u8> {
+    let mut key = Vec::with_capacity(code.len() + 33);
+    key.extend_from_slice(code.as_bytes());
+    key.push(0);
+    key.extend_from_slice(user_id.as_bytes());
+    key
+}
+
+/// Create a gift card key from a gift card ID.
+#[must_use]
+pub fn gift_card_key(card_id: &GiftCardId) -> Vec<u8> {
+    card_id.to_bytes().to_vec()
+}
+
+/// Create a gift card code index key.
+#[must_use]
+pub fn gift_card_code_key(code: &str) -> Vec<u8> {
+    code.as_bytes().to_vec()
+}
+
+/// Create a purchaser-gift card index key.
+///
+/// Format: `user_id (16 bytes) || card_id (16 bytes)`
+#[must_use]
+pub fn purchaser_gift_card_key(user_id: &UserId, card_id: &GiftCardId) -> Vec<u8> {
+    let mut key = Vec::with_capacity(32);
+    key.extend_from_slice(user_id.as_bytes());
+    key.extend_from_slice(&card_id.to_bytes());
+    key
+}
+
+/// Create a prefix for iterating all gift cards bought by a user.
+#[must_use]
+pub fn purchaser_gift_cards_prefix(user_id: &UserId) -> Vec<u8> {
+    user_id.as_bytes().to_vec()
+}
+
+/// Create an outbox key from an outbox message ID.
+///
+/// ULID bytes sort by creation time, so the column family is oldest first.
+#[must_use]
+pub fn outbox_key(id: &OutboxId) -> Vec<u8> {
+    id.to_bytes().to_vec()
+}
+
+/// Create a pricing catalog key from a catalog version.
+#[must_use]
+pub fn pricing_catalog_key(version: &str) -> Vec<u8> {
+    version.as_bytes().to_vec()
+}
+
+/// Create a storage meter key.
+///
+/// Format: `user_id (16 bytes) || resource_id`
+#[must_use]
+pub fn storage_meter_key(user_id: &UserId, resource_id: &str) -> Vec<u8> {
+    let mut key = Vec::with_capacity(16 + resource_id.len());
+    key.extend_from_slice(user_id.as_bytes());
+    key.extend_from_slice(resource_id.as_bytes());
+    key
+}
+
+/// Extract the gift card ID from a gift card ID value or a
+/// purchaser-gift card index key (the ID is the last 16 bytes).
+///
+/// # Errors
+///
+/// Returns an error if the input is shorter than 16 bytes.
+pub fn extract_gift_card_id(bytes: &[u8]) -> Result<GiftCardId, crate::error::StoreError> {
+    if bytes.len() < 16 {
+        return Err(crate::error::StoreError::Database(format!(
+            "gift card key too short: expected at least 16 bytes, got {}",
+            bytes.len()
+        )));
+    }
+    let mut id = [0u8; 16];
+    id.copy_from_slice(&bytes[bytes.len() - 16..]);
+    GiftCardId::from_bytes(id).map_err(|e| {
+        crate::error::StoreError::Database(format!("invalid gift card ID in key: {e}"))
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -107,4 +485,54 @@ mod tests {
         let result = extract_transaction_id_from_user_key(&short_key);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn extract_reservation_id_roundtrip() {
+        let user_id = UserId::generate();
+        let reservation_id = ReservationId::generate();
+        let key = user_reservation_key(&user_id, &reservation_id);
+
+        assert_eq!(key.len(), 32);
+        let extracted = extract_reservation_id_from_user_key(&key).unwrap();
+        assert_eq!(extracted, reservation_id);
+    }
+
+    #[test]
+    fn lot_expiry_key_sorts_by_time() {
+        let user_id = UserId::generate();
+        let lot_id = LotId::generate();
+        let sooner = chrono::Utc::now();
+        let later = sooner + chrono::Duration::hours(1);
+
+        let sooner_key = credit_lot_expiry_key(sooner, &user_id, &lot_id);
+        let later_key = credit_lot_expiry_key(later, &user_id, &lot_id);
+        assert!(sooner_key < later_key);
+
+        let (millis, lot_key) = split_credit_lot_expiry_key(&sooner_key).unwrap();
+        assert_eq!(millis, u64::try_from(sooner.timestamp_millis()).unwrap());
+        assert_eq!(lot_key, credit_lot_key(&user_id, &lot_id).as_slice());
+    }
+
+    #[test]
+    fn extract_gift_card_id_roundtrip() {
+        let user_id = UserId::generate();
+        let card_id = GiftCardId::generate();
+
+        let key = purchaser_gift_card_key(&user_id, &card_id);
+        assert_eq!(key.len(), 32);
+        assert_eq!(extract_gift_card_id(&key).unwrap(), card_id);
+        assert_eq!(
+            extract_gift_card_id(&gift_card_key(&card_id)).unwrap(),
+            card_id
+        );
+        assert!(extract_gift_card_id(&[0u8; 8]).is_err());
+    }
+
+    #[test]
+    fn extract_user_id_roundtrip() {
+        let user_id = UserId::generate();
+
+        assert_eq!(extract_user_id(&account_key(&user_id)).unwrap(), user_id);
+        assert!(extract_user_id(&[0u8; 8]).is_err());
+    }
 }
@@ -3,14 +3,41 @@
 //! This crate provides persistent storage for accounts, transactions, and usage events
 //! using `RocksDB` with column families for efficient indexing.
 //!
+//! [`PgStore`] implements the same [`Store`] trait on `PostgreSQL`. With the
+//! `sqlite-backend` feature, `SqliteStore` does the same on `SQLite`, and with
+//! the `memory-backend` feature, `MemoryStore` keeps everything in memory for
+//! tests and embedding. `tests/conformance.rs` runs one suite against every
+//! backend.
+//!
 //! # Architecture
 //!
 //! The storage uses the following column families:
 //!
 //! - `accounts`: Primary account records, keyed by `user_id`
+//! - `accounts_by_stripe_customer`: Index of accounts by Stripe customer ID
+//! - `accounts_by_lago_customer`: Index of accounts by Lago customer ID
 //! - `transactions`: Credit transactions, keyed by `transaction_id` (ULID)
 //! - `transactions_by_user`: Index for listing transactions by user
 //! - `usage_events`: Usage events for idempotency checking, keyed by `event_id`
+//! - `usage_reversals`: Refunds of usage charges, keyed by `event_id || reversal_id`
+//! - `reservations`: Credit holds, keyed by `reservation_id` (ULID)
+//! - `active_reservations_by_user`: Index of open holds per user
+//! - `organizations`: Organizations with shared balances, keyed by `org_id`
+//! - `org_members`: Organization memberships, keyed by `org_id || user_id`
+//! - `credit_lots`: Open credit lots, keyed by `user_id || lot_id`
+//! - `credit_lots_by_expiry`: Index of expiring lots for the sweeper
+//! - `agent_budgets`: Per-agent spending limits, keyed by `user_id || agent_id || period`
+//! - `agent_spend`: Running per-agent spend totals, keyed by `user_id || agent_id`
+//! - `ledger_entries`: Double-entry postings, keyed by `account || created_at || entry_id`
+//! - `credit_transfers`: Completed transfers, keyed by `from_user_id || idempotency_key`
+//! - `promo_codes`: Promo codes, keyed by `code`
+//! - `promo_redemptions`: Promo code redemptions, keyed by `code || user_id || transaction_id`
+//! - `gift_cards`: Gift cards, keyed by `card_id` (ULID)
+//! - `gift_card_codes`: Index of gift cards by redemption code
+//! - `gift_cards_by_purchaser`: Index of gift cards by buyer
+//! - `outbox`: Pending and dead-lettered side effects, keyed by `outbox_id` (ULID)
+//! - `pricing_catalogs`: Saved pricing catalog versions, keyed by `version`
+//! - `meta`: Markers for one-time backfills that have run
 //!
 //! # Example
 //!
@@ -18,15 +45,18 @@
 //! use z_billing_store::{RocksStore, Store};
 //! use z_billing_core::{UserId, Account};
 //!
-//! let store = RocksStore::open("/tmp/z-billing-db").unwrap();
+//! # async fn example() -> z_billing_store::Result<()> {
+//! let store = RocksStore::open("/tmp/z-billing-db")?;
 //!
 //! // Create an account
 //! let user_id = UserId::generate();
 //! let account = Account::new(user_id);
-//! store.put_account(&account).unwrap();
+//! store.put_account(&account).await?;
 //!
 //! // Get balance
-//! let retrieved = store.get_account(&user_id).unwrap();
+//! let retrieved = store.get_account(&user_id).await?;
+//! # Ok(())
+//! # }
 //! ```
 
 #![forbid(unsafe_code)]
@@ -37,6 +67,12 @@
 pub mod error;
 pub mod postgres;
 
+#[cfg(feature = "memory-backend")]
+pub mod memory;
+
+#[cfg(feature = "sqlite-backend")]
+pub mod sqlite;
+
 #[cfg(feature = "rocksdb-backend")]
 pub mod keys;
 #[cfg(feature = "rocksdb-backend")]
@@ -47,51 +83,121 @@ pub mod schema;
 pub use error::{Result, StoreError};
 pub use postgres::PgStore;
 
+#[cfg(feature = "memory-backend")]
+pub use memory::MemoryStore;
+
+#[cfg(feature = "sqlite-backend")]
+pub use sqlite::SqliteStore;
+
 #[cfg(feature = "rocksdb-backend")]
 pub use rocks::RocksStore;
 
-use z_billing_core::{Account, CreditTransaction, TransactionId, UsageEvent, UserId};
+use z_billing_core::{
+    Account, AgentBudget, AgentId, AgentSpend, BudgetPeriod, CreditLot, CreditTransaction,
+    CreditTransfer, GiftCard, GiftCardId, LedgerAccount, LedgerEntry, LedgerReport, OrgId,
+    OrgMembership, Organization, OutboxId, OutboxMessage, OutboxStatus, PricingCatalog,
+    ProcessedWebhook, PromoCode, PromoRedemption, Reservation, ReservationId, StorageMeter,
+    TransactionId, TransactionQuery, UsageEvent, UsageReversal, UsageSummaryQuery, UsageSummaryRow,
+    UserId,
+};
 
 /// The storage trait defining all database operations.
 ///
 /// This trait abstracts the storage layer, allowing for different implementations
 /// (e.g., `RocksDB`, in-memory for testing).
+///
+/// Every operation is async. Backends built on blocking I/O, like
+/// `RocksStore`, run it on tokio's blocking thread pool so callers never
+/// stall a runtime worker.
+#[async_trait::async_trait]
 pub trait Store: Send + Sync {
     // =========================================================================
     // Account Operations
     // =========================================================================
 
-    /// Insert or update an account record.
+    /// Insert or update an account's settings, bumping its version.
+    ///
+    /// This never writes the balance or the lifetime counters: they only
+    /// move through operations that post to the ledger, and a new account
+    /// starts at zero. The overdraft lock is kept as stored unless
+    /// `credit_limit_cents` changed, in which case it is recomputed against
+    /// the new limit.
+    ///
+    /// The write is unconditional, so a read-modify-write can lose a
+    /// concurrent update; use [`Store::put_account_if_version`] for those.
     ///
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn put_account(&self, account: &Account) -> Result<()>;
+    async fn put_account(&self, account: &Account) -> Result<()>;
+
+    /// Write an account's settings like [`Store::put_account`], but only if
+    /// the stored version is still `expected_version`.
+    ///
+    /// Pass the `version` of the account as read; an expected version of 0
+    /// inserts a new account. The `outbox` messages are written in the same
+    /// atomic write. Returns the new version.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::VersionConflict` if the account was written since it
+    ///   was read, was created concurrently, or no longer exists.
+    async fn put_account_if_version(
+        &self,
+        account: &Account,
+        expected_version: i64,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64>;
+
+    /// Insert a new account, posting a non-zero `balance_cents` to the
+    /// ledger as its opening balance.
+    ///
+    /// This is the only way to give an account a balance without a credit
+    /// transaction, for balances carried over from another system.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::VersionConflict` if the account already exists.
+    async fn create_account(&self, account: &Account) -> Result<()>;
 
     /// Get an account by user ID.
     ///
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn get_account(&self, user_id: &UserId) -> Result<Option<Account>>;
+    async fn get_account(&self, user_id: &UserId) -> Result<Option<Account>>;
 
     /// Find an account by its Stripe customer ID.
     ///
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn find_account_by_stripe_customer(&self, customer_id: &str) -> Result<Option<Account>>;
+    async fn find_account_by_stripe_customer(&self, customer_id: &str) -> Result<Option<Account>>;
 
-    /// Delete an account by user ID.
+    /// Find an account by its Lago customer ID.
     ///
     /// # Errors
     ///
-    /// Returns `StoreError::NotFound` if the account doesn't exist.
-    fn delete_account(&self, user_id: &UserId) -> Result<()>;
+    /// Returns an error if the database operation fails.
+    async fn find_account_by_lago_customer(&self, customer_id: &str) -> Result<Option<Account>>;
+
+    /// Delete an account by user ID, along with its agent budgets and
+    /// reservations.
+    ///
+    /// Only an account that never moved credits can be deleted: its
+    /// transactions and ledger entries are kept for the books.
+    ///
+    /// # Errors
+    ///
+    /// Returns `StoreError::NotFound` if the account doesn't exist, or
+    /// `StoreError::InvalidState` if it has credit transactions or ledger
+    /// entries.
+    async fn delete_account(&self, user_id: &UserId) -> Result<()>;
 
     /// Update account balance by delta.
     ///
-    /// Returns the new balance after the update.
+    /// Returns the new balance after the update. The change is posted to the
+    /// ledger against the `adjustments` system account.
     ///
     /// # Warning
     ///
@@ -112,7 +218,73 @@ pub trait Store: Send + Sync {
         since = "0.2.0",
         note = "Use `add_credits` or `process_usage` for atomic operations with transaction recording"
     )]
-    fn update_balance(&self, user_id: &UserId, delta_cents: i64) -> Result<i64>;
+    async fn update_balance(&self, user_id: &UserId, delta_cents: i64) -> Result<i64>;
+
+    // =========================================================================
+    // Organization Operations
+    // =========================================================================
+
+    /// Create or update an organization.
+    ///
+    /// Like [`Self::put_account`], this never overwrites the stored balance of
+    /// an existing organization; use [`Self::add_org_credits`] and
+    /// [`Self::process_org_usage`] to move credits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn put_organization(&self, org: &Organization) -> Result<()>;
+
+    /// Get an organization by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_organization(&self, org_id: &OrgId) -> Result<Option<Organization>>;
+
+    /// Create or update a membership.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn put_org_membership(&self, membership: &OrgMembership) -> Result<()>;
+
+    /// Get a user's membership in an organization.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_org_membership(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+    ) -> Result<Option<OrgMembership>>;
+
+    /// List all members of an organization.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_org_members(&self, org_id: &OrgId) -> Result<Vec<OrgMembership>>;
+
+    /// Remove a user from an organization.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn delete_org_membership(&self, org_id: &OrgId, user_id: &UserId) -> Result<()>;
+
+    /// Sum of a member's usage charged to an organization since `since`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn org_member_spend_since(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<i64>;
 
     // =========================================================================
     // Transaction Operations
@@ -125,27 +297,43 @@ pub trait Store: Send + Sync {
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn put_transaction(&self, transaction: &CreditTransaction) -> Result<()>;
+    async fn put_transaction(&self, transaction: &CreditTransaction) -> Result<()>;
 
     /// Get a transaction by ID.
     ///
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn get_transaction(&self, transaction_id: &TransactionId) -> Result<Option<CreditTransaction>>;
+    async fn get_transaction(
+        &self,
+        transaction_id: &TransactionId,
+    ) -> Result<Option<CreditTransaction>>;
 
     /// List transactions for a user, ordered by time (newest first).
     ///
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn list_transactions_by_user(
+    async fn list_transactions_by_user(
         &self,
         user_id: &UserId,
         limit: usize,
         offset: usize,
     ) -> Result<Vec<CreditTransaction>>;
 
+    /// List a user's transactions matching `query`, newest first.
+    ///
+    /// Returns at most `query.limit` transactions older than `query.before`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn query_transactions(
+        &self,
+        user_id: &UserId,
+        query: &TransactionQuery,
+    ) -> Result<Vec<CreditTransaction>>;
+
     /// Sum the `amount_cents` of `monthly_allowance` transactions for a user
     /// at or after the given timestamp.
     ///
@@ -156,7 +344,7 @@ pub trait Store: Send + Sync {
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn sum_monthly_allowance_since(
+    async fn sum_monthly_allowance_since(
         &self,
         user_id: &UserId,
         since: chrono::DateTime<chrono::Utc>,
@@ -170,7 +358,7 @@ pub trait Store: Send + Sync {
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn has_referral_bonus(&self, user_id: &UserId) -> Result<bool>;
+    async fn has_referral_bonus(&self, user_id: &UserId) -> Result<bool>;
 
     // =========================================================================
     // Usage Event Operations (for idempotency)
@@ -181,21 +369,37 @@ pub trait Store: Send + Sync {
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn has_usage_event(&self, event_id: &str) -> Result<bool>;
+    async fn has_usage_event(&self, event_id: &str) -> Result<bool>;
 
     /// Record a usage event for idempotency.
     ///
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn put_usage_event(&self, event: &UsageEvent) -> Result<()>;
+    async fn put_usage_event(&self, event: &UsageEvent) -> Result<()>;
 
     /// Get a usage event by ID.
     ///
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn get_usage_event(&self, event_id: &str) -> Result<Option<UsageEvent>>;
+    async fn get_usage_event(&self, event_id: &str) -> Result<Option<UsageEvent>>;
+
+    /// List the reversals recorded against a usage event, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_usage_reversals(&self, event_id: &str) -> Result<Vec<UsageReversal>>;
+
+    /// Total usage events by time bucket and the attributes in `query`.
+    ///
+    /// Costs are as charged, before any reversals.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn aggregate_usage(&self, query: &UsageSummaryQuery) -> Result<Vec<UsageSummaryRow>>;
 
     // =========================================================================
     // Webhook Idempotency
@@ -206,14 +410,486 @@ pub trait Store: Send + Sync {
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn has_webhook_event(&self, event_id: &str) -> Result<bool>;
+    async fn has_webhook_event(&self, event_id: &str) -> Result<bool>;
 
     /// Record a webhook event as processed for replay protection.
     ///
     /// # Errors
     ///
     /// Returns an error if the database operation fails.
-    fn record_webhook_event(&self, event_id: &str, source: &str) -> Result<()>;
+    async fn record_webhook_event(&self, event_id: &str, source: &str) -> Result<()>;
+
+    // =========================================================================
+    // Reservation Operations
+    // =========================================================================
+
+    /// Place a hold on part of an account's balance.
+    ///
+    /// The hold succeeds only if the balance (plus any unlocked credit line)
+    /// minus all other active holds covers `reservation.amount_cents`.
+    /// Returns the available balance after the hold is placed.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::NotFound` if the account doesn't exist.
+    /// - `StoreError::InsufficientCredits` if the available balance is too low.
+    async fn create_reservation(&self, reservation: &Reservation) -> Result<i64>;
+
+    /// Get a reservation by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_reservation(&self, reservation_id: &ReservationId) -> Result<Option<Reservation>>;
+
+    /// Sum the amounts of a user's active, unexpired holds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn reserved_cents(&self, user_id: &UserId) -> Result<i64>;
+
+    /// Settle a reservation into a usage debit.
+    ///
+    /// Behaves like [`Self::process_usage`], except the reservation's own hold
+    /// is not counted against the balance and the reservation is marked
+    /// settled in the same write. The debit is the event's actual cost, which
+    /// may be more or less than the amount held. Holds that expired before
+    /// settlement can still be settled.
+    ///
+    /// Returns the new balance after deduction.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::NotFound` if the reservation or account doesn't exist.
+    /// - `StoreError::InvalidState` if the reservation was already settled or released.
+    /// - `StoreError::InsufficientCredits` if the available balance is too low.
+    /// - `StoreError::BudgetExceeded` if the event's agent is over a budget.
+    /// - `StoreError::DuplicateEvent` if the event was already processed.
+    async fn settle_reservation(
+        &self,
+        reservation_id: &ReservationId,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64>;
+
+    /// Release a reservation without charging.
+    ///
+    /// Releasing a reservation that is already released or expired is a no-op.
+    /// Returns the reservation in its final state.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::NotFound` if the reservation doesn't exist.
+    /// - `StoreError::InvalidState` if the reservation was already settled.
+    async fn release_reservation(&self, reservation_id: &ReservationId) -> Result<Reservation>;
+
+    /// Mark every active reservation whose TTL has passed as expired.
+    ///
+    /// Expired holds already stop counting against the balance once their
+    /// `expires_at` passes; this sweep only records the final state.
+    /// Returns the number of reservations expired.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn expire_reservations(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize>;
+
+    // =========================================================================
+    // Credit Lot Operations
+    // =========================================================================
+
+    /// List a user's open credit lots (credits not yet spent or expired),
+    /// in consumption order.
+    ///
+    /// The lots may sum to less than the account balance: credits added
+    /// before lots were tracked have no lot and are spent last.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_credit_lots(&self, user_id: &UserId) -> Result<Vec<CreditLot>>;
+
+    /// Remove the unspent remainder of every lot that has expired by `now`.
+    ///
+    /// Each expired lot debits the account and records a
+    /// `TransactionType::Expiry` transaction. Returns how many lots were
+    /// expired.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn expire_credit_lots(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize>;
+
+    // =========================================================================
+    // Credit Transfer Operations
+    // =========================================================================
+
+    /// Get the transfer a user made with the given idempotency key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_credit_transfer(
+        &self,
+        from_user_id: &UserId,
+        idempotency_key: &str,
+    ) -> Result<Option<CreditTransfer>>;
+
+    // =========================================================================
+    // Promo Code Operations
+    // =========================================================================
+
+    /// Create or update a promo code.
+    ///
+    /// The redemption count of an existing code is never overwritten: it
+    /// only changes through `redeem_promo_code`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn put_promo_code(&self, promo: &PromoCode) -> Result<()>;
+
+    /// Get a promo code by its normalized code.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_promo_code(&self, code: &str) -> Result<Option<PromoCode>>;
+
+    /// List all promo codes, ordered by code.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_promo_codes(&self) -> Result<Vec<PromoCode>>;
+
+    /// Delete a promo code. Its redemptions are kept, so recreating the code
+    /// does not reset per-user limits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn delete_promo_code(&self, code: &str) -> Result<()>;
+
+    /// Count how many times a user has redeemed a promo code.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn count_promo_redemptions(&self, code: &str, user_id: &UserId) -> Result<i64>;
+
+    // =========================================================================
+    // Gift Card Operations
+    // =========================================================================
+
+    /// Store a newly issued gift card.
+    ///
+    /// Idempotent on `stripe_session_id`: if a card was already issued for
+    /// the same checkout session, nothing is written (not even `outbox`) and
+    /// that card is returned instead. Otherwise `card` is stored, the
+    /// `outbox` messages are written in the same atomic write, and `card` is
+    /// returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails or the card's code
+    /// is already taken.
+    async fn create_gift_card(&self, card: &GiftCard, outbox: &[OutboxMessage])
+        -> Result<GiftCard>;
+
+    /// Get a gift card by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_gift_card(&self, card_id: &GiftCardId) -> Result<Option<GiftCard>>;
+
+    /// Get a gift card by its normalized redemption code.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_gift_card_by_code(&self, code: &str) -> Result<Option<GiftCard>>;
+
+    /// List the gift cards a user has bought, newest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_gift_cards_by_purchaser(&self, user_id: &UserId) -> Result<Vec<GiftCard>>;
+
+    /// Void an issued gift card so it can no longer be redeemed.
+    ///
+    /// Returns the voided card.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::NotFound` if the card doesn't exist.
+    /// - `StoreError::InvalidState` if the card was already redeemed or
+    ///   voided.
+    async fn void_gift_card(&self, card_id: &GiftCardId, reason: &str) -> Result<GiftCard>;
+
+    // =========================================================================
+    // Agent Budget Operations
+    // =========================================================================
+
+    /// Create or replace an agent's budget for one period.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn put_agent_budget(&self, budget: &AgentBudget) -> Result<()>;
+
+    /// List an agent's budgets.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_agent_budgets(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+    ) -> Result<Vec<AgentBudget>>;
+
+    /// Remove an agent's budget for one period.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn delete_agent_budget(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+        period: BudgetPeriod,
+    ) -> Result<()>;
+
+    /// Get an agent's running spend totals, if it has spent anything.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_agent_spend(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+    ) -> Result<Option<AgentSpend>>;
+
+    /// List spend totals for every agent that has spent from a user's balance.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_agent_spend(&self, user_id: &UserId) -> Result<Vec<AgentSpend>>;
+
+    // =========================================================================
+    // Ledger Operations
+    // =========================================================================
+
+    /// List the entries posted to a ledger account, ordered by time (newest
+    /// first).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_ledger_entries(
+        &self,
+        account: &LedgerAccount,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<LedgerEntry>>;
+
+    /// Sum of all entries posted to a ledger account.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn ledger_balance(&self, account: &LedgerAccount) -> Result<i64>;
+
+    /// Check that every account's and organization's balance equals the sum
+    /// of its ledger entries, and that all entries sum to zero.
+    ///
+    /// Drift is reported, not repaired.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn verify_ledger(&self) -> Result<LedgerReport>;
+
+    // =========================================================================
+    // Outbox Operations
+    // =========================================================================
+
+    /// Insert or update an outbox message.
+    ///
+    /// Side effects of balance changes should be written together with the
+    /// change instead (see [`Self::process_usage`]); this is for side effects
+    /// with no balance change, and for recording delivery failures and
+    /// replays.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn put_outbox_message(&self, message: &OutboxMessage) -> Result<()>;
+
+    /// Get an outbox message by ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_outbox_message(&self, id: &OutboxId) -> Result<Option<OutboxMessage>>;
+
+    /// List outbox messages, oldest first, optionally only those in `status`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_outbox_messages(
+        &self,
+        status: Option<OutboxStatus>,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>>;
+
+    /// Claim up to `limit` pending messages that are due at `now`, oldest
+    /// first.
+    ///
+    /// Claimed messages have `next_attempt_at` pushed to `now + lease`, so
+    /// no other dispatcher picks them up while they are being delivered. A
+    /// dispatcher that dies mid-delivery leaves them to be claimed again
+    /// once the lease runs out.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn claim_outbox_messages(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+        lease: chrono::Duration,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>>;
+
+    /// Delete a delivered outbox message. Deleting a missing message is a
+    /// no-op.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn delete_outbox_message(&self, id: &OutboxId) -> Result<()>;
+
+    // =========================================================================
+    // Pricing Catalog
+    // =========================================================================
+
+    /// Save a new pricing catalog version. The most recently saved version
+    /// is the one [`Self::get_latest_pricing_catalog`] returns.
+    ///
+    /// Versions are immutable: a changed catalog needs a new version.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::InvalidState` if the version has already been saved.
+    /// - Database errors if the operation fails.
+    async fn put_pricing_catalog(&self, catalog: &PricingCatalog) -> Result<()>;
+
+    /// Get the most recently saved pricing catalog.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails or the stored
+    /// catalog does not parse.
+    async fn get_latest_pricing_catalog(&self) -> Result<Option<PricingCatalog>>;
+
+    // =========================================================================
+    // Storage Meters
+    // =========================================================================
+
+    /// Get the last reading of a storage resource.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn get_storage_meter(
+        &self,
+        user_id: &UserId,
+        resource_id: &str,
+    ) -> Result<Option<StorageMeter>>;
+
+    /// Write a storage meter, but only if the stored version is still
+    /// `expected_version`.
+    ///
+    /// Pass the `version` of the meter as read; an expected version of 0
+    /// inserts a new meter. Returns the new version.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::VersionConflict` if the meter was written since it was
+    ///   read or was created concurrently.
+    /// - Database errors if the operation fails.
+    async fn put_storage_meter_if_version(
+        &self,
+        meter: &StorageMeter,
+        expected_version: i64,
+    ) -> Result<i64>;
+
+    // =========================================================================
+    // Export
+    // =========================================================================
+    //
+    // These page through a whole store in key order, for copying it to
+    // another backend. Pass the key of the last item of a page as `after`
+    // to get the next page; an empty page means the end.
+
+    /// List accounts in `user_id` order, starting after `after`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_accounts(&self, after: Option<&UserId>, limit: usize) -> Result<Vec<Account>>;
+
+    /// List transactions of all users in ID (creation) order, starting after
+    /// `after`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_transactions(
+        &self,
+        after: Option<&TransactionId>,
+        limit: usize,
+    ) -> Result<Vec<CreditTransaction>>;
+
+    /// List usage events in `event_id` order, starting after `after`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_usage_events(&self, after: Option<&str>, limit: usize)
+        -> Result<Vec<UsageEvent>>;
+
+    /// List processed webhook events in `event_id` order, starting after
+    /// `after`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn list_webhook_events(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ProcessedWebhook>>;
+
+    /// Count the data the listings above don't cover, per kind: open
+    /// credit lots (`credit_lots`), `org_memberships`, `agent_budgets`,
+    /// `promo_codes`, issued gift cards (`gift_cards`), given grants
+    /// (`credit_grants`) and `storage_meters`.
+    ///
+    /// A copy made from the listings alone leaves these behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database operation fails.
+    async fn count_unlisted(&self) -> Result<Vec<(&'static str, u64)>>;
 
     // =========================================================================
     // Compound Operations
@@ -221,26 +897,227 @@ pub trait Store: Send + Sync {
 
     /// Process a usage event: deduct credits and record transaction atomically.
     ///
+    /// Active reservations are held back: the charge must fit within the
+    /// balance minus all active holds. The charge draws down the user's
+    /// credit lots in consumption order.
+    ///
+    /// An account with a credit limit may go below zero by up to that limit.
+    /// Reaching the limit sets `overdraft_locked`, which blocks usage until
+    /// credits bring the balance back above zero.
+    ///
+    /// If the event has an agent, the charge must also fit within each of
+    /// the agent's budgets, and is added to the agent's spend totals.
+    ///
+    /// The `outbox` messages are written in the same atomic write, so the
+    /// charge's side effects are recorded if and only if the charge is.
+    ///
     /// Returns the new balance after deduction.
     ///
     /// # Errors
     ///
     /// - `StoreError::NotFound` if the account doesn't exist.
     /// - `StoreError::InsufficientCredits` if balance is too low.
+    /// - `StoreError::BudgetExceeded` if the event's agent is over a budget.
+    /// - `StoreError::DuplicateEvent` if the event was already processed.
+    async fn process_usage(
+        &self,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64>;
+
+    /// Process a usage event against an organization's shared pool.
+    ///
+    /// The event's `user_id` is the acting member, who must belong to the
+    /// organization. The charge must fit within the pool balance and within
+    /// the member's monthly spend cap, if set. The transaction should carry
+    /// the org ID (see `CreditTransaction::with_org`). As with personal
+    /// usage, the event's agent must be within its budgets, and the charge
+    /// counts toward its spend. `outbox` is written as in
+    /// [`Self::process_usage`].
+    ///
+    /// Returns the organization's new balance after deduction.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::NotFound` if the organization or membership doesn't exist.
+    /// - `StoreError::SpendCapExceeded` if the member's monthly cap is reached.
+    /// - `StoreError::InsufficientCredits` if the pool balance is too low.
+    /// - `StoreError::BudgetExceeded` if the event's agent is over a budget.
     /// - `StoreError::DuplicateEvent` if the event was already processed.
-    fn process_usage(&self, event: &UsageEvent, transaction: &CreditTransaction) -> Result<i64>;
+    async fn process_org_usage(
+        &self,
+        org_id: &OrgId,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64>;
+
+    /// Add credits to an organization's pool and record the transaction
+    /// atomically.
+    ///
+    /// Returns the organization's new balance.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::NotFound` if the organization doesn't exist.
+    async fn add_org_credits(
+        &self,
+        org_id: &OrgId,
+        amount_cents: i64,
+        transaction: &CreditTransaction,
+    ) -> Result<i64>;
 
     /// Add credits to an account and record transaction atomically.
     ///
+    /// A positive amount opens a credit lot whose source is the
+    /// transaction's type, with that type's default expiry. Credits that pay
+    /// off an overdraft are not kept in the lot. A negative amount draws down
+    /// existing lots like usage does.
+    ///
+    /// The `outbox` messages are written in the same atomic write.
+    ///
     /// Returns the new balance after addition.
     ///
     /// # Errors
     ///
     /// - `StoreError::NotFound` if the account doesn't exist.
-    fn add_credits(
+    async fn add_credits(
         &self,
         user_id: &UserId,
         amount_cents: i64,
         transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64>;
+
+    /// Give a free credit grant at most once per `grant_key`.
+    ///
+    /// The key names the grant, e.g. `daily:2026-10-16` or
+    /// `monthly:<period start>` (see `z_billing_core::grant`). It is recorded
+    /// in the same atomic write as the credit, which is applied like
+    /// [`Store::add_credits`] with `transaction.amount_cents`, so concurrent
+    /// calls with the same key credit the account only once.
+    ///
+    /// Returns the new balance, or `None` if the key was already granted.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::NotFound` if the account doesn't exist.
+    async fn grant_once(
+        &self,
+        user_id: &UserId,
+        grant_key: &str,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<Option<i64>>;
+
+    /// Refund all or part of a usage event's charge and record the reversal
+    /// transaction atomically.
+    ///
+    /// The refund is credited to the balance the event was charged against:
+    /// the organization pool if the transaction carries an org ID, otherwise
+    /// the user's account, where it opens a new credit lot. Lifetime usage is
+    /// reduced by the refunded amount.
+    ///
+    /// Reversals are idempotent on `(event_id, reversal_id)`: if one already
+    /// exists it is returned unchanged and nothing is credited.
+    ///
+    /// The `outbox` messages are written in the same atomic write as the
+    /// refund, and not at all if nothing is credited.
+    ///
+    /// Returns the recorded reversal.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::NotFound` if the usage event or the account to credit
+    ///   doesn't exist.
+    /// - `StoreError::ReversalExceedsCharge` if the event's reversals would
+    ///   total more than it charged.
+    async fn reverse_usage(
+        &self,
+        reversal: &UsageReversal,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<UsageReversal>;
+
+    /// Move credits from a user's personal balance to another user or an
+    /// organization pool, recording both transactions atomically.
+    ///
+    /// `debit` is the sender's `TransferOut` transaction and `credit` the
+    /// recipient's `TransferIn` transaction. Only purchased credits (and
+    /// credits the sender was themselves transferred) can be sent: they are
+    /// drawn from the sender's transferable lots, and a user recipient gets
+    /// them as a new transferable lot. The sender's credit line and reserved
+    /// credits are never available for a transfer.
+    ///
+    /// Transfers are idempotent on `(from_user_id, idempotency_key)`: if one
+    /// already exists it is returned unchanged and nothing is moved.
+    ///
+    /// The `outbox` messages are written in the same atomic write as the
+    /// transfer, and not at all if nothing is moved.
+    ///
+    /// Returns the recorded transfer.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::NotFound` if the sender or recipient doesn't exist.
+    /// - `StoreError::InsufficientCredits` if the sender's unreserved
+    ///   balance doesn't cover the amount.
+    /// - `StoreError::TransferExceedsPurchased` if the sender holds fewer
+    ///   transferable credits than the amount.
+    async fn transfer_credits(
+        &self,
+        transfer: &CreditTransfer,
+        debit: &CreditTransaction,
+        credit: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<CreditTransfer>;
+
+    /// Redeem a promo code, crediting the user and recording the redemption
+    /// and its `Bonus` transaction atomically.
+    ///
+    /// The code's rules are checked against its current redemption count
+    /// and the user's earlier redemptions while concurrent redemptions of
+    /// the same code are held off, so limits cannot be overrun by racing
+    /// requests. The credits open a bonus lot.
+    ///
+    /// The `outbox` messages are written in the same atomic write.
+    ///
+    /// Returns the user's new balance.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::NotFound` if the code or the account doesn't exist.
+    /// - `StoreError::PromoRejected` if the code cannot be redeemed by this
+    ///   user right now.
+    async fn redeem_promo_code(
+        &self,
+        redemption: &PromoRedemption,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64>;
+
+    /// Redeem an issued gift card into `transaction.user_id`'s account,
+    /// marking the card redeemed and recording its `GiftCard` transaction
+    /// atomically.
+    ///
+    /// The card's status is checked while concurrent redemptions and voids
+    /// of it are held off, so a card is only ever credited once. The credits
+    /// open a lot like purchased credits.
+    ///
+    /// The `outbox` messages are written in the same atomic write.
+    ///
+    /// Returns the user's new balance.
+    ///
+    /// # Errors
+    ///
+    /// - `StoreError::NotFound` if the card or the account doesn't exist.
+    /// - `StoreError::InvalidState` if the card was already redeemed or
+    ///   voided.
+    async fn redeem_gift_card(
+        &self,
+        card_id: &GiftCardId,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
     ) -> Result<i64>;
 }
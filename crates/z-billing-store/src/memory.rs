@@ -0,0 +1,1894 @@
+//! In-memory storage implementation.
+//!
+//! This module provides the `MemoryStore` implementation of the `Store` trait,
+//! for tests and for embedding z-billing without a database. Nothing is
+//! persisted.
+//!
+//! All tables sit behind a single lock, and every operation makes all of its
+//! checks before it changes anything. Compound operations therefore apply in
+//! full or not at all, like a `RocksDB` write batch or a `PostgreSQL`
+//! transaction, and concurrent operations never interleave. Operations never
+//! await, so the lock is never held across an await point.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::{DateTime, Utc};
+
+use z_billing_core::{
+    budget, ledger, lot, org, Account, AgentBudget, AgentId, AgentSpend, BudgetPeriod, CreditLot,
+    CreditTransaction, CreditTransfer, DailyUsage, GiftCard, GiftCardId, GiftCardStatus,
+    LedgerAccount, LedgerDrift, LedgerEntry, LedgerReport, LotDraw, OrgId, OrgMembership,
+    Organization, OutboxId, OutboxMessage, OutboxStatus, PricingCatalog, ProcessedWebhook,
+    PromoCode, PromoRedemption, Reservation, ReservationId, ReservationStatus, StorageMeter,
+    SystemAccount, TransactionId, TransactionQuery, TransactionType, UsageEvent, UsageReversal,
+    UsageSummaryQuery, UsageSummaryRow, UserId,
+};
+
+use crate::error::{Result, StoreError};
+use crate::Store;
+
+/// In-memory storage implementation.
+#[derive(Default)]
+pub struct MemoryStore {
+    tables: Mutex<Tables>,
+}
+
+/// Everything the store holds.
+#[derive(Default)]
+struct Tables {
+    accounts: HashMap<UserId, Account>,
+    organizations: HashMap<OrgId, Organization>,
+    org_members: HashMap<(OrgId, UserId), OrgMembership>,
+    transactions: HashMap<TransactionId, CreditTransaction>,
+    /// Transaction IDs per user, in insertion order.
+    transactions_by_user: HashMap<UserId, Vec<TransactionId>>,
+    usage_events: HashMap<String, UsageEvent>,
+    /// What each usage event took from the user's lots, keyed by event ID.
+    usage_lot_draws: HashMap<String, Vec<LotDraw>>,
+    /// Reversals keyed by `(event_id, reversal_id)`.
+    usage_reversals: HashMap<(String, String), UsageReversal>,
+    /// Processed webhook sources keyed by event ID.
+    webhook_events: HashMap<String, String>,
+    reservations: HashMap<ReservationId, Reservation>,
+    /// Open credit lots per user.
+    credit_lots: HashMap<UserId, Vec<CreditLot>>,
+    /// Transfers keyed by `(from_user_id, idempotency_key)`.
+    credit_transfers: HashMap<(UserId, String), CreditTransfer>,
+    promo_codes: BTreeMap<String, PromoCode>,
+    /// Redemption counts keyed by `(code, user_id)`.
+    promo_redemptions: HashMap<(String, UserId), i64>,
+    gift_cards: HashMap<GiftCardId, GiftCard>,
+    /// Gift card IDs by redemption code.
+    gift_card_codes: HashMap<String, GiftCardId>,
+    /// Budgets per `(user_id, agent_id)`, at most one per period.
+    agent_budgets: HashMap<(UserId, AgentId), Vec<AgentBudget>>,
+    agent_spend: HashMap<(UserId, AgentId), AgentSpend>,
+    ledger_entries: Vec<LedgerEntry>,
+    outbox: HashMap<OutboxId, OutboxMessage>,
+    /// Free grants already given, keyed by `(user_id, grant_key)`.
+    grants: HashSet<(UserId, String)>,
+    /// Saved pricing catalogs, oldest first.
+    pricing_catalogs: Vec<PricingCatalog>,
+    /// Storage meters keyed by `(user_id, resource_id)`.
+    storage_meters: HashMap<(UserId, String), StorageMeter>,
+}
+
+/// The wallet a transfer credits.
+enum Recipient {
+    User(Box<Account>),
+    Org(Organization),
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the table lock.
+    fn tables(&self) -> Result<MutexGuard<'_, Tables>> {
+        self.tables
+            .lock()
+            .map_err(|e| StoreError::Database(format!("memory store lock poisoned: {e}")))
+    }
+}
+
+impl Tables {
+    /// Queue outbox messages written alongside a compound operation.
+    fn write_outbox(&mut self, messages: &[OutboxMessage]) {
+        for message in messages {
+            self.outbox.insert(message.id, message.clone());
+        }
+    }
+
+    /// Write an account's settings, keeping the stored balance, lifetime
+    /// counters and overdraft lock, and bump its version. Returns the new
+    /// version.
+    fn write_account_settings(&mut self, account: &Account) -> i64 {
+        let mut account = account.clone();
+        if let Some(existing) = self.accounts.get(&account.user_id) {
+            // The balance only moves through operations that post to the
+            // ledger, and the overdraft lock follows it unless the credit
+            // limit changed
+            account.balance_cents = existing.balance_cents;
+            account.lifetime_purchased_cents = existing.lifetime_purchased_cents;
+            account.lifetime_granted_cents = existing.lifetime_granted_cents;
+            account.lifetime_used_cents = existing.lifetime_used_cents;
+            account.overdraft_locked = existing.overdraft_locked;
+            if account.credit_limit_cents != existing.credit_limit_cents {
+                account.overdraft_locked = false;
+                account.update_overdraft_lock();
+            }
+            account.version = existing.version + 1;
+        } else {
+            account.balance_cents = 0;
+            account.lifetime_purchased_cents = 0;
+            account.lifetime_granted_cents = 0;
+            account.lifetime_used_cents = 0;
+            account.overdraft_locked = false;
+            account.version = 1;
+        }
+
+        let version = account.version;
+        self.accounts.insert(account.user_id, account);
+        version
+    }
+
+    /// Fail with a version conflict unless the stored account is at
+    /// `expected` (0 for an account that must not exist yet).
+    fn check_account_version(&self, user_id: &UserId, expected: i64) -> Result<()> {
+        let actual = self.accounts.get(user_id).map_or(0, |a| a.version);
+        if actual != expected {
+            return Err(StoreError::VersionConflict {
+                entity: "Account",
+                id: user_id.to_string(),
+                expected,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// Get a copy of an account that must exist.
+    fn account(&self, user_id: &UserId) -> Result<Account> {
+        self.accounts
+            .get(user_id)
+            .cloned()
+            .ok_or(StoreError::NotFound {
+                entity: "Account",
+                id: user_id.to_string(),
+            })
+    }
+
+    /// Get a copy of an organization that must exist.
+    fn organization(&self, org_id: &OrgId) -> Result<Organization> {
+        self.organizations
+            .get(org_id)
+            .cloned()
+            .ok_or(StoreError::NotFound {
+                entity: "Organization",
+                id: org_id.to_string(),
+            })
+    }
+
+    /// A user's transactions, newest first.
+    fn user_transactions(&self, user_id: &UserId) -> Vec<&CreditTransaction> {
+        let mut transactions: Vec<_> = self
+            .transactions_by_user
+            .get(user_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.transactions.get(id))
+            .collect();
+        transactions.sort_by(|a, b| b.id.as_ulid().cmp(a.id.as_ulid()));
+        transactions
+    }
+
+    /// Store a transaction and index it by user.
+    fn put_transaction(&mut self, transaction: &CreditTransaction) {
+        if self
+            .transactions
+            .insert(transaction.id, transaction.clone())
+            .is_none()
+        {
+            self.transactions_by_user
+                .entry(transaction.user_id)
+                .or_default()
+                .push(transaction.id);
+        }
+    }
+
+    /// Store a transaction and its ledger postings. `amount_cents` is the
+    /// change in `wallet`'s balance.
+    fn write_transaction(
+        &mut self,
+        wallet: LedgerAccount,
+        transaction: &CreditTransaction,
+        amount_cents: i64,
+    ) {
+        self.put_transaction(transaction);
+        self.ledger_entries
+            .extend(ledger::postings(wallet, transaction, amount_cents));
+    }
+
+    /// Sum the amounts of a user's holds that are active at `now`.
+    fn reserved_cents(&self, user_id: &UserId, now: DateTime<Utc>) -> i64 {
+        self.reservations
+            .values()
+            .filter(|r| r.user_id == *user_id && r.is_active_at(now))
+            .map(|r| r.amount_cents)
+            .sum()
+    }
+
+    /// A user's open lots, in consumption order.
+    fn list_credit_lots(&self, user_id: &UserId) -> Vec<CreditLot> {
+        let mut lots = self.credit_lots.get(user_id).cloned().unwrap_or_default();
+        lot::sort_for_consumption(&mut lots);
+        lots
+    }
+
+    /// Write a credit lot, dropping it once it has nothing left.
+    fn write_credit_lot(&mut self, lot: &CreditLot) {
+        let lots = self.credit_lots.entry(lot.user_id).or_default();
+        lots.retain(|existing| existing.id != lot.id);
+        if lot.remaining_cents > 0 {
+            lots.push(lot.clone());
+        }
+    }
+
+    /// Draw `amount_cents` down from the user's open lots.
+    fn draw_down_lots(&mut self, user_id: &UserId, amount_cents: i64) {
+        let mut lots = self.list_credit_lots(user_id);
+        let touched = lot::draw_down(&mut lots, amount_cents);
+        for lot in &lots[..touched] {
+            self.write_credit_lot(lot);
+        }
+    }
+
+    /// Draw a usage charge down from the user's open lots and remember what
+    /// it took, for reversals.
+    fn draw_down_usage_lots(&mut self, event: &UsageEvent) {
+        let mut lots = self.list_credit_lots(&event.user_id);
+        let (touched, draws) = lot::draw_down_recorded(&mut lots, event.cost_cents);
+        for lot in &lots[..touched] {
+            self.write_credit_lot(lot);
+        }
+        self.usage_lot_draws.insert(event.event_id.clone(), draws);
+    }
+
+    /// Draw `amount_cents` down from the user's transferable lots.
+    fn draw_down_transferable_lots(&mut self, user_id: &UserId, amount_cents: i64) {
+        let mut lots = self.list_credit_lots(user_id);
+        let touched = lot::draw_down_transferable(&mut lots, amount_cents);
+        for lot in &lots[..touched] {
+            self.write_credit_lot(lot);
+        }
+    }
+
+    /// Open a lot for credits just added to an account whose balance is now
+    /// `balance_cents`.
+    fn open_credit_lot(
+        &mut self,
+        user_id: UserId,
+        transaction: &CreditTransaction,
+        amount_cents: i64,
+        expires_at: Option<DateTime<Utc>>,
+        balance_cents: i64,
+    ) {
+        let mut lot = CreditLot::new(
+            user_id,
+            transaction.transaction_type.clone(),
+            amount_cents,
+            expires_at,
+        );
+        lot.cap_to_balance(balance_cents);
+        self.write_credit_lot(&lot);
+    }
+
+    /// Reject `event` if it would push its agent over any of its budgets.
+    fn check_agent_budgets(&self, event: &UsageEvent, now: DateTime<Utc>) -> Result<()> {
+        let Some(agent_id) = event.agent_id else {
+            return Ok(());
+        };
+        let Some(budgets) = self.agent_budgets.get(&(event.user_id, agent_id)) else {
+            return Ok(());
+        };
+
+        let totals = self
+            .agent_spend
+            .get(&(event.user_id, agent_id))
+            .cloned()
+            .unwrap_or_else(|| AgentSpend::new(event.user_id, agent_id, now));
+        if let Some((budget, spent)) =
+            budget::exceeded_budget(budgets, &totals, event.cost_cents, now)
+        {
+            return Err(StoreError::BudgetExceeded {
+                agent_id,
+                period: budget.period,
+                limit: budget.limit_cents,
+                spent,
+                required: event.cost_cents,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Reject `event` if it was already processed.
+    fn check_new_event(&self, event: &UsageEvent) -> Result<()> {
+        if self.usage_events.contains_key(&event.event_id) {
+            return Err(StoreError::DuplicateEvent {
+                event_id: event.event_id.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Debit `event.cost_cents` from `account` and record the account,
+    /// transaction, usage event, lot draw-down and agent spend.
+    ///
+    /// The caller performs all checks first.
+    fn apply_usage(
+        &mut self,
+        mut account: Account,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+    ) -> i64 {
+        account.balance_cents -= event.cost_cents;
+        account.update_overdraft_lock();
+        account.lifetime_used_cents += event.cost_cents;
+        account.updated_at = Utc::now();
+
+        let balance = account.balance_cents;
+        let now = account.updated_at;
+        self.accounts.insert(event.user_id, account);
+        self.write_transaction(
+            LedgerAccount::User(event.user_id),
+            transaction,
+            -event.cost_cents,
+        );
+        self.usage_events
+            .insert(event.event_id.clone(), event.clone());
+        self.draw_down_usage_lots(event);
+        self.record_agent_spend(event, now);
+
+        balance
+    }
+
+    /// Add `event.cost_cents` to its agent's spend totals, if it has an agent.
+    fn record_agent_spend(&mut self, event: &UsageEvent, now: DateTime<Utc>) {
+        if let Some(agent_id) = event.agent_id {
+            self.agent_spend
+                .entry((event.user_id, agent_id))
+                .or_insert_with(|| AgentSpend::new(event.user_id, agent_id, now))
+                .record(event.cost_cents, now);
+        }
+    }
+
+    /// Add credits to an account: update the balance, post the transaction
+    /// and open a credit lot (or draw lots down for a negative amount).
+    /// Returns the new balance.
+    fn credit_account(
+        &mut self,
+        user_id: &UserId,
+        amount_cents: i64,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let mut account = self.account(user_id)?;
+
+        account.balance_cents += amount_cents;
+        account.update_overdraft_lock();
+        account.updated_at = Utc::now();
+
+        // Track lifetime stats based on transaction type
+        match transaction.transaction_type {
+            TransactionType::Purchase | TransactionType::AutoRefill => {
+                account.lifetime_purchased_cents += amount_cents;
+            }
+            TransactionType::SubscriptionGrant | TransactionType::Bonus => {
+                account.lifetime_granted_cents += amount_cents;
+            }
+            _ => {}
+        }
+
+        let balance = account.balance_cents;
+        self.accounts.insert(*user_id, account);
+        self.write_transaction(LedgerAccount::User(*user_id), transaction, amount_cents);
+
+        // Track where the credits came from, or spend lots for a deduction
+        if amount_cents > 0 {
+            self.open_credit_lot(
+                *user_id,
+                transaction,
+                amount_cents,
+                lot::default_expiry(&transaction.transaction_type, transaction.created_at),
+                balance,
+            );
+        } else {
+            self.draw_down_lots(user_id, -amount_cents);
+        }
+        self.write_outbox(outbox);
+
+        Ok(balance)
+    }
+
+    /// Get a copy of a gift card that must exist and still be issued.
+    fn issued_gift_card(&self, card_id: &GiftCardId) -> Result<GiftCard> {
+        let card = self
+            .gift_cards
+            .get(card_id)
+            .cloned()
+            .ok_or_else(|| StoreError::NotFound {
+                entity: "GiftCard",
+                id: card_id.to_string(),
+            })?;
+        if card.status != GiftCardStatus::Issued {
+            return Err(StoreError::InvalidState {
+                entity: "GiftCard",
+                id: card_id.to_string(),
+                state: card.status.as_str().to_string(),
+            });
+        }
+        Ok(card)
+    }
+}
+
+#[async_trait::async_trait]
+impl Store for MemoryStore {
+    // =========================================================================
+    // Account Operations
+    // =========================================================================
+
+    async fn put_account(&self, account: &Account) -> Result<()> {
+        self.tables()?.write_account_settings(account);
+        Ok(())
+    }
+
+    async fn put_account_if_version(
+        &self,
+        account: &Account,
+        expected_version: i64,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let mut tables = self.tables()?;
+        tables.check_account_version(&account.user_id, expected_version)?;
+        tables.write_outbox(outbox);
+        Ok(tables.write_account_settings(account))
+    }
+
+    async fn create_account(&self, account: &Account) -> Result<()> {
+        let mut tables = self.tables()?;
+        tables.check_account_version(&account.user_id, 0)?;
+
+        if account.balance_cents != 0 {
+            tables.ledger_entries.extend(ledger::transfer(
+                LedgerAccount::User(account.user_id),
+                SystemAccount::OpeningBalances,
+                account.balance_cents,
+                None,
+                Utc::now(),
+            ));
+        }
+        let mut account = account.clone();
+        account.version = 1;
+        tables.accounts.insert(account.user_id, account);
+
+        Ok(())
+    }
+
+    async fn get_account(&self, user_id: &UserId) -> Result<Option<Account>> {
+        Ok(self.tables()?.accounts.get(user_id).cloned())
+    }
+
+    async fn find_account_by_stripe_customer(&self, customer_id: &str) -> Result<Option<Account>> {
+        Ok(self
+            .tables()?
+            .accounts
+            .values()
+            .find(|account| account.stripe_customer_id.as_deref() == Some(customer_id))
+            .cloned())
+    }
+
+    async fn find_account_by_lago_customer(&self, customer_id: &str) -> Result<Option<Account>> {
+        Ok(self
+            .tables()?
+            .accounts
+            .values()
+            .find(|account| account.lago_customer_id.as_deref() == Some(customer_id))
+            .cloned())
+    }
+
+    async fn delete_account(&self, user_id: &UserId) -> Result<()> {
+        let mut tables = self.tables()?;
+        tables.account(user_id)?;
+
+        // Credits that ever moved stay on the books
+        let wallet = LedgerAccount::User(*user_id);
+        if tables.transactions_by_user.contains_key(user_id)
+            || tables.ledger_entries.iter().any(|e| e.account == wallet)
+        {
+            return Err(StoreError::InvalidState {
+                entity: "Account",
+                id: user_id.to_string(),
+                state: "still on the ledger".to_string(),
+            });
+        }
+
+        // Budgets and holds go with the account
+        tables
+            .agent_budgets
+            .retain(|(owner, _), _| owner != user_id);
+        tables.reservations.retain(|_, r| r.user_id != *user_id);
+        tables.accounts.remove(user_id);
+        Ok(())
+    }
+
+    #[allow(deprecated)] // Implementing deprecated trait method
+    async fn update_balance(&self, user_id: &UserId, delta_cents: i64) -> Result<i64> {
+        let mut tables = self.tables()?;
+        let mut account = tables.account(user_id)?;
+
+        account.balance_cents += delta_cents;
+        account.update_overdraft_lock();
+        account.updated_at = Utc::now();
+        if delta_cents < 0 {
+            account.lifetime_used_cents += delta_cents.abs();
+        }
+
+        tables.ledger_entries.extend(ledger::transfer(
+            LedgerAccount::User(*user_id),
+            SystemAccount::Adjustments,
+            delta_cents,
+            None,
+            account.updated_at,
+        ));
+        let balance = account.balance_cents;
+        tables.accounts.insert(*user_id, account);
+
+        Ok(balance)
+    }
+
+    // =========================================================================
+    // Organization Operations
+    // =========================================================================
+
+    async fn put_organization(&self, org: &Organization) -> Result<()> {
+        let mut tables = self.tables()?;
+
+        let mut org = org.clone();
+        if let Some(existing) = tables.organizations.get(&org.id) {
+            org.balance_cents = existing.balance_cents;
+        } else if org.balance_cents != 0 {
+            tables.ledger_entries.extend(ledger::transfer(
+                LedgerAccount::Org(org.id),
+                SystemAccount::OpeningBalances,
+                org.balance_cents,
+                None,
+                Utc::now(),
+            ));
+        }
+        tables.organizations.insert(org.id, org);
+
+        Ok(())
+    }
+
+    async fn get_organization(&self, org_id: &OrgId) -> Result<Option<Organization>> {
+        Ok(self.tables()?.organizations.get(org_id).cloned())
+    }
+
+    async fn put_org_membership(&self, membership: &OrgMembership) -> Result<()> {
+        self.tables()?
+            .org_members
+            .insert((membership.org_id, membership.user_id), membership.clone());
+        Ok(())
+    }
+
+    async fn get_org_membership(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+    ) -> Result<Option<OrgMembership>> {
+        Ok(self
+            .tables()?
+            .org_members
+            .get(&(*org_id, *user_id))
+            .cloned())
+    }
+
+    async fn list_org_members(&self, org_id: &OrgId) -> Result<Vec<OrgMembership>> {
+        let tables = self.tables()?;
+        let mut members: Vec<_> = tables
+            .org_members
+            .values()
+            .filter(|m| m.org_id == *org_id)
+            .cloned()
+            .collect();
+        members.sort_by(|a, b| a.user_id.as_bytes().cmp(b.user_id.as_bytes()));
+        Ok(members)
+    }
+
+    async fn delete_org_membership(&self, org_id: &OrgId, user_id: &UserId) -> Result<()> {
+        self.tables()?.org_members.remove(&(*org_id, *user_id));
+        Ok(())
+    }
+
+    async fn org_member_spend_since(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        since: DateTime<Utc>,
+    ) -> Result<i64> {
+        Ok(self
+            .tables()?
+            .user_transactions(user_id)
+            .into_iter()
+            .filter(|tx| {
+                tx.created_at >= since
+                    && tx.org_id.as_ref() == Some(org_id)
+                    && tx.transaction_type == TransactionType::Usage
+            })
+            .map(|tx| -tx.amount_cents)
+            .sum())
+    }
+
+    // =========================================================================
+    // Transaction Operations
+    // =========================================================================
+
+    async fn put_transaction(&self, transaction: &CreditTransaction) -> Result<()> {
+        self.tables()?.put_transaction(transaction);
+        Ok(())
+    }
+
+    async fn get_transaction(
+        &self,
+        transaction_id: &TransactionId,
+    ) -> Result<Option<CreditTransaction>> {
+        Ok(self.tables()?.transactions.get(transaction_id).cloned())
+    }
+
+    async fn list_transactions_by_user(
+        &self,
+        user_id: &UserId,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<CreditTransaction>> {
+        Ok(self
+            .tables()?
+            .user_transactions(user_id)
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn query_transactions(
+        &self,
+        user_id: &UserId,
+        query: &TransactionQuery,
+    ) -> Result<Vec<CreditTransaction>> {
+        Ok(self
+            .tables()?
+            .user_transactions(user_id)
+            .into_iter()
+            .filter(|tx| query.matches(tx))
+            .take(query.limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn sum_monthly_allowance_since(
+        &self,
+        user_id: &UserId,
+        since: DateTime<Utc>,
+    ) -> Result<i64> {
+        Ok(self
+            .tables()?
+            .user_transactions(user_id)
+            .into_iter()
+            .filter(|tx| {
+                tx.created_at >= since && tx.transaction_type == TransactionType::MonthlyAllowance
+            })
+            .map(|tx| tx.amount_cents)
+            .sum())
+    }
+
+    async fn has_referral_bonus(&self, user_id: &UserId) -> Result<bool> {
+        Ok(self
+            .tables()?
+            .user_transactions(user_id)
+            .into_iter()
+            .any(|tx| tx.transaction_type == TransactionType::ReferralBonus))
+    }
+
+    // =========================================================================
+    // Usage Event Operations
+    // =========================================================================
+
+    async fn has_usage_event(&self, event_id: &str) -> Result<bool> {
+        Ok(self.tables()?.usage_events.contains_key(event_id))
+    }
+
+    async fn put_usage_event(&self, event: &UsageEvent) -> Result<()> {
+        self.tables()?
+            .usage_events
+            .insert(event.event_id.clone(), event.clone());
+        Ok(())
+    }
+
+    async fn get_usage_event(&self, event_id: &str) -> Result<Option<UsageEvent>> {
+        Ok(self.tables()?.usage_events.get(event_id).cloned())
+    }
+
+    async fn list_usage_reversals(&self, event_id: &str) -> Result<Vec<UsageReversal>> {
+        let tables = self.tables()?;
+        let mut reversals: Vec<_> = tables
+            .usage_reversals
+            .values()
+            .filter(|r| r.event_id == event_id)
+            .cloned()
+            .collect();
+        reversals.sort_by_key(|r| r.created_at);
+        Ok(reversals)
+    }
+
+    async fn aggregate_usage(&self, query: &UsageSummaryQuery) -> Result<Vec<UsageSummaryRow>> {
+        let tables = self.tables()?;
+        Ok(query.summarize(
+            tables
+                .usage_events
+                .values()
+                .filter(|event| query.includes(event))
+                .map(DailyUsage::from_event),
+        ))
+    }
+
+    // =========================================================================
+    // Webhook Idempotency
+    // =========================================================================
+
+    async fn has_webhook_event(&self, event_id: &str) -> Result<bool> {
+        Ok(self.tables()?.webhook_events.contains_key(event_id))
+    }
+
+    async fn record_webhook_event(&self, event_id: &str, source: &str) -> Result<()> {
+        self.tables()?
+            .webhook_events
+            .entry(event_id.to_string())
+            .or_insert_with(|| source.to_string());
+        Ok(())
+    }
+
+    // =========================================================================
+    // Reservation Operations
+    // =========================================================================
+
+    async fn create_reservation(&self, reservation: &Reservation) -> Result<i64> {
+        let mut tables = self.tables()?;
+        let account = tables.account(&reservation.user_id)?;
+
+        let available =
+            account.spendable_cents() - tables.reserved_cents(&reservation.user_id, Utc::now());
+        if available < reservation.amount_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: available,
+                required: reservation.amount_cents,
+            });
+        }
+
+        tables
+            .reservations
+            .insert(reservation.id, reservation.clone());
+
+        Ok(available - reservation.amount_cents)
+    }
+
+    async fn get_reservation(&self, reservation_id: &ReservationId) -> Result<Option<Reservation>> {
+        Ok(self.tables()?.reservations.get(reservation_id).cloned())
+    }
+
+    async fn reserved_cents(&self, user_id: &UserId) -> Result<i64> {
+        Ok(self.tables()?.reserved_cents(user_id, Utc::now()))
+    }
+
+    async fn settle_reservation(
+        &self,
+        reservation_id: &ReservationId,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let mut tables = self.tables()?;
+        let mut reservation =
+            tables
+                .reservations
+                .get(reservation_id)
+                .cloned()
+                .ok_or(StoreError::NotFound {
+                    entity: "Reservation",
+                    id: reservation_id.to_string(),
+                })?;
+
+        if matches!(
+            reservation.status,
+            ReservationStatus::Settled | ReservationStatus::Released
+        ) {
+            return Err(StoreError::InvalidState {
+                entity: "Reservation",
+                id: reservation_id.to_string(),
+                state: reservation.status.as_str().to_string(),
+            });
+        }
+
+        tables.check_new_event(event)?;
+        let account = tables.account(&event.user_id)?;
+
+        // This reservation's own hold is what pays for the charge, so only
+        // the user's other holds are subtracted from the balance.
+        let now = Utc::now();
+        let mut held_elsewhere = tables.reserved_cents(&event.user_id, now);
+        if reservation.is_active_at(now) {
+            held_elsewhere -= reservation.amount_cents;
+        }
+        let available = account.spendable_cents() - held_elsewhere;
+        if available < event.cost_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: available,
+                required: event.cost_cents,
+            });
+        }
+        tables.check_agent_budgets(event, now)?;
+
+        reservation.status = ReservationStatus::Settled;
+        reservation.transaction_id = Some(transaction.id);
+        reservation.updated_at = now;
+        tables.reservations.insert(reservation.id, reservation);
+        tables.write_outbox(outbox);
+
+        Ok(tables.apply_usage(account, event, transaction))
+    }
+
+    async fn release_reservation(&self, reservation_id: &ReservationId) -> Result<Reservation> {
+        let mut tables = self.tables()?;
+        let reservation =
+            tables
+                .reservations
+                .get_mut(reservation_id)
+                .ok_or(StoreError::NotFound {
+                    entity: "Reservation",
+                    id: reservation_id.to_string(),
+                })?;
+
+        match reservation.status {
+            ReservationStatus::Settled => {
+                return Err(StoreError::InvalidState {
+                    entity: "Reservation",
+                    id: reservation_id.to_string(),
+                    state: reservation.status.as_str().to_string(),
+                });
+            }
+            ReservationStatus::Released | ReservationStatus::Expired => {
+                return Ok(reservation.clone())
+            }
+            ReservationStatus::Active => {}
+        }
+
+        reservation.status = ReservationStatus::Released;
+        reservation.updated_at = Utc::now();
+
+        Ok(reservation.clone())
+    }
+
+    async fn expire_reservations(&self, now: DateTime<Utc>) -> Result<usize> {
+        let mut tables = self.tables()?;
+
+        let mut expired = 0;
+        for reservation in tables.reservations.values_mut() {
+            if reservation.status == ReservationStatus::Active && reservation.expires_at <= now {
+                reservation.status = ReservationStatus::Expired;
+                reservation.updated_at = now;
+                expired += 1;
+            }
+        }
+
+        Ok(expired)
+    }
+
+    // =========================================================================
+    // Credit Lot Operations
+    // =========================================================================
+
+    async fn list_credit_lots(&self, user_id: &UserId) -> Result<Vec<CreditLot>> {
+        Ok(self.tables()?.list_credit_lots(user_id))
+    }
+
+    async fn expire_credit_lots(&self, now: DateTime<Utc>) -> Result<usize> {
+        let mut tables = self.tables()?;
+
+        // Expire lots in the order they fell due
+        let mut due: Vec<CreditLot> = tables
+            .credit_lots
+            .values()
+            .flatten()
+            .filter(|lot| lot.is_expired_at(now))
+            .cloned()
+            .collect();
+        due.sort_by_key(|lot| lot.expires_at);
+
+        let mut expired = 0;
+        for mut lot in due {
+            let Some(account) = tables.accounts.get_mut(&lot.user_id) else {
+                // Orphaned lot; just clean it up.
+                lot.remaining_cents = 0;
+                tables.write_credit_lot(&lot);
+                continue;
+            };
+
+            account.balance_cents -= lot.remaining_cents;
+            account.updated_at = now;
+            let transaction = CreditTransaction::expiry(&lot, account.balance_cents);
+
+            tables.write_transaction(
+                LedgerAccount::User(lot.user_id),
+                &transaction,
+                -lot.remaining_cents,
+            );
+            lot.remaining_cents = 0;
+            tables.write_credit_lot(&lot);
+            expired += 1;
+        }
+
+        Ok(expired)
+    }
+
+    // =========================================================================
+    // Credit Transfer Operations
+    // =========================================================================
+
+    async fn get_credit_transfer(
+        &self,
+        from_user_id: &UserId,
+        idempotency_key: &str,
+    ) -> Result<Option<CreditTransfer>> {
+        Ok(self
+            .tables()?
+            .credit_transfers
+            .get(&(*from_user_id, idempotency_key.to_string()))
+            .cloned())
+    }
+
+    // =========================================================================
+    // Promo Code Operations
+    // =========================================================================
+
+    async fn put_promo_code(&self, promo: &PromoCode) -> Result<()> {
+        let mut tables = self.tables()?;
+
+        let mut promo = promo.clone();
+        if let Some(existing) = tables.promo_codes.get(&promo.code) {
+            promo.redemption_count = existing.redemption_count;
+            promo.created_at = existing.created_at;
+        }
+        tables.promo_codes.insert(promo.code.clone(), promo);
+
+        Ok(())
+    }
+
+    async fn get_promo_code(&self, code: &str) -> Result<Option<PromoCode>> {
+        Ok(self.tables()?.promo_codes.get(code).cloned())
+    }
+
+    async fn list_promo_codes(&self) -> Result<Vec<PromoCode>> {
+        Ok(self.tables()?.promo_codes.values().cloned().collect())
+    }
+
+    async fn delete_promo_code(&self, code: &str) -> Result<()> {
+        self.tables()?.promo_codes.remove(code);
+        Ok(())
+    }
+
+    async fn count_promo_redemptions(&self, code: &str, user_id: &UserId) -> Result<i64> {
+        Ok(self
+            .tables()?
+            .promo_redemptions
+            .get(&(code.to_string(), *user_id))
+            .copied()
+            .unwrap_or(0))
+    }
+
+    // =========================================================================
+    // Gift Card Operations
+    // =========================================================================
+
+    async fn create_gift_card(
+        &self,
+        card: &GiftCard,
+        outbox: &[OutboxMessage],
+    ) -> Result<GiftCard> {
+        let mut tables = self.tables()?;
+
+        if card.stripe_session_id.is_some() {
+            if let Some(existing) = tables
+                .gift_cards
+                .values()
+                .find(|c| c.stripe_session_id == card.stripe_session_id)
+            {
+                return Ok(existing.clone());
+            }
+        }
+
+        if tables.gift_card_codes.contains_key(&card.code) {
+            return Err(StoreError::Database(format!(
+                "gift card code already exists for card {}",
+                card.id
+            )));
+        }
+
+        tables.gift_card_codes.insert(card.code.clone(), card.id);
+        tables.gift_cards.insert(card.id, card.clone());
+        tables.write_outbox(outbox);
+
+        Ok(card.clone())
+    }
+
+    async fn get_gift_card(&self, card_id: &GiftCardId) -> Result<Option<GiftCard>> {
+        Ok(self.tables()?.gift_cards.get(card_id).cloned())
+    }
+
+    async fn get_gift_card_by_code(&self, code: &str) -> Result<Option<GiftCard>> {
+        let tables = self.tables()?;
+        Ok(tables
+            .gift_card_codes
+            .get(code)
+            .and_then(|card_id| tables.gift_cards.get(card_id))
+            .cloned())
+    }
+
+    async fn list_gift_cards_by_purchaser(&self, user_id: &UserId) -> Result<Vec<GiftCard>> {
+        let tables = self.tables()?;
+        let mut cards: Vec<_> = tables
+            .gift_cards
+            .values()
+            .filter(|card| card.purchaser_id == *user_id)
+            .cloned()
+            .collect();
+        cards.sort_by(|a, b| b.id.as_ulid().cmp(a.id.as_ulid()));
+        Ok(cards)
+    }
+
+    async fn void_gift_card(&self, card_id: &GiftCardId, reason: &str) -> Result<GiftCard> {
+        let mut tables = self.tables()?;
+        let mut card = tables.issued_gift_card(card_id)?;
+        card.void(reason.to_string());
+        tables.gift_cards.insert(card.id, card.clone());
+        Ok(card)
+    }
+
+    // =========================================================================
+    // Agent Budget Operations
+    // =========================================================================
+
+    async fn put_agent_budget(&self, budget: &AgentBudget) -> Result<()> {
+        let mut tables = self.tables()?;
+        let budgets = tables
+            .agent_budgets
+            .entry((budget.user_id, budget.agent_id))
+            .or_default();
+        budgets.retain(|existing| existing.period != budget.period);
+        budgets.push(budget.clone());
+        Ok(())
+    }
+
+    async fn list_agent_budgets(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+    ) -> Result<Vec<AgentBudget>> {
+        Ok(self
+            .tables()?
+            .agent_budgets
+            .get(&(*user_id, *agent_id))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn delete_agent_budget(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+        period: BudgetPeriod,
+    ) -> Result<()> {
+        if let Some(budgets) = self.tables()?.agent_budgets.get_mut(&(*user_id, *agent_id)) {
+            budgets.retain(|budget| budget.period != period);
+        }
+        Ok(())
+    }
+
+    async fn get_agent_spend(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+    ) -> Result<Option<AgentSpend>> {
+        Ok(self
+            .tables()?
+            .agent_spend
+            .get(&(*user_id, *agent_id))
+            .cloned())
+    }
+
+    async fn list_agent_spend(&self, user_id: &UserId) -> Result<Vec<AgentSpend>> {
+        let tables = self.tables()?;
+        let mut totals: Vec<_> = tables
+            .agent_spend
+            .values()
+            .filter(|spend| spend.user_id == *user_id)
+            .cloned()
+            .collect();
+        totals.sort_by(|a, b| a.agent_id.as_bytes().cmp(b.agent_id.as_bytes()));
+        Ok(totals)
+    }
+
+    // =========================================================================
+    // Ledger Operations
+    // =========================================================================
+
+    async fn list_ledger_entries(
+        &self,
+        account: &LedgerAccount,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<LedgerEntry>> {
+        let tables = self.tables()?;
+        let mut entries: Vec<_> = tables
+            .ledger_entries
+            .iter()
+            .filter(|entry| entry.account == *account)
+            .collect();
+        entries.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| b.id.as_bytes().cmp(a.id.as_bytes()))
+        });
+        Ok(entries
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn ledger_balance(&self, account: &LedgerAccount) -> Result<i64> {
+        Ok(self
+            .tables()?
+            .ledger_entries
+            .iter()
+            .filter(|entry| entry.account == *account)
+            .map(|entry| entry.amount_cents)
+            .sum())
+    }
+
+    async fn verify_ledger(&self) -> Result<LedgerReport> {
+        let tables = self.tables()?;
+
+        let mut sums = HashMap::new();
+        let mut report = LedgerReport::default();
+        for entry in &tables.ledger_entries {
+            *sums.entry(entry.account).or_insert(0) += entry.amount_cents;
+            report.unbalanced_cents += entry.amount_cents;
+        }
+
+        let wallets = tables
+            .accounts
+            .values()
+            .map(|account| (LedgerAccount::User(account.user_id), account.balance_cents))
+            .chain(
+                tables
+                    .organizations
+                    .values()
+                    .map(|org| (LedgerAccount::Org(org.id), org.balance_cents)),
+            );
+        for (account, balance_cents) in wallets {
+            report.accounts_checked += 1;
+            let ledger_cents = sums.get(&account).copied().unwrap_or(0);
+            if ledger_cents != balance_cents {
+                report.drift.push(LedgerDrift {
+                    account,
+                    balance_cents,
+                    ledger_cents,
+                });
+            }
+        }
+
+        Ok(report)
+    }
+
+    // =========================================================================
+    // Outbox Operations
+    // =========================================================================
+
+    async fn put_outbox_message(&self, message: &OutboxMessage) -> Result<()> {
+        self.tables()?.outbox.insert(message.id, message.clone());
+        Ok(())
+    }
+
+    async fn get_outbox_message(&self, id: &OutboxId) -> Result<Option<OutboxMessage>> {
+        Ok(self.tables()?.outbox.get(id).cloned())
+    }
+
+    async fn list_outbox_messages(
+        &self,
+        status: Option<OutboxStatus>,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>> {
+        let tables = self.tables()?;
+        let mut messages: Vec<_> = tables
+            .outbox
+            .values()
+            .filter(|message| status.is_none() || Some(message.status) == status)
+            .cloned()
+            .collect();
+        messages.sort_by_key(|message| *message.id.as_ulid());
+        messages.truncate(limit);
+        Ok(messages)
+    }
+
+    async fn claim_outbox_messages(
+        &self,
+        now: DateTime<Utc>,
+        lease: chrono::Duration,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>> {
+        let mut tables = self.tables()?;
+        let mut due: Vec<_> = tables
+            .outbox
+            .values_mut()
+            .filter(|message| message.is_due_at(now))
+            .collect();
+        due.sort_by_key(|message| *message.id.as_ulid());
+        Ok(due
+            .into_iter()
+            .take(limit)
+            .map(|message| {
+                message.next_attempt_at = now + lease;
+                message.clone()
+            })
+            .collect())
+    }
+
+    async fn delete_outbox_message(&self, id: &OutboxId) -> Result<()> {
+        self.tables()?.outbox.remove(id);
+        Ok(())
+    }
+
+    // =========================================================================
+    // Pricing Catalog
+    // =========================================================================
+
+    async fn put_pricing_catalog(&self, catalog: &PricingCatalog) -> Result<()> {
+        let mut tables = self.tables()?;
+        if tables
+            .pricing_catalogs
+            .iter()
+            .any(|saved| saved.version == catalog.version)
+        {
+            return Err(StoreError::InvalidState {
+                entity: "pricing catalog",
+                id: catalog.version.clone(),
+                state: "already saved".to_string(),
+            });
+        }
+        tables.pricing_catalogs.push(catalog.clone());
+        Ok(())
+    }
+
+    async fn get_latest_pricing_catalog(&self) -> Result<Option<PricingCatalog>> {
+        Ok(self.tables()?.pricing_catalogs.last().cloned())
+    }
+
+    // =========================================================================
+    // Storage Meters
+    // =========================================================================
+
+    async fn get_storage_meter(
+        &self,
+        user_id: &UserId,
+        resource_id: &str,
+    ) -> Result<Option<StorageMeter>> {
+        Ok(self
+            .tables()?
+            .storage_meters
+            .get(&(*user_id, resource_id.to_string()))
+            .cloned())
+    }
+
+    async fn put_storage_meter_if_version(
+        &self,
+        meter: &StorageMeter,
+        expected_version: i64,
+    ) -> Result<i64> {
+        let mut tables = self.tables()?;
+        let key = (meter.user_id, meter.resource_id.clone());
+        let actual = tables.storage_meters.get(&key).map_or(0, |m| m.version);
+        if actual != expected_version {
+            return Err(StoreError::VersionConflict {
+                entity: "StorageMeter",
+                id: format!("{}/{}", meter.user_id, meter.resource_id),
+                expected: expected_version,
+                actual,
+            });
+        }
+
+        let mut meter = meter.clone();
+        meter.version = expected_version + 1;
+        tables.storage_meters.insert(key, meter);
+        Ok(expected_version + 1)
+    }
+
+    // =========================================================================
+    // Export
+    // =========================================================================
+
+    async fn list_accounts(&self, after: Option<&UserId>, limit: usize) -> Result<Vec<Account>> {
+        let tables = self.tables()?;
+        let mut accounts: Vec<_> = tables
+            .accounts
+            .values()
+            // `None` sorts before every key
+            .filter(|account| Some(account.user_id.as_bytes()) > after.map(UserId::as_bytes))
+            .cloned()
+            .collect();
+        accounts.sort_by_key(|account| *account.user_id.as_bytes());
+        accounts.truncate(limit);
+        Ok(accounts)
+    }
+
+    async fn list_transactions(
+        &self,
+        after: Option<&TransactionId>,
+        limit: usize,
+    ) -> Result<Vec<CreditTransaction>> {
+        let tables = self.tables()?;
+        let mut transactions: Vec<_> = tables
+            .transactions
+            .values()
+            .filter(|tx| Some(tx.id.as_ulid()) > after.map(TransactionId::as_ulid))
+            .cloned()
+            .collect();
+        transactions.sort_by_key(|tx| *tx.id.as_ulid());
+        transactions.truncate(limit);
+        Ok(transactions)
+    }
+
+    async fn list_usage_events(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<UsageEvent>> {
+        let tables = self.tables()?;
+        let mut events: Vec<_> = tables
+            .usage_events
+            .values()
+            .filter(|event| Some(event.event_id.as_str()) > after)
+            .cloned()
+            .collect();
+        events.sort_by(|a, b| a.event_id.cmp(&b.event_id));
+        events.truncate(limit);
+        Ok(events)
+    }
+
+    async fn list_webhook_events(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ProcessedWebhook>> {
+        let tables = self.tables()?;
+        let mut webhooks: Vec<_> = tables
+            .webhook_events
+            .iter()
+            .filter(|(event_id, _)| Some(event_id.as_str()) > after)
+            .map(|(event_id, source)| ProcessedWebhook {
+                event_id: event_id.clone(),
+                source: source.clone(),
+            })
+            .collect();
+        webhooks.sort_by(|a, b| a.event_id.cmp(&b.event_id));
+        webhooks.truncate(limit);
+        Ok(webhooks)
+    }
+
+    async fn count_unlisted(&self) -> Result<Vec<(&'static str, u64)>> {
+        let tables = self.tables()?;
+        let counts = [
+            (
+                "credit_lots",
+                tables.credit_lots.values().map(Vec::len).sum(),
+            ),
+            ("org_memberships", tables.org_members.len()),
+            (
+                "agent_budgets",
+                tables.agent_budgets.values().map(Vec::len).sum(),
+            ),
+            ("promo_codes", tables.promo_codes.len()),
+            (
+                "gift_cards",
+                tables
+                    .gift_cards
+                    .values()
+                    .filter(|card| card.status == GiftCardStatus::Issued)
+                    .count(),
+            ),
+            ("credit_grants", tables.grants.len()),
+            ("storage_meters", tables.storage_meters.len()),
+        ];
+        Ok(counts
+            .into_iter()
+            .map(|(kind, count)| (kind, count as u64))
+            .collect())
+    }
+
+    // =========================================================================
+    // Compound Operations
+    // =========================================================================
+
+    async fn process_usage(
+        &self,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let mut tables = self.tables()?;
+        tables.check_new_event(event)?;
+        let account = tables.account(&event.user_id)?;
+
+        // Check sufficient balance, holding back active reservations
+        let now = Utc::now();
+        let available = account.spendable_cents() - tables.reserved_cents(&event.user_id, now);
+        if available < event.cost_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: available,
+                required: event.cost_cents,
+            });
+        }
+        tables.check_agent_budgets(event, now)?;
+        tables.write_outbox(outbox);
+
+        Ok(tables.apply_usage(account, event, transaction))
+    }
+
+    async fn process_org_usage(
+        &self,
+        org_id: &OrgId,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let mut tables = self.tables()?;
+        tables.check_new_event(event)?;
+        let mut org = tables.organization(org_id)?;
+        let membership =
+            tables
+                .org_members
+                .get(&(*org_id, event.user_id))
+                .ok_or(StoreError::NotFound {
+                    entity: "OrgMembership",
+                    id: format!("{org_id}/{}", event.user_id),
+                })?;
+
+        let now = Utc::now();
+        if let Some(cap) = membership.monthly_spend_cap_cents {
+            let since = org::spend_cap_period_start(now);
+            let spent: i64 = tables
+                .user_transactions(&event.user_id)
+                .into_iter()
+                .filter(|tx| {
+                    tx.created_at >= since
+                        && tx.org_id.as_ref() == Some(org_id)
+                        && tx.transaction_type == TransactionType::Usage
+                })
+                .map(|tx| -tx.amount_cents)
+                .sum();
+            if spent + event.cost_cents > cap {
+                return Err(StoreError::SpendCapExceeded {
+                    cap,
+                    spent,
+                    required: event.cost_cents,
+                });
+            }
+        }
+
+        if org.balance_cents < event.cost_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: org.balance_cents,
+                required: event.cost_cents,
+            });
+        }
+        tables.check_agent_budgets(event, now)?;
+
+        org.balance_cents -= event.cost_cents;
+        org.lifetime_used_cents += event.cost_cents;
+        org.updated_at = now;
+
+        let balance = org.balance_cents;
+        tables.organizations.insert(*org_id, org);
+        tables.write_transaction(LedgerAccount::Org(*org_id), transaction, -event.cost_cents);
+        tables
+            .usage_events
+            .insert(event.event_id.clone(), event.clone());
+        tables.record_agent_spend(event, now);
+        tables.write_outbox(outbox);
+
+        Ok(balance)
+    }
+
+    async fn add_org_credits(
+        &self,
+        org_id: &OrgId,
+        amount_cents: i64,
+        transaction: &CreditTransaction,
+    ) -> Result<i64> {
+        let mut tables = self.tables()?;
+        let mut org = tables.organization(org_id)?;
+
+        org.balance_cents += amount_cents;
+        if amount_cents > 0 {
+            org.lifetime_purchased_cents += amount_cents;
+        }
+        org.updated_at = Utc::now();
+
+        let balance = org.balance_cents;
+        tables.organizations.insert(*org_id, org);
+        tables.write_transaction(LedgerAccount::Org(*org_id), transaction, amount_cents);
+
+        Ok(balance)
+    }
+
+    async fn add_credits(
+        &self,
+        user_id: &UserId,
+        amount_cents: i64,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        self.tables()?
+            .credit_account(user_id, amount_cents, transaction, outbox)
+    }
+
+    async fn grant_once(
+        &self,
+        user_id: &UserId,
+        grant_key: &str,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<Option<i64>> {
+        let mut tables = self.tables()?;
+        let key = (*user_id, grant_key.to_string());
+        if tables.grants.contains(&key) {
+            return Ok(None);
+        }
+
+        let balance =
+            tables.credit_account(user_id, transaction.amount_cents, transaction, outbox)?;
+        tables.grants.insert(key);
+        Ok(Some(balance))
+    }
+
+    async fn reverse_usage(
+        &self,
+        reversal: &UsageReversal,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<UsageReversal> {
+        let mut tables = self.tables()?;
+        let reversal_key = (reversal.event_id.clone(), reversal.reversal_id.clone());
+
+        if let Some(existing) = tables.usage_reversals.get(&reversal_key) {
+            return Ok(existing.clone());
+        }
+
+        let charged = tables
+            .usage_events
+            .get(&reversal.event_id)
+            .map(|event| event.cost_cents)
+            .ok_or_else(|| StoreError::NotFound {
+                entity: "UsageEvent",
+                id: reversal.event_id.clone(),
+            })?;
+
+        let reversed: i64 = tables
+            .usage_reversals
+            .values()
+            .filter(|r| r.event_id == reversal.event_id)
+            .map(|r| r.amount_cents)
+            .sum();
+        if reversed + reversal.amount_cents > charged {
+            return Err(StoreError::ReversalExceedsCharge {
+                charged,
+                reversed,
+                requested: reversal.amount_cents,
+            });
+        }
+
+        let now = Utc::now();
+
+        // Credit whichever balance the usage was charged against
+        if let Some(org_id) = transaction.org_id {
+            let mut org = tables.organization(&org_id)?;
+            org.balance_cents += reversal.amount_cents;
+            org.lifetime_used_cents -= reversal.amount_cents;
+            org.updated_at = now;
+            tables.organizations.insert(org_id, org);
+        } else {
+            let mut account = tables.account(&transaction.user_id)?;
+            account.balance_cents += reversal.amount_cents;
+            account.lifetime_used_cents -= reversal.amount_cents;
+            account.update_overdraft_lock();
+            account.updated_at = now;
+
+            // Refill the lots the usage drew from
+            let draws = tables
+                .usage_lot_draws
+                .get(&reversal.event_id)
+                .map_or(&[][..], Vec::as_slice);
+            let lots = lot::refund_draws(
+                transaction.user_id,
+                draws,
+                charged,
+                reversed,
+                reversal.amount_cents,
+                account.balance_cents,
+            );
+            tables.accounts.insert(transaction.user_id, account);
+            for lot in &lots {
+                tables.write_credit_lot(lot);
+            }
+        }
+
+        tables.write_transaction(
+            LedgerAccount::wallet_for(transaction),
+            transaction,
+            reversal.amount_cents,
+        );
+        tables
+            .usage_reversals
+            .insert(reversal_key, reversal.clone());
+        tables.write_outbox(outbox);
+
+        Ok(reversal.clone())
+    }
+
+    async fn transfer_credits(
+        &self,
+        transfer: &CreditTransfer,
+        debit: &CreditTransaction,
+        credit: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<CreditTransfer> {
+        let mut tables = self.tables()?;
+        let transfer_key = (transfer.from_user_id, transfer.idempotency_key.clone());
+
+        if let Some(existing) = tables.credit_transfers.get(&transfer_key) {
+            return Ok(existing.clone());
+        }
+
+        let mut sender = tables.account(&transfer.from_user_id)?;
+        let now = Utc::now();
+
+        // Neither the credit line nor held credits can be given away
+        let available = sender.balance_cents - tables.reserved_cents(&transfer.from_user_id, now);
+        if available < transfer.amount_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: available,
+                required: transfer.amount_cents,
+            });
+        }
+
+        let transferable =
+            lot::transferable_cents(&tables.list_credit_lots(&transfer.from_user_id));
+        if transferable < transfer.amount_cents {
+            return Err(StoreError::TransferExceedsPurchased {
+                transferable,
+                requested: transfer.amount_cents,
+            });
+        }
+
+        // Load the recipient before anything is written
+        let recipient = match transfer.to {
+            LedgerAccount::User(user_id) => Recipient::User(Box::new(tables.account(&user_id)?)),
+            LedgerAccount::Org(org_id) => Recipient::Org(tables.organization(&org_id)?),
+            LedgerAccount::System(_) => {
+                return Err(StoreError::NotFound {
+                    entity: "Wallet",
+                    id: transfer.to.to_string(),
+                });
+            }
+        };
+
+        sender.balance_cents -= transfer.amount_cents;
+        sender.update_overdraft_lock();
+        sender.updated_at = now;
+        tables.accounts.insert(transfer.from_user_id, sender);
+        tables.draw_down_transferable_lots(&transfer.from_user_id, transfer.amount_cents);
+        tables.write_transaction(
+            LedgerAccount::User(transfer.from_user_id),
+            debit,
+            -transfer.amount_cents,
+        );
+
+        match recipient {
+            Recipient::User(mut account) => {
+                account.balance_cents += transfer.amount_cents;
+                account.update_overdraft_lock();
+                account.updated_at = now;
+
+                let user_id = account.user_id;
+                let balance = account.balance_cents;
+                tables.accounts.insert(user_id, *account);
+                tables.open_credit_lot(user_id, credit, transfer.amount_cents, None, balance);
+            }
+            Recipient::Org(mut org) => {
+                org.balance_cents += transfer.amount_cents;
+                org.updated_at = now;
+                tables.organizations.insert(org.id, org);
+            }
+        }
+
+        tables.write_transaction(transfer.to, credit, transfer.amount_cents);
+        tables
+            .credit_transfers
+            .insert(transfer_key, transfer.clone());
+        tables.write_outbox(outbox);
+
+        Ok(transfer.clone())
+    }
+
+    async fn redeem_promo_code(
+        &self,
+        redemption: &PromoRedemption,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let mut tables = self.tables()?;
+
+        let mut promo = tables
+            .promo_codes
+            .get(&redemption.code)
+            .cloned()
+            .ok_or_else(|| StoreError::NotFound {
+                entity: "PromoCode",
+                id: redemption.code.clone(),
+            })?;
+        let mut account = tables.account(&redemption.user_id)?;
+
+        let now = Utc::now();
+        let redemption_key = (promo.code.clone(), redemption.user_id);
+        let redeemed = tables
+            .promo_redemptions
+            .get(&redemption_key)
+            .copied()
+            .unwrap_or(0);
+        promo
+            .check_redeemable(&account.current_plan(), redeemed, now)
+            .map_err(|reason| StoreError::PromoRejected {
+                code: promo.code.clone(),
+                reason,
+            })?;
+
+        promo.redemption_count += 1;
+        account.balance_cents += redemption.amount_cents;
+        account.lifetime_granted_cents += redemption.amount_cents;
+        account.update_overdraft_lock();
+        account.updated_at = now;
+
+        let balance = account.balance_cents;
+        tables.promo_codes.insert(promo.code.clone(), promo);
+        *tables.promo_redemptions.entry(redemption_key).or_insert(0) += 1;
+        tables.accounts.insert(redemption.user_id, account);
+        tables.write_transaction(
+            LedgerAccount::User(redemption.user_id),
+            transaction,
+            redemption.amount_cents,
+        );
+        tables.open_credit_lot(
+            redemption.user_id,
+            transaction,
+            redemption.amount_cents,
+            None,
+            balance,
+        );
+        tables.write_outbox(outbox);
+
+        Ok(balance)
+    }
+
+    async fn redeem_gift_card(
+        &self,
+        card_id: &GiftCardId,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let mut tables = self.tables()?;
+
+        let mut card = tables.issued_gift_card(card_id)?;
+        let user_id = transaction.user_id;
+        let mut account = tables.account(&user_id)?;
+
+        card.redeem(user_id, transaction.id);
+        account.balance_cents += card.amount_cents;
+        account.lifetime_purchased_cents += card.amount_cents;
+        account.update_overdraft_lock();
+        account.updated_at = Utc::now();
+
+        let amount_cents = card.amount_cents;
+        let balance = account.balance_cents;
+        tables.gift_cards.insert(*card_id, card);
+        tables.accounts.insert(user_id, account);
+        tables.write_transaction(LedgerAccount::User(user_id), transaction, amount_cents);
+        tables.open_credit_lot(user_id, transaction, amount_cents, None, balance);
+        tables.write_outbox(outbox);
+
+        Ok(balance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn failed_usage_writes_nothing() {
+        let store = MemoryStore::new();
+        let user_id = UserId::generate();
+        let mut account = Account::new(user_id);
+        account.balance_cents = 50;
+        store.create_account(&account).await.unwrap();
+
+        let event = UsageEvent {
+            event_id: "evt-too-big".to_string(),
+            user_id,
+            agent_id: None,
+            source: z_billing_core::UsageSource::AuraRuntime,
+            metric: z_billing_core::UsageMetric::ApiCalls {
+                endpoint: "test".to_string(),
+            },
+            quantity: 1.0,
+            cost_cents: 80,
+            timestamp: Utc::now(),
+            metadata: serde_json::Value::Null,
+            transaction_id: None,
+        };
+        let tx = CreditTransaction::usage(user_id, 80, -30, "usage".into(), serde_json::json!({}));
+        assert!(matches!(
+            store.process_usage(&event, &tx, &[]).await,
+            Err(StoreError::InsufficientCredits { .. })
+        ));
+
+        let tables = store.tables().unwrap();
+        assert!(tables.transactions.is_empty());
+        assert!(tables.usage_events.is_empty());
+        assert_eq!(tables.ledger_entries.len(), 2); // Opening balance only
+        assert_eq!(tables.accounts[&user_id].balance_cents, 50);
+    }
+
+    #[tokio::test]
+    async fn finds_account_by_stripe_customer() {
+        let store = MemoryStore::new();
+        let mut account = Account::new(UserId::generate());
+        account.stripe_customer_id = Some("cus_123".into());
+        store.put_account(&account).await.unwrap();
+
+        let found = store
+            .find_account_by_stripe_customer("cus_123")
+            .await
+            .unwrap();
+        assert_eq!(found.map(|a| a.user_id), Some(account.user_id));
+        assert!(store
+            .find_account_by_stripe_customer("cus_other")
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
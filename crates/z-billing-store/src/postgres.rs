@@ -4,7 +4,15 @@
 
 use sqlx::PgPool;
 
-use z_billing_core::{Account, CreditTransaction, TransactionId, UsageEvent, UserId};
+use z_billing_core::{
+    budget, ledger, lot, org, Account, AgentBudget, AgentId, AgentSpend, AmountSign, BudgetPeriod,
+    CreditLot, CreditTransaction, CreditTransfer, DailyUsage, GiftCard, GiftCardId, GiftCardStatus,
+    LedgerAccount, LedgerDrift, LedgerEntry, LedgerEntryId, LedgerReport, LotDraw, LotId, OrgId,
+    OrgMembership, Organization, OutboxId, OutboxMessage, OutboxStatus, PricingCatalog,
+    ProcessedWebhook, PromoCode, PromoRedemption, Reservation, ReservationId, ReservationStatus,
+    StorageMeter, SystemAccount, TransactionId, TransactionQuery, TransactionType, UsageEvent,
+    UsageReversal, UsageSummaryQuery, UsageSummaryRow, UserId,
+};
 
 use crate::error::{Result, StoreError};
 use crate::Store;
@@ -27,575 +35,3356 @@ impl PgStore {
     }
 }
 
+#[async_trait::async_trait]
 impl Store for PgStore {
-    fn put_account(&self, account: &Account) -> Result<()> {
-        let pool = self.pool.clone();
-        let account = account.clone();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
+    async fn put_account(&self, account: &Account) -> Result<()> {
+        // The balance and lifetime counters only move through operations
+        // that post to the ledger, so they are never written here. The
+        // overdraft lock follows the balance unless the credit limit changed.
+        bind_account_settings(
+            sqlx::query(
+                r#"
+                INSERT INTO accounts (user_id, subscription, auto_refill, lago_customer_id,
+                    stripe_customer_id, is_zero_pro, referred_by, signup_grant_at,
+                    last_daily_grant_at, last_monthly_grant_at, created_at, updated_at,
+                    credit_limit_cents, version)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, 1)
+                ON CONFLICT (user_id) DO UPDATE SET
+                    subscription = $2,
+                    auto_refill = $3,
+                    lago_customer_id = $4,
+                    stripe_customer_id = $5,
+                    is_zero_pro = $6,
+                    referred_by = $7,
+                    signup_grant_at = $8,
+                    last_daily_grant_at = $9,
+                    last_monthly_grant_at = $10,
+                    updated_at = $12,
+                    credit_limit_cents = $13,
+                    overdraft_locked = CASE
+                        WHEN accounts.credit_limit_cents = $13 THEN accounts.overdraft_locked
+                        ELSE $13 > 0 AND accounts.balance_cents <= -$13
+                    END,
+                    version = accounts.version + 1
+                "#,
+            ),
+            account,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn put_account_if_version(
+        &self,
+        account: &Account,
+        expected_version: i64,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let written = if expected_version == 0 {
+            bind_account_settings(
                 sqlx::query(
                     r#"
-                    INSERT INTO accounts (user_id, balance_cents, lifetime_purchased_cents,
-                        lifetime_granted_cents, lifetime_used_cents, subscription, auto_refill,
-                        lago_customer_id, stripe_customer_id, is_zero_pro, referred_by,
-                        signup_grant_at, last_daily_grant_at, last_monthly_grant_at,
-                        created_at, updated_at)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
-                    ON CONFLICT (user_id) DO UPDATE SET
-                        balance_cents = $2,
-                        lifetime_purchased_cents = $3,
-                        lifetime_granted_cents = $4,
-                        lifetime_used_cents = $5,
-                        subscription = $6,
-                        auto_refill = $7,
-                        lago_customer_id = $8,
-                        stripe_customer_id = $9,
-                        is_zero_pro = $10,
-                        referred_by = $11,
-                        signup_grant_at = $12,
-                        last_daily_grant_at = $13,
-                        last_monthly_grant_at = $14,
-                        updated_at = $16
+                    INSERT INTO accounts (user_id, subscription, auto_refill, lago_customer_id,
+                        stripe_customer_id, is_zero_pro, referred_by, signup_grant_at,
+                        last_daily_grant_at, last_monthly_grant_at, created_at, updated_at,
+                        credit_limit_cents, version)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, 1)
+                    ON CONFLICT (user_id) DO NOTHING
                     "#,
-                )
-                .bind(account.user_id.as_uuid())
-                .bind(account.balance_cents)
-                .bind(account.lifetime_purchased_cents)
-                .bind(account.lifetime_granted_cents)
-                .bind(account.lifetime_used_cents)
-                .bind(serde_json::to_value(&account.subscription).unwrap_or_default())
-                .bind(serde_json::to_value(&account.auto_refill).unwrap_or_default())
-                .bind(&account.lago_customer_id)
-                .bind(&account.stripe_customer_id)
-                .bind(account.is_zero_pro)
-                .bind(&account.referred_by)
-                .bind(account.signup_grant_at)
-                .bind(account.last_daily_grant_at)
-                .bind(account.last_monthly_grant_at)
-                .bind(account.created_at)
-                .bind(account.updated_at)
-                .execute(&pool)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+                ),
+                account,
+            )
+            .execute(&mut *db_tx)
+            .await
+        } else {
+            bind_account_settings(
+                sqlx::query(
+                    r#"
+                    UPDATE accounts SET
+                        subscription = $2,
+                        auto_refill = $3,
+                        lago_customer_id = $4,
+                        stripe_customer_id = $5,
+                        is_zero_pro = $6,
+                        referred_by = $7,
+                        signup_grant_at = $8,
+                        last_daily_grant_at = $9,
+                        last_monthly_grant_at = $10,
+                        updated_at = $12,
+                        credit_limit_cents = $13,
+                        overdraft_locked = CASE
+                            WHEN credit_limit_cents = $13 THEN overdraft_locked
+                            ELSE $13 > 0 AND balance_cents <= -$13
+                        END,
+                        version = version + 1
+                    WHERE user_id = $1 AND version = $14
+                    "#,
+                ),
+                account,
+            )
+            .bind(expected_version)
+            .execute(&mut *db_tx)
+            .await
+        }
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .rows_affected();
 
-                Ok(())
-            })
-        })
+        if written == 0 {
+            return Err(
+                account_version_conflict(&mut db_tx, account.user_id, expected_version).await,
+            );
+        }
+
+        insert_outbox_messages(&mut db_tx, outbox).await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(expected_version + 1)
+    }
+
+    async fn create_account(&self, account: &Account) -> Result<()> {
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO accounts (user_id, balance_cents, lifetime_purchased_cents,
+                lifetime_granted_cents, lifetime_used_cents, subscription, auto_refill,
+                lago_customer_id, stripe_customer_id, is_zero_pro, referred_by,
+                signup_grant_at, last_daily_grant_at, last_monthly_grant_at,
+                created_at, updated_at, credit_limit_cents, overdraft_locked, version)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16,
+                $17, $18, 1)
+            ON CONFLICT (user_id) DO NOTHING
+            "#,
+        )
+        .bind(account.user_id.as_uuid())
+        .bind(account.balance_cents)
+        .bind(account.lifetime_purchased_cents)
+        .bind(account.lifetime_granted_cents)
+        .bind(account.lifetime_used_cents)
+        .bind(serde_json::to_value(&account.subscription).unwrap_or_default())
+        .bind(serde_json::to_value(&account.auto_refill).unwrap_or_default())
+        .bind(&account.lago_customer_id)
+        .bind(&account.stripe_customer_id)
+        .bind(account.is_zero_pro)
+        .bind(&account.referred_by)
+        .bind(account.signup_grant_at)
+        .bind(account.last_daily_grant_at)
+        .bind(account.last_monthly_grant_at)
+        .bind(account.created_at)
+        .bind(account.updated_at)
+        .bind(account.credit_limit_cents)
+        .bind(account.overdraft_locked)
+        .execute(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .rows_affected();
+
+        if inserted == 0 {
+            return Err(account_version_conflict(&mut db_tx, account.user_id, 0).await);
+        }
+
+        if account.balance_cents != 0 {
+            let entries = ledger::transfer(
+                LedgerAccount::User(account.user_id),
+                SystemAccount::OpeningBalances,
+                account.balance_cents,
+                None,
+                chrono::Utc::now(),
+            );
+            insert_ledger_entries(&mut db_tx, &entries).await?;
+        }
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))
     }
 
-    fn get_account(&self, user_id: &UserId) -> Result<Option<Account>> {
-        let pool = self.pool.clone();
+    async fn get_account(&self, user_id: &UserId) -> Result<Option<Account>> {
         let user_id = *user_id;
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let row =
-                    sqlx::query_as::<_, AccountRow>("SELECT * FROM accounts WHERE user_id = $1")
-                        .bind(user_id.as_uuid())
-                        .fetch_optional(&pool)
-                        .await
-                        .map_err(|e| StoreError::Database(e.to_string()))?;
-
-                Ok(row.map(|r| r.into_account()))
-            })
-        })
+        let row = sqlx::query_as::<_, AccountRow>("SELECT * FROM accounts WHERE user_id = $1")
+            .bind(user_id.as_uuid())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(row.map(|r| r.into_account()))
     }
 
-    fn find_account_by_stripe_customer(&self, customer_id: &str) -> Result<Option<Account>> {
-        let pool = self.pool.clone();
+    async fn find_account_by_stripe_customer(&self, customer_id: &str) -> Result<Option<Account>> {
         let customer_id = customer_id.to_string();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let row = sqlx::query_as::<_, AccountRow>(
-                    "SELECT * FROM accounts WHERE stripe_customer_id = $1",
-                )
+        let row =
+            sqlx::query_as::<_, AccountRow>("SELECT * FROM accounts WHERE stripe_customer_id = $1")
                 .bind(&customer_id)
-                .fetch_optional(&pool)
+                .fetch_optional(&self.pool)
                 .await
                 .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(row.map(|r| r.into_account()))
-            })
-        })
+        Ok(row.map(|r| r.into_account()))
     }
 
-    fn delete_account(&self, user_id: &UserId) -> Result<()> {
-        let pool = self.pool.clone();
+    async fn find_account_by_lago_customer(&self, customer_id: &str) -> Result<Option<Account>> {
+        let customer_id = customer_id.to_string();
+        let row =
+            sqlx::query_as::<_, AccountRow>("SELECT * FROM accounts WHERE lago_customer_id = $1")
+                .bind(&customer_id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(row.map(|r| r.into_account()))
+    }
+
+    async fn delete_account(&self, user_id: &UserId) -> Result<()> {
         let user_id = *user_id;
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let result = sqlx::query("DELETE FROM accounts WHERE user_id = $1")
-                    .bind(user_id.as_uuid())
-                    .execute(&pool)
-                    .await
-                    .map_err(|e| StoreError::Database(e.to_string()))?;
-
-                if result.rows_affected() == 0 {
-                    return Err(StoreError::NotFound {
-                        entity: "account",
-                        id: user_id.to_string(),
-                    });
-                }
-                Ok(())
-            })
-        })
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        // Credits that ever moved stay on the books
+        let has_history = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM credit_transactions WHERE user_id = $1)
+                OR EXISTS (SELECT 1 FROM ledger_entries WHERE account = $2)
+            "#,
+        )
+        .bind(user_id.as_uuid())
+        .bind(LedgerAccount::User(user_id).to_string())
+        .fetch_one(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+        if has_history {
+            return Err(StoreError::InvalidState {
+                entity: "account",
+                id: user_id.to_string(),
+                state: "still on the ledger".to_string(),
+            });
+        }
+
+        // Budgets and holds go with the account
+        sqlx::query("DELETE FROM agent_budgets WHERE user_id = $1")
+            .bind(user_id.as_uuid())
+            .execute(&mut *db_tx)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+        sqlx::query("DELETE FROM credit_reservations WHERE user_id = $1")
+            .bind(user_id.as_uuid())
+            .execute(&mut *db_tx)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let result = sqlx::query("DELETE FROM accounts WHERE user_id = $1")
+            .bind(user_id.as_uuid())
+            .execute(&mut *db_tx)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Err(StoreError::NotFound {
+                entity: "account",
+                id: user_id.to_string(),
+            });
+        }
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))
     }
 
     #[allow(deprecated)]
-    fn update_balance(&self, user_id: &UserId, delta_cents: i64) -> Result<i64> {
-        let pool = self.pool.clone();
+    async fn update_balance(&self, user_id: &UserId, delta_cents: i64) -> Result<i64> {
         let user_id = *user_id;
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let row = sqlx::query_scalar::<_, i64>(
-                    r#"
-                    UPDATE accounts
-                    SET balance_cents = balance_cents + $2, updated_at = NOW()
-                    WHERE user_id = $1
-                    RETURNING balance_cents
-                    "#,
-                )
-                .bind(user_id.as_uuid())
-                .bind(delta_cents)
-                .fetch_optional(&pool)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                row.ok_or(StoreError::NotFound {
-                    entity: "account",
-                    id: user_id.to_string(),
-                })
-            })
-        })
+        let new_balance = sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE accounts
+            SET balance_cents = balance_cents + $2,
+                overdraft_locked = CASE
+                    WHEN balance_cents + $2 > 0 THEN FALSE
+                    WHEN credit_limit_cents > 0
+                        AND balance_cents + $2 <= -credit_limit_cents THEN TRUE
+                    ELSE overdraft_locked
+                END,
+                updated_at = NOW()
+            WHERE user_id = $1
+            RETURNING balance_cents
+            "#,
+        )
+        .bind(user_id.as_uuid())
+        .bind(delta_cents)
+        .fetch_optional(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .ok_or(StoreError::NotFound {
+            entity: "account",
+            id: user_id.to_string(),
+        })?;
+
+        let entries = ledger::transfer(
+            LedgerAccount::User(user_id),
+            SystemAccount::Adjustments,
+            delta_cents,
+            None,
+            chrono::Utc::now(),
+        );
+        insert_ledger_entries(&mut db_tx, &entries).await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(new_balance)
+    }
+
+    async fn put_organization(&self, org: &Organization) -> Result<()> {
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let inserted = sqlx::query_scalar::<_, bool>(
+            r#"
+            INSERT INTO organizations (id, name, balance_cents, lifetime_purchased_cents,
+                lifetime_used_cents, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (id) DO UPDATE SET
+                name = $2,
+                lifetime_purchased_cents = $4,
+                lifetime_used_cents = $5,
+                updated_at = $7
+            RETURNING (xmax = 0)
+            "#,
+        )
+        .bind(org.id.as_uuid())
+        .bind(&org.name)
+        .bind(org.balance_cents)
+        .bind(org.lifetime_purchased_cents)
+        .bind(org.lifetime_used_cents)
+        .bind(org.created_at)
+        .bind(org.updated_at)
+        .fetch_one(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        if inserted && org.balance_cents != 0 {
+            let entries = ledger::transfer(
+                LedgerAccount::Org(org.id),
+                SystemAccount::OpeningBalances,
+                org.balance_cents,
+                None,
+                chrono::Utc::now(),
+            );
+            insert_ledger_entries(&mut db_tx, &entries).await?;
+        }
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    async fn get_organization(&self, org_id: &OrgId) -> Result<Option<Organization>> {
+        let org_id = *org_id;
+        let row = sqlx::query_as::<_, OrganizationRow>("SELECT * FROM organizations WHERE id = $1")
+            .bind(org_id.as_uuid())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(row.map(OrganizationRow::into_organization))
+    }
+
+    async fn put_org_membership(&self, membership: &OrgMembership) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO org_memberships (org_id, user_id, role, monthly_spend_cap_cents,
+                created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (org_id, user_id) DO UPDATE SET
+                role = $3,
+                monthly_spend_cap_cents = $4,
+                updated_at = $6
+            "#,
+        )
+        .bind(membership.org_id.as_uuid())
+        .bind(membership.user_id.as_uuid())
+        .bind(membership.role.as_str())
+        .bind(membership.monthly_spend_cap_cents)
+        .bind(membership.created_at)
+        .bind(membership.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_org_membership(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+    ) -> Result<Option<OrgMembership>> {
+        let org_id = *org_id;
+        let user_id = *user_id;
+        let row = sqlx::query_as::<_, OrgMembershipRow>(
+            "SELECT * FROM org_memberships WHERE org_id = $1 AND user_id = $2",
+        )
+        .bind(org_id.as_uuid())
+        .bind(user_id.as_uuid())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        row.map(OrgMembershipRow::into_membership).transpose()
+    }
+
+    async fn list_org_members(&self, org_id: &OrgId) -> Result<Vec<OrgMembership>> {
+        let org_id = *org_id;
+        let rows = sqlx::query_as::<_, OrgMembershipRow>(
+            "SELECT * FROM org_memberships WHERE org_id = $1 ORDER BY created_at",
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        rows.into_iter()
+            .map(OrgMembershipRow::into_membership)
+            .collect()
+    }
+
+    async fn delete_org_membership(&self, org_id: &OrgId, user_id: &UserId) -> Result<()> {
+        let org_id = *org_id;
+        let user_id = *user_id;
+        sqlx::query("DELETE FROM org_memberships WHERE org_id = $1 AND user_id = $2")
+            .bind(org_id.as_uuid())
+            .bind(user_id.as_uuid())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn org_member_spend_since(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<i64> {
+        let org_id = *org_id;
+        let user_id = *user_id;
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        sum_org_member_spend(&mut conn, &org_id, &user_id, since).await
     }
 
-    fn put_transaction(&self, transaction: &CreditTransaction) -> Result<()> {
-        let pool = self.pool.clone();
+    async fn put_transaction(&self, transaction: &CreditTransaction) -> Result<()> {
         let tx = transaction.clone();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                sqlx::query(
-                    r#"
-                    INSERT INTO credit_transactions (id, user_id, amount_cents, transaction_type,
-                        balance_after_cents, description, metadata, created_at)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-                    "#,
-                )
-                .bind(tx.id.to_string())
-                .bind(tx.user_id.as_uuid())
-                .bind(tx.amount_cents)
-                .bind(
-                    serde_json::to_string(&tx.transaction_type)
-                        .unwrap_or_default()
-                        .trim_matches('"'),
-                )
-                .bind(tx.balance_after_cents)
-                .bind(&tx.description)
-                .bind(&tx.metadata)
-                .bind(tx.created_at)
-                .execute(&pool)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(())
-            })
-        })
+        insert_transaction(&mut conn, &tx, tx.balance_after_cents).await
     }
 
-    fn get_transaction(&self, transaction_id: &TransactionId) -> Result<Option<CreditTransaction>> {
-        let pool = self.pool.clone();
+    async fn get_transaction(
+        &self,
+        transaction_id: &TransactionId,
+    ) -> Result<Option<CreditTransaction>> {
         let tx_id = transaction_id.to_string();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let row = sqlx::query_as::<_, TransactionRow>(
-                    "SELECT * FROM credit_transactions WHERE id = $1",
-                )
+        let row =
+            sqlx::query_as::<_, TransactionRow>("SELECT * FROM credit_transactions WHERE id = $1")
                 .bind(&tx_id)
-                .fetch_optional(&pool)
+                .fetch_optional(&self.pool)
                 .await
                 .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(row.map(|r| r.into_transaction()))
-            })
-        })
+        Ok(row.map(|r| r.into_transaction()))
     }
 
-    fn list_transactions_by_user(
+    async fn list_transactions_by_user(
         &self,
         user_id: &UserId,
         limit: usize,
         offset: usize,
     ) -> Result<Vec<CreditTransaction>> {
-        let pool = self.pool.clone();
         let user_id = *user_id;
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let rows = sqlx::query_as::<_, TransactionRow>(
-                    r#"
-                    SELECT * FROM credit_transactions
-                    WHERE user_id = $1
-                    ORDER BY created_at DESC
-                    LIMIT $2 OFFSET $3
-                    "#,
-                )
-                .bind(user_id.as_uuid())
-                .bind(limit as i64)
-                .bind(offset as i64)
-                .fetch_all(&pool)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+        let rows = sqlx::query_as::<_, TransactionRow>(
+            r#"
+            SELECT * FROM credit_transactions
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(user_id.as_uuid())
+        .bind(limit as i64)
+        .bind(offset as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(rows.into_iter().map(|r| r.into_transaction()).collect())
-            })
-        })
+        Ok(rows.into_iter().map(|r| r.into_transaction()).collect())
+    }
+
+    async fn query_transactions(
+        &self,
+        user_id: &UserId,
+        query: &TransactionQuery,
+    ) -> Result<Vec<CreditTransaction>> {
+        let mut builder = sqlx::QueryBuilder::<sqlx::Postgres>::new(
+            "SELECT * FROM credit_transactions WHERE user_id = ",
+        );
+        builder.push_bind(user_id.as_uuid());
+        if !query.transaction_types.is_empty() {
+            let types: Vec<&str> = query
+                .transaction_types
+                .iter()
+                .map(TransactionType::as_str)
+                .collect();
+            builder.push(" AND transaction_type = ANY(");
+            builder.push_bind(types);
+            builder.push(")");
+        }
+        if let Some(since) = query.since {
+            builder.push(" AND created_at >= ");
+            builder.push_bind(since);
+        }
+        if let Some(until) = query.until {
+            builder.push(" AND created_at < ");
+            builder.push_bind(until);
+        }
+        match query.sign {
+            Some(AmountSign::Credit) => {
+                builder.push(" AND amount_cents > 0");
+            }
+            Some(AmountSign::Debit) => {
+                builder.push(" AND amount_cents < 0");
+            }
+            None => {}
+        }
+        for (key, value) in &query.metadata {
+            builder.push(" AND metadata ->> ");
+            builder.push_bind(key.as_str());
+            builder.push(" = ");
+            builder.push_bind(value.as_str());
+        }
+        // ULID strings sort in creation order, so the ID doubles as the cursor
+        if let Some(before) = query.before {
+            builder.push(" AND id < ");
+            builder.push_bind(before.to_string());
+        }
+        builder.push(" ORDER BY id DESC LIMIT ");
+        builder.push_bind(query.limit as i64);
+
+        let rows = builder
+            .build_query_as::<TransactionRow>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(rows.into_iter().map(|r| r.into_transaction()).collect())
     }
 
-    fn sum_monthly_allowance_since(
+    async fn sum_monthly_allowance_since(
         &self,
         user_id: &UserId,
         since: chrono::DateTime<chrono::Utc>,
     ) -> Result<i64> {
-        let pool = self.pool.clone();
         let user_id = *user_id;
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let total: i64 = sqlx::query_scalar(
-                    r#"
-                    SELECT COALESCE(SUM(amount_cents), 0)::BIGINT
-                    FROM credit_transactions
-                    WHERE user_id = $1
-                      AND transaction_type = 'monthly_allowance'
-                      AND created_at >= $2
-                    "#,
-                )
-                .bind(user_id.as_uuid())
-                .bind(since)
-                .fetch_one(&pool)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(amount_cents), 0)::BIGINT
+            FROM credit_transactions
+            WHERE user_id = $1
+              AND transaction_type = 'monthly_allowance'
+              AND created_at >= $2
+            "#,
+        )
+        .bind(user_id.as_uuid())
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(total)
-            })
-        })
+        Ok(total)
     }
 
-    fn has_referral_bonus(&self, user_id: &UserId) -> Result<bool> {
-        let pool = self.pool.clone();
+    async fn has_referral_bonus(&self, user_id: &UserId) -> Result<bool> {
         let user_id = *user_id;
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let exists: bool = sqlx::query_scalar(
-                    r#"
-                    SELECT EXISTS(
-                        SELECT 1 FROM credit_transactions
-                        WHERE user_id = $1
-                          AND transaction_type = 'referral_bonus'
-                    )
-                    "#,
-                )
-                .bind(user_id.as_uuid())
-                .fetch_one(&pool)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+        let exists: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM credit_transactions
+                WHERE user_id = $1
+                  AND transaction_type = 'referral_bonus'
+            )
+            "#,
+        )
+        .bind(user_id.as_uuid())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(exists)
-            })
-        })
+        Ok(exists)
     }
 
-    fn has_usage_event(&self, event_id: &str) -> Result<bool> {
-        let pool = self.pool.clone();
+    async fn has_usage_event(&self, event_id: &str) -> Result<bool> {
         let event_id = event_id.to_string();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let exists = sqlx::query_scalar::<_, bool>(
-                    "SELECT EXISTS(SELECT 1 FROM usage_events WHERE event_id = $1)",
-                )
-                .bind(&event_id)
-                .fetch_one(&pool)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM usage_events WHERE event_id = $1)",
+        )
+        .bind(&event_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(exists)
-            })
-        })
+        Ok(exists)
     }
 
-    fn put_usage_event(&self, event: &UsageEvent) -> Result<()> {
-        let pool = self.pool.clone();
-        let event = event.clone();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                sqlx::query(
-                    r#"
-                    INSERT INTO usage_events (event_id, user_id, agent_id, source, metric,
-                        quantity, cost_cents, event_timestamp, metadata)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-                    ON CONFLICT (event_id) DO NOTHING
-                    "#,
-                )
-                .bind(&event.event_id)
-                .bind(event.user_id.as_uuid())
-                .bind(event.agent_id.map(|a| *a.as_uuid()))
-                .bind(serde_json::to_value(&event.source).unwrap_or_default())
-                .bind(serde_json::to_value(&event.metric).unwrap_or_default())
-                .bind(event.quantity)
-                .bind(event.cost_cents)
-                .bind(event.timestamp)
-                .bind(&event.metadata)
-                .execute(&pool)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+    async fn put_usage_event(&self, event: &UsageEvent) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO usage_events (event_id, user_id, agent_id, source, metric,
+                quantity, cost_cents, event_timestamp, metadata, transaction_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (event_id) DO NOTHING
+            "#,
+        )
+        .bind(&event.event_id)
+        .bind(event.user_id.as_uuid())
+        .bind(event.agent_id.map(|a| *a.as_uuid()))
+        .bind(serde_json::to_value(&event.source).unwrap_or_default())
+        .bind(serde_json::to_value(&event.metric).unwrap_or_default())
+        .bind(event.quantity)
+        .bind(event.cost_cents)
+        .bind(event.timestamp)
+        .bind(&event.metadata)
+        .bind(event.transaction_id.map(|id| id.to_string()))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(())
-            })
-        })
+        Ok(())
     }
 
-    fn get_usage_event(&self, event_id: &str) -> Result<Option<UsageEvent>> {
-        let pool = self.pool.clone();
+    async fn get_usage_event(&self, event_id: &str) -> Result<Option<UsageEvent>> {
         let event_id = event_id.to_string();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let row = sqlx::query_as::<_, UsageEventRow>(
-                    "SELECT * FROM usage_events WHERE event_id = $1",
-                )
+        let row =
+            sqlx::query_as::<_, UsageEventRow>("SELECT * FROM usage_events WHERE event_id = $1")
                 .bind(&event_id)
-                .fetch_optional(&pool)
+                .fetch_optional(&self.pool)
                 .await
                 .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(row.map(|r| r.into_usage_event()))
-            })
-        })
+        Ok(row.map(|r| r.into_usage_event()))
     }
 
-    fn has_webhook_event(&self, event_id: &str) -> Result<bool> {
-        let pool = self.pool.clone();
+    async fn list_usage_reversals(&self, event_id: &str) -> Result<Vec<UsageReversal>> {
         let event_id = event_id.to_string();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let exists = sqlx::query_scalar::<_, bool>(
-                    "SELECT EXISTS(SELECT 1 FROM processed_webhooks WHERE event_id = $1)",
-                )
-                .bind(&event_id)
-                .fetch_one(&pool)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(exists)
-            })
-        })
+        fetch_usage_reversals(&mut conn, &event_id).await
+    }
+
+    async fn aggregate_usage(&self, query: &UsageSummaryQuery) -> Result<Vec<UsageSummaryRow>> {
+        let mut builder = sqlx::QueryBuilder::<sqlx::Postgres>::new(
+            r#"
+            SELECT (event_timestamp AT TIME ZONE 'UTC')::DATE AS day, source, agent_id,
+                metric -> 'provider' AS provider, metric ->> 'model' AS model,
+                metric ->> 'direction' AS direction, SUM(quantity) AS quantity,
+                SUM(cost_cents)::BIGINT AS cost_cents, COUNT(*) AS event_count
+            FROM usage_events
+            WHERE event_timestamp >= "#,
+        );
+        builder.push_bind(query.since);
+        builder.push(" AND event_timestamp < ");
+        builder.push_bind(query.until);
+        if let Some(user_id) = query.user_id {
+            builder.push(" AND user_id = ");
+            builder.push_bind(*user_id.as_uuid());
+        }
+        builder.push(" GROUP BY 1, 2, 3, 4, 5, 6");
+
+        let rows = builder
+            .build_query_as::<DailyUsageRow>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let usage = rows
+            .into_iter()
+            .map(DailyUsageRow::into_daily_usage)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(query.summarize(usage))
+    }
+
+    async fn has_webhook_event(&self, event_id: &str) -> Result<bool> {
+        let event_id = event_id.to_string();
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM processed_webhooks WHERE event_id = $1)",
+        )
+        .bind(&event_id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(exists)
     }
 
-    fn record_webhook_event(&self, event_id: &str, source: &str) -> Result<()> {
-        let pool = self.pool.clone();
+    async fn record_webhook_event(&self, event_id: &str, source: &str) -> Result<()> {
         let event_id = event_id.to_string();
         let source = source.to_string();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                sqlx::query(
-                    "INSERT INTO processed_webhooks (event_id, source) VALUES ($1, $2) ON CONFLICT (event_id) DO NOTHING",
-                )
-                .bind(&event_id)
-                .bind(&source)
-                .execute(&pool)
+        sqlx::query(
+            "INSERT INTO processed_webhooks (event_id, source) VALUES ($1, $2) ON CONFLICT (event_id) DO NOTHING",
+        )
+        .bind(&event_id)
+        .bind(&source)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn create_reservation(&self, reservation: &Reservation) -> Result<i64> {
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let spendable = lock_spendable(&mut db_tx, &reservation.user_id).await?;
+        let available =
+            spendable - sum_active_holds(&mut db_tx, &reservation.user_id, None).await?;
+
+        if available < reservation.amount_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: available,
+                required: reservation.amount_cents,
+            });
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO credit_reservations (id, user_id, amount_cents, status,
+                transaction_id, metadata, created_at, expires_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(reservation.id.to_string())
+        .bind(reservation.user_id.as_uuid())
+        .bind(reservation.amount_cents)
+        .bind(reservation.status.as_str())
+        .bind(reservation.transaction_id.map(|id| id.to_string()))
+        .bind(&reservation.metadata)
+        .bind(reservation.created_at)
+        .bind(reservation.expires_at)
+        .bind(reservation.updated_at)
+        .execute(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(available - reservation.amount_cents)
+    }
+
+    async fn get_reservation(&self, reservation_id: &ReservationId) -> Result<Option<Reservation>> {
+        let reservation_id = reservation_id.to_string();
+        let row =
+            sqlx::query_as::<_, ReservationRow>("SELECT * FROM credit_reservations WHERE id = $1")
+                .bind(&reservation_id)
+                .fetch_optional(&self.pool)
                 .await
                 .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(())
-            })
-        })
+        row.map(ReservationRow::into_reservation).transpose()
+    }
+
+    async fn reserved_cents(&self, user_id: &UserId) -> Result<i64> {
+        let user_id = *user_id;
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        sum_active_holds(&mut conn, &user_id, None).await
+    }
+
+    async fn settle_reservation(
+        &self,
+        reservation_id: &ReservationId,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let reservation_id = reservation_id.to_string();
+        let tx = transaction.clone();
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let status = sqlx::query_scalar::<_, String>(
+            "SELECT status FROM credit_reservations WHERE id = $1 FOR UPDATE",
+        )
+        .bind(&reservation_id)
+        .fetch_optional(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .ok_or_else(|| StoreError::NotFound {
+            entity: "reservation",
+            id: reservation_id.clone(),
+        })?;
+
+        if status == ReservationStatus::Settled.as_str()
+            || status == ReservationStatus::Released.as_str()
+        {
+            return Err(StoreError::InvalidState {
+                entity: "reservation",
+                id: reservation_id.clone(),
+                state: status,
+            });
+        }
+
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM usage_events WHERE event_id = $1)",
+        )
+        .bind(&event.event_id)
+        .fetch_one(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        if exists {
+            return Err(StoreError::DuplicateEvent {
+                event_id: event.event_id.clone(),
+            });
+        }
+
+        // This reservation's own hold pays for the charge, so only the
+        // user's other holds are subtracted from the balance.
+        let spendable = lock_spendable(&mut db_tx, &event.user_id).await?;
+        let available =
+            spendable - sum_active_holds(&mut db_tx, &event.user_id, Some(&reservation_id)).await?;
+
+        if available < event.cost_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: available,
+                required: event.cost_cents,
+            });
+        }
+        check_agent_budgets(&mut db_tx, event).await?;
+
+        let new_balance = record_usage(&mut db_tx, event, &tx).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE credit_reservations
+            SET status = 'settled', transaction_id = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(&reservation_id)
+        .bind(tx.id.to_string())
+        .execute(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        insert_outbox_messages(&mut db_tx, outbox).await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(new_balance)
+    }
+
+    async fn release_reservation(&self, reservation_id: &ReservationId) -> Result<Reservation> {
+        let reservation_id = reservation_id.to_string();
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let row = sqlx::query_as::<_, ReservationRow>(
+            "SELECT * FROM credit_reservations WHERE id = $1 FOR UPDATE",
+        )
+        .bind(&reservation_id)
+        .fetch_optional(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .ok_or_else(|| StoreError::NotFound {
+            entity: "reservation",
+            id: reservation_id.clone(),
+        })?;
+        let mut reservation = row.into_reservation()?;
+
+        match reservation.status {
+            ReservationStatus::Settled => {
+                return Err(StoreError::InvalidState {
+                    entity: "reservation",
+                    id: reservation_id.clone(),
+                    state: reservation.status.as_str().to_string(),
+                });
+            }
+            ReservationStatus::Released | ReservationStatus::Expired => {
+                return Ok(reservation);
+            }
+            ReservationStatus::Active => {}
+        }
+
+        reservation.status = ReservationStatus::Released;
+        reservation.updated_at = chrono::Utc::now();
+
+        sqlx::query("UPDATE credit_reservations SET status = $2, updated_at = $3 WHERE id = $1")
+            .bind(&reservation_id)
+            .bind(reservation.status.as_str())
+            .bind(reservation.updated_at)
+            .execute(&mut *db_tx)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(reservation)
+    }
+
+    async fn expire_reservations(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize> {
+        let result = sqlx::query(
+            r#"
+            UPDATE credit_reservations
+            SET status = 'expired', updated_at = $1
+            WHERE status = 'active' AND expires_at <= $1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(usize::try_from(result.rows_affected()).unwrap_or(usize::MAX))
+    }
+
+    async fn list_credit_lots(&self, user_id: &UserId) -> Result<Vec<CreditLot>> {
+        let user_id = *user_id;
+        let rows = sqlx::query_as::<_, CreditLotRow>(
+            "SELECT * FROM credit_lots WHERE user_id = $1 AND remaining_cents > 0",
+        )
+        .bind(user_id.as_uuid())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let mut lots = rows
+            .into_iter()
+            .map(CreditLotRow::into_lot)
+            .collect::<Result<Vec<_>>>()?;
+        lot::sort_for_consumption(&mut lots);
+        Ok(lots)
+    }
+
+    async fn expire_credit_lots(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize> {
+        let due = sqlx::query_as::<_, (String, uuid::Uuid)>(
+            r#"
+            SELECT id, user_id FROM credit_lots
+            WHERE remaining_cents > 0 AND expires_at <= $1
+            ORDER BY expires_at
+            "#,
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let mut expired = 0;
+        for (lot_id, user_id) in due {
+            let user_id = UserId::from_uuid(user_id);
+            let mut db_tx = self
+                .pool
+                .begin()
+                .await
+                .map_err(|e| StoreError::Database(e.to_string()))?;
+
+            // Lock the account before the lot, matching the usage path.
+            lock_spendable(&mut db_tx, &user_id).await?;
+            let row = sqlx::query_as::<_, CreditLotRow>(
+                "SELECT * FROM credit_lots WHERE id = $1 AND remaining_cents > 0 FOR UPDATE",
+            )
+            .bind(&lot_id)
+            .fetch_optional(&mut *db_tx)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+            // Spent in full since the scan
+            let Some(lot) = row.map(CreditLotRow::into_lot).transpose()? else {
+                continue;
+            };
+
+            let new_balance = sqlx::query_scalar::<_, i64>(
+                r#"
+                UPDATE accounts
+                SET balance_cents = balance_cents - $2,
+                    updated_at = NOW()
+                WHERE user_id = $1
+                RETURNING balance_cents
+                "#,
+            )
+            .bind(user_id.as_uuid())
+            .bind(lot.remaining_cents)
+            .fetch_one(&mut *db_tx)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+            let tx = CreditTransaction::expiry(&lot, new_balance);
+            post_transaction(
+                &mut db_tx,
+                LedgerAccount::User(user_id),
+                &tx,
+                -lot.remaining_cents,
+                new_balance,
+            )
+            .await?;
+
+            sqlx::query("UPDATE credit_lots SET remaining_cents = 0 WHERE id = $1")
+                .bind(&lot_id)
+                .execute(&mut *db_tx)
+                .await
+                .map_err(|e| StoreError::Database(e.to_string()))?;
+
+            db_tx
+                .commit()
+                .await
+                .map_err(|e| StoreError::Database(e.to_string()))?;
+            expired += 1;
+        }
+
+        Ok(expired)
+    }
+
+    async fn get_credit_transfer(
+        &self,
+        from_user_id: &UserId,
+        idempotency_key: &str,
+    ) -> Result<Option<CreditTransfer>> {
+        let from_user_id = *from_user_id;
+        let idempotency_key = idempotency_key.to_string();
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        fetch_credit_transfer(&mut conn, &from_user_id, &idempotency_key).await
+    }
+
+    async fn put_promo_code(&self, promo: &PromoCode) -> Result<()> {
+        let plans = serde_json::to_value(&promo.plans)
+            .map_err(|e| StoreError::Serialization(e.to_string()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO promo_codes (code, amount_cents, max_redemptions, per_user_limit,
+                valid_from, valid_until, plans, active, description, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (code) DO UPDATE SET
+                amount_cents = $2,
+                max_redemptions = $3,
+                per_user_limit = $4,
+                valid_from = $5,
+                valid_until = $6,
+                plans = $7,
+                active = $8,
+                description = $9,
+                updated_at = $11
+            "#,
+        )
+        .bind(&promo.code)
+        .bind(promo.amount_cents)
+        .bind(promo.max_redemptions)
+        .bind(promo.per_user_limit)
+        .bind(promo.valid_from)
+        .bind(promo.valid_until)
+        .bind(plans)
+        .bind(promo.active)
+        .bind(&promo.description)
+        .bind(promo.created_at)
+        .bind(promo.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_promo_code(&self, code: &str) -> Result<Option<PromoCode>> {
+        let code = code.to_string();
+        let row = sqlx::query_as::<_, PromoCodeRow>("SELECT * FROM promo_codes WHERE code = $1")
+            .bind(&code)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        row.map(PromoCodeRow::into_promo).transpose()
+    }
+
+    async fn list_promo_codes(&self) -> Result<Vec<PromoCode>> {
+        let rows = sqlx::query_as::<_, PromoCodeRow>("SELECT * FROM promo_codes ORDER BY code")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        rows.into_iter().map(PromoCodeRow::into_promo).collect()
+    }
+
+    async fn delete_promo_code(&self, code: &str) -> Result<()> {
+        let code = code.to_string();
+        sqlx::query("DELETE FROM promo_codes WHERE code = $1")
+            .bind(&code)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn count_promo_redemptions(&self, code: &str, user_id: &UserId) -> Result<i64> {
+        let code = code.to_string();
+        let user_id = *user_id;
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        count_user_redemptions(&mut conn, &code, &user_id).await
+    }
+
+    async fn create_gift_card(
+        &self,
+        card: &GiftCard,
+        outbox: &[OutboxMessage],
+    ) -> Result<GiftCard> {
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO gift_cards (id, code, amount_cents, purchaser_id, status,
+                stripe_session_id, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (stripe_session_id) DO NOTHING
+            "#,
+        )
+        .bind(card.id.to_string())
+        .bind(&card.code)
+        .bind(card.amount_cents)
+        .bind(card.purchaser_id.as_uuid())
+        .bind(card.status.as_str())
+        .bind(&card.stripe_session_id)
+        .bind(card.created_at)
+        .bind(card.updated_at)
+        .execute(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .rows_affected();
+
+        if inserted == 0 {
+            // A redelivered checkout: hand back the card it already minted
+            let row = sqlx::query_as::<_, GiftCardRow>(
+                "SELECT * FROM gift_cards WHERE stripe_session_id = $1",
+            )
+            .bind(&card.stripe_session_id)
+            .fetch_one(&mut *db_tx)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+            return row.into_gift_card();
+        }
+
+        insert_outbox_messages(&mut db_tx, outbox).await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(card.clone())
+    }
+
+    async fn get_gift_card(&self, card_id: &GiftCardId) -> Result<Option<GiftCard>> {
+        let card_id = card_id.to_string();
+        let row = sqlx::query_as::<_, GiftCardRow>("SELECT * FROM gift_cards WHERE id = $1")
+            .bind(&card_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        row.map(GiftCardRow::into_gift_card).transpose()
+    }
+
+    async fn get_gift_card_by_code(&self, code: &str) -> Result<Option<GiftCard>> {
+        let code = code.to_string();
+        let row = sqlx::query_as::<_, GiftCardRow>("SELECT * FROM gift_cards WHERE code = $1")
+            .bind(&code)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        row.map(GiftCardRow::into_gift_card).transpose()
+    }
+
+    async fn list_gift_cards_by_purchaser(&self, user_id: &UserId) -> Result<Vec<GiftCard>> {
+        let user_id = *user_id;
+        let rows = sqlx::query_as::<_, GiftCardRow>(
+            "SELECT * FROM gift_cards WHERE purchaser_id = $1 ORDER BY created_at DESC, id DESC",
+        )
+        .bind(user_id.as_uuid())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        rows.into_iter().map(GiftCardRow::into_gift_card).collect()
+    }
+
+    async fn void_gift_card(&self, card_id: &GiftCardId, reason: &str) -> Result<GiftCard> {
+        let card_id = *card_id;
+        let reason = reason.to_string();
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let mut card = lock_issued_gift_card(&mut db_tx, &card_id).await?;
+        card.void(reason);
+
+        sqlx::query(
+            r#"
+            UPDATE gift_cards
+            SET status = $2, voided_at = $3, void_reason = $4, updated_at = $5
+            WHERE id = $1
+            "#,
+        )
+        .bind(card.id.to_string())
+        .bind(card.status.as_str())
+        .bind(card.voided_at)
+        .bind(&card.void_reason)
+        .bind(card.updated_at)
+        .execute(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(card)
+    }
+
+    async fn put_agent_budget(&self, budget: &AgentBudget) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO agent_budgets (user_id, agent_id, period, limit_cents,
+                created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_id, agent_id, period) DO UPDATE SET
+                limit_cents = $4,
+                updated_at = $6
+            "#,
+        )
+        .bind(budget.user_id.as_uuid())
+        .bind(budget.agent_id.as_uuid())
+        .bind(budget.period.as_str())
+        .bind(budget.limit_cents)
+        .bind(budget.created_at)
+        .bind(budget.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn list_agent_budgets(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+    ) -> Result<Vec<AgentBudget>> {
+        let user_id = *user_id;
+        let agent_id = *agent_id;
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        fetch_agent_budgets(&mut conn, &user_id, &agent_id).await
+    }
+
+    async fn delete_agent_budget(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+        period: BudgetPeriod,
+    ) -> Result<()> {
+        let user_id = *user_id;
+        let agent_id = *agent_id;
+        sqlx::query(
+            "DELETE FROM agent_budgets WHERE user_id = $1 AND agent_id = $2 AND period = $3",
+        )
+        .bind(user_id.as_uuid())
+        .bind(agent_id.as_uuid())
+        .bind(period.as_str())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn get_agent_spend(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+    ) -> Result<Option<AgentSpend>> {
+        let user_id = *user_id;
+        let agent_id = *agent_id;
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        fetch_agent_spend(&mut conn, &user_id, &agent_id).await
+    }
+
+    async fn list_agent_spend(&self, user_id: &UserId) -> Result<Vec<AgentSpend>> {
+        let user_id = *user_id;
+        let rows = sqlx::query_as::<_, AgentSpendRow>(
+            "SELECT * FROM agent_spend WHERE user_id = $1 ORDER BY agent_id",
+        )
+        .bind(user_id.as_uuid())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(rows.into_iter().map(AgentSpendRow::into_spend).collect())
+    }
+
+    async fn list_ledger_entries(
+        &self,
+        account: &LedgerAccount,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<LedgerEntry>> {
+        let account = account.to_string();
+        let rows = sqlx::query_as::<_, LedgerEntryRow>(
+            r#"
+            SELECT * FROM ledger_entries
+            WHERE account = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(&account)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .bind(i64::try_from(offset).unwrap_or(i64::MAX))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        rows.into_iter().map(LedgerEntryRow::into_entry).collect()
+    }
+
+    async fn ledger_balance(&self, account: &LedgerAccount) -> Result<i64> {
+        let account = account.to_string();
+        sqlx::query_scalar(
+            "SELECT COALESCE(SUM(amount_cents), 0)::BIGINT FROM ledger_entries WHERE account = $1",
+        )
+        .bind(&account)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    async fn verify_ledger(&self) -> Result<LedgerReport> {
+        let rows = sqlx::query_as::<_, LedgerDriftRow>(
+            r#"
+            SELECT 'user:' || a.user_id::TEXT AS account, a.balance_cents,
+                COALESCE(SUM(l.amount_cents), 0)::BIGINT AS ledger_cents
+            FROM accounts a
+            LEFT JOIN ledger_entries l ON l.account = 'user:' || a.user_id::TEXT
+            GROUP BY a.user_id, a.balance_cents
+            HAVING a.balance_cents <> COALESCE(SUM(l.amount_cents), 0)
+            UNION ALL
+            SELECT 'org:' || o.id::TEXT AS account, o.balance_cents,
+                COALESCE(SUM(l.amount_cents), 0)::BIGINT AS ledger_cents
+            FROM organizations o
+            LEFT JOIN ledger_entries l ON l.account = 'org:' || o.id::TEXT
+            GROUP BY o.id, o.balance_cents
+            HAVING o.balance_cents <> COALESCE(SUM(l.amount_cents), 0)
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let (accounts_checked, unbalanced_cents) = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            SELECT (SELECT COUNT(*) FROM accounts) + (SELECT COUNT(*) FROM organizations),
+                (SELECT COALESCE(SUM(amount_cents), 0)::BIGINT FROM ledger_entries)
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(LedgerReport {
+            accounts_checked: usize::try_from(accounts_checked).unwrap_or(0),
+            drift: rows
+                .into_iter()
+                .map(LedgerDriftRow::into_drift)
+                .collect::<Result<_>>()?,
+            unbalanced_cents,
+        })
+    }
+
+    async fn put_outbox_message(&self, message: &OutboxMessage) -> Result<()> {
+        let mut conn = self
+            .pool
+            .acquire()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+        upsert_outbox_message(&mut conn, message).await
+    }
+
+    async fn get_outbox_message(&self, id: &OutboxId) -> Result<Option<OutboxMessage>> {
+        let row = sqlx::query_as::<_, OutboxRow>("SELECT * FROM outbox WHERE id = $1")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        row.map(OutboxRow::into_message).transpose()
+    }
+
+    async fn list_outbox_messages(
+        &self,
+        status: Option<OutboxStatus>,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>> {
+        let rows = sqlx::query_as::<_, OutboxRow>(
+            r#"
+            SELECT * FROM outbox
+            WHERE $1 IS NULL OR status = $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+        )
+        .bind(status.map(|status| status.as_str()))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        rows.into_iter().map(OutboxRow::into_message).collect()
+    }
+
+    async fn claim_outbox_messages(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+        lease: chrono::Duration,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>> {
+        let rows = sqlx::query_as::<_, OutboxRow>(
+            r#"
+            UPDATE outbox SET next_attempt_at = $2
+            WHERE id IN (
+                SELECT id FROM outbox
+                WHERE status = 'pending' AND next_attempt_at <= $1
+                ORDER BY id
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(now)
+        .bind(now + lease)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let mut messages = rows
+            .into_iter()
+            .map(OutboxRow::into_message)
+            .collect::<Result<Vec<_>>>()?;
+        messages.sort_by_key(|message| *message.id.as_ulid());
+        Ok(messages)
+    }
+
+    async fn delete_outbox_message(&self, id: &OutboxId) -> Result<()> {
+        sqlx::query("DELETE FROM outbox WHERE id = $1")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn put_pricing_catalog(&self, catalog: &PricingCatalog) -> Result<()> {
+        let document =
+            serde_json::to_value(catalog).map_err(|e| StoreError::Serialization(e.to_string()))?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO pricing_catalog (version, catalog, created_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (version) DO NOTHING
+            "#,
+        )
+        .bind(&catalog.version)
+        .bind(document)
+        .bind(chrono::Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(StoreError::InvalidState {
+                entity: "pricing catalog",
+                id: catalog.version.clone(),
+                state: "already saved".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn get_latest_pricing_catalog(&self) -> Result<Option<PricingCatalog>> {
+        let document = sqlx::query_scalar::<_, serde_json::Value>(
+            "SELECT catalog FROM pricing_catalog ORDER BY created_at DESC LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        document
+            .map(|document| {
+                serde_json::from_value(document)
+                    .map_err(|e| StoreError::Serialization(e.to_string()))
+            })
+            .transpose()
+    }
+
+    async fn get_storage_meter(
+        &self,
+        user_id: &UserId,
+        resource_id: &str,
+    ) -> Result<Option<StorageMeter>> {
+        let row = sqlx::query_as::<_, (serde_json::Value, i64)>(
+            "SELECT meter, version FROM storage_meters WHERE user_id = $1 AND resource_id = $2",
+        )
+        .bind(user_id.as_uuid())
+        .bind(resource_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        row.map(|(document, version)| {
+            let mut meter: StorageMeter = serde_json::from_value(document)
+                .map_err(|e| StoreError::Serialization(e.to_string()))?;
+            meter.version = version;
+            Ok(meter)
+        })
+        .transpose()
+    }
+
+    async fn put_storage_meter_if_version(
+        &self,
+        meter: &StorageMeter,
+        expected_version: i64,
+    ) -> Result<i64> {
+        let mut document = meter.clone();
+        document.version = expected_version + 1;
+        let document = serde_json::to_value(&document)
+            .map_err(|e| StoreError::Serialization(e.to_string()))?;
+
+        let insert = expected_version == 0;
+        let mut query = sqlx::query(if insert {
+            r#"
+            INSERT INTO storage_meters (user_id, resource_id, meter, version, updated_at)
+            VALUES ($1, $2, $3, 1, $4)
+            ON CONFLICT (user_id, resource_id) DO NOTHING
+            "#
+        } else {
+            r#"
+            UPDATE storage_meters SET
+                meter = $3,
+                version = version + 1,
+                updated_at = $4
+            WHERE user_id = $1 AND resource_id = $2 AND version = $5
+            "#
+        })
+        .bind(meter.user_id.as_uuid())
+        .bind(&meter.resource_id)
+        .bind(document)
+        .bind(chrono::Utc::now());
+        if !insert {
+            query = query.bind(expected_version);
+        }
+        let written = query
+            .execute(&self.pool)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .rows_affected();
+
+        if written == 0 {
+            let actual = self
+                .get_storage_meter(&meter.user_id, &meter.resource_id)
+                .await?
+                .map_or(0, |stored| stored.version);
+            return Err(StoreError::VersionConflict {
+                entity: "StorageMeter",
+                id: format!("{}/{}", meter.user_id, meter.resource_id),
+                expected: expected_version,
+                actual,
+            });
+        }
+        Ok(expected_version + 1)
+    }
+
+    async fn list_accounts(&self, after: Option<&UserId>, limit: usize) -> Result<Vec<Account>> {
+        let rows = sqlx::query_as::<_, AccountRow>(
+            r#"
+            SELECT * FROM accounts
+            WHERE $1::uuid IS NULL OR user_id > $1
+            ORDER BY user_id
+            LIMIT $2
+            "#,
+        )
+        .bind(after.map(|id| *id.as_uuid()))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(rows.into_iter().map(AccountRow::into_account).collect())
+    }
+
+    async fn list_transactions(
+        &self,
+        after: Option<&TransactionId>,
+        limit: usize,
+    ) -> Result<Vec<CreditTransaction>> {
+        let rows = sqlx::query_as::<_, TransactionRow>(
+            r#"
+            SELECT * FROM credit_transactions
+            WHERE $1::text IS NULL OR id COLLATE "C" > $1
+            ORDER BY id COLLATE "C"
+            LIMIT $2
+            "#,
+        )
+        .bind(after.map(ToString::to_string))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(TransactionRow::into_transaction)
+            .collect())
+    }
+
+    async fn list_usage_events(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<UsageEvent>> {
+        let rows = sqlx::query_as::<_, UsageEventRow>(
+            r#"
+            SELECT * FROM usage_events
+            WHERE $1::text IS NULL OR event_id COLLATE "C" > $1
+            ORDER BY event_id COLLATE "C"
+            LIMIT $2
+            "#,
+        )
+        .bind(after)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(UsageEventRow::into_usage_event)
+            .collect())
+    }
+
+    async fn list_webhook_events(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ProcessedWebhook>> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT event_id, source FROM processed_webhooks
+            WHERE $1::text IS NULL OR event_id COLLATE "C" > $1
+            ORDER BY event_id COLLATE "C"
+            LIMIT $2
+            "#,
+        )
+        .bind(after)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(event_id, source)| ProcessedWebhook { event_id, source })
+            .collect())
+    }
+
+    async fn count_unlisted(&self) -> Result<Vec<(&'static str, u64)>> {
+        const QUERIES: [(&str, &str); 7] = [
+            (
+                "credit_lots",
+                "SELECT COUNT(*) FROM credit_lots WHERE remaining_cents > 0",
+            ),
+            ("org_memberships", "SELECT COUNT(*) FROM org_memberships"),
+            ("agent_budgets", "SELECT COUNT(*) FROM agent_budgets"),
+            ("promo_codes", "SELECT COUNT(*) FROM promo_codes"),
+            (
+                "gift_cards",
+                "SELECT COUNT(*) FROM gift_cards WHERE status = 'issued'",
+            ),
+            ("credit_grants", "SELECT COUNT(*) FROM credit_grants"),
+            ("storage_meters", "SELECT COUNT(*) FROM storage_meters"),
+        ];
+
+        let mut counts = Vec::with_capacity(QUERIES.len());
+        for (kind, query) in QUERIES {
+            let count = sqlx::query_scalar::<_, i64>(query)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| StoreError::Database(e.to_string()))?;
+            counts.push((kind, u64::try_from(count).unwrap_or(0)));
+        }
+        Ok(counts)
+    }
+
+    async fn process_usage(
+        &self,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let tx = transaction.clone();
+        // Use a database transaction for atomicity
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        // Check idempotency
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM usage_events WHERE event_id = $1)",
+        )
+        .bind(&event.event_id)
+        .fetch_one(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        if exists {
+            return Err(StoreError::DuplicateEvent {
+                event_id: event.event_id.clone(),
+            });
+        }
+
+        // Lock and check balance, holding back active reservations
+        let spendable = lock_spendable(&mut db_tx, &event.user_id).await?;
+        let available = spendable - sum_active_holds(&mut db_tx, &event.user_id, None).await?;
+
+        if available < event.cost_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: available,
+                required: event.cost_cents,
+            });
+        }
+
+        // Check the agent's budgets (serialized by the account lock)
+        check_agent_budgets(&mut db_tx, event).await?;
+
+        let new_balance = record_usage(&mut db_tx, event, &tx).await?;
+
+        insert_outbox_messages(&mut db_tx, outbox).await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(new_balance)
+    }
+
+    async fn process_org_usage(
+        &self,
+        org_id: &OrgId,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let org_id = *org_id;
+        let tx = transaction.clone();
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS(SELECT 1 FROM usage_events WHERE event_id = $1)",
+        )
+        .bind(&event.event_id)
+        .fetch_one(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        if exists {
+            return Err(StoreError::DuplicateEvent {
+                event_id: event.event_id.clone(),
+            });
+        }
+
+        // Agent spend updates are serialized by the member's account lock,
+        // taken before the organization's as transfers do
+        if event.agent_id.is_some() {
+            lock_account(&mut db_tx, &event.user_id).await?;
+        }
+
+        // Lock the org account row; this also serializes members' cap checks
+        let balance = sqlx::query_scalar::<_, i64>(
+            "SELECT balance_cents FROM organizations WHERE id = $1 FOR UPDATE",
+        )
+        .bind(org_id.as_uuid())
+        .fetch_optional(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .ok_or(StoreError::NotFound {
+            entity: "Organization",
+            id: org_id.to_string(),
+        })?;
+
+        let cap = sqlx::query_scalar::<_, Option<i64>>(
+            r#"
+            SELECT monthly_spend_cap_cents FROM org_memberships
+            WHERE org_id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(event.user_id.as_uuid())
+        .fetch_optional(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .ok_or(StoreError::NotFound {
+            entity: "OrgMembership",
+            id: format!("{org_id}/{}", event.user_id),
+        })?;
+
+        if let Some(cap) = cap {
+            let since = org::spend_cap_period_start(chrono::Utc::now());
+            let spent = sum_org_member_spend(&mut db_tx, &org_id, &event.user_id, since).await?;
+            if spent + event.cost_cents > cap {
+                return Err(StoreError::SpendCapExceeded {
+                    cap,
+                    spent,
+                    required: event.cost_cents,
+                });
+            }
+        }
+
+        if balance < event.cost_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance,
+                required: event.cost_cents,
+            });
+        }
+        check_agent_budgets(&mut db_tx, event).await?;
+
+        let new_balance = sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE organizations
+            SET balance_cents = balance_cents - $2,
+                lifetime_used_cents = lifetime_used_cents + $2,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING balance_cents
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(event.cost_cents)
+        .fetch_one(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        post_transaction(
+            &mut db_tx,
+            LedgerAccount::Org(org_id),
+            &tx,
+            -event.cost_cents,
+            new_balance,
+        )
+        .await?;
+        insert_usage_event(&mut db_tx, event).await?;
+        record_agent_spend(&mut db_tx, event).await?;
+
+        insert_outbox_messages(&mut db_tx, outbox).await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(new_balance)
+    }
+
+    async fn add_org_credits(
+        &self,
+        org_id: &OrgId,
+        amount_cents: i64,
+        transaction: &CreditTransaction,
+    ) -> Result<i64> {
+        let org_id = *org_id;
+        let tx = transaction.clone();
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let new_balance = sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE organizations
+            SET balance_cents = balance_cents + $2,
+                lifetime_purchased_cents = lifetime_purchased_cents + GREATEST($2, 0),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING balance_cents
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(amount_cents)
+        .fetch_optional(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .ok_or(StoreError::NotFound {
+            entity: "Organization",
+            id: org_id.to_string(),
+        })?;
+
+        post_transaction(
+            &mut db_tx,
+            LedgerAccount::Org(org_id),
+            &tx,
+            amount_cents,
+            new_balance,
+        )
+        .await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(new_balance)
+    }
+
+    async fn add_credits(
+        &self,
+        user_id: &UserId,
+        amount_cents: i64,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+        let new_balance =
+            credit_account(&mut db_tx, *user_id, amount_cents, transaction, outbox).await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(new_balance)
+    }
+
+    async fn grant_once(
+        &self,
+        user_id: &UserId,
+        grant_key: &str,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<Option<i64>> {
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        // Claim the key first; a concurrent grant waits here and then sees it
+        let claimed = sqlx::query(
+            r#"
+            INSERT INTO credit_grants (user_id, grant_key, transaction_id, created_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id, grant_key) DO NOTHING
+            "#,
+        )
+        .bind(user_id.as_uuid())
+        .bind(grant_key)
+        .bind(transaction.id.to_string())
+        .bind(transaction.created_at)
+        .execute(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .rows_affected();
+        if claimed == 0 {
+            return Ok(None);
+        }
+
+        let new_balance = credit_account(
+            &mut db_tx,
+            *user_id,
+            transaction.amount_cents,
+            transaction,
+            outbox,
+        )
+        .await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(Some(new_balance))
+    }
+
+    async fn reverse_usage(
+        &self,
+        reversal: &UsageReversal,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<UsageReversal> {
+        let tx = transaction.clone();
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        // Lock the event so concurrent reversals of it are serialized
+        let charged = sqlx::query_scalar::<_, i64>(
+            "SELECT cost_cents FROM usage_events WHERE event_id = $1 FOR UPDATE",
+        )
+        .bind(&reversal.event_id)
+        .fetch_optional(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .ok_or_else(|| StoreError::NotFound {
+            entity: "UsageEvent",
+            id: reversal.event_id.clone(),
+        })?;
+
+        let existing = fetch_usage_reversals(&mut db_tx, &reversal.event_id).await?;
+        if let Some(found) = existing
+            .iter()
+            .find(|r| r.reversal_id == reversal.reversal_id)
+        {
+            return Ok(found.clone());
+        }
+
+        let reversed: i64 = existing.iter().map(|r| r.amount_cents).sum();
+        if reversed + reversal.amount_cents > charged {
+            return Err(StoreError::ReversalExceedsCharge {
+                charged,
+                reversed,
+                requested: reversal.amount_cents,
+            });
+        }
+
+        let new_balance = credit_reversal(
+            &mut db_tx,
+            &tx,
+            &reversal.event_id,
+            charged,
+            reversed,
+            reversal.amount_cents,
+        )
+        .await?;
+        post_transaction(
+            &mut db_tx,
+            LedgerAccount::wallet_for(&tx),
+            &tx,
+            reversal.amount_cents,
+            new_balance,
+        )
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO usage_reversals (event_id, reversal_id, amount_cents,
+                transaction_id, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(&reversal.event_id)
+        .bind(&reversal.reversal_id)
+        .bind(reversal.amount_cents)
+        .bind(reversal.transaction_id.to_string())
+        .bind(reversal.created_at)
+        .execute(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        insert_outbox_messages(&mut db_tx, outbox).await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(reversal.clone())
+    }
+
+    async fn transfer_credits(
+        &self,
+        transfer: &CreditTransfer,
+        debit: &CreditTransaction,
+        credit: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<CreditTransfer> {
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+        let from_user_id = transfer.from_user_id;
+
+        // Lock both personal accounts in a fixed order so opposing
+        // transfers can't deadlock
+        let mut user_ids = vec![*from_user_id.as_uuid()];
+        if let LedgerAccount::User(to_user_id) = transfer.to {
+            user_ids.push(*to_user_id.as_uuid());
+        }
+        sqlx::query(
+            "SELECT user_id FROM accounts WHERE user_id = ANY($1) ORDER BY user_id FOR UPDATE",
+        )
+        .bind(&user_ids)
+        .execute(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        if let Some(existing) =
+            fetch_credit_transfer(&mut db_tx, &from_user_id, &transfer.idempotency_key).await?
+        {
+            return Ok(existing);
+        }
+
+        let sender_balance =
+            debit_transfer(&mut db_tx, &from_user_id, transfer.amount_cents).await?;
+        post_transaction(
+            &mut db_tx,
+            LedgerAccount::User(from_user_id),
+            debit,
+            -transfer.amount_cents,
+            sender_balance,
+        )
+        .await?;
+
+        let recipient_balance =
+            credit_transfer(&mut db_tx, &transfer.to, credit, transfer.amount_cents).await?;
+        post_transaction(
+            &mut db_tx,
+            transfer.to,
+            credit,
+            transfer.amount_cents,
+            recipient_balance,
+        )
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO credit_transfers (from_user_id, idempotency_key, to_account,
+                amount_cents, out_transaction_id, in_transaction_id, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(from_user_id.as_uuid())
+        .bind(&transfer.idempotency_key)
+        .bind(transfer.to.to_string())
+        .bind(transfer.amount_cents)
+        .bind(transfer.out_transaction_id.to_string())
+        .bind(transfer.in_transaction_id.to_string())
+        .bind(transfer.created_at)
+        .execute(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        insert_outbox_messages(&mut db_tx, outbox).await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(transfer.clone())
+    }
+
+    async fn redeem_promo_code(
+        &self,
+        redemption: &PromoRedemption,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let tx = transaction.clone();
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        // Lock the code so concurrent redemptions of it are serialized
+        let promo = sqlx::query_as::<_, PromoCodeRow>(
+            "SELECT * FROM promo_codes WHERE code = $1 FOR UPDATE",
+        )
+        .bind(&redemption.code)
+        .fetch_optional(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .ok_or_else(|| StoreError::NotFound {
+            entity: "PromoCode",
+            id: redemption.code.clone(),
+        })?
+        .into_promo()?;
+
+        let account =
+            sqlx::query_as::<_, AccountRow>("SELECT * FROM accounts WHERE user_id = $1 FOR UPDATE")
+                .bind(redemption.user_id.as_uuid())
+                .fetch_optional(&mut *db_tx)
+                .await
+                .map_err(|e| StoreError::Database(e.to_string()))?
+                .ok_or(StoreError::NotFound {
+                    entity: "account",
+                    id: redemption.user_id.to_string(),
+                })?
+                .into_account();
+
+        let redeemed = count_user_redemptions(&mut db_tx, &promo.code, &redemption.user_id).await?;
+        promo
+            .check_redeemable(&account.current_plan(), redeemed, chrono::Utc::now())
+            .map_err(|reason| StoreError::PromoRejected {
+                code: promo.code.clone(),
+                reason,
+            })?;
+
+        sqlx::query(
+            "UPDATE promo_codes SET redemption_count = redemption_count + 1 WHERE code = $1",
+        )
+        .bind(&promo.code)
+        .execute(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        let new_balance = sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE accounts
+            SET balance_cents = balance_cents + $2,
+                lifetime_granted_cents = lifetime_granted_cents + $2,
+                overdraft_locked = overdraft_locked AND balance_cents + $2 <= 0,
+                updated_at = NOW()
+            WHERE user_id = $1
+            RETURNING balance_cents
+            "#,
+        )
+        .bind(redemption.user_id.as_uuid())
+        .bind(redemption.amount_cents)
+        .fetch_one(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        post_transaction(
+            &mut db_tx,
+            LedgerAccount::User(redemption.user_id),
+            &tx,
+            redemption.amount_cents,
+            new_balance,
+        )
+        .await?;
+
+        let mut lot = CreditLot::new(
+            redemption.user_id,
+            tx.transaction_type.clone(),
+            redemption.amount_cents,
+            None,
+        );
+        lot.cap_to_balance(new_balance);
+        insert_credit_lot(&mut db_tx, &lot).await?;
+
+        insert_promo_redemption(&mut db_tx, redemption).await?;
+
+        insert_outbox_messages(&mut db_tx, outbox).await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(new_balance)
+    }
+
+    async fn redeem_gift_card(
+        &self,
+        card_id: &GiftCardId,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let card_id = *card_id;
+        let tx = transaction.clone();
+        let mut db_tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        // Locks the card so it cannot be redeemed or voided concurrently
+        let mut card = lock_issued_gift_card(&mut db_tx, &card_id).await?;
+        card.redeem(tx.user_id, tx.id);
+
+        let new_balance = sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE accounts
+            SET balance_cents = balance_cents + $2,
+                lifetime_purchased_cents = lifetime_purchased_cents + $2,
+                overdraft_locked = overdraft_locked AND balance_cents + $2 <= 0,
+                updated_at = NOW()
+            WHERE user_id = $1
+            RETURNING balance_cents
+            "#,
+        )
+        .bind(tx.user_id.as_uuid())
+        .bind(card.amount_cents)
+        .fetch_optional(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .ok_or(StoreError::NotFound {
+            entity: "account",
+            id: tx.user_id.to_string(),
+        })?;
+
+        post_transaction(
+            &mut db_tx,
+            LedgerAccount::User(tx.user_id),
+            &tx,
+            card.amount_cents,
+            new_balance,
+        )
+        .await?;
+
+        let mut lot = CreditLot::new(
+            tx.user_id,
+            tx.transaction_type.clone(),
+            card.amount_cents,
+            None,
+        );
+        lot.cap_to_balance(new_balance);
+        insert_credit_lot(&mut db_tx, &lot).await?;
+
+        sqlx::query(
+            r#"
+            UPDATE gift_cards
+            SET status = $2, redeemed_by = $3, transaction_id = $4, redeemed_at = $5,
+                updated_at = $6
+            WHERE id = $1
+            "#,
+        )
+        .bind(card.id.to_string())
+        .bind(card.status.as_str())
+        .bind(tx.user_id.as_uuid())
+        .bind(tx.id.to_string())
+        .bind(card.redeemed_at)
+        .bind(card.updated_at)
+        .execute(&mut *db_tx)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        insert_outbox_messages(&mut db_tx, outbox).await?;
+
+        db_tx
+            .commit()
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(new_balance)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Shared query helpers
+// ---------------------------------------------------------------------------
+
+/// Lock an account row for the rest of the transaction and return the credits
+/// available for usage: the balance plus any unlocked credit line.
+async fn lock_spendable(conn: &mut sqlx::PgConnection, user_id: &UserId) -> Result<i64> {
+    sqlx::query_scalar::<_, i64>(
+        r#"
+        SELECT CASE WHEN overdraft_locked THEN balance_cents
+            ELSE balance_cents + credit_limit_cents END
+        FROM accounts
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(user_id.as_uuid())
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?
+    .ok_or(StoreError::NotFound {
+        entity: "account",
+        id: user_id.to_string(),
+    })
+}
+
+/// Lock a user's account row, if there is one, without reading it.
+async fn lock_account(conn: &mut sqlx::PgConnection, user_id: &UserId) -> Result<()> {
+    sqlx::query("SELECT user_id FROM accounts WHERE user_id = $1 FOR UPDATE")
+        .bind(user_id.as_uuid())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+    Ok(())
+}
+
+/// Sum a user's active, unexpired holds, optionally excluding one reservation.
+async fn sum_active_holds(
+    conn: &mut sqlx::PgConnection,
+    user_id: &UserId,
+    exclude: Option<&str>,
+) -> Result<i64> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(SUM(amount_cents), 0)::BIGINT
+        FROM credit_reservations
+        WHERE user_id = $1
+          AND status = 'active'
+          AND expires_at > NOW()
+          AND ($2::TEXT IS NULL OR id <> $2)
+        "#,
+    )
+    .bind(user_id.as_uuid())
+    .bind(exclude)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))
+}
+
+/// Debit a usage event and record its transaction and event rows.
+///
+/// The caller must already hold the account lock and have checked the balance.
+async fn record_usage(
+    conn: &mut sqlx::PgConnection,
+    event: &UsageEvent,
+    tx: &CreditTransaction,
+) -> Result<i64> {
+    // Deduct credits
+    let new_balance = sqlx::query_scalar::<_, i64>(
+        r#"
+        UPDATE accounts
+        SET balance_cents = balance_cents - $2,
+            lifetime_used_cents = lifetime_used_cents + $2,
+            overdraft_locked = overdraft_locked
+                OR (credit_limit_cents > 0 AND balance_cents - $2 <= -credit_limit_cents),
+            updated_at = NOW()
+        WHERE user_id = $1
+        RETURNING balance_cents
+        "#,
+    )
+    .bind(event.user_id.as_uuid())
+    .bind(event.cost_cents)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
+
+    // Record transaction
+    post_transaction(
+        &mut *conn,
+        LedgerAccount::User(event.user_id),
+        tx,
+        -event.cost_cents,
+        new_balance,
+    )
+    .await?;
+
+    // Record usage event
+    insert_usage_event(&mut *conn, event).await?;
+
+    // Spend credit lots in consumption order
+    draw_down_usage_lots(&mut *conn, event).await?;
+
+    // Add to the agent's spend totals
+    record_agent_spend(&mut *conn, event).await?;
+
+    Ok(new_balance)
+}
+
+/// Add `event.cost_cents` to its agent's spend totals, if it has an agent.
+async fn record_agent_spend(conn: &mut sqlx::PgConnection, event: &UsageEvent) -> Result<()> {
+    if let Some(agent_id) = event.agent_id {
+        let now = chrono::Utc::now();
+        let mut totals = fetch_agent_spend(&mut *conn, &event.user_id, &agent_id)
+            .await?
+            .unwrap_or_else(|| AgentSpend::new(event.user_id, agent_id, now));
+        totals.record(event.cost_cents, now);
+        upsert_agent_spend(&mut *conn, &totals).await?;
+    }
+    Ok(())
+}
+
+/// Load an agent's budgets.
+async fn fetch_agent_budgets(
+    conn: &mut sqlx::PgConnection,
+    user_id: &UserId,
+    agent_id: &AgentId,
+) -> Result<Vec<AgentBudget>> {
+    let rows = sqlx::query_as::<_, AgentBudgetRow>(
+        "SELECT * FROM agent_budgets WHERE user_id = $1 AND agent_id = $2",
+    )
+    .bind(user_id.as_uuid())
+    .bind(agent_id.as_uuid())
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
+
+    rows.into_iter().map(AgentBudgetRow::into_budget).collect()
+}
+
+/// Load an agent's spend totals.
+async fn fetch_agent_spend(
+    conn: &mut sqlx::PgConnection,
+    user_id: &UserId,
+    agent_id: &AgentId,
+) -> Result<Option<AgentSpend>> {
+    let row = sqlx::query_as::<_, AgentSpendRow>(
+        "SELECT * FROM agent_spend WHERE user_id = $1 AND agent_id = $2",
+    )
+    .bind(user_id.as_uuid())
+    .bind(agent_id.as_uuid())
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
+
+    Ok(row.map(AgentSpendRow::into_spend))
+}
+
+/// Write an agent's spend totals.
+async fn upsert_agent_spend(conn: &mut sqlx::PgConnection, totals: &AgentSpend) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO agent_spend (user_id, agent_id, day_start, day_cents, month_start,
+            month_cents, lifetime_cents, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (user_id, agent_id) DO UPDATE SET
+            day_start = $3,
+            day_cents = $4,
+            month_start = $5,
+            month_cents = $6,
+            lifetime_cents = $7,
+            updated_at = $8
+        "#,
+    )
+    .bind(totals.user_id.as_uuid())
+    .bind(totals.agent_id.as_uuid())
+    .bind(totals.day_start)
+    .bind(totals.day_cents)
+    .bind(totals.month_start)
+    .bind(totals.month_cents)
+    .bind(totals.lifetime_cents)
+    .bind(totals.updated_at)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Reject `event` if it would push its agent over any of its budgets.
+///
+/// The caller must hold the account lock, which serializes the user's
+/// agent spend updates.
+async fn check_agent_budgets(conn: &mut sqlx::PgConnection, event: &UsageEvent) -> Result<()> {
+    let Some(agent_id) = event.agent_id else {
+        return Ok(());
+    };
+    let budgets = fetch_agent_budgets(&mut *conn, &event.user_id, &agent_id).await?;
+    if budgets.is_empty() {
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now();
+    let totals = fetch_agent_spend(&mut *conn, &event.user_id, &agent_id)
+        .await?
+        .unwrap_or_else(|| AgentSpend::new(event.user_id, agent_id, now));
+    if let Some((budget, spent)) = budget::exceeded_budget(&budgets, &totals, event.cost_cents, now)
+    {
+        return Err(StoreError::BudgetExceeded {
+            agent_id,
+            period: budget.period,
+            limit: budget.limit_cents,
+            spent,
+            required: event.cost_cents,
+        });
+    }
+
+    Ok(())
+}
+
+/// Insert a usage event row.
+async fn insert_usage_event(conn: &mut sqlx::PgConnection, event: &UsageEvent) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO usage_events (event_id, user_id, agent_id, source, metric,
+            quantity, cost_cents, event_timestamp, metadata, transaction_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+    )
+    .bind(&event.event_id)
+    .bind(event.user_id.as_uuid())
+    .bind(event.agent_id.map(|a| *a.as_uuid()))
+    .bind(serde_json::to_value(&event.source).unwrap_or_default())
+    .bind(serde_json::to_value(&event.metric).unwrap_or_default())
+    .bind(event.quantity)
+    .bind(event.cost_cents)
+    .bind(event.timestamp)
+    .bind(&event.metadata)
+    .bind(event.transaction_id.map(|id| id.to_string()))
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Credit a usage refund to whichever balance the usage was charged against
+/// and return the new balance.
+///
+/// Personal refunds refill the lots the usage drew from (see
+/// [`lot::refund_draws`]); organization pools have no lots.
+async fn credit_reversal(
+    conn: &mut sqlx::PgConnection,
+    tx: &CreditTransaction,
+    event_id: &str,
+    charged_cents: i64,
+    reversed_cents: i64,
+    amount_cents: i64,
+) -> Result<i64> {
+    if let Some(org_id) = tx.org_id {
+        return sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE organizations
+            SET balance_cents = balance_cents + $2,
+                lifetime_used_cents = lifetime_used_cents - $2,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING balance_cents
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(amount_cents)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .ok_or(StoreError::NotFound {
+            entity: "Organization",
+            id: org_id.to_string(),
+        });
+    }
+
+    let new_balance = sqlx::query_scalar::<_, i64>(
+        r#"
+        UPDATE accounts
+        SET balance_cents = balance_cents + $2,
+            lifetime_used_cents = lifetime_used_cents - $2,
+            overdraft_locked = overdraft_locked AND balance_cents + $2 <= 0,
+            updated_at = NOW()
+        WHERE user_id = $1
+        RETURNING balance_cents
+        "#,
+    )
+    .bind(tx.user_id.as_uuid())
+    .bind(amount_cents)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?
+    .ok_or(StoreError::NotFound {
+        entity: "account",
+        id: tx.user_id.to_string(),
+    })?;
+
+    let draws = fetch_lot_draws(&mut *conn, event_id).await?;
+    let lots = lot::refund_draws(
+        tx.user_id,
+        &draws,
+        charged_cents,
+        reversed_cents,
+        amount_cents,
+        new_balance,
+    );
+    for lot in &lots {
+        insert_credit_lot(&mut *conn, lot).await?;
+    }
+
+    Ok(new_balance)
+}
+
+/// Load what a usage event took from credit lots. Events charged before
+/// draws were recorded have none.
+async fn fetch_lot_draws(conn: &mut sqlx::PgConnection, event_id: &str) -> Result<Vec<LotDraw>> {
+    let draws = sqlx::query_scalar::<_, Option<serde_json::Value>>(
+        "SELECT lot_draws FROM usage_events WHERE event_id = $1",
+    )
+    .bind(event_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
+
+    draws
+        .map(|draws| {
+            serde_json::from_value(draws).map_err(|e| StoreError::Serialization(e.to_string()))
+        })
+        .transpose()
+        .map(Option::unwrap_or_default)
+}
+
+/// Fetch the reversals recorded against a usage event, oldest first.
+async fn fetch_usage_reversals(
+    conn: &mut sqlx::PgConnection,
+    event_id: &str,
+) -> Result<Vec<UsageReversal>> {
+    let rows = sqlx::query_as::<_, UsageReversalRow>(
+        "SELECT * FROM usage_reversals WHERE event_id = $1 ORDER BY created_at, reversal_id",
+    )
+    .bind(event_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
+
+    rows.into_iter()
+        .map(UsageReversalRow::into_usage_reversal)
+        .collect()
+}
+
+/// Debit the sender of a transfer and return their new balance.
+///
+/// Only the unreserved balance is available, the credit line never is, and
+/// the credits are drawn from the sender's transferable lots. The caller
+/// must already hold the account lock.
+async fn debit_transfer(
+    conn: &mut sqlx::PgConnection,
+    user_id: &UserId,
+    amount_cents: i64,
+) -> Result<i64> {
+    let balance =
+        sqlx::query_scalar::<_, i64>("SELECT balance_cents FROM accounts WHERE user_id = $1")
+            .bind(user_id.as_uuid())
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .ok_or(StoreError::NotFound {
+                entity: "account",
+                id: user_id.to_string(),
+            })?;
+    let available = balance - sum_active_holds(conn, user_id, None).await?;
+    if available < amount_cents {
+        return Err(StoreError::InsufficientCredits {
+            balance: available,
+            required: amount_cents,
+        });
+    }
+
+    let mut lots = lock_credit_lots(conn, user_id).await?;
+    let transferable = lot::transferable_cents(&lots);
+    if transferable < amount_cents {
+        return Err(StoreError::TransferExceedsPurchased {
+            transferable,
+            requested: amount_cents,
+        });
+    }
+    let touched = lot::draw_down_transferable(&mut lots, amount_cents);
+    update_credit_lots(conn, &lots[..touched]).await?;
+
+    sqlx::query_scalar::<_, i64>(
+        r#"
+        UPDATE accounts
+        SET balance_cents = balance_cents - $2,
+            updated_at = NOW()
+        WHERE user_id = $1
+        RETURNING balance_cents
+        "#,
+    )
+    .bind(user_id.as_uuid())
+    .bind(amount_cents)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))
+}
+
+/// Credit the recipient of a transfer and return its new balance.
+///
+/// A user recipient gets the credits as a new lot; organization pools have
+/// no lots.
+async fn credit_transfer(
+    conn: &mut sqlx::PgConnection,
+    to: &LedgerAccount,
+    tx: &CreditTransaction,
+    amount_cents: i64,
+) -> Result<i64> {
+    match to {
+        LedgerAccount::User(user_id) => {
+            let new_balance = sqlx::query_scalar::<_, i64>(
+                r#"
+                UPDATE accounts
+                SET balance_cents = balance_cents + $2,
+                    overdraft_locked = overdraft_locked AND balance_cents + $2 <= 0,
+                    updated_at = NOW()
+                WHERE user_id = $1
+                RETURNING balance_cents
+                "#,
+            )
+            .bind(user_id.as_uuid())
+            .bind(amount_cents)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .ok_or(StoreError::NotFound {
+                entity: "account",
+                id: user_id.to_string(),
+            })?;
+
+            let mut lot = CreditLot::new(*user_id, tx.transaction_type.clone(), amount_cents, None);
+            lot.cap_to_balance(new_balance);
+            insert_credit_lot(conn, &lot).await?;
+
+            Ok(new_balance)
+        }
+        LedgerAccount::Org(org_id) => sqlx::query_scalar::<_, i64>(
+            r#"
+            UPDATE organizations
+            SET balance_cents = balance_cents + $2,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING balance_cents
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(amount_cents)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?
+        .ok_or(StoreError::NotFound {
+            entity: "Organization",
+            id: org_id.to_string(),
+        }),
+        LedgerAccount::System(_) => Err(StoreError::NotFound {
+            entity: "Wallet",
+            id: to.to_string(),
+        }),
+    }
+}
+
+/// Fetch the transfer a user made with the given idempotency key.
+async fn fetch_credit_transfer(
+    conn: &mut sqlx::PgConnection,
+    from_user_id: &UserId,
+    idempotency_key: &str,
+) -> Result<Option<CreditTransfer>> {
+    let row = sqlx::query_as::<_, CreditTransferRow>(
+        "SELECT * FROM credit_transfers WHERE from_user_id = $1 AND idempotency_key = $2",
+    )
+    .bind(from_user_id.as_uuid())
+    .bind(idempotency_key)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
+
+    row.map(CreditTransferRow::into_transfer).transpose()
+}
+
+/// Count how many times a user has redeemed a promo code.
+async fn count_user_redemptions(
+    conn: &mut sqlx::PgConnection,
+    code: &str,
+    user_id: &UserId,
+) -> Result<i64> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*)::BIGINT FROM promo_redemptions WHERE code = $1 AND user_id = $2",
+    )
+    .bind(code)
+    .bind(user_id.as_uuid())
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))
+}
+
+/// Lock a gift card for update, checking it exists and is still issued.
+async fn lock_issued_gift_card(
+    conn: &mut sqlx::PgConnection,
+    card_id: &GiftCardId,
+) -> Result<GiftCard> {
+    let card =
+        sqlx::query_as::<_, GiftCardRow>("SELECT * FROM gift_cards WHERE id = $1 FOR UPDATE")
+            .bind(card_id.to_string())
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .ok_or_else(|| StoreError::NotFound {
+                entity: "GiftCard",
+                id: card_id.to_string(),
+            })?
+            .into_gift_card()?;
+
+    if card.status != GiftCardStatus::Issued {
+        return Err(StoreError::InvalidState {
+            entity: "GiftCard",
+            id: card_id.to_string(),
+            state: card.status.as_str().to_string(),
+        });
+    }
+    Ok(card)
+}
+
+/// Record a promo code redemption.
+async fn insert_promo_redemption(
+    conn: &mut sqlx::PgConnection,
+    redemption: &PromoRedemption,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO promo_redemptions (transaction_id, code, user_id, amount_cents, redeemed_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(redemption.transaction_id.to_string())
+    .bind(&redemption.code)
+    .bind(redemption.user_id.as_uuid())
+    .bind(redemption.amount_cents)
+    .bind(redemption.redeemed_at)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Sum a member's usage charged to an organization since `since`.
+async fn sum_org_member_spend(
+    conn: &mut sqlx::PgConnection,
+    org_id: &OrgId,
+    user_id: &UserId,
+    since: chrono::DateTime<chrono::Utc>,
+) -> Result<i64> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(SUM(-amount_cents), 0)::BIGINT
+        FROM credit_transactions
+        WHERE org_id = $1
+          AND user_id = $2
+          AND transaction_type = 'usage'
+          AND created_at >= $3
+        "#,
+    )
+    .bind(org_id.as_uuid())
+    .bind(user_id.as_uuid())
+    .bind(since)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))
+}
+
+/// Insert a credit transaction row with the given resulting balance.
+async fn insert_transaction(
+    conn: &mut sqlx::PgConnection,
+    tx: &CreditTransaction,
+    balance_after_cents: i64,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO credit_transactions (id, user_id, org_id, amount_cents, transaction_type,
+            balance_after_cents, description, metadata, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        "#,
+    )
+    .bind(tx.id.to_string())
+    .bind(tx.user_id.as_uuid())
+    .bind(tx.org_id.map(|id| *id.as_uuid()))
+    .bind(tx.amount_cents)
+    .bind(tx.transaction_type.as_str())
+    .bind(balance_after_cents)
+    .bind(&tx.description)
+    .bind(&tx.metadata)
+    .bind(tx.created_at)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
+
+    Ok(())
+}
+
+/// Insert a credit transaction row and post its ledger entries.
+///
+/// `amount_cents` is the change in `wallet`'s balance.
+async fn post_transaction(
+    conn: &mut sqlx::PgConnection,
+    wallet: LedgerAccount,
+    tx: &CreditTransaction,
+    amount_cents: i64,
+    balance_after_cents: i64,
+) -> Result<()> {
+    insert_transaction(&mut *conn, tx, balance_after_cents).await?;
+    insert_ledger_entries(conn, &ledger::postings(wallet, tx, amount_cents)).await
+}
+
+/// Insert ledger entry rows.
+async fn insert_ledger_entries(
+    conn: &mut sqlx::PgConnection,
+    entries: &[LedgerEntry],
+) -> Result<()> {
+    for entry in entries {
+        sqlx::query(
+            r#"
+            INSERT INTO ledger_entries (id, account, amount_cents, transaction_id, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(entry.id.as_uuid())
+        .bind(entry.account.to_string())
+        .bind(entry.amount_cents)
+        .bind(entry.transaction_id.map(|id| id.to_string()))
+        .bind(entry.created_at)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Bind an account's settings, everything `put_account` writes, as `$1`
+/// to `$13`.
+fn bind_account_settings<'q>(
+    query: sqlx::query::Query<'q, sqlx::Postgres, sqlx::postgres::PgArguments>,
+    account: &'q Account,
+) -> sqlx::query::Query<'q, sqlx::Postgres, sqlx::postgres::PgArguments> {
+    query
+        .bind(account.user_id.as_uuid())
+        .bind(serde_json::to_value(&account.subscription).unwrap_or_default())
+        .bind(serde_json::to_value(&account.auto_refill).unwrap_or_default())
+        .bind(&account.lago_customer_id)
+        .bind(&account.stripe_customer_id)
+        .bind(account.is_zero_pro)
+        .bind(&account.referred_by)
+        .bind(account.signup_grant_at)
+        .bind(account.last_daily_grant_at)
+        .bind(account.last_monthly_grant_at)
+        .bind(account.created_at)
+        .bind(account.updated_at)
+        .bind(account.credit_limit_cents)
+}
+
+/// The error for a conditional account write that found the account at
+/// another version than `expected` (0 if it does not exist).
+async fn account_version_conflict(
+    conn: &mut sqlx::PgConnection,
+    user_id: UserId,
+    expected: i64,
+) -> StoreError {
+    let actual = sqlx::query_scalar::<_, i64>("SELECT version FROM accounts WHERE user_id = $1")
+        .bind(user_id.as_uuid())
+        .fetch_optional(conn)
+        .await;
+    match actual {
+        Ok(actual) => StoreError::VersionConflict {
+            entity: "Account",
+            id: user_id.to_string(),
+            expected,
+            actual: actual.unwrap_or(0),
+        },
+        Err(e) => StoreError::Database(e.to_string()),
+    }
+}
+
+/// Add credits to an account: update the balance, post the transaction and
+/// open a credit lot (or draw lots down for a negative amount).
+///
+/// Returns the new balance.
+async fn credit_account(
+    conn: &mut sqlx::PgConnection,
+    user_id: UserId,
+    amount_cents: i64,
+    tx: &CreditTransaction,
+    outbox: &[OutboxMessage],
+) -> Result<i64> {
+    // Track lifetime stats based on transaction type
+    let (purchased_cents, granted_cents) = match tx.transaction_type {
+        TransactionType::Purchase | TransactionType::AutoRefill => (amount_cents, 0),
+        TransactionType::SubscriptionGrant | TransactionType::Bonus => (0, amount_cents),
+        _ => (0, 0),
+    };
+
+    // Add credits
+    let new_balance = sqlx::query_scalar::<_, i64>(
+        r#"
+        UPDATE accounts
+        SET balance_cents = balance_cents + $2,
+            lifetime_purchased_cents = lifetime_purchased_cents + $3,
+            lifetime_granted_cents = lifetime_granted_cents + $4,
+            overdraft_locked = CASE
+                WHEN balance_cents + $2 > 0 THEN FALSE
+                WHEN credit_limit_cents > 0
+                    AND balance_cents + $2 <= -credit_limit_cents THEN TRUE
+                ELSE overdraft_locked
+            END,
+            updated_at = NOW()
+        WHERE user_id = $1
+        RETURNING balance_cents
+        "#,
+    )
+    .bind(user_id.as_uuid())
+    .bind(amount_cents)
+    .bind(purchased_cents)
+    .bind(granted_cents)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?
+    .ok_or(StoreError::NotFound {
+        entity: "account",
+        id: user_id.to_string(),
+    })?;
+
+    // Record transaction
+    post_transaction(
+        &mut *conn,
+        LedgerAccount::User(user_id),
+        tx,
+        amount_cents,
+        new_balance,
+    )
+    .await?;
+
+    // Track where the credits came from, or spend lots for a deduction
+    if amount_cents > 0 {
+        let mut lot = CreditLot::new(
+            user_id,
+            tx.transaction_type.clone(),
+            amount_cents,
+            lot::default_expiry(&tx.transaction_type, tx.created_at),
+        );
+        lot.cap_to_balance(new_balance);
+        insert_credit_lot(&mut *conn, &lot).await?;
+    } else {
+        draw_down_lots(&mut *conn, &user_id, -amount_cents).await?;
+    }
+
+    insert_outbox_messages(&mut *conn, outbox).await?;
+
+    Ok(new_balance)
+}
+
+/// Insert outbox messages written alongside a compound operation.
+async fn insert_outbox_messages(
+    conn: &mut sqlx::PgConnection,
+    messages: &[OutboxMessage],
+) -> Result<()> {
+    for message in messages {
+        upsert_outbox_message(&mut *conn, message).await?;
     }
+    Ok(())
+}
 
-    fn process_usage(&self, event: &UsageEvent, transaction: &CreditTransaction) -> Result<i64> {
-        let pool = self.pool.clone();
-        let event = event.clone();
-        let tx = transaction.clone();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                // Use a database transaction for atomicity
-                let mut db_tx = pool
-                    .begin()
-                    .await
-                    .map_err(|e| StoreError::Database(e.to_string()))?;
-
-                // Check idempotency
-                let exists = sqlx::query_scalar::<_, bool>(
-                    "SELECT EXISTS(SELECT 1 FROM usage_events WHERE event_id = $1)",
-                )
-                .bind(&event.event_id)
-                .fetch_one(&mut *db_tx)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+/// Insert an outbox message, or update the delivery state of an existing one.
+async fn upsert_outbox_message(
+    conn: &mut sqlx::PgConnection,
+    message: &OutboxMessage,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO outbox (id, topic, payload, status, attempts, next_attempt_at, last_error,
+            created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (id) DO UPDATE SET
+            status = EXCLUDED.status,
+            attempts = EXCLUDED.attempts,
+            next_attempt_at = EXCLUDED.next_attempt_at,
+            last_error = EXCLUDED.last_error,
+            updated_at = EXCLUDED.updated_at
+        "#,
+    )
+    .bind(message.id.to_string())
+    .bind(message.topic.as_str())
+    .bind(&message.payload)
+    .bind(message.status.as_str())
+    .bind(i32::try_from(message.attempts).unwrap_or(i32::MAX))
+    .bind(message.next_attempt_at)
+    .bind(&message.last_error)
+    .bind(message.created_at)
+    .bind(message.updated_at)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                if exists {
-                    return Err(StoreError::DuplicateEvent {
-                        event_id: event.event_id.clone(),
-                    });
-                }
-
-                // Lock and check balance
-                let balance = sqlx::query_scalar::<_, i64>(
-                    "SELECT balance_cents FROM accounts WHERE user_id = $1 FOR UPDATE",
-                )
-                .bind(event.user_id.as_uuid())
-                .fetch_optional(&mut *db_tx)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?
-                .ok_or(StoreError::NotFound {
-                    entity: "account",
-                    id: event.user_id.to_string(),
-                })?;
-
-                if balance < event.cost_cents {
-                    return Err(StoreError::InsufficientCredits {
-                        balance,
-                        required: event.cost_cents,
-                    });
-                }
-
-                // Deduct credits
-                let new_balance = sqlx::query_scalar::<_, i64>(
-                    r#"
-                    UPDATE accounts
-                    SET balance_cents = balance_cents - $2,
-                        lifetime_used_cents = lifetime_used_cents + $2,
-                        updated_at = NOW()
-                    WHERE user_id = $1
-                    RETURNING balance_cents
-                    "#,
-                )
-                .bind(event.user_id.as_uuid())
-                .bind(event.cost_cents)
-                .fetch_one(&mut *db_tx)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+    Ok(())
+}
 
-                // Record transaction
-                sqlx::query(
-                    r#"
-                    INSERT INTO credit_transactions (id, user_id, amount_cents, transaction_type,
-                        balance_after_cents, description, metadata, created_at)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-                    "#,
-                )
-                .bind(tx.id.to_string())
-                .bind(tx.user_id.as_uuid())
-                .bind(tx.amount_cents)
-                .bind(
-                    serde_json::to_string(&tx.transaction_type)
-                        .unwrap_or_default()
-                        .trim_matches('"'),
-                )
-                .bind(new_balance)
-                .bind(&tx.description)
-                .bind(&tx.metadata)
-                .bind(tx.created_at)
-                .execute(&mut *db_tx)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+/// Insert a new credit lot.
+async fn insert_credit_lot(conn: &mut sqlx::PgConnection, lot: &CreditLot) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO credit_lots (id, user_id, source, amount_cents, remaining_cents,
+            expires_at, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(lot.id.to_string())
+    .bind(lot.user_id.as_uuid())
+    .bind(lot.source.as_str())
+    .bind(lot.amount_cents)
+    .bind(lot.remaining_cents)
+    .bind(lot.expires_at)
+    .bind(lot.created_at)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                // Record usage event
-                sqlx::query(
-                    r#"
-                    INSERT INTO usage_events (event_id, user_id, agent_id, source, metric,
-                        quantity, cost_cents, event_timestamp, metadata)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-                    "#,
-                )
-                .bind(&event.event_id)
-                .bind(event.user_id.as_uuid())
-                .bind(event.agent_id.map(|a| *a.as_uuid()))
-                .bind(serde_json::to_value(&event.source).unwrap_or_default())
-                .bind(serde_json::to_value(&event.metric).unwrap_or_default())
-                .bind(event.quantity)
-                .bind(event.cost_cents)
-                .bind(event.timestamp)
-                .bind(&event.metadata)
-                .execute(&mut *db_tx)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+    Ok(())
+}
 
-                db_tx
-                    .commit()
-                    .await
-                    .map_err(|e| StoreError::Database(e.to_string()))?;
+/// Lock a user's open credit lots for the rest of the transaction.
+async fn lock_credit_lots(
+    conn: &mut sqlx::PgConnection,
+    user_id: &UserId,
+) -> Result<Vec<CreditLot>> {
+    let rows = sqlx::query_as::<_, CreditLotRow>(
+        "SELECT * FROM credit_lots WHERE user_id = $1 AND remaining_cents > 0 FOR UPDATE",
+    )
+    .bind(user_id.as_uuid())
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| StoreError::Database(e.to_string()))?;
 
-                Ok(new_balance)
-            })
-        })
+    rows.into_iter().map(CreditLotRow::into_lot).collect()
+}
+
+/// Write back the remaining amount of drawn-down lots.
+async fn update_credit_lots(conn: &mut sqlx::PgConnection, lots: &[CreditLot]) -> Result<()> {
+    for lot in lots {
+        sqlx::query("UPDATE credit_lots SET remaining_cents = $2 WHERE id = $1")
+            .bind(lot.id.to_string())
+            .bind(lot.remaining_cents)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?;
     }
 
-    fn add_credits(
-        &self,
-        user_id: &UserId,
-        amount_cents: i64,
-        transaction: &CreditTransaction,
-    ) -> Result<i64> {
-        let pool = self.pool.clone();
-        let user_id = *user_id;
-        let tx = transaction.clone();
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current().block_on(async {
-                let mut db_tx = pool
-                    .begin()
-                    .await
-                    .map_err(|e| StoreError::Database(e.to_string()))?;
-
-                // Add credits
-                let new_balance = sqlx::query_scalar::<_, i64>(
-                    r#"
-                    UPDATE accounts
-                    SET balance_cents = balance_cents + $2,
-                        lifetime_purchased_cents = lifetime_purchased_cents + $2,
-                        updated_at = NOW()
-                    WHERE user_id = $1
-                    RETURNING balance_cents
-                    "#,
-                )
-                .bind(user_id.as_uuid())
-                .bind(amount_cents)
-                .fetch_optional(&mut *db_tx)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?
-                .ok_or(StoreError::NotFound {
-                    entity: "account",
-                    id: user_id.to_string(),
-                })?;
+    Ok(())
+}
 
-                // Record transaction
-                sqlx::query(
-                    r#"
-                    INSERT INTO credit_transactions (id, user_id, amount_cents, transaction_type,
-                        balance_after_cents, description, metadata, created_at)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-                    "#,
-                )
-                .bind(tx.id.to_string())
-                .bind(tx.user_id.as_uuid())
-                .bind(tx.amount_cents)
-                .bind(
-                    serde_json::to_string(&tx.transaction_type)
-                        .unwrap_or_default()
-                        .trim_matches('"'),
-                )
-                .bind(new_balance)
-                .bind(&tx.description)
-                .bind(&tx.metadata)
-                .bind(tx.created_at)
-                .execute(&mut *db_tx)
-                .await
-                .map_err(|e| StoreError::Database(e.to_string()))?;
+/// Lock a user's open credit lots and draw `amount_cents` down from them in
+/// consumption order.
+///
+/// The caller must already hold the account lock.
+async fn draw_down_lots(
+    conn: &mut sqlx::PgConnection,
+    user_id: &UserId,
+    amount_cents: i64,
+) -> Result<()> {
+    let mut lots = lock_credit_lots(conn, user_id).await?;
+    let touched = lot::draw_down(&mut lots, amount_cents);
+    update_credit_lots(conn, &lots[..touched]).await
+}
 
-                db_tx
-                    .commit()
-                    .await
-                    .map_err(|e| StoreError::Database(e.to_string()))?;
+/// Draw a usage charge down from the user's open credit lots and record
+/// on the event what it took, for reversals.
+async fn draw_down_usage_lots(conn: &mut sqlx::PgConnection, event: &UsageEvent) -> Result<()> {
+    let mut lots = lock_credit_lots(conn, &event.user_id).await?;
+    let (touched, draws) = lot::draw_down_recorded(&mut lots, event.cost_cents);
+    update_credit_lots(conn, &lots[..touched]).await?;
 
-                Ok(new_balance)
-            })
-        })
-    }
+    let draws =
+        serde_json::to_value(&draws).map_err(|e| StoreError::Serialization(e.to_string()))?;
+    sqlx::query("UPDATE usage_events SET lot_draws = $2 WHERE event_id = $1")
+        .bind(&event.event_id)
+        .bind(draws)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| StoreError::Database(e.to_string()))?;
+
+    Ok(())
 }
 
 // ---------------------------------------------------------------------------
@@ -618,6 +3407,9 @@ struct AccountRow {
     signup_grant_at: Option<chrono::DateTime<chrono::Utc>>,
     last_daily_grant_at: Option<chrono::DateTime<chrono::Utc>>,
     last_monthly_grant_at: Option<chrono::DateTime<chrono::Utc>>,
+    credit_limit_cents: i64,
+    overdraft_locked: bool,
+    version: i64,
     created_at: chrono::DateTime<chrono::Utc>,
     updated_at: chrono::DateTime<chrono::Utc>,
 }
@@ -643,6 +3435,9 @@ impl AccountRow {
             signup_grant_at: self.signup_grant_at,
             last_daily_grant_at: self.last_daily_grant_at,
             last_monthly_grant_at: self.last_monthly_grant_at,
+            credit_limit_cents: self.credit_limit_cents,
+            overdraft_locked: self.overdraft_locked,
+            version: self.version,
             created_at: self.created_at,
             updated_at: self.updated_at,
         }
@@ -653,6 +3448,7 @@ impl AccountRow {
 struct TransactionRow {
     id: String,
     user_id: uuid::Uuid,
+    org_id: Option<uuid::Uuid>,
     amount_cents: i64,
     transaction_type: String,
     balance_after_cents: i64,
@@ -669,6 +3465,7 @@ impl TransactionRow {
                 .parse::<TransactionId>()
                 .unwrap_or_else(|_| TransactionId::generate()),
             user_id: UserId::from_uuid(self.user_id),
+            org_id: self.org_id.map(OrgId::from_uuid),
             amount_cents: self.amount_cents,
             transaction_type: serde_json::from_str(&format!("\"{}\"", self.transaction_type))
                 .unwrap_or(z_billing_core::TransactionType::Purchase),
@@ -691,6 +3488,7 @@ struct UsageEventRow {
     cost_cents: i64,
     event_timestamp: chrono::DateTime<chrono::Utc>,
     metadata: serde_json::Value,
+    transaction_id: Option<String>,
 }
 
 impl UsageEventRow {
@@ -710,6 +3508,407 @@ impl UsageEventRow {
             cost_cents: self.cost_cents,
             timestamp: self.event_timestamp,
             metadata: self.metadata,
+            transaction_id: self.transaction_id.and_then(|id| id.parse().ok()),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct DailyUsageRow {
+    day: chrono::NaiveDate,
+    source: serde_json::Value,
+    agent_id: Option<uuid::Uuid>,
+    provider: Option<serde_json::Value>,
+    model: Option<String>,
+    direction: Option<String>,
+    quantity: f64,
+    cost_cents: i64,
+    event_count: i64,
+}
+
+impl DailyUsageRow {
+    fn into_daily_usage(self) -> Result<DailyUsage> {
+        Ok(DailyUsage {
+            day: self.day,
+            source: serde_json::from_value(self.source)
+                .unwrap_or(z_billing_core::UsageSource::Custom("unknown".to_string())),
+            agent_id: self.agent_id.map(z_billing_core::AgentId::from_uuid),
+            provider: self
+                .provider
+                .and_then(|provider| serde_json::from_value(provider).ok()),
+            model: self.model,
+            direction: self.direction.and_then(|direction| {
+                serde_json::from_value(serde_json::Value::String(direction)).ok()
+            }),
+            quantity: self.quantity,
+            cost_cents: self.cost_cents,
+            event_count: u64::try_from(self.event_count)
+                .map_err(|e| StoreError::Database(e.to_string()))?,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct UsageReversalRow {
+    event_id: String,
+    reversal_id: String,
+    amount_cents: i64,
+    transaction_id: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl UsageReversalRow {
+    fn into_usage_reversal(self) -> Result<UsageReversal> {
+        Ok(UsageReversal {
+            event_id: self.event_id,
+            reversal_id: self.reversal_id,
+            amount_cents: self.amount_cents,
+            transaction_id: self
+                .transaction_id
+                .parse::<TransactionId>()
+                .map_err(|e| StoreError::Serialization(e.to_string()))?,
+            created_at: self.created_at,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct CreditTransferRow {
+    from_user_id: uuid::Uuid,
+    idempotency_key: String,
+    to_account: String,
+    amount_cents: i64,
+    out_transaction_id: String,
+    in_transaction_id: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl CreditTransferRow {
+    fn into_transfer(self) -> Result<CreditTransfer> {
+        Ok(CreditTransfer {
+            from_user_id: UserId::from_uuid(self.from_user_id),
+            idempotency_key: self.idempotency_key,
+            to: self.to_account.parse().map_err(StoreError::Serialization)?,
+            amount_cents: self.amount_cents,
+            out_transaction_id: self
+                .out_transaction_id
+                .parse::<TransactionId>()
+                .map_err(|e| StoreError::Serialization(e.to_string()))?,
+            in_transaction_id: self
+                .in_transaction_id
+                .parse::<TransactionId>()
+                .map_err(|e| StoreError::Serialization(e.to_string()))?,
+            created_at: self.created_at,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct PromoCodeRow {
+    code: String,
+    amount_cents: i64,
+    max_redemptions: Option<i64>,
+    per_user_limit: i64,
+    redemption_count: i64,
+    valid_from: Option<chrono::DateTime<chrono::Utc>>,
+    valid_until: Option<chrono::DateTime<chrono::Utc>>,
+    plans: serde_json::Value,
+    active: bool,
+    description: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PromoCodeRow {
+    fn into_promo(self) -> Result<PromoCode> {
+        Ok(PromoCode {
+            code: self.code,
+            amount_cents: self.amount_cents,
+            max_redemptions: self.max_redemptions,
+            per_user_limit: self.per_user_limit,
+            redemption_count: self.redemption_count,
+            valid_from: self.valid_from,
+            valid_until: self.valid_until,
+            plans: serde_json::from_value(self.plans)
+                .map_err(|e| StoreError::Serialization(e.to_string()))?,
+            active: self.active,
+            description: self.description,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct GiftCardRow {
+    id: String,
+    code: String,
+    amount_cents: i64,
+    purchaser_id: uuid::Uuid,
+    status: String,
+    stripe_session_id: Option<String>,
+    redeemed_by: Option<uuid::Uuid>,
+    transaction_id: Option<String>,
+    redeemed_at: Option<chrono::DateTime<chrono::Utc>>,
+    voided_at: Option<chrono::DateTime<chrono::Utc>>,
+    void_reason: Option<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl GiftCardRow {
+    fn into_gift_card(self) -> Result<GiftCard> {
+        Ok(GiftCard {
+            id: self
+                .id
+                .parse::<GiftCardId>()
+                .map_err(|e| StoreError::Serialization(e.to_string()))?,
+            code: self.code,
+            amount_cents: self.amount_cents,
+            purchaser_id: UserId::from_uuid(self.purchaser_id),
+            status: self.status.parse().map_err(StoreError::Serialization)?,
+            stripe_session_id: self.stripe_session_id,
+            redeemed_by: self.redeemed_by.map(UserId::from_uuid),
+            transaction_id: self.transaction_id.and_then(|id| id.parse().ok()),
+            redeemed_at: self.redeemed_at,
+            voided_at: self.voided_at,
+            void_reason: self.void_reason,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct OutboxRow {
+    id: String,
+    topic: String,
+    payload: serde_json::Value,
+    status: String,
+    attempts: i32,
+    next_attempt_at: chrono::DateTime<chrono::Utc>,
+    last_error: Option<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl OutboxRow {
+    fn into_message(self) -> Result<OutboxMessage> {
+        Ok(OutboxMessage {
+            id: self
+                .id
+                .parse::<OutboxId>()
+                .map_err(|e| StoreError::Serialization(e.to_string()))?,
+            topic: self.topic.parse().map_err(StoreError::Serialization)?,
+            payload: self.payload,
+            status: self.status.parse().map_err(StoreError::Serialization)?,
+            attempts: u32::try_from(self.attempts)
+                .map_err(|e| StoreError::Serialization(e.to_string()))?,
+            next_attempt_at: self.next_attempt_at,
+            last_error: self.last_error,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ReservationRow {
+    id: String,
+    user_id: uuid::Uuid,
+    amount_cents: i64,
+    status: String,
+    transaction_id: Option<String>,
+    metadata: serde_json::Value,
+    created_at: chrono::DateTime<chrono::Utc>,
+    expires_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ReservationRow {
+    fn into_reservation(self) -> Result<Reservation> {
+        Ok(Reservation {
+            id: self
+                .id
+                .parse::<ReservationId>()
+                .map_err(|e| StoreError::Serialization(e.to_string()))?,
+            user_id: UserId::from_uuid(self.user_id),
+            amount_cents: self.amount_cents,
+            status: self.status.parse().map_err(StoreError::Serialization)?,
+            transaction_id: self.transaction_id.and_then(|id| id.parse().ok()),
+            metadata: self.metadata,
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct CreditLotRow {
+    id: String,
+    user_id: uuid::Uuid,
+    source: String,
+    amount_cents: i64,
+    remaining_cents: i64,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl CreditLotRow {
+    fn into_lot(self) -> Result<CreditLot> {
+        Ok(CreditLot {
+            id: self
+                .id
+                .parse::<LotId>()
+                .map_err(|e| StoreError::Serialization(e.to_string()))?,
+            user_id: UserId::from_uuid(self.user_id),
+            source: serde_json::from_str(&format!("\"{}\"", self.source))
+                .map_err(|e| StoreError::Serialization(e.to_string()))?,
+            amount_cents: self.amount_cents,
+            remaining_cents: self.remaining_cents,
+            expires_at: self.expires_at,
+            created_at: self.created_at,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct OrganizationRow {
+    id: uuid::Uuid,
+    name: String,
+    balance_cents: i64,
+    lifetime_purchased_cents: i64,
+    lifetime_used_cents: i64,
+    created_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl OrganizationRow {
+    fn into_organization(self) -> Organization {
+        Organization {
+            id: OrgId::from_uuid(self.id),
+            name: self.name,
+            balance_cents: self.balance_cents,
+            lifetime_purchased_cents: self.lifetime_purchased_cents,
+            lifetime_used_cents: self.lifetime_used_cents,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct OrgMembershipRow {
+    org_id: uuid::Uuid,
+    user_id: uuid::Uuid,
+    role: String,
+    monthly_spend_cap_cents: Option<i64>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl OrgMembershipRow {
+    fn into_membership(self) -> Result<OrgMembership> {
+        Ok(OrgMembership {
+            org_id: OrgId::from_uuid(self.org_id),
+            user_id: UserId::from_uuid(self.user_id),
+            role: self.role.parse().map_err(StoreError::Serialization)?,
+            monthly_spend_cap_cents: self.monthly_spend_cap_cents,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct AgentBudgetRow {
+    user_id: uuid::Uuid,
+    agent_id: uuid::Uuid,
+    period: String,
+    limit_cents: i64,
+    created_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl AgentBudgetRow {
+    fn into_budget(self) -> Result<AgentBudget> {
+        Ok(AgentBudget {
+            user_id: UserId::from_uuid(self.user_id),
+            agent_id: AgentId::from_uuid(self.agent_id),
+            period: self.period.parse().map_err(StoreError::Serialization)?,
+            limit_cents: self.limit_cents,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct AgentSpendRow {
+    user_id: uuid::Uuid,
+    agent_id: uuid::Uuid,
+    day_start: chrono::DateTime<chrono::Utc>,
+    day_cents: i64,
+    month_start: chrono::DateTime<chrono::Utc>,
+    month_cents: i64,
+    lifetime_cents: i64,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl AgentSpendRow {
+    fn into_spend(self) -> AgentSpend {
+        AgentSpend {
+            user_id: UserId::from_uuid(self.user_id),
+            agent_id: AgentId::from_uuid(self.agent_id),
+            day_start: self.day_start,
+            day_cents: self.day_cents,
+            month_start: self.month_start,
+            month_cents: self.month_cents,
+            lifetime_cents: self.lifetime_cents,
+            updated_at: self.updated_at,
         }
     }
 }
+
+#[derive(sqlx::FromRow)]
+struct LedgerEntryRow {
+    id: uuid::Uuid,
+    account: String,
+    amount_cents: i64,
+    transaction_id: Option<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl LedgerEntryRow {
+    fn into_entry(self) -> Result<LedgerEntry> {
+        Ok(LedgerEntry {
+            id: LedgerEntryId::from_uuid(self.id),
+            account: self.account.parse().map_err(StoreError::Serialization)?,
+            amount_cents: self.amount_cents,
+            transaction_id: self
+                .transaction_id
+                .map(|id| id.parse::<TransactionId>())
+                .transpose()
+                .map_err(|e| StoreError::Serialization(e.to_string()))?,
+            created_at: self.created_at,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct LedgerDriftRow {
+    account: String,
+    balance_cents: i64,
+    ledger_cents: i64,
+}
+
+impl LedgerDriftRow {
+    fn into_drift(self) -> Result<LedgerDrift> {
+        Ok(LedgerDrift {
+            account: self.account.parse().map_err(StoreError::Serialization)?,
+            balance_cents: self.balance_cents,
+            ledger_cents: self.ledger_cents,
+        })
+    }
+}
@@ -3,14 +3,22 @@
 //! This module provides the `RocksStore` implementation of the `Store` trait.
 
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use rocksdb::{
     BoundColumnFamily, ColumnFamilyDescriptor, DBWithThreadMode, IteratorMode, MultiThreaded,
     Options, WriteBatch,
 };
 
-use z_billing_core::{Account, CreditTransaction, TransactionId, UsageEvent, UserId};
+use z_billing_core::{
+    budget, ledger, lot, org, Account, AgentBudget, AgentId, AgentSpend, BudgetPeriod, CreditLot,
+    CreditTransaction, CreditTransfer, DailyUsage, GiftCard, GiftCardId, GiftCardStatus,
+    LedgerAccount, LedgerDrift, LedgerEntry, LedgerReport, LotDraw, OrgId, OrgMembership,
+    Organization, OutboxId, OutboxMessage, OutboxStatus, PricingCatalog, ProcessedWebhook,
+    PromoCode, PromoRedemption, Reservation, ReservationId, ReservationStatus, StorageMeter,
+    SystemAccount, TransactionId, TransactionQuery, UsageEvent, UsageReversal, UsageSummaryQuery,
+    UsageSummaryRow, UserId,
+};
 
 use crate::error::{Result, StoreError};
 use crate::keys;
@@ -18,8 +26,12 @@ use crate::schema::{all_column_families, cf};
 use crate::Store;
 
 /// RocksDB-backed storage implementation.
+///
+/// `RocksDB` calls block, so every operation runs on tokio's blocking thread
+/// pool. Clones share the same database.
+#[derive(Clone)]
 pub struct RocksStore {
-    db: Arc<DBWithThreadMode<MultiThreaded>>,
+    inner: Arc<RocksDb>,
 }
 
 impl RocksStore {
@@ -29,6 +41,59 @@ impl RocksStore {
     ///
     /// Returns an error if the database cannot be opened or created.
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            inner: Arc::new(RocksDb::open(path)?),
+        })
+    }
+
+    /// Run a blocking operation on tokio's blocking thread pool.
+    async fn blocking<T, F>(&self, op: F) -> Result<T>
+    where
+        F: FnOnce(&RocksDb) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        tokio::task::spawn_blocking(move || op(&inner))
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?
+    }
+}
+
+/// The blocking half of [`RocksStore`], which does the actual reads and
+/// writes.
+struct RocksDb {
+    db: Arc<DBWithThreadMode<MultiThreaded>>,
+    /// Serializes promo code writes so redemption limits hold under
+    /// concurrent requests.
+    promo_lock: Mutex<()>,
+    /// Serializes gift card status changes so a card is redeemed or voided
+    /// at most once.
+    gift_card_lock: Mutex<()>,
+    /// Serializes outbox claims so a message is handed to one dispatcher
+    /// at a time.
+    outbox_lock: Mutex<()>,
+    /// Serializes free credit grants so each grant key is given at most
+    /// once.
+    grant_lock: Mutex<()>,
+    /// Serializes account settings writes so version checks hold under
+    /// concurrent requests.
+    account_lock: Mutex<()>,
+    /// Serializes pricing catalog saves so each version is saved once.
+    pricing_catalog_lock: Mutex<()>,
+    /// Serializes storage meter writes so version checks hold under
+    /// concurrent readings.
+    storage_meter_lock: Mutex<()>,
+}
+
+/// A pricing catalog with the time it was saved, which orders versions.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedPricingCatalog {
+    saved_at: chrono::DateTime<chrono::Utc>,
+    catalog: PricingCatalog,
+}
+
+impl RocksDb {
+    fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let mut opts = Options::default();
         opts.create_if_missing(true);
         opts.create_missing_column_families(true);
@@ -38,13 +103,59 @@ impl RocksStore {
             .map(|name| ColumnFamilyDescriptor::new(name, Options::default()))
             .collect();
 
-        let db = DBWithThreadMode::open_cf_descriptors(&opts, path, cf_descriptors)
+        let db = DBWithThreadMode::open_cf_descriptors(&opts, &path, cf_descriptors)
             .map_err(|e| StoreError::Database(e.to_string()))?;
 
-        Ok(Self { db: Arc::new(db) })
+        let store = Self {
+            db: Arc::new(db),
+            promo_lock: Mutex::new(()),
+            gift_card_lock: Mutex::new(()),
+            outbox_lock: Mutex::new(()),
+            grant_lock: Mutex::new(()),
+            account_lock: Mutex::new(()),
+            pricing_catalog_lock: Mutex::new(()),
+            storage_meter_lock: Mutex::new(()),
+        };
+
+        // Databases created before the customer indexes existed need them
+        // filled in once. The marker is written with the rebuild, so a crash
+        // part way through rebuilds again on the next open.
+        if !store.customer_indexes_built()? {
+            store.rebuild_customer_indexes()?;
+        }
+
+        Ok(store)
+    }
+
+    /// Whether the customer indexes have been built for every account.
+    fn customer_indexes_built(&self) -> Result<bool> {
+        let cf = self.cf(cf::META)?;
+
+        Ok(self
+            .db
+            .get_cf(&cf, keys::CUSTOMER_INDEXES_BUILT)
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .is_some())
+    }
+
+    /// Index every stored account by its Stripe and Lago customer IDs, and
+    /// mark the indexes built.
+    fn rebuild_customer_indexes(&self) -> Result<()> {
+        let cf_accounts = self.cf(cf::ACCOUNTS)?;
+        let mut batch = WriteBatch::default();
+
+        for item in self.db.iterator_cf(&cf_accounts, IteratorMode::Start) {
+            let (_, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            let account: Account = Self::deserialize(&value)?;
+            self.write_customer_indexes(&mut batch, &account.user_id, None, Some(&account))?;
+        }
+        batch.put_cf(&self.cf(cf::META)?, keys::CUSTOMER_INDEXES_BUILT, []);
+
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))
     }
 
-    /// Get a column family handle.
     fn cf(&self, name: &str) -> Result<Arc<BoundColumnFamily<'_>>> {
         self.db
             .cf_handle(name)
@@ -63,293 +174,599 @@ impl RocksStore {
     fn deserialize<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T> {
         ciborium::from_reader(data).map_err(|e| StoreError::Serialization(e.to_string()))
     }
-}
 
-impl Store for RocksStore {
-    // =========================================================================
-    // Account Operations
-    // =========================================================================
+    /// Keys in column family `name` that start with `prefix`.
+    fn keys_with_prefix(&self, name: &str, prefix: &[u8]) -> Result<Vec<Box<[u8]>>> {
+        let cf = self.cf(name)?;
 
-    fn put_account(&self, account: &Account) -> Result<()> {
-        let cf = self.cf(cf::ACCOUNTS)?;
-        let key = keys::account_key(&account.user_id);
-        let value = Self::serialize(account)?;
+        let mut found = Vec::new();
+        for item in self
+            .db
+            .iterator_cf(&cf, IteratorMode::From(prefix, rocksdb::Direction::Forward))
+        {
+            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            found.push(key);
+        }
 
-        self.db
-            .put_cf(&cf, key, value)
-            .map_err(|e| StoreError::Database(e.to_string()))?;
+        Ok(found)
+    }
+
+    /// Move `user_id`'s customer index entries from `previous`'s IDs to
+    /// `current`'s into `batch`.
+    fn write_customer_indexes(
+        &self,
+        batch: &mut WriteBatch,
+        user_id: &UserId,
+        previous: Option<&Account>,
+        current: Option<&Account>,
+    ) -> Result<()> {
+        self.write_customer_index(
+            batch,
+            cf::ACCOUNTS_BY_STRIPE_CUSTOMER,
+            keys::stripe_customer_key,
+            user_id,
+            previous.and_then(|account| account.stripe_customer_id.as_deref()),
+            current.and_then(|account| account.stripe_customer_id.as_deref()),
+        )?;
+        self.write_customer_index(
+            batch,
+            cf::ACCOUNTS_BY_LAGO_CUSTOMER,
+            keys::lago_customer_key,
+            user_id,
+            previous.and_then(|account| account.lago_customer_id.as_deref()),
+            current.and_then(|account| account.lago_customer_id.as_deref()),
+        )
+    }
+
+    /// Point one customer index at `user_id` for `new` instead of `old`.
+    fn write_customer_index(
+        &self,
+        batch: &mut WriteBatch,
+        name: &str,
+        key: fn(&str) -> Vec<u8>,
+        user_id: &UserId,
+        old: Option<&str>,
+        new: Option<&str>,
+    ) -> Result<()> {
+        let cf = self.cf(name)?;
+
+        if let Some(old) = old.filter(|old| Some(*old) != new) {
+            // Leave the entry alone if another account has since claimed the
+            // customer
+            let owner = self
+                .db
+                .get_cf(&cf, key(old))
+                .map_err(|e| StoreError::Database(e.to_string()))?;
+            if owner.as_deref() == Some(user_id.as_bytes().as_slice()) {
+                batch.delete_cf(&cf, key(old));
+            }
+        }
+        if let Some(new) = new {
+            batch.put_cf(&cf, key(new), user_id.as_bytes());
+        }
 
         Ok(())
     }
 
-    fn get_account(&self, user_id: &UserId) -> Result<Option<Account>> {
-        let cf = self.cf(cf::ACCOUNTS)?;
-        let key = keys::account_key(user_id);
+    /// Add a transaction, its per-user index entry and its ledger postings to
+    /// `batch`. `amount_cents` is the change in `wallet`'s balance.
+    fn write_transaction(
+        &self,
+        batch: &mut WriteBatch,
+        wallet: LedgerAccount,
+        transaction: &CreditTransaction,
+        amount_cents: i64,
+    ) -> Result<()> {
+        let cf_tx = self.cf(cf::TRANSACTIONS)?;
+        let cf_tx_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
 
-        self.db
-            .get_cf(&cf, key)
-            .map_err(|e| StoreError::Database(e.to_string()))?
-            .map(|data| Self::deserialize(&data))
-            .transpose()
+        batch.put_cf(
+            &cf_tx,
+            keys::transaction_key(&transaction.id),
+            Self::serialize(transaction)?,
+        );
+        batch.put_cf(
+            &cf_tx_by_user,
+            keys::user_transaction_key(&transaction.user_id, &transaction.id),
+            [],
+        );
+        self.write_ledger_entries(batch, &ledger::postings(wallet, transaction, amount_cents))
     }
 
-    fn find_account_by_stripe_customer(&self, _customer_id: &str) -> Result<Option<Account>> {
-        // RocksDB backend does not index by stripe_customer_id.
-        // This is only used as a webhook fallback and production uses PgStore.
-        Ok(None)
+    /// Add ledger entries to `batch`.
+    fn write_ledger_entries(&self, batch: &mut WriteBatch, entries: &[LedgerEntry]) -> Result<()> {
+        let cf_ledger = self.cf(cf::LEDGER_ENTRIES)?;
+        for entry in entries {
+            batch.put_cf(
+                &cf_ledger,
+                keys::ledger_entry_key(entry),
+                Self::serialize(entry)?,
+            );
+        }
+        Ok(())
     }
 
-    fn delete_account(&self, user_id: &UserId) -> Result<()> {
-        let cf = self.cf(cf::ACCOUNTS)?;
-        let key = keys::account_key(user_id);
+    /// Sum the ledger entries under `prefix`.
+    fn sum_ledger_entries(&self, prefix: &[u8]) -> Result<i64> {
+        let cf = self.cf(cf::LEDGER_ENTRIES)?;
+        let iter = self
+            .db
+            .iterator_cf(&cf, IteratorMode::From(prefix, rocksdb::Direction::Forward));
 
-        // Check if account exists
-        if self.get_account(user_id)?.is_none() {
-            return Err(StoreError::NotFound {
-                entity: "Account",
-                id: user_id.to_string(),
-            });
+        let mut total = 0;
+        for item in iter {
+            let (key, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            total += Self::deserialize::<LedgerEntry>(&value)?.amount_cents;
         }
 
-        self.db
-            .delete_cf(&cf, key)
-            .map_err(|e| StoreError::Database(e.to_string()))?;
-
-        Ok(())
+        Ok(total)
     }
 
-    /// Update account balance by delta.
+    /// Debit `event.cost_cents` from `account` and build the write batch that
+    /// records the account, transaction, and usage event.
     ///
-    /// # Warning
-    ///
-    /// This operation is NOT atomic under concurrent access. For production use,
-    /// prefer the compound operations [`process_usage`] and [`add_credits`] which
-    /// use `WriteBatch` to ensure atomicity within a single operation.
-    ///
-    /// For true atomic read-modify-write semantics under concurrent access,
-    /// the store should be migrated to use `OptimisticTransactionDB` or implement
-    /// application-level optimistic locking.
-    ///
-    /// # Deprecation Notice
-    ///
-    /// This method is deprecated in favor of `add_credits` and `process_usage`,
-    /// which provide atomic operations with proper transaction recording. Direct
-    /// balance updates bypass the transaction audit trail and are not safe under
-    /// concurrent access. This method may be removed in a future version.
-    #[allow(deprecated)] // Implementing deprecated trait method
-    fn update_balance(&self, user_id: &UserId, delta_cents: i64) -> Result<i64> {
-        let cf = self.cf(cf::ACCOUNTS)?;
-        let key = keys::account_key(user_id);
-
-        // Get current account
-        let mut account = self.get_account(user_id)?.ok_or(StoreError::NotFound {
-            entity: "Account",
-            id: user_id.to_string(),
-        })?;
+    /// The caller performs all checks and may add further writes to the batch
+    /// before committing it.
+    fn usage_batch(
+        &self,
+        account: &mut Account,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+    ) -> Result<WriteBatch> {
+        let cf_accounts = self.cf(cf::ACCOUNTS)?;
+        let cf_usage = self.cf(cf::USAGE_EVENTS)?;
 
-        // Update balance
-        account.balance_cents += delta_cents;
+        // Update account
+        account.balance_cents -= event.cost_cents;
+        account.update_overdraft_lock();
+        account.lifetime_used_cents += event.cost_cents;
         account.updated_at = chrono::Utc::now();
 
-        // Track lifetime stats
-        if delta_cents > 0 {
-            // This is a simplified approach; the actual type tracking happens in the service
-        } else {
-            account.lifetime_used_cents += delta_cents.abs();
+        let account_key = keys::account_key(&event.user_id);
+        let event_key = keys::usage_event_key(&event.event_id);
+
+        let account_value = Self::serialize(account)?;
+        let event_value = Self::serialize(event)?;
+
+        let mut batch = WriteBatch::default();
+        batch.put_cf(&cf_accounts, &account_key, &account_value);
+        self.write_transaction(
+            &mut batch,
+            LedgerAccount::User(event.user_id),
+            transaction,
+            -event.cost_cents,
+        )?;
+        batch.put_cf(&cf_usage, &event_key, &event_value);
+        self.draw_down_usage_lots(&mut batch, event)?;
+        self.record_agent_spend(&mut batch, event, account.updated_at)?;
+
+        Ok(batch)
+    }
+
+    /// Reject `event` if it would push its agent over any of its budgets.
+    fn check_agent_budgets(
+        &self,
+        event: &UsageEvent,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        let Some(agent_id) = event.agent_id else {
+            return Ok(());
+        };
+        let budgets = self.list_agent_budgets(&event.user_id, &agent_id)?;
+        if budgets.is_empty() {
+            return Ok(());
         }
 
-        let value = Self::serialize(&account)?;
-        self.db
-            .put_cf(&cf, key, value)
-            .map_err(|e| StoreError::Database(e.to_string()))?;
+        let totals = self
+            .get_agent_spend(&event.user_id, &agent_id)?
+            .unwrap_or_else(|| AgentSpend::new(event.user_id, agent_id, now));
+        if let Some((budget, spent)) =
+            budget::exceeded_budget(&budgets, &totals, event.cost_cents, now)
+        {
+            return Err(StoreError::BudgetExceeded {
+                agent_id,
+                period: budget.period,
+                limit: budget.limit_cents,
+                spent,
+                required: event.cost_cents,
+            });
+        }
 
-        Ok(account.balance_cents)
+        Ok(())
     }
 
-    // =========================================================================
-    // Transaction Operations
-    // =========================================================================
+    /// Add `event`'s cost to its agent's spend totals into `batch`.
+    fn record_agent_spend(
+        &self,
+        batch: &mut WriteBatch,
+        event: &UsageEvent,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        let Some(agent_id) = event.agent_id else {
+            return Ok(());
+        };
+        let cf_spend = self.cf(cf::AGENT_SPEND)?;
+
+        let mut totals = self
+            .get_agent_spend(&event.user_id, &agent_id)?
+            .unwrap_or_else(|| AgentSpend::new(event.user_id, agent_id, now));
+        totals.record(event.cost_cents, now);
+        batch.put_cf(
+            &cf_spend,
+            keys::agent_spend_key(&event.user_id, &agent_id),
+            Self::serialize(&totals)?,
+        );
 
-    fn put_transaction(&self, transaction: &CreditTransaction) -> Result<()> {
-        let cf_tx = self.cf(cf::TRANSACTIONS)?;
-        let cf_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
+        Ok(())
+    }
 
-        let tx_key = keys::transaction_key(&transaction.id);
-        let user_tx_key = keys::user_transaction_key(&transaction.user_id, &transaction.id);
-        let value = Self::serialize(transaction)?;
+    /// Write a credit lot, dropping it (and its expiry index entry) once it
+    /// has nothing left.
+    fn write_credit_lot(&self, batch: &mut WriteBatch, lot: &CreditLot) -> Result<()> {
+        let cf_lots = self.cf(cf::CREDIT_LOTS)?;
+        let cf_by_expiry = self.cf(cf::CREDIT_LOTS_BY_EXPIRY)?;
+
+        let lot_key = keys::credit_lot_key(&lot.user_id, &lot.id);
+        let expiry_key = lot
+            .expires_at
+            .map(|at| keys::credit_lot_expiry_key(at, &lot.user_id, &lot.id));
+
+        if lot.remaining_cents > 0 {
+            batch.put_cf(&cf_lots, &lot_key, Self::serialize(lot)?);
+            if let Some(expiry_key) = expiry_key {
+                batch.put_cf(&cf_by_expiry, &expiry_key, []);
+            }
+        } else {
+            batch.delete_cf(&cf_lots, &lot_key);
+            if let Some(expiry_key) = expiry_key {
+                batch.delete_cf(&cf_by_expiry, &expiry_key);
+            }
+        }
 
-        let mut batch = WriteBatch::default();
-        batch.put_cf(&cf_tx, &tx_key, &value);
-        batch.put_cf(&cf_by_user, &user_tx_key, []); // Index entry (empty value)
+        Ok(())
+    }
 
-        self.db
-            .write(batch)
-            .map_err(|e| StoreError::Database(e.to_string()))?;
+    /// Draw `amount_cents` down from the user's open lots into `batch`.
+    fn draw_down_lots(
+        &self,
+        batch: &mut WriteBatch,
+        user_id: &UserId,
+        amount_cents: i64,
+    ) -> Result<()> {
+        let mut lots = self.list_credit_lots(user_id)?;
+        let touched = lot::draw_down(&mut lots, amount_cents);
+        for lot in &lots[..touched] {
+            self.write_credit_lot(batch, lot)?;
+        }
+        Ok(())
+    }
+
+    /// Draw a usage charge down from the user's open lots into `batch` and
+    /// record what it took, for reversals.
+    fn draw_down_usage_lots(&self, batch: &mut WriteBatch, event: &UsageEvent) -> Result<()> {
+        let cf_draws = self.cf(cf::USAGE_LOT_DRAWS)?;
 
+        let mut lots = self.list_credit_lots(&event.user_id)?;
+        let (touched, draws) = lot::draw_down_recorded(&mut lots, event.cost_cents);
+        for lot in &lots[..touched] {
+            self.write_credit_lot(batch, lot)?;
+        }
+        batch.put_cf(
+            &cf_draws,
+            keys::usage_event_key(&event.event_id),
+            Self::serialize(&draws)?,
+        );
         Ok(())
     }
 
-    fn get_transaction(&self, transaction_id: &TransactionId) -> Result<Option<CreditTransaction>> {
-        let cf = self.cf(cf::TRANSACTIONS)?;
-        let key = keys::transaction_key(transaction_id);
+    /// What a usage event took from credit lots. Events charged before
+    /// draws were recorded have none.
+    fn get_lot_draws(&self, event_id: &str) -> Result<Vec<LotDraw>> {
+        let cf = self.cf(cf::USAGE_LOT_DRAWS)?;
 
         self.db
-            .get_cf(&cf, key)
+            .get_cf(&cf, keys::usage_event_key(event_id))
             .map_err(|e| StoreError::Database(e.to_string()))?
             .map(|data| Self::deserialize(&data))
             .transpose()
+            .map(Option::unwrap_or_default)
     }
 
-    fn list_transactions_by_user(
+    /// Draw `amount_cents` down from the user's transferable lots into
+    /// `batch`.
+    fn draw_down_transferable_lots(
         &self,
+        batch: &mut WriteBatch,
         user_id: &UserId,
-        limit: usize,
-        offset: usize,
-    ) -> Result<Vec<CreditTransaction>> {
-        let cf_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
-        let prefix = keys::user_transactions_prefix(user_id);
-
-        let mut transactions = Vec::new();
-        let mut skipped = 0;
-
-        // Create an upper bound key by appending 0xFF bytes to the prefix
-        // This ensures we start just past the last possible transaction for this user
-        let mut upper_bound = prefix.clone();
-        upper_bound.extend([0xFF; 16]); // Max ULID bytes
-
-        // Iterate in reverse order (newest first) starting from the upper bound
-        let iter = self.db.iterator_cf(
-            &cf_by_user,
-            IteratorMode::From(&upper_bound, rocksdb::Direction::Reverse),
-        );
-
-        // Iterate directly in reverse - no need to collect and reverse
-        for item in iter {
-            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+        amount_cents: i64,
+    ) -> Result<()> {
+        let mut lots = self.list_credit_lots(user_id)?;
+        let touched = lot::draw_down_transferable(&mut lots, amount_cents);
+        for lot in &lots[..touched] {
+            self.write_credit_lot(batch, lot)?;
+        }
+        Ok(())
+    }
 
-            // Stop if we've moved past this user's keys
-            if !key.starts_with(&prefix) {
-                break;
-            }
+    /// Take the promo code write lock.
+    fn lock_promos(&self) -> Result<std::sync::MutexGuard<'_, ()>> {
+        self.promo_lock
+            .lock()
+            .map_err(|e| StoreError::Database(format!("promo lock poisoned: {e}")))
+    }
 
-            // Handle pagination offset
-            if skipped < offset {
-                skipped += 1;
-                continue;
-            }
+    /// Take the gift card write lock.
+    fn lock_gift_cards(&self) -> Result<std::sync::MutexGuard<'_, ()>> {
+        self.gift_card_lock
+            .lock()
+            .map_err(|e| StoreError::Database(format!("gift card lock poisoned: {e}")))
+    }
 
-            // Stop once we have enough
-            if transactions.len() >= limit {
-                break;
-            }
+    /// Take the account settings write lock.
+    fn lock_accounts(&self) -> Result<std::sync::MutexGuard<'_, ()>> {
+        self.account_lock
+            .lock()
+            .map_err(|e| StoreError::Database(format!("account lock poisoned: {e}")))
+    }
 
-            let tx_id = keys::extract_transaction_id_from_user_key(&key)?;
-            if let Some(tx) = self.get_transaction(&tx_id)? {
-                transactions.push(tx);
-            }
+    /// Fail with a version conflict unless `existing` is at `expected` (0
+    /// for an account that must not exist yet).
+    fn check_account_version(
+        user_id: &UserId,
+        existing: Option<&Account>,
+        expected: i64,
+    ) -> Result<()> {
+        let actual = existing.map_or(0, |account| account.version);
+        if actual != expected {
+            return Err(StoreError::VersionConflict {
+                entity: "Account",
+                id: user_id.to_string(),
+                expected,
+                actual,
+            });
         }
-
-        Ok(transactions)
+        Ok(())
     }
 
-    fn sum_monthly_allowance_since(
+    /// Write an account's settings, keeping the stored balance, lifetime
+    /// counters and overdraft lock, and bump its version. Returns the new
+    /// version. The caller holds the account lock.
+    fn write_account_settings(
         &self,
-        user_id: &UserId,
-        since: chrono::DateTime<chrono::Utc>,
+        account: &Account,
+        existing: Option<Account>,
+        outbox: &[OutboxMessage],
     ) -> Result<i64> {
-        let cf_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
-        let prefix = keys::user_transactions_prefix(user_id);
-
-        let mut upper_bound = prefix.clone();
-        upper_bound.extend([0xFF; 16]);
-
-        let iter = self.db.iterator_cf(
-            &cf_by_user,
-            IteratorMode::From(&upper_bound, rocksdb::Direction::Reverse),
+        let cf = self.cf(cf::ACCOUNTS)?;
+        let mut batch = WriteBatch::default();
+        self.write_customer_indexes(
+            &mut batch,
+            &account.user_id,
+            existing.as_ref(),
+            Some(account),
+        )?;
+
+        // The balance only moves through operations that post to the ledger,
+        // and the overdraft lock follows it unless the credit limit changed
+        let mut account = account.clone();
+        if let Some(existing) = existing {
+            account.balance_cents = existing.balance_cents;
+            account.lifetime_purchased_cents = existing.lifetime_purchased_cents;
+            account.lifetime_granted_cents = existing.lifetime_granted_cents;
+            account.lifetime_used_cents = existing.lifetime_used_cents;
+            account.overdraft_locked = existing.overdraft_locked;
+            if account.credit_limit_cents != existing.credit_limit_cents {
+                account.overdraft_locked = false;
+                account.update_overdraft_lock();
+            }
+            account.version = existing.version + 1;
+        } else {
+            account.balance_cents = 0;
+            account.lifetime_purchased_cents = 0;
+            account.lifetime_granted_cents = 0;
+            account.lifetime_used_cents = 0;
+            account.overdraft_locked = false;
+            account.version = 1;
+        }
+        batch.put_cf(
+            &cf,
+            keys::account_key(&account.user_id),
+            Self::serialize(&account)?,
         );
+        self.write_outbox(&mut batch, outbox)?;
 
-        let mut total: i64 = 0;
-        for item in iter {
-            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
-            if !key.starts_with(&prefix) {
-                break;
-            }
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
 
-            let tx_id = keys::extract_transaction_id_from_user_key(&key)?;
-            if let Some(tx) = self.get_transaction(&tx_id)? {
-                if tx.created_at < since {
-                    break;
-                }
-                if tx.transaction_type == z_billing_core::TransactionType::MonthlyAllowance {
-                    total += tx.amount_cents;
-                }
-            }
-        }
+        Ok(account.version)
+    }
 
-        Ok(total)
+    /// Take the credit grant write lock.
+    fn lock_grants(&self) -> Result<std::sync::MutexGuard<'_, ()>> {
+        self.grant_lock
+            .lock()
+            .map_err(|e| StoreError::Database(format!("grant lock poisoned: {e}")))
     }
 
-    fn has_referral_bonus(&self, user_id: &UserId) -> Result<bool> {
-        let cf_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
-        let prefix = keys::user_transactions_prefix(user_id);
+    /// Add credits to an account in `batch`: update the balance, post the
+    /// transaction and open a credit lot (or draw lots down for a negative
+    /// amount). Returns the new balance.
+    fn credit_account(
+        &self,
+        batch: &mut WriteBatch,
+        user_id: &UserId,
+        amount_cents: i64,
+        transaction: &CreditTransaction,
+    ) -> Result<i64> {
+        let mut account = self.get_account(user_id)?.ok_or(StoreError::NotFound {
+            entity: "Account",
+            id: user_id.to_string(),
+        })?;
 
-        let mut upper_bound = prefix.clone();
-        upper_bound.extend([0xFF; 16]);
+        account.balance_cents += amount_cents;
+        account.update_overdraft_lock();
+        account.updated_at = chrono::Utc::now();
 
-        let iter = self.db.iterator_cf(
-            &cf_by_user,
-            IteratorMode::From(&upper_bound, rocksdb::Direction::Reverse),
+        // Track lifetime stats based on transaction type
+        match transaction.transaction_type {
+            z_billing_core::TransactionType::Purchase
+            | z_billing_core::TransactionType::AutoRefill => {
+                account.lifetime_purchased_cents += amount_cents;
+            }
+            z_billing_core::TransactionType::SubscriptionGrant
+            | z_billing_core::TransactionType::Bonus => {
+                account.lifetime_granted_cents += amount_cents;
+            }
+            _ => {}
+        }
+
+        let cf_accounts = self.cf(cf::ACCOUNTS)?;
+        batch.put_cf(
+            &cf_accounts,
+            keys::account_key(user_id),
+            Self::serialize(&account)?,
         );
+        self.write_transaction(
+            batch,
+            LedgerAccount::User(*user_id),
+            transaction,
+            amount_cents,
+        )?;
+
+        // Track where the credits came from, or spend lots for a deduction
+        if amount_cents > 0 {
+            let mut lot = CreditLot::new(
+                *user_id,
+                transaction.transaction_type.clone(),
+                amount_cents,
+                lot::default_expiry(&transaction.transaction_type, transaction.created_at),
+            );
+            lot.cap_to_balance(account.balance_cents);
+            self.write_credit_lot(batch, &lot)?;
+        } else {
+            self.draw_down_lots(batch, user_id, -amount_cents)?;
+        }
 
-        for item in iter {
-            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
-            if !key.starts_with(&prefix) {
-                break;
-            }
+        Ok(account.balance_cents)
+    }
 
-            let tx_id = keys::extract_transaction_id_from_user_key(&key)?;
-            if let Some(tx) = self.get_transaction(&tx_id)? {
-                if tx.transaction_type == z_billing_core::TransactionType::ReferralBonus {
-                    return Ok(true);
-                }
-            }
+    /// Load a gift card that must exist and still be issued.
+    fn load_issued_gift_card(&self, card_id: &GiftCardId) -> Result<GiftCard> {
+        let card = self
+            .get_gift_card(card_id)?
+            .ok_or_else(|| StoreError::NotFound {
+                entity: "GiftCard",
+                id: card_id.to_string(),
+            })?;
+        if card.status != GiftCardStatus::Issued {
+            return Err(StoreError::InvalidState {
+                entity: "GiftCard",
+                id: card_id.to_string(),
+                state: card.status.as_str().to_string(),
+            });
         }
+        Ok(card)
+    }
 
-        Ok(false)
+    /// Add outbox messages to a compound operation's batch.
+    fn write_outbox(&self, batch: &mut WriteBatch, messages: &[OutboxMessage]) -> Result<()> {
+        let cf_outbox = self.cf(cf::OUTBOX)?;
+        for message in messages {
+            batch.put_cf(
+                &cf_outbox,
+                keys::outbox_key(&message.id),
+                Self::serialize(message)?,
+            );
+        }
+        Ok(())
+    }
+
+    /// Write a closed reservation and drop it from the active index.
+    fn close_reservation(&self, batch: &mut WriteBatch, reservation: &Reservation) -> Result<()> {
+        let cf_reservations = self.cf(cf::RESERVATIONS)?;
+        let cf_active = self.cf(cf::ACTIVE_RESERVATIONS_BY_USER)?;
+
+        batch.put_cf(
+            &cf_reservations,
+            keys::reservation_key(&reservation.id),
+            Self::serialize(reservation)?,
+        );
+        batch.delete_cf(
+            &cf_active,
+            keys::user_reservation_key(&reservation.user_id, &reservation.id),
+        );
+
+        Ok(())
     }
+}
 
+/// Blocking implementations of the [`Store`] operations.
+impl RocksDb {
     // =========================================================================
-    // Usage Event Operations
+    // Account Operations
     // =========================================================================
 
-    fn has_usage_event(&self, event_id: &str) -> Result<bool> {
-        let cf = self.cf(cf::USAGE_EVENTS)?;
-        let key = keys::usage_event_key(event_id);
-
-        let exists = self
-            .db
-            .get_cf(&cf, key)
-            .map_err(|e| StoreError::Database(e.to_string()))?
-            .is_some();
+    fn put_account(&self, account: &Account) -> Result<()> {
+        let _guard = self.lock_accounts()?;
+        let existing = self.get_account(&account.user_id)?;
+        self.write_account_settings(account, existing, &[])?;
+        Ok(())
+    }
 
-        Ok(exists)
+    fn put_account_if_version(
+        &self,
+        account: &Account,
+        expected_version: i64,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let _guard = self.lock_accounts()?;
+        let existing = self.get_account(&account.user_id)?;
+        Self::check_account_version(&account.user_id, existing.as_ref(), expected_version)?;
+        self.write_account_settings(account, existing, outbox)
     }
 
-    fn put_usage_event(&self, event: &UsageEvent) -> Result<()> {
-        let cf = self.cf(cf::USAGE_EVENTS)?;
-        let key = keys::usage_event_key(&event.event_id);
-        let value = Self::serialize(event)?;
+    fn create_account(&self, account: &Account) -> Result<()> {
+        let _guard = self.lock_accounts()?;
+        let existing = self.get_account(&account.user_id)?;
+        Self::check_account_version(&account.user_id, existing.as_ref(), 0)?;
 
-        self.db
-            .put_cf(&cf, key, value)
-            .map_err(|e| StoreError::Database(e.to_string()))?;
+        let mut batch = WriteBatch::default();
+        self.write_customer_indexes(&mut batch, &account.user_id, None, Some(account))?;
+        if account.balance_cents != 0 {
+            self.write_ledger_entries(
+                &mut batch,
+                &ledger::transfer(
+                    LedgerAccount::User(account.user_id),
+                    SystemAccount::OpeningBalances,
+                    account.balance_cents,
+                    None,
+                    chrono::Utc::now(),
+                ),
+            )?;
+        }
+        let mut account = account.clone();
+        account.version = 1;
+        batch.put_cf(
+            &self.cf(cf::ACCOUNTS)?,
+            keys::account_key(&account.user_id),
+            Self::serialize(&account)?,
+        );
 
-        Ok(())
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))
     }
 
-    fn get_usage_event(&self, event_id: &str) -> Result<Option<UsageEvent>> {
-        let cf = self.cf(cf::USAGE_EVENTS)?;
-        let key = keys::usage_event_key(event_id);
+    fn get_account(&self, user_id: &UserId) -> Result<Option<Account>> {
+        let cf = self.cf(cf::ACCOUNTS)?;
+        let key = keys::account_key(user_id);
 
         self.db
             .get_cf(&cf, key)
@@ -358,181 +775,3036 @@ impl Store for RocksStore {
             .transpose()
     }
 
-    // =========================================================================
-    // Webhook Idempotency
-    // =========================================================================
-
-    fn has_webhook_event(&self, event_id: &str) -> Result<bool> {
-        let key = format!("webhook:{event_id}");
-        self.has_usage_event(&key)
-    }
-
-    fn record_webhook_event(&self, event_id: &str, source: &str) -> Result<()> {
-        let key = format!("webhook:{event_id}");
-        let cf = self.cf(cf::USAGE_EVENTS)?;
-        let value = serde_json::json!({
-            "source": source,
-            "recorded_at": chrono::Utc::now().to_rfc3339()
-        });
-        self.db
-            .put_cf(&cf, key.as_bytes(), value.to_string().as_bytes())
-            .map_err(|e| StoreError::Database(e.to_string()))
-    }
-
-    // =========================================================================
-    // Compound Operations
-    // =========================================================================
+    fn delete_account(&self, user_id: &UserId) -> Result<()> {
+        let cf = self.cf(cf::ACCOUNTS)?;
+        let key = keys::account_key(user_id);
 
-    fn process_usage(&self, event: &UsageEvent, transaction: &CreditTransaction) -> Result<i64> {
-        // Check for duplicate event
-        if self.has_usage_event(&event.event_id)? {
-            return Err(StoreError::DuplicateEvent {
-                event_id: event.event_id.clone(),
+        // Check if account exists
+        let Some(existing) = self.get_account(user_id)? else {
+            return Err(StoreError::NotFound {
+                entity: "Account",
+                id: user_id.to_string(),
             });
-        }
+        };
 
-        // Get current account
-        let mut account = self
-            .get_account(&event.user_id)?
-            .ok_or(StoreError::NotFound {
+        // Credits that ever moved stay on the books
+        let transactions = self.keys_with_prefix(
+            cf::TRANSACTIONS_BY_USER,
+            &keys::user_transactions_prefix(user_id),
+        )?;
+        let entries = self.keys_with_prefix(
+            cf::LEDGER_ENTRIES,
+            &keys::ledger_account_prefix(&LedgerAccount::User(*user_id)),
+        )?;
+        if !transactions.is_empty() || !entries.is_empty() {
+            return Err(StoreError::InvalidState {
                 entity: "Account",
-                id: event.user_id.to_string(),
-            })?;
-
-        // Check sufficient balance
-        if account.balance_cents < event.cost_cents {
-            return Err(StoreError::InsufficientCredits {
-                balance: account.balance_cents,
-                required: event.cost_cents,
+                id: user_id.to_string(),
+                state: "still on the ledger".to_string(),
             });
         }
 
-        // Prepare updates
-        let cf_accounts = self.cf(cf::ACCOUNTS)?;
-        let cf_tx = self.cf(cf::TRANSACTIONS)?;
-        let cf_tx_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
-        let cf_usage = self.cf(cf::USAGE_EVENTS)?;
-
-        // Update account
-        account.balance_cents -= event.cost_cents;
-        account.lifetime_used_cents += event.cost_cents;
-        account.updated_at = chrono::Utc::now();
-
-        let account_key = keys::account_key(&event.user_id);
-        let tx_key = keys::transaction_key(&transaction.id);
-        let user_tx_key = keys::user_transaction_key(&event.user_id, &transaction.id);
-        let event_key = keys::usage_event_key(&event.event_id);
-
-        let account_value = Self::serialize(&account)?;
-        let tx_value = Self::serialize(transaction)?;
-        let event_value = Self::serialize(event)?;
-
-        // Write atomically
         let mut batch = WriteBatch::default();
-        batch.put_cf(&cf_accounts, &account_key, &account_value);
-        batch.put_cf(&cf_tx, &tx_key, &tx_value);
-        batch.put_cf(&cf_tx_by_user, &user_tx_key, []);
-        batch.put_cf(&cf_usage, &event_key, &event_value);
+        self.write_customer_indexes(&mut batch, user_id, Some(&existing), None)?;
+        batch.delete_cf(&cf, key);
+
+        // Budgets and holds go with the account
+        let cf_budgets = self.cf(cf::AGENT_BUDGETS)?;
+        for key in
+            self.keys_with_prefix(cf::AGENT_BUDGETS, &keys::user_agent_budgets_prefix(user_id))?
+        {
+            batch.delete_cf(&cf_budgets, key);
+        }
+        let cf_active = self.cf(cf::ACTIVE_RESERVATIONS_BY_USER)?;
+        for key in self.keys_with_prefix(
+            cf::ACTIVE_RESERVATIONS_BY_USER,
+            &keys::user_reservations_prefix(user_id),
+        )? {
+            batch.delete_cf(&cf_active, key);
+        }
+        let cf_reservations = self.cf(cf::RESERVATIONS)?;
+        for item in self.db.iterator_cf(&cf_reservations, IteratorMode::Start) {
+            let (key, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if Self::deserialize::<Reservation>(&value)?.user_id == *user_id {
+                batch.delete_cf(&cf_reservations, key);
+            }
+        }
 
         self.db
             .write(batch)
             .map_err(|e| StoreError::Database(e.to_string()))?;
 
-        Ok(account.balance_cents)
+        Ok(())
     }
 
-    fn add_credits(
-        &self,
-        user_id: &UserId,
-        amount_cents: i64,
-        transaction: &CreditTransaction,
-    ) -> Result<i64> {
+    fn find_account_by_stripe_customer(&self, customer_id: &str) -> Result<Option<Account>> {
+        let cf = self.cf(cf::ACCOUNTS_BY_STRIPE_CUSTOMER)?;
+
+        let Some(user_id) = self
+            .db
+            .get_cf(&cf, keys::stripe_customer_key(customer_id))
+            .map_err(|e| StoreError::Database(e.to_string()))?
+        else {
+            return Ok(None);
+        };
+
+        self.get_account(&keys::extract_user_id(&user_id)?)
+    }
+
+    fn find_account_by_lago_customer(&self, customer_id: &str) -> Result<Option<Account>> {
+        let cf = self.cf(cf::ACCOUNTS_BY_LAGO_CUSTOMER)?;
+
+        let Some(user_id) = self
+            .db
+            .get_cf(&cf, keys::lago_customer_key(customer_id))
+            .map_err(|e| StoreError::Database(e.to_string()))?
+        else {
+            return Ok(None);
+        };
+
+        self.get_account(&keys::extract_user_id(&user_id)?)
+    }
+
+    /// Update account balance by delta.
+    ///
+    /// # Warning
+    ///
+    /// This operation is NOT atomic under concurrent access. For production use,
+    /// prefer the compound operations [`process_usage`] and [`add_credits`] which
+    /// use `WriteBatch` to ensure atomicity within a single operation.
+    ///
+    /// For true atomic read-modify-write semantics under concurrent access,
+    /// the store should be migrated to use `OptimisticTransactionDB` or implement
+    /// application-level optimistic locking.
+    ///
+    /// # Deprecation Notice
+    ///
+    /// This method is deprecated in favor of `add_credits` and `process_usage`,
+    /// which provide atomic operations with proper transaction recording. Direct
+    /// balance updates bypass the transaction audit trail and are not safe under
+    /// concurrent access. This method may be removed in a future version.
+    #[allow(deprecated)] // Implementing deprecated trait method
+    fn update_balance(&self, user_id: &UserId, delta_cents: i64) -> Result<i64> {
+        let cf = self.cf(cf::ACCOUNTS)?;
+        let key = keys::account_key(user_id);
+
         // Get current account
         let mut account = self.get_account(user_id)?.ok_or(StoreError::NotFound {
             entity: "Account",
             id: user_id.to_string(),
         })?;
 
-        // Prepare updates
-        let cf_accounts = self.cf(cf::ACCOUNTS)?;
-        let cf_tx = self.cf(cf::TRANSACTIONS)?;
-        let cf_tx_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
-
-        // Update account
-        account.balance_cents += amount_cents;
+        // Update balance
+        account.balance_cents += delta_cents;
+        account.update_overdraft_lock();
         account.updated_at = chrono::Utc::now();
 
-        // Track lifetime stats based on transaction type
-        match transaction.transaction_type {
-            z_billing_core::TransactionType::Purchase
-            | z_billing_core::TransactionType::AutoRefill => {
-                account.lifetime_purchased_cents += amount_cents;
+        // Track lifetime stats
+        if delta_cents > 0 {
+            // This is a simplified approach; the actual type tracking happens in the service
+        } else {
+            account.lifetime_used_cents += delta_cents.abs();
+        }
+
+        let mut batch = WriteBatch::default();
+        batch.put_cf(&cf, key, Self::serialize(&account)?);
+        self.write_ledger_entries(
+            &mut batch,
+            &ledger::transfer(
+                LedgerAccount::User(*user_id),
+                SystemAccount::Adjustments,
+                delta_cents,
+                None,
+                account.updated_at,
+            ),
+        )?;
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(account.balance_cents)
+    }
+
+    // =========================================================================
+    // Organization Operations
+    // =========================================================================
+
+    fn put_organization(&self, org: &Organization) -> Result<()> {
+        let cf = self.cf(cf::ORGANIZATIONS)?;
+        let key = keys::org_key(&org.id);
+        let mut batch = WriteBatch::default();
+
+        let value = if let Some(existing) = self.get_organization(&org.id)? {
+            let mut org = org.clone();
+            org.balance_cents = existing.balance_cents;
+            Self::serialize(&org)?
+        } else {
+            if org.balance_cents != 0 {
+                self.write_ledger_entries(
+                    &mut batch,
+                    &ledger::transfer(
+                        LedgerAccount::Org(org.id),
+                        SystemAccount::OpeningBalances,
+                        org.balance_cents,
+                        None,
+                        chrono::Utc::now(),
+                    ),
+                )?;
             }
-            z_billing_core::TransactionType::SubscriptionGrant
-            | z_billing_core::TransactionType::Bonus => {
-                account.lifetime_granted_cents += amount_cents;
+            Self::serialize(org)?
+        };
+        batch.put_cf(&cf, key, value);
+
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    fn get_organization(&self, org_id: &OrgId) -> Result<Option<Organization>> {
+        let cf = self.cf(cf::ORGANIZATIONS)?;
+        let key = keys::org_key(org_id);
+
+        self.db
+            .get_cf(&cf, key)
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .map(|data| Self::deserialize(&data))
+            .transpose()
+    }
+
+    fn put_org_membership(&self, membership: &OrgMembership) -> Result<()> {
+        let cf = self.cf(cf::ORG_MEMBERS)?;
+        let key = keys::org_member_key(&membership.org_id, &membership.user_id);
+        let value = Self::serialize(membership)?;
+
+        self.db
+            .put_cf(&cf, key, value)
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    fn get_org_membership(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+    ) -> Result<Option<OrgMembership>> {
+        let cf = self.cf(cf::ORG_MEMBERS)?;
+        let key = keys::org_member_key(org_id, user_id);
+
+        self.db
+            .get_cf(&cf, key)
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .map(|data| Self::deserialize(&data))
+            .transpose()
+    }
+
+    fn list_org_members(&self, org_id: &OrgId) -> Result<Vec<OrgMembership>> {
+        let cf = self.cf(cf::ORG_MEMBERS)?;
+        let prefix = keys::org_members_prefix(org_id);
+
+        let iter = self.db.iterator_cf(
+            &cf,
+            IteratorMode::From(&prefix, rocksdb::Direction::Forward),
+        );
+
+        let mut members = Vec::new();
+        for item in iter {
+            let (key, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
             }
-            _ => {}
+            members.push(Self::deserialize(&value)?);
         }
 
-        let account_key = keys::account_key(user_id);
-        let tx_key = keys::transaction_key(&transaction.id);
-        let user_tx_key = keys::user_transaction_key(user_id, &transaction.id);
+        Ok(members)
+    }
 
-        let account_value = Self::serialize(&account)?;
-        let tx_value = Self::serialize(transaction)?;
+    fn delete_org_membership(&self, org_id: &OrgId, user_id: &UserId) -> Result<()> {
+        let cf = self.cf(cf::ORG_MEMBERS)?;
+        let key = keys::org_member_key(org_id, user_id);
+
+        self.db
+            .delete_cf(&cf, key)
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    fn org_member_spend_since(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<i64> {
+        let cf_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
+        let prefix = keys::user_transactions_prefix(user_id);
+
+        let mut upper_bound = prefix.clone();
+        upper_bound.extend([0xFF; 16]);
+
+        // Walk the member's transactions newest first until we pass `since`.
+        let iter = self.db.iterator_cf(
+            &cf_by_user,
+            IteratorMode::From(&upper_bound, rocksdb::Direction::Reverse),
+        );
+
+        let mut total: i64 = 0;
+        for item in iter {
+            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let tx_id = keys::extract_transaction_id_from_user_key(&key)?;
+            let Some(tx) = self.get_transaction(&tx_id)? else {
+                continue;
+            };
+            if tx.created_at < since {
+                break;
+            }
+            if tx.org_id.as_ref() == Some(org_id)
+                && tx.transaction_type == z_billing_core::TransactionType::Usage
+            {
+                total += -tx.amount_cents;
+            }
+        }
+
+        Ok(total)
+    }
+
+    // =========================================================================
+    // Transaction Operations
+    // =========================================================================
+
+    fn put_transaction(&self, transaction: &CreditTransaction) -> Result<()> {
+        let cf_tx = self.cf(cf::TRANSACTIONS)?;
+        let cf_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
+
+        let tx_key = keys::transaction_key(&transaction.id);
+        let user_tx_key = keys::user_transaction_key(&transaction.user_id, &transaction.id);
+        let value = Self::serialize(transaction)?;
 
-        // Write atomically
         let mut batch = WriteBatch::default();
-        batch.put_cf(&cf_accounts, &account_key, &account_value);
-        batch.put_cf(&cf_tx, &tx_key, &tx_value);
-        batch.put_cf(&cf_tx_by_user, &user_tx_key, []);
+        batch.put_cf(&cf_tx, &tx_key, &value);
+        batch.put_cf(&cf_by_user, &user_tx_key, []); // Index entry (empty value)
 
         self.db
             .write(batch)
             .map_err(|e| StoreError::Database(e.to_string()))?;
 
-        Ok(account.balance_cents)
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::TempDir;
-    use z_billing_core::{CreditTransaction, UsageEvent, UsageMetric, UsageSource};
+    fn get_transaction(&self, transaction_id: &TransactionId) -> Result<Option<CreditTransaction>> {
+        let cf = self.cf(cf::TRANSACTIONS)?;
+        let key = keys::transaction_key(transaction_id);
+
+        self.db
+            .get_cf(&cf, key)
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .map(|data| Self::deserialize(&data))
+            .transpose()
+    }
+
+    fn list_transactions_by_user(
+        &self,
+        user_id: &UserId,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<CreditTransaction>> {
+        let cf_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
+        let prefix = keys::user_transactions_prefix(user_id);
+
+        let mut transactions = Vec::new();
+        let mut skipped = 0;
+
+        // Create an upper bound key by appending 0xFF bytes to the prefix
+        // This ensures we start just past the last possible transaction for this user
+        let mut upper_bound = prefix.clone();
+        upper_bound.extend([0xFF; 16]); // Max ULID bytes
+
+        // Iterate in reverse order (newest first) starting from the upper bound
+        let iter = self.db.iterator_cf(
+            &cf_by_user,
+            IteratorMode::From(&upper_bound, rocksdb::Direction::Reverse),
+        );
+
+        // Iterate directly in reverse - no need to collect and reverse
+        for item in iter {
+            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+
+            // Stop if we've moved past this user's keys
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            // Handle pagination offset
+            if skipped < offset {
+                skipped += 1;
+                continue;
+            }
+
+            // Stop once we have enough
+            if transactions.len() >= limit {
+                break;
+            }
+
+            let tx_id = keys::extract_transaction_id_from_user_key(&key)?;
+            if let Some(tx) = self.get_transaction(&tx_id)? {
+                transactions.push(tx);
+            }
+        }
+
+        Ok(transactions)
+    }
+
+    fn query_transactions(
+        &self,
+        user_id: &UserId,
+        query: &TransactionQuery,
+    ) -> Result<Vec<CreditTransaction>> {
+        let cf_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
+        let prefix = keys::user_transactions_prefix(user_id);
+
+        // Seek straight to the cursor instead of skipping over newer keys
+        let start = if let Some(before) = &query.before {
+            keys::user_transaction_key(user_id, before)
+        } else {
+            let mut upper_bound = prefix.clone();
+            upper_bound.extend([0xFF; 16]);
+            upper_bound
+        };
+        let iter = self.db.iterator_cf(
+            &cf_by_user,
+            IteratorMode::From(&start, rocksdb::Direction::Reverse),
+        );
+
+        let mut transactions = Vec::new();
+        for item in iter {
+            if transactions.len() >= query.limit {
+                break;
+            }
+
+            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let tx_id = keys::extract_transaction_id_from_user_key(&key)?;
+            if let Some(tx) = self.get_transaction(&tx_id)? {
+                if query.matches(&tx) {
+                    transactions.push(tx);
+                }
+            }
+        }
+
+        Ok(transactions)
+    }
+
+    fn sum_monthly_allowance_since(
+        &self,
+        user_id: &UserId,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<i64> {
+        let cf_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
+        let prefix = keys::user_transactions_prefix(user_id);
+
+        let mut upper_bound = prefix.clone();
+        upper_bound.extend([0xFF; 16]);
+
+        let iter = self.db.iterator_cf(
+            &cf_by_user,
+            IteratorMode::From(&upper_bound, rocksdb::Direction::Reverse),
+        );
+
+        let mut total: i64 = 0;
+        for item in iter {
+            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let tx_id = keys::extract_transaction_id_from_user_key(&key)?;
+            if let Some(tx) = self.get_transaction(&tx_id)? {
+                if tx.created_at < since {
+                    break;
+                }
+                if tx.transaction_type == z_billing_core::TransactionType::MonthlyAllowance {
+                    total += tx.amount_cents;
+                }
+            }
+        }
+
+        Ok(total)
+    }
+
+    fn has_referral_bonus(&self, user_id: &UserId) -> Result<bool> {
+        let cf_by_user = self.cf(cf::TRANSACTIONS_BY_USER)?;
+        let prefix = keys::user_transactions_prefix(user_id);
+
+        let mut upper_bound = prefix.clone();
+        upper_bound.extend([0xFF; 16]);
+
+        let iter = self.db.iterator_cf(
+            &cf_by_user,
+            IteratorMode::From(&upper_bound, rocksdb::Direction::Reverse),
+        );
+
+        for item in iter {
+            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let tx_id = keys::extract_transaction_id_from_user_key(&key)?;
+            if let Some(tx) = self.get_transaction(&tx_id)? {
+                if tx.transaction_type == z_billing_core::TransactionType::ReferralBonus {
+                    return Ok(true);
+                }
+            }
+        }
+
+        Ok(false)
+    }
+
+    // =========================================================================
+    // Usage Event Operations
+    // =========================================================================
+
+    fn has_usage_event(&self, event_id: &str) -> Result<bool> {
+        let cf = self.cf(cf::USAGE_EVENTS)?;
+        let key = keys::usage_event_key(event_id);
+
+        let exists = self
+            .db
+            .get_cf(&cf, key)
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .is_some();
+
+        Ok(exists)
+    }
+
+    fn put_usage_event(&self, event: &UsageEvent) -> Result<()> {
+        let cf = self.cf(cf::USAGE_EVENTS)?;
+        let key = keys::usage_event_key(&event.event_id);
+        let value = Self::serialize(event)?;
+
+        self.db
+            .put_cf(&cf, key, value)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    fn get_usage_event(&self, event_id: &str) -> Result<Option<UsageEvent>> {
+        let cf = self.cf(cf::USAGE_EVENTS)?;
+        let key = keys::usage_event_key(event_id);
+
+        self.db
+            .get_cf(&cf, key)
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .map(|data| Self::deserialize(&data))
+            .transpose()
+    }
+
+    fn list_usage_reversals(&self, event_id: &str) -> Result<Vec<UsageReversal>> {
+        let cf = self.cf(cf::USAGE_REVERSALS)?;
+        let prefix = keys::usage_reversals_prefix(event_id);
+
+        let iter = self.db.iterator_cf(
+            &cf,
+            IteratorMode::From(&prefix, rocksdb::Direction::Forward),
+        );
+
+        let mut reversals: Vec<UsageReversal> = Vec::new();
+        for item in iter {
+            let (key, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            reversals.push(Self::deserialize(&value)?);
+        }
+
+        reversals.sort_by_key(|r| r.created_at);
+        Ok(reversals)
+    }
+
+    fn aggregate_usage(&self, query: &UsageSummaryQuery) -> Result<Vec<UsageSummaryRow>> {
+        let cf = self.cf(cf::USAGE_EVENTS)?;
+
+        // Usage events are keyed by event ID only, so this scans all of them
+        let mut usage = Vec::new();
+        for item in self.db.iterator_cf(&cf, IteratorMode::Start) {
+            let (key, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            // Webhook idempotency markers share the column family
+            if key.starts_with(b"webhook:") {
+                continue;
+            }
+            let event: UsageEvent = Self::deserialize(&value)?;
+            if query.includes(&event) {
+                usage.push(DailyUsage::from_event(&event));
+            }
+        }
+
+        Ok(query.summarize(usage))
+    }
+
+    // =========================================================================
+    // Webhook Idempotency
+    // =========================================================================
+
+    fn has_webhook_event(&self, event_id: &str) -> Result<bool> {
+        let key = format!("webhook:{event_id}");
+        self.has_usage_event(&key)
+    }
+
+    fn record_webhook_event(&self, event_id: &str, source: &str) -> Result<()> {
+        let key = format!("webhook:{event_id}");
+        let cf = self.cf(cf::USAGE_EVENTS)?;
+        let value = serde_json::json!({
+            "source": source,
+            "recorded_at": chrono::Utc::now().to_rfc3339()
+        });
+        self.db
+            .put_cf(&cf, key.as_bytes(), value.to_string().as_bytes())
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    // =========================================================================
+    // Reservation Operations
+    // =========================================================================
+
+    fn create_reservation(&self, reservation: &Reservation) -> Result<i64> {
+        let account = self
+            .get_account(&reservation.user_id)?
+            .ok_or(StoreError::NotFound {
+                entity: "Account",
+                id: reservation.user_id.to_string(),
+            })?;
+
+        let available = account.spendable_cents() - self.reserved_cents(&reservation.user_id)?;
+        if available < reservation.amount_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: available,
+                required: reservation.amount_cents,
+            });
+        }
+
+        let cf_reservations = self.cf(cf::RESERVATIONS)?;
+        let cf_active = self.cf(cf::ACTIVE_RESERVATIONS_BY_USER)?;
+
+        let mut batch = WriteBatch::default();
+        batch.put_cf(
+            &cf_reservations,
+            keys::reservation_key(&reservation.id),
+            Self::serialize(reservation)?,
+        );
+        batch.put_cf(
+            &cf_active,
+            keys::user_reservation_key(&reservation.user_id, &reservation.id),
+            [],
+        );
+
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(available - reservation.amount_cents)
+    }
+
+    fn get_reservation(&self, reservation_id: &ReservationId) -> Result<Option<Reservation>> {
+        let cf = self.cf(cf::RESERVATIONS)?;
+        let key = keys::reservation_key(reservation_id);
+
+        self.db
+            .get_cf(&cf, key)
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .map(|data| Self::deserialize(&data))
+            .transpose()
+    }
+
+    fn reserved_cents(&self, user_id: &UserId) -> Result<i64> {
+        let cf_active = self.cf(cf::ACTIVE_RESERVATIONS_BY_USER)?;
+        let prefix = keys::user_reservations_prefix(user_id);
+        let now = chrono::Utc::now();
+
+        let iter = self.db.iterator_cf(
+            &cf_active,
+            IteratorMode::From(&prefix, rocksdb::Direction::Forward),
+        );
+
+        let mut total: i64 = 0;
+        for item in iter {
+            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let reservation_id = keys::extract_reservation_id_from_user_key(&key)?;
+            if let Some(reservation) = self.get_reservation(&reservation_id)? {
+                if reservation.is_active_at(now) {
+                    total += reservation.amount_cents;
+                }
+            }
+        }
+
+        Ok(total)
+    }
+
+    fn settle_reservation(
+        &self,
+        reservation_id: &ReservationId,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let mut reservation =
+            self.get_reservation(reservation_id)?
+                .ok_or(StoreError::NotFound {
+                    entity: "Reservation",
+                    id: reservation_id.to_string(),
+                })?;
+
+        if matches!(
+            reservation.status,
+            ReservationStatus::Settled | ReservationStatus::Released
+        ) {
+            return Err(StoreError::InvalidState {
+                entity: "Reservation",
+                id: reservation_id.to_string(),
+                state: reservation.status.as_str().to_string(),
+            });
+        }
+
+        if self.has_usage_event(&event.event_id)? {
+            return Err(StoreError::DuplicateEvent {
+                event_id: event.event_id.clone(),
+            });
+        }
+
+        let mut account = self
+            .get_account(&event.user_id)?
+            .ok_or(StoreError::NotFound {
+                entity: "Account",
+                id: event.user_id.to_string(),
+            })?;
+
+        // This reservation's own hold is what pays for the charge, so only
+        // the user's other holds are subtracted from the balance.
+        let mut held_elsewhere = self.reserved_cents(&event.user_id)?;
+        if reservation.is_active_at(chrono::Utc::now()) {
+            held_elsewhere -= reservation.amount_cents;
+        }
+        let available = account.spendable_cents() - held_elsewhere;
+        if available < event.cost_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: available,
+                required: event.cost_cents,
+            });
+        }
+        self.check_agent_budgets(event, chrono::Utc::now())?;
+
+        reservation.status = ReservationStatus::Settled;
+        reservation.transaction_id = Some(transaction.id);
+        reservation.updated_at = chrono::Utc::now();
+
+        let mut batch = self.usage_batch(&mut account, event, transaction)?;
+        self.close_reservation(&mut batch, &reservation)?;
+        self.write_outbox(&mut batch, outbox)?;
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(account.balance_cents)
+    }
+
+    fn release_reservation(&self, reservation_id: &ReservationId) -> Result<Reservation> {
+        let mut reservation =
+            self.get_reservation(reservation_id)?
+                .ok_or(StoreError::NotFound {
+                    entity: "Reservation",
+                    id: reservation_id.to_string(),
+                })?;
+
+        match reservation.status {
+            ReservationStatus::Settled => {
+                return Err(StoreError::InvalidState {
+                    entity: "Reservation",
+                    id: reservation_id.to_string(),
+                    state: reservation.status.as_str().to_string(),
+                });
+            }
+            ReservationStatus::Released | ReservationStatus::Expired => return Ok(reservation),
+            ReservationStatus::Active => {}
+        }
+
+        reservation.status = ReservationStatus::Released;
+        reservation.updated_at = chrono::Utc::now();
+
+        let mut batch = WriteBatch::default();
+        self.close_reservation(&mut batch, &reservation)?;
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(reservation)
+    }
+
+    fn expire_reservations(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize> {
+        let cf_active = self.cf(cf::ACTIVE_RESERVATIONS_BY_USER)?;
+
+        let mut batch = WriteBatch::default();
+        let mut expired = 0;
+        for item in self.db.iterator_cf(&cf_active, IteratorMode::Start) {
+            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            let reservation_id = keys::extract_reservation_id_from_user_key(&key)?;
+            let Some(mut reservation) = self.get_reservation(&reservation_id)? else {
+                continue;
+            };
+
+            if reservation.status == ReservationStatus::Active && reservation.expires_at <= now {
+                reservation.status = ReservationStatus::Expired;
+                reservation.updated_at = now;
+                self.close_reservation(&mut batch, &reservation)?;
+                expired += 1;
+            }
+        }
+
+        if expired > 0 {
+            self.db
+                .write(batch)
+                .map_err(|e| StoreError::Database(e.to_string()))?;
+        }
+
+        Ok(expired)
+    }
+
+    // =========================================================================
+    // Credit Lot Operations
+    // =========================================================================
+
+    fn list_credit_lots(&self, user_id: &UserId) -> Result<Vec<CreditLot>> {
+        let cf_lots = self.cf(cf::CREDIT_LOTS)?;
+        let prefix = keys::user_credit_lots_prefix(user_id);
+
+        let iter = self.db.iterator_cf(
+            &cf_lots,
+            IteratorMode::From(&prefix, rocksdb::Direction::Forward),
+        );
+
+        let mut lots = Vec::new();
+        for item in iter {
+            let (key, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            lots.push(Self::deserialize::<CreditLot>(&value)?);
+        }
+
+        lot::sort_for_consumption(&mut lots);
+        Ok(lots)
+    }
+
+    fn expire_credit_lots(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize> {
+        let cf_lots = self.cf(cf::CREDIT_LOTS)?;
+        let cf_by_expiry = self.cf(cf::CREDIT_LOTS_BY_EXPIRY)?;
+        let cf_accounts = self.cf(cf::ACCOUNTS)?;
+        let now_millis = u64::try_from(now.timestamp_millis()).unwrap_or(0);
+
+        // Collect due lots first; the index is ordered by expiry time.
+        let mut due = Vec::new();
+        for item in self.db.iterator_cf(&cf_by_expiry, IteratorMode::Start) {
+            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            let (expires_millis, lot_key) = keys::split_credit_lot_expiry_key(&key)?;
+            if expires_millis > now_millis {
+                break;
+            }
+            due.push((key.to_vec(), lot_key.to_vec()));
+        }
+
+        let mut expired = 0;
+        for (expiry_key, lot_key) in due {
+            let lot = self
+                .db
+                .get_cf(&cf_lots, &lot_key)
+                .map_err(|e| StoreError::Database(e.to_string()))?
+                .map(|data| Self::deserialize::<CreditLot>(&data))
+                .transpose()?;
+
+            let mut batch = WriteBatch::default();
+            let account = match &lot {
+                Some(lot) => self.get_account(&lot.user_id)?,
+                None => None,
+            };
+            let (Some(mut lot), Some(mut account)) = (lot, account) else {
+                // Stale index entry or orphaned lot; just clean it up.
+                batch.delete_cf(&cf_by_expiry, &expiry_key);
+                batch.delete_cf(&cf_lots, &lot_key);
+                self.db
+                    .write(batch)
+                    .map_err(|e| StoreError::Database(e.to_string()))?;
+                continue;
+            };
+
+            account.balance_cents -= lot.remaining_cents;
+            account.updated_at = now;
+            let transaction = CreditTransaction::expiry(&lot, account.balance_cents);
+
+            batch.put_cf(
+                &cf_accounts,
+                keys::account_key(&account.user_id),
+                Self::serialize(&account)?,
+            );
+            self.write_transaction(
+                &mut batch,
+                LedgerAccount::User(account.user_id),
+                &transaction,
+                -lot.remaining_cents,
+            )?;
+            lot.remaining_cents = 0;
+            self.write_credit_lot(&mut batch, &lot)?;
+            self.db
+                .write(batch)
+                .map_err(|e| StoreError::Database(e.to_string()))?;
+            expired += 1;
+        }
+
+        Ok(expired)
+    }
+
+    // =========================================================================
+    // Credit Transfer Operations
+    // =========================================================================
+
+    fn get_credit_transfer(
+        &self,
+        from_user_id: &UserId,
+        idempotency_key: &str,
+    ) -> Result<Option<CreditTransfer>> {
+        let cf = self.cf(cf::CREDIT_TRANSFERS)?;
+        let key = keys::credit_transfer_key(from_user_id, idempotency_key);
+
+        self.db
+            .get_cf(&cf, key)
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .map(|data| Self::deserialize(&data))
+            .transpose()
+    }
+
+    // =========================================================================
+    // Promo Code Operations
+    // =========================================================================
+
+    fn put_promo_code(&self, promo: &PromoCode) -> Result<()> {
+        let _guard = self.lock_promos()?;
+        let cf = self.cf(cf::PROMO_CODES)?;
+
+        let mut promo = promo.clone();
+        if let Some(existing) = self.get_promo_code(&promo.code)? {
+            promo.redemption_count = existing.redemption_count;
+            promo.created_at = existing.created_at;
+        }
+
+        self.db
+            .put_cf(
+                &cf,
+                keys::promo_code_key(&promo.code),
+                Self::serialize(&promo)?,
+            )
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    fn get_promo_code(&self, code: &str) -> Result<Option<PromoCode>> {
+        let cf = self.cf(cf::PROMO_CODES)?;
+
+        self.db
+            .get_cf(&cf, keys::promo_code_key(code))
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .map(|data| Self::deserialize(&data))
+            .transpose()
+    }
+
+    fn list_promo_codes(&self) -> Result<Vec<PromoCode>> {
+        let cf = self.cf(cf::PROMO_CODES)?;
+
+        let mut promos = Vec::new();
+        for item in self.db.iterator_cf(&cf, IteratorMode::Start) {
+            let (_, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            promos.push(Self::deserialize(&value)?);
+        }
+
+        Ok(promos)
+    }
+
+    fn delete_promo_code(&self, code: &str) -> Result<()> {
+        let _guard = self.lock_promos()?;
+        let cf = self.cf(cf::PROMO_CODES)?;
+
+        self.db
+            .delete_cf(&cf, keys::promo_code_key(code))
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    fn count_promo_redemptions(&self, code: &str, user_id: &UserId) -> Result<i64> {
+        let cf = self.cf(cf::PROMO_REDEMPTIONS)?;
+        let prefix = keys::user_promo_redemptions_prefix(code, user_id);
+
+        let iter = self.db.iterator_cf(
+            &cf,
+            IteratorMode::From(&prefix, rocksdb::Direction::Forward),
+        );
+
+        let mut count = 0;
+        for item in iter {
+            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    // =========================================================================
+    // Gift Card Operations
+    // =========================================================================
+
+    fn create_gift_card(&self, card: &GiftCard, outbox: &[OutboxMessage]) -> Result<GiftCard> {
+        let _guard = self.lock_gift_cards()?;
+        let cf_cards = self.cf(cf::GIFT_CARDS)?;
+        let cf_codes = self.cf(cf::GIFT_CARD_CODES)?;
+        let cf_purchaser = self.cf(cf::GIFT_CARDS_BY_PURCHASER)?;
+
+        // A checkout session mints one card, and it belongs to the buyer
+        if card.stripe_session_id.is_some() {
+            if let Some(existing) = self
+                .list_gift_cards_by_purchaser(&card.purchaser_id)?
+                .into_iter()
+                .find(|c| c.stripe_session_id == card.stripe_session_id)
+            {
+                return Ok(existing);
+            }
+        }
+
+        if self.get_gift_card_by_code(&card.code)?.is_some() {
+            return Err(StoreError::Database(format!(
+                "gift card code already exists for card {}",
+                card.id
+            )));
+        }
+
+        let mut batch = WriteBatch::default();
+        batch.put_cf(
+            &cf_cards,
+            keys::gift_card_key(&card.id),
+            Self::serialize(card)?,
+        );
+        batch.put_cf(
+            &cf_codes,
+            keys::gift_card_code_key(&card.code),
+            keys::gift_card_key(&card.id),
+        );
+        batch.put_cf(
+            &cf_purchaser,
+            keys::purchaser_gift_card_key(&card.purchaser_id, &card.id),
+            [],
+        );
+
+        self.write_outbox(&mut batch, outbox)?;
+
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(card.clone())
+    }
+
+    fn get_gift_card(&self, card_id: &GiftCardId) -> Result<Option<GiftCard>> {
+        let cf = self.cf(cf::GIFT_CARDS)?;
+
+        self.db
+            .get_cf(&cf, keys::gift_card_key(card_id))
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .map(|data| Self::deserialize(&data))
+            .transpose()
+    }
+
+    fn get_gift_card_by_code(&self, code: &str) -> Result<Option<GiftCard>> {
+        let cf = self.cf(cf::GIFT_CARD_CODES)?;
+
+        let Some(card_id) = self
+            .db
+            .get_cf(&cf, keys::gift_card_code_key(code))
+            .map_err(|e| StoreError::Database(e.to_string()))?
+        else {
+            return Ok(None);
+        };
+
+        self.get_gift_card(&keys::extract_gift_card_id(&card_id)?)
+    }
+
+    fn list_gift_cards_by_purchaser(&self, user_id: &UserId) -> Result<Vec<GiftCard>> {
+        let cf = self.cf(cf::GIFT_CARDS_BY_PURCHASER)?;
+        let prefix = keys::purchaser_gift_cards_prefix(user_id);
+
+        let iter = self.db.iterator_cf(
+            &cf,
+            IteratorMode::From(&prefix, rocksdb::Direction::Forward),
+        );
+
+        let mut cards = Vec::new();
+        for item in iter {
+            let (key, _) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            if let Some(card) = self.get_gift_card(&keys::extract_gift_card_id(&key)?)? {
+                cards.push(card);
+            }
+        }
+
+        // Card IDs are ULIDs, so the index is oldest first
+        cards.reverse();
+        Ok(cards)
+    }
+
+    fn void_gift_card(&self, card_id: &GiftCardId, reason: &str) -> Result<GiftCard> {
+        let _guard = self.lock_gift_cards()?;
+        let mut card = self.load_issued_gift_card(card_id)?;
+        card.void(reason.to_string());
+
+        let cf = self.cf(cf::GIFT_CARDS)?;
+        self.db
+            .put_cf(&cf, keys::gift_card_key(card_id), Self::serialize(&card)?)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(card)
+    }
+
+    // =========================================================================
+    // Agent Budget Operations
+    // =========================================================================
+
+    fn put_agent_budget(&self, budget: &AgentBudget) -> Result<()> {
+        let cf = self.cf(cf::AGENT_BUDGETS)?;
+        let key = keys::agent_budget_key(&budget.user_id, &budget.agent_id, budget.period);
+        let value = Self::serialize(budget)?;
+
+        self.db
+            .put_cf(&cf, key, value)
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    fn list_agent_budgets(&self, user_id: &UserId, agent_id: &AgentId) -> Result<Vec<AgentBudget>> {
+        let cf = self.cf(cf::AGENT_BUDGETS)?;
+        let prefix = keys::agent_budgets_prefix(user_id, agent_id);
+
+        let iter = self.db.iterator_cf(
+            &cf,
+            IteratorMode::From(&prefix, rocksdb::Direction::Forward),
+        );
+
+        let mut budgets = Vec::new();
+        for item in iter {
+            let (key, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            budgets.push(Self::deserialize(&value)?);
+        }
+
+        Ok(budgets)
+    }
+
+    fn delete_agent_budget(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+        period: BudgetPeriod,
+    ) -> Result<()> {
+        let cf = self.cf(cf::AGENT_BUDGETS)?;
+
+        self.db
+            .delete_cf(&cf, keys::agent_budget_key(user_id, agent_id, period))
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    fn get_agent_spend(&self, user_id: &UserId, agent_id: &AgentId) -> Result<Option<AgentSpend>> {
+        let cf = self.cf(cf::AGENT_SPEND)?;
+
+        match self
+            .db
+            .get_cf(&cf, keys::agent_spend_key(user_id, agent_id))
+            .map_err(|e| StoreError::Database(e.to_string()))?
+        {
+            Some(data) => Ok(Some(Self::deserialize(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn list_agent_spend(&self, user_id: &UserId) -> Result<Vec<AgentSpend>> {
+        let cf = self.cf(cf::AGENT_SPEND)?;
+        let prefix = keys::user_agent_spend_prefix(user_id);
+
+        let iter = self.db.iterator_cf(
+            &cf,
+            IteratorMode::From(&prefix, rocksdb::Direction::Forward),
+        );
+
+        let mut totals = Vec::new();
+        for item in iter {
+            let (key, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            totals.push(Self::deserialize(&value)?);
+        }
+
+        Ok(totals)
+    }
+
+    // =========================================================================
+    // Ledger Operations
+    // =========================================================================
+
+    fn list_ledger_entries(
+        &self,
+        account: &LedgerAccount,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<LedgerEntry>> {
+        let cf = self.cf(cf::LEDGER_ENTRIES)?;
+        let prefix = keys::ledger_account_prefix(account);
+
+        // Start just past the last possible entry for this account
+        let mut upper_bound = prefix.clone();
+        upper_bound.extend([0xFF; 24]); // Max timestamp + entry ID bytes
+
+        let iter = self.db.iterator_cf(
+            &cf,
+            IteratorMode::From(&upper_bound, rocksdb::Direction::Reverse),
+        );
+
+        let mut entries = Vec::new();
+        for item in iter.skip(offset) {
+            let (key, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(&prefix) || entries.len() >= limit {
+                break;
+            }
+            entries.push(Self::deserialize(&value)?);
+        }
+
+        Ok(entries)
+    }
+
+    fn ledger_balance(&self, account: &LedgerAccount) -> Result<i64> {
+        self.sum_ledger_entries(&keys::ledger_account_prefix(account))
+    }
+
+    fn verify_ledger(&self) -> Result<LedgerReport> {
+        let cf_ledger = self.cf(cf::LEDGER_ENTRIES)?;
+        let cf_accounts = self.cf(cf::ACCOUNTS)?;
+        let cf_orgs = self.cf(cf::ORGANIZATIONS)?;
+
+        // Sum every account's entries in one pass
+        let mut sums = std::collections::HashMap::new();
+        let mut report = LedgerReport::default();
+        for item in self.db.iterator_cf(&cf_ledger, IteratorMode::Start) {
+            let (_, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            let entry = Self::deserialize::<LedgerEntry>(&value)?;
+            *sums.entry(entry.account).or_insert(0) += entry.amount_cents;
+            report.unbalanced_cents += entry.amount_cents;
+        }
+
+        let mut check = |account: LedgerAccount, balance_cents: i64| {
+            report.accounts_checked += 1;
+            let ledger_cents = sums.get(&account).copied().unwrap_or(0);
+            if ledger_cents != balance_cents {
+                report.drift.push(LedgerDrift {
+                    account,
+                    balance_cents,
+                    ledger_cents,
+                });
+            }
+        };
+
+        for item in self.db.iterator_cf(&cf_accounts, IteratorMode::Start) {
+            let (_, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            let account = Self::deserialize::<Account>(&value)?;
+            check(LedgerAccount::User(account.user_id), account.balance_cents);
+        }
+        for item in self.db.iterator_cf(&cf_orgs, IteratorMode::Start) {
+            let (_, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            let org = Self::deserialize::<Organization>(&value)?;
+            check(LedgerAccount::Org(org.id), org.balance_cents);
+        }
+
+        Ok(report)
+    }
+
+    // =========================================================================
+    // Outbox Operations
+    // =========================================================================
+
+    fn put_outbox_message(&self, message: &OutboxMessage) -> Result<()> {
+        let cf = self.cf(cf::OUTBOX)?;
+
+        self.db
+            .put_cf(
+                &cf,
+                keys::outbox_key(&message.id),
+                Self::serialize(message)?,
+            )
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    fn get_outbox_message(&self, id: &OutboxId) -> Result<Option<OutboxMessage>> {
+        let cf = self.cf(cf::OUTBOX)?;
+
+        self.db
+            .get_cf(&cf, keys::outbox_key(id))
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .map(|data| Self::deserialize(&data))
+            .transpose()
+    }
+
+    /// Every outbox message, oldest first.
+    fn outbox_messages(&self) -> Result<Vec<OutboxMessage>> {
+        let cf = self.cf(cf::OUTBOX)?;
+
+        self.db
+            .iterator_cf(&cf, IteratorMode::Start)
+            .map(|item| {
+                let (_, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+                Self::deserialize(&value)
+            })
+            .collect()
+    }
+
+    fn list_outbox_messages(
+        &self,
+        status: Option<OutboxStatus>,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>> {
+        let mut messages = self.outbox_messages()?;
+        messages.retain(|message| status.is_none() || Some(message.status) == status);
+        messages.truncate(limit);
+        Ok(messages)
+    }
+
+    fn claim_outbox_messages(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+        lease: chrono::Duration,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>> {
+        let _guard = self
+            .outbox_lock
+            .lock()
+            .map_err(|e| StoreError::Database(format!("outbox lock poisoned: {e}")))?;
+        let cf = self.cf(cf::OUTBOX)?;
+
+        let mut claimed = Vec::new();
+        let mut batch = WriteBatch::default();
+        for mut message in self.outbox_messages()? {
+            if claimed.len() == limit {
+                break;
+            }
+            if !message.is_due_at(now) {
+                continue;
+            }
+            message.next_attempt_at = now + lease;
+            batch.put_cf(
+                &cf,
+                keys::outbox_key(&message.id),
+                Self::serialize(&message)?,
+            );
+            claimed.push(message);
+        }
+
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(claimed)
+    }
+
+    fn delete_outbox_message(&self, id: &OutboxId) -> Result<()> {
+        let cf = self.cf(cf::OUTBOX)?;
+
+        self.db
+            .delete_cf(&cf, keys::outbox_key(id))
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    // =========================================================================
+    // Pricing Catalog
+    // =========================================================================
+
+    fn put_pricing_catalog(&self, catalog: &PricingCatalog) -> Result<()> {
+        let _guard = self
+            .pricing_catalog_lock
+            .lock()
+            .map_err(|e| StoreError::Database(format!("pricing catalog lock poisoned: {e}")))?;
+
+        let cf = self.cf(cf::PRICING_CATALOGS)?;
+        let key = keys::pricing_catalog_key(&catalog.version);
+        if self
+            .db
+            .get_cf(&cf, &key)
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .is_some()
+        {
+            return Err(StoreError::InvalidState {
+                entity: "pricing catalog",
+                id: catalog.version.clone(),
+                state: "already saved".to_string(),
+            });
+        }
+
+        let saved = SavedPricingCatalog {
+            saved_at: chrono::Utc::now(),
+            catalog: catalog.clone(),
+        };
+        self.db
+            .put_cf(&cf, key, Self::serialize(&saved)?)
+            .map_err(|e| StoreError::Database(e.to_string()))
+    }
+
+    fn get_latest_pricing_catalog(&self) -> Result<Option<PricingCatalog>> {
+        let cf = self.cf(cf::PRICING_CATALOGS)?;
+
+        let mut latest: Option<SavedPricingCatalog> = None;
+        for item in self.db.iterator_cf(&cf, IteratorMode::Start) {
+            let (_, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            let saved: SavedPricingCatalog = Self::deserialize(&value)?;
+            // `None` sorts before every time
+            if latest.as_ref().map(|latest| latest.saved_at) < Some(saved.saved_at) {
+                latest = Some(saved);
+            }
+        }
+        Ok(latest.map(|saved| saved.catalog))
+    }
+
+    // =========================================================================
+    // Storage Meters
+    // =========================================================================
+
+    fn get_storage_meter(
+        &self,
+        user_id: &UserId,
+        resource_id: &str,
+    ) -> Result<Option<StorageMeter>> {
+        let cf = self.cf(cf::STORAGE_METERS)?;
+        self.db
+            .get_cf(&cf, keys::storage_meter_key(user_id, resource_id))
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .map(|data| Self::deserialize(&data))
+            .transpose()
+    }
+
+    fn put_storage_meter_if_version(
+        &self,
+        meter: &StorageMeter,
+        expected_version: i64,
+    ) -> Result<i64> {
+        let _guard = self
+            .storage_meter_lock
+            .lock()
+            .map_err(|e| StoreError::Database(format!("storage meter lock poisoned: {e}")))?;
+
+        let actual = self
+            .get_storage_meter(&meter.user_id, &meter.resource_id)?
+            .map_or(0, |stored| stored.version);
+        if actual != expected_version {
+            return Err(StoreError::VersionConflict {
+                entity: "StorageMeter",
+                id: format!("{}/{}", meter.user_id, meter.resource_id),
+                expected: expected_version,
+                actual,
+            });
+        }
+
+        let mut meter = meter.clone();
+        meter.version = expected_version + 1;
+        self.db
+            .put_cf(
+                &self.cf(cf::STORAGE_METERS)?,
+                keys::storage_meter_key(&meter.user_id, &meter.resource_id),
+                Self::serialize(&meter)?,
+            )
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+        Ok(meter.version)
+    }
+
+    // =========================================================================
+    // Export
+    // =========================================================================
+
+    /// Deserialize up to `limit` values of `cf_name` in key order, starting
+    /// after the key `after` and stopping at the first key outside `prefix`.
+    /// Keys for which `skip` is true are passed over.
+    fn scan_after<T: serde::de::DeserializeOwned>(
+        &self,
+        cf_name: &str,
+        prefix: &[u8],
+        after: Option<&[u8]>,
+        limit: usize,
+        skip: impl Fn(&[u8]) -> bool,
+    ) -> Result<Vec<T>> {
+        let cf = self.cf(cf_name)?;
+        let start = after.unwrap_or(prefix);
+        let iter = self
+            .db
+            .iterator_cf(&cf, IteratorMode::From(start, rocksdb::Direction::Forward));
+
+        let mut values = Vec::new();
+        for item in iter {
+            if values.len() == limit {
+                break;
+            }
+            let (key, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            if Some(&*key) == after || skip(&key) {
+                continue;
+            }
+            values.push(Self::deserialize(&value)?);
+        }
+
+        Ok(values)
+    }
+
+    fn list_accounts(&self, after: Option<&UserId>, limit: usize) -> Result<Vec<Account>> {
+        let after = after.map(keys::account_key);
+        self.scan_after(cf::ACCOUNTS, &[], after.as_deref(), limit, |_| false)
+    }
+
+    fn list_transactions(
+        &self,
+        after: Option<&TransactionId>,
+        limit: usize,
+    ) -> Result<Vec<CreditTransaction>> {
+        let after = after.map(keys::transaction_key);
+        self.scan_after(cf::TRANSACTIONS, &[], after.as_deref(), limit, |_| false)
+    }
+
+    fn list_usage_events(&self, after: Option<&str>, limit: usize) -> Result<Vec<UsageEvent>> {
+        let after = after.map(keys::usage_event_key);
+        // Webhook idempotency markers share the column family
+        self.scan_after(cf::USAGE_EVENTS, &[], after.as_deref(), limit, |key| {
+            key.starts_with(b"webhook:")
+        })
+    }
+
+    fn list_webhook_events(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ProcessedWebhook>> {
+        #[derive(serde::Deserialize)]
+        struct Marker {
+            source: String,
+        }
+
+        let cf = self.cf(cf::USAGE_EVENTS)?;
+        let prefix = b"webhook:";
+        let after = after.map(|event_id| format!("webhook:{event_id}"));
+        let start = after.as_ref().map_or(&prefix[..], String::as_bytes);
+        let iter = self
+            .db
+            .iterator_cf(&cf, IteratorMode::From(start, rocksdb::Direction::Forward));
+
+        let mut webhooks = Vec::new();
+        for item in iter {
+            if webhooks.len() == limit {
+                break;
+            }
+            let (key, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            let Some(event_id) = key.strip_prefix(prefix) else {
+                break;
+            };
+            if Some(&*key) == after.as_ref().map(String::as_bytes) {
+                continue;
+            }
+            // Markers are JSON, unlike the CBOR usage events around them
+            let marker: Marker = serde_json::from_slice(&value)
+                .map_err(|e| StoreError::Serialization(e.to_string()))?;
+            webhooks.push(ProcessedWebhook {
+                event_id: String::from_utf8_lossy(event_id).into_owned(),
+                source: marker.source,
+            });
+        }
+
+        Ok(webhooks)
+    }
+
+    fn count_unlisted(&self) -> Result<Vec<(&'static str, u64)>> {
+        let count_keys = |name: &str| -> Result<u64> {
+            let cf = self.cf(name)?;
+            let mut count = 0;
+            for item in self.db.iterator_cf(&cf, IteratorMode::Start) {
+                item.map_err(|e| StoreError::Database(e.to_string()))?;
+                count += 1;
+            }
+            Ok(count)
+        };
+
+        let cf_cards = self.cf(cf::GIFT_CARDS)?;
+        let mut issued_cards = 0;
+        for item in self.db.iterator_cf(&cf_cards, IteratorMode::Start) {
+            let (_, value) = item.map_err(|e| StoreError::Database(e.to_string()))?;
+            let card: GiftCard = Self::deserialize(&value)?;
+            if card.status == GiftCardStatus::Issued {
+                issued_cards += 1;
+            }
+        }
+
+        // Spent lots are deleted, so every stored lot is open
+        Ok(vec![
+            ("credit_lots", count_keys(cf::CREDIT_LOTS)?),
+            ("org_memberships", count_keys(cf::ORG_MEMBERS)?),
+            ("agent_budgets", count_keys(cf::AGENT_BUDGETS)?),
+            ("promo_codes", count_keys(cf::PROMO_CODES)?),
+            ("gift_cards", issued_cards),
+            ("credit_grants", count_keys(cf::CREDIT_GRANTS)?),
+            ("storage_meters", count_keys(cf::STORAGE_METERS)?),
+        ])
+    }
+
+    // =========================================================================
+    // Compound Operations
+    // =========================================================================
+
+    fn process_usage(
+        &self,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        // Check for duplicate event
+        if self.has_usage_event(&event.event_id)? {
+            return Err(StoreError::DuplicateEvent {
+                event_id: event.event_id.clone(),
+            });
+        }
+
+        // Get current account
+        let mut account = self
+            .get_account(&event.user_id)?
+            .ok_or(StoreError::NotFound {
+                entity: "Account",
+                id: event.user_id.to_string(),
+            })?;
+
+        // Check sufficient balance, holding back active reservations
+        let available = account.spendable_cents() - self.reserved_cents(&event.user_id)?;
+        if available < event.cost_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: available,
+                required: event.cost_cents,
+            });
+        }
+
+        // Check the agent's budgets
+        self.check_agent_budgets(event, chrono::Utc::now())?;
+
+        // Write atomically
+        let mut batch = self.usage_batch(&mut account, event, transaction)?;
+        self.write_outbox(&mut batch, outbox)?;
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(account.balance_cents)
+    }
+
+    fn process_org_usage(
+        &self,
+        org_id: &OrgId,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        if self.has_usage_event(&event.event_id)? {
+            return Err(StoreError::DuplicateEvent {
+                event_id: event.event_id.clone(),
+            });
+        }
+
+        let mut org = self.get_organization(org_id)?.ok_or(StoreError::NotFound {
+            entity: "Organization",
+            id: org_id.to_string(),
+        })?;
+        let membership =
+            self.get_org_membership(org_id, &event.user_id)?
+                .ok_or(StoreError::NotFound {
+                    entity: "OrgMembership",
+                    id: format!("{org_id}/{}", event.user_id),
+                })?;
+
+        let now = chrono::Utc::now();
+        if let Some(cap) = membership.monthly_spend_cap_cents {
+            let spent = self.org_member_spend_since(
+                org_id,
+                &event.user_id,
+                org::spend_cap_period_start(now),
+            )?;
+            if spent + event.cost_cents > cap {
+                return Err(StoreError::SpendCapExceeded {
+                    cap,
+                    spent,
+                    required: event.cost_cents,
+                });
+            }
+        }
+
+        if org.balance_cents < event.cost_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: org.balance_cents,
+                required: event.cost_cents,
+            });
+        }
+        self.check_agent_budgets(event, now)?;
+
+        org.balance_cents -= event.cost_cents;
+        org.lifetime_used_cents += event.cost_cents;
+        org.updated_at = now;
+
+        let cf_orgs = self.cf(cf::ORGANIZATIONS)?;
+        let cf_usage = self.cf(cf::USAGE_EVENTS)?;
+
+        let mut batch = WriteBatch::default();
+        batch.put_cf(&cf_orgs, keys::org_key(org_id), Self::serialize(&org)?);
+        self.write_transaction(
+            &mut batch,
+            LedgerAccount::Org(*org_id),
+            transaction,
+            -event.cost_cents,
+        )?;
+        batch.put_cf(
+            &cf_usage,
+            keys::usage_event_key(&event.event_id),
+            Self::serialize(event)?,
+        );
+        self.record_agent_spend(&mut batch, event, now)?;
+
+        self.write_outbox(&mut batch, outbox)?;
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(org.balance_cents)
+    }
+
+    fn add_org_credits(
+        &self,
+        org_id: &OrgId,
+        amount_cents: i64,
+        transaction: &CreditTransaction,
+    ) -> Result<i64> {
+        let mut org = self.get_organization(org_id)?.ok_or(StoreError::NotFound {
+            entity: "Organization",
+            id: org_id.to_string(),
+        })?;
+
+        org.balance_cents += amount_cents;
+        if amount_cents > 0 {
+            org.lifetime_purchased_cents += amount_cents;
+        }
+        org.updated_at = chrono::Utc::now();
+
+        let cf_orgs = self.cf(cf::ORGANIZATIONS)?;
+
+        let mut batch = WriteBatch::default();
+        batch.put_cf(&cf_orgs, keys::org_key(org_id), Self::serialize(&org)?);
+        self.write_transaction(
+            &mut batch,
+            LedgerAccount::Org(*org_id),
+            transaction,
+            amount_cents,
+        )?;
+
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(org.balance_cents)
+    }
+
+    fn add_credits(
+        &self,
+        user_id: &UserId,
+        amount_cents: i64,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let mut batch = WriteBatch::default();
+        let balance = self.credit_account(&mut batch, user_id, amount_cents, transaction)?;
+
+        // Write atomically
+        self.write_outbox(&mut batch, outbox)?;
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(balance)
+    }
+
+    fn grant_once(
+        &self,
+        user_id: &UserId,
+        grant_key: &str,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<Option<i64>> {
+        let _guard = self.lock_grants()?;
+
+        let cf_grants = self.cf(cf::CREDIT_GRANTS)?;
+        let key = keys::credit_grant_key(user_id, grant_key);
+        if self
+            .db
+            .get_cf(&cf_grants, &key)
+            .map_err(|e| StoreError::Database(e.to_string()))?
+            .is_some()
+        {
+            return Ok(None);
+        }
+
+        let mut batch = WriteBatch::default();
+        let balance =
+            self.credit_account(&mut batch, user_id, transaction.amount_cents, transaction)?;
+        batch.put_cf(&cf_grants, &key, transaction.id.to_string().as_bytes());
+        self.write_outbox(&mut batch, outbox)?;
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(Some(balance))
+    }
+
+    fn reverse_usage(
+        &self,
+        reversal: &UsageReversal,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<UsageReversal> {
+        let cf_reversals = self.cf(cf::USAGE_REVERSALS)?;
+        let reversal_key = keys::usage_reversal_key(&reversal.event_id, &reversal.reversal_id);
+
+        if let Some(existing) = self
+            .db
+            .get_cf(&cf_reversals, &reversal_key)
+            .map_err(|e| StoreError::Database(e.to_string()))?
+        {
+            return Self::deserialize(&existing);
+        }
+
+        let event =
+            self.get_usage_event(&reversal.event_id)?
+                .ok_or_else(|| StoreError::NotFound {
+                    entity: "UsageEvent",
+                    id: reversal.event_id.clone(),
+                })?;
+
+        let reversed: i64 = self
+            .list_usage_reversals(&reversal.event_id)?
+            .iter()
+            .map(|r| r.amount_cents)
+            .sum();
+        if reversed + reversal.amount_cents > event.cost_cents {
+            return Err(StoreError::ReversalExceedsCharge {
+                charged: event.cost_cents,
+                reversed,
+                requested: reversal.amount_cents,
+            });
+        }
+
+        let now = chrono::Utc::now();
+
+        let mut batch = WriteBatch::default();
+
+        // Credit whichever balance the usage was charged against
+        if let Some(org_id) = transaction.org_id {
+            let mut org = self
+                .get_organization(&org_id)?
+                .ok_or(StoreError::NotFound {
+                    entity: "Organization",
+                    id: org_id.to_string(),
+                })?;
+            org.balance_cents += reversal.amount_cents;
+            org.lifetime_used_cents -= reversal.amount_cents;
+            org.updated_at = now;
+
+            let cf_orgs = self.cf(cf::ORGANIZATIONS)?;
+            batch.put_cf(&cf_orgs, keys::org_key(&org_id), Self::serialize(&org)?);
+        } else {
+            let mut account =
+                self.get_account(&transaction.user_id)?
+                    .ok_or(StoreError::NotFound {
+                        entity: "Account",
+                        id: transaction.user_id.to_string(),
+                    })?;
+            account.balance_cents += reversal.amount_cents;
+            account.lifetime_used_cents -= reversal.amount_cents;
+            account.update_overdraft_lock();
+            account.updated_at = now;
+
+            let cf_accounts = self.cf(cf::ACCOUNTS)?;
+            batch.put_cf(
+                &cf_accounts,
+                keys::account_key(&transaction.user_id),
+                Self::serialize(&account)?,
+            );
+
+            // Refill the lots the usage drew from
+            let lots = lot::refund_draws(
+                transaction.user_id,
+                &self.get_lot_draws(&reversal.event_id)?,
+                event.cost_cents,
+                reversed,
+                reversal.amount_cents,
+                account.balance_cents,
+            );
+            for lot in &lots {
+                self.write_credit_lot(&mut batch, lot)?;
+            }
+        }
+
+        self.write_transaction(
+            &mut batch,
+            LedgerAccount::wallet_for(transaction),
+            transaction,
+            reversal.amount_cents,
+        )?;
+        batch.put_cf(&cf_reversals, &reversal_key, Self::serialize(reversal)?);
+
+        self.write_outbox(&mut batch, outbox)?;
+
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(reversal.clone())
+    }
+
+    fn transfer_credits(
+        &self,
+        transfer: &CreditTransfer,
+        debit: &CreditTransaction,
+        credit: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<CreditTransfer> {
+        if let Some(existing) =
+            self.get_credit_transfer(&transfer.from_user_id, &transfer.idempotency_key)?
+        {
+            return Ok(existing);
+        }
+
+        let mut sender = self
+            .get_account(&transfer.from_user_id)?
+            .ok_or(StoreError::NotFound {
+                entity: "Account",
+                id: transfer.from_user_id.to_string(),
+            })?;
+
+        // Neither the credit line nor held credits can be given away
+        let available = sender.balance_cents - self.reserved_cents(&transfer.from_user_id)?;
+        if available < transfer.amount_cents {
+            return Err(StoreError::InsufficientCredits {
+                balance: available,
+                required: transfer.amount_cents,
+            });
+        }
+
+        let transferable = lot::transferable_cents(&self.list_credit_lots(&transfer.from_user_id)?);
+        if transferable < transfer.amount_cents {
+            return Err(StoreError::TransferExceedsPurchased {
+                transferable,
+                requested: transfer.amount_cents,
+            });
+        }
+
+        let now = chrono::Utc::now();
+        let cf_accounts = self.cf(cf::ACCOUNTS)?;
+        let mut batch = WriteBatch::default();
+
+        sender.balance_cents -= transfer.amount_cents;
+        sender.update_overdraft_lock();
+        sender.updated_at = now;
+        batch.put_cf(
+            &cf_accounts,
+            keys::account_key(&transfer.from_user_id),
+            Self::serialize(&sender)?,
+        );
+        self.draw_down_transferable_lots(
+            &mut batch,
+            &transfer.from_user_id,
+            transfer.amount_cents,
+        )?;
+        self.write_transaction(
+            &mut batch,
+            LedgerAccount::User(transfer.from_user_id),
+            debit,
+            -transfer.amount_cents,
+        )?;
+
+        match transfer.to {
+            LedgerAccount::User(user_id) => {
+                let mut recipient = self.get_account(&user_id)?.ok_or(StoreError::NotFound {
+                    entity: "Account",
+                    id: user_id.to_string(),
+                })?;
+                recipient.balance_cents += transfer.amount_cents;
+                recipient.update_overdraft_lock();
+                recipient.updated_at = now;
+                batch.put_cf(
+                    &cf_accounts,
+                    keys::account_key(&user_id),
+                    Self::serialize(&recipient)?,
+                );
+
+                let mut lot = CreditLot::new(
+                    user_id,
+                    credit.transaction_type.clone(),
+                    transfer.amount_cents,
+                    None,
+                );
+                lot.cap_to_balance(recipient.balance_cents);
+                self.write_credit_lot(&mut batch, &lot)?;
+            }
+            LedgerAccount::Org(org_id) => {
+                let mut org = self
+                    .get_organization(&org_id)?
+                    .ok_or(StoreError::NotFound {
+                        entity: "Organization",
+                        id: org_id.to_string(),
+                    })?;
+                org.balance_cents += transfer.amount_cents;
+                org.updated_at = now;
+
+                let cf_orgs = self.cf(cf::ORGANIZATIONS)?;
+                batch.put_cf(&cf_orgs, keys::org_key(&org_id), Self::serialize(&org)?);
+            }
+            LedgerAccount::System(_) => {
+                return Err(StoreError::NotFound {
+                    entity: "Wallet",
+                    id: transfer.to.to_string(),
+                });
+            }
+        }
+
+        self.write_transaction(&mut batch, transfer.to, credit, transfer.amount_cents)?;
+
+        let cf_transfers = self.cf(cf::CREDIT_TRANSFERS)?;
+        batch.put_cf(
+            &cf_transfers,
+            keys::credit_transfer_key(&transfer.from_user_id, &transfer.idempotency_key),
+            Self::serialize(transfer)?,
+        );
+
+        self.write_outbox(&mut batch, outbox)?;
+
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(transfer.clone())
+    }
+
+    fn redeem_promo_code(
+        &self,
+        redemption: &PromoRedemption,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let _guard = self.lock_promos()?;
+
+        let mut promo =
+            self.get_promo_code(&redemption.code)?
+                .ok_or_else(|| StoreError::NotFound {
+                    entity: "PromoCode",
+                    id: redemption.code.clone(),
+                })?;
+        let mut account = self
+            .get_account(&redemption.user_id)?
+            .ok_or(StoreError::NotFound {
+                entity: "Account",
+                id: redemption.user_id.to_string(),
+            })?;
+
+        let now = chrono::Utc::now();
+        let redeemed = self.count_promo_redemptions(&promo.code, &redemption.user_id)?;
+        promo
+            .check_redeemable(&account.current_plan(), redeemed, now)
+            .map_err(|reason| StoreError::PromoRejected {
+                code: promo.code.clone(),
+                reason,
+            })?;
+
+        promo.redemption_count += 1;
+        account.balance_cents += redemption.amount_cents;
+        account.lifetime_granted_cents += redemption.amount_cents;
+        account.update_overdraft_lock();
+        account.updated_at = now;
+
+        let cf_promos = self.cf(cf::PROMO_CODES)?;
+        let cf_redemptions = self.cf(cf::PROMO_REDEMPTIONS)?;
+        let cf_accounts = self.cf(cf::ACCOUNTS)?;
+
+        let mut batch = WriteBatch::default();
+        batch.put_cf(
+            &cf_promos,
+            keys::promo_code_key(&promo.code),
+            Self::serialize(&promo)?,
+        );
+        batch.put_cf(
+            &cf_redemptions,
+            keys::promo_redemption_key(&promo.code, &redemption.user_id, &transaction.id),
+            [],
+        );
+        batch.put_cf(
+            &cf_accounts,
+            keys::account_key(&redemption.user_id),
+            Self::serialize(&account)?,
+        );
+        self.write_transaction(
+            &mut batch,
+            LedgerAccount::User(redemption.user_id),
+            transaction,
+            redemption.amount_cents,
+        )?;
+
+        let mut lot = CreditLot::new(
+            redemption.user_id,
+            transaction.transaction_type.clone(),
+            redemption.amount_cents,
+            None,
+        );
+        lot.cap_to_balance(account.balance_cents);
+        self.write_credit_lot(&mut batch, &lot)?;
+
+        self.write_outbox(&mut batch, outbox)?;
+
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(account.balance_cents)
+    }
+
+    fn redeem_gift_card(
+        &self,
+        card_id: &GiftCardId,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let _guard = self.lock_gift_cards()?;
+
+        let mut card = self.load_issued_gift_card(card_id)?;
+        let user_id = transaction.user_id;
+        let mut account = self.get_account(&user_id)?.ok_or(StoreError::NotFound {
+            entity: "Account",
+            id: user_id.to_string(),
+        })?;
+
+        card.redeem(user_id, transaction.id);
+        account.balance_cents += card.amount_cents;
+        account.lifetime_purchased_cents += card.amount_cents;
+        account.update_overdraft_lock();
+        account.updated_at = chrono::Utc::now();
+
+        let cf_cards = self.cf(cf::GIFT_CARDS)?;
+        let cf_accounts = self.cf(cf::ACCOUNTS)?;
+
+        let mut batch = WriteBatch::default();
+        batch.put_cf(
+            &cf_cards,
+            keys::gift_card_key(card_id),
+            Self::serialize(&card)?,
+        );
+        batch.put_cf(
+            &cf_accounts,
+            keys::account_key(&user_id),
+            Self::serialize(&account)?,
+        );
+        self.write_transaction(
+            &mut batch,
+            LedgerAccount::User(user_id),
+            transaction,
+            card.amount_cents,
+        )?;
+
+        let mut lot = CreditLot::new(
+            user_id,
+            transaction.transaction_type.clone(),
+            card.amount_cents,
+            None,
+        );
+        lot.cap_to_balance(account.balance_cents);
+        self.write_credit_lot(&mut batch, &lot)?;
+
+        self.write_outbox(&mut batch, outbox)?;
+
+        self.db
+            .write(batch)
+            .map_err(|e| StoreError::Database(e.to_string()))?;
+
+        Ok(account.balance_cents)
+    }
+}
+
+#[async_trait::async_trait]
+impl Store for RocksStore {
+    // =========================================================================
+    // Account Operations
+    // =========================================================================
+
+    async fn put_account(&self, account: &Account) -> Result<()> {
+        let account = account.clone();
+        self.blocking(move |db| db.put_account(&account)).await
+    }
+
+    async fn put_account_if_version(
+        &self,
+        account: &Account,
+        expected_version: i64,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let account = account.clone();
+        let outbox = outbox.to_vec();
+        self.blocking(move |db| db.put_account_if_version(&account, expected_version, &outbox))
+            .await
+    }
+
+    async fn create_account(&self, account: &Account) -> Result<()> {
+        let account = account.clone();
+        self.blocking(move |db| db.create_account(&account)).await
+    }
+
+    async fn get_account(&self, user_id: &UserId) -> Result<Option<Account>> {
+        let user_id = *user_id;
+        self.blocking(move |db| db.get_account(&user_id)).await
+    }
+
+    async fn find_account_by_stripe_customer(&self, customer_id: &str) -> Result<Option<Account>> {
+        let customer_id = customer_id.to_owned();
+        self.blocking(move |db| db.find_account_by_stripe_customer(&customer_id))
+            .await
+    }
+
+    async fn find_account_by_lago_customer(&self, customer_id: &str) -> Result<Option<Account>> {
+        let customer_id = customer_id.to_owned();
+        self.blocking(move |db| db.find_account_by_lago_customer(&customer_id))
+            .await
+    }
+
+    async fn delete_account(&self, user_id: &UserId) -> Result<()> {
+        let user_id = *user_id;
+        self.blocking(move |db| db.delete_account(&user_id)).await
+    }
+
+    #[allow(deprecated)]
+    async fn update_balance(&self, user_id: &UserId, delta_cents: i64) -> Result<i64> {
+        let user_id = *user_id;
+        self.blocking(move |db| db.update_balance(&user_id, delta_cents))
+            .await
+    }
+
+    // =========================================================================
+    // Organization Operations
+    // =========================================================================
+
+    async fn put_organization(&self, org: &Organization) -> Result<()> {
+        let org = org.clone();
+        self.blocking(move |db| db.put_organization(&org)).await
+    }
+
+    async fn get_organization(&self, org_id: &OrgId) -> Result<Option<Organization>> {
+        let org_id = *org_id;
+        self.blocking(move |db| db.get_organization(&org_id)).await
+    }
+
+    async fn put_org_membership(&self, membership: &OrgMembership) -> Result<()> {
+        let membership = membership.clone();
+        self.blocking(move |db| db.put_org_membership(&membership))
+            .await
+    }
+
+    async fn get_org_membership(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+    ) -> Result<Option<OrgMembership>> {
+        let org_id = *org_id;
+        let user_id = *user_id;
+        self.blocking(move |db| db.get_org_membership(&org_id, &user_id))
+            .await
+    }
+
+    async fn list_org_members(&self, org_id: &OrgId) -> Result<Vec<OrgMembership>> {
+        let org_id = *org_id;
+        self.blocking(move |db| db.list_org_members(&org_id)).await
+    }
+
+    async fn delete_org_membership(&self, org_id: &OrgId, user_id: &UserId) -> Result<()> {
+        let org_id = *org_id;
+        let user_id = *user_id;
+        self.blocking(move |db| db.delete_org_membership(&org_id, &user_id))
+            .await
+    }
+
+    async fn org_member_spend_since(
+        &self,
+        org_id: &OrgId,
+        user_id: &UserId,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<i64> {
+        let org_id = *org_id;
+        let user_id = *user_id;
+        self.blocking(move |db| db.org_member_spend_since(&org_id, &user_id, since))
+            .await
+    }
+
+    // =========================================================================
+    // Transaction Operations
+    // =========================================================================
+
+    async fn put_transaction(&self, transaction: &CreditTransaction) -> Result<()> {
+        let transaction = transaction.clone();
+        self.blocking(move |db| db.put_transaction(&transaction))
+            .await
+    }
+
+    async fn get_transaction(
+        &self,
+        transaction_id: &TransactionId,
+    ) -> Result<Option<CreditTransaction>> {
+        let transaction_id = *transaction_id;
+        self.blocking(move |db| db.get_transaction(&transaction_id))
+            .await
+    }
+
+    async fn list_transactions_by_user(
+        &self,
+        user_id: &UserId,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<CreditTransaction>> {
+        let user_id = *user_id;
+        self.blocking(move |db| db.list_transactions_by_user(&user_id, limit, offset))
+            .await
+    }
+
+    async fn query_transactions(
+        &self,
+        user_id: &UserId,
+        query: &TransactionQuery,
+    ) -> Result<Vec<CreditTransaction>> {
+        let user_id = *user_id;
+        let query = query.clone();
+        self.blocking(move |db| db.query_transactions(&user_id, &query))
+            .await
+    }
+
+    async fn sum_monthly_allowance_since(
+        &self,
+        user_id: &UserId,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<i64> {
+        let user_id = *user_id;
+        self.blocking(move |db| db.sum_monthly_allowance_since(&user_id, since))
+            .await
+    }
+
+    async fn has_referral_bonus(&self, user_id: &UserId) -> Result<bool> {
+        let user_id = *user_id;
+        self.blocking(move |db| db.has_referral_bonus(&user_id))
+            .await
+    }
+
+    // =========================================================================
+    // Usage Event Operations (for idempotency)
+    // =========================================================================
+
+    async fn has_usage_event(&self, event_id: &str) -> Result<bool> {
+        let event_id = event_id.to_owned();
+        self.blocking(move |db| db.has_usage_event(&event_id)).await
+    }
+
+    async fn put_usage_event(&self, event: &UsageEvent) -> Result<()> {
+        let event = event.clone();
+        self.blocking(move |db| db.put_usage_event(&event)).await
+    }
+
+    async fn get_usage_event(&self, event_id: &str) -> Result<Option<UsageEvent>> {
+        let event_id = event_id.to_owned();
+        self.blocking(move |db| db.get_usage_event(&event_id)).await
+    }
+
+    async fn list_usage_reversals(&self, event_id: &str) -> Result<Vec<UsageReversal>> {
+        let event_id = event_id.to_owned();
+        self.blocking(move |db| db.list_usage_reversals(&event_id))
+            .await
+    }
+
+    async fn aggregate_usage(&self, query: &UsageSummaryQuery) -> Result<Vec<UsageSummaryRow>> {
+        let query = query.clone();
+        self.blocking(move |db| db.aggregate_usage(&query)).await
+    }
+
+    // =========================================================================
+    // Webhook Idempotency
+    // =========================================================================
+
+    async fn has_webhook_event(&self, event_id: &str) -> Result<bool> {
+        let event_id = event_id.to_owned();
+        self.blocking(move |db| db.has_webhook_event(&event_id))
+            .await
+    }
+
+    async fn record_webhook_event(&self, event_id: &str, source: &str) -> Result<()> {
+        let event_id = event_id.to_owned();
+        let source = source.to_owned();
+        self.blocking(move |db| db.record_webhook_event(&event_id, &source))
+            .await
+    }
+
+    // =========================================================================
+    // Reservation Operations
+    // =========================================================================
+
+    async fn create_reservation(&self, reservation: &Reservation) -> Result<i64> {
+        let reservation = reservation.clone();
+        self.blocking(move |db| db.create_reservation(&reservation))
+            .await
+    }
+
+    async fn get_reservation(&self, reservation_id: &ReservationId) -> Result<Option<Reservation>> {
+        let reservation_id = *reservation_id;
+        self.blocking(move |db| db.get_reservation(&reservation_id))
+            .await
+    }
+
+    async fn reserved_cents(&self, user_id: &UserId) -> Result<i64> {
+        let user_id = *user_id;
+        self.blocking(move |db| db.reserved_cents(&user_id)).await
+    }
+
+    async fn settle_reservation(
+        &self,
+        reservation_id: &ReservationId,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let reservation_id = *reservation_id;
+        let event = event.clone();
+        let transaction = transaction.clone();
+        let outbox = outbox.to_vec();
+        self.blocking(move |db| {
+            db.settle_reservation(&reservation_id, &event, &transaction, &outbox)
+        })
+        .await
+    }
+
+    async fn release_reservation(&self, reservation_id: &ReservationId) -> Result<Reservation> {
+        let reservation_id = *reservation_id;
+        self.blocking(move |db| db.release_reservation(&reservation_id))
+            .await
+    }
+
+    async fn expire_reservations(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize> {
+        self.blocking(move |db| db.expire_reservations(now)).await
+    }
+
+    // =========================================================================
+    // Credit Lot Operations
+    // =========================================================================
+
+    async fn list_credit_lots(&self, user_id: &UserId) -> Result<Vec<CreditLot>> {
+        let user_id = *user_id;
+        self.blocking(move |db| db.list_credit_lots(&user_id)).await
+    }
+
+    async fn expire_credit_lots(&self, now: chrono::DateTime<chrono::Utc>) -> Result<usize> {
+        self.blocking(move |db| db.expire_credit_lots(now)).await
+    }
+
+    // =========================================================================
+    // Credit Transfer Operations
+    // =========================================================================
+
+    async fn get_credit_transfer(
+        &self,
+        from_user_id: &UserId,
+        idempotency_key: &str,
+    ) -> Result<Option<CreditTransfer>> {
+        let from_user_id = *from_user_id;
+        let idempotency_key = idempotency_key.to_owned();
+        self.blocking(move |db| db.get_credit_transfer(&from_user_id, &idempotency_key))
+            .await
+    }
+
+    // =========================================================================
+    // Promo Code Operations
+    // =========================================================================
+
+    async fn put_promo_code(&self, promo: &PromoCode) -> Result<()> {
+        let promo = promo.clone();
+        self.blocking(move |db| db.put_promo_code(&promo)).await
+    }
+
+    async fn get_promo_code(&self, code: &str) -> Result<Option<PromoCode>> {
+        let code = code.to_owned();
+        self.blocking(move |db| db.get_promo_code(&code)).await
+    }
+
+    async fn list_promo_codes(&self) -> Result<Vec<PromoCode>> {
+        self.blocking(RocksDb::list_promo_codes).await
+    }
+
+    async fn delete_promo_code(&self, code: &str) -> Result<()> {
+        let code = code.to_owned();
+        self.blocking(move |db| db.delete_promo_code(&code)).await
+    }
+
+    async fn count_promo_redemptions(&self, code: &str, user_id: &UserId) -> Result<i64> {
+        let code = code.to_owned();
+        let user_id = *user_id;
+        self.blocking(move |db| db.count_promo_redemptions(&code, &user_id))
+            .await
+    }
+
+    // =========================================================================
+    // Gift Card Operations
+    // =========================================================================
+
+    async fn create_gift_card(
+        &self,
+        card: &GiftCard,
+        outbox: &[OutboxMessage],
+    ) -> Result<GiftCard> {
+        let card = card.clone();
+        let outbox = outbox.to_vec();
+        self.blocking(move |db| db.create_gift_card(&card, &outbox))
+            .await
+    }
+
+    async fn get_gift_card(&self, card_id: &GiftCardId) -> Result<Option<GiftCard>> {
+        let card_id = *card_id;
+        self.blocking(move |db| db.get_gift_card(&card_id)).await
+    }
+
+    async fn get_gift_card_by_code(&self, code: &str) -> Result<Option<GiftCard>> {
+        let code = code.to_owned();
+        self.blocking(move |db| db.get_gift_card_by_code(&code))
+            .await
+    }
+
+    async fn list_gift_cards_by_purchaser(&self, user_id: &UserId) -> Result<Vec<GiftCard>> {
+        let user_id = *user_id;
+        self.blocking(move |db| db.list_gift_cards_by_purchaser(&user_id))
+            .await
+    }
+
+    async fn void_gift_card(&self, card_id: &GiftCardId, reason: &str) -> Result<GiftCard> {
+        let card_id = *card_id;
+        let reason = reason.to_owned();
+        self.blocking(move |db| db.void_gift_card(&card_id, &reason))
+            .await
+    }
+
+    // =========================================================================
+    // Agent Budget Operations
+    // =========================================================================
+
+    async fn put_agent_budget(&self, budget: &AgentBudget) -> Result<()> {
+        let budget = budget.clone();
+        self.blocking(move |db| db.put_agent_budget(&budget)).await
+    }
+
+    async fn list_agent_budgets(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+    ) -> Result<Vec<AgentBudget>> {
+        let user_id = *user_id;
+        let agent_id = *agent_id;
+        self.blocking(move |db| db.list_agent_budgets(&user_id, &agent_id))
+            .await
+    }
+
+    async fn delete_agent_budget(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+        period: BudgetPeriod,
+    ) -> Result<()> {
+        let user_id = *user_id;
+        let agent_id = *agent_id;
+        self.blocking(move |db| db.delete_agent_budget(&user_id, &agent_id, period))
+            .await
+    }
+
+    async fn get_agent_spend(
+        &self,
+        user_id: &UserId,
+        agent_id: &AgentId,
+    ) -> Result<Option<AgentSpend>> {
+        let user_id = *user_id;
+        let agent_id = *agent_id;
+        self.blocking(move |db| db.get_agent_spend(&user_id, &agent_id))
+            .await
+    }
+
+    async fn list_agent_spend(&self, user_id: &UserId) -> Result<Vec<AgentSpend>> {
+        let user_id = *user_id;
+        self.blocking(move |db| db.list_agent_spend(&user_id)).await
+    }
+
+    // =========================================================================
+    // Ledger Operations
+    // =========================================================================
+
+    async fn list_ledger_entries(
+        &self,
+        account: &LedgerAccount,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<LedgerEntry>> {
+        let account = *account;
+        self.blocking(move |db| db.list_ledger_entries(&account, limit, offset))
+            .await
+    }
+
+    async fn ledger_balance(&self, account: &LedgerAccount) -> Result<i64> {
+        let account = *account;
+        self.blocking(move |db| db.ledger_balance(&account)).await
+    }
+
+    async fn verify_ledger(&self) -> Result<LedgerReport> {
+        self.blocking(RocksDb::verify_ledger).await
+    }
+
+    // =========================================================================
+    // Outbox Operations
+    // =========================================================================
+
+    async fn put_outbox_message(&self, message: &OutboxMessage) -> Result<()> {
+        let message = message.clone();
+        self.blocking(move |db| db.put_outbox_message(&message))
+            .await
+    }
+
+    async fn get_outbox_message(&self, id: &OutboxId) -> Result<Option<OutboxMessage>> {
+        let id = *id;
+        self.blocking(move |db| db.get_outbox_message(&id)).await
+    }
+
+    async fn list_outbox_messages(
+        &self,
+        status: Option<OutboxStatus>,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>> {
+        self.blocking(move |db| db.list_outbox_messages(status, limit))
+            .await
+    }
+
+    async fn claim_outbox_messages(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+        lease: chrono::Duration,
+        limit: usize,
+    ) -> Result<Vec<OutboxMessage>> {
+        self.blocking(move |db| db.claim_outbox_messages(now, lease, limit))
+            .await
+    }
+
+    async fn delete_outbox_message(&self, id: &OutboxId) -> Result<()> {
+        let id = *id;
+        self.blocking(move |db| db.delete_outbox_message(&id)).await
+    }
+
+    // =========================================================================
+    // Pricing Catalog
+    // =========================================================================
+
+    async fn put_pricing_catalog(&self, catalog: &PricingCatalog) -> Result<()> {
+        let catalog = catalog.clone();
+        self.blocking(move |db| db.put_pricing_catalog(&catalog))
+            .await
+    }
+
+    async fn get_latest_pricing_catalog(&self) -> Result<Option<PricingCatalog>> {
+        self.blocking(RocksDb::get_latest_pricing_catalog).await
+    }
+
+    // =========================================================================
+    // Storage Meters
+    // =========================================================================
+
+    async fn get_storage_meter(
+        &self,
+        user_id: &UserId,
+        resource_id: &str,
+    ) -> Result<Option<StorageMeter>> {
+        let user_id = *user_id;
+        let resource_id = resource_id.to_string();
+        self.blocking(move |db| db.get_storage_meter(&user_id, &resource_id))
+            .await
+    }
+
+    async fn put_storage_meter_if_version(
+        &self,
+        meter: &StorageMeter,
+        expected_version: i64,
+    ) -> Result<i64> {
+        let meter = meter.clone();
+        self.blocking(move |db| db.put_storage_meter_if_version(&meter, expected_version))
+            .await
+    }
+
+    // =========================================================================
+    // Export
+    // =========================================================================
+
+    async fn list_accounts(&self, after: Option<&UserId>, limit: usize) -> Result<Vec<Account>> {
+        let after = after.copied();
+        self.blocking(move |db| db.list_accounts(after.as_ref(), limit))
+            .await
+    }
+
+    async fn list_transactions(
+        &self,
+        after: Option<&TransactionId>,
+        limit: usize,
+    ) -> Result<Vec<CreditTransaction>> {
+        let after = after.copied();
+        self.blocking(move |db| db.list_transactions(after.as_ref(), limit))
+            .await
+    }
+
+    async fn list_usage_events(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<UsageEvent>> {
+        let after = after.map(str::to_string);
+        self.blocking(move |db| db.list_usage_events(after.as_deref(), limit))
+            .await
+    }
+
+    async fn list_webhook_events(
+        &self,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ProcessedWebhook>> {
+        let after = after.map(str::to_string);
+        self.blocking(move |db| db.list_webhook_events(after.as_deref(), limit))
+            .await
+    }
+
+    async fn count_unlisted(&self) -> Result<Vec<(&'static str, u64)>> {
+        self.blocking(RocksDb::count_unlisted).await
+    }
+
+    // =========================================================================
+    // Compound Operations
+    // =========================================================================
+
+    async fn process_usage(
+        &self,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let event = event.clone();
+        let transaction = transaction.clone();
+        let outbox = outbox.to_vec();
+        self.blocking(move |db| db.process_usage(&event, &transaction, &outbox))
+            .await
+    }
+
+    async fn process_org_usage(
+        &self,
+        org_id: &OrgId,
+        event: &UsageEvent,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let org_id = *org_id;
+        let event = event.clone();
+        let transaction = transaction.clone();
+        let outbox = outbox.to_vec();
+        self.blocking(move |db| db.process_org_usage(&org_id, &event, &transaction, &outbox))
+            .await
+    }
+
+    async fn add_org_credits(
+        &self,
+        org_id: &OrgId,
+        amount_cents: i64,
+        transaction: &CreditTransaction,
+    ) -> Result<i64> {
+        let org_id = *org_id;
+        let transaction = transaction.clone();
+        self.blocking(move |db| db.add_org_credits(&org_id, amount_cents, &transaction))
+            .await
+    }
+
+    async fn add_credits(
+        &self,
+        user_id: &UserId,
+        amount_cents: i64,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let user_id = *user_id;
+        let transaction = transaction.clone();
+        let outbox = outbox.to_vec();
+        self.blocking(move |db| db.add_credits(&user_id, amount_cents, &transaction, &outbox))
+            .await
+    }
+
+    async fn grant_once(
+        &self,
+        user_id: &UserId,
+        grant_key: &str,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<Option<i64>> {
+        let user_id = *user_id;
+        let grant_key = grant_key.to_string();
+        let transaction = transaction.clone();
+        let outbox = outbox.to_vec();
+        self.blocking(move |db| db.grant_once(&user_id, &grant_key, &transaction, &outbox))
+            .await
+    }
+
+    async fn reverse_usage(
+        &self,
+        reversal: &UsageReversal,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<UsageReversal> {
+        let reversal = reversal.clone();
+        let transaction = transaction.clone();
+        let outbox = outbox.to_vec();
+        self.blocking(move |db| db.reverse_usage(&reversal, &transaction, &outbox))
+            .await
+    }
+
+    async fn transfer_credits(
+        &self,
+        transfer: &CreditTransfer,
+        debit: &CreditTransaction,
+        credit: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<CreditTransfer> {
+        let transfer = transfer.clone();
+        let debit = debit.clone();
+        let credit = credit.clone();
+        let outbox = outbox.to_vec();
+        self.blocking(move |db| db.transfer_credits(&transfer, &debit, &credit, &outbox))
+            .await
+    }
+
+    async fn redeem_promo_code(
+        &self,
+        redemption: &PromoRedemption,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let redemption = redemption.clone();
+        let transaction = transaction.clone();
+        let outbox = outbox.to_vec();
+        self.blocking(move |db| db.redeem_promo_code(&redemption, &transaction, &outbox))
+            .await
+    }
+
+    async fn redeem_gift_card(
+        &self,
+        card_id: &GiftCardId,
+        transaction: &CreditTransaction,
+        outbox: &[OutboxMessage],
+    ) -> Result<i64> {
+        let card_id = *card_id;
+        let transaction = transaction.clone();
+        let outbox = outbox.to_vec();
+        self.blocking(move |db| db.redeem_gift_card(&card_id, &transaction, &outbox))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+    use z_billing_core::{
+        CreditTransaction, TransactionType, UsageEvent, UsageMetric, UsageSource,
+    };
+
+    fn api_call_event(event_id: &str, user_id: UserId, cost_cents: i64) -> UsageEvent {
+        UsageEvent {
+            event_id: event_id.to_string(),
+            user_id,
+            agent_id: None,
+            source: UsageSource::AuraRuntime,
+            metric: UsageMetric::ApiCalls {
+                endpoint: "test".to_string(),
+            },
+            quantity: 1.0,
+            cost_cents,
+            timestamp: chrono::Utc::now(),
+            metadata: serde_json::Value::Null,
+            transaction_id: None,
+        }
+    }
+
+    fn create_test_store() -> (RocksDb, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let store = RocksDb::open(dir.path()).unwrap();
+        (store, dir)
+    }
+
+    #[test]
+    fn account_crud() {
+        let (store, _dir) = create_test_store();
+        let user_id = UserId::generate();
+        let mut account = Account::new(user_id);
+        account.balance_cents = 5000;
+
+        // Create
+        store.create_account(&account).unwrap();
+
+        // Read
+        let retrieved = store.get_account(&user_id).unwrap().unwrap();
+        assert_eq!(retrieved.balance_cents, 5000);
+
+        // Update balance
+        let new_balance = store.update_balance(&user_id, -100).unwrap();
+        assert_eq!(new_balance, 4900);
+
+        let updated = store.get_account(&user_id).unwrap().unwrap();
+        assert_eq!(updated.balance_cents, 4900);
+        assert_eq!(updated.lifetime_used_cents, 100);
+
+        // Delete
+        store.delete_account(&user_id).unwrap();
+        assert!(store.get_account(&user_id).unwrap().is_none());
+    }
+
+    #[test]
+    fn customer_indexes_follow_account() {
+        let (store, _dir) = create_test_store();
+        let user_id = UserId::generate();
+        let mut account = Account::new(user_id);
+        account.stripe_customer_id = Some("cus_old".into());
+        account.lago_customer_id = Some("lago_1".into());
+        store.put_account(&account).unwrap();
+
+        let found = store.find_account_by_stripe_customer("cus_old").unwrap();
+        assert_eq!(found.unwrap().user_id, user_id);
+
+        // Changing the customer moves the entry
+        account.stripe_customer_id = Some("cus_new".into());
+        store.put_account(&account).unwrap();
+        assert!(store
+            .find_account_by_stripe_customer("cus_old")
+            .unwrap()
+            .is_none());
+        assert!(store
+            .find_account_by_stripe_customer("cus_new")
+            .unwrap()
+            .is_some());
+
+        // Clearing it drops the entry
+        account.stripe_customer_id = None;
+        store.put_account(&account).unwrap();
+        assert!(store
+            .find_account_by_stripe_customer("cus_new")
+            .unwrap()
+            .is_none());
+
+        // Deleting the account drops the rest
+        let found = store.find_account_by_lago_customer("lago_1").unwrap();
+        assert_eq!(found.unwrap().user_id, user_id);
+        store.delete_account(&user_id).unwrap();
+        assert!(store
+            .find_account_by_lago_customer("lago_1")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn customer_index_keeps_entry_claimed_by_another_account() {
+        let (store, _dir) = create_test_store();
+        let mut first = Account::new(UserId::generate());
+        first.stripe_customer_id = Some("cus_shared".into());
+        store.put_account(&first).unwrap();
+
+        let mut second = Account::new(UserId::generate());
+        second.stripe_customer_id = Some("cus_shared".into());
+        store.put_account(&second).unwrap();
+
+        first.stripe_customer_id = None;
+        store.put_account(&first).unwrap();
+
+        let found = store.find_account_by_stripe_customer("cus_shared").unwrap();
+        assert_eq!(found.unwrap().user_id, second.user_id);
+    }
+
+    #[test]
+    fn open_rebuilds_missing_customer_indexes() {
+        let dir = TempDir::new().unwrap();
+        let user_id = UserId::generate();
+        {
+            let store = RocksDb::open(dir.path()).unwrap();
+            let mut account = Account::new(user_id);
+            account.stripe_customer_id = Some("cus_123".into());
+            account.lago_customer_id = Some("lago_123".into());
+            store.put_account(&account).unwrap();
+
+            // Simulate a database from before the indexes existed
+            store.db.drop_cf(cf::ACCOUNTS_BY_STRIPE_CUSTOMER).unwrap();
+            store.db.drop_cf(cf::ACCOUNTS_BY_LAGO_CUSTOMER).unwrap();
+            store.db.drop_cf(cf::META).unwrap();
+        }
 
-    fn create_test_store() -> (RocksStore, TempDir) {
-        let dir = TempDir::new().unwrap();
-        let store = RocksStore::open(dir.path()).unwrap();
-        (store, dir)
+        let store = RocksDb::open(dir.path()).unwrap();
+        let found = store.find_account_by_stripe_customer("cus_123").unwrap();
+        assert_eq!(found.unwrap().user_id, user_id);
+        let found = store.find_account_by_lago_customer("lago_123").unwrap();
+        assert_eq!(found.unwrap().user_id, user_id);
+        assert!(store.customer_indexes_built().unwrap());
     }
 
     #[test]
-    fn account_crud() {
-        let (store, _dir) = create_test_store();
+    fn open_retries_an_unfinished_customer_index_rebuild() {
+        let dir = TempDir::new().unwrap();
         let user_id = UserId::generate();
-        let mut account = Account::new(user_id);
-        account.balance_cents = 5000;
-
-        // Create
-        store.put_account(&account).unwrap();
-
-        // Read
-        let retrieved = store.get_account(&user_id).unwrap().unwrap();
-        assert_eq!(retrieved.balance_cents, 5000);
-
-        // Update balance
-        let new_balance = store.update_balance(&user_id, -100).unwrap();
-        assert_eq!(new_balance, 4900);
-
-        let updated = store.get_account(&user_id).unwrap().unwrap();
-        assert_eq!(updated.balance_cents, 4900);
-        assert_eq!(updated.lifetime_used_cents, 100);
+        {
+            let store = RocksDb::open(dir.path()).unwrap();
+            let mut account = Account::new(user_id);
+            account.stripe_customer_id = Some("cus_456".into());
+            store.put_account(&account).unwrap();
+
+            // A crash after the index column families were created but
+            // before the rebuild was written leaves them empty and unmarked
+            let cf_index = store.cf(cf::ACCOUNTS_BY_STRIPE_CUSTOMER).unwrap();
+            store
+                .db
+                .delete_cf(&cf_index, keys::stripe_customer_key("cus_456"))
+                .unwrap();
+            let cf_meta = store.cf(cf::META).unwrap();
+            store
+                .db
+                .delete_cf(&cf_meta, keys::CUSTOMER_INDEXES_BUILT)
+                .unwrap();
+        }
 
-        // Delete
-        store.delete_account(&user_id).unwrap();
-        assert!(store.get_account(&user_id).unwrap().is_none());
+        let store = RocksDb::open(dir.path()).unwrap();
+        let found = store.find_account_by_stripe_customer("cus_456").unwrap();
+        assert_eq!(found.unwrap().user_id, user_id);
     }
 
     #[test]
@@ -581,7 +3853,7 @@ mod tests {
         // Create account with balance
         let mut account = Account::new(user_id);
         account.balance_cents = 1000;
-        store.put_account(&account).unwrap();
+        store.create_account(&account).unwrap();
 
         let event = UsageEvent {
             event_id: "evt_123".to_string(),
@@ -595,17 +3867,18 @@ mod tests {
             cost_cents: 10,
             timestamp: chrono::Utc::now(),
             metadata: serde_json::Value::Null,
+            transaction_id: None,
         };
 
         let tx =
             CreditTransaction::usage(user_id, 10, 990, "API call".into(), serde_json::json!({}));
 
         // First call should succeed
-        let balance = store.process_usage(&event, &tx).unwrap();
+        let balance = store.process_usage(&event, &tx, &[]).unwrap();
         assert_eq!(balance, 990);
 
         // Second call should fail with duplicate error
-        let result = store.process_usage(&event, &tx);
+        let result = store.process_usage(&event, &tx, &[]);
         assert!(matches!(result, Err(StoreError::DuplicateEvent { .. })));
     }
 
@@ -617,7 +3890,7 @@ mod tests {
         // Create account with low balance
         let mut account = Account::new(user_id);
         account.balance_cents = 5;
-        store.put_account(&account).unwrap();
+        store.create_account(&account).unwrap();
 
         let event = UsageEvent {
             event_id: "evt_456".to_string(),
@@ -631,12 +3904,13 @@ mod tests {
             cost_cents: 100, // More than balance
             timestamp: chrono::Utc::now(),
             metadata: serde_json::Value::Null,
+            transaction_id: None,
         };
 
         let tx =
             CreditTransaction::usage(user_id, 100, 0, "API call".into(), serde_json::json!({}));
 
-        let result = store.process_usage(&event, &tx);
+        let result = store.process_usage(&event, &tx, &[]);
         assert!(matches!(
             result,
             Err(StoreError::InsufficientCredits {
@@ -657,7 +3931,7 @@ mod tests {
 
         // Add credits
         let tx = CreditTransaction::purchase(user_id, 5000, 5000, "Purchase $50".into());
-        let balance = store.add_credits(&user_id, 5000, &tx).unwrap();
+        let balance = store.add_credits(&user_id, 5000, &tx, &[]).unwrap();
         assert_eq!(balance, 5000);
 
         // Verify account updated
@@ -708,15 +3982,702 @@ mod tests {
         store.put_account(&Account::new(user_with)).unwrap();
         store.put_account(&Account::new(user_without)).unwrap();
 
-        let tx = CreditTransaction::referral_bonus(
-            user_with,
-            500,
-            500,
-            "Referral bonus".into(),
-        );
+        let tx = CreditTransaction::referral_bonus(user_with, 500, 500, "Referral bonus".into());
         store.put_transaction(&tx).unwrap();
 
         assert!(store.has_referral_bonus(&user_with).unwrap());
         assert!(!store.has_referral_bonus(&user_without).unwrap());
     }
+
+    #[test]
+    fn reservation_holds_back_balance() {
+        let (store, _dir) = create_test_store();
+        let user_id = UserId::generate();
+        let mut account = Account::new(user_id);
+        account.balance_cents = 100;
+        store.create_account(&account).unwrap();
+
+        let hold = Reservation::new(
+            user_id,
+            80,
+            chrono::Duration::seconds(60),
+            serde_json::Value::Null,
+        );
+        assert_eq!(store.create_reservation(&hold).unwrap(), 20);
+        assert_eq!(store.reserved_cents(&user_id).unwrap(), 80);
+
+        // A second hold cannot exceed what is left
+        let second = Reservation::new(
+            user_id,
+            30,
+            chrono::Duration::seconds(60),
+            serde_json::Value::Null,
+        );
+        assert!(matches!(
+            store.create_reservation(&second),
+            Err(StoreError::InsufficientCredits {
+                balance: 20,
+                required: 30
+            })
+        ));
+
+        // Plain usage is also limited to the unreserved balance
+        let event = api_call_event("evt_hold", user_id, 30);
+        let tx =
+            CreditTransaction::usage(user_id, 30, 70, "API call".into(), serde_json::json!({}));
+        assert!(matches!(
+            store.process_usage(&event, &tx, &[]),
+            Err(StoreError::InsufficientCredits { .. })
+        ));
+    }
+
+    #[test]
+    fn credit_limit_allows_overdraft_until_back_above_zero() {
+        let (store, _dir) = create_test_store();
+        let user_id = UserId::generate();
+        let mut account = Account::new(user_id);
+        account.credit_limit_cents = 500;
+        store.put_account(&account).unwrap();
+
+        let usage = |event_id: &str, cost_cents: i64| {
+            let tx = CreditTransaction::usage(
+                user_id,
+                cost_cents,
+                0,
+                "API call".into(),
+                serde_json::json!({}),
+            );
+            store.process_usage(&api_call_event(event_id, user_id, cost_cents), &tx, &[])
+        };
+
+        assert_eq!(usage("evt_od_1", 300).unwrap(), -300);
+        assert!(matches!(
+            usage("evt_od_2", 300),
+            Err(StoreError::InsufficientCredits {
+                balance: 200,
+                required: 300
+            })
+        ));
+        assert_eq!(usage("evt_od_3", 200).unwrap(), -500);
+        let account = store.get_account(&user_id).unwrap().unwrap();
+        assert!(account.overdraft_locked);
+
+        // A partial repayment does not reopen the credit line
+        let tx = CreditTransaction::purchase(user_id, 400, -100, "Purchase".into());
+        assert_eq!(store.add_credits(&user_id, 400, &tx, &[]).unwrap(), -100);
+        assert!(matches!(
+            usage("evt_od_4", 10),
+            Err(StoreError::InsufficientCredits { .. })
+        ));
+
+        // Back above zero unlocks it; repaid credits are not left in the lot
+        let tx = CreditTransaction::purchase(user_id, 150, 50, "Purchase".into());
+        assert_eq!(store.add_credits(&user_id, 150, &tx, &[]).unwrap(), 50);
+        let account = store.get_account(&user_id).unwrap().unwrap();
+        assert!(!account.overdraft_locked);
+        let lots: i64 = store
+            .list_credit_lots(&user_id)
+            .unwrap()
+            .iter()
+            .map(|lot| lot.remaining_cents)
+            .sum();
+        assert_eq!(lots, 50);
+        assert_eq!(usage("evt_od_5", 400).unwrap(), -350);
+        assert!(store.verify_ledger().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn settle_reservation_debits_actual_cost() {
+        let (store, _dir) = create_test_store();
+        let user_id = UserId::generate();
+        let mut account = Account::new(user_id);
+        account.balance_cents = 100;
+        store.create_account(&account).unwrap();
+
+        let hold = Reservation::new(
+            user_id,
+            50,
+            chrono::Duration::seconds(60),
+            serde_json::Value::Null,
+        );
+        store.create_reservation(&hold).unwrap();
+
+        let event = api_call_event("evt_settle", user_id, 70);
+        let tx = CreditTransaction::usage(user_id, 70, 30, "Stream".into(), serde_json::json!({}));
+        let balance = store
+            .settle_reservation(&hold.id, &event, &tx, &[])
+            .unwrap();
+        assert_eq!(balance, 30);
+        assert_eq!(store.reserved_cents(&user_id).unwrap(), 0);
+
+        let settled = store.get_reservation(&hold.id).unwrap().unwrap();
+        assert_eq!(settled.status, ReservationStatus::Settled);
+        assert_eq!(settled.transaction_id, Some(tx.id));
+
+        // Settling twice is rejected
+        let again = api_call_event("evt_settle_again", user_id, 1);
+        assert!(matches!(
+            store.settle_reservation(&hold.id, &again, &tx, &[]),
+            Err(StoreError::InvalidState { .. })
+        ));
+    }
+
+    #[test]
+    fn release_and_expire_reservations() {
+        let (store, _dir) = create_test_store();
+        let user_id = UserId::generate();
+        let mut account = Account::new(user_id);
+        account.balance_cents = 100;
+        store.create_account(&account).unwrap();
+
+        let released = Reservation::new(
+            user_id,
+            40,
+            chrono::Duration::seconds(60),
+            serde_json::Value::Null,
+        );
+        store.create_reservation(&released).unwrap();
+        let closed = store.release_reservation(&released.id).unwrap();
+        assert_eq!(closed.status, ReservationStatus::Released);
+        assert_eq!(store.reserved_cents(&user_id).unwrap(), 0);
+
+        let lapsed = Reservation::new(
+            user_id,
+            40,
+            chrono::Duration::seconds(60),
+            serde_json::Value::Null,
+        );
+        store.create_reservation(&lapsed).unwrap();
+        assert_eq!(store.reserved_cents(&user_id).unwrap(), 40);
+
+        let later = lapsed.expires_at + chrono::Duration::seconds(1);
+        assert_eq!(store.expire_reservations(later).unwrap(), 1);
+        let expired = store.get_reservation(&lapsed.id).unwrap().unwrap();
+        assert_eq!(expired.status, ReservationStatus::Expired);
+        assert_eq!(store.reserved_cents(&user_id).unwrap(), 0);
+        assert_eq!(
+            store.get_account(&user_id).unwrap().unwrap().balance_cents,
+            100
+        );
+    }
+
+    #[test]
+    fn usage_spends_daily_grant_before_purchased_credits() {
+        let (store, _dir) = create_test_store();
+        let user_id = UserId::generate();
+        store.put_account(&Account::new(user_id)).unwrap();
+
+        let purchase = CreditTransaction::purchase(user_id, 1000, 1000, "Purchase".into());
+        store.add_credits(&user_id, 1000, &purchase, &[]).unwrap();
+        let daily = CreditTransaction::daily_grant(user_id, 50, 1050);
+        store.add_credits(&user_id, 50, &daily, &[]).unwrap();
+
+        let lots = store.list_credit_lots(&user_id).unwrap();
+        assert_eq!(lots.len(), 2);
+        assert_eq!(lots[0].source, z_billing_core::TransactionType::DailyGrant);
+        assert!(lots[0].expires_at.is_some());
+
+        let event = api_call_event("evt-lots", user_id, 80);
+        let tx =
+            CreditTransaction::usage(user_id, 80, 970, "usage".into(), serde_json::Value::Null);
+        assert_eq!(store.process_usage(&event, &tx, &[]).unwrap(), 970);
+
+        // The daily lot is spent in full and dropped; the rest comes from the purchase.
+        let lots = store.list_credit_lots(&user_id).unwrap();
+        assert_eq!(lots.len(), 1);
+        assert_eq!(lots[0].source, z_billing_core::TransactionType::Purchase);
+        assert_eq!(lots[0].remaining_cents, 970);
+    }
+
+    #[test]
+    fn expire_credit_lots_records_expiry_transaction() {
+        let (store, _dir) = create_test_store();
+        let user_id = UserId::generate();
+        store.put_account(&Account::new(user_id)).unwrap();
+
+        let purchase = CreditTransaction::purchase(user_id, 1000, 1000, "Purchase".into());
+        store.add_credits(&user_id, 1000, &purchase, &[]).unwrap();
+        let daily = CreditTransaction::daily_grant(user_id, 50, 1050);
+        store.add_credits(&user_id, 50, &daily, &[]).unwrap();
+
+        let event = api_call_event("evt-expiry", user_id, 20);
+        let tx =
+            CreditTransaction::usage(user_id, 20, 1030, "usage".into(), serde_json::Value::Null);
+        store.process_usage(&event, &tx, &[]).unwrap();
+
+        // Nothing is due yet.
+        assert_eq!(store.expire_credit_lots(chrono::Utc::now()).unwrap(), 0);
+
+        let tomorrow = chrono::Utc::now() + chrono::Duration::days(1);
+        assert_eq!(store.expire_credit_lots(tomorrow).unwrap(), 1);
+        assert_eq!(store.expire_credit_lots(tomorrow).unwrap(), 0);
+
+        let account = store.get_account(&user_id).unwrap().unwrap();
+        assert_eq!(account.balance_cents, 1000);
+
+        let transactions = store.list_transactions_by_user(&user_id, 10, 0).unwrap();
+        let expiry = transactions
+            .iter()
+            .find(|tx| tx.transaction_type == z_billing_core::TransactionType::Expiry)
+            .unwrap();
+        assert_eq!(expiry.amount_cents, -30);
+        assert_eq!(expiry.balance_after_cents, 1000);
+
+        let lots = store.list_credit_lots(&user_id).unwrap();
+        assert_eq!(lots.len(), 1);
+        assert_eq!(lots[0].remaining_cents, 1000);
+    }
+
+    fn org_with_member(store: &RocksDb, balance_cents: i64) -> (OrgId, UserId) {
+        let mut org = Organization::new("Acme".into());
+        org.balance_cents = balance_cents;
+        store.put_organization(&org).unwrap();
+
+        let user_id = UserId::generate();
+        store.put_account(&Account::new(user_id)).unwrap();
+        store
+            .put_org_membership(&OrgMembership::new(
+                org.id,
+                user_id,
+                z_billing_core::OrgRole::Member,
+            ))
+            .unwrap();
+
+        (org.id, user_id)
+    }
+
+    #[test]
+    fn org_usage_debits_pool_and_records_member() {
+        let (store, _dir) = create_test_store();
+        let (org_id, user_id) = org_with_member(&store, 1000);
+
+        let event = api_call_event("evt-org", user_id, 300);
+        let tx =
+            CreditTransaction::usage(user_id, 300, 700, "usage".into(), serde_json::Value::Null)
+                .with_org(org_id);
+        assert_eq!(
+            store.process_org_usage(&org_id, &event, &tx, &[]).unwrap(),
+            700
+        );
+
+        // The pool pays; the member's own balance is untouched.
+        let org = store.get_organization(&org_id).unwrap().unwrap();
+        assert_eq!(org.balance_cents, 700);
+        assert_eq!(org.lifetime_used_cents, 300);
+        let account = store.get_account(&user_id).unwrap().unwrap();
+        assert_eq!(account.balance_cents, 0);
+
+        let transactions = store.list_transactions_by_user(&user_id, 10, 0).unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].org_id, Some(org_id));
+
+        // Non-members cannot spend from the pool.
+        let outsider = UserId::generate();
+        let event = api_call_event("evt-org-outsider", outsider, 10);
+        let tx =
+            CreditTransaction::usage(outsider, 10, 690, "usage".into(), serde_json::Value::Null)
+                .with_org(org_id);
+        assert!(matches!(
+            store.process_org_usage(&org_id, &event, &tx, &[]),
+            Err(StoreError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn org_member_spend_cap_enforced() {
+        let (store, _dir) = create_test_store();
+        let (org_id, user_id) = org_with_member(&store, 1000);
+
+        let mut membership = store
+            .get_org_membership(&org_id, &user_id)
+            .unwrap()
+            .unwrap();
+        membership.monthly_spend_cap_cents = Some(250);
+        store.put_org_membership(&membership).unwrap();
+
+        let event = api_call_event("evt-cap-1", user_id, 200);
+        let tx =
+            CreditTransaction::usage(user_id, 200, 800, "usage".into(), serde_json::Value::Null)
+                .with_org(org_id);
+        store.process_org_usage(&org_id, &event, &tx, &[]).unwrap();
+        assert_eq!(
+            store
+                .org_member_spend_since(
+                    &org_id,
+                    &user_id,
+                    chrono::Utc::now() - chrono::Duration::days(1)
+                )
+                .unwrap(),
+            200
+        );
+
+        let event = api_call_event("evt-cap-2", user_id, 100);
+        let tx =
+            CreditTransaction::usage(user_id, 100, 700, "usage".into(), serde_json::Value::Null)
+                .with_org(org_id);
+        let result = store.process_org_usage(&org_id, &event, &tx, &[]);
+        assert!(matches!(
+            result,
+            Err(StoreError::SpendCapExceeded {
+                cap: 250,
+                spent: 200,
+                required: 100
+            })
+        ));
+    }
+
+    #[test]
+    fn agent_budget_blocks_usage_and_tracks_spend() {
+        let (store, _dir) = create_test_store();
+        let user_id = UserId::generate();
+        let agent_id = AgentId::generate();
+
+        let mut account = Account::new(user_id);
+        account.balance_cents = 1000;
+        store.create_account(&account).unwrap();
+        store
+            .put_agent_budget(&AgentBudget::new(
+                user_id,
+                agent_id,
+                BudgetPeriod::Daily,
+                150,
+            ))
+            .unwrap();
+
+        let mut event = api_call_event("evt-agent-1", user_id, 100);
+        event.agent_id = Some(agent_id);
+        let tx =
+            CreditTransaction::usage(user_id, 100, 900, "usage".into(), serde_json::Value::Null);
+        store.process_usage(&event, &tx, &[]).unwrap();
+
+        let mut event = api_call_event("evt-agent-2", user_id, 100);
+        event.agent_id = Some(agent_id);
+        let tx =
+            CreditTransaction::usage(user_id, 100, 800, "usage".into(), serde_json::Value::Null);
+        let result = store.process_usage(&event, &tx, &[]);
+        assert!(matches!(
+            result,
+            Err(StoreError::BudgetExceeded {
+                period: BudgetPeriod::Daily,
+                limit: 150,
+                spent: 100,
+                required: 100,
+                ..
+            })
+        ));
+
+        // Other agents and agent-less usage are unaffected
+        let event = api_call_event("evt-agent-3", user_id, 100);
+        let tx =
+            CreditTransaction::usage(user_id, 100, 800, "usage".into(), serde_json::Value::Null);
+        assert_eq!(store.process_usage(&event, &tx, &[]).unwrap(), 800);
+
+        let totals = store.get_agent_spend(&user_id, &agent_id).unwrap().unwrap();
+        assert_eq!(totals.day_cents, 100);
+        assert_eq!(totals.lifetime_cents, 100);
+        assert_eq!(store.list_agent_spend(&user_id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn reverse_usage_is_idempotent_and_capped() {
+        let (store, _dir) = create_test_store();
+        let user_id = UserId::generate();
+
+        let mut account = Account::new(user_id);
+        account.balance_cents = 1000;
+        store.create_account(&account).unwrap();
+
+        let usage =
+            CreditTransaction::usage(user_id, 300, 700, "usage".into(), serde_json::Value::Null);
+        let event = api_call_event("evt-rev-1", user_id, 300).with_transaction(usage.id);
+        store.process_usage(&event, &usage, &[]).unwrap();
+
+        let tx = CreditTransaction::reversal(&usage, "evt-rev-1", 100, 800, "refund".into());
+        let reversal = UsageReversal::new("evt-rev-1".into(), "part-1".into(), 100, tx.id);
+        store.reverse_usage(&reversal, &tx, &[]).unwrap();
+
+        // Retrying the same reversal credits nothing
+        let retry = CreditTransaction::reversal(&usage, "evt-rev-1", 100, 900, "refund".into());
+        let replayed = store
+            .reverse_usage(
+                &UsageReversal::new("evt-rev-1".into(), "part-1".into(), 100, retry.id),
+                &retry,
+                &[],
+            )
+            .unwrap();
+        assert_eq!(replayed.transaction_id, tx.id);
+        assert_eq!(
+            store.get_account(&user_id).unwrap().unwrap().balance_cents,
+            800
+        );
+
+        let tx = CreditTransaction::reversal(&usage, "evt-rev-1", 250, 1050, "refund".into());
+        let result = store.reverse_usage(
+            &UsageReversal::new("evt-rev-1".into(), "part-2".into(), 250, tx.id),
+            &tx,
+            &[],
+        );
+        assert!(matches!(
+            result,
+            Err(StoreError::ReversalExceedsCharge {
+                charged: 300,
+                reversed: 100,
+                requested: 250,
+            })
+        ));
+
+        let account = store.get_account(&user_id).unwrap().unwrap();
+        assert_eq!(account.balance_cents, 800);
+        assert_eq!(account.lifetime_used_cents, 200);
+        assert_eq!(store.list_usage_reversals("evt-rev-1").unwrap().len(), 1);
+        assert_eq!(
+            store
+                .get_usage_event("evt-rev-1")
+                .unwrap()
+                .unwrap()
+                .transaction_id,
+            Some(usage.id)
+        );
+    }
+
+    #[test]
+    fn transfer_moves_only_purchased_credits() {
+        let (store, _dir) = create_test_store();
+        let sender_id = UserId::generate();
+        let recipient_id = UserId::generate();
+        let to = LedgerAccount::User(recipient_id);
+        store.put_account(&Account::new(sender_id)).unwrap();
+        store.put_account(&Account::new(recipient_id)).unwrap();
+
+        let bonus = CreditTransaction::bonus(sender_id, 300, 300, "Bonus".into());
+        store.add_credits(&sender_id, 300, &bonus, &[]).unwrap();
+        let purchase = CreditTransaction::purchase(sender_id, 1000, 1300, "Purchase".into());
+        store.add_credits(&sender_id, 1000, &purchase, &[]).unwrap();
+
+        let transfer_of = |amount_cents: i64, key: &str| {
+            let debit = CreditTransaction::transfer_out(sender_id, &to, amount_cents, 0, key);
+            let credit = CreditTransaction::transfer_in(sender_id, &to, amount_cents, 0, key);
+            let transfer =
+                CreditTransfer::new(sender_id, key.into(), to, amount_cents, debit.id, credit.id);
+            store.transfer_credits(&transfer, &debit, &credit, &[])
+        };
+
+        // The bonus can't be given away
+        assert!(matches!(
+            transfer_of(1200, "t-1"),
+            Err(StoreError::TransferExceedsPurchased {
+                transferable: 1000,
+                requested: 1200,
+            })
+        ));
+
+        let transfer = transfer_of(600, "t-2").unwrap();
+        let replayed = transfer_of(600, "t-2").unwrap();
+        assert_eq!(replayed.out_transaction_id, transfer.out_transaction_id);
+
+        let sender = store.get_account(&sender_id).unwrap().unwrap();
+        assert_eq!(sender.balance_cents, 700);
+        assert_eq!(
+            lot::transferable_cents(&store.list_credit_lots(&sender_id).unwrap()),
+            400
+        );
+
+        let recipient_lots = store.list_credit_lots(&recipient_id).unwrap();
+        assert_eq!(recipient_lots.len(), 1);
+        assert_eq!(recipient_lots[0].source, TransactionType::TransferIn);
+        assert_eq!(recipient_lots[0].remaining_cents, 600);
+        assert_eq!(
+            store
+                .get_account(&recipient_id)
+                .unwrap()
+                .unwrap()
+                .balance_cents,
+            600
+        );
+
+        assert_eq!(
+            store
+                .ledger_balance(&LedgerAccount::System(SystemAccount::Transfers))
+                .unwrap(),
+            0
+        );
+        assert!(store.verify_ledger().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn promo_redemption_respects_limits() {
+        let (store, _dir) = create_test_store();
+        let first = UserId::generate();
+        let second = UserId::generate();
+        store.put_account(&Account::new(first)).unwrap();
+        store.put_account(&Account::new(second)).unwrap();
+
+        let mut promo = PromoCode::new("launch", 500);
+        promo.max_redemptions = Some(1);
+        store.put_promo_code(&promo).unwrap();
+
+        let redeem = |user_id: UserId| {
+            let tx = CreditTransaction::promo(user_id, 500, 500, "LAUNCH");
+            let redemption = PromoRedemption::new("LAUNCH".into(), user_id, tx.id, 500);
+            store.redeem_promo_code(&redemption, &tx, &[])
+        };
+
+        assert_eq!(redeem(first).unwrap(), 500);
+        assert!(matches!(
+            redeem(first),
+            Err(StoreError::PromoRejected {
+                reason: z_billing_core::PromoRejection::Exhausted,
+                ..
+            })
+        ));
+
+        // Updating the code keeps its redemption count
+        promo.max_redemptions = Some(2);
+        store.put_promo_code(&promo).unwrap();
+        assert_eq!(
+            store
+                .get_promo_code("LAUNCH")
+                .unwrap()
+                .unwrap()
+                .redemption_count,
+            1
+        );
+
+        assert!(matches!(
+            redeem(first),
+            Err(StoreError::PromoRejected {
+                reason: z_billing_core::PromoRejection::UserLimitReached,
+                ..
+            })
+        ));
+        assert_eq!(redeem(second).unwrap(), 500);
+        assert_eq!(store.count_promo_redemptions("LAUNCH", &first).unwrap(), 1);
+
+        let lots = store.list_credit_lots(&second).unwrap();
+        assert_eq!(lots[0].source, TransactionType::Bonus);
+        assert!(store.verify_ledger().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn gift_card_is_redeemed_once() {
+        let (store, _dir) = create_test_store();
+        let buyer = UserId::generate();
+        let recipient = UserId::generate();
+        store.put_account(&Account::new(recipient)).unwrap();
+
+        let card = GiftCard::issue(buyer, 2500, Some("cs_test".into()));
+        store.create_gift_card(&card, &[]).unwrap();
+        assert_eq!(
+            store.get_gift_card_by_code(&card.code).unwrap().unwrap().id,
+            card.id
+        );
+        assert_eq!(store.list_gift_cards_by_purchaser(&buyer).unwrap().len(), 1);
+
+        let tx = CreditTransaction::gift_card(recipient, &card, 2500);
+        assert_eq!(store.redeem_gift_card(&card.id, &tx, &[]).unwrap(), 2500);
+
+        let again = CreditTransaction::gift_card(recipient, &card, 5000);
+        assert!(matches!(
+            store.redeem_gift_card(&card.id, &again, &[]),
+            Err(StoreError::InvalidState { .. })
+        ));
+        assert!(matches!(
+            store.void_gift_card(&card.id, "fraud"),
+            Err(StoreError::InvalidState { .. })
+        ));
+
+        let redeemed = store.get_gift_card(&card.id).unwrap().unwrap();
+        assert_eq!(redeemed.status, GiftCardStatus::Redeemed);
+        assert_eq!(redeemed.redeemed_by, Some(recipient));
+        let lots = store.list_credit_lots(&recipient).unwrap();
+        assert_eq!(lots[0].source, TransactionType::GiftCard);
+        assert!(store.verify_ledger().unwrap().is_consistent());
+
+        // A voided card cannot be redeemed
+        let voided = GiftCard::issue(buyer, 1000, None);
+        store.create_gift_card(&voided, &[]).unwrap();
+        store.void_gift_card(&voided.id, "chargeback").unwrap();
+        let tx = CreditTransaction::gift_card(recipient, &voided, 3500);
+        assert!(matches!(
+            store.redeem_gift_card(&voided.id, &tx, &[]),
+            Err(StoreError::InvalidState { .. })
+        ));
+    }
+
+    #[test]
+    fn ledger_matches_balances_and_reports_drift() {
+        let (store, _dir) = create_test_store();
+        let user_id = UserId::generate();
+        let wallet = LedgerAccount::User(user_id);
+
+        let mut account = Account::new(user_id);
+        account.balance_cents = 500;
+        store.create_account(&account).unwrap();
+
+        let purchase = CreditTransaction::purchase(user_id, 1000, 1500, "Purchase".into());
+        store.add_credits(&user_id, 1000, &purchase, &[]).unwrap();
+        let usage =
+            CreditTransaction::usage(user_id, 300, 1200, "usage".into(), serde_json::Value::Null);
+        store
+            .process_usage(&api_call_event("evt-ledger-1", user_id, 300), &usage, &[])
+            .unwrap();
+
+        // Re-saving a stale copy of the account keeps the stored balance
+        store.put_account(&account).unwrap();
+        assert_eq!(
+            store.get_account(&user_id).unwrap().unwrap().balance_cents,
+            1200
+        );
+
+        assert_eq!(store.ledger_balance(&wallet).unwrap(), 1200);
+        assert_eq!(
+            store
+                .ledger_balance(&LedgerAccount::System(SystemAccount::StripeClearing))
+                .unwrap(),
+            -1000
+        );
+        assert_eq!(
+            store
+                .ledger_balance(&LedgerAccount::System(SystemAccount::UsageRevenue))
+                .unwrap(),
+            300
+        );
+
+        let entries = store.list_ledger_entries(&wallet, 10, 0).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert!(entries
+            .windows(2)
+            .all(|w| w[0].created_at.timestamp_millis() >= w[1].created_at.timestamp_millis()));
+        assert_eq!(
+            entries
+                .iter()
+                .filter(|e| e.transaction_id.is_none())
+                .count(),
+            1
+        );
+
+        let report = store.verify_ledger().unwrap();
+        assert_eq!(report.accounts_checked, 1);
+        assert!(report.is_consistent());
+
+        // A balance written around the ledger shows up as drift
+        let mut tampered = store.get_account(&user_id).unwrap().unwrap();
+        tampered.balance_cents += 50;
+        let cf = store.cf(cf::ACCOUNTS).unwrap();
+        store
+            .db
+            .put_cf(
+                &cf,
+                keys::account_key(&user_id),
+                RocksDb::serialize(&tampered).unwrap(),
+            )
+            .unwrap();
+
+        let report = store.verify_ledger().unwrap();
+        assert!(!report.is_consistent());
+        assert_eq!(report.unbalanced_cents, 0);
+        assert_eq!(report.drift.len(), 1);
+        assert_eq!(report.drift[0].account, wallet);
+        assert_eq!(report.drift[0].drift_cents(), 50);
+    }
 }
@@ -7,6 +7,14 @@ pub mod cf {
     /// Primary account records, keyed by `user_id`.
     pub const ACCOUNTS: &str = "accounts";
 
+    /// Index: account by Stripe customer, keyed by `stripe_customer_id`.
+    /// Value is the `user_id`.
+    pub const ACCOUNTS_BY_STRIPE_CUSTOMER: &str = "accounts_by_stripe_customer";
+
+    /// Index: account by Lago customer, keyed by `lago_customer_id`.
+    /// Value is the `user_id`.
+    pub const ACCOUNTS_BY_LAGO_CUSTOMER: &str = "accounts_by_lago_customer";
+
     /// Credit transactions, keyed by `transaction_id` (ULID).
     pub const TRANSACTIONS: &str = "transactions";
 
@@ -16,6 +24,81 @@ pub mod cf {
 
     /// Usage events for idempotency, keyed by `event_id`.
     pub const USAGE_EVENTS: &str = "usage_events";
+
+    /// Usage reversals, keyed by `event_id || 0x00 || reversal_id`.
+    pub const USAGE_REVERSALS: &str = "usage_reversals";
+
+    /// What each usage event took from credit lots, keyed by `event_id`.
+    /// Value is the list of lot draws, for reversals.
+    pub const USAGE_LOT_DRAWS: &str = "usage_lot_draws";
+
+    /// Credit reservations, keyed by `reservation_id` (ULID).
+    pub const RESERVATIONS: &str = "reservations";
+
+    /// Index: active reservations by user, keyed by `user_id || reservation_id`.
+    /// Value is empty (index only). Entries are removed once a hold is closed.
+    pub const ACTIVE_RESERVATIONS_BY_USER: &str = "active_reservations_by_user";
+
+    /// Organizations, keyed by `org_id`.
+    pub const ORGANIZATIONS: &str = "organizations";
+
+    /// Organization memberships, keyed by `org_id || user_id`.
+    pub const ORG_MEMBERS: &str = "org_members";
+
+    /// Open credit lots, keyed by `user_id || lot_id`.
+    /// Lots are removed once fully spent or expired.
+    pub const CREDIT_LOTS: &str = "credit_lots";
+
+    /// Index: expiring lots, keyed by `expires_at_millis || user_id || lot_id`.
+    /// Value is empty (index only).
+    pub const CREDIT_LOTS_BY_EXPIRY: &str = "credit_lots_by_expiry";
+
+    /// Agent budgets, keyed by `user_id || agent_id || period`.
+    pub const AGENT_BUDGETS: &str = "agent_budgets";
+
+    /// Running agent spend totals, keyed by `user_id || agent_id`.
+    pub const AGENT_SPEND: &str = "agent_spend";
+
+    /// Ledger entries, keyed by `account || created_at_millis || entry_id`.
+    pub const LEDGER_ENTRIES: &str = "ledger_entries";
+
+    /// Completed credit transfers, keyed by `from_user_id || idempotency_key`.
+    pub const CREDIT_TRANSFERS: &str = "credit_transfers";
+
+    /// Promo codes, keyed by `code`.
+    pub const PROMO_CODES: &str = "promo_codes";
+
+    /// Promo code redemptions, keyed by `code || 0x00 || user_id || transaction_id`.
+    /// Value is empty (index only).
+    pub const PROMO_REDEMPTIONS: &str = "promo_redemptions";
+
+    /// Gift cards, keyed by `card_id`.
+    pub const GIFT_CARDS: &str = "gift_cards";
+
+    /// Index: gift card by redemption code, keyed by `code`.
+    /// Value is the `card_id`.
+    pub const GIFT_CARD_CODES: &str = "gift_card_codes";
+
+    /// Index: gift cards by purchaser, keyed by `user_id || card_id`.
+    /// Value is empty (index only).
+    pub const GIFT_CARDS_BY_PURCHASER: &str = "gift_cards_by_purchaser";
+
+    /// Pending and dead-lettered outbox messages, keyed by `outbox_id`.
+    pub const OUTBOX: &str = "outbox";
+
+    /// Free grants already given, keyed by `user_id || grant_key`.
+    /// Value is the `transaction_id` of the credit.
+    pub const CREDIT_GRANTS: &str = "credit_grants";
+
+    /// Saved pricing catalogs, keyed by `version`.
+    pub const PRICING_CATALOGS: &str = "pricing_catalogs";
+
+    /// Storage meters, keyed by `user_id || resource_id`.
+    pub const STORAGE_METERS: &str = "storage_meters";
+
+    /// Markers for one-time backfills that have run, keyed by name.
+    /// Value is empty.
+    pub const META: &str = "meta";
 }
 
 /// Returns all column family names for database initialization.
@@ -23,8 +106,32 @@ pub mod cf {
 pub fn all_column_families() -> Vec<&'static str> {
     vec![
         cf::ACCOUNTS,
+        cf::ACCOUNTS_BY_STRIPE_CUSTOMER,
+        cf::ACCOUNTS_BY_LAGO_CUSTOMER,
         cf::TRANSACTIONS,
         cf::TRANSACTIONS_BY_USER,
         cf::USAGE_EVENTS,
+        cf::USAGE_REVERSALS,
+        cf::USAGE_LOT_DRAWS,
+        cf::RESERVATIONS,
+        cf::ACTIVE_RESERVATIONS_BY_USER,
+        cf::ORGANIZATIONS,
+        cf::ORG_MEMBERS,
+        cf::CREDIT_LOTS,
+        cf::CREDIT_LOTS_BY_EXPIRY,
+        cf::AGENT_BUDGETS,
+        cf::AGENT_SPEND,
+        cf::LEDGER_ENTRIES,
+        cf::CREDIT_TRANSFERS,
+        cf::PROMO_CODES,
+        cf::PROMO_REDEMPTIONS,
+        cf::GIFT_CARDS,
+        cf::GIFT_CARD_CODES,
+        cf::GIFT_CARDS_BY_PURCHASER,
+        cf::OUTBOX,
+        cf::CREDIT_GRANTS,
+        cf::PRICING_CATALOGS,
+        cf::STORAGE_METERS,
+        cf::META,
     ]
 }
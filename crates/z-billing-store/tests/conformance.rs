@@ -0,0 +1,1843 @@
+//! Store conformance tests.
+//!
+//! Every backend runs the same suite against the `Store` trait, so they
+//! cannot drift apart in behaviour. Each test only looks at records it
+//! created itself, which lets the `PostgreSQL` run share a database.
+//!
+//! ```text
+//! cargo test -p z-billing-store --features memory-backend --test conformance
+//! cargo test -p z-billing-store --features sqlite-backend --test conformance
+//! DATABASE_URL=postgres://... cargo test -p z-billing-store --test conformance -- --ignored
+//! ```
+
+use chrono::Datelike;
+use z_billing_core::{
+    grant, lot, Account, AgentBudget, AgentId, AmountSign, BudgetPeriod, CreditTransaction,
+    CreditTransfer, GiftCard, GiftCardStatus, LedgerAccount, LlmProvider, OrgId, OrgMembership,
+    OrgRole, Organization, OutboxMessage, OutboxStatus, OutboxTopic, PricingCatalog,
+    ProcessedWebhook, PromoCode, PromoRedemption, PromoRejection, Reservation, ReservationStatus,
+    StorageMeter, SystemAccount, TokenDirection, TransactionId, TransactionQuery, TransactionType,
+    UsageDimension, UsageEvent, UsageInterval, UsageMetric, UsageReversal, UsageSource,
+    UsageSummaryQuery, UserId, OUTBOX_MAX_ATTEMPTS,
+};
+use z_billing_store::{Store, StoreError};
+
+/// Generate one `#[tokio::test]` per conformance test, each running it
+/// against a fresh store from `$setup`.
+///
+/// `$setup` returns the store and a guard that must outlive it, such as the
+/// store's temp directory.
+macro_rules! conformance_tests {
+    ($setup:path $(, #[$attr:meta])*) => {
+        conformance_tests!(
+            @tests $setup,
+            [$(#[$attr])*],
+            account_round_trip,
+            accounts_are_found_by_customer,
+            account_writes_check_the_version,
+            transactions_list_newest_first,
+            transactions_query_filters_and_pages,
+            usage_is_idempotent_and_all_or_nothing,
+            usage_aggregates_by_model_and_day,
+            add_credits_opens_and_spends_lots,
+            grants_are_given_once_per_key,
+            credit_limit_allows_overdraft_until_back_above_zero,
+            reservations_hold_settle_and_release,
+            credit_lots_expire,
+            org_usage_respects_spend_cap,
+            agent_budget_blocks_usage,
+            agent_budget_blocks_org_usage,
+            reverse_usage_is_idempotent_and_capped,
+            reverse_usage_refills_the_lots_it_drew,
+            transfer_moves_only_purchased_credits,
+            promo_redemption_respects_limits,
+            gift_card_is_redeemed_once,
+            gift_card_is_issued_once_per_checkout,
+            webhook_events_are_recorded,
+            export_lists_everything_in_key_order,
+            export_counts_what_it_does_not_list,
+            outbox_is_written_with_charges_and_claimed_once,
+            pricing_catalogs_keep_the_latest_version,
+            storage_meters_are_written_with_a_version_check,
+        );
+    };
+    (@tests $setup:path, [$($attr:tt)*], $name:ident, $($rest:ident,)*) => {
+        #[tokio::test]
+        $($attr)*
+        async fn $name() {
+            let (store, _guard) = $setup().await;
+            crate::$name(&store).await;
+        }
+
+        conformance_tests!(@tests $setup, [$($attr)*], $($rest,)*);
+    };
+    (@tests $setup:path, [$($attr:tt)*],) => {};
+}
+
+#[cfg(feature = "memory-backend")]
+mod memory {
+    use z_billing_store::MemoryStore;
+
+    #[allow(clippy::unused_async)] // Matches the other backends' setup
+    async fn setup() -> (MemoryStore, ()) {
+        (MemoryStore::new(), ())
+    }
+
+    conformance_tests!(setup);
+}
+
+#[cfg(feature = "rocksdb-backend")]
+mod rocks {
+    use tempfile::TempDir;
+    use z_billing_store::RocksStore;
+
+    #[allow(clippy::unused_async)] // Matches the other backends' setup
+    async fn setup() -> (RocksStore, TempDir) {
+        let dir = TempDir::new().unwrap();
+        (RocksStore::open(dir.path()).unwrap(), dir)
+    }
+
+    conformance_tests!(setup);
+}
+
+#[cfg(feature = "sqlite-backend")]
+mod sqlite {
+    use sqlx::sqlite::SqliteConnectOptions;
+    use tempfile::TempDir;
+    use z_billing_store::SqliteStore;
+
+    async fn setup() -> (SqliteStore, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let options = SqliteConnectOptions::new()
+            .filename(dir.path().join("billing.db"))
+            .create_if_missing(true);
+        let pool = sqlx::SqlitePool::connect_with(options).await.unwrap();
+        sqlx::migrate!("./migrations-sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        (SqliteStore::new(pool), dir)
+    }
+
+    conformance_tests!(setup);
+}
+
+mod postgres {
+    use z_billing_store::PgStore;
+
+    async fn setup() -> (PgStore, ()) {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+        (PgStore::new(pool), ())
+    }
+
+    conformance_tests!(setup, #[ignore = "needs DATABASE_URL"]);
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// A unique event ID, so runs against a shared database never collide.
+fn unique(prefix: &str) -> String {
+    format!("{prefix}-{}", TransactionId::generate())
+}
+
+async fn new_account(store: &dyn Store, balance_cents: i64) -> UserId {
+    let user_id = UserId::generate();
+    let mut account = Account::new(user_id);
+    account.balance_cents = balance_cents;
+    store.create_account(&account).await.unwrap();
+    user_id
+}
+
+async fn balance(store: &dyn Store, user_id: &UserId) -> i64 {
+    store
+        .get_account(user_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance_cents
+}
+
+fn api_call_event(user_id: UserId, cost_cents: i64) -> UsageEvent {
+    UsageEvent {
+        event_id: unique("evt"),
+        user_id,
+        agent_id: None,
+        source: UsageSource::AuraRuntime,
+        metric: UsageMetric::ApiCalls {
+            endpoint: "test".to_string(),
+        },
+        quantity: 1.0,
+        cost_cents,
+        timestamp: chrono::Utc::now(),
+        metadata: serde_json::Value::Null,
+        transaction_id: None,
+    }
+}
+
+async fn usage(
+    store: &dyn Store,
+    user_id: UserId,
+    cost_cents: i64,
+) -> z_billing_store::Result<i64> {
+    let tx = CreditTransaction::usage(
+        user_id,
+        cost_cents,
+        0,
+        "usage".into(),
+        serde_json::json!({}),
+    );
+    store
+        .process_usage(&api_call_event(user_id, cost_cents), &tx, &[])
+        .await
+}
+
+async fn purchase(store: &dyn Store, user_id: UserId, amount_cents: i64) -> i64 {
+    let tx = CreditTransaction::purchase(user_id, amount_cents, 0, "Purchase".into());
+    store
+        .add_credits(&user_id, amount_cents, &tx, &[])
+        .await
+        .unwrap()
+}
+
+async fn lot_total(store: &dyn Store, user_id: &UserId) -> i64 {
+    store
+        .list_credit_lots(user_id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|lot| lot.remaining_cents)
+        .sum()
+}
+
+/// Check that each wallet's stored balance matches its ledger entries.
+async fn assert_ledger_matches(store: &dyn Store, wallets: &[LedgerAccount]) {
+    let report = store.verify_ledger().await.unwrap();
+    for wallet in wallets {
+        let stored = match wallet {
+            LedgerAccount::User(user_id) => balance(store, user_id).await,
+            LedgerAccount::Org(org_id) => {
+                store
+                    .get_organization(org_id)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .balance_cents
+            }
+            LedgerAccount::System(_) => continue,
+        };
+        assert_eq!(
+            store.ledger_balance(wallet).await.unwrap(),
+            stored,
+            "{wallet}"
+        );
+        assert!(
+            report.drift.iter().all(|d| d.account != *wallet),
+            "{wallet}"
+        );
+    }
+}
+
+// ============================================================================
+// Accounts and transactions
+// ============================================================================
+
+async fn account_round_trip(store: &dyn Store) {
+    let user_id = new_account(store, 500).await;
+    assert_eq!(balance(store, &user_id).await, 500);
+
+    // Re-saving a stale copy keeps the stored balance and lifetime counters
+    purchase(store, user_id, 1000).await;
+    let mut stale = Account::new(user_id);
+    stale.balance_cents = 500;
+    store.put_account(&stale).await.unwrap();
+    let account = store.get_account(&user_id).await.unwrap().unwrap();
+    assert_eq!(account.balance_cents, 1500);
+    assert_eq!(account.lifetime_purchased_cents, 1000);
+    assert_ledger_matches(store, &[LedgerAccount::User(user_id)]).await;
+
+    // An opening balance is only accepted when the account is created
+    assert!(matches!(
+        store.create_account(&stale).await,
+        Err(StoreError::VersionConflict { expected: 0, .. })
+    ));
+    let other = UserId::generate();
+    let mut fresh = Account::new(other);
+    fresh.balance_cents = 700;
+    store.put_account(&fresh).await.unwrap();
+    assert_eq!(balance(store, &other).await, 0);
+
+    // An account with credit history stays on the books
+    assert!(matches!(
+        store.delete_account(&user_id).await,
+        Err(StoreError::InvalidState { .. })
+    ));
+    assert!(store.get_account(&user_id).await.unwrap().is_some());
+
+    // One without goes, along with its budgets
+    let agent_id = AgentId::generate();
+    store
+        .put_agent_budget(&AgentBudget::new(other, agent_id, BudgetPeriod::Daily, 100))
+        .await
+        .unwrap();
+    store.delete_account(&other).await.unwrap();
+    assert!(store.get_account(&other).await.unwrap().is_none());
+    assert!(store
+        .list_agent_budgets(&other, &agent_id)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        store.delete_account(&other).await,
+        Err(StoreError::NotFound { .. })
+    ));
+}
+
+async fn accounts_are_found_by_customer(store: &dyn Store) {
+    let user_id = UserId::generate();
+    let (stripe_id, lago_id) = (unique("cus"), unique("lago"));
+    let mut account = Account::new(user_id);
+    account.stripe_customer_id = Some(stripe_id.clone());
+    account.lago_customer_id = Some(lago_id.clone());
+    store.create_account(&account).await.unwrap();
+
+    let by_stripe = store.find_account_by_stripe_customer(&stripe_id).await;
+    assert_eq!(by_stripe.unwrap().unwrap().user_id, user_id);
+    let by_lago = store.find_account_by_lago_customer(&lago_id).await;
+    assert_eq!(by_lago.unwrap().unwrap().user_id, user_id);
+    assert!(store
+        .find_account_by_lago_customer(&stripe_id)
+        .await
+        .unwrap()
+        .is_none());
+
+    store.delete_account(&user_id).await.unwrap();
+    assert!(store
+        .find_account_by_lago_customer(&lago_id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn account_writes_check_the_version(store: &dyn Store) {
+    let user_id = UserId::generate();
+    let mut account = Account::new(user_id);
+    assert_eq!(
+        store
+            .put_account_if_version(&account, 0, &[])
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(matches!(
+        store.put_account_if_version(&account, 0, &[]).await,
+        Err(StoreError::VersionConflict {
+            expected: 0,
+            actual: 1,
+            ..
+        })
+    ));
+
+    // Two writers read version 1; the second one loses
+    let mut first = store.get_account(&user_id).await.unwrap().unwrap();
+    let mut second = first.clone();
+    assert_eq!(first.version, 1);
+    first.is_zero_pro = true;
+    assert_eq!(
+        store.put_account_if_version(&first, 1, &[]).await.unwrap(),
+        2
+    );
+    second.referred_by = Some("someone".into());
+    assert!(matches!(
+        store.put_account_if_version(&second, 1, &[]).await,
+        Err(StoreError::VersionConflict {
+            expected: 1,
+            actual: 2,
+            ..
+        })
+    ));
+
+    // Balance changes do not move the version, and settings writes never
+    // touch the balance
+    purchase(store, user_id, 300).await;
+    account = store.get_account(&user_id).await.unwrap().unwrap();
+    assert_eq!(account.version, 2);
+    assert!(account.is_zero_pro);
+    assert_eq!(account.referred_by, None);
+    account.balance_cents = 0;
+    account.lifetime_purchased_cents = 0;
+    assert_eq!(
+        store
+            .put_account_if_version(&account, 2, &[])
+            .await
+            .unwrap(),
+        3
+    );
+    store.put_account(&account).await.unwrap();
+    let account = store.get_account(&user_id).await.unwrap().unwrap();
+    assert_eq!(account.version, 4);
+    assert_eq!(account.balance_cents, 300);
+    assert_eq!(account.lifetime_purchased_cents, 300);
+
+    // A conditional write to a missing account conflicts with version 0
+    let missing = Account::new(UserId::generate());
+    assert!(matches!(
+        store.put_account_if_version(&missing, 1, &[]).await,
+        Err(StoreError::VersionConflict {
+            expected: 1,
+            actual: 0,
+            ..
+        })
+    ));
+}
+
+async fn transactions_list_newest_first(store: &dyn Store) {
+    let user_id = new_account(store, 0).await;
+
+    let first = CreditTransaction::purchase(user_id, 5000, 5000, "Purchase 1".into());
+    store.put_transaction(&first).await.unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(2)); // Ensure different ULIDs
+    let second = CreditTransaction::referral_bonus(user_id, 500, 5500, "Referral".into());
+    store.put_transaction(&second).await.unwrap();
+
+    let page1 = store
+        .list_transactions_by_user(&user_id, 1, 0)
+        .await
+        .unwrap();
+    let page2 = store
+        .list_transactions_by_user(&user_id, 1, 1)
+        .await
+        .unwrap();
+    assert_eq!(page1[0].id, second.id);
+    assert_eq!(page2[0].id, first.id);
+    assert_eq!(
+        store
+            .list_transactions_by_user(&user_id, 10, 0)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+    assert_eq!(
+        store
+            .get_transaction(&first.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .amount_cents,
+        5000
+    );
+
+    assert!(store.has_referral_bonus(&user_id).await.unwrap());
+    assert!(!store.has_referral_bonus(&UserId::generate()).await.unwrap());
+}
+
+async fn transactions_query_filters_and_pages(store: &dyn Store) {
+    let user_id = new_account(store, 0).await;
+
+    let mut expected = Vec::new();
+    for (i, model) in ["gpt-5", "claude-sonnet-4", "gpt-5"]
+        .into_iter()
+        .enumerate()
+    {
+        let purchase = CreditTransaction::purchase(user_id, 1000, 1000, format!("Purchase {i}"));
+        store.put_transaction(&purchase).await.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2)); // Ensure different ULIDs
+        let usage = CreditTransaction::usage(
+            user_id,
+            100,
+            900,
+            format!("Usage {i}"),
+            serde_json::json!({ "model": model }),
+        );
+        store.put_transaction(&usage).await.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        expected.push((purchase, usage));
+    }
+
+    // Walk every page with the cursor
+    let mut seen = Vec::new();
+    let mut query = TransactionQuery::new(4);
+    loop {
+        let page = store.query_transactions(&user_id, &query).await.unwrap();
+        let Some(last) = page.last() else { break };
+        query.before = Some(last.id);
+        seen.extend(page.iter().map(|tx| tx.id));
+    }
+    assert_eq!(seen.len(), 6);
+    assert_eq!(seen[0], expected[2].1.id);
+    assert_eq!(seen[5], expected[0].0.id);
+
+    let debits = TransactionQuery {
+        sign: Some(AmountSign::Debit),
+        ..TransactionQuery::new(10)
+    };
+    let page = store.query_transactions(&user_id, &debits).await.unwrap();
+    assert_eq!(page.len(), 3);
+    assert!(page.iter().all(|tx| tx.amount_cents < 0));
+
+    let purchases = TransactionQuery {
+        transaction_types: vec![TransactionType::Purchase],
+        before: Some(expected[2].0.id),
+        ..TransactionQuery::new(10)
+    };
+    let page = store
+        .query_transactions(&user_id, &purchases)
+        .await
+        .unwrap();
+    let ids: Vec<_> = page.iter().map(|tx| tx.id).collect();
+    assert_eq!(ids, [expected[1].0.id, expected[0].0.id]);
+
+    let mut by_model = TransactionQuery::new(10);
+    by_model.metadata.insert("model".into(), "gpt-5".into());
+    let page = store.query_transactions(&user_id, &by_model).await.unwrap();
+    let ids: Vec<_> = page.iter().map(|tx| tx.id).collect();
+    assert_eq!(ids, [expected[2].1.id, expected[0].1.id]);
+
+    let window = TransactionQuery {
+        since: Some(expected[1].0.created_at),
+        until: Some(expected[2].0.created_at),
+        ..TransactionQuery::new(10)
+    };
+    let page = store.query_transactions(&user_id, &window).await.unwrap();
+    let ids: Vec<_> = page.iter().map(|tx| tx.id).collect();
+    assert_eq!(ids, [expected[1].1.id, expected[1].0.id]);
+}
+
+async fn usage_is_idempotent_and_all_or_nothing(store: &dyn Store) {
+    let user_id = new_account(store, 1000).await;
+    let wallet = LedgerAccount::User(user_id);
+
+    let event = api_call_event(user_id, 300);
+    let tx = CreditTransaction::usage(user_id, 300, 700, "usage".into(), serde_json::json!({}));
+    assert_eq!(store.process_usage(&event, &tx, &[]).await.unwrap(), 700);
+    assert!(store.has_usage_event(&event.event_id).await.unwrap());
+
+    // A replay is rejected without charging again
+    let retry = CreditTransaction::usage(user_id, 300, 400, "usage".into(), serde_json::json!({}));
+    assert!(matches!(
+        store.process_usage(&event, &retry, &[]).await,
+        Err(StoreError::DuplicateEvent { .. })
+    ));
+    assert!(store.get_transaction(&retry.id).await.unwrap().is_none());
+
+    // A rejected charge leaves no trace
+    let too_big = api_call_event(user_id, 800);
+    let tx = CreditTransaction::usage(user_id, 800, -100, "usage".into(), serde_json::json!({}));
+    assert!(matches!(
+        store.process_usage(&too_big, &tx, &[]).await,
+        Err(StoreError::InsufficientCredits {
+            balance: 700,
+            required: 800
+        })
+    ));
+    assert!(!store.has_usage_event(&too_big.event_id).await.unwrap());
+    assert!(store.get_transaction(&tx.id).await.unwrap().is_none());
+    assert_eq!(
+        store
+            .list_ledger_entries(&wallet, 10, 0)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let account = store.get_account(&user_id).await.unwrap().unwrap();
+    assert_eq!(account.balance_cents, 700);
+    assert_eq!(account.lifetime_used_cents, 300);
+    assert!(matches!(
+        usage(store, UserId::generate(), 1).await,
+        Err(StoreError::NotFound { .. })
+    ));
+    assert_ledger_matches(store, &[wallet]).await;
+}
+
+async fn usage_aggregates_by_model_and_day(store: &dyn Store) {
+    let user_id = new_account(store, 10_000).await;
+    let agent_id = AgentId::generate();
+    let day = |d: u32| {
+        chrono::NaiveDate::from_ymd_opt(2025, 3, d)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_utc()
+    };
+
+    let llm = |model: &str, direction: TokenDirection, tokens: u64, cost_cents: i64, d: u32| {
+        let mut event = UsageEvent::llm(
+            unique("evt"),
+            user_id,
+            Some(agent_id),
+            LlmProvider::Anthropic,
+            model.to_string(),
+            direction,
+            tokens,
+            cost_cents,
+        );
+        event.timestamp = day(d);
+        event
+    };
+    let mut api_call = api_call_event(user_id, 5);
+    api_call.timestamp = day(4);
+    let events = [
+        llm("claude-opus-4", TokenDirection::Input, 1000, 30, 3),
+        llm("claude-opus-4", TokenDirection::Output, 200, 20, 3),
+        llm("claude-opus-4", TokenDirection::Input, 500, 15, 4),
+        llm("gpt-5", TokenDirection::Input, 100, 1, 4),
+        api_call,
+    ];
+    for event in &events {
+        let tx = CreditTransaction::usage(
+            user_id,
+            event.cost_cents,
+            0,
+            "usage".into(),
+            serde_json::json!({}),
+        );
+        store.process_usage(event, &tx, &[]).await.unwrap();
+    }
+    // Another user's usage is left out
+    let other = new_account(store, 100).await;
+    let mut theirs = api_call_event(other, 7);
+    theirs.timestamp = day(3);
+    let tx = CreditTransaction::usage(other, 7, 93, "usage".into(), serde_json::json!({}));
+    store.process_usage(&theirs, &tx, &[]).await.unwrap();
+
+    let mut query = UsageSummaryQuery {
+        user_id: Some(user_id),
+        since: day(1),
+        until: day(10),
+        interval: Some(UsageInterval::Day),
+        group_by: vec![UsageDimension::Model],
+    };
+    let rows = store.aggregate_usage(&query).await.unwrap();
+    let totals: Vec<_> = rows
+        .iter()
+        .map(|row| {
+            (
+                row.group.period_start.unwrap().day(),
+                row.group.model.as_deref(),
+                row.input_tokens,
+                row.output_tokens,
+                row.cost_cents,
+                row.event_count,
+            )
+        })
+        .collect();
+    assert_eq!(
+        totals,
+        [
+            (3, Some("claude-opus-4"), 1000, 200, 50, 2),
+            (4, Some("claude-opus-4"), 500, 0, 15, 1),
+            (4, None, 0, 0, 5, 1),
+            (4, Some("gpt-5"), 100, 0, 1, 1),
+        ]
+    );
+
+    query.interval = None;
+    query.group_by = vec![UsageDimension::Agent, UsageDimension::Source];
+    query.until = day(4);
+    let rows = store.aggregate_usage(&query).await.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].group.agent_id, Some(agent_id));
+    assert_eq!(rows[0].group.source, Some(UsageSource::AuraRuntime));
+    assert_eq!(rows[0].cost_cents, 50);
+}
+
+async fn add_credits_opens_and_spends_lots(store: &dyn Store) {
+    let user_id = new_account(store, 0).await;
+
+    assert_eq!(purchase(store, user_id, 1000).await, 1000);
+    let daily = CreditTransaction::daily_grant(user_id, 50, 1050);
+    assert_eq!(
+        store.add_credits(&user_id, 50, &daily, &[]).await.unwrap(),
+        1050
+    );
+
+    let account = store.get_account(&user_id).await.unwrap().unwrap();
+    assert_eq!(account.lifetime_purchased_cents, 1000);
+    assert_eq!(account.lifetime_granted_cents, 0);
+
+    let lots = store.list_credit_lots(&user_id).await.unwrap();
+    assert_eq!(lots.len(), 2);
+    assert_eq!(lots[0].source, TransactionType::DailyGrant);
+    assert!(lots[0].expires_at.is_some());
+
+    // The daily lot is spent first and dropped once empty
+    assert_eq!(usage(store, user_id, 80).await.unwrap(), 970);
+    let lots = store.list_credit_lots(&user_id).await.unwrap();
+    assert_eq!(lots.len(), 1);
+    assert_eq!(lots[0].source, TransactionType::Purchase);
+    assert_eq!(lots[0].remaining_cents, 970);
+
+    // A negative amount draws lots down like usage
+    let refund = CreditTransaction::refund(user_id, 100, 870, "Refund".into());
+    assert_eq!(
+        store
+            .add_credits(&user_id, -100, &refund, &[])
+            .await
+            .unwrap(),
+        870
+    );
+    assert_eq!(lot_total(store, &user_id).await, 870);
+    assert!(matches!(
+        store
+            .add_credits(&UserId::generate(), 100, &refund, &[])
+            .await,
+        Err(StoreError::NotFound { .. })
+    ));
+    assert_ledger_matches(store, &[LedgerAccount::User(user_id)]).await;
+}
+
+async fn grants_are_given_once_per_key(store: &dyn Store) {
+    let user_id = new_account(store, 100).await;
+    let today = grant::daily(chrono::Utc::now().date_naive());
+    let daily = |balance| CreditTransaction::daily_grant(user_id, 50, balance);
+
+    // Racing grants with the same key credit the account once
+    let (a, b) = (daily(150), daily(150));
+    let (first, second) = tokio::join!(
+        store.grant_once(&user_id, &today, &a, &[]),
+        store.grant_once(&user_id, &today, &b, &[]),
+    );
+    let mut results = [first.unwrap(), second.unwrap()];
+    results.sort();
+    assert_eq!(results, [None, Some(150)]);
+
+    // A repeat writes neither the credit nor its outbox messages
+    let message = OutboxMessage::new(
+        OutboxTopic::BalanceUpdate,
+        serde_json::json!({ "user_id": user_id.to_string() }),
+    );
+    let repeat = daily(200);
+    assert_eq!(
+        store
+            .grant_once(&user_id, &today, &repeat, std::slice::from_ref(&message))
+            .await
+            .unwrap(),
+        None
+    );
+    assert!(store.get_transaction(&repeat.id).await.unwrap().is_none());
+    assert!(store
+        .get_outbox_message(&message.id)
+        .await
+        .unwrap()
+        .is_none());
+
+    let account = store.get_account(&user_id).await.unwrap().unwrap();
+    assert_eq!(account.balance_cents, 150);
+    assert_eq!(lot_total(store, &user_id).await, 50);
+
+    // Another key is another grant, and the key is per user
+    let signup = CreditTransaction::signup_grant(user_id, 1000, 1150);
+    assert_eq!(
+        store
+            .grant_once(&user_id, grant::SIGNUP, &signup, &[])
+            .await
+            .unwrap(),
+        Some(1150)
+    );
+    let other = new_account(store, 0).await;
+    let other_daily = CreditTransaction::daily_grant(other, 50, 50);
+    assert_eq!(
+        store
+            .grant_once(&other, &today, &other_daily, &[])
+            .await
+            .unwrap(),
+        Some(50)
+    );
+
+    // An unknown account is not granted, and its key stays free
+    let unknown = UserId::generate();
+    let tx = CreditTransaction::daily_grant(unknown, 50, 50);
+    assert!(matches!(
+        store.grant_once(&unknown, &today, &tx, &[]).await,
+        Err(StoreError::NotFound { .. })
+    ));
+    store.put_account(&Account::new(unknown)).await.unwrap();
+    assert_eq!(
+        store.grant_once(&unknown, &today, &tx, &[]).await.unwrap(),
+        Some(50)
+    );
+}
+
+async fn credit_limit_allows_overdraft_until_back_above_zero(store: &dyn Store) {
+    let user_id = UserId::generate();
+    let mut account = Account::new(user_id);
+    account.credit_limit_cents = 500;
+    store.put_account(&account).await.unwrap();
+
+    assert_eq!(usage(store, user_id, 300).await.unwrap(), -300);
+    assert!(matches!(
+        usage(store, user_id, 300).await,
+        Err(StoreError::InsufficientCredits {
+            balance: 200,
+            required: 300
+        })
+    ));
+    assert_eq!(usage(store, user_id, 200).await.unwrap(), -500);
+    assert!(
+        store
+            .get_account(&user_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .overdraft_locked
+    );
+
+    // A partial repayment does not reopen the credit line
+    assert_eq!(purchase(store, user_id, 400).await, -100);
+    assert!(matches!(
+        usage(store, user_id, 10).await,
+        Err(StoreError::InsufficientCredits { .. })
+    ));
+
+    // Back above zero unlocks it; repaid credits are not left in the lot
+    assert_eq!(purchase(store, user_id, 150).await, 50);
+    assert!(
+        !store
+            .get_account(&user_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .overdraft_locked
+    );
+    assert_eq!(lot_total(store, &user_id).await, 50);
+    assert_eq!(usage(store, user_id, 400).await.unwrap(), -350);
+    assert_ledger_matches(store, &[LedgerAccount::User(user_id)]).await;
+}
+
+// ============================================================================
+// Reservations and lots
+// ============================================================================
+
+async fn reservations_hold_settle_and_release(store: &dyn Store) {
+    let user_id = new_account(store, 100).await;
+    let ttl = chrono::Duration::seconds(60);
+
+    let hold = Reservation::new(user_id, 50, ttl, serde_json::Value::Null);
+    assert_eq!(store.create_reservation(&hold).await.unwrap(), 50);
+    assert_eq!(store.reserved_cents(&user_id).await.unwrap(), 50);
+
+    // Other holds and plain usage are limited to the unreserved balance
+    let second = Reservation::new(user_id, 60, ttl, serde_json::Value::Null);
+    assert!(matches!(
+        store.create_reservation(&second).await,
+        Err(StoreError::InsufficientCredits {
+            balance: 50,
+            required: 60
+        })
+    ));
+    assert!(matches!(
+        usage(store, user_id, 60).await,
+        Err(StoreError::InsufficientCredits { .. })
+    ));
+
+    // Settling debits the actual cost, which may exceed the hold
+    let event = api_call_event(user_id, 70);
+    let tx = CreditTransaction::usage(user_id, 70, 30, "Stream".into(), serde_json::json!({}));
+    assert_eq!(
+        store
+            .settle_reservation(&hold.id, &event, &tx, &[])
+            .await
+            .unwrap(),
+        30
+    );
+    assert_eq!(store.reserved_cents(&user_id).await.unwrap(), 0);
+    let settled = store.get_reservation(&hold.id).await.unwrap().unwrap();
+    assert_eq!(settled.status, ReservationStatus::Settled);
+    assert_eq!(settled.transaction_id, Some(tx.id));
+    assert!(matches!(
+        store
+            .settle_reservation(&hold.id, &api_call_event(user_id, 1), &tx, &[])
+            .await,
+        Err(StoreError::InvalidState { .. })
+    ));
+    assert!(matches!(
+        store.release_reservation(&hold.id).await,
+        Err(StoreError::InvalidState { .. })
+    ));
+
+    let released = Reservation::new(user_id, 20, ttl, serde_json::Value::Null);
+    store.create_reservation(&released).await.unwrap();
+    let closed = store.release_reservation(&released.id).await.unwrap();
+    assert_eq!(closed.status, ReservationStatus::Released);
+    assert_eq!(
+        store
+            .release_reservation(&released.id)
+            .await
+            .unwrap()
+            .status,
+        ReservationStatus::Released
+    );
+
+    let lapsed = Reservation::new(user_id, 20, ttl, serde_json::Value::Null);
+    store.create_reservation(&lapsed).await.unwrap();
+    assert_eq!(store.reserved_cents(&user_id).await.unwrap(), 20);
+    let later = lapsed.expires_at + chrono::Duration::seconds(1);
+    assert!(store.expire_reservations(later).await.unwrap() >= 1);
+    assert_eq!(
+        store
+            .get_reservation(&lapsed.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .status,
+        ReservationStatus::Expired
+    );
+    assert_eq!(store.reserved_cents(&user_id).await.unwrap(), 0);
+    assert_eq!(balance(store, &user_id).await, 30);
+}
+
+async fn credit_lots_expire(store: &dyn Store) {
+    let user_id = new_account(store, 0).await;
+    purchase(store, user_id, 1000).await;
+    let daily = CreditTransaction::daily_grant(user_id, 50, 1050);
+    store.add_credits(&user_id, 50, &daily, &[]).await.unwrap();
+    usage(store, user_id, 20).await.unwrap();
+
+    let tomorrow = chrono::Utc::now() + chrono::Duration::days(1);
+    assert!(store.expire_credit_lots(tomorrow).await.unwrap() >= 1);
+    assert_eq!(balance(store, &user_id).await, 1000);
+
+    let transactions = store
+        .list_transactions_by_user(&user_id, 10, 0)
+        .await
+        .unwrap();
+    let expiry = transactions
+        .iter()
+        .find(|tx| tx.transaction_type == TransactionType::Expiry)
+        .unwrap();
+    assert_eq!(expiry.amount_cents, -30);
+    assert_eq!(expiry.balance_after_cents, 1000);
+
+    let lots = store.list_credit_lots(&user_id).await.unwrap();
+    assert_eq!(lots.len(), 1);
+    assert_eq!(lots[0].remaining_cents, 1000);
+    assert_ledger_matches(store, &[LedgerAccount::User(user_id)]).await;
+}
+
+// ============================================================================
+// Organizations and agents
+// ============================================================================
+
+async fn org_with_member(store: &dyn Store, balance_cents: i64) -> (OrgId, UserId) {
+    let mut org = Organization::new("Acme".into());
+    org.balance_cents = balance_cents;
+    store.put_organization(&org).await.unwrap();
+
+    let user_id = new_account(store, 0).await;
+    store
+        .put_org_membership(&OrgMembership::new(org.id, user_id, OrgRole::Member))
+        .await
+        .unwrap();
+
+    (org.id, user_id)
+}
+
+async fn org_usage_respects_spend_cap(store: &dyn Store) {
+    let (org_id, user_id) = org_with_member(store, 1000).await;
+    let org_usage = |user_id: UserId, cost_cents: i64| {
+        let tx = CreditTransaction::usage(
+            user_id,
+            cost_cents,
+            0,
+            "usage".into(),
+            serde_json::json!({}),
+        )
+        .with_org(org_id);
+        let event = api_call_event(user_id, cost_cents);
+        async move { store.process_org_usage(&org_id, &event, &tx, &[]).await }
+    };
+
+    let mut membership = store
+        .get_org_membership(&org_id, &user_id)
+        .await
+        .unwrap()
+        .unwrap();
+    membership.monthly_spend_cap_cents = Some(250);
+    store.put_org_membership(&membership).await.unwrap();
+    assert_eq!(store.list_org_members(&org_id).await.unwrap().len(), 1);
+
+    // The pool pays; the member's own balance is untouched
+    assert_eq!(org_usage(user_id, 200).await.unwrap(), 800);
+    assert_eq!(balance(store, &user_id).await, 0);
+    let since = chrono::Utc::now() - chrono::Duration::days(1);
+    assert_eq!(
+        store
+            .org_member_spend_since(&org_id, &user_id, since)
+            .await
+            .unwrap(),
+        200
+    );
+
+    assert!(matches!(
+        org_usage(user_id, 100).await,
+        Err(StoreError::SpendCapExceeded {
+            cap: 250,
+            spent: 200,
+            required: 100
+        })
+    ));
+    assert!(matches!(
+        org_usage(UserId::generate(), 10).await,
+        Err(StoreError::NotFound { .. })
+    ));
+
+    let topup =
+        CreditTransaction::purchase(user_id, 500, 1300, "Org top-up".into()).with_org(org_id);
+    assert_eq!(
+        store.add_org_credits(&org_id, 500, &topup).await.unwrap(),
+        1300
+    );
+    let org = store.get_organization(&org_id).await.unwrap().unwrap();
+    assert_eq!(org.lifetime_used_cents, 200);
+    assert_eq!(org.lifetime_purchased_cents, 500);
+    assert_ledger_matches(store, &[LedgerAccount::Org(org_id)]).await;
+
+    store
+        .delete_org_membership(&org_id, &user_id)
+        .await
+        .unwrap();
+    assert!(store
+        .get_org_membership(&org_id, &user_id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn agent_budget_blocks_usage(store: &dyn Store) {
+    let user_id = new_account(store, 1000).await;
+    let agent_id = AgentId::generate();
+    store
+        .put_agent_budget(&AgentBudget::new(
+            user_id,
+            agent_id,
+            BudgetPeriod::Daily,
+            150,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .list_agent_budgets(&user_id, &agent_id)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let agent_usage = |cost_cents: i64| {
+        let mut event = api_call_event(user_id, cost_cents);
+        event.agent_id = Some(agent_id);
+        let tx = CreditTransaction::usage(
+            user_id,
+            cost_cents,
+            0,
+            "usage".into(),
+            serde_json::json!({}),
+        );
+        async move { store.process_usage(&event, &tx, &[]).await }
+    };
+
+    assert_eq!(agent_usage(100).await.unwrap(), 900);
+    assert!(matches!(
+        agent_usage(100).await,
+        Err(StoreError::BudgetExceeded {
+            period: BudgetPeriod::Daily,
+            limit: 150,
+            spent: 100,
+            required: 100,
+            ..
+        })
+    ));
+
+    // Agent-less usage is unaffected
+    assert_eq!(usage(store, user_id, 100).await.unwrap(), 800);
+
+    let totals = store
+        .get_agent_spend(&user_id, &agent_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(totals.day_cents, 100);
+    assert_eq!(totals.lifetime_cents, 100);
+    assert_eq!(store.list_agent_spend(&user_id).await.unwrap().len(), 1);
+
+    store
+        .delete_agent_budget(&user_id, &agent_id, BudgetPeriod::Daily)
+        .await
+        .unwrap();
+    assert_eq!(agent_usage(100).await.unwrap(), 700);
+}
+
+async fn agent_budget_blocks_org_usage(store: &dyn Store) {
+    let (org_id, user_id) = org_with_member(store, 1000).await;
+    let agent_id = AgentId::generate();
+    store
+        .put_agent_budget(&AgentBudget::new(
+            user_id,
+            agent_id,
+            BudgetPeriod::Daily,
+            150,
+        ))
+        .await
+        .unwrap();
+
+    let agent_usage = |cost_cents: i64| {
+        let mut event = api_call_event(user_id, cost_cents);
+        event.agent_id = Some(agent_id);
+        let tx = CreditTransaction::usage(
+            user_id,
+            cost_cents,
+            0,
+            "usage".into(),
+            serde_json::json!({}),
+        )
+        .with_org(org_id);
+        async move { store.process_org_usage(&org_id, &event, &tx, &[]).await }
+    };
+
+    // The pool pays, but the spend still counts against the agent's budget
+    assert_eq!(agent_usage(100).await.unwrap(), 900);
+    assert!(matches!(
+        agent_usage(100).await,
+        Err(StoreError::BudgetExceeded {
+            period: BudgetPeriod::Daily,
+            limit: 150,
+            spent: 100,
+            required: 100,
+            ..
+        })
+    ));
+    let org = store.get_organization(&org_id).await.unwrap().unwrap();
+    assert_eq!(org.balance_cents, 900);
+
+    let totals = store
+        .get_agent_spend(&user_id, &agent_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(totals.day_cents, 100);
+    assert_eq!(totals.lifetime_cents, 100);
+}
+
+// ============================================================================
+// Reversals, transfers, promos and gift cards
+// ============================================================================
+
+async fn reverse_usage_is_idempotent_and_capped(store: &dyn Store) {
+    let user_id = new_account(store, 1000).await;
+
+    let charge = CreditTransaction::usage(user_id, 300, 700, "usage".into(), serde_json::json!({}));
+    let event = api_call_event(user_id, 300).with_transaction(charge.id);
+    let event_id = event.event_id.clone();
+    store.process_usage(&event, &charge, &[]).await.unwrap();
+
+    let reverse = |reversal_id: &str, amount_cents: i64| {
+        let tx = CreditTransaction::reversal(&charge, &event_id, amount_cents, 0, "refund".into());
+        let reversal =
+            UsageReversal::new(event_id.clone(), reversal_id.into(), amount_cents, tx.id);
+        async move { store.reverse_usage(&reversal, &tx, &[]).await }
+    };
+
+    let first = reverse("part-1", 100).await.unwrap();
+    let replayed = reverse("part-1", 100).await.unwrap();
+    assert_eq!(replayed.transaction_id, first.transaction_id);
+    assert_eq!(balance(store, &user_id).await, 800);
+
+    assert!(matches!(
+        reverse("part-2", 250).await,
+        Err(StoreError::ReversalExceedsCharge {
+            charged: 300,
+            reversed: 100,
+            requested: 250,
+        })
+    ));
+
+    let account = store.get_account(&user_id).await.unwrap().unwrap();
+    assert_eq!(account.balance_cents, 800);
+    assert_eq!(account.lifetime_used_cents, 200);
+    assert_eq!(
+        store.list_usage_reversals(&event_id).await.unwrap().len(),
+        1
+    );
+    assert_eq!(
+        store
+            .get_usage_event(&event_id)
+            .await
+            .unwrap()
+            .unwrap()
+            .transaction_id,
+        Some(charge.id)
+    );
+
+    // Outbox messages are written with a refund, and not with a replay
+    let refund = |reversal_id: &str, amount_cents: i64| {
+        let tx = CreditTransaction::reversal(&charge, &event_id, amount_cents, 0, "refund".into());
+        let reversal =
+            UsageReversal::new(event_id.clone(), reversal_id.into(), amount_cents, tx.id);
+        let message = OutboxMessage::new(
+            OutboxTopic::BalanceUpdate,
+            serde_json::json!({ "user_id": user_id.to_string() }),
+        );
+        async move {
+            store
+                .reverse_usage(&reversal, &tx, std::slice::from_ref(&message))
+                .await
+                .unwrap();
+            store.get_outbox_message(&message.id).await.unwrap()
+        }
+    };
+    assert!(refund("part-1", 100).await.is_none());
+    assert!(refund("part-3", 50).await.is_some());
+    assert_eq!(balance(store, &user_id).await, 850);
+    assert_ledger_matches(store, &[LedgerAccount::User(user_id)]).await;
+}
+
+async fn reverse_usage_refills_the_lots_it_drew(store: &dyn Store) {
+    let user_id = new_account(store, 0).await;
+    purchase(store, user_id, 200).await;
+    let daily = CreditTransaction::daily_grant(user_id, 50, 250);
+    store.add_credits(&user_id, 50, &daily, &[]).await.unwrap();
+    let daily_expiry = store.list_credit_lots(&user_id).await.unwrap()[0].expires_at;
+    assert!(daily_expiry.is_some());
+
+    // The daily grant is spent first, then 100 purchased credits
+    let charge = CreditTransaction::usage(user_id, 150, 100, "usage".into(), serde_json::json!({}));
+    let event = api_call_event(user_id, 150).with_transaction(charge.id);
+    let event_id = event.event_id.clone();
+    store.process_usage(&event, &charge, &[]).await.unwrap();
+    assert_eq!(store.list_credit_lots(&user_id).await.unwrap().len(), 1);
+
+    let reverse = |reversal_id: &str, amount_cents: i64| {
+        let tx = CreditTransaction::reversal(&charge, &event_id, amount_cents, 0, "refund".into());
+        let reversal =
+            UsageReversal::new(event_id.clone(), reversal_id.into(), amount_cents, tx.id);
+        async move { store.reverse_usage(&reversal, &tx, &[]).await }
+    };
+
+    // Refunds undo the last draw first: purchased credits come back
+    // without an expiry...
+    reverse("part-1", 100).await.unwrap();
+    let lots = store.list_credit_lots(&user_id).await.unwrap();
+    assert!(lots
+        .iter()
+        .all(|lot| lot.source == TransactionType::Purchase && lot.expires_at.is_none()));
+    assert_eq!(lot_total(store, &user_id).await, 200);
+
+    // ...then the daily grant, which still expires at midnight
+    reverse("part-2", 50).await.unwrap();
+    let lots = store.list_credit_lots(&user_id).await.unwrap();
+    assert_eq!(lots[0].source, TransactionType::DailyGrant);
+    assert_eq!(lots[0].remaining_cents, 50);
+    assert_eq!(lots[0].expires_at, daily_expiry);
+    assert!(lots
+        .iter()
+        .all(|lot| lot.source != TransactionType::Reversal));
+    assert_eq!(lot_total(store, &user_id).await, 250);
+    assert_eq!(balance(store, &user_id).await, 250);
+
+    // Refilled daily credits expire like the original grant
+    let midnight = daily_expiry.unwrap();
+    assert!(store.expire_credit_lots(midnight).await.unwrap() >= 1);
+    assert_eq!(balance(store, &user_id).await, 200);
+    assert_ledger_matches(store, &[LedgerAccount::User(user_id)]).await;
+}
+
+async fn transfer_moves_only_purchased_credits(store: &dyn Store) {
+    let sender_id = new_account(store, 0).await;
+    let recipient_id = new_account(store, 0).await;
+    let to = LedgerAccount::User(recipient_id);
+
+    let bonus = CreditTransaction::bonus(sender_id, 300, 300, "Bonus".into());
+    store
+        .add_credits(&sender_id, 300, &bonus, &[])
+        .await
+        .unwrap();
+    purchase(store, sender_id, 1000).await;
+
+    let key = unique("transfer");
+    let transfer_of = |amount_cents: i64, key: &str| {
+        let debit = CreditTransaction::transfer_out(sender_id, &to, amount_cents, 0, key);
+        let credit = CreditTransaction::transfer_in(sender_id, &to, amount_cents, 0, key);
+        let transfer =
+            CreditTransfer::new(sender_id, key.into(), to, amount_cents, debit.id, credit.id);
+        async move {
+            store
+                .transfer_credits(&transfer, &debit, &credit, &[])
+                .await
+        }
+    };
+
+    // The bonus can't be given away
+    assert!(matches!(
+        transfer_of(1200, &unique("transfer")).await,
+        Err(StoreError::TransferExceedsPurchased {
+            transferable: 1000,
+            requested: 1200,
+        })
+    ));
+
+    let transfer = transfer_of(600, &key).await.unwrap();
+    let replayed = transfer_of(600, &key).await.unwrap();
+    assert_eq!(replayed.out_transaction_id, transfer.out_transaction_id);
+    assert_eq!(
+        store
+            .get_credit_transfer(&sender_id, &key)
+            .await
+            .unwrap()
+            .unwrap()
+            .in_transaction_id,
+        transfer.in_transaction_id
+    );
+
+    assert_eq!(balance(store, &sender_id).await, 700);
+    assert_eq!(
+        lot::transferable_cents(&store.list_credit_lots(&sender_id).await.unwrap()),
+        400
+    );
+    let recipient_lots = store.list_credit_lots(&recipient_id).await.unwrap();
+    assert_eq!(recipient_lots.len(), 1);
+    assert_eq!(recipient_lots[0].source, TransactionType::TransferIn);
+    assert_eq!(recipient_lots[0].remaining_cents, 600);
+    assert_eq!(balance(store, &recipient_id).await, 600);
+
+    // A missing recipient moves nothing
+    let nobody = LedgerAccount::User(UserId::generate());
+    let key = unique("transfer");
+    let debit = CreditTransaction::transfer_out(sender_id, &nobody, 100, 0, &key);
+    let credit = CreditTransaction::transfer_in(sender_id, &nobody, 100, 0, &key);
+    let transfer = CreditTransfer::new(sender_id, key, nobody, 100, debit.id, credit.id);
+    assert!(matches!(
+        store
+            .transfer_credits(&transfer, &debit, &credit, &[])
+            .await,
+        Err(StoreError::NotFound { .. })
+    ));
+    assert_eq!(balance(store, &sender_id).await, 700);
+    assert_ledger_matches(
+        store,
+        &[
+            LedgerAccount::User(sender_id),
+            LedgerAccount::User(recipient_id),
+        ],
+    )
+    .await;
+}
+
+async fn promo_redemption_respects_limits(store: &dyn Store) {
+    let first = new_account(store, 0).await;
+    let second = new_account(store, 0).await;
+
+    let mut promo = PromoCode::new(&unique("conf"), 500);
+    promo.max_redemptions = Some(1);
+    store.put_promo_code(&promo).await.unwrap();
+    let code = promo.code.clone();
+
+    let redeem = |user_id: UserId| {
+        let tx = CreditTransaction::promo(user_id, 500, 500, &code);
+        let redemption = PromoRedemption::new(code.clone(), user_id, tx.id, 500);
+        async move { store.redeem_promo_code(&redemption, &tx, &[]).await }
+    };
+
+    assert_eq!(redeem(first).await.unwrap(), 500);
+    assert!(matches!(
+        redeem(second).await,
+        Err(StoreError::PromoRejected {
+            reason: PromoRejection::Exhausted,
+            ..
+        })
+    ));
+
+    // Updating the code keeps its redemption count
+    promo.max_redemptions = Some(2);
+    store.put_promo_code(&promo).await.unwrap();
+    assert_eq!(
+        store
+            .get_promo_code(&code)
+            .await
+            .unwrap()
+            .unwrap()
+            .redemption_count,
+        1
+    );
+    assert!(store
+        .list_promo_codes()
+        .await
+        .unwrap()
+        .iter()
+        .any(|p| p.code == code));
+
+    assert!(matches!(
+        redeem(first).await,
+        Err(StoreError::PromoRejected {
+            reason: PromoRejection::UserLimitReached,
+            ..
+        })
+    ));
+    assert_eq!(redeem(second).await.unwrap(), 500);
+    assert_eq!(
+        store.count_promo_redemptions(&code, &first).await.unwrap(),
+        1
+    );
+    assert_eq!(
+        store.list_credit_lots(&second).await.unwrap()[0].source,
+        TransactionType::Bonus
+    );
+
+    // Deleting the code keeps its redemptions
+    store.delete_promo_code(&code).await.unwrap();
+    assert!(store.get_promo_code(&code).await.unwrap().is_none());
+    assert_eq!(
+        store.count_promo_redemptions(&code, &second).await.unwrap(),
+        1
+    );
+    assert!(matches!(
+        redeem(second).await,
+        Err(StoreError::NotFound { .. })
+    ));
+    assert_ledger_matches(
+        store,
+        &[LedgerAccount::User(first), LedgerAccount::User(second)],
+    )
+    .await;
+}
+
+async fn gift_card_is_redeemed_once(store: &dyn Store) {
+    let buyer = UserId::generate();
+    let recipient = new_account(store, 0).await;
+
+    let card = GiftCard::issue(buyer, 2500, Some(unique("cs")));
+    store.create_gift_card(&card, &[]).await.unwrap();
+    assert_eq!(
+        store
+            .get_gift_card_by_code(&card.code)
+            .await
+            .unwrap()
+            .unwrap()
+            .id,
+        card.id
+    );
+    let mut duplicate = GiftCard::issue(buyer, 100, None);
+    duplicate.code.clone_from(&card.code);
+    assert!(store.create_gift_card(&duplicate, &[]).await.is_err());
+
+    let tx = CreditTransaction::gift_card(recipient, &card, 2500);
+    assert_eq!(
+        store.redeem_gift_card(&card.id, &tx, &[]).await.unwrap(),
+        2500
+    );
+    let again = CreditTransaction::gift_card(recipient, &card, 5000);
+    assert!(matches!(
+        store.redeem_gift_card(&card.id, &again, &[]).await,
+        Err(StoreError::InvalidState { .. })
+    ));
+    assert!(matches!(
+        store.void_gift_card(&card.id, "fraud").await,
+        Err(StoreError::InvalidState { .. })
+    ));
+
+    let redeemed = store.get_gift_card(&card.id).await.unwrap().unwrap();
+    assert_eq!(redeemed.status, GiftCardStatus::Redeemed);
+    assert_eq!(redeemed.redeemed_by, Some(recipient));
+    assert_eq!(redeemed.transaction_id, Some(tx.id));
+    assert_eq!(
+        store.list_credit_lots(&recipient).await.unwrap()[0].source,
+        TransactionType::GiftCard
+    );
+
+    // A voided card cannot be redeemed
+    std::thread::sleep(std::time::Duration::from_millis(2)); // Ensure different ULIDs
+    let voided = GiftCard::issue(buyer, 1000, None);
+    store.create_gift_card(&voided, &[]).await.unwrap();
+    let closed = store
+        .void_gift_card(&voided.id, "chargeback")
+        .await
+        .unwrap();
+    assert_eq!(closed.void_reason.as_deref(), Some("chargeback"));
+    let tx = CreditTransaction::gift_card(recipient, &voided, 3500);
+    assert!(matches!(
+        store.redeem_gift_card(&voided.id, &tx, &[]).await,
+        Err(StoreError::InvalidState { .. })
+    ));
+
+    let cards = store.list_gift_cards_by_purchaser(&buyer).await.unwrap();
+    assert_eq!(cards.len(), 2);
+    assert_eq!(cards[0].id, voided.id); // Newest first
+    let account = store.get_account(&recipient).await.unwrap().unwrap();
+    assert_eq!(account.balance_cents, 2500);
+    assert_eq!(account.lifetime_purchased_cents, 2500);
+    assert_eq!(account.lifetime_granted_cents, 0);
+    assert_ledger_matches(store, &[LedgerAccount::User(recipient)]).await;
+}
+
+async fn gift_card_is_issued_once_per_checkout(store: &dyn Store) {
+    let buyer = UserId::generate();
+    let session = unique("cs");
+
+    let card = GiftCard::issue(buyer, 2500, Some(session.clone()));
+    assert_eq!(
+        store.create_gift_card(&card, &[]).await.unwrap().id,
+        card.id
+    );
+
+    // A redelivered checkout returns the first card and writes nothing
+    let message = OutboxMessage::new(
+        OutboxTopic::BalanceUpdate,
+        serde_json::json!({ "user_id": buyer.to_string() }),
+    );
+    let replay = GiftCard::issue(buyer, 2500, Some(session));
+    assert_eq!(
+        store
+            .create_gift_card(&replay, std::slice::from_ref(&message))
+            .await
+            .unwrap()
+            .id,
+        card.id
+    );
+    assert!(store.get_gift_card(&replay.id).await.unwrap().is_none());
+    assert!(store
+        .get_outbox_message(&message.id)
+        .await
+        .unwrap()
+        .is_none());
+
+    let cards = store.list_gift_cards_by_purchaser(&buyer).await.unwrap();
+    assert_eq!(cards.len(), 1);
+    assert_eq!(cards[0].id, card.id);
+}
+
+async fn webhook_events_are_recorded(store: &dyn Store) {
+    let event_id = unique("evt_stripe");
+    assert!(!store.has_webhook_event(&event_id).await.unwrap());
+    store
+        .record_webhook_event(&event_id, "stripe")
+        .await
+        .unwrap();
+    assert!(store.has_webhook_event(&event_id).await.unwrap());
+
+    // Ledger postings for system accounts are kept alongside wallets
+    let user_id = new_account(store, 0).await;
+    purchase(store, user_id, 700).await;
+    let entries = store
+        .list_ledger_entries(&LedgerAccount::User(user_id), 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert!(
+        store
+            .ledger_balance(&LedgerAccount::System(SystemAccount::StripeClearing))
+            .await
+            .unwrap()
+            <= -700
+    );
+}
+
+async fn export_lists_everything_in_key_order(store: &dyn Store) {
+    let mut user_ids = Vec::new();
+    let mut event_ids = Vec::new();
+    for _ in 0..3 {
+        let user_id = new_account(store, 0).await;
+        purchase(store, user_id, 500).await;
+        let event = api_call_event(user_id, 10);
+        let tx = CreditTransaction::usage(user_id, 10, 490, "Usage".into(), serde_json::json!({}));
+        store.process_usage(&event, &tx, &[]).await.unwrap();
+        user_ids.push(user_id);
+        event_ids.push(event.event_id);
+    }
+    let webhook_ids = [unique("evt_a"), unique("evt_b")];
+    for event_id in &webhook_ids {
+        store.record_webhook_event(event_id, "lago").await.unwrap();
+    }
+
+    // Page through each listing two at a time; keys must strictly increase
+    let mut accounts = Vec::new();
+    loop {
+        let page = store
+            .list_accounts(accounts.last().map(|a: &Account| &a.user_id), 2)
+            .await
+            .unwrap();
+        if page.is_empty() {
+            break;
+        }
+        accounts.extend(page);
+    }
+    assert!(accounts
+        .windows(2)
+        .all(|w| w[0].user_id.as_bytes() < w[1].user_id.as_bytes()));
+    for user_id in &user_ids {
+        let account = accounts.iter().find(|a| a.user_id == *user_id).unwrap();
+        assert_eq!(account.balance_cents, 490);
+    }
+
+    let mut transactions = Vec::new();
+    loop {
+        let page = store
+            .list_transactions(transactions.last().map(|tx: &CreditTransaction| &tx.id), 2)
+            .await
+            .unwrap();
+        if page.is_empty() {
+            break;
+        }
+        transactions.extend(page);
+    }
+    assert!(transactions
+        .windows(2)
+        .all(|w| w[0].id.as_ulid() < w[1].id.as_ulid()));
+    for user_id in &user_ids {
+        assert_eq!(
+            transactions
+                .iter()
+                .filter(|tx| tx.user_id == *user_id)
+                .count(),
+            2
+        );
+    }
+
+    let mut events = Vec::new();
+    loop {
+        let after = events
+            .last()
+            .map(|event: &UsageEvent| event.event_id.clone());
+        let page = store.list_usage_events(after.as_deref(), 2).await.unwrap();
+        if page.is_empty() {
+            break;
+        }
+        events.extend(page);
+    }
+    assert!(events.windows(2).all(|w| w[0].event_id < w[1].event_id));
+    for event_id in &event_ids {
+        assert!(events.iter().any(|event| event.event_id == *event_id));
+    }
+
+    let mut webhooks = Vec::new();
+    loop {
+        let after = webhooks
+            .last()
+            .map(|webhook: &ProcessedWebhook| webhook.event_id.clone());
+        let page = store
+            .list_webhook_events(after.as_deref(), 2)
+            .await
+            .unwrap();
+        if page.is_empty() {
+            break;
+        }
+        webhooks.extend(page);
+    }
+    assert!(webhooks.windows(2).all(|w| w[0].event_id < w[1].event_id));
+    for event_id in &webhook_ids {
+        let webhook = webhooks.iter().find(|w| w.event_id == *event_id).unwrap();
+        assert_eq!(webhook.source, "lago");
+    }
+    // Webhook markers are not usage events
+    assert!(!events
+        .iter()
+        .any(|event| webhook_ids.contains(&event.event_id)));
+}
+
+async fn export_counts_what_it_does_not_list(store: &dyn Store) {
+    let user_id = new_account(store, 0).await;
+    purchase(store, user_id, 500).await;
+    let today = grant::daily(chrono::Utc::now().date_naive());
+    let tx = CreditTransaction::daily_grant(user_id, 50, 550);
+    store.grant_once(&user_id, &today, &tx, &[]).await.unwrap();
+    let card = GiftCard::issue(user_id, 1000, None);
+    store.create_gift_card(&card, &[]).await.unwrap();
+
+    let counts = store.count_unlisted().await.unwrap();
+    let kinds: Vec<_> = counts.iter().map(|(kind, _)| *kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            "credit_lots",
+            "org_memberships",
+            "agent_budgets",
+            "promo_codes",
+            "gift_cards",
+            "credit_grants",
+            "storage_meters",
+        ]
+    );
+    let count = |kind| counts.iter().find(|(k, _)| *k == kind).unwrap().1;
+    assert!(count("credit_lots") >= 2);
+    assert!(count("gift_cards") >= 1);
+    assert!(count("credit_grants") >= 1);
+}
+
+async fn outbox_is_written_with_charges_and_claimed_once(store: &dyn Store) {
+    let user_id = new_account(store, 500).await;
+    let message = |label: &str| {
+        OutboxMessage::new(
+            OutboxTopic::BalanceUpdate,
+            serde_json::json!({ "user_id": user_id.to_string(), "label": label }),
+        )
+    };
+
+    // A rejected charge leaves its messages unwritten
+    let event = api_call_event(user_id, 900);
+    let tx = CreditTransaction::usage(user_id, 900, -400, "usage".into(), serde_json::json!({}));
+    let rejected = message("rejected");
+    assert!(store
+        .process_usage(&event, &tx, std::slice::from_ref(&rejected))
+        .await
+        .is_err());
+    assert!(store
+        .get_outbox_message(&rejected.id)
+        .await
+        .unwrap()
+        .is_none());
+
+    let event = api_call_event(user_id, 200);
+    let tx = CreditTransaction::usage(user_id, 200, 300, "usage".into(), serde_json::json!({}));
+    let charged = message("charged");
+    store
+        .process_usage(&event, &tx, std::slice::from_ref(&charged))
+        .await
+        .unwrap();
+    let tx = CreditTransaction::purchase(user_id, 100, 400, "Purchase".into());
+    let credited = message("credited");
+    store
+        .add_credits(&user_id, 100, &tx, std::slice::from_ref(&credited))
+        .await
+        .unwrap();
+    let ids = [charged.id, credited.id];
+
+    // Claiming leases the due messages, so a second claim skips them until
+    // the lease runs out
+    let now = chrono::Utc::now();
+    let lease = chrono::Duration::minutes(5);
+    let claimed: Vec<_> = store
+        .claim_outbox_messages(now, lease, 1000)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|message| ids.contains(&message.id))
+        .collect();
+    assert_eq!(claimed.len(), 2);
+    assert!(store
+        .claim_outbox_messages(now, lease, 1000)
+        .await
+        .unwrap()
+        .iter()
+        .all(|message| !ids.contains(&message.id)));
+
+    // Failures are kept for replay; deliveries are deleted
+    let mut failed = claimed
+        .into_iter()
+        .find(|message| message.id == charged.id)
+        .unwrap();
+    for _ in 0..OUTBOX_MAX_ATTEMPTS {
+        failed.record_failure("unreachable", now);
+    }
+    store.put_outbox_message(&failed).await.unwrap();
+    let dead = store
+        .list_outbox_messages(Some(OutboxStatus::DeadLettered), 1000)
+        .await
+        .unwrap();
+    assert!(dead.iter().any(|message| message.id == failed.id));
+    let stored = store.get_outbox_message(&failed.id).await.unwrap().unwrap();
+    assert_eq!(stored.attempts, OUTBOX_MAX_ATTEMPTS);
+    assert_eq!(stored.last_error.as_deref(), Some("unreachable"));
+
+    store.delete_outbox_message(&credited.id).await.unwrap();
+    assert!(store
+        .get_outbox_message(&credited.id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn pricing_catalogs_keep_the_latest_version(store: &dyn Store) {
+    let mut first = PricingCatalog::builtin();
+    first.version = unique("pricing");
+    store.put_pricing_catalog(&first).await.unwrap();
+    assert_eq!(
+        store.get_latest_pricing_catalog().await.unwrap(),
+        Some(first.clone())
+    );
+
+    let mut second = first.clone();
+    second.version = unique("pricing");
+    second.models[0].input_credits_per_million += 10;
+    store.put_pricing_catalog(&second).await.unwrap();
+    assert_eq!(
+        store.get_latest_pricing_catalog().await.unwrap(),
+        Some(second)
+    );
+
+    // A saved version cannot be changed in place
+    first.cpu_hour_credits += 1;
+    assert!(matches!(
+        store.put_pricing_catalog(&first).await,
+        Err(StoreError::InvalidState { .. })
+    ));
+}
+
+async fn storage_meters_are_written_with_a_version_check(store: &dyn Store) {
+    let user_id = UserId::generate();
+    let resource_id = unique("bucket");
+    assert!(store
+        .get_storage_meter(&user_id, &resource_id)
+        .await
+        .unwrap()
+        .is_none());
+
+    let start = chrono::Utc::now();
+    let mut meter = StorageMeter::new(user_id, resource_id.clone(), 1_000_000_000, start);
+    assert_eq!(
+        store.put_storage_meter_if_version(&meter, 0).await.unwrap(),
+        1
+    );
+    // Created concurrently
+    assert!(matches!(
+        store.put_storage_meter_if_version(&meter, 0).await,
+        Err(StoreError::VersionConflict { actual: 1, .. })
+    ));
+
+    let mut stored = store
+        .get_storage_meter(&user_id, &resource_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.version, 1);
+    assert_eq!(stored.bytes, 1_000_000_000);
+
+    let charge = stored.record(2_000_000_000, start + chrono::Duration::hours(1), 1.0);
+    assert_eq!(charge.cost_cents, 1);
+    assert_eq!(
+        store
+            .put_storage_meter_if_version(&stored, 1)
+            .await
+            .unwrap(),
+        2
+    );
+
+    // Written since it was read
+    meter.bytes = 0;
+    assert!(matches!(
+        store.put_storage_meter_if_version(&meter, 1).await,
+        Err(StoreError::VersionConflict {
+            expected: 1,
+            actual: 2,
+            ..
+        })
+    ));
+    let stored = store
+        .get_storage_meter(&user_id, &resource_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.version, 2);
+    assert_eq!(stored.bytes, 2_000_000_000);
+
+    // Meters are per resource
+    assert!(store
+        .get_storage_meter(&user_id, &unique("bucket"))
+        .await
+        .unwrap()
+        .is_none());
+}